@@ -66,12 +66,15 @@ pub const X_AMZN_CODEWHISPERER_OPT_OUT_HEADER: &str = "x-amzn-codewhisperer-opto
 // TODO(bskiser): confirm timeout is updated to an appropriate value?
 const DEFAULT_TIMEOUT_DURATION: Duration = Duration::from_secs(60 * 5);
 
+/// Responses of the mock client, where `None` stands for a failed request
+type MockResponses = std::vec::IntoIter<Option<Vec<ChatResponseStream>>>;
+
 #[derive(Clone, Debug)]
 pub struct ApiClient {
     client: CodewhispererClient,
     streaming_client: Option<CodewhispererStreamingClient>,
     sigv4_streaming_client: Option<QDeveloperStreamingClient>,
-    mock_client: Option<Arc<Mutex<std::vec::IntoIter<Vec<ChatResponseStream>>>>>,
+    mock_client: Option<Arc<Mutex<MockResponses>>>,
     profile: Option<AuthProfile>,
 }
 
@@ -414,7 +417,12 @@ impl ApiClient {
                 },
             }
         } else if let Some(client) = &self.mock_client {
-            let mut new_events = client.lock().next().unwrap_or_default().clone();
+            let Some(mut new_events) = client.lock().next().unwrap_or(Some(vec![])) else {
+                return Err(ApiClientError::ModelOverloadedError {
+                    request_id: None,
+                    status_code: Some(500),
+                });
+            };
             new_events.reverse();
 
             return Ok(SendMessageOutput::Mock(new_events));
@@ -423,10 +431,15 @@ impl ApiClient {
         }
     }
 
-    /// Only meant for testing. Do not use outside of testing responses.
+    /// Only meant for testing. Do not use outside of testing responses. Requests for responses
+    /// given as `null` fail.
     pub fn set_mock_output(&mut self, json: serde_json::Value) {
         let mut mock = Vec::new();
         for response in json.as_array().unwrap() {
+            if response.is_null() {
+                mock.push(None);
+                continue;
+            }
             let mut stream = Vec::new();
             for event in response.as_array().unwrap() {
                 match event {
@@ -441,7 +454,7 @@ impl ApiClient {
                     other => panic!("Unexpected value: {:?}", other),
                 }
             }
-            mock.push(stream);
+            mock.push(Some(stream));
         }
 
         self.mock_client = Some(Arc::new(Mutex::new(mock.into_iter())));
@@ -553,7 +566,7 @@ mod tests {
             .unwrap();
 
         client.mock_client = Some(Arc::new(Mutex::new(
-            vec![vec![
+            vec![Some(vec![
                 ChatResponseStream::AssistantResponseEvent {
                     content: "Hello!".to_owned(),
                 },
@@ -563,7 +576,7 @@ mod tests {
                 ChatResponseStream::AssistantResponseEvent {
                     content: " assist you today?".to_owned(),
                 },
-            ]]
+            ])]
             .into_iter(),
         )));
 
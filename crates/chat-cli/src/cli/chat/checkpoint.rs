@@ -0,0 +1,285 @@
+use std::path::{
+    Path,
+    PathBuf,
+};
+
+use eyre::{
+    Result,
+    bail,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use time::OffsetDateTime;
+use tracing::warn;
+
+use crate::os::Os;
+use crate::util::directories;
+
+/// The state of a file before it was modified. Its contents are stored in the directory of the
+/// checkpoint rather than along with the conversation, see [CheckpointStore::checkpoint_dir].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileSnapshot {
+    pub path: PathBuf,
+    /// If the file did not exist, restoring the snapshot deletes it.
+    #[serde(default)]
+    pub existed: bool,
+    /// The directories that did not exist either, outermost first. Restoring the snapshot removes
+    /// them if they are empty.
+    #[serde(default)]
+    pub missing_dirs: Vec<PathBuf>,
+}
+
+impl FileSnapshot {
+    /// Takes a snapshot of `path`, storing its contents at `contents_path`.
+    async fn take(os: &Os, path: &Path, contents_path: &Path) -> Result<Self> {
+        let existed = os.fs.exists(path);
+        if existed {
+            os.fs.write(contents_path, os.fs.read(path).await?).await?;
+        }
+        let mut missing_dirs = path
+            .ancestors()
+            .skip(1)
+            .take_while(|dir| !dir.as_os_str().is_empty() && !os.fs.exists(dir))
+            .map(Path::to_path_buf)
+            .collect::<Vec<_>>();
+        missing_dirs.reverse();
+        Ok(Self {
+            path: path.to_path_buf(),
+            existed,
+            missing_dirs,
+        })
+    }
+
+    async fn restore(&self, os: &Os, contents_path: &Path) -> Result<()> {
+        if self.existed {
+            if let Some(parent) = self.path.parent() {
+                os.fs.create_dir_all(parent).await?;
+            }
+            os.fs.write(&self.path, os.fs.read(contents_path).await?).await?;
+            return Ok(());
+        }
+
+        if os.fs.exists(&self.path) {
+            os.fs.remove_file(&self.path).await?;
+        }
+        for dir in self.missing_dirs.iter().rev() {
+            if !os.fs.exists(dir) || os.fs.read_dir(dir).await?.next_entry().await?.is_some() {
+                break;
+            }
+            os.fs.remove_dir_all(dir).await?;
+        }
+        Ok(())
+    }
+}
+
+/// The state of the files touched by a single tool use, taken right before it ran.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub id: usize,
+    pub description: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub timestamp: OffsetDateTime,
+    pub files: Vec<FileSnapshot>,
+}
+
+/// Checkpoints of the file changes made during a conversation, ordered from oldest to newest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CheckpointStore {
+    checkpoints: Vec<Checkpoint>,
+    next_id: usize,
+    /// Names the directory of the contents of the snapshots of this store
+    #[serde(default = "new_store_id")]
+    store_id: String,
+}
+
+impl Default for CheckpointStore {
+    fn default() -> Self {
+        Self {
+            checkpoints: Vec::new(),
+            next_id: 0,
+            store_id: new_store_id(),
+        }
+    }
+}
+
+fn new_store_id() -> String {
+    uuid::Uuid::new_v4().to_string()
+}
+
+impl CheckpointStore {
+    /// Records the current contents of `paths` and returns the id of the new checkpoint.
+    pub async fn snapshot(&mut self, os: &Os, description: impl Into<String>, paths: &[PathBuf]) -> Result<usize> {
+        let id = self.next_id + 1;
+        let dir = self.checkpoint_dir(os, id)?;
+        os.fs.create_dir_all(&dir).await?;
+
+        let mut files: Vec<FileSnapshot> = Vec::with_capacity(paths.len());
+        for path in paths {
+            if files.iter().any(|file| &file.path == path) {
+                continue;
+            }
+            match FileSnapshot::take(os, path, &dir.join(files.len().to_string())).await {
+                Ok(file) => files.push(file),
+                Err(err) => {
+                    remove_dir(os, &dir).await;
+                    return Err(err);
+                },
+            }
+        }
+
+        self.next_id = id;
+        self.checkpoints.push(Checkpoint {
+            id,
+            description: description.into(),
+            timestamp: OffsetDateTime::now_utc(),
+            files,
+        });
+        Ok(id)
+    }
+
+    /// Removes a checkpoint without restoring it, e.g. when the change it was taken for failed.
+    pub async fn discard(&mut self, os: &Os, id: usize) {
+        self.checkpoints.retain(|checkpoint| checkpoint.id != id);
+        if let Ok(dir) = self.checkpoint_dir(os, id) {
+            remove_dir(os, &dir).await;
+        }
+    }
+
+    pub fn list(&self) -> &[Checkpoint] {
+        &self.checkpoints
+    }
+
+    /// Rolls the files back to their state before checkpoint `id` was taken, undoing every later
+    /// change as well. Returns the restored checkpoints, newest first.
+    pub async fn restore(&mut self, os: &Os, id: usize) -> Result<Vec<Checkpoint>> {
+        let Some(index) = self.checkpoints.iter().position(|checkpoint| checkpoint.id == id) else {
+            bail!("No checkpoint with id {id}");
+        };
+
+        let mut restored = Vec::new();
+        while self.checkpoints.len() > index {
+            let Some(checkpoint) = self.checkpoints.pop() else {
+                break;
+            };
+            let dir = self.checkpoint_dir(os, checkpoint.id)?;
+            for (i, file) in checkpoint.files.iter().enumerate().rev() {
+                if let Err(err) = file.restore(os, &dir.join(i.to_string())).await {
+                    let message = format!("Failed to restore {}: {err}", file.path.display());
+                    // Keep the checkpoint so that the restore can be retried.
+                    self.checkpoints.push(checkpoint);
+                    bail!(message);
+                }
+            }
+            remove_dir(os, &dir).await;
+            restored.push(checkpoint);
+        }
+        Ok(restored)
+    }
+
+    /// Undoes the most recent change, returning its checkpoint.
+    pub async fn undo(&mut self, os: &Os) -> Result<Option<Checkpoint>> {
+        match self.checkpoints.last() {
+            Some(checkpoint) => {
+                let id = checkpoint.id;
+                Ok(self.restore(os, id).await?.pop())
+            },
+            None => Ok(None),
+        }
+    }
+
+    /// The directory the contents of the files of checkpoint `id` are stored in, by their index.
+    fn checkpoint_dir(&self, os: &Os, id: usize) -> Result<PathBuf> {
+        Ok(directories::chat_checkpoints_dir(os)?
+            .join(&self.store_id)
+            .join(id.to_string()))
+    }
+}
+
+async fn remove_dir(os: &Os, dir: &Path) {
+    if let Err(err) = os.fs.remove_dir_all(dir).await {
+        warn!(?err, "Failed to remove the checkpoint directory {}", dir.display());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_checkpoint_restore() {
+        let os = Os::new().await.unwrap();
+        let existing = PathBuf::from("/existing.txt");
+        let created = PathBuf::from("/new/dir/created.txt");
+        os.fs.write(&existing, "one\n").await.unwrap();
+
+        let mut store = CheckpointStore::default();
+        let first = store.snapshot(&os, "str_replace", &[existing.clone()]).await.unwrap();
+        os.fs.write(&existing, "two\n").await.unwrap();
+        store.snapshot(&os, "create", &[created.clone()]).await.unwrap();
+        os.fs.create_dir_all("/new/dir").await.unwrap();
+        os.fs.write(&created, "new\n").await.unwrap();
+        store.snapshot(&os, "append", &[existing.clone()]).await.unwrap();
+        os.fs.write(&existing, "two\nthree\n").await.unwrap();
+        assert_eq!(store.list().len(), 3);
+
+        let undone = store.undo(&os).await.unwrap().unwrap();
+        assert_eq!(undone.description, "append");
+        assert_eq!(os.fs.read_to_string(&existing).await.unwrap(), "two\n");
+
+        let restored = store.restore(&os, first).await.unwrap();
+        assert_eq!(restored.len(), 2);
+        assert_eq!(os.fs.read_to_string(&existing).await.unwrap(), "one\n");
+        assert!(!os.fs.exists("/new"));
+        assert!(store.list().is_empty());
+        assert!(store.undo(&os).await.unwrap().is_none());
+        assert!(store.restore(&os, first).await.is_err());
+        assert!(!os.fs.exists(store.checkpoint_dir(&os, first).unwrap()));
+    }
+
+    #[tokio::test]
+    async fn test_checkpoint_binary_file() {
+        let os = Os::new().await.unwrap();
+        let path = PathBuf::from("/image.png");
+        let contents = vec![0x89, b'P', b'N', b'G', 0xff, 0x00];
+        os.fs.write(&path, &contents).await.unwrap();
+
+        let mut store = CheckpointStore::default();
+        store.snapshot(&os, "create", &[path.clone()]).await.unwrap();
+        os.fs.write(&path, "replaced").await.unwrap();
+        store.undo(&os).await.unwrap();
+        assert_eq!(os.fs.read(&path).await.unwrap(), contents);
+    }
+
+    #[tokio::test]
+    async fn test_checkpoint_keeps_dirs_in_use() {
+        let os = Os::new().await.unwrap();
+        let created = PathBuf::from("/new/created.txt");
+        let mut store = CheckpointStore::default();
+        store.snapshot(&os, "create", &[created.clone()]).await.unwrap();
+        os.fs.create_dir_all("/new").await.unwrap();
+        os.fs.write(&created, "new\n").await.unwrap();
+        os.fs.write("/new/other.txt", "other\n").await.unwrap();
+
+        store.undo(&os).await.unwrap();
+        assert!(!os.fs.exists(&created));
+        assert!(os.fs.exists("/new/other.txt"));
+    }
+
+    #[tokio::test]
+    async fn test_checkpoint_serialization() {
+        let os = Os::new().await.unwrap();
+        let path = PathBuf::from("/file.txt");
+        os.fs.write(&path, "contents").await.unwrap();
+        let mut store = CheckpointStore::default();
+        store.snapshot(&os, "create", &[path.clone(), path]).await.unwrap();
+        assert_eq!(store.list()[0].files.len(), 1);
+
+        let serialized = serde_json::to_string(&store).unwrap();
+        let deserialized: CheckpointStore = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(store, deserialized);
+        // The contents of the files are stored out of line
+        assert!(!serialized.contains("contents"));
+    }
+}
@@ -0,0 +1,153 @@
+use clap::{
+    Args,
+    Subcommand,
+};
+use crossterm::style::{
+    self,
+    Attribute,
+    Color,
+};
+use crossterm::{
+    execute,
+    queue,
+};
+use time::UtcOffset;
+use time::macros::format_description;
+
+use crate::cli::chat::checkpoint::Checkpoint;
+use crate::cli::chat::tools::format_path;
+use crate::cli::chat::{
+    ChatError,
+    ChatSession,
+    ChatState,
+};
+use crate::os::Os;
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Args)]
+pub struct UndoArgs;
+
+impl UndoArgs {
+    pub async fn execute(self, os: &Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        match session.conversation.checkpoints.undo(os).await {
+            Ok(Some(checkpoint)) => print_restored(os, session, &[checkpoint])?,
+            Ok(None) => print_message(session, Color::DarkGrey, "\nThere are no file changes to undo.\n\n")?,
+            Err(err) => print_message(session, Color::Red, &format!("\nFailed to undo: {err}\n\n"))?,
+        }
+
+        Ok(ChatState::PromptUser {
+            skip_printing_tools: true,
+        })
+    }
+}
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Subcommand)]
+#[command(
+    before_long_help = "Every file change made by fs_write during this session is recorded in a checkpoint, which is \
+saved along with the conversation by /save.
+
+Restoring a checkpoint rolls back the change it was taken for, along with every change made after it. Files that \
+were created by these changes are deleted."
+)]
+pub enum CheckpointSubcommand {
+    /// List the checkpoints of this session
+    List,
+    /// Roll back the files to their state before checkpoint <ID>
+    Restore {
+        /// Id of the checkpoint, as shown by /checkpoint list
+        id: usize,
+    },
+}
+
+impl CheckpointSubcommand {
+    pub async fn execute(self, os: &Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        match self {
+            Self::List => {
+                let checkpoints = session.conversation.checkpoints.list();
+                if checkpoints.is_empty() {
+                    print_message(session, Color::DarkGrey, "\nNo checkpoints.\n\n")?;
+                } else {
+                    let cwd = os.env.current_dir()?;
+                    let offset = UtcOffset::current_local_offset().unwrap_or(UtcOffset::UTC);
+                    let time_format = format_description!("[hour]:[minute]:[second]");
+                    queue!(session.stderr, style::Print("\n"))?;
+                    for checkpoint in checkpoints {
+                        let time = checkpoint
+                            .timestamp
+                            .to_offset(offset)
+                            .format(time_format)
+                            .unwrap_or_default();
+                        let files = checkpoint
+                            .files
+                            .iter()
+                            .map(|file| format_path(&cwd, &file.path))
+                            .collect::<Vec<_>>()
+                            .join(", ");
+                        queue!(
+                            session.stderr,
+                            style::SetAttribute(Attribute::Bold),
+                            style::Print(format!("{:>4}", checkpoint.id)),
+                            style::SetAttribute(Attribute::Reset),
+                            style::SetForegroundColor(Color::DarkGrey),
+                            style::Print(format!("  {time}  ")),
+                            style::SetForegroundColor(Color::Reset),
+                            style::Print(format!("{:<20} ", checkpoint.description)),
+                            style::SetForegroundColor(Color::Green),
+                            style::Print(files),
+                            style::SetForegroundColor(Color::Reset),
+                            style::Print("\n"),
+                        )?;
+                    }
+                    execute!(session.stderr, style::Print("\n"))?;
+                }
+            },
+            Self::Restore { id } => match session.conversation.checkpoints.restore(os, id).await {
+                Ok(restored) => print_restored(os, session, &restored)?,
+                Err(err) => print_message(session, Color::Red, &format!("\nFailed to restore: {err}\n\n"))?,
+            },
+        }
+
+        Ok(ChatState::PromptUser {
+            skip_printing_tools: true,
+        })
+    }
+}
+
+fn print_message(session: &mut ChatSession, color: Color, message: &str) -> Result<(), ChatError> {
+    execute!(
+        session.stderr,
+        style::SetForegroundColor(color),
+        style::Print(message),
+        style::SetForegroundColor(Color::Reset),
+    )?;
+    Ok(())
+}
+
+/// Prints the files rolled back by `checkpoints`, given newest first.
+fn print_restored(os: &Os, session: &mut ChatSession, checkpoints: &[Checkpoint]) -> Result<(), ChatError> {
+    let cwd = os.env.current_dir()?;
+    queue!(session.stderr, style::Print("\n"))?;
+    for checkpoint in checkpoints {
+        queue!(
+            session.stderr,
+            style::SetForegroundColor(Color::Green),
+            style::Print(format!("✔ Restored checkpoint {} ", checkpoint.id)),
+            style::SetForegroundColor(Color::DarkGrey),
+            style::Print(format!("({})\n", checkpoint.description)),
+            style::SetForegroundColor(Color::Reset),
+        )?;
+        for file in &checkpoint.files {
+            let action = if file.existed { "reverted" } else { "deleted" };
+            queue!(
+                session.stderr,
+                style::Print(format!("  {} ", format_path(&cwd, &file.path))),
+                style::SetForegroundColor(Color::DarkGrey),
+                style::Print(format!("({action})\n")),
+                style::SetForegroundColor(Color::Reset),
+            )?;
+        }
+    }
+    execute!(session.stderr, style::Print("\n"))?;
+    Ok(())
+}
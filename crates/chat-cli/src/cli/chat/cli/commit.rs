@@ -0,0 +1,280 @@
+use clap::Args;
+use crossterm::style::{
+    self,
+    Color,
+    Stylize,
+};
+use crossterm::{
+    cursor,
+    execute,
+};
+
+use crate::api_client::model::{
+    ChatResponseStream,
+    ConversationState as FigConversationState,
+    UserInputMessage,
+};
+use crate::cli::chat::cli::editor::open_editor;
+use crate::cli::chat::tools::git::{
+    self,
+    GitDiff,
+};
+use crate::cli::chat::util::truncate_safe;
+use crate::cli::chat::{
+    ChatError,
+    ChatSession,
+    ChatState,
+};
+use crate::os::Os;
+
+/// Max size of the staged diff sent to draft the message, in bytes.
+const DRAFT_DIFF_MAX_SIZE: usize = 100_000;
+
+/// Number of recent commit subjects the draft follows the conventions of.
+const RECENT_COMMITS: usize = 10;
+
+const EDITOR_NOTE: &str = "# Edit the commit message above. Lines starting with '#' are ignored, and an empty
+# message cancels the commit.";
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Args)]
+#[command(
+    before_long_help = "Drafts a commit message for the staged changes, following the conventions of the recent \
+commits. The draft opens in your editor ($EDITOR), and the changes are committed once you confirm the message."
+)]
+pub struct CommitArgs {
+    /// Confirm the drafted message without opening it in the editor
+    #[arg(long)]
+    pub no_edit: bool,
+}
+
+impl CommitArgs {
+    pub async fn execute(self, os: &Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        let staged = match git::diff(os, true, None, None).await {
+            Ok(staged) if staged.files.is_empty() => {
+                execute!(
+                    session.stderr,
+                    style::SetForegroundColor(Color::Yellow),
+                    style::Print("\nNothing is staged. Stage the changes to commit with git add.\n\n"),
+                    style::SetForegroundColor(Color::Reset)
+                )?;
+                return Ok(ChatState::PromptUser {
+                    skip_printing_tools: true,
+                });
+            },
+            Ok(staged) => staged,
+            Err(err) => {
+                return Err(ChatError::Custom(
+                    format!("Failed to get the staged changes: {}", err).into(),
+                ));
+            },
+        };
+
+        execute!(
+            session.stderr,
+            style::SetForegroundColor(Color::DarkGrey),
+            style::Print(format!(
+                "\nDrafting a commit message for {} staged file{}...\n",
+                staged.files.len(),
+                if staged.files.len() == 1 { "" } else { "s" }
+            )),
+            style::SetForegroundColor(Color::Reset)
+        )?;
+        let recent_subjects = match git::log(os, None, None, RECENT_COMMITS).await {
+            Ok(log) => log.commits.into_iter().map(|commit| commit.subject).collect(),
+            // There are no commits yet
+            Err(_) => Vec::new(),
+        };
+        let draft = draft_message(os, session.conversation.model.clone(), &staged, &recent_subjects).await?;
+
+        let message = if self.no_edit {
+            draft
+        } else {
+            strip_comments(&open_editor(Some(format!("{}\n\n{}\n", draft, EDITOR_NOTE)))?)
+        };
+        if message.is_empty() {
+            execute!(
+                session.stderr,
+                style::SetForegroundColor(Color::Yellow),
+                style::Print("\nEmpty commit message, not committing.\n\n"),
+                style::SetForegroundColor(Color::Reset)
+            )?;
+            return Ok(ChatState::PromptUser {
+                skip_printing_tools: true,
+            });
+        }
+
+        execute!(
+            session.stderr,
+            style::Print("\n"),
+            style::Print(&message),
+            style::SetForegroundColor(Color::DarkGrey),
+            style::Print("\n\nCommit the staged changes with this message? "),
+            style::Print("["),
+            style::SetForegroundColor(Color::Green),
+            style::Print("y"),
+            style::SetForegroundColor(Color::DarkGrey),
+            style::Print("/"),
+            style::SetForegroundColor(Color::Green),
+            style::Print("n"),
+            style::SetForegroundColor(Color::DarkGrey),
+            style::Print("]:\n\n"),
+            style::SetForegroundColor(Color::Reset),
+            cursor::Show,
+        )?;
+        let user_input = session
+            .read_user_input("> ".yellow().to_string().as_str(), true)
+            .unwrap_or_default();
+        if !["y", "Y"].contains(&user_input.trim()) {
+            return Ok(ChatState::PromptUser {
+                skip_printing_tools: true,
+            });
+        }
+
+        match git::commit(os, &message).await {
+            Ok(commit) => execute!(
+                session.stderr,
+                style::SetForegroundColor(Color::Green),
+                style::Print(format!("\n{}\n\n", commit.summary)),
+                style::SetForegroundColor(Color::Reset)
+            )?,
+            Err(err) => execute!(
+                session.stderr,
+                style::SetForegroundColor(Color::Red),
+                style::Print(format!("\nFailed to commit: {}\n\n", err)),
+                style::SetForegroundColor(Color::Reset)
+            )?,
+        }
+
+        Ok(ChatState::PromptUser {
+            skip_printing_tools: true,
+        })
+    }
+}
+
+/// Asks the model for a commit message of the `staged` changes, outside of the conversation.
+async fn draft_message(
+    os: &Os,
+    model_id: Option<String>,
+    staged: &GitDiff,
+    recent_subjects: &[String],
+) -> Result<String, ChatError> {
+    let conversation = FigConversationState {
+        conversation_id: None,
+        user_input_message: UserInputMessage {
+            content: draft_prompt(staged, recent_subjects),
+            user_input_message_context: None,
+            user_intent: None,
+            images: None,
+            model_id,
+        },
+        history: None,
+    };
+
+    let mut response = os.client.send_message(conversation).await?;
+    let mut text = String::new();
+    while let Some(event) = response.recv().await? {
+        if let ChatResponseStream::AssistantResponseEvent { content } = event {
+            text.push_str(&content);
+        }
+    }
+    Ok(clean_draft(&text))
+}
+
+fn draft_prompt(staged: &GitDiff, recent_subjects: &[String]) -> String {
+    let mut prompt = "Write a git commit message for the staged changes below. Start with a summary line of at \
+most 72 characters in the imperative mood, followed by a blank line and a short body explaining what changed \
+and why, unless the change is trivial. Reply with the commit message only."
+        .to_string();
+    if !recent_subjects.is_empty() {
+        prompt.push_str("\n\nFollow the conventions of the recent commits:\n");
+        for subject in recent_subjects {
+            prompt.push_str(&format!("- {}\n", subject));
+        }
+    }
+    prompt.push_str("\n\nStaged changes:\n");
+    for file in &staged.files {
+        match (file.additions, file.deletions) {
+            (Some(additions), Some(deletions)) => {
+                prompt.push_str(&format!("{} (+{} -{})\n", file.path, additions, deletions));
+            },
+            _ => prompt.push_str(&format!("{} (binary)\n", file.path)),
+        }
+    }
+    prompt.push('\n');
+    prompt.push_str(truncate_safe(&staged.patch, DRAFT_DIFF_MAX_SIZE));
+    if staged.truncated || staged.patch.len() > DRAFT_DIFF_MAX_SIZE {
+        prompt.push_str("\n[diff truncated]");
+    }
+    prompt
+}
+
+/// Removes the code fence the model may have wrapped the message in.
+fn clean_draft(text: &str) -> String {
+    let text = text.trim();
+    let text = match text.strip_prefix("```") {
+        Some(fenced) => fenced
+            .split_once('\n')
+            .map_or("", |(_language, rest)| rest)
+            .trim_end()
+            .trim_end_matches("```"),
+        None => text,
+    };
+    text.trim().to_string()
+}
+
+/// Removes the lines starting with `#` as git does, and the surrounding whitespace.
+fn strip_comments(message: &str) -> String {
+    message
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::chat::tools::git::DiffStat;
+
+    #[test]
+    fn test_clean_draft() {
+        assert_eq!(clean_draft("  Fix the parser\n\nBody\n"), "Fix the parser\n\nBody");
+        assert_eq!(clean_draft("```text\nFix the parser\n```"), "Fix the parser");
+        assert_eq!(clean_draft("```\nFix the parser\n```\n"), "Fix the parser");
+    }
+
+    #[test]
+    fn test_strip_comments() {
+        let edited = format!("Fix the parser\n\nBody\n\n{}\n", EDITOR_NOTE);
+        assert_eq!(strip_comments(&edited), "Fix the parser\n\nBody");
+        assert_eq!(strip_comments(EDITOR_NOTE), "");
+    }
+
+    #[test]
+    fn test_draft_prompt() {
+        let staged = GitDiff {
+            files: vec![
+                DiffStat {
+                    path: "src/lib.rs".to_string(),
+                    additions: Some(3),
+                    deletions: Some(1),
+                },
+                DiffStat {
+                    path: "logo.png".to_string(),
+                    additions: None,
+                    deletions: None,
+                },
+            ],
+            patch: "+fn parse() {}".to_string(),
+            truncated: false,
+        };
+        let prompt = draft_prompt(&staged, &["Add the lexer".to_string()]);
+        assert!(prompt.contains("- Add the lexer\n"));
+        assert!(prompt.contains("src/lib.rs (+3 -1)\n"));
+        assert!(prompt.contains("logo.png (binary)\n"));
+        assert!(prompt.ends_with("+fn parse() {}"));
+    }
+}
@@ -1,5 +1,11 @@
 use clap::Args;
+use crossterm::execute;
+use crossterm::style::{
+    self,
+    Color,
+};
 
+use crate::cli::chat::compaction::CompactionStrategy;
 use crate::cli::chat::{
     ChatError,
     ChatSession,
@@ -24,17 +30,59 @@ How it works
 • Creates an AI-generated summary of your conversation
 • Retains key information, code, and tool executions in the summary
 • Clears the conversation history to free up space
-• The assistant will reference the summary context in future responses"
+• The assistant will reference the summary context in future responses
+
+Strategies
+• summarize: replace the history with a summary (default)
+• truncate-tool-results: truncate the oldest tool results first, and summarize only if
+  that does not free enough space
+• sliding-window: remove the oldest messages, and summarize only if that does not free
+  enough space, e.g. because the remaining messages are pinned
+
+Messages pinned with /pin are kept in the history by every strategy. The default strategy
+and threshold for compacting automatically can be set with the chat.compactionStrategy and
+chat.autoCompactThreshold settings."
 )]
 pub struct CompactArgs {
     /// The prompt to use when generating the summary
     prompt: Option<String>,
     #[arg(long)]
     show_summary: bool,
+    /// How to compact the history, instead of the strategy of this conversation
+    #[arg(long, value_enum)]
+    strategy: Option<CompactionStrategy>,
+    /// Compact automatically when this percentage of the context window is used, or never if 0,
+    /// instead of compacting now
+    #[arg(long, value_name = "PERCENT", value_parser = clap::value_parser!(u8).range(0..=100))]
+    auto: Option<u8>,
 }
 
 impl CompactArgs {
     pub async fn execute(self, os: &Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
-        session.compact_history(os, self.prompt, self.show_summary).await
+        if let Some(percent) = self.auto {
+            session.conversation.compaction.auto_compact_percent = (percent > 0).then_some(percent.into());
+            if let Some(strategy) = self.strategy {
+                session.conversation.compaction.strategy = strategy;
+            }
+            let message = match percent {
+                0 => "\nAutomatic compaction is disabled.\n\n".to_string(),
+                percent => {
+                    format!("\nThe history will be compacted when {percent}% of the context window is used.\n\n")
+                },
+            };
+            execute!(
+                session.stderr,
+                style::SetForegroundColor(Color::Green),
+                style::Print(message),
+                style::SetForegroundColor(Color::Reset)
+            )?;
+            return Ok(ChatState::PromptUser {
+                skip_printing_tools: true,
+            });
+        }
+
+        session
+            .compact_history(os, self.prompt, self.show_summary, self.strategy)
+            .await
     }
 }
@@ -1,4 +1,4 @@
-use std::collections::HashSet;
+use std::io::Write;
 
 use clap::Subcommand;
 use crossterm::style::{
@@ -11,13 +11,19 @@ use crossterm::{
 };
 
 use crate::cli::chat::cli::hooks::{
-    HookTrigger,
     map_chat_error,
-    print_hook_section,
+    print_hook_sections,
+};
+use crate::cli::chat::context::{
+    CollectedContext,
+    ContextConfig,
+    ContextEntryStatus,
 };
-use crate::cli::chat::consts::CONTEXT_FILES_MAX_SIZE;
 use crate::cli::chat::token_counter::TokenCounter;
-use crate::cli::chat::util::drop_matched_context_files;
+use crate::cli::chat::workspace_config::{
+    ConfigScope,
+    WORKSPACE_CONFIG_PATH,
+};
 use crate::cli::chat::{
     ChatError,
     ChatSession,
@@ -35,8 +41,14 @@ more accurate and helpful responses.
 
 Notes:
 • You can add specific files or use glob patterns (e.g., \"*.py\", \"src/**/*.js\")
+• Files ignored by git are skipped, unless their directory is named explicitly
+• Rules can also include the output of a command (\"cmd:git diff --stat\"), a URL fetched once \
+per session (\"url:https://...\") or the results of a knowledge base query (\"knowledge:<query>\")
+• When the context exceeds the size limit, rules with the lowest priority are dropped first
 • Profile rules apply only to the current profile
 • Global rules apply across all profiles
+• Workspace rules are checked in at .amazonq/config.json and apply in that directory, they are \
+edited in that file rather than with these commands
 • Context is preserved between chat sessions"
 )]
 pub enum ContextSubcommand {
@@ -55,6 +67,10 @@ pub enum ContextSubcommand {
         /// Include even if matched files exceed size limits
         #[arg(short, long)]
         force: bool,
+        /// Priority of the rules, 0 by default. Rules with higher priorities are kept first when
+        /// the context exceeds the size limit
+        #[arg(short, long, allow_negative_numbers = true)]
+        priority: Option<i32>,
         #[arg(required = true)]
         paths: Vec<String>,
     },
@@ -93,6 +109,8 @@ impl ContextSubcommand {
 
         match self {
             Self::Show { expand } => {
+                let context = context_manager.collect_context_with_limit(os).await;
+
                 // Display global context
                 execute!(
                     session.stderr,
@@ -101,55 +119,45 @@ impl ContextSubcommand {
                     style::Print("\n🌍 global:\n"),
                     style::SetAttribute(Attribute::Reset),
                 )?;
-                let mut global_context_files = HashSet::new();
-                let mut profile_context_files = HashSet::new();
-                if context_manager.global_config.paths.is_empty() {
+                print_rules(
+                    &mut session.stderr,
+                    &context_manager.global_config,
+                    &context,
+                    ConfigScope::Global,
+                )?;
+
+                if expand {
                     execute!(
                         session.stderr,
-                        style::SetForegroundColor(Color::DarkGrey),
-                        style::Print("    <none>\n"),
-                        style::SetForegroundColor(Color::Reset)
+                        style::SetAttribute(Attribute::Bold),
+                        style::SetForegroundColor(Color::DarkYellow),
+                        style::Print("\n    🔧 Hooks:\n")
                     )?;
-                } else {
-                    for path in &context_manager.global_config.paths {
-                        execute!(session.stderr, style::Print(format!("    {} ", path)))?;
-                        if let Ok(context_files) = context_manager.get_context_files_by_path(os, path).await {
-                            execute!(
-                                session.stderr,
-                                style::SetForegroundColor(Color::Green),
-                                style::Print(format!(
-                                    "({} match{})",
-                                    context_files.len(),
-                                    if context_files.len() == 1 { "" } else { "es" }
-                                )),
-                                style::SetForegroundColor(Color::Reset)
-                            )?;
-                            global_context_files.extend(context_files);
-                        }
-                        execute!(session.stderr, style::Print("\n"))?;
-                    }
+                    print_hook_sections(&mut session.stderr, &context_manager.global_config.hooks)
+                        .map_err(map_chat_error)?;
                 }
 
-                if expand {
+                // Display workspace context, if the current directory has any
+                let workspace_config = &context_manager.workspace_config;
+                if !workspace_config.paths.is_empty() || !workspace_config.hooks.is_empty() {
                     execute!(
                         session.stderr,
                         style::SetAttribute(Attribute::Bold),
-                        style::SetForegroundColor(Color::DarkYellow),
-                        style::Print("\n    🔧 Hooks:\n")
+                        style::SetForegroundColor(Color::Magenta),
+                        style::Print(format!("\n📁 workspace ({}):\n", WORKSPACE_CONFIG_PATH)),
+                        style::SetAttribute(Attribute::Reset),
                     )?;
-                    print_hook_section(
-                        &mut session.stderr,
-                        &context_manager.global_config.hooks,
-                        HookTrigger::ConversationStart,
-                    )
-                    .map_err(map_chat_error)?;
+                    print_rules(&mut session.stderr, workspace_config, &context, ConfigScope::Workspace)?;
 
-                    print_hook_section(
-                        &mut session.stderr,
-                        &context_manager.global_config.hooks,
-                        HookTrigger::PerPrompt,
-                    )
-                    .map_err(map_chat_error)?;
+                    if expand {
+                        execute!(
+                            session.stderr,
+                            style::SetAttribute(Attribute::Bold),
+                            style::SetForegroundColor(Color::DarkYellow),
+                            style::Print("\n    🔧 Hooks:\n")
+                        )?;
+                        print_hook_sections(&mut session.stderr, &workspace_config.hooks).map_err(map_chat_error)?;
+                    }
                 }
 
                 // Display profile context
@@ -160,34 +168,13 @@ impl ContextSubcommand {
                     style::Print(format!("\n👤 profile ({}):\n", context_manager.current_profile)),
                     style::SetAttribute(Attribute::Reset),
                 )?;
-
-                if context_manager.profile_config.paths.is_empty() {
-                    execute!(
-                        session.stderr,
-                        style::SetForegroundColor(Color::DarkGrey),
-                        style::Print("    <none>\n\n"),
-                        style::SetForegroundColor(Color::Reset)
-                    )?;
-                } else {
-                    for path in &context_manager.profile_config.paths {
-                        execute!(session.stderr, style::Print(format!("    {} ", path)))?;
-                        if let Ok(context_files) = context_manager.get_context_files_by_path(os, path).await {
-                            execute!(
-                                session.stderr,
-                                style::SetForegroundColor(Color::Green),
-                                style::Print(format!(
-                                    "({} match{})",
-                                    context_files.len(),
-                                    if context_files.len() == 1 { "" } else { "es" }
-                                )),
-                                style::SetForegroundColor(Color::Reset)
-                            )?;
-                            profile_context_files.extend(context_files);
-                        }
-                        execute!(session.stderr, style::Print("\n"))?;
-                    }
-                    execute!(session.stderr, style::Print("\n"))?;
-                }
+                print_rules(
+                    &mut session.stderr,
+                    &context_manager.profile_config,
+                    &context,
+                    ConfigScope::Profile,
+                )?;
+                execute!(session.stderr, style::Print("\n"))?;
 
                 if expand {
                     execute!(
@@ -196,22 +183,12 @@ impl ContextSubcommand {
                         style::SetForegroundColor(Color::DarkYellow),
                         style::Print("    🔧 Hooks:\n")
                     )?;
-                    print_hook_section(
-                        &mut session.stderr,
-                        &context_manager.profile_config.hooks,
-                        HookTrigger::ConversationStart,
-                    )
-                    .map_err(map_chat_error)?;
-                    print_hook_section(
-                        &mut session.stderr,
-                        &context_manager.profile_config.hooks,
-                        HookTrigger::PerPrompt,
-                    )
-                    .map_err(map_chat_error)?;
+                    print_hook_sections(&mut session.stderr, &context_manager.profile_config.hooks)
+                        .map_err(map_chat_error)?;
                     execute!(session.stderr, style::Print("\n"))?;
                 }
 
-                if global_context_files.is_empty() && profile_context_files.is_empty() {
+                if context.entries.is_empty() {
                     execute!(
                         session.stderr,
                         style::SetForegroundColor(Color::DarkGrey),
@@ -219,61 +196,52 @@ impl ContextSubcommand {
                         style::SetForegroundColor(Color::Reset)
                     )?;
                 } else {
-                    let total = global_context_files.len() + profile_context_files.len();
-                    let total_tokens = global_context_files
+                    let (used, dropped): (Vec<_>, Vec<_>) = context
+                        .entries
+                        .iter()
+                        .partition(|entry| entry.status != ContextEntryStatus::Dropped);
+                    let total_tokens = used
                         .iter()
-                        .map(|(_, content)| TokenCounter::count_tokens(content))
-                        .sum::<usize>()
-                        + profile_context_files
-                            .iter()
-                            .map(|(_, content)| TokenCounter::count_tokens(content))
-                            .sum::<usize>();
+                        .map(|entry| TokenCounter::count_tokens(&entry.content))
+                        .sum::<usize>();
                     execute!(
                         session.stderr,
                         style::SetForegroundColor(Color::Green),
                         style::SetAttribute(Attribute::Bold),
                         style::Print(format!(
                             "{} matched file{} in use:\n",
-                            total,
-                            if total == 1 { "" } else { "s" }
+                            used.len(),
+                            if used.len() == 1 { "" } else { "s" }
                         )),
                         style::SetForegroundColor(Color::Reset),
                         style::SetAttribute(Attribute::Reset)
                     )?;
 
-                    for (filename, content) in &global_context_files {
-                        let est_tokens = TokenCounter::count_tokens(content);
+                    for entry in &used {
+                        let est_tokens = TokenCounter::count_tokens(&entry.content);
                         execute!(
                             session.stderr,
-                            style::Print(format!("🌍 {} ", filename)),
+                            style::Print(format!("{} {} ", scope_icon(entry.scope), entry.name)),
                             style::SetForegroundColor(Color::DarkGrey),
-                            style::Print(format!("(~{} tkns)\n", est_tokens)),
-                            style::SetForegroundColor(Color::Reset),
+                            style::Print(format!("(~{} tkns)", est_tokens)),
                         )?;
-                        if expand {
+                        if let ContextEntryStatus::Truncated { original_tokens } = entry.status {
                             execute!(
                                 session.stderr,
-                                style::SetForegroundColor(Color::DarkGrey),
-                                style::Print(format!("{}\n\n", content)),
-                                style::SetForegroundColor(Color::Reset)
+                                style::SetForegroundColor(Color::DarkYellow),
+                                style::Print(format!(" truncated from ~{} tkns", original_tokens)),
                             )?;
                         }
-                    }
-
-                    for (filename, content) in &profile_context_files {
-                        let est_tokens = TokenCounter::count_tokens(content);
                         execute!(
                             session.stderr,
-                            style::Print(format!("👤 {} ", filename)),
-                            style::SetForegroundColor(Color::DarkGrey),
-                            style::Print(format!("(~{} tkns)\n", est_tokens)),
                             style::SetForegroundColor(Color::Reset),
+                            style::Print("\n")
                         )?;
                         if expand {
                             execute!(
                                 session.stderr,
                                 style::SetForegroundColor(Color::DarkGrey),
-                                style::Print(format!("{}\n\n", content)),
+                                style::Print(format!("{}\n\n", entry.content)),
                                 style::SetForegroundColor(Color::Reset)
                             )?;
                         }
@@ -283,51 +251,30 @@ impl ContextSubcommand {
                         execute!(session.stderr, style::Print(format!("{}\n\n", "▔".repeat(3))),)?;
                     }
 
-                    let mut combined_files: Vec<(String, String)> = global_context_files
-                        .iter()
-                        .chain(profile_context_files.iter())
-                        .cloned()
-                        .collect();
-
-                    let dropped_files = drop_matched_context_files(&mut combined_files, CONTEXT_FILES_MAX_SIZE).ok();
-
                     execute!(
                         session.stderr,
                         style::Print(format!("\nTotal: ~{} tokens\n\n", total_tokens))
                     )?;
 
-                    if let Some(dropped_files) = dropped_files {
-                        if !dropped_files.is_empty() {
+                    if !dropped.is_empty() {
+                        execute!(
+                            session.stderr,
+                            style::SetForegroundColor(Color::DarkYellow),
+                            style::Print(format!(
+                                "Total token count exceeds limit: {}. The following files are dropped when interacting with Q. Consider removing them or lowering the priority of their rules.\n\n",
+                                context_manager.max_context_files_size()
+                            )),
+                            style::SetForegroundColor(Color::Reset)
+                        )?;
+                        for entry in &dropped {
+                            let est_tokens = TokenCounter::count_tokens(&entry.content);
                             execute!(
                                 session.stderr,
-                                style::SetForegroundColor(Color::DarkYellow),
-                                style::Print(format!(
-                                    "Total token count exceeds limit: {}. The following files will be automatically dropped when interacting with Q. Consider removing them. \n\n",
-                                    CONTEXT_FILES_MAX_SIZE
-                                )),
-                                style::SetForegroundColor(Color::Reset)
+                                style::Print(format!("{} ", entry.name)),
+                                style::SetForegroundColor(Color::DarkGrey),
+                                style::Print(format!("(~{} tkns)\n", est_tokens)),
+                                style::SetForegroundColor(Color::Reset),
                             )?;
-                            let total_files = dropped_files.len();
-
-                            let truncated_dropped_files = &dropped_files[..10];
-
-                            for (filename, content) in truncated_dropped_files {
-                                let est_tokens = TokenCounter::count_tokens(content);
-                                execute!(
-                                    session.stderr,
-                                    style::Print(format!("{} ", filename)),
-                                    style::SetForegroundColor(Color::DarkGrey),
-                                    style::Print(format!("(~{} tkns)\n", est_tokens)),
-                                    style::SetForegroundColor(Color::Reset),
-                                )?;
-                            }
-
-                            if total_files > 10 {
-                                execute!(
-                                    session.stderr,
-                                    style::Print(format!("({} more files)\n", total_files - 10))
-                                )?;
-                            }
                         }
                     }
 
@@ -356,8 +303,20 @@ impl ContextSubcommand {
                     }
                 }
             },
-            Self::Add { global, force, paths } => {
-                match context_manager.add_paths(os, paths.clone(), global, force).await {
+            Self::Add {
+                global,
+                force,
+                priority,
+                paths,
+            } => {
+                let result = match context_manager.add_paths(os, paths.clone(), global, force).await {
+                    Ok(()) => match priority {
+                        Some(priority) => context_manager.set_priority(os, &paths, priority, global).await,
+                        None => Ok(()),
+                    },
+                    Err(e) => Err(e),
+                };
+                match result {
                     Ok(_) => {
                         let target = if global { "global" } else { "profile" };
                         execute!(
@@ -442,3 +401,62 @@ impl ContextSubcommand {
         })
     }
 }
+
+fn scope_icon(scope: ConfigScope) -> &'static str {
+    match scope {
+        ConfigScope::Global => "🌍",
+        ConfigScope::Workspace => "📁",
+        ConfigScope::Profile => "👤",
+    }
+}
+
+/// Prints the rules of `config`, with the number of entries of `context` they produced, or the
+/// error they failed with.
+fn print_rules(
+    output: &mut impl Write,
+    config: &ContextConfig,
+    context: &CollectedContext,
+    scope: ConfigScope,
+) -> Result<(), ChatError> {
+    if config.paths.is_empty() {
+        execute!(
+            output,
+            style::SetForegroundColor(Color::DarkGrey),
+            style::Print("    <none>\n"),
+            style::SetForegroundColor(Color::Reset)
+        )?;
+        return Ok(());
+    }
+
+    for rule in &config.paths {
+        execute!(output, style::Print(format!("    {} ", rule)))?;
+        if let Some(priority) = config.priorities.get(rule) {
+            execute!(
+                output,
+                style::SetForegroundColor(Color::DarkGrey),
+                style::Print(format!("[priority {}] ", priority)),
+            )?;
+        }
+        match context.failed_rules.iter().find(|(failed_rule, _)| failed_rule == rule) {
+            Some((_, err)) => execute!(
+                output,
+                style::SetForegroundColor(Color::Red),
+                style::Print(format!("(failed: {})", err)),
+            )?,
+            None => {
+                let matches = context
+                    .entries
+                    .iter()
+                    .filter(|entry| entry.scope == scope && entry.rule == *rule)
+                    .count();
+                execute!(
+                    output,
+                    style::SetForegroundColor(Color::Green),
+                    style::Print(format!("({} match{})", matches, if matches == 1 { "" } else { "es" })),
+                )?;
+            },
+        }
+        execute!(output, style::SetForegroundColor(Color::Reset), style::Print("\n"))?;
+    }
+    Ok(())
+}
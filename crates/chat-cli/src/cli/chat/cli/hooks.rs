@@ -10,6 +10,7 @@ use bstr::ByteSlice;
 use clap::{
     Args,
     Subcommand,
+    ValueEnum,
 };
 use crossterm::style::{
     self,
@@ -32,6 +33,7 @@ use futures::stream::{
     FuturesUnordered,
     StreamExt,
 };
+use globset::Glob;
 use serde::{
     Deserialize,
     Serialize,
@@ -40,8 +42,10 @@ use spinners::{
     Spinner,
     Spinners,
 };
+use tokio::io::AsyncWriteExt;
 
 use crate::cli::chat::util::truncate_safe;
+use crate::cli::chat::workspace_config::WORKSPACE_CONFIG_PATH;
 use crate::cli::chat::{
     ChatError,
     ChatSession,
@@ -53,6 +57,9 @@ const DEFAULT_TIMEOUT_MS: u64 = 30_000;
 const DEFAULT_MAX_OUTPUT_SIZE: usize = 1024 * 10;
 const DEFAULT_CACHE_TTL_SECONDS: u64 = 0;
 
+/// Exit code of event hooks that block what triggered them, see [HookOutcome::Blocked].
+pub const BLOCKING_EXIT_CODE: i32 = 2;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Hook {
     pub trigger: HookTrigger,
@@ -74,6 +81,11 @@ pub struct Hook {
     #[serde(default = "Hook::default_cache_ttl_seconds")]
     pub cache_ttl_seconds: u64,
 
+    /// Glob of the tool names a tool use hook runs for, e.g. `fs_*`. Runs for every tool if not
+    /// set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub matcher: Option<String>,
+
     // Type-specific fields
     /// The bash command to execute
     pub command: Option<String>, // For inline hooks
@@ -94,6 +106,7 @@ impl Hook {
             timeout_ms: Self::default_timeout_ms(),
             max_output_size: Self::default_max_output_size(),
             cache_ttl_seconds: Self::default_cache_ttl_seconds(),
+            matcher: None,
             command: Some(command),
             is_global: false,
             name: "new hook".to_string(),
@@ -115,6 +128,13 @@ impl Hook {
     fn default_cache_ttl_seconds() -> u64 {
         DEFAULT_CACHE_TTL_SECONDS
     }
+
+    /// Whether the hook runs for uses of `tool_name`, according to its matcher.
+    pub fn matches_tool(&self, tool_name: &str) -> bool {
+        self.matcher
+            .as_ref()
+            .is_none_or(|matcher| Glob::new(matcher).is_ok_and(|glob| glob.compile_matcher().is_match(tool_name)))
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
@@ -124,11 +144,61 @@ pub enum HookType {
     Inline,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Eq, PartialEq, ValueEnum)]
 #[serde(rename_all = "snake_case")]
+#[value(rename_all = "snake_case")]
 pub enum HookTrigger {
+    /// Adds its output to the context of the conversation, on the first prompt
     ConversationStart,
+    /// Adds its output to the context of each prompt
     PerPrompt,
+    /// Runs before a tool is used, and can block the tool use or rewrite its input
+    PreToolUse,
+    /// Runs after a tool was used, and can add feedback to its result
+    PostToolUse,
+    /// Runs when a response ends without tool uses, and can have the model continue
+    Stop,
+    /// Runs when the chat session exits
+    SessionEnd,
+}
+
+impl HookTrigger {
+    pub const ALL: [Self; 6] = [
+        Self::ConversationStart,
+        Self::PerPrompt,
+        Self::PreToolUse,
+        Self::PostToolUse,
+        Self::Stop,
+        Self::SessionEnd,
+    ];
+
+    /// Whether hooks with this trigger add their output to the context of prompts. The others are
+    /// event hooks, run by [run_event_hooks].
+    pub fn is_context(&self) -> bool {
+        matches!(self, Self::ConversationStart | Self::PerPrompt)
+    }
+}
+
+/// The outcome of an event hook, i.e. one whose trigger is not [HookTrigger::is_context].
+///
+/// Event hooks receive the event as a JSON object on stdin, with the `hook_event_name`,
+/// `conversation_id` and `cwd` fields, plus `tool_name`, `tool_input` and for post tool use hooks
+/// `tool_response` and `tool_status`, or `stop_hook_active` for stop hooks. Exit codes other than
+/// 0 and [BLOCKING_EXIT_CODE] are reported as errors, and do not block anything.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HookOutcome {
+    /// The hook exited with 0, with its stdout.
+    ///
+    /// The stdout of post tool use hooks is added to the tool result. If the stdout of a pre tool
+    /// use hook is a JSON object with a `tool_input` field, the tool is used with that input
+    /// instead.
+    Success(String),
+    /// The hook exited with [BLOCKING_EXIT_CODE], with its stderr, or its stdout if stderr is
+    /// empty, as the reason which is sent to the model.
+    ///
+    /// Pre tool use hooks block the tool use, post tool use hooks mark the tool result as an
+    /// error, and stop hooks have the model continue. Session end hooks cannot block.
+    Blocked(String),
 }
 
 #[derive(Debug, Clone)]
@@ -219,37 +289,7 @@ impl HookExecutor {
                 )?;
             }
 
-            match &result {
-                Ok(_) => {
-                    queue!(
-                        output,
-                        style::SetForegroundColor(style::Color::Green),
-                        style::Print("✓ "),
-                        style::SetForegroundColor(style::Color::Blue),
-                        style::Print(&hook.name),
-                        style::ResetColor,
-                        style::Print(" finished in "),
-                        style::SetForegroundColor(style::Color::Yellow),
-                        style::Print(format!("{:.2} s\n", duration.as_secs_f32())),
-                        style::ResetColor,
-                    )?;
-                },
-                Err(e) => {
-                    queue!(
-                        output,
-                        style::SetForegroundColor(style::Color::Red),
-                        style::Print("✗ "),
-                        style::SetForegroundColor(style::Color::Blue),
-                        style::Print(&hook.name),
-                        style::ResetColor,
-                        style::Print(" failed after "),
-                        style::SetForegroundColor(style::Color::Yellow),
-                        style::Print(format!("{:.2} s", duration.as_secs_f32())),
-                        style::ResetColor,
-                        style::Print(format!(": {}\n", e)),
-                    )?;
-                },
-            }
+            queue_hook_status(output, &hook.name, result.as_ref().err(), duration)?;
 
             // Process results regardless of output enabled
             if let Ok(output) = result {
@@ -285,7 +325,7 @@ impl HookExecutor {
         results.iter().skip(start_cache_index).for_each(|(_, (hook, output))| {
             let expiry = match hook.trigger {
                 HookTrigger::ConversationStart => None,
-                HookTrigger::PerPrompt => Some(Instant::now() + Duration::from_secs(hook.cache_ttl_seconds)),
+                _ => Some(Instant::now() + Duration::from_secs(hook.cache_ttl_seconds)),
             };
             self.insert_cache(hook, CachedHook {
                 output: output.clone(),
@@ -309,24 +349,7 @@ impl HookExecutor {
 
     async fn execute_inline_hook(&self, hook: &Hook) -> Result<String> {
         let command = hook.command.as_ref().ok_or_else(|| eyre!("no command specified"))?;
-
-        #[cfg(unix)]
-        let command_future = tokio::process::Command::new("bash")
-            .arg("-c")
-            .arg(command)
-            .stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .output();
-
-        #[cfg(windows)]
-        let command_future = tokio::process::Command::new("cmd")
-            .arg("/C")
-            .arg(command)
-            .stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .output();
+        let command_future = shell_command(command).output();
 
         let timeout = Duration::from_millis(hook.timeout_ms);
 
@@ -386,6 +409,151 @@ impl HookExecutor {
     }
 }
 
+/// Queues the line reporting that the hook `name` finished, or failed with `error`.
+fn queue_hook_status(
+    output: &mut impl Write,
+    name: &str,
+    error: Option<&ErrReport>,
+    duration: Duration,
+) -> std::io::Result<()> {
+    match error {
+        None => queue!(
+            output,
+            style::SetForegroundColor(style::Color::Green),
+            style::Print("✓ "),
+            style::SetForegroundColor(style::Color::Blue),
+            style::Print(name),
+            style::ResetColor,
+            style::Print(" finished in "),
+            style::SetForegroundColor(style::Color::Yellow),
+            style::Print(format!("{:.2} s\n", duration.as_secs_f32())),
+            style::ResetColor,
+        )?,
+        Some(e) => queue!(
+            output,
+            style::SetForegroundColor(style::Color::Red),
+            style::Print("✗ "),
+            style::SetForegroundColor(style::Color::Blue),
+            style::Print(name),
+            style::ResetColor,
+            style::Print(" failed after "),
+            style::SetForegroundColor(style::Color::Yellow),
+            style::Print(format!("{:.2} s", duration.as_secs_f32())),
+            style::ResetColor,
+            style::Print(format!(": {}\n", e)),
+        )?,
+    }
+    Ok(())
+}
+
+/// A command running `command` in the shell, with piped stdio.
+pub fn shell_command(command: &str) -> tokio::process::Command {
+    #[cfg(unix)]
+    let mut shell_command = tokio::process::Command::new("bash");
+    #[cfg(unix)]
+    shell_command.arg("-c");
+
+    #[cfg(windows)]
+    let mut shell_command = tokio::process::Command::new("cmd");
+    #[cfg(windows)]
+    shell_command.arg("/C");
+
+    shell_command
+        .arg(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    shell_command
+}
+
+/// Runs the event `hooks` one after another with `input` as JSON on stdin, stopping at the first
+/// hook that blocks. Event hooks are never cached, and those that fail are reported to `output`
+/// and skipped. See [HookOutcome] for how the outcomes are used.
+///
+/// The `tool_input` of `input` is rewritten by pre tool use hooks, so that each hook receives the
+/// input as rewritten by the hooks before it.
+pub async fn run_event_hooks(
+    hooks: &[Hook],
+    input: &mut serde_json::Value,
+    output: &mut impl Write,
+) -> Result<Vec<(Hook, HookOutcome)>, ChatError> {
+    let mut outcomes = Vec::new();
+    for hook in hooks.iter().filter(|hook| !hook.disabled) {
+        let start_time = Instant::now();
+        let result = execute_event_hook(hook, input).await;
+        let duration = start_time.elapsed();
+
+        match result {
+            Ok(HookOutcome::Success(stdout)) => {
+                queue_hook_status(output, &hook.name, None, duration)?;
+                if hook.trigger == HookTrigger::PreToolUse {
+                    if let Ok(serde_json::Value::Object(mut rewritten)) = serde_json::from_str(&stdout) {
+                        if let Some(tool_input) = rewritten.remove("tool_input") {
+                            input["tool_input"] = tool_input;
+                        }
+                    }
+                }
+                outcomes.push((hook.clone(), HookOutcome::Success(stdout)));
+            },
+            Ok(HookOutcome::Blocked(reason)) => {
+                queue!(
+                    output,
+                    style::SetForegroundColor(Color::Yellow),
+                    style::Print("✗ "),
+                    style::SetForegroundColor(Color::Blue),
+                    style::Print(&hook.name),
+                    style::ResetColor,
+                    style::Print(format!(" blocked: {reason}\n")),
+                )?;
+                outcomes.push((hook.clone(), HookOutcome::Blocked(reason)));
+                break;
+            },
+            Err(err) => queue_hook_status(output, &hook.name, Some(&err), duration)?,
+        }
+    }
+    output.flush()?;
+
+    Ok(outcomes)
+}
+
+async fn execute_event_hook(hook: &Hook, input: &serde_json::Value) -> Result<HookOutcome> {
+    let command = hook.command.as_ref().ok_or_else(|| eyre!("no command specified"))?;
+    let mut child = shell_command(command).kill_on_drop(true).spawn()?;
+
+    // Write stdin while waiting, since hooks may not read all of it
+    let mut stdin = child.stdin.take();
+    let input = serde_json::to_vec(input)?;
+    let write_input = async move {
+        if let Some(stdin) = stdin.as_mut() {
+            let _ = stdin.write_all(&input).await;
+        }
+        drop(stdin);
+    };
+
+    let timeout = Duration::from_millis(hook.timeout_ms);
+    let result = match tokio::time::timeout(timeout, futures::future::join(write_input, child.wait_with_output())).await
+    {
+        Ok((_, result)) => result?,
+        Err(_) => return Err(eyre!("command timed out after {} ms", timeout.as_millis())),
+    };
+
+    let output_text = |bytes: &[u8]| {
+        let text = bytes.to_str_lossy();
+        truncate_safe(text.trim_end(), hook.max_output_size).to_string()
+    };
+    match result.status.code() {
+        Some(0) => Ok(HookOutcome::Success(output_text(&result.stdout))),
+        Some(BLOCKING_EXIT_CODE) => {
+            let reason = match output_text(&result.stderr) {
+                stderr if stderr.is_empty() => output_text(&result.stdout),
+                stderr => stderr,
+            };
+            Ok(HookOutcome::Blocked(reason))
+        },
+        _ => Err(eyre!("command returned non-zero exit code: {}", result.status)),
+    }
+}
+
 #[deny(missing_docs)]
 #[derive(Debug, PartialEq, Args)]
 #[command(
@@ -396,7 +564,20 @@ in global or local profiles.
 Notes:
 • Hooks are executed in parallel
 • 'conversation_start' hooks run on the first user prompt and are attached once to the conversation history sent to Amazon Q
-• 'per_prompt' hooks run on each user prompt and are attached to the prompt, but are not stored in conversation history"
+• 'per_prompt' hooks run on each user prompt and are attached to the prompt, but are not stored in conversation history
+
+Event hooks run one after another, and receive the event as JSON on stdin:
+• 'pre_tool_use' hooks run before a tool is used, for the tools matching their --matcher glob
+• 'post_tool_use' hooks run after a tool was used, for the tools matching their --matcher glob
+• 'stop' hooks run when a response ends without tool uses
+• 'session_end' hooks run when the chat exits
+
+Exit codes of event hooks:
+• 0: success. The output of 'post_tool_use' hooks is added to the tool result, and a 'pre_tool_use' hook
+  can print a JSON object with a 'tool_input' field to use the tool with that input instead
+• 2: block, with stderr as the reason sent to Amazon Q. Blocks the tool use for 'pre_tool_use' hooks, marks
+  the tool result as an error for 'post_tool_use' hooks, and has Amazon Q continue for 'stop' hooks
+• Anything else: the hook failed, which is reported without blocking anything"
 )]
 pub struct HooksArgs {
     #[command(subcommand)]
@@ -423,18 +604,20 @@ impl HooksArgs {
             style::SetAttribute(Attribute::Reset),
         )?;
 
-        print_hook_section(
-            &mut session.stderr,
-            &context_manager.global_config.hooks,
-            HookTrigger::ConversationStart,
-        )
-        .map_err(map_chat_error)?;
-        print_hook_section(
-            &mut session.stderr,
-            &context_manager.global_config.hooks,
-            HookTrigger::PerPrompt,
-        )
-        .map_err(map_chat_error)?;
+        print_hook_sections(&mut session.stderr, &context_manager.global_config.hooks).map_err(map_chat_error)?;
+
+        if !context_manager.workspace_config.hooks.is_empty() {
+            queue!(
+                session.stderr,
+                style::SetAttribute(Attribute::Bold),
+                style::SetForegroundColor(Color::Magenta),
+                style::Print(format!("\n📁 workspace ({}):\n", WORKSPACE_CONFIG_PATH)),
+                style::SetAttribute(Attribute::Reset),
+            )?;
+
+            print_hook_sections(&mut session.stderr, &context_manager.workspace_config.hooks)
+                .map_err(map_chat_error)?;
+        }
 
         queue!(
             session.stderr,
@@ -444,18 +627,7 @@ impl HooksArgs {
             style::SetAttribute(Attribute::Reset),
         )?;
 
-        print_hook_section(
-            &mut session.stderr,
-            &context_manager.profile_config.hooks,
-            HookTrigger::ConversationStart,
-        )
-        .map_err(map_chat_error)?;
-        print_hook_section(
-            &mut session.stderr,
-            &context_manager.profile_config.hooks,
-            HookTrigger::PerPrompt,
-        )
-        .map_err(map_chat_error)?;
+        print_hook_sections(&mut session.stderr, &context_manager.profile_config.hooks).map_err(map_chat_error)?;
 
         execute!(
             session.stderr,
@@ -478,12 +650,15 @@ pub enum HooksSubcommand {
     Add {
         /// The name of the hook
         name: String,
-        /// When to trigger the hook, valid options: `per_prompt` or `conversation_start`
-        #[arg(long, value_parser = ["per_prompt", "conversation_start"])]
-        trigger: String,
+        /// When to trigger the hook
+        #[arg(long, value_enum)]
+        trigger: HookTrigger,
         /// Shell command to execute
         #[arg(long, value_parser = clap::value_parser!(String))]
         command: String,
+        /// Glob of the tool names a tool use hook runs for, e.g. `fs_*`
+        #[arg(long)]
+        matcher: Option<String>,
         /// Add to global hooks
         #[arg(long)]
         global: bool,
@@ -544,17 +719,13 @@ impl HooksSubcommand {
                 name,
                 trigger,
                 command,
+                matcher,
                 global,
             } => {
-                let trigger = if trigger == "conversation_start" {
-                    HookTrigger::ConversationStart
-                } else {
-                    HookTrigger::PerPrompt
-                };
+                let mut hook = Hook::new_inline_hook(trigger, command);
+                hook.matcher = matcher;
 
-                let result = context_manager
-                    .add_hook(os, name.clone(), Hook::new_inline_hook(trigger, command), global)
-                    .await;
+                let result = context_manager.add_hook(os, name.clone(), hook, global).await;
                 match result {
                     Ok(_) => {
                         execute!(
@@ -671,18 +842,22 @@ impl HooksSubcommand {
                     style::SetAttribute(Attribute::Reset),
                 )?;
 
-                print_hook_section(
-                    &mut session.stderr,
-                    &context_manager.global_config.hooks,
-                    HookTrigger::ConversationStart,
-                )
-                .map_err(map_chat_error)?;
-                print_hook_section(
-                    &mut session.stderr,
-                    &context_manager.global_config.hooks,
-                    HookTrigger::PerPrompt,
-                )
-                .map_err(map_chat_error)?;
+                print_hook_sections(&mut session.stderr, &context_manager.global_config.hooks)
+                    .map_err(map_chat_error)?;
+
+                // Display workspace hooks, which are only edited in the workspace configuration
+                if !context_manager.workspace_config.hooks.is_empty() {
+                    execute!(
+                        session.stderr,
+                        style::SetAttribute(Attribute::Bold),
+                        style::SetForegroundColor(Color::Magenta),
+                        style::Print(format!("\n📁 workspace ({}):\n", WORKSPACE_CONFIG_PATH)),
+                        style::SetAttribute(Attribute::Reset),
+                    )?;
+
+                    print_hook_sections(&mut session.stderr, &context_manager.workspace_config.hooks)
+                        .map_err(map_chat_error)?;
+                }
 
                 // Display profile hooks
                 execute!(
@@ -693,18 +868,8 @@ impl HooksSubcommand {
                     style::SetAttribute(Attribute::Reset),
                 )?;
 
-                print_hook_section(
-                    &mut session.stderr,
-                    &context_manager.profile_config.hooks,
-                    HookTrigger::ConversationStart,
-                )
-                .map_err(map_chat_error)?;
-                print_hook_section(
-                    &mut session.stderr,
-                    &context_manager.profile_config.hooks,
-                    HookTrigger::PerPrompt,
-                )
-                .map_err(map_chat_error)?;
+                print_hook_sections(&mut session.stderr, &context_manager.profile_config.hooks)
+                    .map_err(map_chat_error)?;
                 execute!(session.stderr, style::Print("\n"))?;
             },
         }
@@ -715,11 +880,24 @@ impl HooksSubcommand {
     }
 }
 
-/// Prints hook configuration grouped by trigger: conversation session start or per user message
+/// Prints hook configuration grouped by trigger, for every trigger.
+pub fn print_hook_sections(output: &mut impl Write, hooks: &HashMap<String, Hook>) -> Result<()> {
+    for trigger in HookTrigger::ALL {
+        print_hook_section(output, hooks, trigger)?;
+    }
+    Ok(())
+}
+
+/// Prints the hook configuration of one trigger, e.g. conversation session start or per user
+/// message
 pub fn print_hook_section(output: &mut impl Write, hooks: &HashMap<String, Hook>, trigger: HookTrigger) -> Result<()> {
     let section = match trigger {
         HookTrigger::ConversationStart => "On Session Start",
         HookTrigger::PerPrompt => "Per User Message",
+        HookTrigger::PreToolUse => "Before Tool Use",
+        HookTrigger::PostToolUse => "After Tool Use",
+        HookTrigger::Stop => "When a Response Ends",
+        HookTrigger::SessionEnd => "On Session End",
     };
     let hooks: Vec<(&String, &Hook)> = hooks.iter().filter(|(_, h)| h.trigger == trigger).collect();
 
@@ -739,15 +917,19 @@ pub fn print_hook_section(output: &mut impl Write, hooks: &HashMap<String, Hook>
         )?;
     } else {
         for (name, hook) in hooks {
+            let matcher = match &hook.matcher {
+                Some(matcher) => format!(" (tools: {matcher})"),
+                None => String::new(),
+            };
             if hook.disabled {
                 queue!(
                     output,
                     style::SetForegroundColor(Color::DarkGrey),
-                    style::Print(format!("      {} (disabled)\n", name)),
+                    style::Print(format!("      {}{matcher} (disabled)\n", name)),
                     style::SetForegroundColor(Color::Reset)
                 )?;
             } else {
-                queue!(output, style::Print(format!("      {}\n", name)),)?;
+                queue!(output, style::Print(format!("      {}{matcher}\n", name)),)?;
             }
         }
     }
@@ -1094,6 +1276,83 @@ mod tests {
         assert!(results[0].1.len() <= hook.max_output_size + " ... truncated".len());
     }
 
+    #[test]
+    fn test_hook_matches_tool() {
+        let mut hook = Hook::new_inline_hook(HookTrigger::PreToolUse, "true".to_string());
+        assert!(hook.matches_tool("fs_write"));
+
+        hook.matcher = Some("fs_*".to_string());
+        assert!(hook.matches_tool("fs_write"));
+        assert!(!hook.matches_tool("execute_bash"));
+
+        hook.matcher = Some("{fs_write,use_aws}".to_string());
+        assert!(hook.matches_tool("use_aws"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_run_event_hooks() {
+        let mut input = serde_json::json!({ "tool_name": "fs_write", "tool_input": { "path": "Cargo.lock" } });
+
+        // Hooks receive the input on stdin, and rewrite it for the hooks after them
+        let mut rewrite = Hook::new_inline_hook(
+            HookTrigger::PreToolUse,
+            r#"echo '{"tool_input": {"path": "other.txt"}}'"#.to_string(),
+        );
+        rewrite.name = "a".to_string();
+        let mut echo = Hook::new_inline_hook(HookTrigger::PreToolUse, "cat".to_string());
+        echo.name = "b".to_string();
+        let outcomes = run_event_hooks(&[rewrite, echo], &mut input, &mut vec![])
+            .await
+            .unwrap();
+        assert_eq!(outcomes.len(), 2);
+        assert_eq!(input["tool_input"]["path"], "other.txt");
+        let HookOutcome::Success(stdout) = &outcomes[1].1 else {
+            panic!("expected success");
+        };
+        assert!(stdout.contains("other.txt"), "{stdout}");
+
+        // Failed hooks are skipped, and blocking hooks stop the others from running
+        let failing = Hook::new_inline_hook(HookTrigger::PreToolUse, "exit 1".to_string());
+        let block = Hook::new_inline_hook(
+            HookTrigger::PreToolUse,
+            "grep -q other.txt && echo 'no writes to other.txt' >&2 && exit 2".to_string(),
+        );
+        let after = Hook::new_inline_hook(HookTrigger::PreToolUse, "echo after".to_string());
+        let outcomes = run_event_hooks(&[failing, block, after], &mut input, &mut vec![])
+            .await
+            .unwrap();
+        assert_eq!(outcomes.len(), 1);
+        assert_eq!(
+            outcomes[0].1,
+            HookOutcome::Blocked("no writes to other.txt".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_event_hooks_are_not_context_hooks() -> Result<()> {
+        let os = Os::new().await.unwrap();
+        let mut manager = create_test_context_manager(None).await?;
+        let mut hook = Hook::new_inline_hook(HookTrigger::PostToolUse, "echo test".to_string());
+        hook.matcher = Some("fs_write".to_string());
+        manager.add_hook(&os, "lint".to_string(), hook, false).await?;
+
+        assert!(manager.run_hooks(&mut vec![]).await.unwrap().is_empty());
+        assert_eq!(manager.event_hooks(HookTrigger::PostToolUse, Some("fs_write")).len(), 1);
+        assert!(
+            manager
+                .event_hooks(HookTrigger::PostToolUse, Some("fs_read"))
+                .is_empty()
+        );
+        assert!(
+            manager
+                .event_hooks(HookTrigger::PreToolUse, Some("fs_write"))
+                .is_empty()
+        );
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_os_specific_command_execution() {
         let mut executor = HookExecutor::new();
@@ -8,6 +8,7 @@ use crossterm::style::{
 };
 use eyre::Result;
 use semantic_search_client::{
+    IndexingFilter,
     KnowledgeContext,
     OperationStatus,
     SystemStatus,
@@ -29,7 +30,18 @@ pub enum KnowledgeSubcommand {
     /// Display the knowledge base contents
     Show,
     /// Add a file or directory to knowledge base
-    Add { path: String },
+    Add {
+        path: String,
+        /// Only index the files matching these glob patterns, e.g. '*.rs'
+        #[arg(long, value_name = "GLOB")]
+        include: Vec<String>,
+        /// Skip the files and directories matching these glob patterns, e.g. 'node_modules'
+        #[arg(long, value_name = "GLOB")]
+        exclude: Vec<String>,
+        /// Also index the files ignored by .gitignore and .ignore files
+        #[arg(long)]
+        no_ignore: bool,
+    },
     /// Remove specified knowledge context by path
     #[command(alias = "rm")]
     Remove { path: String },
@@ -98,7 +110,19 @@ impl KnowledgeSubcommand {
                     Err(e) => OperationResult::Error(format!("Failed to show contexts: {}", e)),
                 }
             },
-            KnowledgeSubcommand::Add { path } => Self::handle_add(os, path).await,
+            KnowledgeSubcommand::Add {
+                path,
+                include,
+                exclude,
+                no_ignore,
+            } => {
+                let filter = IndexingFilter {
+                    include: include.clone(),
+                    exclude: exclude.clone(),
+                    no_ignore: *no_ignore,
+                };
+                Self::handle_add(os, path, filter).await
+            },
             KnowledgeSubcommand::Remove { path } => Self::handle_remove(os, path).await,
             KnowledgeSubcommand::Update { path } => Self::handle_update(os, path).await,
             KnowledgeSubcommand::Clear => Self::handle_clear(session).await,
@@ -179,6 +203,21 @@ impl KnowledgeSubcommand {
         if let Some(path) = &context.source_path {
             queue!(session.stderr, style::Print(format!("   Source: {}\n", path)))?;
         }
+        if !context.filter.include.is_empty() {
+            queue!(
+                session.stderr,
+                style::Print(format!("   Include: {}\n", context.filter.include.join(", ")))
+            )?;
+        }
+        if !context.filter.exclude.is_empty() {
+            queue!(
+                session.stderr,
+                style::Print(format!("   Exclude: {}\n", context.filter.exclude.join(", ")))
+            )?;
+        }
+        if context.filter.no_ignore {
+            queue!(session.stderr, style::Print("   Ignore files: not honored\n"))?;
+        }
 
         queue!(
             session.stderr,
@@ -210,14 +249,14 @@ impl KnowledgeSubcommand {
     }
 
     /// Handle add operation
-    async fn handle_add(os: &Os, path: &str) -> OperationResult {
+    async fn handle_add(os: &Os, path: &str, filter: IndexingFilter) -> OperationResult {
         match Self::validate_and_sanitize_path(os, path) {
             Ok(sanitized_path) => {
                 let async_knowledge_store = KnowledgeStore::get_async_instance().await;
                 let mut store = async_knowledge_store.lock().await;
 
                 // Use the async add method which is fire-and-forget
-                match store.add(path, &sanitized_path).await {
+                match store.add(path, &sanitized_path, filter).await {
                     Ok(message) => OperationResult::Info(message),
                     Err(e) => OperationResult::Error(format!("Failed to add to knowledge base: {}", e)),
                 }
@@ -1,4 +1,6 @@
+pub mod checkpoint;
 pub mod clear;
+pub mod commit;
 pub mod compact;
 pub mod context;
 pub mod editor;
@@ -7,14 +9,22 @@ pub mod knowledge;
 pub mod mcp;
 pub mod model;
 pub mod persist;
+pub mod pin;
 pub mod profile;
 pub mod prompts;
+pub mod resources;
+pub mod sessions;
 pub mod subscribe;
 pub mod tools;
 pub mod usage;
 
+use checkpoint::{
+    CheckpointSubcommand,
+    UndoArgs,
+};
 use clap::Parser;
 use clear::ClearArgs;
+use commit::CommitArgs;
 use compact::CompactArgs;
 use context::ContextSubcommand;
 use editor::EditorArgs;
@@ -23,8 +33,11 @@ use knowledge::KnowledgeSubcommand;
 use mcp::McpArgs;
 use model::ModelArgs;
 use persist::PersistSubcommand;
+use pin::PinSubcommand;
 use profile::ProfileSubcommand;
 use prompts::PromptsArgs;
+use resources::ResourcesArgs;
+use sessions::SessionsArgs;
 use tools::ToolsArgs;
 
 use crate::cli::chat::cli::subscribe::SubscribeArgs;
@@ -62,12 +75,24 @@ pub enum SlashCommand {
     PromptEditor(EditorArgs),
     /// Summarize the conversation to free up context space
     Compact(CompactArgs),
+    /// Pin messages so that they survive compaction
+    #[command(subcommand)]
+    Pin(PinSubcommand),
+    /// Undo the most recent file change made by fs_write
+    Undo(UndoArgs),
+    /// List and restore checkpoints of the file changes made in this session
+    #[command(subcommand)]
+    Checkpoint(CheckpointSubcommand),
+    /// Draft a message for the staged changes and commit them
+    Commit(CommitArgs),
     /// View and manage tools and permissions
     Tools(ToolsArgs),
     /// Create a new Github issue or make a feature request
     Issue(issue::IssueArgs),
     /// View and retrieve prompts
     Prompts(PromptsArgs),
+    /// View, read and subscribe to resources
+    Resources(ResourcesArgs),
     /// View and manage context hooks
     Hooks(HooksArgs),
     /// Show current session's context window usage
@@ -80,11 +105,24 @@ pub enum SlashCommand {
     Subscribe(SubscribeArgs),
     #[command(flatten)]
     Persist(PersistSubcommand),
+    /// Browse, resume and delete saved sessions
+    Sessions(SessionsArgs),
     // #[command(flatten)]
     // Root(RootSubcommand),
 }
 
 impl SlashCommand {
+    /// Whether the command opens an editor or a picker, which need a terminal that sessions
+    /// driven by another program do not have.
+    pub fn needs_terminal(&self) -> bool {
+        match self {
+            Self::PromptEditor(_) | Self::Model(_) => true,
+            Self::Sessions(args) => args.opens_picker(),
+            Self::Commit(args) => !args.no_edit,
+            _ => false,
+        }
+    }
+
     pub async fn execute(self, os: &mut Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
         match self {
             Self::Quit => Ok(ChatState::Exit),
@@ -94,7 +132,11 @@ impl SlashCommand {
             Self::Knowledge(subcommand) => subcommand.execute(os, session).await,
             Self::PromptEditor(args) => args.execute(session).await,
             Self::Compact(args) => args.execute(os, session).await,
-            Self::Tools(args) => args.execute(session).await,
+            Self::Pin(subcommand) => subcommand.execute(session).await,
+            Self::Undo(args) => args.execute(os, session).await,
+            Self::Checkpoint(subcommand) => subcommand.execute(os, session).await,
+            Self::Commit(args) => args.execute(os, session).await,
+            Self::Tools(args) => args.execute(os, session).await,
             Self::Issue(args) => {
                 if let Err(err) = args.execute(os).await {
                     return Err(ChatError::Custom(err.to_string().into()));
@@ -104,13 +146,15 @@ impl SlashCommand {
                     skip_printing_tools: true,
                 })
             },
-            Self::Prompts(args) => args.execute(session).await,
+            Self::Prompts(args) => args.execute(os, session).await,
+            Self::Resources(args) => args.execute(session).await,
             Self::Hooks(args) => args.execute(os, session).await,
             Self::Usage(args) => args.execute(os, session).await,
             Self::Mcp(args) => args.execute(session).await,
             Self::Model(args) => args.execute(session).await,
             Self::Subscribe(args) => args.execute(os, session).await,
             Self::Persist(subcommand) => subcommand.execute(os, session).await,
+            Self::Sessions(args) => args.execute(os, session).await,
             // Self::Root(subcommand) => {
             //     if let Err(err) = subcommand.execute(os, database, telemetry).await {
             //         return Err(ChatError::Custom(err.to_string().into()));
@@ -9,6 +9,7 @@ use crossterm::{
 };
 use dialoguer::Select;
 
+use crate::cli::chat::consts::CONTEXT_WINDOW_SIZE;
 use crate::cli::chat::{
     ChatError,
     ChatSession,
@@ -19,23 +20,37 @@ use crate::os::Os;
 pub struct ModelOption {
     pub name: &'static str,
     pub model_id: &'static str,
+    /// Size of the context window of the model, in tokens
+    pub context_window_tokens: usize,
 }
 
 pub const MODEL_OPTIONS: [ModelOption; 3] = [
     ModelOption {
         name: "claude-4-sonnet",
         model_id: "CLAUDE_SONNET_4_20250514_V1_0",
+        context_window_tokens: 200_000,
     },
     ModelOption {
         name: "claude-3.7-sonnet",
         model_id: "CLAUDE_3_7_SONNET_20250219_V1_0",
+        context_window_tokens: 200_000,
     },
     ModelOption {
         name: "claude-3.5-sonnet",
         model_id: "CLAUDE_3_5_SONNET_20241022_V2_0",
+        context_window_tokens: 200_000,
     },
 ];
 
+/// Returns the size of the context window of the model with id `model_id`, in tokens, or that of
+/// the default model if the model is not set or unknown.
+pub fn context_window_tokens(model_id: Option<&str>) -> usize {
+    MODEL_OPTIONS
+        .iter()
+        .find(|option| Some(option.model_id) == model_id)
+        .map_or(CONTEXT_WINDOW_SIZE, |option| option.context_window_tokens)
+}
+
 #[deny(missing_docs)]
 #[derive(Debug, PartialEq, Args)]
 pub struct ModelArgs;
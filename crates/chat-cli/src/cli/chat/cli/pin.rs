@@ -0,0 +1,130 @@
+use clap::Subcommand;
+use crossterm::style::{
+    self,
+    Attribute,
+    Color,
+};
+use crossterm::{
+    execute,
+    queue,
+};
+
+use crate::cli::chat::util::truncate_safe;
+use crate::cli::chat::{
+    ChatError,
+    ChatSession,
+    ChatState,
+};
+
+/// Number of bytes of a prompt shown by /pin list.
+const PROMPT_PREVIEW_LEN: usize = 80;
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Subcommand)]
+#[command(
+    before_long_help = "Pinned messages survive /compact and automatic compaction: they are kept in the history \
+after it is summarized or its oldest messages are removed, together with the response to them.
+
+Messages are referred to by the id shown by /pin list."
+)]
+pub enum PinSubcommand {
+    /// List the prompts in the history, and whether they are pinned
+    List,
+    /// Pin a prompt, by default the most recent one
+    Add {
+        /// Id of the prompt, as shown by /pin list
+        id: Option<usize>,
+    },
+    /// Unpin a prompt
+    Remove {
+        /// Id of the prompt, as shown by /pin list
+        id: usize,
+    },
+}
+
+impl PinSubcommand {
+    pub async fn execute(self, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        match self {
+            Self::List => {
+                let prompts = session
+                    .conversation
+                    .history()
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(index, (user, _))| Some((index + 1, user.pinned, user.prompt()?.to_string())))
+                    .collect::<Vec<_>>();
+                if prompts.is_empty() {
+                    print_message(session, Color::DarkGrey, "\nThere are no prompts in the history.\n\n")?;
+                } else {
+                    queue!(session.stderr, style::Print("\n"))?;
+                    for (id, pinned, prompt) in prompts {
+                        let first_line = prompt.lines().next().unwrap_or_default();
+                        let preview = truncate_safe(first_line, PROMPT_PREVIEW_LEN);
+                        let ellipsis = if preview.len() < prompt.trim_end().len() {
+                            "…"
+                        } else {
+                            ""
+                        };
+                        queue!(
+                            session.stderr,
+                            style::SetAttribute(Attribute::Bold),
+                            style::Print(format!("{id:>4}")),
+                            style::SetAttribute(Attribute::Reset),
+                            style::SetForegroundColor(Color::Green),
+                            style::Print(if pinned { " 📌 " } else { "    " }),
+                            style::SetForegroundColor(Color::Reset),
+                            style::Print(format!("{preview}{ellipsis}\n")),
+                        )?;
+                    }
+                    execute!(session.stderr, style::Print("\n"))?;
+                }
+            },
+            Self::Add { id } => {
+                let index = match id {
+                    Some(id) => id.checked_sub(1),
+                    None => session
+                        .conversation
+                        .history()
+                        .iter()
+                        .rposition(|(user, _)| user.prompt().is_some()),
+                };
+                match index {
+                    Some(index) if set_pinned(session, index, true) => {
+                        print_message(session, Color::Green, &format!("\nPinned message {}.\n\n", index + 1))?;
+                    },
+                    _ => print_message(session, Color::Red, "\nNo such prompt, see /pin list.\n\n")?,
+                }
+            },
+            Self::Remove { id } => match id.checked_sub(1) {
+                Some(index) if set_pinned(session, index, false) => {
+                    print_message(session, Color::Green, &format!("\nUnpinned message {id}.\n\n"))?;
+                },
+                _ => print_message(session, Color::Red, "\nNo such prompt, see /pin list.\n\n")?,
+            },
+        }
+
+        Ok(ChatState::PromptUser {
+            skip_printing_tools: true,
+        })
+    }
+}
+
+/// Pins or unpins the prompt at `index` in the history, returning [false] if it is not a prompt.
+fn set_pinned(session: &mut ChatSession, index: usize, pinned: bool) -> bool {
+    let is_prompt = session
+        .conversation
+        .history()
+        .get(index)
+        .is_some_and(|(user, _)| user.prompt().is_some());
+    is_prompt && session.conversation.set_pinned(index, pinned)
+}
+
+fn print_message(session: &mut ChatSession, color: Color, message: &str) -> Result<(), ChatError> {
+    execute!(
+        session.stderr,
+        style::SetForegroundColor(color),
+        style::Print(message),
+        style::SetForegroundColor(Color::Reset)
+    )?;
+    Ok(())
+}
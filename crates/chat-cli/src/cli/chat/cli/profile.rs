@@ -101,7 +101,7 @@ impl ProfileSubcommand {
                         style::Print(format!("\nCreated profile: {}\n", name)),
                         style::SetForegroundColor(Color::Reset)
                     )?;
-                    
+
                     // Automatically switch to the newly created profile
                     match context_manager.switch_profile(os, &name).await {
                         Ok(_) => {
@@ -111,14 +111,14 @@ impl ProfileSubcommand {
                                 style::Print(format!("Switched to profile: {}\n", name)),
                                 style::SetForegroundColor(Color::Reset)
                             )?;
-                            
+
                             // Reload MCP servers for the new profile
                             let mut os_mut = os.clone();
-                            if let Err(e) = session.conversation.reload_mcp_servers_for_profile(
-                                &mut os_mut, 
-                                Some(&name), 
-                                &mut session.stderr
-                            ).await {
+                            if let Err(e) = session
+                                .conversation
+                                .reload_mcp_servers_for_profile(&mut os_mut, Some(&name), &mut session.stderr)
+                                .await
+                            {
                                 // Log the error but don't fail the profile creation - graceful degradation
                                 execute!(
                                     session.stderr,
@@ -136,9 +136,9 @@ impl ProfileSubcommand {
                                 style::Print(format!("Warning: Failed to switch to new profile: {}\n", e)),
                                 style::SetForegroundColor(Color::Reset)
                             )?;
-                        }
+                        },
                     }
-                    
+
                     execute!(session.stderr, style::Print("\n"))?;
                 },
                 Err(e) => print_err!(e),
@@ -162,14 +162,14 @@ impl ProfileSubcommand {
                         style::Print(format!("\nSwitched to profile: {}\n", name)),
                         style::SetForegroundColor(Color::Reset)
                     )?;
-                    
+
                     // Reload MCP servers for the new profile to provide seamless tool availability
                     let mut os_mut = os.clone();
-                    if let Err(e) = session.conversation.reload_mcp_servers_for_profile(
-                        &mut os_mut, 
-                        Some(&name), 
-                        &mut session.stderr
-                    ).await {
+                    if let Err(e) = session
+                        .conversation
+                        .reload_mcp_servers_for_profile(&mut os_mut, Some(&name), &mut session.stderr)
+                        .await
+                    {
                         // Log the error but don't fail the profile switch - graceful degradation
                         execute!(
                             session.stderr,
@@ -178,7 +178,7 @@ impl ProfileSubcommand {
                             style::SetForegroundColor(Color::Reset)
                         )?;
                     }
-                    
+
                     execute!(session.stderr, style::Print("\n"))?;
                 },
                 Err(e) => print_err!(e),
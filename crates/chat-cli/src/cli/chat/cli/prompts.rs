@@ -2,6 +2,7 @@ use std::collections::{
     HashMap,
     VecDeque,
 };
+use std::io::Write;
 
 use clap::{
     Args,
@@ -20,13 +21,25 @@ use thiserror::Error;
 use unicode_width::UnicodeWidthStr;
 
 use crate::cli::chat::error_formatter::format_mcp_error;
+use crate::cli::chat::local_prompts::{
+    self,
+    LocalPrompt,
+    PromptScope,
+    WORKSPACE_PROMPTS_DIR,
+};
 use crate::cli::chat::tool_manager::PromptBundle;
 use crate::cli::chat::{
     ChatError,
     ChatSession,
     ChatState,
 };
-use crate::mcp_client::PromptGetResult;
+use crate::mcp_client::{
+    MessageContent,
+    Prompt,
+    PromptGetResult,
+    Role,
+};
+use crate::os::Os;
 
 #[derive(Debug, Error)]
 pub enum GetPromptError {
@@ -50,7 +63,13 @@ pub enum GetPromptError {
 #[derive(Debug, PartialEq, Args)]
 #[command(color = clap::ColorChoice::Always,
     before_long_help = color_print::cstr!{"Prompts are reusable templates that help you quickly access common workflows and tasks. 
-These templates are provided by the mcp servers you have installed and configured.
+These templates are provided by the mcp servers you have installed and configured, or are markdown files in \
+.amazonq/prompts of the workspace or in ~/.aws/amazonq/prompts.
+
+Markdown templates declare their arguments in front matter, and can include files and the output of commands:
+  <em>{{<<argument>>}}</em>                                   <black!>Value of the argument</black!>
+  <em>{{file:<<path>>}}</em>                                  <black!>Contents of the file, relative to the current directory</black!>
+  <em>{{shell:<<command>>}}</em>                              <black!>Output of the command</black!>
 
 To actually retrieve a prompt, directly start with the following command (without prepending /prompt get):
   <em>@<<prompt name>> [arg]</em>                             <black!>Retrieve prompt specified</black!>
@@ -63,7 +82,7 @@ pub struct PromptsArgs {
 }
 
 impl PromptsArgs {
-    pub async fn execute(self, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+    pub async fn execute(self, os: &Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
         let search_word = match &self.subcommand {
             Some(PromptsSubcommand::List { search_word }) => search_word.clone(),
             _ => None,
@@ -71,11 +90,16 @@ impl PromptsArgs {
 
         if let Some(subcommand) = self.subcommand {
             if matches!(subcommand, PromptsSubcommand::Get { .. }) {
-                return subcommand.execute(session).await;
+                return subcommand.execute(os, session).await;
             }
         }
 
         let terminal_width = session.terminal_width();
+        let local_prompts = local_prompts::load_prompts(os)
+            .await
+            .into_iter()
+            .filter(|prompt| prompt.name.contains(search_word.as_deref().unwrap_or("")))
+            .collect::<Vec<_>>();
         let mut prompts_wl = session.conversation.tool_manager.prompts.write().map_err(|e| {
             ChatError::Custom(format!("Poison error encountered while retrieving prompts: {}", e).into())
         })?;
@@ -143,12 +167,42 @@ impl PromptsArgs {
             .collect();
         prompts_by_server.sort_by_key(|(server_name, _)| server_name.as_str());
 
-        for (i, (server_name, bundles)) in prompts_by_server.iter_mut().enumerate() {
+        let mut is_first_section = true;
+        for (scope, label) in [
+            (PromptScope::Workspace, format!("workspace ({WORKSPACE_PROMPTS_DIR})")),
+            (PromptScope::Global, "global (~/.aws/amazonq/prompts)".to_string()),
+        ] {
+            let prompts = local_prompts
+                .iter()
+                .filter(|prompt| prompt.scope == scope)
+                .collect::<Vec<_>>();
+            if prompts.is_empty() {
+                continue;
+            }
+            if !is_first_section {
+                queue!(session.stderr, style::Print("\n"))?;
+            }
+            is_first_section = false;
+            queue!(
+                session.stderr,
+                style::SetAttribute(Attribute::Bold),
+                style::Print(label),
+                style::Print(":"),
+                style::SetAttribute(Attribute::Reset),
+                style::Print("\n"),
+            )?;
+            for prompt in prompts {
+                queue_local_prompt(&mut session.stderr, prompt, arg_pos)?;
+            }
+        }
+
+        for (server_name, bundles) in prompts_by_server.iter_mut() {
             bundles.sort_by_key(|bundle| &bundle.prompt_get.name);
 
-            if i > 0 {
+            if !is_first_section {
                 queue!(session.stderr, style::Print("\n"))?;
             }
+            is_first_section = false;
             queue!(
                 session.stderr,
                 style::SetAttribute(Attribute::Bold),
@@ -200,6 +254,31 @@ impl PromptsArgs {
     }
 }
 
+fn queue_local_prompt(output: &mut impl Write, prompt: &LocalPrompt, arg_pos: usize) -> Result<(), ChatError> {
+    queue!(output, style::Print("- "), style::Print(&prompt.name))?;
+    if prompt.arguments.is_empty() {
+        queue!(output, style::Print("\n"))?;
+        return Ok(());
+    }
+    let name_width = UnicodeWidthStr::width(prompt.name.as_str());
+    let padding = arg_pos.saturating_sub(name_width + UnicodeWidthStr::width("- "));
+    queue!(output, style::Print(" ".repeat(padding.max(1))))?;
+    for (i, arg) in prompt.arguments.iter().enumerate() {
+        queue!(
+            output,
+            style::SetForegroundColor(Color::DarkGrey),
+            style::Print(if arg.required {
+                format!("{}*", arg.name)
+            } else {
+                arg.name.clone()
+            }),
+            style::SetForegroundColor(Color::Reset),
+            style::Print(if i < prompt.arguments.len() - 1 { ", " } else { "\n" }),
+        )?;
+    }
+    Ok(())
+}
+
 #[deny(missing_docs)]
 #[derive(Clone, Debug, PartialEq, Subcommand)]
 pub enum PromptsSubcommand {
@@ -214,7 +293,7 @@ pub enum PromptsSubcommand {
 }
 
 impl PromptsSubcommand {
-    pub async fn execute(self, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+    pub async fn execute(self, os: &Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
         let PromptsSubcommand::Get {
             orig_input,
             name,
@@ -224,6 +303,71 @@ impl PromptsSubcommand {
             unreachable!("List has already been parsed out at this point");
         };
 
+        // Templates of the workspace and the global prompts directory take precedence over the
+        // prompts of MCP servers
+        if let Some(prompt) = local_prompts::find_prompt(os, &name).await {
+            // Workspace templates come with the repository, so the commands they run are shown
+            // to the user first
+            let commands = prompt.shell_commands();
+            if prompt.scope == PromptScope::Workspace && !commands.is_empty() {
+                queue!(
+                    session.stderr,
+                    style::SetForegroundColor(Color::DarkGrey),
+                    style::Print(format!(
+                        "\nPrompt {name} of the workspace runs the following commands:\n"
+                    )),
+                    style::SetForegroundColor(Color::Reset),
+                )?;
+                for command in commands {
+                    queue!(session.stderr, style::Print(format!("  {command}\n")))?;
+                }
+                execute!(
+                    session.stderr,
+                    style::SetForegroundColor(Color::DarkGrey),
+                    style::Print("\nRun them? ["),
+                    style::SetForegroundColor(Color::Green),
+                    style::Print("y"),
+                    style::SetForegroundColor(Color::DarkGrey),
+                    style::Print("/"),
+                    style::SetForegroundColor(Color::Green),
+                    style::Print("n"),
+                    style::SetForegroundColor(Color::DarkGrey),
+                    style::Print("]:\n\n"),
+                    style::SetForegroundColor(Color::Reset),
+                )?;
+
+                let user_input = session.read_user_input("> ", true).unwrap_or_default();
+                if !["y", "Y"].contains(&user_input.trim()) {
+                    return Ok(ChatState::PromptUser {
+                        skip_printing_tools: true,
+                    });
+                }
+            }
+
+            let text = match prompt.render(os, arguments.as_deref().unwrap_or_default()).await {
+                Ok(text) => text,
+                Err(err) => {
+                    execute!(
+                        session.stderr,
+                        style::SetForegroundColor(Color::Red),
+                        style::Print(format!("\nFailed to render prompt {name}: {err}\n\n")),
+                        style::SetForegroundColor(Color::Reset),
+                    )?;
+                    return Ok(ChatState::PromptUser {
+                        skip_printing_tools: true,
+                    });
+                },
+            };
+            session.pending_prompts.clear();
+            session.pending_prompts.push_back(Prompt {
+                role: Role::User,
+                content: MessageContent::Text { text },
+            });
+            return Ok(ChatState::HandleInput {
+                input: orig_input.unwrap_or_default(),
+            });
+        }
+
         let prompts = match session.conversation.tool_manager.get_prompt(name, arguments).await {
             Ok(resp) => resp,
             Err(e) => {
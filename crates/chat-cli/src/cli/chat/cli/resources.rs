@@ -0,0 +1,439 @@
+use std::io::Write;
+
+use clap::{
+    Args,
+    Subcommand,
+};
+use crossterm::style::{
+    self,
+    Attribute,
+    Color,
+};
+use crossterm::{
+    execute,
+    queue,
+};
+use thiserror::Error;
+use unicode_width::UnicodeWidthStr;
+
+use crate::cli::chat::consts::MAX_TOOL_RESPONSE_SIZE;
+use crate::cli::chat::error_formatter::format_mcp_error;
+use crate::cli::chat::util::truncate_safe;
+use crate::cli::chat::{
+    ChatError,
+    ChatSession,
+    ChatState,
+};
+use crate::mcp_client::ResourceReadResult;
+
+/// Characters that are stripped from the end of a mention, since they are more likely to be
+/// punctuation of the surrounding sentence than part of the uri.
+const MENTION_TRAILING_PUNCTUATION: &[char] = &['.', ',', ';', '!', '?', ')', '"', '\''];
+
+/// Max size of the contents of a mentioned resource that are inlined in a message, in bytes.
+const MAX_INLINED_RESOURCE_SIZE: usize = MAX_TOOL_RESPONSE_SIZE / 4;
+
+#[derive(Debug, Error)]
+pub enum GetResourceError {
+    #[error("Server {0} does not exist")]
+    ServerNotFound(String),
+    #[error("Invalid resource reference {0}. Expected <server>:<uri>")]
+    InvalidReference(String),
+    #[error("Result field missing from resources/read response")]
+    MissingResult,
+    #[error("{}", format_mcp_error(.0))]
+    Mcp(serde_json::Value),
+    #[error(transparent)]
+    General(#[from] eyre::Report),
+}
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Args)]
+#[command(color = clap::ColorChoice::Always,
+    before_long_help = color_print::cstr!{"Resources are pieces of data, such as files or database records, exposed by the mcp servers you have installed and configured.
+
+To include the contents of a resource in your next message, mention it anywhere in the message:
+  <em>@<<server>>:<<uri>></em>                               <black!>Inline the resource specified</black!>"
+})]
+pub struct ResourcesArgs {
+    #[command(subcommand)]
+    subcommand: Option<ResourcesSubcommand>,
+}
+
+impl ResourcesArgs {
+    pub async fn execute(self, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        match self.subcommand {
+            Some(ResourcesSubcommand::List { search_word }) => list_resources(session, search_word.as_deref())?,
+            None => list_resources(session, None)?,
+            Some(subcommand) => subcommand.execute(session).await?,
+        }
+
+        Ok(ChatState::PromptUser {
+            skip_printing_tools: true,
+        })
+    }
+}
+
+#[deny(missing_docs)]
+#[derive(Clone, Debug, PartialEq, Subcommand)]
+pub enum ResourcesSubcommand {
+    /// List available resources and resource templates, optionally filtered by a search word
+    List { search_word: Option<String> },
+    /// Show the contents of a resource, given as <server>:<uri>
+    Read { reference: String },
+    /// Get notified when a resource, given as <server>:<uri>, is updated
+    Subscribe { reference: String },
+    /// Stop getting notified of updates to a resource, given as <server>:<uri>
+    Unsubscribe { reference: String },
+}
+
+impl ResourcesSubcommand {
+    async fn execute(self, session: &mut ChatSession) -> Result<(), ChatError> {
+        let (reference, subscribe) = match &self {
+            ResourcesSubcommand::List { .. } => unreachable!("List has already been handled at this point"),
+            ResourcesSubcommand::Read { reference } => (reference, None),
+            ResourcesSubcommand::Subscribe { reference } => (reference, Some(true)),
+            ResourcesSubcommand::Unsubscribe { reference } => (reference, Some(false)),
+        };
+        let result = match reference.split_once(':') {
+            Some((server_name, uri)) if !uri.is_empty() => match subscribe {
+                None => session
+                    .conversation
+                    .tool_manager
+                    .read_resource(server_name, uri)
+                    .await
+                    .map(|result| format_resource_contents(&result)),
+                Some(subscribe) => session
+                    .conversation
+                    .tool_manager
+                    .set_resource_subscription(server_name, uri, subscribe)
+                    .await
+                    .map(|supported| match (supported, subscribe) {
+                        (false, _) => format!("{server_name} does not support resource subscriptions\n"),
+                        (true, true) => format!("Subscribed to updates of {reference}\n"),
+                        (true, false) => format!("Unsubscribed from updates of {reference}\n"),
+                    }),
+            },
+            _ => Err(GetResourceError::InvalidReference(reference.clone())),
+        };
+
+        match result {
+            Ok(text) => {
+                queue!(session.stderr, style::Print("\n"), style::Print(text))?;
+            },
+            Err(e) => {
+                queue!(
+                    session.stderr,
+                    style::Print("\n"),
+                    style::SetForegroundColor(Color::Red),
+                    style::Print(format!("Failed to retrieve {reference}: {e}\n")),
+                    style::SetForegroundColor(Color::Reset),
+                )?;
+            },
+        }
+        execute!(session.stderr, style::Print("\n"))?;
+        Ok(())
+    }
+}
+
+fn list_resources(session: &mut ChatSession, search_word: Option<&str>) -> Result<(), ChatError> {
+    let terminal_width = session.terminal_width();
+    let resources =
+        session.conversation.tool_manager.resources.read().map_err(|e| {
+            ChatError::Custom(format!("Poison error encountered while retrieving resources: {}", e).into())
+        })?;
+    let search_word = search_word.unwrap_or_default();
+    let matches = |name: &str, uri: &str| name.contains(search_word) || uri.contains(search_word);
+    let mut servers = resources.iter().collect::<Vec<_>>();
+    servers.sort_by_key(|(server_name, _)| server_name.as_str());
+    let longest_name = servers
+        .iter()
+        .flat_map(|(_, r)| {
+            r.resources
+                .iter()
+                .map(|r| r.title.as_deref().unwrap_or(&r.name))
+                .chain(r.templates.iter().map(|t| t.title.as_deref().unwrap_or(&t.name)))
+        })
+        .map(UnicodeWidthStr::width)
+        .max()
+        .unwrap_or_default();
+    let uri_pos = (longest_name + 4).min(terminal_width / 3);
+
+    queue!(
+        session.stderr,
+        style::Print("\n"),
+        style::SetAttribute(Attribute::Bold),
+        style::Print("Usage: "),
+        style::SetAttribute(Attribute::Reset),
+        style::Print("You can include a resource in your next message by mentioning "),
+        style::SetAttribute(Attribute::Bold),
+        style::SetForegroundColor(Color::Green),
+        style::Print("'@<server>:<uri>'"),
+        style::SetForegroundColor(Color::Reset),
+        style::SetAttribute(Attribute::Reset),
+        style::Print("\n\n"),
+    )?;
+    if servers.is_empty() {
+        queue!(
+            session.stderr,
+            style::SetForegroundColor(Color::DarkGrey),
+            style::Print("No mcp server offers resources\n"),
+            style::SetForegroundColor(Color::Reset),
+        )?;
+        return Ok(());
+    }
+
+    for (i, (server_name, server_resources)) in servers.into_iter().enumerate() {
+        if i > 0 {
+            queue!(session.stderr, style::Print("\n"))?;
+        }
+        queue!(
+            session.stderr,
+            style::SetAttribute(Attribute::Bold),
+            style::Print(server_name),
+            style::Print(" (MCP):"),
+            style::SetAttribute(Attribute::Reset),
+            style::Print("\n"),
+        )?;
+        for resource in server_resources.resources.iter().filter(|r| matches(&r.name, &r.uri)) {
+            let name = resource.title.as_deref().unwrap_or(&resource.name);
+            queue_resource_entry(
+                &mut session.stderr,
+                uri_pos,
+                name,
+                &resource.uri,
+                resource.description.as_deref(),
+            )?;
+        }
+        let templates = server_resources
+            .templates
+            .iter()
+            .filter(|t| matches(&t.name, &t.uri_template))
+            .collect::<Vec<_>>();
+        if !templates.is_empty() {
+            queue!(
+                session.stderr,
+                style::SetForegroundColor(Color::DarkGrey),
+                style::Print("Templates (fill in the placeholders to mention a resource):\n"),
+                style::SetForegroundColor(Color::Reset),
+            )?;
+        }
+        for template in templates {
+            let name = template.title.as_deref().unwrap_or(&template.name);
+            queue_resource_entry(
+                &mut session.stderr,
+                uri_pos,
+                name,
+                &template.uri_template,
+                template.description.as_deref(),
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+fn queue_resource_entry(
+    output: &mut impl Write,
+    uri_pos: usize,
+    name: &str,
+    uri: &str,
+    description: Option<&str>,
+) -> Result<(), std::io::Error> {
+    let padding = uri_pos.saturating_sub(UnicodeWidthStr::width(name) + UnicodeWidthStr::width("- "));
+    queue!(
+        output,
+        style::Print("- "),
+        style::Print(name),
+        style::Print(" ".repeat(padding.max(1))),
+        style::SetForegroundColor(Color::Cyan),
+        style::Print(uri),
+        style::SetForegroundColor(Color::Reset),
+        style::Print("\n"),
+    )?;
+    if let Some(description) = description {
+        queue!(
+            output,
+            style::SetForegroundColor(Color::DarkGrey),
+            style::Print(format!("  {description}\n")),
+            style::SetForegroundColor(Color::Reset),
+        )?;
+    }
+    Ok(())
+}
+
+/// A mention of a resource in a message, written as `@<server>:<uri>`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResourceMention {
+    pub server_name: String,
+    pub uri: String,
+}
+
+/// Finds the resource mentions in `input` that refer to a server for which `is_server` holds.
+/// Mentions that do not refer to a known server are ignored since they are indistinguishable
+/// from prompts and other uses of `@`.
+pub fn find_resource_mentions(input: &str, is_server: impl Fn(&str) -> bool) -> Vec<ResourceMention> {
+    let mut mentions = Vec::<ResourceMention>::new();
+    for word in input.split_whitespace() {
+        let Some((server_name, uri)) = word.strip_prefix('@').and_then(|w| w.split_once(':')) else {
+            continue;
+        };
+        let uri = uri.trim_end_matches(MENTION_TRAILING_PUNCTUATION);
+        if uri.is_empty() || !is_server(server_name) {
+            continue;
+        }
+        let mention = ResourceMention {
+            server_name: server_name.to_string(),
+            uri: uri.to_string(),
+        };
+        if !mentions.contains(&mention) {
+            mentions.push(mention);
+        }
+    }
+    mentions
+}
+
+/// Reads the resources mentioned in `input` and appends their contents to it. Mentioned resources
+/// are also subscribed to where possible so that the user can be told when they change.
+pub async fn inline_resource_mentions(session: &mut ChatSession, input: String) -> Result<String, ChatError> {
+    let tool_manager = &session.conversation.tool_manager;
+    let mentions = find_resource_mentions(&input, |name| tool_manager.clients.contains_key(name));
+    if mentions.is_empty() {
+        return Ok(input);
+    }
+
+    let mut inlined = input;
+    for ResourceMention { server_name, uri } in mentions {
+        match session
+            .conversation
+            .tool_manager
+            .read_resource(&server_name, &uri)
+            .await
+        {
+            Ok(result) => {
+                inlined.push_str(&format!(
+                    "\n\n--- RESOURCE @{server_name}:{uri} BEGIN ---\n{}--- RESOURCE @{server_name}:{uri} END ---",
+                    truncate_resource_contents(format_resource_contents(&result), MAX_INLINED_RESOURCE_SIZE)
+                ));
+                if let Err(e) = session
+                    .conversation
+                    .tool_manager
+                    .set_resource_subscription(&server_name, &uri, true)
+                    .await
+                {
+                    tracing::warn!("Failed to subscribe to {server_name}:{uri}: {e}");
+                }
+            },
+            Err(e) => {
+                queue!(
+                    session.stderr,
+                    style::SetForegroundColor(Color::Yellow),
+                    style::Print(format!("Failed to read resource @{server_name}:{uri}: {e}\n")),
+                    style::SetForegroundColor(Color::Reset),
+                )?;
+            },
+        }
+    }
+    Ok(inlined)
+}
+
+/// Renders the contents of a resource as text. Binary contents are only described since they
+/// cannot be shown to the user or the model as is.
+pub fn format_resource_contents(result: &ResourceReadResult) -> String {
+    let mut formatted = String::new();
+    for contents in &result.contents {
+        match (&contents.text, &contents.blob) {
+            (Some(text), _) => {
+                formatted.push_str(text);
+                if !text.ends_with('\n') {
+                    formatted.push('\n');
+                }
+            },
+            (None, Some(blob)) => formatted.push_str(&format!(
+                "[{}: binary contents ({}) of {} base64 characters omitted]\n",
+                contents.uri,
+                contents.mime_type.as_deref().unwrap_or("unknown type"),
+                blob.len()
+            )),
+            (None, None) => formatted.push_str(&format!("[{}: no contents]\n", contents.uri)),
+        }
+    }
+    formatted
+}
+
+/// Truncates the formatted contents of a resource to `max_size` bytes, telling how much of them
+/// was left out.
+fn truncate_resource_contents(contents: String, max_size: usize) -> String {
+    if contents.len() <= max_size {
+        return contents;
+    }
+    let truncated = truncate_safe(&contents, max_size);
+    format!(
+        "{truncated}\n[truncated, {} of {} bytes shown]\n",
+        truncated.len(),
+        contents.len()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp_client::ResourceReadContents;
+
+    fn mention(server_name: &str, uri: &str) -> ResourceMention {
+        ResourceMention {
+            server_name: server_name.to_string(),
+            uri: uri.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_find_resource_mentions() {
+        let is_server = |name: &str| ["files", "db"].contains(&name);
+        assert_eq!(
+            find_resource_mentions(
+                "compare @files:file:///a.txt with @db:postgres://table/users, @files:file:///a.txt",
+                is_server
+            ),
+            vec![
+                mention("files", "file:///a.txt"),
+                mention("db", "postgres://table/users")
+            ]
+        );
+        // Prompts, unknown servers, and incomplete mentions are not resource mentions
+        assert!(find_resource_mentions("@files/prompt @unknown:file:///a @files: email@files:x", is_server).is_empty());
+    }
+
+    #[test]
+    fn test_format_resource_contents() {
+        let result = ResourceReadResult {
+            contents: vec![
+                ResourceReadContents {
+                    uri: "file:///a.txt".to_string(),
+                    mime_type: Some("text/plain".to_string()),
+                    text: Some("hello".to_string()),
+                    blob: None,
+                },
+                ResourceReadContents {
+                    uri: "file:///b.png".to_string(),
+                    mime_type: Some("image/png".to_string()),
+                    text: None,
+                    blob: Some("aGVsbG8=".to_string()),
+                },
+            ],
+        };
+        assert_eq!(
+            format_resource_contents(&result),
+            "hello\n[file:///b.png: binary contents (image/png) of 8 base64 characters omitted]\n"
+        );
+    }
+
+    #[test]
+    fn test_truncate_resource_contents() {
+        assert_eq!(truncate_resource_contents("hello\n".to_string(), 6), "hello\n");
+        assert_eq!(
+            truncate_resource_contents("hello world\n".to_string(), 5),
+            "hello\n[truncated, 5 of 12 bytes shown]\n"
+        );
+    }
+}
@@ -0,0 +1,291 @@
+use std::io::Write;
+
+use clap::{
+    Args,
+    Subcommand,
+};
+use crossterm::style::{
+    self,
+    Attribute,
+    Color,
+};
+use crossterm::{
+    execute,
+    queue,
+};
+use eyre::{
+    Result,
+    bail,
+};
+use time::macros::format_description;
+use time::{
+    Duration,
+    OffsetDateTime,
+    UtcOffset,
+};
+
+use crate::cli::chat::{
+    ChatError,
+    ChatSession,
+    ChatState,
+};
+use crate::database::SessionSummary;
+use crate::database::settings::Setting;
+use crate::os::Os;
+
+/// Number of characters of a session id shown in listings, which is enough to resume it.
+const SHORT_ID_LEN: usize = 8;
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Args)]
+#[command(color = clap::ColorChoice::Always,
+    before_long_help = color_print::cstr!{"Every conversation is saved as a session, which can be resumed later on, including from other directories.
+
+Sessions can be referred to by any unique prefix of their id, e.g. the one shown by <em>/sessions list</em>. To resume a session when starting a chat, use:
+  <em>q chat --list-sessions</em>                   <black!>List the saved sessions</black!>
+  <em>q chat --resume-id <<id>></em>                  <black!>Resume the session with the given id</black!>
+
+Set <em>q settings chat.sessionRetentionDays <<days>></em> to automatically delete sessions that were not used for that many days."
+})]
+pub struct SessionsArgs {
+    #[command(subcommand)]
+    subcommand: Option<SessionsSubcommand>,
+}
+
+impl SessionsArgs {
+    /// Whether the sessions are picked from with a fuzzy finder
+    pub fn opens_picker(&self) -> bool {
+        cfg!(unix) && self.subcommand.is_none()
+    }
+}
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Subcommand)]
+pub enum SessionsSubcommand {
+    /// List the saved sessions, most recent first
+    List,
+    /// Switch to a saved session
+    Resume {
+        /// Id, or unique prefix of the id, of the session
+        id: String,
+    },
+    /// Set the title of the current session
+    Rename {
+        /// The new title
+        #[arg(required = true, num_args = 1..)]
+        title: Vec<String>,
+    },
+    /// Delete a saved session
+    Delete {
+        /// Id, or unique prefix of the id, of the session
+        id: String,
+    },
+    /// Delete the sessions that were not used for a number of days
+    Prune {
+        /// Delete sessions last used more than this many days ago
+        #[arg(long, default_value_t = 30)]
+        older_than: u32,
+    },
+}
+
+impl SessionsArgs {
+    pub async fn execute(self, os: &mut Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        let result = match self.subcommand {
+            Some(SessionsSubcommand::List) => list_sessions(os, session),
+            Some(SessionsSubcommand::Resume { id }) => match resolve_session_id(os, &id) {
+                Ok(id) => resume_session(os, session, &id).await,
+                Err(err) => Err(err),
+            },
+            Some(SessionsSubcommand::Rename { title }) => rename_session(os, session, &title.join(" ")),
+            Some(SessionsSubcommand::Delete { id }) => delete_session(os, session, &id),
+            Some(SessionsSubcommand::Prune { older_than }) => prune_sessions(os, session, older_than),
+            None => pick_session(os, session).await,
+        };
+
+        if let Err(err) = result {
+            execute!(
+                session.stderr,
+                style::SetForegroundColor(Color::Red),
+                style::Print(format!("\n{err}\n\n")),
+                style::SetAttribute(Attribute::Reset)
+            )?;
+        }
+
+        Ok(ChatState::PromptUser {
+            skip_printing_tools: true,
+        })
+    }
+}
+
+fn list_sessions(os: &Os, session: &mut ChatSession) -> Result<()> {
+    let sessions = os.database.list_sessions()?;
+    let current = session.conversation.conversation_id().to_string();
+    queue!(session.stderr, style::Print("\n"))?;
+    print_sessions(&mut session.stderr, &sessions, Some(&current))?;
+    execute!(session.stderr, style::Print("\n"))?;
+    Ok(())
+}
+
+async fn resume_session(os: &mut Os, session: &mut ChatSession, id: &str) -> Result<()> {
+    if id == session.conversation.conversation_id() {
+        bail!("Session {} is the current session", short_id(id));
+    }
+    let Some(mut conversation) = os.database.get_session(id)? else {
+        bail!("No session with id {id}");
+    };
+
+    conversation.reload_serialized_state(os).await;
+    conversation.tool_manager = std::mem::take(&mut session.conversation.tool_manager);
+    conversation.update_state(true).await;
+    conversation.enforce_tool_use_history_invariants();
+    let title = conversation.title();
+    session.conversation = conversation;
+
+    execute!(
+        session.stderr,
+        style::SetForegroundColor(Color::Green),
+        style::Print(format!("\n✔ Resumed session {}: ", short_id(id))),
+        style::SetForegroundColor(Color::Reset),
+        style::Print(format!("{title}\n\n")),
+    )?;
+    Ok(())
+}
+
+fn rename_session(os: &mut Os, session: &mut ChatSession, title: &str) -> Result<()> {
+    let id = session.conversation.conversation_id().to_string();
+    if !os.database.rename_session(&id, title)? {
+        bail!("The current session has not been saved yet, send a message first");
+    }
+    execute!(
+        session.stderr,
+        style::SetForegroundColor(Color::Green),
+        style::Print(format!("\n✔ Renamed session {} to {title}\n\n", short_id(&id))),
+        style::SetForegroundColor(Color::Reset),
+    )?;
+    Ok(())
+}
+
+fn delete_session(os: &mut Os, session: &mut ChatSession, id: &str) -> Result<()> {
+    let id = resolve_session_id(os, id)?;
+    if id == session.conversation.conversation_id() {
+        bail!("Session {} is the current session and cannot be deleted", short_id(&id));
+    }
+    os.database.delete_session(&id)?;
+    execute!(
+        session.stderr,
+        style::SetForegroundColor(Color::Green),
+        style::Print(format!("\n✔ Deleted session {}\n\n", short_id(&id))),
+        style::SetForegroundColor(Color::Reset),
+    )?;
+    Ok(())
+}
+
+fn prune_sessions(os: &mut Os, session: &mut ChatSession, older_than: u32) -> Result<()> {
+    let deleted = os
+        .database
+        .prune_sessions(OffsetDateTime::now_utc() - Duration::days(older_than.into()))?;
+    execute!(
+        session.stderr,
+        style::SetForegroundColor(Color::Green),
+        style::Print(format!(
+            "\n✔ Deleted {deleted} session(s) not used in the last {older_than} day(s)\n\n"
+        )),
+        style::SetForegroundColor(Color::Reset),
+    )?;
+    Ok(())
+}
+
+/// Lets the user pick the session to resume with a fuzzy finder
+#[cfg(unix)]
+async fn pick_session(os: &mut Os, session: &mut ChatSession) -> Result<()> {
+    let sessions = os.database.list_sessions()?;
+    if sessions.is_empty() {
+        return list_sessions(os, session);
+    }
+    match crate::cli::chat::skim_integration::select_session_with_skim(&sessions)? {
+        Some(id) => resume_session(os, session, &id).await,
+        None => Ok(()),
+    }
+}
+
+#[cfg(not(unix))]
+async fn pick_session(os: &mut Os, session: &mut ChatSession) -> Result<()> {
+    list_sessions(os, session)
+}
+
+/// Returns the id of the session whose id is, or starts with, `id`.
+pub fn resolve_session_id(os: &Os, id: &str) -> Result<String> {
+    let sessions = os.database.list_sessions()?;
+    if sessions.iter().any(|session| session.id == id) {
+        return Ok(id.to_string());
+    }
+    let mut matches = sessions.into_iter().filter(|session| session.id.starts_with(id));
+    match (matches.next(), matches.next()) {
+        (Some(session), None) => Ok(session.id),
+        (Some(_), Some(_)) => bail!("Session id {id} is ambiguous, use a longer prefix"),
+        (None, _) => bail!("No session with id {id}. Use --list-sessions or /sessions list to see saved sessions"),
+    }
+}
+
+/// Deletes the sessions older than the `chat.sessionRetentionDays` setting, if set.
+pub fn prune_expired_sessions(os: &mut Os) -> Result<usize> {
+    match os.database.settings.get_int(Setting::ChatSessionRetentionDays) {
+        Some(days) if days > 0 => Ok(os
+            .database
+            .prune_sessions(OffsetDateTime::now_utc() - Duration::days(days))?),
+        _ => Ok(0),
+    }
+}
+
+pub fn short_id(id: &str) -> &str {
+    id.get(..SHORT_ID_LEN).unwrap_or(id)
+}
+
+/// Formats a session as a single line, starting with its short id.
+pub fn format_session(session: &SessionSummary) -> String {
+    let offset = UtcOffset::current_local_offset().unwrap_or(UtcOffset::UTC);
+    let updated_at = session
+        .updated_at
+        .to_offset(offset)
+        .format(format_description!("[year]-[month]-[day] [hour]:[minute]"))
+        .unwrap_or_default();
+    let mut line = format!("{}  {updated_at}  {}", short_id(&session.id), session.title);
+    if let Some(cwd) = &session.cwd {
+        line.push_str(&format!("  ({cwd})"));
+    }
+    line
+}
+
+/// Prints `sessions`, marking the one with id `current`.
+pub fn print_sessions(output: &mut impl Write, sessions: &[SessionSummary], current: Option<&str>) -> Result<()> {
+    if sessions.is_empty() {
+        queue!(
+            output,
+            style::SetForegroundColor(Color::DarkGrey),
+            style::Print("No saved sessions.\n"),
+            style::SetForegroundColor(Color::Reset),
+        )?;
+        return Ok(());
+    }
+
+    for session in sessions {
+        let is_current = current == Some(session.id.as_str());
+        queue!(
+            output,
+            style::Print(if is_current { "* " } else { "  " }),
+            style::SetForegroundColor(if is_current { Color::Green } else { Color::Reset }),
+            style::Print(format_session(session)),
+            style::SetForegroundColor(Color::Reset),
+        )?;
+        if let Some(model) = &session.model {
+            queue!(
+                output,
+                style::SetForegroundColor(Color::DarkGrey),
+                style::Print(format!("  [{model}]")),
+                style::SetForegroundColor(Color::Reset),
+            )?;
+        }
+        queue!(output, style::Print("\n"))?;
+    }
+    Ok(())
+}
@@ -8,6 +8,7 @@ use clap::{
 use crossterm::style::{
     Attribute,
     Color,
+    Stylize,
 };
 use crossterm::{
     queue,
@@ -17,12 +18,18 @@ use crossterm::{
 use crate::api_client::model::Tool as FigTool;
 use crate::cli::chat::consts::DUMMY_TOOL_NAME;
 use crate::cli::chat::tools::ToolOrigin;
+use crate::cli::chat::tools::permissions::{
+    RuleEffect,
+    is_plain_tool_name,
+    join_rule_words,
+};
 use crate::cli::chat::{
     ChatError,
     ChatSession,
     ChatState,
     TRUST_ALL_TEXT,
 };
+use crate::os::Os;
 
 #[deny(missing_docs)]
 #[derive(Debug, PartialEq, Args)]
@@ -32,9 +39,9 @@ pub struct ToolsArgs {
 }
 
 impl ToolsArgs {
-    pub async fn execute(self, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+    pub async fn execute(self, os: &Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
         if let Some(subcommand) = self.subcommand {
-            return subcommand.execute(session).await;
+            return subcommand.execute(os, session).await;
         }
 
         // No subcommand - print the current tools and their permissions.
@@ -82,22 +89,17 @@ impl ToolsArgs {
                 FigTool::ToolSpecification(spec) => &spec.name,
             });
 
-            let to_display = sorted_tools
-                .iter()
-                .fold(String::new(), |mut acc, FigTool::ToolSpecification(spec)| {
-                    let width = longest - spec.name.len() + 4;
-                    acc.push_str(
-                        format!(
-                            "- {}{:>width$}{}\n",
-                            spec.name,
-                            "",
-                            session.tool_permissions.display_label(&spec.name),
-                            width = width
-                        )
-                        .as_str(),
-                    );
-                    acc
-                });
+            let mut to_display = String::new();
+            for FigTool::ToolSpecification(spec) in sorted_tools {
+                // Rules that inspect arguments are only reflected in the permission rules below
+                let label = match session.tool_permission_rule(os, &spec.name, None) {
+                    Some((RuleEffect::Deny, _)) => format!("  {}", "denied".dark_red().bold()),
+                    Some((RuleEffect::Allow, _)) => format!("  {}", "trusted".dark_green().bold()),
+                    None => session.tool_permissions.display_label(&spec.name),
+                };
+                let width = longest - spec.name.len() + 4;
+                to_display.push_str(&format!("- {}{:>width$}{}\n", spec.name, "", label, width = width));
+            }
 
             let _ = queue!(
                 session.stderr,
@@ -109,6 +111,48 @@ impl ToolsArgs {
             );
         }
 
+        let mut rule_sets = vec![("session".to_string(), &session.tool_permissions.rules)];
+        if let Some(context_manager) = &session.conversation.context_manager {
+            rule_sets.push(("global".to_string(), &context_manager.global_config.tool_permissions));
+            rule_sets.push((
+                "workspace".to_string(),
+                &context_manager.workspace_config.tool_permissions,
+            ));
+            rule_sets.push((
+                format!("profile {}", context_manager.current_profile),
+                &context_manager.profile_config.tool_permissions,
+            ));
+        }
+        if rule_sets.iter().any(|(_, rules)| !rules.is_empty()) {
+            queue!(
+                session.stderr,
+                style::SetAttribute(Attribute::Bold),
+                style::Print("Permission rules"),
+                style::SetAttribute(Attribute::Reset),
+                style::Print("\n"),
+                style::Print("▔".repeat(terminal_width)),
+            )?;
+            for (source, rules) in rule_sets {
+                for (effect, rule) in rules.iter() {
+                    queue!(
+                        session.stderr,
+                        style::Print("- "),
+                        style::SetForegroundColor(match effect {
+                            RuleEffect::Allow => Color::Green,
+                            RuleEffect::Deny => Color::Red,
+                        }),
+                        style::Print(format!("{:<6}", effect.to_string())),
+                        style::SetForegroundColor(Color::Reset),
+                        style::Print(rule),
+                        style::SetForegroundColor(Color::DarkGrey),
+                        style::Print(format!("  ({source})\n")),
+                        style::SetForegroundColor(Color::Reset),
+                    )?;
+                }
+            }
+            queue!(session.stderr, style::Print("\n"))?;
+        }
+
         let loading = session.conversation.tool_manager.pending_clients().await;
         if !loading.is_empty() {
             queue!(
@@ -126,7 +170,7 @@ impl ToolsArgs {
 
         queue!(
             session.stderr,
-            style::Print("\nTrusted tools will run without confirmation."),
+            style::Print("\nTrusted tools will run without confirmation. Denied tools will not run."),
             style::SetForegroundColor(Color::DarkGrey),
             style::Print(format!("\n{}\n", "* Default settings")),
             style::Print("\n💡 Use "),
@@ -146,17 +190,37 @@ impl ToolsArgs {
 #[derive(Debug, PartialEq, Subcommand)]
 #[command(
     before_long_help = "By default, Amazon Q will ask for your permission to use certain tools. You can control which tools you
-trust so that no confirmation is required. These settings will last only for this session."
+trust so that no confirmation is required, and which tools are denied. These settings will last only for this session,
+unless --persist is given to save them to the current profile.
+
+Besides tool names, trust and deny accept rules:
+  fs_*                                  Glob patterns on tool names
+  @git/*                                All tools of the mcp server 'git'
+  re:git___(status|log)                 Regular expressions, prefixed with 're:'
+  fs_write(path=./src/**)               Conditions on arguments, paths are relative to the working directory
+  execute_bash(command=cargo test*)     Multiple conditions are separated with commas and must all hold
+Deny rules take precedence over trust rules."
 )]
 pub enum ToolsSubcommand {
     /// Show the input schema for all available tools
     Schema,
-    /// Trust a specific tool or tools for the session
+    /// Trust a specific tool or tools, or tool uses matching rules, for the session
     Trust {
         #[arg(required = true)]
         tool_names: Vec<String>,
+        /// Save to the current profile instead of only trusting for the session
+        #[arg(long)]
+        persist: bool,
+    },
+    /// Deny tool uses matching rules, for the session
+    Deny {
+        #[arg(required = true)]
+        rules: Vec<String>,
+        /// Save to the current profile instead of only denying for the session
+        #[arg(long)]
+        persist: bool,
     },
-    /// Revert a tool or tools to per-request confirmation
+    /// Revert a tool or tools to per-request confirmation, or remove rules
     Untrust {
         #[arg(required = true)]
         tool_names: Vec<String>,
@@ -165,18 +229,18 @@ pub enum ToolsSubcommand {
     TrustAll,
     /// Reset all tools to default permission levels
     Reset,
-    /// Reset a single tool to default permission level
+    /// Reset a single tool to default permission level, or remove a rule
     ResetSingle { tool_name: String },
 }
 
 impl ToolsSubcommand {
-    pub async fn execute(self, session: &mut ChatSession) -> Result<ChatState, ChatError> {
-        let existing_tools: HashSet<&String> = session
+    pub async fn execute(self, os: &Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        let existing_tools: HashSet<String> = session
             .conversation
             .tools
             .values()
             .flatten()
-            .map(|FigTool::ToolSpecification(spec)| &spec.name)
+            .map(|FigTool::ToolSpecification(spec)| spec.name.clone())
             .collect();
 
         match self {
@@ -185,7 +249,10 @@ impl ToolsSubcommand {
                     .map_err(|e| ChatError::Custom(format!("Error converting tool schema to string: {e}").into()))?;
                 queue!(session.stderr, style::Print(schema_json), style::Print("\n"))?;
             },
-            Self::Trust { tool_names } => {
+            Self::Trust { tool_names, persist } => {
+                let (tool_names, mut rules): (Vec<String>, Vec<String>) = join_rule_words(tool_names)
+                    .into_iter()
+                    .partition(|name| is_plain_tool_name(name));
                 let (valid_tools, invalid_tools): (Vec<String>, Vec<String>) = tool_names
                     .into_iter()
                     .partition(|tool_name| existing_tools.contains(tool_name));
@@ -229,9 +296,19 @@ impl ToolsSubcommand {
                         style::Print("\n"),
                         style::SetForegroundColor(Color::Reset),
                     )?;
+                    if persist {
+                        rules.extend(valid_tools);
+                    }
                 }
+                add_rules(os, session, RuleEffect::Allow, rules, persist).await?;
+            },
+            Self::Deny { rules, persist } => {
+                add_rules(os, session, RuleEffect::Deny, join_rule_words(rules), persist).await?;
             },
             Self::Untrust { tool_names } => {
+                let (tool_names, rules): (Vec<String>, Vec<String>) = join_rule_words(tool_names)
+                    .into_iter()
+                    .partition(|name| is_plain_tool_name(name));
                 let (valid_tools, invalid_tools): (Vec<String>, Vec<String>) = tool_names
                     .into_iter()
                     .partition(|tool_name| existing_tools.contains(tool_name));
@@ -264,6 +341,12 @@ impl ToolsSubcommand {
                         style::Print("set to per-request confirmation.\n"),
                         style::SetForegroundColor(Color::Reset),
                     )?;
+                    for tool_name in &valid_tools {
+                        remove_persisted_rule(os, session, tool_name).await?;
+                    }
+                }
+                for rule in rules {
+                    remove_rule(os, session, &rule).await?;
                 }
             },
             Self::TrustAll => {
@@ -286,8 +369,12 @@ impl ToolsSubcommand {
                     style::SetForegroundColor(Color::Reset),
                 )?;
             },
+            Self::ResetSingle { tool_name } if !is_plain_tool_name(&tool_name) => {
+                remove_rule(os, session, &tool_name).await?;
+            },
             Self::ResetSingle { tool_name } => {
-                if session.tool_permissions.has(&tool_name) || session.tool_permissions.trust_all {
+                let persisted = remove_persisted_rule(os, session, &tool_name).await?;
+                if session.tool_permissions.has(&tool_name) || session.tool_permissions.trust_all || persisted {
                     session.tool_permissions.reset_tool(&tool_name);
                     queue!(
                         session.stderr,
@@ -316,3 +403,78 @@ impl ToolsSubcommand {
         })
     }
 }
+
+/// Adds permission rules for the session, or to the current profile if `persist` is set.
+async fn add_rules(
+    os: &Os,
+    session: &mut ChatSession,
+    effect: RuleEffect,
+    rules: Vec<String>,
+    persist: bool,
+) -> Result<(), ChatError> {
+    for rule in rules {
+        let result = match (persist, session.conversation.context_manager.as_mut()) {
+            (true, Some(context_manager)) => context_manager
+                .add_tool_permission_rule(os, effect, &rule, false)
+                .await
+                .map(|_| format!(" in profile '{}'", context_manager.current_profile))
+                .map_err(|e| e.to_string()),
+            (true, None) => Err("context manager is not available.".to_string()),
+            (false, _) => match session.tool_permissions.rules.add(effect, &rule) {
+                Ok(true) => Ok(" for this session".to_string()),
+                Ok(false) => Err("already exists.".to_string()),
+                Err(e) => Err(e.to_string()),
+            },
+        };
+        match result {
+            Ok(scope) => queue!(
+                session.stderr,
+                style::SetForegroundColor(Color::Green),
+                style::Print(format!("Tool uses matching '{rule}' {}{scope}.\n", match effect {
+                    RuleEffect::Allow => "will run without confirmation",
+                    RuleEffect::Deny => "will be denied",
+                })),
+                style::SetForegroundColor(Color::Reset),
+            )?,
+            Err(err) => queue!(
+                session.stderr,
+                style::SetForegroundColor(Color::Red),
+                style::Print(format!("Cannot add rule '{rule}': {err}\n")),
+                style::SetForegroundColor(Color::Reset),
+            )?,
+        }
+    }
+    Ok(())
+}
+
+/// Removes a permission rule from the session and the current profile.
+async fn remove_rule(os: &Os, session: &mut ChatSession, rule: &str) -> Result<(), ChatError> {
+    let removed = session.tool_permissions.rules.remove(rule);
+    if remove_persisted_rule(os, session, rule).await? || removed {
+        queue!(
+            session.stderr,
+            style::SetForegroundColor(Color::Green),
+            style::Print(format!("Removed rule '{rule}'.\n")),
+            style::SetForegroundColor(Color::Reset),
+        )?;
+    } else {
+        queue!(
+            session.stderr,
+            style::SetForegroundColor(Color::Red),
+            style::Print(format!("Rule '{rule}' does not exist.\n")),
+            style::SetForegroundColor(Color::Reset),
+        )?;
+    }
+    Ok(())
+}
+
+/// Removes a permission rule from the current profile. Returns false if there was no such rule.
+async fn remove_persisted_rule(os: &Os, session: &mut ChatSession, rule: &str) -> Result<bool, ChatError> {
+    let Some(context_manager) = session.conversation.context_manager.as_mut() else {
+        return Ok(false);
+    };
+    context_manager
+        .remove_tool_permission_rule(os, rule, false)
+        .await
+        .map_err(|e| ChatError::Custom(format!("Failed to update profile: {e}").into()))
+}
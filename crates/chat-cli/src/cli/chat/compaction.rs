@@ -0,0 +1,353 @@
+//! Strategies for keeping the history of a conversation within the context window, used by
+//! `/compact`, automatic compaction, and when the context window overflows.
+
+use std::collections::VecDeque;
+
+use clap::ValueEnum;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use super::message::{
+    AssistantMessage,
+    ToolUseResult,
+    ToolUseResultBlock,
+    UserMessage,
+    UserMessageContent,
+};
+use super::token_counter::CharCounter;
+use super::util::truncate_safe;
+use crate::database::settings::{
+    Setting,
+    Settings,
+};
+
+/// Compaction frees the history down to at most this percentage of the context window.
+const COMPACTION_TARGET_PERCENT: usize = 50;
+
+/// Number of bytes kept from the start of a tool result that is truncated.
+const TRUNCATED_TOOL_RESULT_LEN: usize = 500;
+
+/// Appended to truncated tool results, which also keeps them from being truncated again.
+const TRUNCATED_TOOL_RESULT_NOTE: &str =
+    "[The rest of this tool result was removed to free up context space. Use the tool again if it is needed.]";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum CompactionStrategy {
+    /// Replace the history with a summary generated by the model
+    #[default]
+    Summarize,
+    /// Truncate the oldest tool results first, and summarize only if that does not free enough
+    /// space
+    TruncateToolResults,
+    /// Remove the oldest messages, and summarize only if that does not free enough space, e.g.
+    /// because the remaining messages are pinned
+    SlidingWindow,
+}
+
+/// How the history of a conversation is compacted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompactionConfig {
+    pub strategy: CompactionStrategy,
+    /// Percentage of the context window at which the history is compacted automatically before
+    /// sending a request, if set.
+    pub auto_compact_percent: Option<usize>,
+}
+
+impl CompactionConfig {
+    /// The config of the `chat.compactionStrategy` and `chat.autoCompactThreshold` settings.
+    pub fn from_settings(settings: &Settings) -> Self {
+        Self {
+            strategy: settings
+                .get_string(Setting::ChatCompactionStrategy)
+                .and_then(|strategy| CompactionStrategy::from_str(&strategy, true).ok())
+                .unwrap_or_default(),
+            auto_compact_percent: settings
+                .get_int(Setting::ChatAutoCompactThreshold)
+                .and_then(|percent| usize::try_from(percent).ok())
+                .filter(|percent| (1..=100).contains(percent)),
+        }
+    }
+}
+
+/// Returns the number of tokens compaction should free from a conversation of `used_tokens`, i.e.
+/// at least half of them.
+pub fn tokens_to_free(used_tokens: usize, context_window_tokens: usize) -> usize {
+    let target = (used_tokens / 2).min(context_window_tokens * COMPACTION_TARGET_PERCENT / 100);
+    used_tokens.saturating_sub(target)
+}
+
+/// Truncates the tool results of the history, oldest first, until `tokens` tokens are freed. The
+/// results of the last and of pinned exchanges are kept. Returns the number of truncated results
+/// and the number of freed tokens.
+pub fn truncate_tool_results(history: &mut VecDeque<(UserMessage, AssistantMessage)>, tokens: usize) -> (usize, usize) {
+    let mut truncated = 0;
+    let mut freed = 0;
+    let len = history.len();
+    for (user, _) in history.iter_mut().take(len.saturating_sub(1)) {
+        if user.pinned {
+            continue;
+        }
+        let results = match &mut user.content {
+            UserMessageContent::ToolUseResults { tool_use_results } => tool_use_results,
+            UserMessageContent::CancelledToolUses { tool_use_results, .. } => tool_use_results,
+            UserMessageContent::Prompt { .. } => continue,
+        };
+        for result in results {
+            if freed >= tokens {
+                return (truncated, freed);
+            }
+            let before = *std::slice::from_ref(result).token_count();
+            if truncate_tool_result(result) {
+                truncated += 1;
+                freed += before.saturating_sub(*std::slice::from_ref(result).token_count());
+            }
+        }
+    }
+    (truncated, freed)
+}
+
+/// Returns whether the result was truncated, i.e. it was long and not truncated before.
+fn truncate_tool_result(result: &mut ToolUseResult) -> bool {
+    let text = tool_result_text(result);
+    if text.len() <= TRUNCATED_TOOL_RESULT_LEN || text.ends_with(TRUNCATED_TOOL_RESULT_NOTE) {
+        return false;
+    }
+    let truncated = format!(
+        "{}\n{TRUNCATED_TOOL_RESULT_NOTE}",
+        truncate_safe(&text, TRUNCATED_TOOL_RESULT_LEN)
+    );
+    result.content = vec![ToolUseResultBlock::Text(truncated)];
+    true
+}
+
+/// The content of a tool result as text.
+pub fn tool_result_text(result: &ToolUseResult) -> String {
+    result
+        .content
+        .iter()
+        .map(|block| match block {
+            ToolUseResultBlock::Text(text) => text.clone(),
+            ToolUseResultBlock::Json(json) => json.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Removes the oldest exchanges of the history until `tokens` tokens are freed. Exchanges are
+/// removed together with the tool uses that followed their prompt, so that the history still
+/// starts with a prompt, and pinned exchanges are kept as plain text. Returns the number of
+/// removed exchanges and the number of freed tokens.
+pub fn remove_oldest_messages(
+    history: &mut VecDeque<(UserMessage, AssistantMessage)>,
+    tokens: usize,
+) -> (usize, usize) {
+    let mut pinned = Vec::new();
+    let mut removed = 0;
+    let mut freed = 0;
+    while freed < tokens {
+        // The exchanges up to the next prompt, never including the last exchange
+        let turn_len = history
+            .iter()
+            .skip(1)
+            .position(|(user, _)| matches!(user.content, UserMessageContent::Prompt { .. }))
+            .map_or(history.len(), |position| position + 1);
+        if turn_len >= history.len() {
+            break;
+        }
+        for (user, assistant) in history.drain(..turn_len) {
+            if user.pinned {
+                pinned.push(into_plain_exchange(user, assistant));
+            } else {
+                freed += *user.token_count() + *assistant.token_count();
+                removed += 1;
+            }
+        }
+    }
+
+    for exchange in pinned.into_iter().rev() {
+        history.push_front(exchange);
+    }
+    (removed, freed)
+}
+
+/// Converts an exchange to one without tool uses or results, which is valid anywhere in the
+/// history, by including them as text instead.
+pub fn into_plain_exchange(user: UserMessage, assistant: AssistantMessage) -> (UserMessage, AssistantMessage) {
+    let prompt = match &user.content {
+        UserMessageContent::Prompt { .. } => None,
+        UserMessageContent::CancelledToolUses {
+            prompt,
+            tool_use_results,
+        } => {
+            let mut text = tool_use_results.iter().map(tool_result_text).collect::<Vec<_>>();
+            text.extend(prompt.clone());
+            Some(text.join("\n"))
+        },
+        UserMessageContent::ToolUseResults { tool_use_results } => Some(
+            tool_use_results
+                .iter()
+                .map(tool_result_text)
+                .collect::<Vec<_>>()
+                .join("\n"),
+        ),
+    };
+    let user = match prompt {
+        Some(prompt) => UserMessage {
+            content: UserMessageContent::Prompt { prompt },
+            ..user
+        },
+        None => user,
+    };
+
+    let assistant = match assistant {
+        AssistantMessage::ToolUse {
+            message_id,
+            content,
+            tool_uses,
+        } => {
+            let mut content = content;
+            for tool_use in tool_uses {
+                content.push_str(&format!("\n[Used the tool {} with {}]", tool_use.name, tool_use.args));
+            }
+            AssistantMessage::new_response(message_id, content)
+        },
+        assistant @ AssistantMessage::Response { .. } => assistant,
+    };
+
+    (user, assistant)
+}
+
+/// Returns whether the history should be compacted before sending the next request.
+pub fn should_auto_compact(config: &CompactionConfig, used_tokens: usize, context_window_tokens: usize) -> bool {
+    config
+        .auto_compact_percent
+        .is_some_and(|percent| used_tokens >= context_window_tokens * percent / 100)
+}
+
+/// Returns the number of tokens used by `history`.
+pub fn history_tokens(history: &VecDeque<(UserMessage, AssistantMessage)>) -> usize {
+    history
+        .iter()
+        .map(|(user, assistant)| *user.token_count() + *assistant.token_count())
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api_client::model::ToolResultStatus;
+    use crate::cli::chat::message::AssistantToolUse;
+
+    fn tool_use(id: &str) -> AssistantMessage {
+        AssistantMessage::new_tool_use(None, "Reading the file".to_string(), vec![AssistantToolUse {
+            id: id.to_string(),
+            name: "fs_read".to_string(),
+            orig_name: "fs_read".to_string(),
+            args: serde_json::json!({ "path": "src/main.rs" }),
+            orig_args: serde_json::json!({ "path": "src/main.rs" }),
+        }])
+    }
+
+    fn tool_results(id: &str, len: usize) -> UserMessage {
+        UserMessage::new_tool_use_results(vec![ToolUseResult {
+            tool_use_id: id.to_string(),
+            content: vec![ToolUseResultBlock::Text("fn main() {}\n".repeat(len))],
+            status: ToolResultStatus::Success,
+        }])
+    }
+
+    fn response(content: &str) -> AssistantMessage {
+        AssistantMessage::new_response(None, content.to_string())
+    }
+
+    /// Two turns of a prompt, a tool use and a response, and a last prompt.
+    fn history() -> VecDeque<(UserMessage, AssistantMessage)> {
+        VecDeque::from([
+            (UserMessage::new_prompt("What does main do?".to_string()), tool_use("1")),
+            (tool_results("1", 1000), response("Nothing.")),
+            (UserMessage::new_prompt("And now?".to_string()), tool_use("2")),
+            (tool_results("2", 1000), response("Still nothing.")),
+            (
+                UserMessage::new_prompt("Thanks".to_string()),
+                response("You're welcome."),
+            ),
+        ])
+    }
+
+    #[test]
+    fn test_tokens_to_free() {
+        assert_eq!(tokens_to_free(180_000, 200_000), 90_000);
+        assert_eq!(tokens_to_free(40_000, 200_000), 20_000);
+        assert_eq!(tokens_to_free(0, 200_000), 0);
+    }
+
+    #[test]
+    fn test_truncate_tool_results() {
+        let mut history = history();
+        let before = history_tokens(&history);
+
+        // The oldest result is enough
+        let (truncated, freed) = truncate_tool_results(&mut history, 1);
+        assert_eq!(truncated, 1);
+        assert_eq!(history_tokens(&history), before - freed);
+        let UserMessageContent::ToolUseResults { tool_use_results } = &history[1].0.content else {
+            panic!("tool results were replaced");
+        };
+        assert!(tool_result_text(&tool_use_results[0]).ends_with(TRUNCATED_TOOL_RESULT_NOTE));
+
+        // Truncated and pinned results are skipped
+        history[3].0.pinned = true;
+        assert_eq!(truncate_tool_results(&mut history, usize::MAX).0, 0);
+        history[3].0.pinned = false;
+        assert_eq!(truncate_tool_results(&mut history, usize::MAX).0, 1);
+    }
+
+    #[test]
+    fn test_remove_oldest_messages() {
+        let mut history = history();
+        let (removed, _) = remove_oldest_messages(&mut history, 1);
+        assert_eq!(removed, 2);
+        assert_eq!(history.len(), 3);
+        assert_eq!(history[0].0.prompt(), Some("And now?"));
+
+        // The last exchange is always kept
+        let (removed, _) = remove_oldest_messages(&mut history, usize::MAX);
+        assert_eq!(removed, 2);
+        assert_eq!(history.len(), 1);
+        assert_eq!(remove_oldest_messages(&mut history, usize::MAX), (0, 0));
+    }
+
+    #[test]
+    fn test_remove_oldest_messages_keeps_pinned() {
+        let mut history = history();
+        history[1].0.pinned = true;
+        let (removed, _) = remove_oldest_messages(&mut history, usize::MAX);
+        assert_eq!(removed, 3);
+        assert_eq!(history.len(), 2);
+
+        // The pinned tool results are now a prompt, and the response has no tool uses
+        assert!(history[0].0.pinned);
+        assert!(
+            history[0]
+                .0
+                .prompt()
+                .is_some_and(|prompt| prompt.contains("fn main() {}"))
+        );
+        assert!(history[0].1.tool_uses().is_none());
+        assert_eq!(history[1].0.prompt(), Some("Thanks"));
+    }
+
+    #[test]
+    fn test_should_auto_compact() {
+        let config = CompactionConfig {
+            strategy: CompactionStrategy::Summarize,
+            auto_compact_percent: Some(80),
+        };
+        assert!(!should_auto_compact(&config, 159_999, 200_000));
+        assert!(should_auto_compact(&config, 160_000, 200_000));
+        assert!(!should_auto_compact(&CompactionConfig::default(), 200_000, 200_000));
+    }
+}
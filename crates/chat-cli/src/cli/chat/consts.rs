@@ -1,5 +1,3 @@
-use super::token_counter::TokenCounter;
-
 // These limits are the internal undocumented values from the service for each item
 
 pub const MAX_CURRENT_WORKING_DIRECTORY_LEN: usize = 256;
@@ -18,7 +16,9 @@ pub const CONTEXT_WINDOW_SIZE: usize = 200_000;
 
 pub const CONTEXT_FILES_MAX_SIZE: usize = 150_000;
 
-pub const MAX_CHARS: usize = TokenCounter::token_to_chars(CONTEXT_WINDOW_SIZE); // Character-based warning threshold
+/// Percentage of the context window used at which users are warned to compact the conversation,
+/// leaving room for the next responses before the window overflows.
+pub const CONTEXT_WINDOW_WARNING_PERCENT: usize = 90;
 
 pub const DUMMY_TOOL_NAME: &str = "dummy";
 
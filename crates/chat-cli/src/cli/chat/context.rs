@@ -1,9 +1,16 @@
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::{
+    HashMap,
+    HashSet,
+};
 use std::io::Write;
 use std::path::{
     Path,
     PathBuf,
 };
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Duration;
 
 use eyre::{
     Result,
@@ -11,24 +18,65 @@ use eyre::{
 };
 use glob::glob;
 use regex::Regex;
+use semantic_search_client::SearchOptions;
 use serde::{
     Deserialize,
     Serialize,
 };
-use tracing::debug;
+use tokio::io::AsyncWriteExt;
+use tracing::{
+    debug,
+    warn,
+};
 
 use super::consts::CONTEXT_FILES_MAX_SIZE;
-use super::util::drop_matched_context_files;
+use super::token_counter::TokenCounter;
+use super::util::truncate_safe;
 use crate::cli::chat::ChatError;
 use crate::cli::chat::cli::hooks::{
     Hook,
     HookExecutor,
+    HookTrigger,
+    shell_command,
+};
+use crate::cli::chat::tools::execute::sandbox::SandboxConfig;
+use crate::cli::chat::tools::permissions::{
+    RuleEffect,
+    ToolPermissionRules,
+};
+use crate::cli::chat::workspace_config::{
+    ConfigScope,
+    load_workspace_config,
 };
+use crate::database::settings::Setting;
 use crate::os::Os;
 use crate::util::directories;
+use crate::util::knowledge_store::KnowledgeStore;
 
 pub const AMAZONQ_FILENAME: &str = "AmazonQ.md";
 
+/// Prefix of the rules whose context is the output of a command, e.g. `cmd:git diff --stat`.
+pub const COMMAND_RULE_PREFIX: &str = "cmd:";
+/// Prefix of the rules whose context is the content of a URL.
+pub const URL_RULE_PREFIX: &str = "url:";
+/// Prefix of the rules whose context is the result of a knowledge base query.
+pub const KNOWLEDGE_RULE_PREFIX: &str = "knowledge:";
+
+/// Max time the command of a context rule can run.
+const CONTEXT_COMMAND_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Max time to fetch the URL of a context rule.
+const URL_FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Number of knowledge base results included by a knowledge rule.
+const KNOWLEDGE_RESULTS: usize = 5;
+
+/// An entry is only truncated to fit in the size limit if at least this many tokens are left,
+/// otherwise it is dropped.
+const MIN_TRUNCATED_TOKENS: usize = 200;
+
+const TRUNCATION_NOTICE: &str = "\n[truncated to fit in the context]";
+
 /// Configuration for context files, containing paths to include in the context.
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 #[serde(default)]
@@ -38,6 +86,83 @@ pub struct ContextConfig {
 
     /// Map of Hook Name to [`Hook`]. The hook name serves as the hook's ID.
     pub hooks: HashMap<String, Hook>,
+
+    /// Rules deciding which tool uses are trusted or denied. See [`ToolPermissionRules`].
+    #[serde(skip_serializing_if = "ToolPermissionRules::is_empty")]
+    pub tool_permissions: ToolPermissionRules,
+    /// Sandbox that shell commands run in. A profile's sandbox takes precedence over the global
+    /// one, and the sandbox of the workspace can only make them stricter, see
+    /// [SandboxConfig::restricted_by].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sandbox: Option<SandboxConfig>,
+    /// Priority of the paths, 0 unless set. When the context exceeds the size limit, entries of
+    /// the paths with the lowest priority are dropped first.
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub priorities: HashMap<String, i32>,
+}
+
+/// The source a context rule reads from, given by the prefix of the rule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContextSource {
+    /// A file, directory or glob pattern
+    Path,
+    /// A URL, which is fetched once per session
+    Url,
+    /// The output of a shell command
+    Command,
+    /// The results of a knowledge base query
+    Knowledge,
+}
+
+impl ContextSource {
+    /// Splits a context rule into its source and the path, URL, command or query of the source.
+    pub fn parse(rule: &str) -> (Self, &str) {
+        if let Some(command) = rule.strip_prefix(COMMAND_RULE_PREFIX) {
+            (Self::Command, command.trim())
+        } else if let Some(url) = rule.strip_prefix(URL_RULE_PREFIX) {
+            (Self::Url, url.trim())
+        } else if rule.starts_with("http://") || rule.starts_with("https://") {
+            (Self::Url, rule)
+        } else if let Some(query) = rule.strip_prefix(KNOWLEDGE_RULE_PREFIX) {
+            (Self::Knowledge, query.trim())
+        } else {
+            (Self::Path, rule)
+        }
+    }
+}
+
+/// Content produced by a context rule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContextEntry {
+    /// Path of the file, or the URL, command or query the content comes from.
+    pub name: String,
+    pub content: String,
+    /// The rule that produced the entry.
+    pub rule: String,
+    /// The configuration of the rule.
+    pub scope: ConfigScope,
+    pub priority: i32,
+    pub status: ContextEntryStatus,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContextEntryStatus {
+    Included,
+    /// The start of the content is included, which had `original_tokens` in full.
+    Truncated {
+        original_tokens: usize,
+    },
+    /// Dropped since the context exceeds the size limit.
+    Dropped,
+}
+
+/// The context produced by the rules of the global, workspace and profile configurations.
+#[derive(Debug, Clone, Default)]
+pub struct CollectedContext {
+    /// The entries, in order of the rules that produced them.
+    pub entries: Vec<ContextEntry>,
+    /// The rules that failed to produce context, with the error.
+    pub failed_rules: Vec<(String, String)>,
 }
 
 /// Manager for context files and profiles.
@@ -48,6 +173,11 @@ pub struct ContextManager {
     /// Global context configuration that applies to all profiles.
     pub global_config: ContextConfig,
 
+    /// Context configuration of the current directory, `.amazonq/config.json`. It is not
+    /// modified by the commands, which only edit the global and profile configurations.
+    #[serde(default)]
+    pub workspace_config: ContextConfig,
+
     /// Name of the current active profile.
     pub current_profile: String,
 
@@ -56,6 +186,27 @@ pub struct ContextManager {
 
     #[serde(skip)]
     pub hook_executor: HookExecutor,
+
+    /// Contents of the URLs of the rules, or the error fetching them, since URLs are only fetched
+    /// once per session.
+    #[serde(skip)]
+    url_contents: Arc<tokio::sync::Mutex<HashMap<String, Result<String, String>>>>,
+
+    /// Outputs of the `cmd:` and `knowledge:` rules, or the error producing them, by rule. They
+    /// are produced once per turn rather than for every request, see [Self::clear_rule_outputs].
+    #[serde(skip)]
+    rule_outputs: Arc<tokio::sync::Mutex<HashMap<String, Result<String, String>>>>,
+
+    /// Whether the user allowed the commands of the workspace configuration to run, by command.
+    /// The workspace configuration is checked in to the repository, so its hooks and `cmd:` rules
+    /// only run once the user allowed them, see [Self::unreviewed_workspace_commands].
+    #[serde(skip)]
+    workspace_command_approvals: HashMap<String, bool>,
+
+    /// Whether the user allowed the `tool_permissions.allow` rules of the workspace configuration,
+    /// by rule. They are reviewed along with the commands, while deny rules always apply.
+    #[serde(skip)]
+    workspace_allow_rule_approvals: HashMap<String, bool>,
 }
 
 impl ContextManager {
@@ -64,7 +215,8 @@ impl ContextManager {
     /// This will:
     /// 1. Create the necessary directories if they don't exist
     /// 2. Load the global configuration
-    /// 3. Load the default profile configuration
+    /// 3. Load the workspace configuration of the current directory
+    /// 4. Load the default profile configuration
     ///
     /// # Arguments
     /// * `os` - The context to use
@@ -81,15 +233,21 @@ impl ContextManager {
         os.fs.create_dir_all(&profiles_dir).await?;
 
         let global_config = load_global_config(os).await?;
+        let workspace_config = load_workspace_context_config(os).await;
         let current_profile = "default".to_string();
         let profile_config = load_profile_config(os, &current_profile).await?;
 
         Ok(Self {
             max_context_files_size,
             global_config,
+            workspace_config,
             current_profile,
             profile_config,
             hook_executor: HookExecutor::new(),
+            url_contents: Arc::default(),
+            rule_outputs: Arc::default(),
+            workspace_command_approvals: HashMap::new(),
+            workspace_allow_rule_approvals: HashMap::new(),
         })
     }
 
@@ -122,9 +280,10 @@ impl ContextManager {
         Ok(())
     }
 
-    /// Reloads the global and profile config from disk.
+    /// Reloads the global, workspace and profile config from disk.
     pub async fn reload_config(&mut self, os: &Os) -> Result<()> {
         self.global_config = load_global_config(os).await?;
+        self.workspace_config = load_workspace_context_config(os).await;
         self.profile_config = load_profile_config(os, &self.current_profile).await?;
         Ok(())
     }
@@ -151,7 +310,11 @@ impl ContextManager {
             for path in &paths {
                 // We're using a temporary context_files vector just for validation
                 // Pass is_validation=true to ensure we error if glob patterns don't match any files
-                match process_path(os, path, &mut context_files, true).await {
+                let result = match ContextSource::parse(path) {
+                    (ContextSource::Path, path) => process_path(os, path, &mut context_files, true).await,
+                    (source, value) => validate_source(os, source, value),
+                };
+                match result {
                     Ok(_) => {}, // Path is valid
                     Err(e) => return Err(eyre!("Invalid path '{}': {}. Use --force to add anyway.", path, e)),
                 }
@@ -196,6 +359,7 @@ impl ContextManager {
         for path in paths {
             let original_len = config.paths.len();
             config.paths.retain(|p| p != &path);
+            config.priorities.remove(&path);
 
             if config.paths.len() < original_len {
                 removed_any = true;
@@ -212,6 +376,34 @@ impl ContextManager {
         Ok(())
     }
 
+    /// Set the priority of paths of the context configuration.
+    ///
+    /// # Arguments
+    /// * `paths` - List of paths, which must be in the configuration
+    /// * `priority` - The priority, where 0 is the default and higher priorities are kept first
+    ///   when the context exceeds the size limit
+    /// * `global` - If true, set in the global configuration; otherwise, in the current profile
+    ///   configuration
+    ///
+    /// # Returns
+    /// A Result indicating success or an error
+    pub async fn set_priority(&mut self, os: &Os, paths: &[String], priority: i32, global: bool) -> Result<()> {
+        let config = self.get_config_mut(global);
+        if let Some(path) = paths.iter().find(|path| !config.paths.contains(path)) {
+            return Err(eyre!("Rule '{}' does not exist.", path));
+        }
+
+        for path in paths {
+            if priority == 0 {
+                config.priorities.remove(path);
+            } else {
+                config.priorities.insert(path.clone(), priority);
+            }
+        }
+
+        self.save_config(os, global).await
+    }
+
     /// List all available profiles.
     ///
     /// # Returns
@@ -290,11 +482,9 @@ impl ContextManager {
     /// A Result indicating success or an error
     pub async fn clear(&mut self, os: &Os, global: bool) -> Result<()> {
         // Clear the appropriate config
-        if global {
-            self.global_config.paths.clear();
-        } else {
-            self.profile_config.paths.clear();
-        }
+        let config = self.get_config_mut(global);
+        config.paths.clear();
+        config.priorities.clear();
 
         // Save the updated configuration
         self.save_config(os, global).await?;
@@ -438,62 +628,285 @@ impl ContextManager {
     ///
     /// This method:
     /// 1. Processes all paths in the global and profile configurations
-    /// 2. Expands glob patterns to include matching files
+    /// 2. Expands glob patterns to include matching files, and runs the commands, fetches the URLs
+    ///    and queries the knowledge base of the other rules
     /// 3. Reads the content of each file
     /// 4. Returns a vector of (filename, content) pairs
     ///
+    /// Rules that fail are skipped, see [Self::collect_context].
     ///
     /// # Returns
     /// A Result containing a vector of (filename, content) pairs or an error
     pub async fn get_context_files(&self, os: &Os) -> Result<Vec<(String, String)>> {
-        let mut context_files = Vec::new();
-
-        self.collect_context_files(os, &self.global_config.paths, &mut context_files)
-            .await?;
-        self.collect_context_files(os, &self.profile_config.paths, &mut context_files)
-            .await?;
+        let mut context_files = self
+            .collect_context(os)
+            .await
+            .entries
+            .into_iter()
+            .map(|entry| (entry.name, entry.content))
+            .collect::<Vec<_>>();
 
         context_files.sort_by(|a, b| a.0.cmp(&b.0));
-        context_files.dedup_by(|a, b| a.0 == b.0);
-
-        Ok(context_files)
-    }
 
-    pub async fn get_context_files_by_path(&self, os: &Os, path: &str) -> Result<Vec<(String, String)>> {
-        let mut context_files = Vec::new();
-        process_path(os, path, &mut context_files, true).await?;
         Ok(context_files)
     }
 
-    /// Collects context files and optionally drops files if the total size exceeds the limit.
+    /// Collects context files and drops or truncates entries if the total size exceeds the limit.
     /// Returns (files_to_use, dropped_files)
     pub async fn collect_context_files_with_limit(
         &self,
         os: &Os,
     ) -> Result<(Vec<(String, String)>, Vec<(String, String)>)> {
-        let mut files = self.get_context_files(os).await?;
+        let (files, dropped_files) = self
+            .collect_context_with_limit(os)
+            .await
+            .entries
+            .into_iter()
+            .map(|entry| (entry.status, (entry.name, entry.content)))
+            .partition::<Vec<_>, _>(|(status, _)| *status != ContextEntryStatus::Dropped);
+
+        Ok((
+            files.into_iter().map(|(_, file)| file).collect(),
+            dropped_files.into_iter().map(|(_, file)| file).collect(),
+        ))
+    }
+
+    /// Collects the entries of the global, workspace and then the profile rules, in order of the
+    /// rules.
+    /// Files matched by several rules are only included once, with the highest priority of these
+    /// rules. Rules that fail are reported in [CollectedContext::failed_rules].
+    pub async fn collect_context(&self, os: &Os) -> CollectedContext {
+        let mut context = CollectedContext::default();
+        for (scope, config) in self.configs() {
+            for rule in &config.paths {
+                let priority = config.priorities.get(rule).copied().unwrap_or_default();
+                if let (ConfigScope::Workspace, (ContextSource::Command, command)) = (scope, ContextSource::parse(rule))
+                {
+                    if !self.is_workspace_command_allowed(command) {
+                        context
+                            .failed_rules
+                            .push((rule.clone(), "Commands of the workspace were not allowed".to_string()));
+                        continue;
+                    }
+                }
+                let mut contents = Vec::new();
+                if let Err(err) = self.process_rule(os, rule, &mut contents).await {
+                    warn!(?rule, ?err, "Failed to collect context");
+                    context.failed_rules.push((rule.clone(), err.to_string()));
+                    continue;
+                }
 
-        let dropped_files = drop_matched_context_files(&mut files, self.max_context_files_size).unwrap_or_default();
+                for (name, content) in contents {
+                    match context.entries.iter_mut().find(|entry| entry.name == name) {
+                        Some(entry) => entry.priority = entry.priority.max(priority),
+                        None => context.entries.push(ContextEntry {
+                            name,
+                            content,
+                            rule: rule.clone(),
+                            scope,
+                            priority,
+                            status: ContextEntryStatus::Included,
+                        }),
+                    }
+                }
+            }
+        }
+        context
+    }
 
-        // remove dropped files from files
-        files.retain(|file| !dropped_files.iter().any(|dropped| dropped.0 == file.0));
+    /// Like [Self::collect_context], with entries dropped or truncated so that the context fits in
+    /// the size limit, see [apply_context_limit].
+    pub async fn collect_context_with_limit(&self, os: &Os) -> CollectedContext {
+        let mut context = self.collect_context(os).await;
+        apply_context_limit(&mut context.entries, self.max_context_files_size);
+        context
+    }
 
-        Ok((files, dropped_files))
+    /// The max size of the context files, in tokens.
+    pub fn max_context_files_size(&self) -> usize {
+        self.max_context_files_size
     }
 
-    async fn collect_context_files(
-        &self,
-        os: &Os,
-        paths: &[String],
-        context_files: &mut Vec<(String, String)>,
-    ) -> Result<()> {
-        for path in paths {
+    /// Adds the (name, content) pairs produced by `rule` to `context_files`.
+    async fn process_rule(&self, os: &Os, rule: &str, context_files: &mut Vec<(String, String)>) -> Result<()> {
+        match ContextSource::parse(rule) {
             // Use is_validation=false to handle non-matching globs gracefully
-            process_path(os, path, context_files, false).await?;
+            (ContextSource::Path, path) => process_path(os, path, context_files, false).await?,
+            (ContextSource::Url, url) => context_files.push((url.to_string(), self.url_content(os, url).await?)),
+            (ContextSource::Command, command) => {
+                let output = self.rule_output(rule, run_context_command(os, command)).await?;
+                context_files.push((format!("$ {command}"), output));
+            },
+            (ContextSource::Knowledge, query) => {
+                let output = self.rule_output(rule, query_knowledge(os, query)).await?;
+                context_files.push((format!("knowledge: {query}"), output));
+            },
         }
         Ok(())
     }
 
+    /// The output of `rule`, which is only produced by `produce` the first time it is used in the
+    /// turn.
+    async fn rule_output(&self, rule: &str, produce: impl Future<Output = Result<String>>) -> Result<String> {
+        let mut rule_outputs = self.rule_outputs.lock().await;
+        let output = match rule_outputs.get(rule) {
+            Some(output) => output.clone(),
+            None => {
+                let output = produce.await.map_err(|err| err.to_string());
+                rule_outputs.insert(rule.to_string(), output.clone());
+                output
+            },
+        };
+        output.map_err(|err| eyre!(err))
+    }
+
+    /// Forgets the outputs of the `cmd:` and `knowledge:` rules, so that they are produced again.
+    /// Called at the start of every turn.
+    pub fn clear_rule_outputs(&mut self) {
+        self.rule_outputs = Arc::default();
+    }
+
+    /// The content of `url`, which is only fetched the first time it is used in the session.
+    async fn url_content(&self, os: &Os, url: &str) -> Result<String> {
+        let mut url_contents = self.url_contents.lock().await;
+        let content = match url_contents.get(url) {
+            Some(content) => content.clone(),
+            None => {
+                let content = fetch_url(os, url).await.map_err(|err| err.to_string());
+                url_contents.insert(url.to_string(), content.clone());
+                content
+            },
+        };
+        content.map_err(|err| eyre!(err))
+    }
+
+    /// The sandbox that shell commands run in, if any, with the configuration it was taken from.
+    /// The workspace configuration is only reported when it made the sandbox stricter.
+    pub fn sandbox_config(&self) -> Option<(ConfigScope, SandboxConfig)> {
+        let user = [
+            (ConfigScope::Profile, &self.profile_config),
+            (ConfigScope::Global, &self.global_config),
+        ]
+        .into_iter()
+        .find_map(|(scope, config)| Some((scope, config.sandbox.as_ref()?)));
+        let sandbox =
+            SandboxConfig::restricted_by(user.map(|(_, config)| config), self.workspace_config.sandbox.as_ref())?;
+        match user {
+            Some((scope, config)) if *config == sandbox => Some((scope, sandbox)),
+            _ => Some((ConfigScope::Workspace, sandbox)),
+        }
+    }
+
+    /// The commands of the enabled hooks and the `cmd:` rules of the workspace configuration.
+    pub fn workspace_commands(&self) -> Vec<&str> {
+        let mut hooks = self
+            .workspace_config
+            .hooks
+            .iter()
+            .filter(|(_, hook)| !hook.disabled)
+            .collect::<Vec<_>>();
+        hooks.sort_by(|(a, _), (b, _)| a.cmp(b));
+        let hook_commands = hooks.into_iter().filter_map(|(_, hook)| hook.command.as_deref());
+        let rule_commands = self
+            .workspace_config
+            .paths
+            .iter()
+            .filter_map(|rule| match ContextSource::parse(rule) {
+                (ContextSource::Command, command) => Some(command),
+                _ => None,
+            });
+        let mut seen = HashSet::new();
+        hook_commands
+            .chain(rule_commands)
+            .filter(|command| seen.insert(*command))
+            .collect()
+    }
+
+    /// The commands of the workspace configuration the user has not been asked about yet.
+    pub fn unreviewed_workspace_commands(&self) -> Vec<&str> {
+        self.workspace_commands()
+            .into_iter()
+            .filter(|command| !self.workspace_command_approvals.contains_key(*command))
+            .collect()
+    }
+
+    /// The `tool_permissions.allow` rules of the workspace configuration the user has not been
+    /// asked about yet.
+    pub fn unreviewed_workspace_allow_rules(&self) -> Vec<&str> {
+        self.workspace_config
+            .tool_permissions
+            .allow
+            .iter()
+            .map(String::as_str)
+            .filter(|rule| !self.workspace_allow_rule_approvals.contains_key(*rule))
+            .collect()
+    }
+
+    /// Records whether the user allows the commands and the allow rules of the workspace
+    /// configuration they have not been asked about yet.
+    pub fn review_workspace_commands(&mut self, allowed: bool) {
+        let commands = self
+            .unreviewed_workspace_commands()
+            .into_iter()
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        for command in commands {
+            self.workspace_command_approvals.insert(command, allowed);
+        }
+        let rules = self
+            .unreviewed_workspace_allow_rules()
+            .into_iter()
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        for rule in rules {
+            self.workspace_allow_rule_approvals.insert(rule, allowed);
+        }
+    }
+
+    /// The tool permission rules of the global, workspace and profile configurations. Only the
+    /// allow rules of the workspace the user allowed are included.
+    pub fn tool_permission_rules(&self) -> Vec<Cow<'_, ToolPermissionRules>> {
+        self.configs()
+            .into_iter()
+            .map(|(scope, config)| match scope {
+                ConfigScope::Workspace => Cow::Owned(ToolPermissionRules {
+                    allow: config
+                        .tool_permissions
+                        .allow
+                        .iter()
+                        .filter(|rule| self.workspace_allow_rule_approvals.get(*rule) == Some(&true))
+                        .cloned()
+                        .collect(),
+                    deny: config.tool_permissions.deny.clone(),
+                }),
+                _ => Cow::Borrowed(&config.tool_permissions),
+            })
+            .collect()
+    }
+
+    /// Whether the user allowed `command` of the workspace configuration to run.
+    fn is_workspace_command_allowed(&self, command: &str) -> bool {
+        self.workspace_command_approvals.get(command).copied().unwrap_or(false)
+    }
+
+    /// Whether `hook` of the configuration of `scope` may run.
+    fn is_hook_allowed(&self, scope: ConfigScope, hook: &Hook) -> bool {
+        scope != ConfigScope::Workspace
+            || hook
+                .command
+                .as_deref()
+                .is_none_or(|command| self.is_workspace_command_allowed(command))
+    }
+
+    /// The global, workspace and profile configurations, in increasing order of precedence.
+    pub fn configs(&self) -> [(ConfigScope, &ContextConfig); 3] {
+        [
+            (ConfigScope::Global, &self.global_config),
+            (ConfigScope::Workspace, &self.workspace_config),
+            (ConfigScope::Profile, &self.profile_config),
+        ]
+    }
+
     fn get_config_mut(&mut self, global: bool) -> &mut ContextConfig {
         if global {
             &mut self.global_config
@@ -567,30 +980,87 @@ impl ContextManager {
         self.save_config(os, global).await
     }
 
-    /// Run all the currently enabled hooks from both the global and profile contexts.
+    /// Add a tool permission rule to the context config.
+    /// # Arguments
+    /// * `effect` - Whether tool uses matching the rule are allowed or denied
+    /// * `rule` - The rule, see [`ToolRule`](crate::cli::chat::tools::permissions::ToolRule)
+    /// * `global` - If true, add to the global config. If false, add to the current profile config
+    pub async fn add_tool_permission_rule(
+        &mut self,
+        os: &Os,
+        effect: RuleEffect,
+        rule: &str,
+        global: bool,
+    ) -> Result<()> {
+        let config = self.get_config_mut(global);
+
+        if !config.tool_permissions.add(effect, rule)? {
+            return Err(eyre!("already exists."));
+        }
+
+        self.save_config(os, global).await
+    }
+
+    /// Remove a tool permission rule from the context config, regardless of its effect.
+    /// Returns false if there was no such rule.
+    pub async fn remove_tool_permission_rule(&mut self, os: &Os, rule: &str, global: bool) -> Result<bool> {
+        let config = self.get_config_mut(global);
+
+        if !config.tool_permissions.remove(rule) {
+            return Ok(false);
+        }
+
+        self.save_config(os, global).await?;
+        Ok(true)
+    }
+
+    /// Run all the currently enabled context hooks from the global, workspace and profile contexts.
     /// Skipped hooks (disabled) will not appear in the output.
     /// # Arguments
     /// * `updates` - output stream to write hook run status to if Some, else do nothing if None
     /// # Returns
     /// A vector containing pairs of a [`Hook`] definition and its execution output
     pub async fn run_hooks(&mut self, output: &mut impl Write) -> Result<Vec<(Hook, String)>, ChatError> {
-        let mut hooks: Vec<&Hook> = Vec::new();
+        let mut hooks: Vec<Hook> = Vec::new();
 
         // Set internal hook states
-        let configs = [
-            (&mut self.global_config.hooks, true),
-            (&mut self.profile_config.hooks, false),
-        ];
-
-        for (hook_list, is_global) in configs {
-            hooks.extend(hook_list.iter_mut().map(|(name, h)| {
-                h.name = name.clone();
-                h.is_global = is_global;
-                &*h
-            }));
+        for (scope, config) in self.configs() {
+            hooks.extend(
+                config
+                    .hooks
+                    .iter()
+                    .filter(|(_, h)| h.trigger.is_context() && self.is_hook_allowed(scope, h))
+                    .map(|(name, h)| Hook {
+                        name: name.clone(),
+                        is_global: scope == ConfigScope::Global,
+                        ..h.clone()
+                    }),
+            );
         }
 
-        self.hook_executor.run_hooks(hooks, output).await
+        self.hook_executor.run_hooks(hooks.iter().collect(), output).await
+    }
+
+    /// The enabled event hooks of `trigger` from the global, workspace and profile contexts, in
+    /// that order and then by name. Tool use hooks are only included if they match `tool_name`.
+    pub fn event_hooks(&self, trigger: HookTrigger, tool_name: Option<&str>) -> Vec<Hook> {
+        let mut hooks = Vec::new();
+        for (scope, config) in self.configs() {
+            let mut matching = config
+                .hooks
+                .iter()
+                .filter(|(_, h)| h.trigger == trigger && !h.disabled && self.is_hook_allowed(scope, h))
+                .filter(|(_, h)| tool_name.is_none_or(|tool_name| h.matches_tool(tool_name)))
+                .map(|(name, h)| Hook {
+                    name: name.clone(),
+                    is_global: scope == ConfigScope::Global,
+                    ..h.clone()
+                })
+                .collect::<Vec<_>>();
+            matching.sort_by(|a, b| a.name.cmp(&b.name));
+            hooks.extend(matching);
+        }
+        hooks
     }
 }
 
@@ -625,10 +1095,25 @@ async fn load_global_config(os: &Os) -> Result<ContextConfig> {
                 AMAZONQ_FILENAME.to_string(),
             ],
             hooks: HashMap::new(),
+            tool_permissions: ToolPermissionRules::default(),
+            sandbox: None,
+            priorities: HashMap::new(),
         })
     }
 }
 
+/// Load the context configuration of the workspace, which is empty if it fails to load so that
+/// a broken workspace configuration does not prevent chatting.
+async fn load_workspace_context_config(os: &Os) -> ContextConfig {
+    match load_workspace_config(os).await {
+        Ok(config) => config.context,
+        Err(err) => {
+            warn!(?err, "Failed to load the workspace configuration");
+            ContextConfig::default()
+        },
+    }
+}
+
 /// Load a profile's context configuration.
 ///
 /// If the profile configuration file doesn't exist, creates a default configuration.
@@ -694,20 +1179,24 @@ async fn process_path(
         // Expand glob pattern
         match glob(&full_path) {
             Ok(entries) => {
-                let mut found_any = false;
-
+                let mut matches = Vec::new();
                 for entry in entries {
                     match entry {
                         Ok(path) => {
                             if path.is_file() {
-                                add_file_to_context(os, &path, context_files).await?;
-                                found_any = true;
+                                matches.push(path);
                             }
                         },
                         Err(e) => return Err(eyre!("Glob error: {}", e)),
                     }
                 }
 
+                let matches = filter_gitignored(&glob_base(&full_path), matches).await;
+                let found_any = !matches.is_empty();
+                for path in matches {
+                    add_file_to_context(os, &path, context_files).await?;
+                }
+
                 if !found_any && is_validation {
                     // When validating paths (e.g., for /context add), error if no files match
                     return Err(eyre!("No files found matching glob pattern '{}'", full_path));
@@ -725,13 +1214,18 @@ async fn process_path(
                 add_file_to_context(os, path, context_files).await?;
             } else if path.is_dir() {
                 // For directories, add all files in the directory (non-recursive)
+                let mut files = Vec::new();
                 let mut read_dir = os.fs.read_dir(path).await?;
                 while let Some(entry) = read_dir.next_entry().await? {
                     let path = entry.path();
                     if path.is_file() {
-                        add_file_to_context(os, &path, context_files).await?;
+                        files.push(path);
                     }
                 }
+                files.sort();
+                for path in filter_gitignored(path, files).await {
+                    add_file_to_context(os, &path, context_files).await?;
+                }
             }
         } else if is_validation {
             // When validating paths (e.g., for /context add), error if the path doesn't exist
@@ -761,6 +1255,205 @@ async fn add_file_to_context(os: &Os, path: &Path, context_files: &mut Vec<(Stri
     Ok(())
 }
 
+/// The directory a glob pattern matches files in, i.e. its components before the first one with
+/// a wildcard.
+fn glob_base(pattern: &str) -> PathBuf {
+    Path::new(pattern)
+        .components()
+        .take_while(|component| !component.as_os_str().to_string_lossy().contains(['*', '?', '[']))
+        .collect()
+}
+
+/// Removes the `paths` ignored by git from the paths found in `base`, unless `base` is itself
+/// ignored, i.e. it was named explicitly. Paths outside of git repositories are kept.
+async fn filter_gitignored(base: &Path, paths: Vec<PathBuf>) -> Vec<PathBuf> {
+    if paths.is_empty() || !base.is_dir() {
+        return paths;
+    }
+
+    let mut input = Vec::new();
+    for path in std::iter::once(base).chain(paths.iter().map(PathBuf::as_path)) {
+        input.extend_from_slice(path.as_os_str().as_encoded_bytes());
+        input.push(0);
+    }
+    let child = tokio::process::Command::new("git")
+        .args(["check-ignore", "-z", "--stdin"])
+        .current_dir(base)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn();
+    let Ok(mut child) = child else {
+        return paths;
+    };
+    let Some(mut stdin) = child.stdin.take() else {
+        return paths;
+    };
+    let write_input = async move {
+        let _ = stdin.write_all(&input).await;
+    };
+    let (_, output) = futures::future::join(write_input, child.wait_with_output()).await;
+
+    // Exits with 1 when no path is ignored, and 128 outside of git repositories
+    let Ok(output) = output else {
+        return paths;
+    };
+    if output.status.code() != Some(0) {
+        return paths;
+    }
+    let ignored = output
+        .stdout
+        .split(|byte| *byte == 0)
+        .map(|path| PathBuf::from(String::from_utf8_lossy(path).as_ref()))
+        .collect::<HashSet<_>>();
+    if ignored.contains(base) {
+        return paths;
+    }
+    paths.into_iter().filter(|path| !ignored.contains(path)).collect()
+}
+
+/// Validates the URL, command or query of a rule that is not a path, without using it.
+fn validate_source(os: &Os, source: ContextSource, value: &str) -> Result<()> {
+    if value.is_empty() {
+        return Err(eyre!("The rule is empty"));
+    }
+    match source {
+        ContextSource::Url => {
+            let url = url::Url::parse(value)?;
+            if !matches!(url.scheme(), "http" | "https") {
+                return Err(eyre!("Only http and https URLs are supported"));
+            }
+        },
+        ContextSource::Knowledge
+            if !os
+                .database
+                .settings
+                .get_bool(Setting::EnabledKnowledge)
+                .unwrap_or(false) =>
+        {
+            return Err(eyre!("Knowledge is not enabled, see /knowledge"));
+        },
+        ContextSource::Path | ContextSource::Command | ContextSource::Knowledge => (),
+    }
+    Ok(())
+}
+
+/// The name of the local copy of `url` in [directories::chat_context_mirror_dir].
+fn mirror_file_name(url: &str) -> String {
+    let url = url.split_once("://").map_or(url, |(_, rest)| rest);
+    url.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '.' | '-') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Fetches `url`, keeping a local copy which is used when the URL cannot be fetched, e.g. when
+/// offline.
+async fn fetch_url(os: &Os, url: &str) -> Result<String> {
+    let mirror_dir = directories::chat_context_mirror_dir(os)?;
+    let mirror_path = mirror_dir.join(mirror_file_name(url));
+
+    let fetched = async {
+        let response = crate::request::new_client()?
+            .get(url)
+            .timeout(URL_FETCH_TIMEOUT)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok::<_, eyre::Report>(response.text().await?)
+    }
+    .await;
+
+    match fetched {
+        Ok(content) => {
+            let mirrored = async {
+                os.fs.create_dir_all(&mirror_dir).await?;
+                os.fs.write(&mirror_path, &content).await
+            };
+            if let Err(err) = mirrored.await {
+                warn!(?url, ?err, "Failed to mirror the content of a context URL");
+            }
+            Ok(content)
+        },
+        Err(err) if os.fs.exists(&mirror_path) => {
+            warn!(?url, ?err, "Failed to fetch a context URL, using its local copy");
+            Ok(os.fs.read_to_string(&mirror_path).await?)
+        },
+        Err(err) => Err(eyre!("Failed to fetch {}: {}", url, err)),
+    }
+}
+
+/// Runs the command of a context rule in the current directory, returning its output.
+async fn run_context_command(os: &Os, command: &str) -> Result<String> {
+    let mut child = shell_command(command);
+    child
+        .current_dir(os.fs.chroot_path(os.env.current_dir()?))
+        .stdin(Stdio::null())
+        .kill_on_drop(true);
+    let output = tokio::time::timeout(CONTEXT_COMMAND_TIMEOUT, child.output())
+        .await
+        .map_err(|_elapsed| eyre!("Command timed out after {} s", CONTEXT_COMMAND_TIMEOUT.as_secs()))??;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(eyre!("Command failed with {}: {}", output.status, stderr.trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim_end().to_string())
+}
+
+/// Queries the knowledge base, returning the best results.
+async fn query_knowledge(os: &Os, query: &str) -> Result<String> {
+    validate_source(os, ContextSource::Knowledge, query)?;
+    let store = KnowledgeStore::get_async_instance().await;
+    let results = store.lock().await.search(query, None, SearchOptions::default()).await?;
+    Ok(results
+        .iter()
+        .filter_map(|result| result.text())
+        .take(KNOWLEDGE_RESULTS)
+        .map(|text| format!("- {text}\n"))
+        .collect())
+}
+
+/// Sets the status of `entries` so that the included content fits in `limit` tokens.
+///
+/// Entries are included in order of priority, then in order of their rules. An entry that does not
+/// fit is truncated if at least [MIN_TRUNCATED_TOKENS] are left, and dropped otherwise.
+fn apply_context_limit(entries: &mut [ContextEntry], limit: usize) {
+    let mut order = (0..entries.len()).collect::<Vec<_>>();
+    order.sort_by_key(|index| std::cmp::Reverse(entries[*index].priority));
+
+    let notice_tokens = TokenCounter::count_tokens(TRUNCATION_NOTICE);
+    let mut remaining = limit;
+    for index in order {
+        let entry = &mut entries[index];
+        let tokens = TokenCounter::count_tokens(&entry.content);
+        if tokens <= remaining {
+            entry.status = ContextEntryStatus::Included;
+            remaining -= tokens;
+        } else if remaining >= MIN_TRUNCATED_TOKENS {
+            let budget = remaining - notice_tokens;
+            let mut len = budget * TokenCounter::TOKEN_TO_CHAR_RATIO;
+            let mut truncated = truncate_safe(&entry.content, len);
+            while TokenCounter::count_tokens(truncated) > budget {
+                len = len * 9 / 10;
+                truncated = truncate_safe(&entry.content, len);
+            }
+            entry.content = format!("{truncated}{TRUNCATION_NOTICE}");
+            entry.status = ContextEntryStatus::Truncated {
+                original_tokens: tokens,
+            };
+            remaining = remaining.saturating_sub(TokenCounter::count_tokens(&entry.content));
+        } else {
+            entry.status = ContextEntryStatus::Dropped;
+        }
+    }
+}
+
 /// Validate a profile name.
 ///
 /// Profile names can only contain alphanumeric characters, hyphens, and underscores.
@@ -904,4 +1597,260 @@ mod tests {
 
         Ok(())
     }
+
+    fn entry(name: &str, content: &str, priority: i32) -> ContextEntry {
+        ContextEntry {
+            name: name.to_string(),
+            content: content.to_string(),
+            rule: name.to_string(),
+            scope: ConfigScope::Profile,
+            priority,
+            status: ContextEntryStatus::Included,
+        }
+    }
+
+    #[test]
+    fn test_parse_context_source() {
+        assert_eq!(
+            ContextSource::parse("src/**/*.rs"),
+            (ContextSource::Path, "src/**/*.rs")
+        );
+        assert_eq!(
+            ContextSource::parse("cmd: git diff --stat"),
+            (ContextSource::Command, "git diff --stat")
+        );
+        assert_eq!(
+            ContextSource::parse("url:https://example.com/a"),
+            (ContextSource::Url, "https://example.com/a")
+        );
+        assert_eq!(
+            ContextSource::parse("https://example.com/a"),
+            (ContextSource::Url, "https://example.com/a")
+        );
+        assert_eq!(
+            ContextSource::parse("knowledge:error handling"),
+            (ContextSource::Knowledge, "error handling")
+        );
+    }
+
+    #[test]
+    fn test_apply_context_limit() {
+        let long = "word ".repeat(1000);
+        let mut entries = vec![
+            entry("low", &long, -1),
+            entry("long", &long, 0),
+            entry("high", "high priority content", 5),
+        ];
+        let high_tokens = TokenCounter::count_tokens("high priority content");
+        let limit = high_tokens + MIN_TRUNCATED_TOKENS;
+        apply_context_limit(&mut entries, limit);
+
+        // The high priority entry is kept first, then the long entry is truncated to what is left,
+        // and the low priority entry is dropped
+        assert_eq!(entries[2].status, ContextEntryStatus::Included);
+        assert!(
+            matches!(entries[1].status, ContextEntryStatus::Truncated { original_tokens } if original_tokens > 1000)
+        );
+        assert!(entries[1].content.ends_with(TRUNCATION_NOTICE));
+        assert!(TokenCounter::count_tokens(&entries[1].content) <= MIN_TRUNCATED_TOKENS);
+        assert_eq!(entries[0].status, ContextEntryStatus::Dropped);
+
+        // Entries are dropped rather than truncated when too little is left
+        let mut entries = vec![entry("long", &long, 0), entry("short", "short", 0)];
+        apply_context_limit(&mut entries, 10);
+        assert_eq!(entries[0].status, ContextEntryStatus::Dropped);
+        assert_eq!(entries[1].status, ContextEntryStatus::Included);
+    }
+
+    #[tokio::test]
+    async fn test_dynamic_sources() -> Result<()> {
+        let os = Os::new().await.unwrap();
+        let mut manager = create_test_context_manager(None).await?;
+
+        os.fs.write("/notes.md", "notes").await?;
+        let url = "http://127.0.0.1:9/docs";
+        let mirror_dir = directories::chat_context_mirror_dir(&os)?;
+        os.fs.create_dir_all(&mirror_dir).await?;
+        os.fs
+            .write(mirror_dir.join(mirror_file_name(url)), "mirrored docs")
+            .await?;
+        manager
+            .add_paths(
+                &os,
+                vec![
+                    "notes.md".to_string(),
+                    "cmd:echo from a command".to_string(),
+                    format!("url:{url}"),
+                    "cmd:exit 1".to_string(),
+                ],
+                false,
+                false,
+            )
+            .await?;
+        manager.set_priority(&os, &["notes.md".to_string()], 3, false).await?;
+        assert!(
+            manager
+                .set_priority(&os, &["missing".to_string()], 3, false)
+                .await
+                .is_err()
+        );
+        assert!(
+            manager
+                .add_paths(&os, vec!["url:ftp://a".to_string()], false, false)
+                .await
+                .is_err()
+        );
+        assert!(
+            manager
+                .add_paths(&os, vec!["cmd:".to_string()], false, false)
+                .await
+                .is_err()
+        );
+
+        // URLs that cannot be fetched use their local copy
+        let context = manager.collect_context(&os).await;
+        let entries = context
+            .entries
+            .iter()
+            .map(|entry| (entry.name.as_str(), entry.content.as_str(), entry.priority))
+            .collect::<Vec<_>>();
+        assert!(entries[0].0.ends_with("notes.md"));
+        assert_eq!(entries[0].2, 3);
+        assert_eq!(entries[1..], [
+            ("$ echo from a command", "from a command", 0),
+            (url, "mirrored docs", 0)
+        ]);
+        assert_eq!(context.failed_rules.len(), 1);
+        assert_eq!(context.failed_rules[0].0, "cmd:exit 1");
+
+        // Commands run once per turn
+        let counter = os.fs.chroot_path(os.env.current_dir()?).join("runs.txt");
+        let rule = format!("cmd:echo run >> {0} && wc -l < {0}", counter.display());
+        manager.add_paths(&os, vec![rule], false, false).await?;
+        let command_output = |context: &CollectedContext| context.entries.last().unwrap().content.trim().to_string();
+        assert_eq!(command_output(&manager.collect_context(&os).await), "1");
+        assert_eq!(command_output(&manager.collect_context(&os).await), "1");
+        manager.clear_rule_outputs();
+        assert_eq!(command_output(&manager.collect_context(&os).await), "2");
+
+        // Removing a rule removes its priority
+        manager.remove_paths(&os, vec!["notes.md".to_string()], false).await?;
+        assert!(manager.profile_config.priorities.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_gitignored_files_are_skipped() -> Result<()> {
+        let os = Os::new().await.unwrap();
+        let mut manager = create_test_context_manager(None).await?;
+
+        let status = std::process::Command::new("git")
+            .args(["init", "--quiet"])
+            .current_dir(os.fs.chroot_path("/"))
+            .status()?;
+        assert!(status.success());
+        os.fs.write("/.gitignore", "*.log\nbuild/\n").await?;
+        os.fs.create_dir_all("/docs").await?;
+        os.fs.create_dir_all("/build").await?;
+        os.fs.write("/docs/a.md", "a").await?;
+        os.fs.write("/docs/b.log", "b").await?;
+        os.fs.write("/build/c.md", "c").await?;
+
+        manager
+            .add_paths(&os, vec!["**/*.md".to_string(), "docs".to_string()], false, false)
+            .await?;
+        let files = manager.get_context_files(&os).await?;
+        assert_eq!(files.len(), 1);
+        assert!(files[0].0.ends_with("docs/a.md"));
+
+        // Ignored directories are included when they are named explicitly
+        manager
+            .add_paths(&os, vec!["build/*.md".to_string()], false, false)
+            .await?;
+        let files = manager.get_context_files(&os).await?;
+        assert_eq!(files.len(), 2);
+        assert!(files[0].0.ends_with("build/c.md"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_workspace_commands_need_approval() -> Result<()> {
+        let os = Os::new().await.unwrap();
+        let mut manager = create_test_context_manager(None).await?;
+        manager
+            .workspace_config
+            .paths
+            .push("cmd:echo from the workspace".to_string());
+        manager.workspace_config.hooks.insert(
+            "greeting".to_string(),
+            Hook::new_inline_hook(HookTrigger::PerPrompt, "echo hello".to_string()),
+        );
+        manager.workspace_config.hooks.insert(
+            "check".to_string(),
+            Hook::new_inline_hook(HookTrigger::PreToolUse, "echo check".to_string()),
+        );
+        assert_eq!(manager.unreviewed_workspace_commands(), [
+            "echo check",
+            "echo hello",
+            "echo from the workspace"
+        ]);
+
+        // Nothing of the workspace runs before the user allowed it
+        let context = manager.collect_context(&os).await;
+        assert!(context.entries.is_empty());
+        assert_eq!(context.failed_rules.len(), 1);
+        assert!(manager.run_hooks(&mut vec![]).await?.is_empty());
+        assert!(manager.event_hooks(HookTrigger::PreToolUse, None).is_empty());
+
+        manager.review_workspace_commands(true);
+        assert!(manager.unreviewed_workspace_commands().is_empty());
+        let context = manager.collect_context(&os).await;
+        assert_eq!(context.entries.len(), 1);
+        assert_eq!(context.entries[0].content, "from the workspace");
+        assert_eq!(manager.run_hooks(&mut vec![]).await?.len(), 1);
+        assert_eq!(manager.event_hooks(HookTrigger::PreToolUse, None).len(), 1);
+
+        // Commands added later are asked about again
+        manager.workspace_config.paths.push("cmd:echo more".to_string());
+        assert_eq!(manager.unreviewed_workspace_commands(), ["echo more"]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_workspace_allow_rules_need_approval() -> Result<()> {
+        let mut manager = create_test_context_manager(None).await?;
+        manager
+            .workspace_config
+            .tool_permissions
+            .allow
+            .push("execute_bash".to_string());
+        manager
+            .workspace_config
+            .tool_permissions
+            .deny
+            .push("fs_write".to_string());
+        assert_eq!(manager.unreviewed_workspace_allow_rules(), ["execute_bash"]);
+
+        // Deny rules apply right away, allow rules once the user allowed them
+        let workspace_rules = |manager: &ContextManager| manager.tool_permission_rules()[1].clone().into_owned();
+        assert!(workspace_rules(&manager).allow.is_empty());
+        assert_eq!(workspace_rules(&manager).deny, ["fs_write"]);
+
+        manager.review_workspace_commands(false);
+        assert!(manager.unreviewed_workspace_allow_rules().is_empty());
+        assert!(workspace_rules(&manager).allow.is_empty());
+
+        manager
+            .workspace_config
+            .tool_permissions
+            .allow
+            .push("fs_read".to_string());
+        manager.review_workspace_commands(true);
+        assert_eq!(workspace_rules(&manager).allow, ["fs_read"]);
+
+        Ok(())
+    }
 }
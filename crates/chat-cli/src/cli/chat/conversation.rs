@@ -21,9 +21,15 @@ use tracing::{
     warn,
 };
 
+use super::checkpoint::CheckpointStore;
+use super::cli::model::context_window_tokens;
+use super::compaction::{
+    self,
+    CompactionConfig,
+};
 use super::consts::{
+    CONTEXT_WINDOW_WARNING_PERCENT,
     DUMMY_TOOL_NAME,
-    MAX_CHARS,
     MAX_CONVERSATION_STATE_HISTORY_LEN,
     MAX_USER_MESSAGE_SIZE,
 };
@@ -37,8 +43,8 @@ use super::message::{
     build_env_state,
 };
 use super::token_counter::{
-    CharCount,
     CharCounter,
+    TokenCount,
 };
 use super::tool_manager::{
     ToolManager,
@@ -109,6 +115,12 @@ pub struct ConversationState {
     /// Model explicitly selected by the user in this conversation state via `/model`.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub model: Option<String>,
+    /// Snapshots of the files changed by `fs_write`, used by `/undo` and `/checkpoint`.
+    #[serde(default)]
+    pub checkpoints: CheckpointStore,
+    /// How the history is compacted, by `/compact` and automatically.
+    #[serde(default)]
+    pub compaction: CompactionConfig,
 }
 
 impl ConversationState {
@@ -161,6 +173,8 @@ impl ConversationState {
             context_message_length: None,
             latest_summary: None,
             model: current_model_id,
+            checkpoints: CheckpointStore::default(),
+            compaction: CompactionConfig::from_settings(&os.database.settings),
         }
     }
 
@@ -273,8 +287,24 @@ impl ConversationState {
         self.append_assistant_transcript(&message);
         self.history.push_back((next_user_message, message));
 
-        if let Ok(cwd) = std::env::current_dir() {
-            os.database.set_conversation_by_path(cwd, self).ok();
+        let cwd = std::env::current_dir().ok();
+        os.database.set_session(cwd.as_deref(), &self.title(), self).ok();
+    }
+
+    /// Returns a title for the conversation, taken from the first line of its first prompt.
+    pub fn title(&self) -> String {
+        const MAX_TITLE_LEN: usize = 60;
+        let prompt = self
+            .history
+            .iter()
+            .find_map(|(user, _)| user.prompt())
+            .and_then(|prompt| prompt.lines().map(str::trim).find(|line| !line.is_empty()));
+        match prompt {
+            Some(line) if line.chars().count() > MAX_TITLE_LEN => {
+                format!("{}…", line.chars().take(MAX_TITLE_LEN - 1).collect::<String>())
+            },
+            Some(line) => line.to_string(),
+            None => "Untitled".to_string(),
         }
     }
 
@@ -287,7 +317,7 @@ impl ConversationState {
         stderr: &mut impl Write,
     ) -> eyre::Result<()> {
         debug!("Starting MCP server reload for profile: {:?}", profile_name);
-        
+
         // Provide user feedback about the reload process
         execute!(
             stderr,
@@ -301,7 +331,7 @@ impl ConversationState {
         // Note: In this implementation, trust settings are handled at the tool level
         // and will be preserved through the atomic state update process
         let _current_tools_backup = self.tools.clone();
-        
+
         // Load the new MCP configuration for the specified profile
         let mcp_config = match get_mcp_server_configs_for_profile(os, profile_name).await {
             Ok(config) => {
@@ -310,7 +340,9 @@ impl ConversationState {
                     execute!(
                         stderr,
                         style::SetForegroundColor(style::Color::DarkYellow),
-                        style::Print("⚠ useProfileServersOnly is set to 'true' for this profile. Other mcp configs will be ignored."),
+                        style::Print(
+                            "⚠ useProfileServersOnly is set to 'true' for this profile. Other mcp configs will be ignored."
+                        ),
                         style::SetForegroundColor(style::Color::Reset),
                         style::Print("\n")
                     )?;
@@ -327,7 +359,7 @@ impl ConversationState {
                     style::Print("\n")
                 )?;
                 return Ok(()); // Graceful degradation - continue with existing servers
-            }
+            },
         };
 
         // Create a new tool manager with the updated configuration
@@ -348,7 +380,7 @@ impl ConversationState {
                     style::Print("\n")
                 )?;
                 return Ok(()); // Graceful degradation - keep existing tool manager
-            }
+            },
         };
 
         // Load tools from the new manager
@@ -364,13 +396,13 @@ impl ConversationState {
                     style::Print("\n")
                 )?;
                 return Ok(()); // Graceful degradation
-            }
+            },
         };
 
         // Atomically update the conversation state with new tools and tool manager
         // This preserves conversation history while updating available tools
         let old_tool_manager = std::mem::replace(&mut self.tool_manager, new_tool_manager);
-        
+
         // Update tools mapping, preserving native tools and updating MCP tools
         self.tools = new_tools
             .into_values()
@@ -394,16 +426,22 @@ impl ConversationState {
         execute!(
             stderr,
             style::SetForegroundColor(style::Color::DarkGreen),
-            style::Print(format!("✓ MCP servers reloaded successfully. {} tools available.", tool_count)),
+            style::Print(format!(
+                "✓ MCP servers reloaded successfully. {} tools available.",
+                tool_count
+            )),
             style::SetForegroundColor(style::Color::Reset),
             style::Print("\n")
         )?;
 
-        debug!("MCP server reload completed successfully for profile: {:?}", profile_name);
-        
+        debug!(
+            "MCP server reload completed successfully for profile: {:?}",
+            profile_name
+        );
+
         // Note: The old tool manager will be dropped here, cleaning up old server connections
         drop(old_tool_manager);
-        
+
         Ok(())
     }
 
@@ -557,6 +595,16 @@ impl ConversationState {
         self.next_message = Some(UserMessage::new_tool_use_results_with_images(tool_results, images));
     }
 
+    /// Sets the input of the tool use `tool_use_id` in the last assistant message, so that the
+    /// history records the input the tool was run with after a hook rewrote it.
+    pub fn set_tool_use_args(&mut self, tool_use_id: &str, args: serde_json::Value) {
+        if let Some((_, AssistantMessage::ToolUse { tool_uses, .. })) = self.history.back_mut() {
+            if let Some(tool_use) = tool_uses.iter_mut().find(|tool_use| tool_use.id == tool_use_id) {
+                tool_use.args = args;
+            }
+        }
+    }
+
     /// Sets the next user message with "cancelled" tool results.
     pub fn abandon_tool_use(&mut self, tools_to_be_abandoned: &[QueuedTool], deny_input: String) {
         self.next_message = Some(UserMessage::new_cancelled_tool_uses(
@@ -776,7 +824,13 @@ impl ConversationState {
     }
 
     pub fn replace_history_with_summary(&mut self, summary: String) {
-        self.history.drain(..(self.history.len().saturating_sub(1)));
+        // Pinned messages survive the summary, as plain text since their tool uses are dropped.
+        let pinned = self
+            .history
+            .drain(..(self.history.len().saturating_sub(1)))
+            .filter(|(user, _)| user.pinned)
+            .map(|(user, assistant)| compaction::into_plain_exchange(user, assistant))
+            .collect::<Vec<_>>();
         self.latest_summary = Some(summary);
         // If the last message contains tool results, then we add the results to the content field
         // instead. This is required to avoid validation errors.
@@ -805,6 +859,33 @@ impl ConversationState {
                 user.content = UserMessageContent::Prompt { prompt };
             }
         }
+        for exchange in pinned.into_iter().rev() {
+            self.history.push_front(exchange);
+        }
+    }
+
+    /// Truncates the oldest tool results until `tokens` tokens are freed, returning the number of
+    /// truncated results and freed tokens.
+    pub fn truncate_tool_results(&mut self, tokens: usize) -> (usize, usize) {
+        compaction::truncate_tool_results(&mut self.history, tokens)
+    }
+
+    /// Removes the oldest messages until `tokens` tokens are freed, returning the number of
+    /// removed messages and freed tokens.
+    pub fn remove_oldest_messages(&mut self, tokens: usize) -> (usize, usize) {
+        compaction::remove_oldest_messages(&mut self.history, tokens)
+    }
+
+    /// Pins or unpins the user message at `index` in the history, so that it survives compaction.
+    /// Returns [false] if there is no such message.
+    pub fn set_pinned(&mut self, index: usize, pinned: bool) -> bool {
+        match self.history.get_mut(index) {
+            Some((user, _)) => {
+                user.pinned = pinned;
+                true
+            },
+            None => false,
+        }
     }
 
     pub fn current_profile(&self) -> Option<&str> {
@@ -880,19 +961,25 @@ impl ConversationState {
         self.context_message_length
     }
 
-    /// Calculate the total character count in the conversation
-    pub async fn calculate_char_count(&mut self, os: &Os) -> Result<CharCount, ChatError> {
+    /// Calculate the total token count in the conversation
+    pub async fn calculate_token_count(&mut self, os: &Os) -> Result<TokenCount, ChatError> {
         Ok(self
             .backend_conversation_state(os, false, &mut vec![])
             .await?
-            .char_count())
+            .token_count())
+    }
+
+    /// The size of the context window of the model of the conversation, in tokens.
+    pub fn context_window_tokens(&self) -> usize {
+        context_window_tokens(self.model.as_deref())
     }
 
     /// Get the current token warning level
     pub async fn get_token_warning_level(&mut self, os: &Os) -> Result<TokenWarningLevel, ChatError> {
-        let total_chars = self.calculate_char_count(os).await?;
+        let total_tokens = self.calculate_token_count(os).await?;
+        let threshold = self.context_window_tokens() * CONTEXT_WINDOW_WARNING_PERCENT / 100;
 
-        Ok(if *total_chars >= MAX_CHARS {
+        Ok(if *total_tokens >= threshold {
             TokenWarningLevel::Critical
         } else {
             TokenWarningLevel::None
@@ -1021,33 +1108,33 @@ impl
     }
 
     pub fn calculate_conversation_size(&self) -> ConversationSize {
-        let mut user_chars = 0;
-        let mut assistant_chars = 0;
-        let mut context_chars = 0;
+        let mut user_tokens = 0;
+        let mut assistant_tokens = 0;
+        let mut context_tokens = 0;
 
-        // Count the chars used by the messages in the history.
+        // Count the tokens used by the messages in the history.
         // this clone is cheap
         let history = self.history.clone();
         for (user, assistant) in history {
-            user_chars += *user.char_count();
-            assistant_chars += *assistant.char_count();
+            user_tokens += *user.token_count();
+            assistant_tokens += *assistant.token_count();
         }
 
-        // Add any chars from context messages, if available.
-        context_chars += self
+        // Add any tokens from context messages, if available.
+        context_tokens += self
             .context_messages
             .as_ref()
             .map(|v| {
                 v.iter().fold(0, |acc, (user, assistant)| {
-                    acc + *user.char_count() + *assistant.char_count()
+                    acc + *user.token_count() + *assistant.token_count()
                 })
             })
             .unwrap_or_default();
 
         ConversationSize {
-            context_messages: context_chars.into(),
-            user_messages: user_chars.into(),
-            assistant_messages: assistant_chars.into(),
+            context_messages: context_tokens.into(),
+            user_messages: user_tokens.into(),
+            assistant_messages: assistant_tokens.into(),
         }
     }
 }
@@ -1055,9 +1142,15 @@ impl
 /// Reflects a detailed accounting of the context window utilization for a given conversation.
 #[derive(Debug, Clone, Copy)]
 pub struct ConversationSize {
-    pub context_messages: CharCount,
-    pub user_messages: CharCount,
-    pub assistant_messages: CharCount,
+    pub context_messages: TokenCount,
+    pub user_messages: TokenCount,
+    pub assistant_messages: TokenCount,
+}
+
+impl ConversationSize {
+    pub fn total(&self) -> TokenCount {
+        self.context_messages + self.user_messages + self.assistant_messages
+    }
 }
 
 /// Converts a list of user/assistant message pairs into a flattened list of ChatMessage.
@@ -1077,7 +1170,8 @@ where
 pub enum TokenWarningLevel {
     /// No warning, conversation is within normal limits
     None,
-    /// Critical level - at single warning threshold (600K characters)
+    /// Critical level - at single warning threshold, [CONTEXT_WINDOW_WARNING_PERCENT] of the
+    /// context window
     Critical,
 }
 
@@ -1406,4 +1500,64 @@ mod tests {
             conversation.set_next_user_message(i.to_string()).await;
         }
     }
+
+    #[tokio::test]
+    async fn test_pinned_messages_survive_summary() {
+        let mut os = Os::new().await.unwrap();
+        let mut tool_manager = ToolManager::default();
+        let tools = tool_manager.load_tools(&mut os, &mut vec![]).await.unwrap();
+        let mut conversation = ConversationState::new(&mut os, "fake_conv_id", tools, None, tool_manager, None).await;
+
+        for i in 0..4 {
+            conversation.set_next_user_message(i.to_string()).await;
+            conversation.push_assistant_message(&mut os, AssistantMessage::new_response(None, i.to_string()));
+        }
+        assert!(conversation.set_pinned(1, true));
+        assert!(!conversation.set_pinned(4, true));
+
+        conversation.replace_history_with_summary("summary".to_string());
+        let prompts = conversation
+            .history()
+            .iter()
+            .map(|(user, _)| (user.prompt().unwrap(), user.pinned))
+            .collect::<Vec<_>>();
+        assert_eq!(prompts, vec![("1", true), ("3", false)]);
+
+        conversation.set_next_user_message("next".to_string()).await;
+        let s = conversation
+            .as_sendable_conversation_state(&os, &mut vec![], true)
+            .await
+            .unwrap();
+        assert_conversation_state_invariants(s, 0);
+    }
+
+    #[tokio::test]
+    async fn test_set_tool_use_args() {
+        let mut os = Os::new().await.unwrap();
+        let mut tool_manager = ToolManager::default();
+        let tools = tool_manager.load_tools(&mut os, &mut vec![]).await.unwrap();
+        let mut conversation = ConversationState::new(&mut os, "fake_conv_id", tools, None, tool_manager, None).await;
+
+        conversation.set_next_user_message("start".to_string()).await;
+        let tool_use = |id: &str| AssistantToolUse {
+            id: id.to_string(),
+            name: "tool name".to_string(),
+            args: serde_json::json!({ "command": "ls" }),
+            ..Default::default()
+        };
+        conversation.push_assistant_message(
+            &mut os,
+            AssistantMessage::new_tool_use(None, "content".to_string(), vec![tool_use("1"), tool_use("2")]),
+        );
+        conversation.set_tool_use_args("2", serde_json::json!({ "command": "ls -a" }));
+
+        let args = conversation.history()[0]
+            .1
+            .tool_uses()
+            .unwrap()
+            .iter()
+            .map(|tool_use| tool_use.args["command"].as_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(args, vec!["ls", "ls -a"]);
+    }
 }
@@ -1,4 +1,5 @@
 use eyre::Result;
+use rustyline::ExternalPrinter;
 use rustyline::error::ReadlineError;
 
 use super::prompt::rl;
@@ -14,6 +15,7 @@ mod inner {
     use rustyline::history::FileHistory;
 
     use super::super::prompt::ChatHelper;
+    use super::super::server::Connection;
 
     #[allow(clippy::large_enum_variant)]
     #[derive(Debug)]
@@ -24,6 +26,8 @@ mod inner {
             index: usize,
             lines: Vec<String>,
         },
+        /// Requests of a client driving the session, see [super::super::server].
+        Server(Connection),
     }
 }
 
@@ -66,6 +70,36 @@ impl InputSource {
         }
     }
 
+    /// Returns a printer that writes above the prompt while the user is typing, if the input is
+    /// read from a terminal.
+    pub fn external_printer(&mut self) -> Option<Box<dyn ExternalPrinter + Send>> {
+        match &mut self.0 {
+            inner::Inner::Readline(rl) => rl
+                .create_external_printer()
+                .ok()
+                .map(|printer| Box::new(printer) as Box<dyn ExternalPrinter + Send>),
+            _ => None,
+        }
+    }
+
+    pub fn new_server(connection: super::server::Connection) -> Self {
+        Self(inner::Inner::Server(connection))
+    }
+
+    pub fn server(&self) -> Option<&super::server::Connection> {
+        match &self.0 {
+            inner::Inner::Server(connection) => Some(connection),
+            _ => None,
+        }
+    }
+
+    pub fn server_mut(&mut self) -> Option<&mut super::server::Connection> {
+        match &mut self.0 {
+            inner::Inner::Server(connection) => Some(connection),
+            _ => None,
+        }
+    }
+
     #[allow(dead_code)]
     pub fn new_mock(lines: Vec<String>) -> Self {
         Self(inner::Inner::Mock { index: 0, lines })
@@ -94,6 +128,7 @@ impl InputSource {
                 *index += 1;
                 Ok(lines.get(*index - 1).cloned())
             },
+            inner::Inner::Server(connection) => Ok(connection.read_line(prompt)?),
         }
     }
 
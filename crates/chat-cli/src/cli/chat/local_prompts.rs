@@ -0,0 +1,714 @@
+//! Prompt templates stored as markdown files, under [WORKSPACE_PROMPTS_DIR] in the workspace and
+//! in the global prompts directory, which are used like the prompts of MCP servers with
+//! `@<name> [arg]...`.
+//!
+//! Templates may declare a description and their arguments in front matter:
+//!
+//! ```markdown
+//! ---
+//! description: Review the changes of a branch
+//! arguments:
+//!   - name: branch
+//!     description: The branch to review
+//!     required: true
+//!   - name: focus
+//! ---
+//! Review the changes of {{branch}}, focusing on {{focus}}.
+//!
+//! {{file:CONTRIBUTING.md}}
+//!
+//! {{shell:git diff main...{{branch}}}}
+//! ```
+//!
+//! Arguments are given in the order they are declared. `{{file:<path>}}` placeholders are replaced
+//! with the contents of the file, relative to the current directory, and `{{shell:<command>}}`
+//! placeholders with the output of the command. Placeholders are only read from the template, so
+//! argument values are never expanded themselves, and are quoted when used in commands. Workspace
+//! templates can only read files within the workspace.
+
+use std::collections::{
+    BTreeMap,
+    HashMap,
+};
+use std::path::{
+    Path,
+    PathBuf,
+};
+use std::process::Stdio;
+use std::sync::LazyLock;
+use std::time::Duration;
+
+use bstr::ByteSlice;
+use regex::Regex;
+use thiserror::Error;
+use tracing::warn;
+
+use super::util::truncate_safe;
+use crate::os::Os;
+use crate::util::directories;
+
+/// Directory of the prompt templates of a workspace, relative to its root.
+pub const WORKSPACE_PROMPTS_DIR: &str = ".amazonq/prompts";
+
+/// Max bytes of the contents of a file or output of a command included by a placeholder.
+const MAX_PLACEHOLDER_LEN: usize = 50 * 1024;
+
+/// Max time a command of a `{{shell:...}}` placeholder can run.
+const SHELL_PLACEHOLDER_TIMEOUT: Duration = Duration::from_secs(30);
+
+static ARGUMENT_PLACEHOLDER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\{\{\s*([A-Za-z_][\w-]*)\s*\}\}").expect("the pattern is valid"));
+
+/// Matches `{{file:...}}` and `{{shell:...}}`, which may contain argument placeholders
+static CONTENT_PLACEHOLDER: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\{\{(file|shell):((?:\{\{\s*[A-Za-z_][\w-]*\s*\}\}|[^}]|\}[^}])+?)\}\}").expect("the pattern is valid")
+});
+
+#[derive(Debug, Error)]
+pub enum LocalPromptError {
+    #[error("Invalid front matter in {}, line {line}: {message}", path.display())]
+    FrontMatter {
+        path: PathBuf,
+        line: usize,
+        message: String,
+    },
+    #[error("Missing required argument '{0}'")]
+    MissingArgument(String),
+    #[error("Prompt {name} takes at most {max} arguments")]
+    TooManyArguments { name: String, max: usize },
+    #[error("The template uses '{0}', which is not one of its arguments")]
+    UnknownArgument(String),
+    #[error("Failed to read {}: {source}", path.display())]
+    ReadFile { path: PathBuf, source: std::io::Error },
+    #[error("Command `{command}` failed: {message}")]
+    Shell { command: String, message: String },
+    #[error("Workspace prompts can only include files within the workspace, not {}", .0.display())]
+    OutsideWorkspace(PathBuf),
+    #[error("The value of '{0}' cannot be passed to a command")]
+    UnquotableArgument(String),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+/// Where a prompt template is stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PromptScope {
+    /// [WORKSPACE_PROMPTS_DIR] of the current directory
+    Workspace,
+    /// The global prompts directory
+    Global,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PromptArgument {
+    pub name: String,
+    pub description: Option<String>,
+    pub required: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocalPrompt {
+    pub name: String,
+    pub description: Option<String>,
+    pub arguments: Vec<PromptArgument>,
+    pub scope: PromptScope,
+    template: String,
+}
+
+impl LocalPrompt {
+    /// Parses the prompt template `text`, read from `path`.
+    pub fn parse(name: String, scope: PromptScope, path: &Path, text: &str) -> Result<Self, LocalPromptError> {
+        let (front_matter, template) = parse_front_matter(path, text)?;
+        Ok(Self {
+            name,
+            description: front_matter.description,
+            arguments: front_matter.arguments,
+            scope,
+            template: template.to_string(),
+        })
+    }
+
+    /// The commands of the `{{shell:...}}` placeholders of the template, as written in it
+    pub fn shell_commands(&self) -> Vec<&str> {
+        CONTENT_PLACEHOLDER
+            .captures_iter(&self.template)
+            .filter(|captures| &captures[1] == "shell")
+            .map(|captures| captures.get(2).expect("the command is captured").as_str().trim())
+            .collect()
+    }
+
+    /// Renders the template with `args`, the values of its arguments in the order they are
+    /// declared, replacing placeholders with the contents of files and the output of commands.
+    pub async fn render(&self, os: &Os, args: &[String]) -> Result<String, LocalPromptError> {
+        if args.len() > self.arguments.len() {
+            return Err(LocalPromptError::TooManyArguments {
+                name: self.name.clone(),
+                max: self.arguments.len(),
+            });
+        }
+        let mut values = HashMap::new();
+        for (index, argument) in self.arguments.iter().enumerate() {
+            match args.get(index) {
+                Some(value) => values.insert(argument.name.as_str(), value.as_str()),
+                None if argument.required => return Err(LocalPromptError::MissingArgument(argument.name.clone())),
+                None => values.insert(argument.name.as_str(), ""),
+            };
+        }
+
+        if let Some(captures) = ARGUMENT_PLACEHOLDER
+            .captures_iter(&self.template)
+            .find(|captures| !values.contains_key(&captures[1]))
+        {
+            return Err(LocalPromptError::UnknownArgument(captures[1].to_string()));
+        }
+
+        let cwd = os.env.current_dir()?;
+        let template = &self.template;
+        let mut rendered = String::with_capacity(template.len());
+        let mut last_end = 0;
+        for captures in CONTENT_PLACEHOLDER.captures_iter(template) {
+            let placeholder = captures.get(0).expect("the whole match is a capture");
+            rendered.push_str(&substitute_arguments(
+                &template[last_end..placeholder.start()],
+                &values,
+                |_, value| Ok(value.to_string()),
+            )?);
+            last_end = placeholder.end();
+
+            let value = captures[2].trim();
+            let content = match &captures[1] {
+                "file" => {
+                    let path = cwd.join(substitute_arguments(value, &values, |_, value| Ok(value.to_string()))?);
+                    // Workspace templates come with the repository, and must not send other files
+                    // of the user to the model
+                    if self.scope == PromptScope::Workspace {
+                        let canonical =
+                            os.fs
+                                .canonicalize(&path)
+                                .await
+                                .map_err(|source| LocalPromptError::ReadFile {
+                                    path: path.clone(),
+                                    source,
+                                })?;
+                        if !canonical.starts_with(os.fs.canonicalize(&cwd).await?) {
+                            return Err(LocalPromptError::OutsideWorkspace(path));
+                        }
+                    }
+                    os.fs
+                        .read_to_string(&path)
+                        .await
+                        .map_err(|source| LocalPromptError::ReadFile { path, source })?
+                },
+                _ => run_shell_placeholder(&cwd, &substitute_arguments(value, &values, quote_argument)?).await?,
+            };
+            rendered.push_str(truncate_safe(&content, MAX_PLACEHOLDER_LEN));
+        }
+        rendered.push_str(&substitute_arguments(&template[last_end..], &values, |_, value| {
+            Ok(value.to_string())
+        })?);
+
+        Ok(rendered)
+    }
+}
+
+/// Replaces the argument placeholders of `text` with their values, as transformed by `format`
+fn substitute_arguments(
+    text: &str,
+    values: &HashMap<&str, &str>,
+    format: impl Fn(&str, &str) -> Result<String, LocalPromptError>,
+) -> Result<String, LocalPromptError> {
+    let mut substituted = String::with_capacity(text.len());
+    let mut last_end = 0;
+    for captures in ARGUMENT_PLACEHOLDER.captures_iter(text) {
+        let placeholder = captures.get(0).expect("the whole match is a capture");
+        substituted.push_str(&text[last_end..placeholder.start()]);
+        last_end = placeholder.end();
+
+        let name = &captures[1];
+        let value = values
+            .get(name)
+            .ok_or_else(|| LocalPromptError::UnknownArgument(name.to_string()))?;
+        substituted.push_str(&format(name, value)?);
+    }
+    substituted.push_str(&text[last_end..]);
+    Ok(substituted)
+}
+
+/// Quotes the value of the argument `name` so that the shell passes it to a command as a single
+/// word, without interpreting it.
+fn quote_argument(name: &str, value: &str) -> Result<String, LocalPromptError> {
+    #[cfg(unix)]
+    return shlex::try_quote(value)
+        .map(|quoted| quoted.into_owned())
+        .map_err(|_err| LocalPromptError::UnquotableArgument(name.to_string()));
+
+    // cmd expands variables and interprets some characters even within quotes
+    #[cfg(windows)]
+    return match value.contains(['"', '%', '!', '^', '&', '|', '<', '>', '\r', '\n']) {
+        true => Err(LocalPromptError::UnquotableArgument(name.to_string())),
+        false => Ok(format!("\"{value}\"")),
+    };
+}
+
+#[derive(Debug, Default)]
+struct FrontMatter {
+    description: Option<String>,
+    arguments: Vec<PromptArgument>,
+}
+
+/// Splits `text` into its front matter, which is delimited by `---` lines, and the template. Only
+/// the `description` and `arguments` keys are read, and other keys are ignored.
+fn parse_front_matter<'a>(path: &Path, text: &'a str) -> Result<(FrontMatter, &'a str), LocalPromptError> {
+    let Some(rest) = text.strip_prefix("---\n").or_else(|| text.strip_prefix("---\r\n")) else {
+        return Ok((FrontMatter::default(), text));
+    };
+
+    let mut front_matter = FrontMatter::default();
+    let mut in_arguments = false;
+    let mut offset = 0;
+    for (index, line) in rest.split_inclusive('\n').enumerate() {
+        offset += line.len();
+        let line = line.trim_end();
+        let error = |message: &str| LocalPromptError::FrontMatter {
+            path: path.to_path_buf(),
+            line: index + 2,
+            message: message.to_string(),
+        };
+
+        if line == "---" {
+            if let Some(argument) = front_matter.arguments.iter().find(|argument| {
+                argument.name.is_empty()
+                    || !argument
+                        .name
+                        .chars()
+                        .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+            }) {
+                return Err(error(&format!("invalid argument name '{}'", argument.name)));
+            }
+            return Ok((front_matter, &rest[offset..]));
+        }
+        if line.trim().is_empty() || line.trim_start().starts_with('#') {
+            continue;
+        }
+
+        let is_indented = line.starts_with(char::is_whitespace);
+        let (is_item, entry) = match line.trim_start().strip_prefix("- ") {
+            Some(entry) => (true, entry),
+            None => (false, line.trim_start()),
+        };
+        let (key, value) = entry.split_once(':').ok_or_else(|| error("expected `key: value`"))?;
+        let (key, value) = (key.trim(), unquote(value.trim()));
+
+        if !is_indented && !is_item {
+            in_arguments = false;
+            match key {
+                "description" => front_matter.description = Some(value.to_string()),
+                "arguments" if value.is_empty() => in_arguments = true,
+                "arguments" => return Err(error("expected a list of arguments")),
+                _ => (),
+            }
+            continue;
+        }
+
+        if !in_arguments {
+            return Err(error("unexpected indentation"));
+        }
+        if is_item {
+            front_matter.arguments.push(PromptArgument::default());
+        }
+        let argument = front_matter
+            .arguments
+            .last_mut()
+            .ok_or_else(|| error("expected `- name: <name>`"))?;
+        match key {
+            "name" => argument.name = value.to_string(),
+            "description" => argument.description = Some(value.to_string()),
+            "required" => {
+                argument.required = match value {
+                    "true" => true,
+                    "false" => false,
+                    _ => return Err(error("expected `true` or `false`")),
+                };
+            },
+            _ => (),
+        }
+    }
+
+    Err(LocalPromptError::FrontMatter {
+        path: path.to_path_buf(),
+        line: 1,
+        message: "the front matter is not closed with `---`".to_string(),
+    })
+}
+
+fn unquote(value: &str) -> &str {
+    for quote in ['"', '\''] {
+        if let Some(unquoted) = value.strip_prefix(quote).and_then(|value| value.strip_suffix(quote)) {
+            return unquoted;
+        }
+    }
+    value
+}
+
+async fn run_shell_placeholder(cwd: &Path, command: &str) -> Result<String, LocalPromptError> {
+    let error = |message: String| LocalPromptError::Shell {
+        command: command.to_string(),
+        message,
+    };
+
+    #[cfg(unix)]
+    let mut shell_command = tokio::process::Command::new("bash");
+    #[cfg(unix)]
+    shell_command.arg("-c");
+
+    #[cfg(windows)]
+    let mut shell_command = tokio::process::Command::new("cmd");
+    #[cfg(windows)]
+    shell_command.arg("/C");
+
+    let output = shell_command
+        .arg(command)
+        .current_dir(cwd)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .output();
+    let output = match tokio::time::timeout(SHELL_PLACEHOLDER_TIMEOUT, output).await {
+        Ok(output) => output.map_err(|err| error(err.to_string()))?,
+        Err(_) => {
+            return Err(error(format!(
+                "timed out after {} s",
+                SHELL_PLACEHOLDER_TIMEOUT.as_secs()
+            )));
+        },
+    };
+
+    if !output.status.success() {
+        let stderr = output.stderr.to_str_lossy();
+        return Err(error(format!("{}: {}", output.status, stderr.trim())));
+    }
+    Ok(output.stdout.to_str_lossy().trim_end().to_string())
+}
+
+/// The directories of prompt templates, in order of precedence.
+pub fn prompt_dirs(os: &Os) -> Vec<(PromptScope, PathBuf)> {
+    let mut dirs = Vec::new();
+    if let Ok(cwd) = os.env.current_dir() {
+        dirs.push((PromptScope::Workspace, cwd.join(WORKSPACE_PROMPTS_DIR)));
+    }
+    if let Ok(dir) = directories::chat_global_prompts_dir(os) {
+        dirs.push((PromptScope::Global, dir));
+    }
+    dirs
+}
+
+/// The name of the prompt template at `path`, if it is one.
+fn prompt_name(path: &Path) -> Option<String> {
+    if path.extension()? != "md" {
+        return None;
+    }
+    Some(path.file_stem()?.to_str()?.to_string())
+}
+
+/// Loads the prompt templates of every prompt directory, sorted by name. Workspace templates take
+/// precedence over global ones with the same name, and invalid templates are skipped.
+pub async fn load_prompts(os: &Os) -> Vec<LocalPrompt> {
+    let mut prompts = BTreeMap::new();
+    for (scope, dir) in prompt_dirs(os) {
+        let Ok(mut entries) = os.fs.read_dir(&dir).await else {
+            continue;
+        };
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            // Entries hold the real path, which differs from the one of the fs when it is chrooted
+            let path = dir.join(entry.file_name());
+            let Some(name) = prompt_name(&path) else {
+                continue;
+            };
+            if prompts.contains_key(&name) {
+                continue;
+            }
+            let prompt = match os.fs.read_to_string(&path).await {
+                Ok(text) => LocalPrompt::parse(name.clone(), scope, &path, &text),
+                Err(err) => Err(err.into()),
+            };
+            match prompt {
+                Ok(prompt) => {
+                    prompts.insert(name, prompt);
+                },
+                Err(err) => warn!(?path, %err, "Skipping invalid prompt template"),
+            }
+        }
+    }
+    prompts.into_values().collect()
+}
+
+/// Finds the prompt template `name`, returning [None] if there is no such template. Like in
+/// [load_prompts], invalid templates are skipped, so they don't shadow the prompt of the same name
+/// of an MCP server.
+pub async fn find_prompt(os: &Os, name: &str) -> Option<LocalPrompt> {
+    // Names with a separator refer to the prompt of an MCP server, e.g. `server/name`
+    if name.is_empty() || name.contains(['/', '\\']) {
+        return None;
+    }
+    for (scope, dir) in prompt_dirs(os) {
+        let path = dir.join(format!("{name}.md"));
+        if !os.fs.exists(&path) {
+            continue;
+        }
+        let prompt = match os.fs.read_to_string(&path).await {
+            Ok(text) => LocalPrompt::parse(name.to_string(), scope, &path, &text),
+            Err(err) => Err(err.into()),
+        };
+        match prompt {
+            Ok(prompt) => return Some(prompt),
+            Err(err) => warn!(?path, %err, "Skipping invalid prompt template"),
+        }
+    }
+    None
+}
+
+/// Lists the names of the prompt templates in `dirs`, for tab completion which cannot wait on
+/// async reads.
+pub fn list_prompt_names(dirs: &[PathBuf]) -> Vec<String> {
+    let mut names = dirs
+        .iter()
+        .filter_map(|dir| std::fs::read_dir(dir).ok())
+        .flatten()
+        .filter_map(|entry| prompt_name(&entry.ok()?.path()))
+        .collect::<Vec<_>>();
+    names.sort();
+    names.dedup();
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEMPLATE: &str = "---
+description: Review a branch
+# Arguments are given in order
+arguments:
+  - name: branch
+    description: \"The branch: to review\"
+    required: true
+  - name: focus
+---
+Review {{branch}}{{ focus }}.
+";
+
+    #[test]
+    fn test_parse_front_matter() {
+        let prompt = LocalPrompt::parse(
+            "review".to_string(),
+            PromptScope::Workspace,
+            Path::new("a.md"),
+            TEMPLATE,
+        )
+        .unwrap();
+        assert_eq!(prompt.description.as_deref(), Some("Review a branch"));
+        assert_eq!(prompt.arguments, vec![
+            PromptArgument {
+                name: "branch".to_string(),
+                description: Some("The branch: to review".to_string()),
+                required: true,
+            },
+            PromptArgument {
+                name: "focus".to_string(),
+                description: None,
+                required: false,
+            },
+        ]);
+        assert_eq!(prompt.template, "Review {{branch}}{{ focus }}.\n");
+
+        // Without front matter, the whole file is the template
+        let prompt = LocalPrompt::parse("a".to_string(), PromptScope::Global, Path::new("a.md"), "Hi\n").unwrap();
+        assert!(prompt.arguments.is_empty());
+        assert_eq!(prompt.template, "Hi\n");
+
+        for invalid in [
+            "---\narguments: a\n---\n",
+            "---\n  - name: a\n---\n",
+            "---\narguments:\n  - name: a\n    required: yes\n---\n",
+            "---\narguments:\n  - name: a b\n---\n",
+            "---\ndescription: a\n",
+        ] {
+            assert!(
+                LocalPrompt::parse("a".to_string(), PromptScope::Global, Path::new("a.md"), invalid).is_err(),
+                "{invalid}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_render() {
+        let os = Os::new().await.unwrap();
+        let prompt = LocalPrompt::parse(
+            "review".to_string(),
+            PromptScope::Workspace,
+            Path::new("a.md"),
+            TEMPLATE,
+        )
+        .unwrap();
+
+        let args = |args: &[&str]| args.iter().map(|arg| (*arg).to_string()).collect::<Vec<_>>();
+        assert_eq!(prompt.render(&os, &args(&["main"])).await.unwrap(), "Review main.\n");
+        assert_eq!(
+            prompt.render(&os, &args(&["main", " on tests"])).await.unwrap(),
+            "Review main on tests.\n"
+        );
+        assert!(matches!(
+            prompt.render(&os, &[]).await,
+            Err(LocalPromptError::MissingArgument(name)) if name == "branch"
+        ));
+        assert!(matches!(
+            prompt.render(&os, &args(&["a", "b", "c"])).await,
+            Err(LocalPromptError::TooManyArguments { max: 2, .. })
+        ));
+
+        let prompt = LocalPrompt::parse("a".to_string(), PromptScope::Global, Path::new("a.md"), "{{other}}").unwrap();
+        assert!(matches!(
+            prompt.render(&os, &[]).await,
+            Err(LocalPromptError::UnknownArgument(name)) if name == "other"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_render_placeholders() {
+        let os = Os::new().await.unwrap();
+        let cwd = os.env.current_dir().unwrap();
+        os.fs.create_dir_all(&cwd).await.unwrap();
+        os.fs.write(cwd.join("notes.txt"), "some notes").await.unwrap();
+
+        let template = "---\narguments:\n  - name: word\n---\n{{file:notes.txt}}\n{{file: missing.txt}}";
+        let prompt = LocalPrompt::parse("a".to_string(), PromptScope::Global, Path::new("a.md"), template).unwrap();
+        assert!(matches!(
+            prompt.render(&os, &[]).await,
+            Err(LocalPromptError::ReadFile { .. })
+        ));
+
+        let prompt = LocalPrompt::parse(
+            "a".to_string(),
+            PromptScope::Global,
+            Path::new("a.md"),
+            "---\narguments:\n  - name: word\n---\n{{file:notes.txt}}\n{{shell:echo {{word}}}}",
+        )
+        .unwrap();
+        #[cfg(unix)]
+        assert_eq!(
+            prompt.render(&os, &["hello".to_string()]).await.unwrap(),
+            "some notes\nhello"
+        );
+        assert_eq!(prompt.shell_commands(), vec!["echo {{word}}"]);
+
+        // Values are passed to commands as single words and never expanded
+        #[cfg(unix)]
+        {
+            let value = "$(echo injected); `echo injected` {{shell:echo injected}}";
+            assert_eq!(
+                prompt.render(&os, &[value.to_string()]).await.unwrap(),
+                format!("some notes\n{value}")
+            );
+            assert!(matches!(
+                prompt.render(&os, &["a\0b".to_string()]).await,
+                Err(LocalPromptError::UnquotableArgument(name)) if name == "word"
+            ));
+        }
+        let prompt = LocalPrompt::parse(
+            "a".to_string(),
+            PromptScope::Global,
+            Path::new("a.md"),
+            "---\narguments:\n  - name: word\n---\nSay {{word}}",
+        )
+        .unwrap();
+        assert_eq!(
+            prompt
+                .render(&os, &["{{shell:echo injected}} {{file:notes.txt}}".to_string()])
+                .await
+                .unwrap(),
+            "Say {{shell:echo injected}} {{file:notes.txt}}"
+        );
+        assert!(prompt.shell_commands().is_empty());
+
+        // Workspace templates only include files within the workspace
+        os.fs.create_dir_all("/workspace/docs").await.unwrap();
+        os.fs.write("/workspace/notes.txt", "some notes").await.unwrap();
+        os.fs.write("/secret.txt", "secret").await.unwrap();
+        os.env.set_current_dir_for_test("/workspace");
+        for path in ["/secret.txt", "../secret.txt", "{{word}}"] {
+            let prompt = LocalPrompt::parse(
+                "a".to_string(),
+                PromptScope::Workspace,
+                Path::new("a.md"),
+                &format!("---\narguments:\n  - name: word\n---\n{{{{file:{path}}}}}"),
+            )
+            .unwrap();
+            assert!(matches!(
+                prompt.render(&os, &["/secret.txt".to_string()]).await,
+                Err(LocalPromptError::OutsideWorkspace(_))
+            ));
+        }
+        let prompt = LocalPrompt::parse(
+            "a".to_string(),
+            PromptScope::Workspace,
+            Path::new("a.md"),
+            "{{file:./docs/../notes.txt}}",
+        )
+        .unwrap();
+        assert_eq!(prompt.render(&os, &[]).await.unwrap(), "some notes");
+
+        #[cfg(unix)]
+        {
+            let prompt = LocalPrompt::parse(
+                "a".to_string(),
+                PromptScope::Global,
+                Path::new("a.md"),
+                "{{shell:exit 3}}",
+            )
+            .unwrap();
+            assert!(matches!(
+                prompt.render(&os, &[]).await,
+                Err(LocalPromptError::Shell { .. })
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_load_and_find_prompts() {
+        let os = Os::new().await.unwrap();
+        let dirs = prompt_dirs(&os);
+        let (workspace, global) = (&dirs[0].1, &dirs[1].1);
+        os.fs.create_dir_all(workspace).await.unwrap();
+        os.fs.create_dir_all(global).await.unwrap();
+        os.fs.write(workspace.join("review.md"), "workspace").await.unwrap();
+        os.fs.write(global.join("review.md"), "global").await.unwrap();
+        os.fs.write(global.join("explain.md"), "explain").await.unwrap();
+        os.fs.write(global.join("invalid.md"), "---\n").await.unwrap();
+        os.fs.write(global.join("notes.txt"), "not a prompt").await.unwrap();
+
+        let prompts = load_prompts(&os).await;
+        let names = prompts
+            .iter()
+            .map(|prompt| (prompt.name.as_str(), prompt.scope))
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec![
+            ("explain", PromptScope::Global),
+            ("review", PromptScope::Workspace)
+        ]);
+
+        let review = find_prompt(&os, "review").await.unwrap();
+        assert_eq!(review.scope, PromptScope::Workspace);
+        assert!(find_prompt(&os, "invalid").await.is_none());
+        assert!(find_prompt(&os, "missing").await.is_none());
+        assert!(find_prompt(&os, "server/review").await.is_none());
+
+        // An invalid workspace template doesn't shadow the global one
+        os.fs.write(workspace.join("explain.md"), "---\n").await.unwrap();
+        let explain = find_prompt(&os, "explain").await.unwrap();
+        assert_eq!(explain.scope, PromptScope::Global);
+
+        let chroot_dirs = [os.fs.chroot_path(workspace), os.fs.chroot_path(global)];
+        assert_eq!(list_prompt_names(&chroot_dirs), vec!["explain", "invalid", "review"]);
+    }
+}
@@ -37,6 +37,9 @@ pub struct UserMessage {
     pub env_context: UserEnvContext,
     pub content: UserMessageContent,
     pub images: Option<Vec<ImageBlock>>,
+    /// Whether the exchange starting with this message is kept when the history is compacted.
+    #[serde(default)]
+    pub pinned: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -63,6 +66,7 @@ impl UserMessage {
             images: None,
             additional_context: String::new(),
             env_context: UserEnvContext::generate_new(),
+            pinned: false,
             content: UserMessageContent::Prompt { prompt },
         }
     }
@@ -72,6 +76,7 @@ impl UserMessage {
             images: None,
             additional_context: String::new(),
             env_context: UserEnvContext::generate_new(),
+            pinned: false,
             content: UserMessageContent::CancelledToolUses {
                 prompt,
                 tool_use_results: tool_use_ids
@@ -91,6 +96,7 @@ impl UserMessage {
         Self {
             additional_context: String::new(),
             env_context: UserEnvContext::generate_new(),
+            pinned: false,
             content: UserMessageContent::ToolUseResults {
                 tool_use_results: results,
             },
@@ -102,6 +108,7 @@ impl UserMessage {
         Self {
             additional_context: String::new(),
             env_context: UserEnvContext::generate_new(),
+            pinned: false,
             content: UserMessageContent::ToolUseResults {
                 tool_use_results: results,
             },
@@ -1,22 +1,30 @@
+mod checkpoint;
 mod cli;
+mod compaction;
 mod consts;
 mod context;
 mod conversation;
 mod error_formatter;
 mod input_source;
+mod local_prompts;
 mod message;
+pub mod output;
 mod parse;
 use std::path::MAIN_SEPARATOR;
 mod parser;
 mod prompt;
 mod prompt_parser;
+mod server;
 mod server_messenger;
+mod server_requests;
 #[cfg(unix)]
 mod skim_integration;
 mod token_counter;
+mod tokenizer;
 pub mod tool_manager;
 pub mod tools;
 pub mod util;
+mod workspace_config;
 
 use std::borrow::Cow;
 use std::collections::{
@@ -34,6 +42,7 @@ use clap::{
     CommandFactory,
     Parser,
 };
+use compaction::CompactionStrategy;
 use context::ContextManager;
 pub use conversation::ConversationState;
 use conversation::TokenWarningLevel;
@@ -62,9 +71,17 @@ use message::{
     ToolUseResult,
     ToolUseResultBlock,
 };
+use output::{
+    ChatOutputFormat,
+    EventWriter,
+    OutputEvent,
+    SessionStderr,
+    SessionStdout,
+    ToolResultEventStatus,
+};
 use parse::{
     ParseState,
-    interpret_markdown,
+    render_markdown,
 };
 use parser::{
     RecvErrorKind,
@@ -77,14 +94,26 @@ use spinners::{
 };
 use thiserror::Error;
 use time::OffsetDateTime;
-use token_counter::TokenCounter;
+use token_counter::{
+    CharCounter,
+    TokenCount,
+    TokenCounter,
+};
+use tokenizer::TokenizerBackend;
 use tokio::signal::ctrl_c;
 use tool_manager::{
     McpServerConfig,
     ToolManager,
     ToolManagerBuilder,
 };
+use tools::execute::sandbox::Sandbox;
 use tools::gh_issue::GhIssueContext;
+use tools::permissions::{
+    RuleEffect,
+    evaluate_rules,
+    is_plain_tool_name,
+    split_rules,
+};
 use tools::{
     OutputKind,
     QueuedTool,
@@ -105,8 +134,13 @@ use util::{
     animate_output,
     play_notification_bell,
 };
-use winnow::Partial;
-use winnow::stream::Offset;
+use workspace_config::{
+    ResolvedConfig,
+    WORKSPACE_CONFIG_PATH,
+    WorkspaceConfig,
+    load_workspace_config,
+    resolve_model,
+};
 
 use crate::api_client::ApiClientError;
 use crate::api_client::model::{
@@ -116,7 +150,12 @@ use crate::api_client::model::{
 use crate::api_client::send_message_output::SendMessageOutput;
 use crate::auth::AuthError;
 use crate::auth::builder_id::is_idc_user;
-use crate::cli::chat::cli::SlashCommand;
+use crate::cli::chat::cli::hooks::{
+    self,
+    Hook,
+    HookOutcome,
+    HookTrigger,
+};
 use crate::cli::chat::cli::model::{
     MODEL_OPTIONS,
     default_model_id,
@@ -125,6 +164,10 @@ use crate::cli::chat::cli::prompts::{
     GetPromptError,
     PromptsSubcommand,
 };
+use crate::cli::chat::cli::{
+    SlashCommand,
+    resources,
+};
 use crate::database::settings::Setting;
 use crate::mcp_client::Prompt;
 use crate::os::Os;
@@ -134,6 +177,7 @@ use crate::telemetry::{
     TelemetryResult,
     get_error_reason,
 };
+use crate::util::knowledge_store::KnowledgeStore;
 
 const LIMIT_REACHED_TEXT: &str = color_print::cstr! { "You've used all your free requests for this month. You have two options:
 1. Upgrade to a paid subscription for increased limits. See our Pricing page for what's included> <blue!>https://aws.amazon.com/q/developer/pricing/</blue!>
@@ -153,9 +197,15 @@ pub const EXTRA_HELP: &str = color_print::cstr! {"
 
 #[derive(Debug, Clone, PartialEq, Eq, Default, Args)]
 pub struct ChatArgs {
-    /// Resumes the previous conversation from this directory.
+    /// Resumes the previous conversation from this directory
     #[arg(short, long)]
     pub resume: bool,
+    /// Resumes the saved session with the given id, or a unique prefix of it
+    #[arg(long, value_name = "SESSION_ID", conflicts_with = "resume")]
+    pub resume_id: Option<String>,
+    /// Lists the saved sessions that can be resumed
+    #[arg(long)]
+    pub list_sessions: bool,
     /// Context profile to use
     #[arg(long = "profile")]
     pub profile: Option<String>,
@@ -166,26 +216,69 @@ pub struct ChatArgs {
     #[arg(long)]
     pub trust_all_tools: bool,
     /// Trust only this set of tools. Example: trust some tools:
-    /// '--trust-tools=fs_read,fs_write', trust no tools: '--trust-tools='
-    #[arg(long, value_delimiter = ',', value_name = "TOOL_NAMES")]
+    /// '--trust-tools=fs_read,fs_write', trust no tools: '--trust-tools='. Patterns and argument
+    /// rules are accepted too, e.g. '--trust-tools=@git/*' or
+    /// "--trust-tools='fs_write(path=./src/**)'"
+    #[arg(long, value_name = "TOOL_NAMES")]
     pub trust_tools: Option<Vec<String>>,
     /// Whether the command should run without expecting user input
     #[arg(long, alias = "no-interactive")]
     pub non_interactive: bool,
+    /// Format of the output of non-interactive chats. The JSON formats write events for the
+    /// assistant text, tool uses, tool results and usage to stdout, and exit with an error if a
+    /// tool failed or was denied.
+    #[arg(long, value_enum, default_value_t)]
+    pub output_format: ChatOutputFormat,
+    /// Lets another program drive the chat with JSON-RPC messages over stdin and stdout, instead
+    /// of prompting the user. See the documentation of the protocol in server.rs.
+    #[arg(long)]
+    pub server: bool,
+    /// Prints the configuration that applies to a chat in the current directory as JSON and exits:
+    /// the global, workspace (.amazonq/config.json) and profile configurations merged in this
+    /// order of precedence, annotated with the configuration each part comes from.
+    #[arg(long)]
+    pub print_config: bool,
     /// The first question to ask
     pub input: Option<String>,
 }
 
 impl ChatArgs {
     pub async fn execute(self, os: &mut Os) -> Result<ExitCode> {
+        let stdout = std::io::stdout();
+        let mut stderr = std::io::stderr();
+
+        if let Err(err) = cli::sessions::prune_expired_sessions(os) {
+            warn!(?err, "Failed to prune expired sessions");
+        }
+        tokenizer::init(TokenizerBackend::from_settings(&os.database.settings));
+        if self.list_sessions {
+            cli::sessions::print_sessions(&mut stderr, &os.database.list_sessions()?, None)?;
+            stderr.flush()?;
+            return Ok(ExitCode::SUCCESS);
+        }
+        if self.print_config {
+            let config = ResolvedConfig::resolve(os, self.profile.as_deref(), self.model.as_deref()).await?;
+            println!("{}", serde_json::to_string_pretty(&config)?);
+            return Ok(ExitCode::SUCCESS);
+        }
+        let resume = match &self.resume_id {
+            Some(id) => Some(Some(cli::sessions::resolve_session_id(os, id)?)),
+            None => self.resume.then_some(None),
+        };
+
         if self.non_interactive && self.input.is_none() {
             bail!("Input must be supplied when running in non-interactive mode");
         }
+        if !self.non_interactive && self.output_format != ChatOutputFormat::Text {
+            bail!("--output-format can only be used in non-interactive mode");
+        }
+        if self.server && self.non_interactive {
+            bail!("--server cannot be used in non-interactive mode");
+        }
 
-        let stdout = std::io::stdout();
-        let mut stderr = std::io::stderr();
-
-        let mcp_server_configs = match tool_manager::get_mcp_server_configs_for_profile(os, self.profile.as_deref()).await {
+        let mcp_server_configs = match tool_manager::get_mcp_server_configs_for_profile(os, self.profile.as_deref())
+            .await
+        {
             Ok(config) => {
                 if !os.database.settings.get_bool(Setting::McpLoadedBefore).unwrap_or(false) {
                     execute!(
@@ -196,18 +289,20 @@ impl ChatArgs {
                     )?;
                 }
                 os.database.settings.set(Setting::McpLoadedBefore, true).await?;
-                
+
                 // Display profile exclusivity warning if applicable
                 if config.use_profile_servers_only {
                     execute!(
                         stderr,
                         style::SetForegroundColor(style::Color::DarkYellow),
-                        style::Print("⚠ useProfileServersOnly is set to 'true' for this profile. Other mcp configs will be ignored."),
+                        style::Print(
+                            "⚠ useProfileServersOnly is set to 'true' for this profile. Other mcp configs will be ignored."
+                        ),
                         style::SetForegroundColor(style::Color::Reset),
                         style::Print("\n\n")
                     )?;
                 }
-                
+
                 config
             },
             Err(e) => {
@@ -237,23 +332,22 @@ impl ChatArgs {
             }
         }
 
-        // If modelId is specified, verify it exists before starting the chat
-        let model_id: Option<String> = if let Some(model_name) = self.model {
-            let model_name_lower = model_name.to_lowercase();
-            match MODEL_OPTIONS.iter().find(|opt| opt.name == model_name_lower) {
-                Some(opt) => Some((opt.model_id).to_string()),
-                None => {
-                    let available_names: Vec<&str> = MODEL_OPTIONS.iter().map(|opt| opt.name).collect();
-                    bail!(
-                        "Model '{}' does not exist. Available models: {}",
-                        model_name,
-                        available_names.join(", ")
-                    );
-                },
-            }
-        } else {
-            None
+        // The model given with --model takes precedence over the default of the workspace, verify
+        // it exists before starting the chat
+        let workspace_config = match load_workspace_config(os).await {
+            Ok(config) => config,
+            Err(err) => {
+                execute!(
+                    stderr,
+                    style::SetForegroundColor(style::Color::DarkYellow),
+                    style::Print(format!("⚠ {}. The workspace configuration is ignored.", err)),
+                    style::SetForegroundColor(style::Color::Reset),
+                    style::Print("\n\n")
+                )?;
+                WorkspaceConfig::default()
+            },
         };
+        let model_id = Some(resolve_model(os, self.model.as_deref(), &workspace_config)?.model_id);
 
         let conversation_id = uuid::Uuid::new_v4().to_string();
         info!(?conversation_id, "Generated new conversation id");
@@ -274,10 +368,21 @@ impl ChatArgs {
             for tool in tool_config.values() {
                 tool_permissions.trust_tool(&tool.name);
             }
-        } else if let Some(trusted) = self.trust_tools.map(|vec| vec.into_iter().collect::<HashSet<_>>()) {
+        } else if let Some(trusted) = self.trust_tools.as_ref().map(|lists| {
+            lists
+                .iter()
+                .flat_map(|list| split_rules(list))
+                .map(str::to_string)
+                .collect::<HashSet<_>>()
+        }) {
             // --trust-all-tools takes precedence over --trust-tools=...
             for tool_name in &trusted {
-                if !tool_name.is_empty() {
+                if !tool_name.is_empty() && !is_plain_tool_name(tool_name) {
+                    // Patterns and argument rules are matched when the tools are used
+                    if let Err(err) = tool_permissions.rules.add(RuleEffect::Allow, tool_name) {
+                        bail!("Invalid --trust-tools rule '{tool_name}': {err}");
+                    }
+                } else if !tool_name.is_empty() {
                     // Store the original trust settings for later use with MCP tools
                     tool_permissions.add_pending_trust_tool(tool_name.clone());
                 }
@@ -293,14 +398,18 @@ impl ChatArgs {
             }
         }
 
-        ChatSession::new(
+        let input_source = match self.server {
+            true => InputSource::new_server(server::Connection::stdio()),
+            false => InputSource::new(os, prompt_request_sender, prompt_response_receiver)?,
+        };
+        let mut session = ChatSession::new(
             os,
             stdout,
             stderr,
             &conversation_id,
             self.input,
-            InputSource::new(os, prompt_request_sender, prompt_response_receiver)?,
-            self.resume,
+            input_source,
+            resume,
             || terminal::window_size().map(|s| s.columns.into()).ok(),
             tool_manager,
             self.profile,
@@ -308,11 +417,52 @@ impl ChatArgs {
             tool_config,
             tool_permissions,
             !self.non_interactive,
+            self.output_format,
         )
-        .await?
-        .spawn(os)
-        .await
-        .map(|_| ExitCode::SUCCESS)
+        .await?;
+
+        // Keep the knowledge base in sync with its source files while the session is open
+        let watch_knowledge = os
+            .database
+            .settings
+            .get_bool(Setting::EnabledKnowledge)
+            .unwrap_or(false)
+            && os
+                .database
+                .settings
+                .get_bool(Setting::EnabledKnowledgeWatch)
+                .unwrap_or(false);
+        let _knowledge_watcher = match watch_knowledge {
+            true => KnowledgeStore::get_async_instance()
+                .await
+                .lock()
+                .await
+                .watch()
+                .inspect_err(|err| warn!("Failed to watch knowledge base sources: {}", err))
+                .ok(),
+            false => None,
+        };
+
+        let result = session.spawn(os).await;
+        if let Some(connection) = session.input_source.server_mut() {
+            connection.finish()?;
+            return result.map(|_| ExitCode::SUCCESS);
+        }
+        if !session.events.is_enabled() {
+            return result.map(|_| ExitCode::SUCCESS);
+        }
+
+        let response = session
+            .conversation
+            .history()
+            .back()
+            .map(|(_, message)| message.content().to_string());
+        let conversation_id = session.conversation.conversation_id().to_string();
+        let error = result.err().map(|err| err.to_string());
+        match session.events.finish(&conversation_id, response, error)? {
+            true => Ok(ExitCode::SUCCESS),
+            false => Ok(ExitCode::FAILURE),
+        }
     }
 }
 
@@ -375,6 +525,9 @@ const SMALL_SCREEN_POPULAR_SHORTCUTS: &str = color_print::cstr! {"<black!><green
 </black!>"};
 
 const RESPONSE_TIMEOUT_CONTENT: &str = "Response timed out - message took too long to generate";
+/// The most times in a row that stop hooks can have the model continue a response, so that a
+/// hook that never lets the response end does not loop forever.
+const MAX_STOP_HOOK_CONTINUATIONS: usize = 10;
 const TRUST_ALL_TEXT: &str = color_print::cstr! {"<green!>All tools are now trusted (<red!>!</red!>). Amazon Q will execute tools <bold>without</bold> asking for confirmation.\
 \nAgents can sometimes do unexpected things so understand the risks.</green!>
 \nLearn more at https://docs.aws.amazon.com/amazonq/latest/qdeveloper-ug/command-line-chat-security.html#command-line-chat-trustall-safety"};
@@ -464,9 +617,9 @@ impl From<parser::RecvError> for ChatError {
 
 pub struct ChatSession {
     /// For output read by humans and machine
-    pub stdout: std::io::Stdout,
+    pub stdout: SessionStdout,
     /// For display output, only read by humans
-    pub stderr: std::io::Stderr,
+    pub stderr: SessionStderr,
     initial_input: Option<String>,
     /// Whether we're starting a new conversation or continuing an old one.
     existing_conversation: bool,
@@ -489,19 +642,27 @@ pub struct ChatSession {
     /// Pending prompts to be sent
     pending_prompts: VecDeque<Prompt>,
     interactive: bool,
+    /// Machine readable events, written to stdout in place of the styled output if enabled.
+    events: EventWriter,
+    /// How many times in a row the model continued a response because a stop hook blocked it from
+    /// ending, up to [MAX_STOP_HOOK_CONTINUATIONS].
+    stop_hook_continuations: usize,
+    /// Whether automatic compaction is suspended until the user sends the next message, after an
+    /// automatic compaction failed or was interrupted.
+    auto_compact_suspended: bool,
     inner: Option<ChatState>,
 }
 
 impl ChatSession {
-    #[allow(clippy::too_many_arguments)]
+    #[allow(clippy::too_many_arguments, clippy::option_option)]
     pub async fn new(
         os: &mut Os,
         stdout: std::io::Stdout,
         stderr: std::io::Stderr,
         conversation_id: &str,
         mut input: Option<String>,
-        input_source: InputSource,
-        resume_conversation: bool,
+        mut input_source: InputSource,
+        resume: Option<Option<String>>,
         terminal_width_provider: fn() -> Option<usize>,
         tool_manager: ToolManager,
         profile: Option<String>,
@@ -509,6 +670,7 @@ impl ChatSession {
         tool_config: HashMap<String, ToolSpec>,
         tool_permissions: ToolPermissions,
         interactive: bool,
+        output_format: ChatOutputFormat,
     ) -> Result<Self> {
         let valid_model_id = model_id
             .or_else(|| {
@@ -524,22 +686,21 @@ impl ChatSession {
             })
             .unwrap_or_else(|| default_model_id(os).to_owned());
 
-        // Reload prior conversation
+        // Reload prior conversation, either the one of the given session or of this directory
         let mut existing_conversation = false;
-        let previous_conversation = std::env::current_dir()
-            .ok()
-            .and_then(|cwd| os.database.get_conversation_by_path(cwd).ok())
-            .flatten();
+        let previous_conversation = match &resume {
+            Some(Some(session_id)) => os.database.get_session(session_id).ok().flatten(),
+            Some(None) => std::env::current_dir()
+                .ok()
+                .and_then(|cwd| os.database.get_conversation_by_path(cwd).ok())
+                .flatten(),
+            None => None,
+        };
 
         // Only restore conversations where there were actual messages.
         // Prevents edge case where user clears conversation then exits without chatting.
-        let conversation = match resume_conversation
-            && previous_conversation
-                .as_ref()
-                .is_some_and(|cs| !cs.history().is_empty())
-        {
-            true => {
-                let mut cs = previous_conversation.unwrap();
+        let conversation = match previous_conversation.filter(|cs| !cs.history().is_empty()) {
+            Some(mut cs) => {
                 existing_conversation = true;
                 cs.reload_serialized_state(os).await;
                 input = Some(input.unwrap_or("In a few words, summarize our conversation so far.".to_owned()));
@@ -548,7 +709,7 @@ impl ChatSession {
                 cs.enforce_tool_use_history_invariants();
                 cs
             },
-            false => {
+            None => {
                 ConversationState::new(
                     os,
                     conversation_id,
@@ -561,6 +722,27 @@ impl ChatSession {
             },
         };
 
+        if let Some(printer) = input_source.external_printer() {
+            conversation.tool_manager.prompt_notifier.set_printer(printer);
+        }
+
+        // In server mode, stdout is reserved for protocol messages and the output for humans is
+        // sent along with them.
+        let (stdout, stderr, events) = match input_source.server() {
+            Some(connection) => (
+                SessionStdout::Discard,
+                SessionStderr::Capture(connection.capture()),
+                EventWriter::notifications(server::EVENT_METHOD),
+            ),
+            None => {
+                let events = EventWriter::new(output_format);
+                let stdout = match events.is_enabled() {
+                    true => SessionStdout::Discard,
+                    false => SessionStdout::Stdout(stdout),
+                };
+                (stdout, SessionStderr::Stderr(stderr), events)
+            },
+        };
         Ok(Self {
             stdout,
             stderr,
@@ -578,6 +760,9 @@ impl ChatSession {
             failed_request_ids: Vec::new(),
             pending_prompts: VecDeque::new(),
             interactive,
+            events,
+            stop_hook_continuations: 0,
+            auto_compact_suspended: false,
             inner: Some(ChatState::default()),
         })
     }
@@ -586,6 +771,9 @@ impl ChatSession {
         // Update conversation state with new tool information
         self.conversation.update_state(false).await;
 
+        // Attend to the requests mcp servers have made while the previous state was handled
+        self.serve_server_requests(os).await?;
+
         let ctrl_c_stream = ctrl_c();
         let result = match self.inner.take().expect("state must always be Some") {
             ChatState::PromptUser { skip_printing_tools } => {
@@ -595,6 +783,13 @@ impl ChatSession {
                         return Ok(());
                     },
                     (false, false) => {
+                        for tool in self.tool_uses.iter().filter(|tool| !tool.accepted) {
+                            self.events.emit(OutputEvent::ToolResult {
+                                tool_use_id: tool.id.clone(),
+                                status: ToolResultEventStatus::Denied,
+                                content: vec![ChatError::NonInteractiveToolApproval.to_string().into()],
+                            })?;
+                        }
                         return Err(ChatError::NonInteractiveToolApproval);
                     },
                     _ => (),
@@ -608,9 +803,13 @@ impl ChatSession {
                     Ok(_) = ctrl_c_stream => Err(ChatError::Interrupted { tool_uses: Some(self.tool_uses.clone()) })
                 }
             },
-            ChatState::CompactHistory { prompt, show_summary } => {
+            ChatState::CompactHistory {
+                prompt,
+                show_summary,
+                strategy,
+            } => {
                 tokio::select! {
-                    res = self.compact_history(os, prompt, show_summary) => res,
+                    res = self.compact_history(os, prompt, show_summary, strategy) => res,
                     Ok(_) = ctrl_c_stream => Err(ChatError::Interrupted { tool_uses: Some(self.tool_uses.clone()) })
                 }
             },
@@ -647,6 +846,7 @@ impl ChatSession {
 
         // We encountered an error. Handle it.
         error!(?err, "An error occurred processing the current state");
+        self.events.record_error(err.to_string());
         let (reason, reason_desc) = get_error_reason(&err);
         self.send_error_telemetry(os, reason, Some(reason_desc), err.status_code())
             .await;
@@ -714,6 +914,7 @@ impl ChatSession {
                     self.inner = Some(ChatState::CompactHistory {
                         prompt: None,
                         show_summary: false,
+                        strategy: None,
                     });
 
                     (
@@ -880,6 +1081,8 @@ enum ChatState {
         prompt: Option<String>,
         /// Whether or not the summary should be shown on compact success.
         show_summary: bool,
+        /// Strategy to compact with, instead of the one of the conversation.
+        strategy: Option<CompactionStrategy>,
     },
     /// Exit the chat.
     Exit,
@@ -971,6 +1174,12 @@ impl ChatSession {
             }
         }
 
+        self.events.emit(OutputEvent::Init {
+            conversation_id: self.conversation.conversation_id().to_string(),
+            model: self.conversation.model.clone(),
+        })?;
+
+        self.review_workspace_commands()?;
         if let Some(user_input) = self.initial_input.take() {
             self.inner = Some(ChatState::HandleInput { input: user_input });
         }
@@ -979,11 +1188,19 @@ impl ChatSession {
             self.next(os).await?;
         }
 
+        // The session is over either way, so failing hooks must not turn its end into an error.
+        if let Err(err) = self
+            .run_event_hooks(os, HookTrigger::SessionEnd, None, serde_json::json!({}))
+            .await
+        {
+            error!(?err, "failed to run the session end hooks");
+        }
+
         Ok(())
     }
 
-    /// Compacts the conversation history, replacing the history with a summary generated by the
-    /// model.
+    /// Compacts the conversation history with `strategy`, or the strategy of the conversation,
+    /// e.g. replacing the history with a summary generated by the model.
     ///
     /// The last two user messages in the history are not included in the compaction process.
     async fn compact_history(
@@ -991,6 +1208,7 @@ impl ChatSession {
         os: &Os,
         custom_prompt: Option<String>,
         show_summary: bool,
+        strategy: Option<CompactionStrategy>,
     ) -> Result<ChatState, ChatError> {
         let hist = self.conversation.history();
         debug!(?hist, "compacting history");
@@ -1008,6 +1226,37 @@ impl ChatSession {
             });
         }
 
+        let strategy = strategy.unwrap_or(self.conversation.compaction.strategy);
+        if strategy != CompactionStrategy::Summarize {
+            let used_tokens = *self.conversation.calculate_token_count(os).await?;
+            let tokens = compaction::tokens_to_free(used_tokens, self.conversation.context_window_tokens());
+            let (count, freed) = match strategy {
+                CompactionStrategy::TruncateToolResults => self.conversation.truncate_tool_results(tokens),
+                _ => self.conversation.remove_oldest_messages(tokens),
+            };
+            let what = match strategy {
+                CompactionStrategy::TruncateToolResults => "Truncated the oldest tool results",
+                _ => "Removed the oldest messages",
+            };
+            execute!(
+                self.stderr,
+                style::SetForegroundColor(Color::Green),
+                style::Print(format!("\n✔ {what} ({count}), freeing {freed} tokens\n")),
+                style::SetForegroundColor(Color::Reset)
+            )?;
+
+            // The summary needs at least two messages to summarize
+            if freed >= tokens || self.conversation.history().len() < 2 {
+                return self.resume_after_compaction(os).await;
+            }
+            execute!(
+                self.stderr,
+                style::SetForegroundColor(Color::DarkGrey),
+                style::Print("That was not enough, summarizing the history as well.\n"),
+                style::SetForegroundColor(Color::Reset)
+            )?;
+        }
+
         // Send a request for summarizing the history.
         let summary_state = self
             .conversation
@@ -1015,7 +1264,9 @@ impl ChatSession {
             .await?;
 
         execute!(self.stderr, cursor::Hide, style::Print("\n"))?;
-        self.spinner = Some(Spinner::new(Spinners::Dots, "Creating summary...".to_string()));
+        if self.spinner_enabled() {
+            self.spinner = Some(Spinner::new(Spinners::Dots, "Creating summary...".to_string()));
+        }
 
         let response = os.client.send_message(summary_state).await;
 
@@ -1159,6 +1410,13 @@ impl ChatSession {
             }
         }
 
+        self.resume_after_compaction(os).await
+    }
+
+    /// Sends the next message after the history was compacted, if any, or returns to the prompt.
+    async fn resume_after_compaction(&mut self, os: &Os) -> Result<ChatState, ChatError> {
+        self.auto_compact_suspended = false;
+
         // If a next message is set, then retry the request.
         if self.conversation.next_user_message().is_some() {
             Ok(ChatState::HandleResponseStream(
@@ -1178,12 +1436,62 @@ impl ChatSession {
         }
     }
 
+    /// Prompts the user about the requests mcp servers have made and sends the outcome back to
+    /// them.
+    async fn serve_server_requests(&mut self, os: &Os) -> Result<(), ChatError> {
+        if let Some(receiver) = self.conversation.tool_manager.server_requests.as_mut() {
+            while let Ok(request) = receiver.try_recv() {
+                server_requests::handle_pending_request(
+                    os,
+                    &mut self.stderr,
+                    &mut self.input_source,
+                    self.interactive,
+                    self.conversation.model.clone(),
+                    request,
+                )
+                .await?;
+            }
+        }
+        Ok(())
+    }
+
     /// Read input from the user.
     async fn prompt_user(&mut self, os: &Os, skip_printing_tools: bool) -> Result<ChatState, ChatError> {
         execute!(self.stderr, cursor::Show)?;
 
+        // Keep the roots exposed to mcp servers in line with the context
+        if let (Some(context_manager), Ok(cwd)) = (&self.conversation.context_manager, os.env.current_dir()) {
+            let paths = context_manager
+                .configs()
+                .into_iter()
+                .flat_map(|(_, config)| config.paths.iter().cloned())
+                .collect::<Vec<_>>();
+            self.conversation
+                .tool_manager
+                .update_roots(server_requests::resolve_roots(&cwd, &paths));
+        }
+        for (server_name, uri) in self.conversation.tool_manager.take_updated_resources() {
+            queue!(
+                self.stderr,
+                style::SetForegroundColor(Color::DarkGrey),
+                style::Print("Resource "),
+                style::SetForegroundColor(Color::Cyan),
+                style::Print(format!("@{server_name}:{uri}")),
+                style::SetForegroundColor(Color::DarkGrey),
+                style::Print(" has been updated. Mention it again to share its latest contents.\n"),
+                style::SetForegroundColor(Color::Reset),
+            )?;
+        }
+
+        // The workspace configuration may have changed since it was reviewed
+        self.review_workspace_commands()?;
+
         // Check token usage and display warnings if needed
         if self.pending_tool_index.is_none() {
+            if let Some(state) = self.auto_compact(os).await? {
+                return Ok(state);
+            }
+
             // Only display warnings when not waiting for tool approval
             if self.conversation.can_create_summary_request(os).await? {
                 if let Err(err) = self.display_char_warnings(os).await {
@@ -1245,7 +1553,30 @@ impl ChatSession {
             style::SetAttribute(Attribute::Reset)
         )?;
         let prompt = self.generate_tool_trust_prompt();
-        let user_input = match self.read_user_input(&prompt, false) {
+        if let Some(connection) = self.input_source.server_mut() {
+            connection.set_state(match self.pending_tool_index.and_then(|i| self.tool_uses.get(i)) {
+                Some(tool) => server::SessionState::AwaitingApproval {
+                    tool: server::PendingTool {
+                        id: tool.id.clone(),
+                        name: tool.name.clone(),
+                        args: tool.args.clone(),
+                    },
+                },
+                _ => server::SessionState::AwaitingPrompt,
+            });
+        }
+        // Requests of mcp servers made while the user is typing are announced, and are served
+        // once the input is submitted, even if it is empty
+        let prompt_notifier = self.conversation.tool_manager.prompt_notifier.clone();
+        prompt_notifier.set_at_prompt(true);
+        let user_input = self.read_user_input(&prompt, false);
+        prompt_notifier.set_at_prompt(false);
+        let user_input = match user_input {
+            Some(input) if input.trim().is_empty() => {
+                return Ok(ChatState::PromptUser {
+                    skip_printing_tools: false,
+                });
+            },
             Some(input) => input,
             None => return Ok(ChatState::Exit),
         };
@@ -1256,6 +1587,11 @@ impl ChatSession {
 
     async fn handle_input(&mut self, os: &mut Os, mut user_input: String) -> Result<ChatState, ChatError> {
         queue!(self.stderr, style::Print('\n'))?;
+        self.auto_compact_suspended = false;
+        self.stop_hook_continuations = 0;
+        if let Some(context_manager) = self.conversation.context_manager.as_mut() {
+            context_manager.clear_rule_outputs();
+        }
 
         let input = user_input.trim();
 
@@ -1339,7 +1675,14 @@ impl ChatSession {
             Ok(ChatState::PromptUser {
                 skip_printing_tools: false,
             })
-        } else if let Some(command) = input.strip_prefix("@") {
+        } else if let Some(command) = input.strip_prefix("@").filter(|command| {
+            // Messages that lead with a resource mention are not prompt invocations
+            let first_word = command.split_whitespace().next().unwrap_or_default();
+            resources::find_resource_mentions(&format!("@{first_word}"), |name| {
+                self.conversation.tool_manager.clients.contains_key(name)
+            })
+            .is_empty()
+        }) {
             let input_parts =
                 shlex::split(command).ok_or(ChatError::Custom("Error splitting prompt command".into()))?;
 
@@ -1356,7 +1699,7 @@ impl ChatSession {
                 name: prompt_name,
                 arguments,
             };
-            return subcommand.execute(self).await;
+            return subcommand.execute(os, self).await;
         } else if let Some(command) = input.strip_prefix("!") {
             // Use platform-appropriate shell
             let result = if cfg!(target_os = "windows") {
@@ -1427,6 +1770,7 @@ impl ChatSession {
                 };
                 self.conversation.abandon_tool_use(&self.tool_uses, user_input);
             } else {
+                let user_input = resources::inline_resource_mentions(self, user_input).await?;
                 self.conversation.set_next_user_message(user_input).await;
             }
 
@@ -1441,7 +1785,7 @@ impl ChatSession {
             queue!(self.stderr, cursor::Hide)?;
             execute!(self.stderr, style::Print("\n"))?;
 
-            if self.interactive {
+            if self.spinner_enabled() {
                 self.spinner = Some(Spinner::new(Spinners::Dots, "Thinking...".to_owned()));
             }
 
@@ -1454,6 +1798,10 @@ impl ChatSession {
     async fn tool_use_execute(&mut self, os: &mut Os) -> Result<ChatState, ChatError> {
         // Verify tools have permissions.
         for i in 0..self.tool_uses.len() {
+            let allowed_by_rule = matches!(
+                self.tool_permission_rule(os, &self.tool_uses[i].name, Some(&self.tool_uses[i].args)),
+                Some((RuleEffect::Allow, _))
+            );
             let tool = &mut self.tool_uses[i];
 
             // Manually accepted by the user or otherwise verified already.
@@ -1463,6 +1811,7 @@ impl ChatSession {
 
             // If there is an override, we will use it. Otherwise fall back to Tool's default.
             let allowed = self.tool_permissions.trust_all
+                || allowed_by_rule
                 || (self.tool_permissions.has(&tool.name) && self.tool_permissions.is_trusted(&tool.name))
                 || !tool.tool.requires_acceptance(os);
 
@@ -1500,8 +1849,50 @@ impl ChatSession {
             let mut tool_telemetry = self.tool_use_telemetry_events.entry(tool.id.clone());
             tool_telemetry = tool_telemetry.and_modify(|ev| ev.is_accepted = true);
 
+            // Snapshot the files about to be written so that the change can be undone.
+            let checkpoint = match &tool.tool {
+                Tool::FsWrite(fs_write) => {
+                    let description = format!("{} {}", tool.name, fs_write.command());
+                    match self
+                        .conversation
+                        .checkpoints
+                        .snapshot(os, description, &fs_write.paths(os))
+                        .await
+                    {
+                        Ok(id) => Some(id),
+                        Err(err) => {
+                            warn!(?err, "Failed to create a checkpoint");
+                            None
+                        },
+                    }
+                },
+                _ => None,
+            };
+
             let tool_start = std::time::Instant::now();
-            let invoke_result = tool.tool.invoke(os, &mut self.stdout).await;
+            let invoke_result = {
+                // Servers may need the user to weigh in on requests of their own (e.g. sampling)
+                // before they can finish the tool call.
+                let model_id = self.conversation.model.clone();
+                let invoke = tool.tool.invoke(os, &mut self.stdout);
+                tokio::pin!(invoke);
+                loop {
+                    tokio::select! {
+                        res = &mut invoke => break res,
+                        Some(request) = server_requests::recv_pending_request(&mut self.conversation.tool_manager.server_requests) => {
+                            server_requests::handle_pending_request(
+                                os,
+                                &mut self.stderr,
+                                &mut self.input_source,
+                                self.interactive,
+                                model_id.clone(),
+                                request,
+                            )
+                            .await?;
+                        },
+                    }
+                }
+            };
 
             if self.spinner.is_some() {
                 queue!(
@@ -1561,6 +1952,9 @@ impl ChatSession {
                 },
                 Err(err) => {
                     error!(?err, "An error occurred processing the tool");
+                    if let Some(id) = checkpoint {
+                        self.conversation.checkpoints.discard(os, id).await;
+                    }
                     execute!(
                         self.stderr,
                         style::Print(CONTINUATION_LINE),
@@ -1595,6 +1989,50 @@ impl ChatSession {
             }
         }
 
+        // Hooks may add feedback to the results, e.g. the output of a linter run on written files.
+        for result in &mut tool_results {
+            let Some(tool) = self.tool_uses.iter().find(|tool| tool.id == result.tool_use_id) else {
+                continue;
+            };
+            let tool_name = tool.name.clone();
+            let fields = serde_json::json!({
+                "tool_name": tool.name,
+                "tool_input": tool.args,
+                "tool_response": compaction::tool_result_text(result),
+                "tool_status": match result.status {
+                    ToolResultStatus::Success => "success",
+                    ToolResultStatus::Error => "error",
+                },
+            });
+            let (_, outcomes) = self
+                .run_event_hooks(os, HookTrigger::PostToolUse, Some(&tool_name), fields)
+                .await?;
+            for (hook, outcome) in outcomes {
+                match outcome {
+                    HookOutcome::Success(stdout) if stdout.is_empty() => (),
+                    HookOutcome::Success(stdout) => result.content.push(ToolUseResultBlock::Text(format!(
+                        "Output of the hook '{}':\n{stdout}",
+                        hook.name
+                    ))),
+                    HookOutcome::Blocked(reason) => {
+                        result.content.push(ToolUseResultBlock::Text(format!(
+                            "The hook '{}' reported a problem:\n{reason}",
+                            hook.name
+                        )));
+                        result.status = ToolResultStatus::Error;
+                    },
+                }
+            }
+        }
+
+        for result in &tool_results {
+            let status = match result.status {
+                ToolResultStatus::Success => ToolResultEventStatus::Success,
+                ToolResultStatus::Error => ToolResultEventStatus::Error,
+            };
+            self.events.emit_tool_result(result, status)?;
+        }
+
         if !image_blocks.is_empty() {
             let images = image_blocks.into_iter().map(|(block, _)| block).collect();
             self.conversation.add_tool_results_with_images(tool_results, images);
@@ -1608,9 +2046,15 @@ impl ChatSession {
             self.conversation.add_tool_results(tool_results);
         }
 
+        // The tool results are sent after the history is compacted
+        if let Some(state) = self.auto_compact(os).await? {
+            self.send_tool_use_telemetry(os).await;
+            return Ok(state);
+        }
+
         execute!(self.stderr, cursor::Hide)?;
         execute!(self.stderr, style::Print("\n"), style::SetAttribute(Attribute::Reset))?;
-        if self.interactive {
+        if self.spinner_enabled() {
             self.spinner = Some(Spinner::new(Spinners::Dots, "Thinking...".to_string()));
         }
 
@@ -1633,6 +2077,7 @@ impl ChatSession {
         let mut ended = false;
         let mut parser = ResponseParser::new(response);
         let mut state = ParseState::new(Some(self.terminal_width()));
+        state.syntax_highlighting = tools::supports_truecolor(os);
         let mut response_prefix_printed = false;
 
         let mut tool_uses = Vec::new();
@@ -1660,6 +2105,7 @@ impl ChatSession {
                             // We need to flush the buffer here, otherwise text will not be
                             // printed while we are receiving tool use events.
                             buf.push('\n');
+                            self.events.emit(OutputEvent::ToolUseStart { name: name.clone() })?;
                             tool_name_being_recvd = Some(name);
                         },
                         parser::ResponseEvent::AssistantText(text) => {
@@ -1675,6 +2121,7 @@ impl ChatSession {
                                 response_prefix_printed = true;
                             }
                             buf.push_str(&text);
+                            self.events.emit(OutputEvent::AssistantText { text })?;
                         },
                         parser::ResponseEvent::ToolUse(tool_use) => {
                             if self.spinner.is_some() {
@@ -1686,6 +2133,11 @@ impl ChatSession {
                                     cursor::Show
                                 )?;
                             }
+                            self.events.emit(OutputEvent::ToolUse {
+                                id: tool_use.id.clone(),
+                                name: tool_use.name.clone(),
+                                args: tool_use.args.clone(),
+                            })?;
                             tool_uses.push(tool_use);
                             tool_name_being_recvd = None;
                         },
@@ -1695,7 +2147,22 @@ impl ChatSession {
                             if message.content() == RESPONSE_TIMEOUT_CONTENT {
                                 error!(?request_id, ?message, "Encountered an unexpected model response");
                             }
+                            let response_tokens = TokenCounter::count_tokens(message.content());
                             self.conversation.push_assistant_message(os, message);
+                            if self.events.is_enabled() {
+                                let conversation_tokens: TokenCount = self
+                                    .conversation
+                                    .history()
+                                    .iter()
+                                    .map(|(user, assistant)| user.token_count() + assistant.token_count())
+                                    .sum();
+                                self.events.emit(OutputEvent::Usage {
+                                    request_id: request_id.clone(),
+                                    response_tokens,
+                                    conversation_tokens: conversation_tokens.value(),
+                                    context_window_tokens: self.conversation.context_window_tokens(),
+                                })?;
+                            }
                             ended = true;
                         },
                     }
@@ -1726,7 +2193,10 @@ impl ChatSession {
                             );
 
                             execute!(self.stderr, cursor::Hide)?;
-                            self.spinner = Some(Spinner::new(Spinners::Dots, "Dividing up the work...".to_string()));
+                            if self.spinner_enabled() {
+                                self.spinner =
+                                    Some(Spinner::new(Spinners::Dots, "Dividing up the work...".to_string()));
+                            }
 
                             // For stream timeouts, we'll tell the model to try and split its response into
                             // smaller chunks.
@@ -1791,6 +2261,7 @@ impl ChatSession {
             // still left in the buffer. I'm not sure how this is intended to be handled.
             if ended {
                 buf.push('\n');
+                state.input_ended = true;
             }
 
             if tool_name_being_recvd.is_none() && !buf.is_empty() && self.spinner.is_some() {
@@ -1805,18 +2276,13 @@ impl ChatSession {
 
             // Print the response for normal cases
             loop {
-                let input = Partial::new(&buf[offset..]);
-                match interpret_markdown(input, &mut self.stdout, &mut state) {
-                    Ok(parsed) => {
-                        offset += parsed.offset_from(&input);
+                match render_markdown(&buf[offset..], &mut self.stdout, &mut state) {
+                    Ok(Some(parsed)) => {
+                        offset += parsed;
                         self.stdout.flush()?;
-                        state.newline = state.set_newline;
-                        state.set_newline = false;
-                    },
-                    Err(err) => match err.into_inner() {
-                        Some(err) => return Err(ChatError::Custom(err.to_string().into())),
-                        None => break, // Data was incomplete
                     },
+                    Ok(None) => break, // Data was incomplete
+                    Err(err) => return Err(ChatError::Custom(err.to_string().into())),
                 }
 
                 // TODO: We should buffer output based on how much we have to parse, not as a constant
@@ -1827,7 +2293,7 @@ impl ChatSession {
             // Set spinner after showing all of the assistant text content so far.
             if tool_name_being_recvd.is_some() {
                 queue!(self.stderr, cursor::Hide)?;
-                if self.interactive {
+                if self.spinner_enabled() {
                     self.spinner = Some(Spinner::new(Spinners::Dots, "Thinking...".to_string()));
                 }
             }
@@ -1871,21 +2337,145 @@ impl ChatSession {
             self.tool_uses.clear();
             self.pending_tool_index = None;
 
+            // Stop hooks may have the model continue, e.g. until the tests pass.
+            let fields = serde_json::json!({ "stop_hook_active": self.stop_hook_continuations > 0 });
+            let (_, outcomes) = self.run_event_hooks(os, HookTrigger::Stop, None, fields).await?;
+            let continuations = std::mem::take(&mut self.stop_hook_continuations);
+            if let Some((hook, HookOutcome::Blocked(reason))) = outcomes.last() {
+                if continuations >= MAX_STOP_HOOK_CONTINUATIONS {
+                    execute!(
+                        self.stderr,
+                        style::SetForegroundColor(Color::Yellow),
+                        style::Print(format!(
+                            "\nThe hook '{}' did not let the response end {} times in a row, ending it anyway.\n",
+                            hook.name, MAX_STOP_HOOK_CONTINUATIONS
+                        )),
+                        style::SetForegroundColor(Color::Reset),
+                    )?;
+                    return Ok(ChatState::PromptUser {
+                        skip_printing_tools: false,
+                    });
+                }
+
+                let prompt = format!("The hook '{}' did not let the response end:\n{reason}", hook.name);
+                self.stop_hook_continuations = continuations + 1;
+                self.conversation.set_next_user_message(prompt).await;
+                let conv_state = self
+                    .conversation
+                    .as_sendable_conversation_state(os, &mut self.stderr, false)
+                    .await?;
+
+                execute!(self.stderr, cursor::Hide, style::Print("\n"))?;
+                if self.spinner_enabled() {
+                    self.spinner = Some(Spinner::new(Spinners::Dots, "Thinking...".to_owned()));
+                }
+                return Ok(ChatState::HandleResponseStream(
+                    os.client.send_message(conv_state).await?,
+                ));
+            }
+
             Ok(ChatState::PromptUser {
                 skip_printing_tools: false,
             })
         }
     }
 
+    /// Runs the event hooks of `trigger` that match `tool_name`, with `fields` added to the input
+    /// they receive on stdin. Returns the input as rewritten by pre tool use hooks, along with the
+    /// outcome of each hook that ran.
+    async fn run_event_hooks(
+        &mut self,
+        os: &Os,
+        trigger: HookTrigger,
+        tool_name: Option<&str>,
+        fields: serde_json::Value,
+    ) -> Result<(serde_json::Value, Vec<(Hook, HookOutcome)>), ChatError> {
+        let mut input = serde_json::json!({
+            "hook_event_name": trigger,
+            "conversation_id": self.conversation.conversation_id(),
+            "cwd": os.env.current_dir().unwrap_or_default(),
+        });
+        if let (Some(input), serde_json::Value::Object(fields)) = (input.as_object_mut(), fields) {
+            input.extend(fields);
+        }
+
+        let hooks = match &self.conversation.context_manager {
+            Some(context_manager) => context_manager.event_hooks(trigger, tool_name),
+            None => Vec::new(),
+        };
+        if hooks.is_empty() {
+            return Ok((input, Vec::new()));
+        }
+
+        // The spinner would overwrite the output of the hooks
+        if let Some(mut spinner) = self.spinner.take() {
+            spinner.stop();
+            execute!(
+                self.stderr,
+                terminal::Clear(terminal::ClearType::CurrentLine),
+                cursor::MoveToColumn(0),
+            )?;
+        }
+        let outcomes = hooks::run_event_hooks(&hooks, &mut input, &mut self.stderr).await?;
+        Ok((input, outcomes))
+    }
+
+    /// Finds the permission rule deciding a use of `tool_name` with `args` among the rules of the
+    /// session and those of the global, workspace and profile context configs, of which the
+    /// workspace allow rules only apply once the user allowed them. Without `args`, only rules that
+    /// do not inspect arguments are considered.
+    fn tool_permission_rule(
+        &self,
+        os: &Os,
+        tool_name: &str,
+        args: Option<&serde_json::Value>,
+    ) -> Option<(RuleEffect, String)> {
+        let config_rules = self
+            .conversation
+            .context_manager
+            .as_ref()
+            .map(|context_manager| context_manager.tool_permission_rules())
+            .unwrap_or_default();
+        let mut rule_sets = vec![&self.tool_permissions.rules];
+        rule_sets.extend(config_rules.iter().map(AsRef::as_ref));
+        let cwd = os.env.current_dir().unwrap_or_default();
+        evaluate_rules(&rule_sets, tool_name, args, &cwd).map(|(effect, rule)| (effect, rule.to_string()))
+    }
+
     async fn validate_tools(&mut self, os: &Os, tool_uses: Vec<AssistantToolUse>) -> Result<ChatState, ChatError> {
         let conv_id = self.conversation.conversation_id().to_owned();
         debug!(?tool_uses, "Validating tool uses");
         let mut queued_tools: Vec<QueuedTool> = Vec::new();
         let mut tool_results: Vec<ToolUseResult> = Vec::new();
+        let mut denied_tool_use_ids: Vec<String> = Vec::new();
+
+        for mut tool_use in tool_uses {
+            // Hooks may block the tool use, or rewrite its input before it is validated and checked
+            // against the permission rules.
+            let fields = serde_json::json!({ "tool_name": tool_use.name, "tool_input": tool_use.args });
+            let (input, outcomes) = self
+                .run_event_hooks(os, HookTrigger::PreToolUse, Some(&tool_use.name), fields)
+                .await?;
+            if let Some((hook, HookOutcome::Blocked(reason))) = outcomes.last() {
+                denied_tool_use_ids.push(tool_use.id.clone());
+                tool_results.push(ToolUseResult {
+                    tool_use_id: tool_use.id.clone(),
+                    content: vec![ToolUseResultBlock::Text(format!(
+                        "Tool use was blocked by the hook '{}': {reason}",
+                        hook.name
+                    ))],
+                    status: ToolResultStatus::Error,
+                });
+                continue;
+            }
+            if let Some(tool_input) = input.get("tool_input").filter(|input| **input != tool_use.args) {
+                tool_use.args = tool_input.clone();
+                self.conversation.set_tool_use_args(&tool_use.id, tool_input.clone());
+            }
 
-        for tool_use in tool_uses {
             let tool_use_id = tool_use.id.clone();
             let tool_use_name = tool_use.name.clone();
+            let tool_use_args = tool_use.args.clone();
             let mut tool_telemetry =
                 ToolUseEventBuilder::new(conv_id.clone(), tool_use.id.clone(), self.conversation.model.clone())
                     .set_tool_use_id(tool_use_id.clone())
@@ -1894,17 +2484,31 @@ impl ChatSession {
             match self.conversation.tool_manager.get_tool_from_tool_use(tool_use) {
                 Ok(mut tool) => {
                     // Apply non-Q-generated context to tools
-                    self.contextualize_tool(&mut tool);
+                    self.contextualize_tool(os, &mut tool);
 
                     match tool.validate(os).await {
                         Ok(()) => {
                             tool_telemetry.is_valid = Some(true);
-                            queued_tools.push(QueuedTool {
-                                id: tool_use_id.clone(),
-                                name: tool_use_name,
-                                tool,
-                                accepted: false,
-                            });
+                            if let Some((RuleEffect::Deny, rule)) =
+                                self.tool_permission_rule(os, &tool_use_name, Some(&tool_use_args))
+                            {
+                                denied_tool_use_ids.push(tool_use_id.clone());
+                                tool_results.push(ToolUseResult {
+                                    tool_use_id: tool_use_id.clone(),
+                                    content: vec![ToolUseResultBlock::Text(format!(
+                                        "Tool use was denied by the permission rule '{rule}'"
+                                    ))],
+                                    status: ToolResultStatus::Error,
+                                });
+                            } else {
+                                queued_tools.push(QueuedTool {
+                                    id: tool_use_id.clone(),
+                                    name: tool_use_name,
+                                    tool,
+                                    accepted: false,
+                                    args: tool_use_args,
+                                });
+                            }
                         },
                         Err(err) => {
                             tool_telemetry.is_valid = Some(false);
@@ -1936,6 +2540,11 @@ impl ChatSession {
                 style::SetAttribute(Attribute::Reset),
             )?;
             for tool_result in &tool_results {
+                let status = match denied_tool_use_ids.contains(&tool_result.tool_use_id) {
+                    true => ToolResultEventStatus::Denied,
+                    false => ToolResultEventStatus::Error,
+                };
+                self.events.emit_tool_result(tool_result, status)?;
                 for block in &tool_result.content {
                     let content: Option<Cow<'_, str>> = match block {
                         ToolUseResultBlock::Text(t) => Some(t.as_str().into()),
@@ -1985,7 +2594,17 @@ impl ChatSession {
     // We cannot attach this any other way because Tools are constructed by deserializing
     // output from Amazon Q.
     // TODO: Is there a better way?
-    fn contextualize_tool(&self, tool: &mut Tool) {
+    fn contextualize_tool(&self, os: &Os, tool: &mut Tool) {
+        if let Tool::ExecuteCommand(execute_command) = tool {
+            let sandbox_config = self
+                .conversation
+                .context_manager
+                .as_ref()
+                .and_then(|context_manager| context_manager.sandbox_config());
+            if let (Some((_, config)), Ok(cwd)) = (sandbox_config, os.env.current_dir()) {
+                execute_command.sandbox = Sandbox::from_config(&config, &cwd);
+            }
+        }
         if let Tool::GhIssue(gh_issue) = tool {
             gh_issue.set_context(GhIssueContext {
                 // Ideally we avoid cloning, but this function is not called very often.
@@ -2046,7 +2665,13 @@ impl ChatSession {
         loop {
             match (self.input_source.read_line(Some(prompt)), ctrl_c) {
                 (Ok(Some(line)), _) => {
-                    if line.trim().is_empty() {
+                    let tool_manager = &self.conversation.tool_manager;
+                    let requests_waiting = tool_manager.prompt_notifier.is_at_prompt()
+                        && tool_manager
+                            .server_requests
+                            .as_ref()
+                            .is_some_and(|receiver| !receiver.is_empty());
+                    if line.trim().is_empty() && !requests_waiting {
                         continue; // Reprompt if the input is empty
                     }
                     return Some(line);
@@ -2071,6 +2696,12 @@ impl ChatSession {
         }
     }
 
+    /// Whether to show spinners, which are written to stdout and so only fit sessions whose
+    /// stdout is read by humans.
+    fn spinner_enabled(&self) -> bool {
+        self.interactive && !self.events.is_enabled()
+    }
+
     /// Helper function to generate a prompt based on the current context
     fn generate_tool_trust_prompt(&mut self) -> String {
         let profile = self.conversation.current_profile().map(|s| s.to_string());
@@ -2100,6 +2731,129 @@ impl ChatSession {
         })
     }
 
+    /// Asks the user whether the commands of the hooks and `cmd:` context rules of the workspace
+    /// configuration may run and whether its tool allow rules apply, since it is checked in to the
+    /// repository, unless they were asked about them before. They do not apply in non-interactive
+    /// mode.
+    fn review_workspace_commands(&mut self) -> Result<(), ChatError> {
+        let Some(context_manager) = self.conversation.context_manager.as_mut() else {
+            return Ok(());
+        };
+        let commands = context_manager
+            .unreviewed_workspace_commands()
+            .into_iter()
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        let allow_rules = context_manager
+            .unreviewed_workspace_allow_rules()
+            .into_iter()
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        if commands.is_empty() && allow_rules.is_empty() {
+            return Ok(());
+        }
+        if !self.interactive {
+            context_manager.review_workspace_commands(false);
+            execute!(
+                self.stderr,
+                style::SetForegroundColor(Color::Yellow),
+                style::Print(format!(
+                    "The hooks, command context rules and tool allow rules of {WORKSPACE_CONFIG_PATH} do not apply in non-interactive mode.\n\n"
+                )),
+                style::SetForegroundColor(Color::Reset),
+            )?;
+            return Ok(());
+        }
+
+        if !commands.is_empty() {
+            queue!(
+                self.stderr,
+                style::SetForegroundColor(Color::DarkGrey),
+                style::Print(format!(
+                    "\nThe hooks and context rules of {WORKSPACE_CONFIG_PATH} run the following commands:\n"
+                )),
+                style::SetForegroundColor(Color::Reset),
+            )?;
+            for command in &commands {
+                queue!(self.stderr, style::Print(format!("  {command}\n")))?;
+            }
+        }
+        if !allow_rules.is_empty() {
+            queue!(
+                self.stderr,
+                style::SetForegroundColor(Color::DarkGrey),
+                style::Print(format!(
+                    "\n{WORKSPACE_CONFIG_PATH} lets these tool uses run without confirmation:\n"
+                )),
+                style::SetForegroundColor(Color::Reset),
+            )?;
+            for rule in &allow_rules {
+                queue!(self.stderr, style::Print(format!("  {rule}\n")))?;
+            }
+        }
+        execute!(
+            self.stderr,
+            style::SetForegroundColor(Color::DarkGrey),
+            style::Print("\nAllow them in this session? ["),
+            style::SetForegroundColor(Color::Green),
+            style::Print("y"),
+            style::SetForegroundColor(Color::DarkGrey),
+            style::Print("/"),
+            style::SetForegroundColor(Color::Green),
+            style::Print("n"),
+            style::SetForegroundColor(Color::DarkGrey),
+            style::Print("]:\n\n"),
+            style::SetForegroundColor(Color::Reset),
+        )?;
+
+        let user_input = self.read_user_input("> ", true).unwrap_or_default();
+        let allowed = ["y", "Y"].contains(&user_input.trim());
+        if let Some(context_manager) = self.conversation.context_manager.as_mut() {
+            context_manager.review_workspace_commands(allowed);
+        }
+        Ok(())
+    }
+
+    /// Returns [ChatState::CompactHistory] if the conversation uses more of the context window
+    /// than its auto compaction threshold. Automatic compaction is attempted only once until it
+    /// succeeds or the user sends a message, so that failing summary requests are not repeated.
+    async fn auto_compact(&mut self, os: &Os) -> Result<Option<ChatState>, ChatError> {
+        if self.conversation.compaction.auto_compact_percent.is_none() || self.auto_compact_suspended {
+            return Ok(None);
+        }
+
+        let used_tokens = *self.conversation.calculate_token_count(os).await?;
+        let context_window_tokens = self.conversation.context_window_tokens();
+        if !compaction::should_auto_compact(&self.conversation.compaction, used_tokens, context_window_tokens)
+            || !self.conversation.can_create_summary_request(os).await?
+        {
+            return Ok(None);
+        }
+        // Compacting is pointless if it is not the history that fills the context window, e.g.
+        // with large context files.
+        let tokens = compaction::tokens_to_free(used_tokens, context_window_tokens);
+        if compaction::history_tokens(self.conversation.history()) < tokens {
+            return Ok(None);
+        }
+
+        execute!(
+            self.stderr,
+            style::SetForegroundColor(Color::DarkGrey),
+            style::Print(format!(
+                "\nThe context window is {}% full, compacting the history...\n",
+                used_tokens * 100 / context_window_tokens.max(1)
+            )),
+            style::SetForegroundColor(Color::Reset)
+        )?;
+
+        self.auto_compact_suspended = true;
+        Ok(Some(ChatState::CompactHistory {
+            prompt: None,
+            show_summary: false,
+            strategy: None,
+        }))
+    }
+
     /// Display character limit warnings based on current conversation size
     async fn display_char_warnings(&mut self, os: &Os) -> Result<(), ChatError> {
         let warning_level = self.conversation.get_token_warning_level(os).await?;
@@ -2295,7 +3049,7 @@ mod tests {
                 "y".to_string(),
                 "exit".to_string(),
             ]),
-            false,
+            None,
             || Some(80),
             tool_manager,
             None,
@@ -2303,6 +3057,7 @@ mod tests {
             tool_config,
             ToolPermissions::new(0),
             true,
+            ChatOutputFormat::Text,
         )
         .await
         .unwrap()
@@ -2436,7 +3191,7 @@ mod tests {
                 "n".to_string(),             // cancel
                 "exit".to_string(),
             ]),
-            false,
+            None,
             || Some(80),
             tool_manager,
             None,
@@ -2444,6 +3199,7 @@ mod tests {
             tool_config,
             ToolPermissions::new(0),
             true,
+            ChatOutputFormat::Text,
         )
         .await
         .unwrap()
@@ -2531,7 +3287,7 @@ mod tests {
                 "y".to_string(),
                 "exit".to_string(),
             ]),
-            false,
+            None,
             || Some(80),
             tool_manager,
             None,
@@ -2539,6 +3295,7 @@ mod tests {
             tool_config,
             ToolPermissions::new(0),
             true,
+            ChatOutputFormat::Text,
         )
         .await
         .unwrap()
@@ -2605,7 +3362,7 @@ mod tests {
                 "create a new file".to_string(),
                 "exit".to_string(),
             ]),
-            false,
+            None,
             || Some(80),
             tool_manager,
             None,
@@ -2613,6 +3370,7 @@ mod tests {
             tool_config,
             ToolPermissions::new(0),
             true,
+            ChatOutputFormat::Text,
         )
         .await
         .unwrap()
@@ -2639,6 +3397,50 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_flow_failed_auto_compaction() {
+        let mut os = Os::new().await.unwrap();
+        os.database
+            .settings
+            .set(Setting::ChatAutoCompactThreshold, 1)
+            .await
+            .unwrap();
+        // The summary request fails, and the summary after it must not be requested until the user
+        // sends another message.
+        let mock_output = serde_json::json!([["lorem ipsum ".repeat(700)], ["Sure"], null, ["A summary"]]);
+        os.client.set_mock_output(mock_output);
+
+        let tool_manager = ToolManager::default();
+        let tool_config = serde_json::from_str::<HashMap<String, ToolSpec>>(include_str!("tools/tool_index.json"))
+            .expect("Tools failed to load");
+        let mut session = ChatSession::new(
+            &mut os,
+            std::io::stdout(),
+            std::io::stderr(),
+            "fake_conv_id",
+            None,
+            InputSource::new_mock(vec![
+                "write a lot".to_string(),
+                "thanks".to_string(),
+                "/quit".to_string(),
+            ]),
+            None,
+            || Some(80),
+            tool_manager,
+            None,
+            None,
+            tool_config,
+            ToolPermissions::new(0),
+            true,
+            ChatOutputFormat::Text,
+        )
+        .await
+        .unwrap();
+        session.spawn(&mut os).await.unwrap();
+
+        assert_eq!(session.conversation.history().len(), 2);
+    }
+
     #[tokio::test]
     async fn test_subscribe_flow() {
         let mut os = Os::new().await.unwrap();
@@ -2653,7 +3455,7 @@ mod tests {
             "fake_conv_id",
             None,
             InputSource::new_mock(vec!["/subscribe".to_string(), "y".to_string(), "/quit".to_string()]),
-            false,
+            None,
             || Some(80),
             tool_manager,
             None,
@@ -2661,6 +3463,7 @@ mod tests {
             tool_config,
             ToolPermissions::new(0),
             true,
+            ChatOutputFormat::Text,
         )
         .await
         .unwrap()
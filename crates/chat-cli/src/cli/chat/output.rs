@@ -0,0 +1,363 @@
+//! Machine readable output of non-interactive chat sessions, selected with `--output-format`.
+
+use std::io::{
+    self,
+    Write,
+};
+use std::sync::{
+    Arc,
+    Mutex,
+};
+
+use clap::ValueEnum;
+use serde::Serialize;
+
+use super::message::{
+    ToolUseResult,
+    ToolUseResultBlock,
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ChatOutputFormat {
+    /// Styled markdown, as shown in interactive sessions
+    #[default]
+    Text,
+    /// A single JSON object with every event and the final result, written once the chat ends
+    Json,
+    /// One JSON event per line, written as soon as it happens
+    StreamJson,
+}
+
+/// A single event of a chat session, mirroring the events of the response stream.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum OutputEvent {
+    /// The session started
+    Init {
+        conversation_id: String,
+        model: Option<String>,
+    },
+    /// A chunk of the text of an assistant response
+    AssistantText { text: String },
+    /// The model started sending a tool use
+    ToolUseStart { name: String },
+    /// The model requested a tool use
+    ToolUse {
+        id: String,
+        name: String,
+        args: serde_json::Value,
+    },
+    /// A tool use finished, failed, or was not allowed to run
+    ToolResult {
+        tool_use_id: String,
+        status: ToolResultEventStatus,
+        content: Vec<serde_json::Value>,
+    },
+    /// An assistant response ended. Tokens are counted with the tokenizer of the `chat.tokenizer`
+    /// setting, so they may differ slightly from the counts of the model.
+    Usage {
+        request_id: Option<String>,
+        response_tokens: usize,
+        conversation_tokens: usize,
+        context_window_tokens: usize,
+    },
+    /// The session ended, always the last event
+    Result {
+        status: ResultStatus,
+        conversation_id: String,
+        /// Text of the last assistant response
+        response: Option<String>,
+        /// Ids of the tool uses that failed or were denied
+        failed_tool_uses: Vec<String>,
+        error: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolResultEventStatus {
+    Success,
+    Error,
+    Denied,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResultStatus {
+    Success,
+    Error,
+}
+
+/// Writes the [OutputEvent]s of a session to stdout in the selected [ChatOutputFormat].
+#[derive(Debug)]
+pub struct EventWriter<W: Write = io::Stdout> {
+    format: ChatOutputFormat,
+    output: W,
+    /// Events held back until the end of the session, for [ChatOutputFormat::Json].
+    events: Vec<OutputEvent>,
+    failed_tool_uses: Vec<String>,
+    /// The last error the session recovered from by returning to the prompt.
+    error: Option<String>,
+    /// If set, events are wrapped in JSON-RPC notifications of this method.
+    notification_method: Option<&'static str>,
+}
+
+impl EventWriter {
+    pub fn new(format: ChatOutputFormat) -> Self {
+        Self::with_output(format, io::stdout())
+    }
+
+    /// Writes every event as a JSON-RPC notification of `method`, for `q chat --server`.
+    pub fn notifications(method: &'static str) -> Self {
+        Self {
+            notification_method: Some(method),
+            ..Self::new(ChatOutputFormat::StreamJson)
+        }
+    }
+}
+
+impl<W: Write> EventWriter<W> {
+    pub fn with_output(format: ChatOutputFormat, output: W) -> Self {
+        Self {
+            format,
+            output,
+            events: Vec::new(),
+            failed_tool_uses: Vec::new(),
+            error: None,
+            notification_method: None,
+        }
+    }
+
+    /// Whether events are written at all. Otherwise, stdout is left to the styled output.
+    pub fn is_enabled(&self) -> bool {
+        self.format != ChatOutputFormat::Text
+    }
+
+    pub fn emit(&mut self, event: OutputEvent) -> io::Result<()> {
+        if let OutputEvent::ToolResult {
+            tool_use_id, status, ..
+        } = &event
+        {
+            if *status != ToolResultEventStatus::Success {
+                self.failed_tool_uses.push(tool_use_id.clone());
+            }
+        }
+
+        match self.format {
+            ChatOutputFormat::Text => Ok(()),
+            ChatOutputFormat::Json => {
+                self.events.push(event);
+                Ok(())
+            },
+            ChatOutputFormat::StreamJson => {
+                match self.notification_method {
+                    Some(method) => serde_json::to_writer(
+                        &mut self.output,
+                        &serde_json::json!({ "jsonrpc": "2.0", "method": method, "params": event }),
+                    )?,
+                    None => serde_json::to_writer(&mut self.output, &event)?,
+                }
+                writeln!(self.output)?;
+                self.output.flush()
+            },
+        }
+    }
+
+    pub fn record_error(&mut self, error: String) {
+        self.error = Some(error);
+    }
+
+    pub fn emit_tool_result(&mut self, result: &ToolUseResult, status: ToolResultEventStatus) -> io::Result<()> {
+        let content = result
+            .content
+            .iter()
+            .map(|block| match block {
+                ToolUseResultBlock::Text(text) => serde_json::Value::String(text.clone()),
+                ToolUseResultBlock::Json(json) => json.clone(),
+            })
+            .collect();
+        self.emit(OutputEvent::ToolResult {
+            tool_use_id: result.tool_use_id.clone(),
+            status,
+            content,
+        })
+    }
+
+    /// Writes the final [OutputEvent::Result], returning whether the session succeeded, i.e. it
+    /// ended without an error, including recorded ones, and every tool use succeeded.
+    pub fn finish(
+        &mut self,
+        conversation_id: &str,
+        response: Option<String>,
+        error: Option<String>,
+    ) -> io::Result<bool> {
+        let error = error.or_else(|| self.error.take());
+        let success = error.is_none() && self.failed_tool_uses.is_empty();
+        self.emit(OutputEvent::Result {
+            status: if success {
+                ResultStatus::Success
+            } else {
+                ResultStatus::Error
+            },
+            conversation_id: conversation_id.to_string(),
+            response,
+            failed_tool_uses: self.failed_tool_uses.clone(),
+            error,
+        })?;
+
+        if self.format == ChatOutputFormat::Json {
+            let mut events = std::mem::take(&mut self.events);
+            let result = events.pop();
+            let mut output = serde_json::to_value(result)?;
+            if let Some(object) = output.as_object_mut() {
+                object.insert("events".to_string(), serde_json::to_value(events)?);
+            }
+            serde_json::to_writer(&mut self.output, &output)?;
+            writeln!(self.output)?;
+            self.output.flush()?;
+        }
+        Ok(success)
+    }
+}
+
+/// Destination of the styled output of a session, which is discarded when stdout is reserved for
+/// [OutputEvent]s.
+pub enum SessionStdout {
+    Stdout(io::Stdout),
+    Discard,
+}
+
+impl Write for SessionStdout {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Stdout(stdout) => stdout.write(buf),
+            Self::Discard => Ok(buf.len()),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Stdout(stdout) => stdout.flush(),
+            Self::Discard => Ok(()),
+        }
+    }
+}
+
+/// Destination of the styled output of a session meant for humans only, which is captured when
+/// the session is driven by another program.
+pub enum SessionStderr {
+    Stderr(io::Stderr),
+    Capture(Arc<Mutex<Vec<u8>>>),
+}
+
+impl Write for SessionStderr {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Stderr(stderr) => stderr.write(buf),
+            Self::Capture(buffer) => buffer.lock().unwrap_or_else(|err| err.into_inner()).write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Stderr(stderr) => stderr.flush(),
+            Self::Capture(_) => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api_client::model::ToolResultStatus;
+
+    fn tool_result(id: &str) -> ToolUseResult {
+        ToolUseResult {
+            tool_use_id: id.to_string(),
+            content: vec![ToolUseResultBlock::Text("output".to_string())],
+            status: ToolResultStatus::Success,
+        }
+    }
+
+    fn lines(output: &[u8]) -> Vec<serde_json::Value> {
+        String::from_utf8_lossy(output)
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_stream_json() {
+        let mut writer = EventWriter::with_output(ChatOutputFormat::StreamJson, Vec::new());
+        writer
+            .emit(OutputEvent::AssistantText {
+                text: "hello".to_string(),
+            })
+            .unwrap();
+        writer
+            .emit_tool_result(&tool_result("1"), ToolResultEventStatus::Success)
+            .unwrap();
+        assert!(writer.finish("conv", Some("hello".to_string()), None).unwrap());
+
+        let events = lines(&writer.output);
+        assert_eq!(events.len(), 3);
+        assert_eq!(
+            events[0],
+            serde_json::json!({ "type": "assistant_text", "text": "hello" })
+        );
+        assert_eq!(events[1]["type"], "tool_result");
+        assert_eq!(events[1]["content"], serde_json::json!(["output"]));
+        assert_eq!(events[2]["type"], "result");
+        assert_eq!(events[2]["status"], "success");
+    }
+
+    #[test]
+    fn test_notifications() {
+        let mut writer = EventWriter {
+            notification_method: Some("chat/event"),
+            ..EventWriter::with_output(ChatOutputFormat::StreamJson, Vec::new())
+        };
+        writer
+            .emit(OutputEvent::ToolUseStart {
+                name: "fs_read".to_string(),
+            })
+            .unwrap();
+        assert_eq!(lines(&writer.output), vec![serde_json::json!({
+            "jsonrpc": "2.0",
+            "method": "chat/event",
+            "params": { "type": "tool_use_start", "name": "fs_read" },
+        })]);
+    }
+
+    #[test]
+    fn test_json_fails_on_denied_tool() {
+        let mut writer = EventWriter::with_output(ChatOutputFormat::Json, Vec::new());
+        writer
+            .emit(OutputEvent::ToolUseStart {
+                name: "fs_write".to_string(),
+            })
+            .unwrap();
+        writer
+            .emit_tool_result(&tool_result("1"), ToolResultEventStatus::Denied)
+            .unwrap();
+        assert!(!writer.finish("conv", None, None).unwrap());
+
+        let output = lines(&writer.output);
+        assert_eq!(output.len(), 1);
+        assert_eq!(output[0]["type"], "result");
+        assert_eq!(output[0]["status"], "error");
+        assert_eq!(output[0]["failed_tool_uses"], serde_json::json!(["1"]));
+        assert_eq!(output[0]["events"].as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_text_writes_nothing() {
+        let mut writer = EventWriter::with_output(ChatOutputFormat::Text, Vec::new());
+        assert!(!writer.is_enabled());
+        writer
+            .emit_tool_result(&tool_result("1"), ToolResultEventStatus::Error)
+            .unwrap();
+        assert!(!writer.finish("conv", None, None).unwrap());
+        assert!(writer.output.is_empty());
+    }
+}
@@ -9,6 +9,8 @@ use crossterm::{
     Command,
     style,
 };
+use syntect::easy::HighlightLines;
+use syntect::util::as_24_bit_terminal_escaped;
 use unicode_width::{
     UnicodeWidthChar,
     UnicodeWidthStr,
@@ -24,6 +26,7 @@ use winnow::ascii::{
 use winnow::combinator::{
     alt,
     delimited,
+    opt,
     preceded,
     repeat,
     terminated,
@@ -31,27 +34,44 @@ use winnow::combinator::{
 use winnow::error::{
     ErrMode,
     ErrorKind,
+    Needed,
     ParserError,
 };
 use winnow::prelude::*;
 use winnow::stream::{
     AsChar,
+    Offset,
     Stream,
 };
 use winnow::token::{
     any,
+    one_of,
     take_till,
     take_until,
     take_while,
 };
 
+use super::tools::fs_write::{
+    SYNTAX_SET,
+    THEME_SET,
+};
+
 const CODE_COLOR: Color = Color::Green;
 const HEADING_COLOR: Color = Color::Magenta;
 const BLOCKQUOTE_COLOR: Color = Color::DarkGrey;
 const URL_TEXT_COLOR: Color = Color::Blue;
 const URL_LINK_COLOR: Color = Color::DarkGrey;
+const TABLE_BORDER_COLOR: Color = Color::DarkGrey;
+const TASK_DONE_COLOR: Color = Color::Green;
 
 const DEFAULT_RULE_WIDTH: usize = 40;
+const HIGHLIGHT_THEME: &str = "base16-ocean.dark";
+/// Bullets of list items, by nesting level
+const BULLETS: [&str; 3] = ["•", "◦", "▪"];
+/// Columns of source indentation per nesting level of lists
+const LIST_INDENT_WIDTH: usize = 2;
+/// Narrowest a table column is shrunk to when the table does not fit the terminal
+const MIN_COLUMN_WIDTH: usize = 5;
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error<'a> {
@@ -90,6 +110,32 @@ pub struct ParseState {
     pub set_newline: bool,
     pub newline: bool,
     pub citations: Vec<(String, String)>,
+    /// Column that wrapped lines start at, e.g. to align them with the text of a list item.
+    pub indent: usize,
+    /// Whether the whole input has been received, so that blocks such as tables, which are only
+    /// printed once they are complete, are not waited on any longer.
+    pub input_ended: bool,
+    /// Whether fenced code blocks are syntax highlighted, which requires truecolor support.
+    pub syntax_highlighting: bool,
+    /// Highlighter of the current code block, if its language is known.
+    pub highlighter: Option<CodeHighlighter>,
+}
+
+/// Highlights the lines of a code block as they are received.
+pub struct CodeHighlighter(HighlightLines<'static>);
+
+impl std::fmt::Debug for CodeHighlighter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("CodeHighlighter").finish_non_exhaustive()
+    }
+}
+
+impl CodeHighlighter {
+    fn new(language: &str) -> Option<Self> {
+        let syntax = SYNTAX_SET.find_syntax_by_token(language)?;
+        let theme = THEME_SET.themes.get(HIGHLIGHT_THEME)?;
+        Some(Self(HighlightLines::new(syntax, theme)))
+    }
 }
 
 impl ParseState {
@@ -104,10 +150,31 @@ impl ParseState {
             set_newline: false,
             newline: true,
             citations: vec![],
+            indent: 0,
+            input_ended: false,
+            syntax_highlighting: false,
+            highlighter: None,
         }
     }
 }
 
+/// Interprets the next complete element of `input`, returning the number of bytes consumed, or
+/// `None` if more input is needed.
+pub fn render_markdown<'a>(input: &'a str, o: impl Write, state: &mut ParseState) -> Result<Option<usize>, Error<'a>> {
+    let input = Partial::new(input);
+    match interpret_markdown(input, o, state) {
+        Ok(parsed) => {
+            state.newline = state.set_newline;
+            state.set_newline = false;
+            Ok(Some(parsed.offset_from(&input)))
+        },
+        Err(err) => match err.into_inner() {
+            Some(err) => Err(err),
+            None => Ok(None), // Data was incomplete
+        },
+    }
+}
+
 pub fn interpret_markdown<'a, 'b>(
     mut i: Partial<&'a str>,
     mut o: impl Write + 'b,
@@ -142,6 +209,7 @@ pub fn interpret_markdown<'a, 'b>(
                 // More importantly, it's needed to support manual wordwrapping
                 text,
                 // multiline patterns
+                table,
                 blockquote,
                 // linted_codeblock,
                 codeblock_begin,
@@ -167,6 +235,9 @@ pub fn interpret_markdown<'a, 'b>(
                 fallback
             );
         },
+        true if state.highlighter.is_some() => {
+            stateful_alt!(highlighted_codeblock_line);
+        },
         true => {
             stateful_alt!(
                 codeblock_less_than,
@@ -227,10 +298,32 @@ fn bulleted_item<'a, 'b>(
         }
 
         let ws = (space0, alt(("-", "*")), space1).parse_next(i)?.0;
-        let print = format!("{ws}• ");
+        let task = opt(terminated(delimited("[", one_of([' ', 'x', 'X']), "]"), space1)).parse_next(i)?;
+        let level = list_level(ws);
+        let indent = " ".repeat(level * LIST_INDENT_WIDTH);
+
+        match task {
+            Some(' ') => {
+                let print = format!("{indent}☐ ");
+                queue_newline_or_advance(&mut o, state, print.width())?;
+                queue(&mut o, style::Print(print))?;
+            },
+            Some(_) => {
+                let print = format!("{indent}☑ ");
+                queue_newline_or_advance(&mut o, state, print.width())?;
+                queue(&mut o, style::SetForegroundColor(TASK_DONE_COLOR))?;
+                queue(&mut o, style::Print(print))?;
+                queue(&mut o, style::SetForegroundColor(Color::Reset))?;
+            },
+            None => {
+                let print = format!("{indent}{} ", BULLETS[level % BULLETS.len()]);
+                queue_newline_or_advance(&mut o, state, print.width())?;
+                queue(&mut o, style::Print(print))?;
+            },
+        }
+        state.indent = state.column;
 
-        queue_newline_or_advance(&mut o, state, print.width())?;
-        queue(&mut o, style::Print(print))
+        Ok(())
     }
 }
 
@@ -244,13 +337,23 @@ fn numbered_item<'a, 'b>(
         }
 
         let (ws, digits, _, _) = (space0, digit1, ".", space1).parse_next(i)?;
-        let print = format!("{ws}{digits}. ");
+        let indent = " ".repeat(list_level(ws) * LIST_INDENT_WIDTH);
+        let print = format!("{indent}{digits}. ");
 
         queue_newline_or_advance(&mut o, state, print.width())?;
-        queue(&mut o, style::Print(print))
+        queue(&mut o, style::Print(print))?;
+        state.indent = state.column;
+
+        Ok(())
     }
 }
 
+/// Returns the nesting level of a list item from its indentation.
+fn list_level(indentation: &str) -> usize {
+    let width: usize = indentation.chars().map(|c| if c == '\t' { 4 } else { 1 }).sum();
+    width / LIST_INDENT_WIDTH
+}
+
 fn horizontal_rule<'a, 'b>(
     mut o: impl Write + 'b,
     state: &'b mut ParseState,
@@ -310,6 +413,228 @@ fn blockquote<'a, 'b>(
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Alignment {
+    Left,
+    Center,
+    Right,
+}
+
+/// A GFM table, which is printed once all of its rows have been received so that the columns
+/// can be laid out to fit the terminal.
+fn table<'a, 'b>(
+    mut o: impl Write + 'b,
+    state: &'b mut ParseState,
+) -> impl FnMut(&mut Partial<&'a str>) -> PResult<(), Error<'a>> + 'b {
+    move |i| {
+        if !state.newline {
+            return Err(ErrMode::from_error_kind(i, ErrorKind::Fail));
+        }
+
+        let input: &'a str = **i;
+        let mut lines = input.split_inclusive('\n');
+        let mut consumed = 0;
+        let mut next_row = |lines: &mut std::str::SplitInclusive<'a, char>| -> Result<Option<&'a str>, ()> {
+            let Some(line) = lines.next() else {
+                return match state.input_ended {
+                    true => Ok(None),
+                    false => Err(()),
+                };
+            };
+            if !is_table_row(line) {
+                return Ok(None);
+            }
+            if !line.ends_with('\n') && !state.input_ended {
+                return Err(());
+            }
+            consumed += line.len();
+            Ok(Some(line.trim_end()))
+        };
+        let incomplete = || Err(ErrMode::Incomplete(Needed::Unknown));
+
+        let header = match next_row(&mut lines) {
+            Ok(Some(header)) => split_row(header),
+            Ok(None) => return Err(ErrMode::from_error_kind(i, ErrorKind::Fail)),
+            Err(()) => return incomplete(),
+        };
+        let alignments = match next_row(&mut lines) {
+            Ok(Some(delimiter)) => match parse_delimiter_row(delimiter) {
+                Some(alignments) if alignments.len() == header.len() => alignments,
+                _ => return Err(ErrMode::from_error_kind(i, ErrorKind::Fail)),
+            },
+            Ok(None) => return Err(ErrMode::from_error_kind(i, ErrorKind::Fail)),
+            Err(()) => return incomplete(),
+        };
+        let mut rows = vec![header];
+        loop {
+            match next_row(&mut lines) {
+                Ok(Some(row)) => {
+                    let mut cells = split_row(row);
+                    cells.resize(alignments.len(), String::new());
+                    rows.push(cells);
+                },
+                Ok(None) => break,
+                Err(()) => return incomplete(),
+            }
+        }
+
+        i.next_slice(consumed);
+        state.column = 0;
+        state.set_newline = true;
+        queue(
+            &mut o,
+            style::Print(layout_table(&rows, &alignments, state.terminal_width)),
+        )
+    }
+}
+
+fn is_table_row(line: &str) -> bool {
+    let trimmed = line.trim_start_matches(' ');
+    line.len() - trimmed.len() <= 3 && trimmed.starts_with('|')
+}
+
+/// Splits a table row into its cells, e.g. `| a | b \| c |` into `a` and `b | c`.
+fn split_row(row: &str) -> Vec<String> {
+    let row = row.trim();
+    let row = row.strip_prefix('|').unwrap_or(row);
+    let row = match row.strip_suffix('|') {
+        Some(stripped) if !stripped.ends_with('\\') => stripped,
+        _ => row,
+    };
+
+    let mut cells = vec![String::new()];
+    let mut chars = row.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek() == Some(&'|') => {
+                cells.last_mut().expect("not empty").push('|');
+                chars.next();
+            },
+            '|' => cells.push(String::new()),
+            c => cells.last_mut().expect("not empty").push(c),
+        }
+    }
+    cells.iter().map(|cell| cell_text(cell.trim())).collect()
+}
+
+/// The text of a table cell without the markers of inline formatting, which is not rendered in
+/// tables.
+fn cell_text(cell: &str) -> String {
+    unescape_entities(&cell.replace("**", "").replace("__", "").replace('`', ""))
+}
+
+/// Parses the delimiter row of a table, e.g. `|:---|:---:|---:|`, into the alignment of each
+/// column.
+fn parse_delimiter_row(row: &str) -> Option<Vec<Alignment>> {
+    split_row(row)
+        .iter()
+        .map(|cell| {
+            let left = cell.starts_with(':');
+            let right = cell.ends_with(':');
+            let dashes = cell.trim_start_matches(':').trim_end_matches(':');
+            if dashes.is_empty() || !dashes.chars().all(|c| c == '-') {
+                return None;
+            }
+            Some(match (left, right) {
+                (true, true) => Alignment::Center,
+                (false, true) => Alignment::Right,
+                _ => Alignment::Left,
+            })
+        })
+        .collect()
+}
+
+/// Lays out a table with box drawing borders, wrapping cells so that it fits in `terminal_width`.
+/// The first row is the header.
+fn layout_table(rows: &[Vec<String>], alignments: &[Alignment], terminal_width: Option<usize>) -> String {
+    let mut widths: Vec<usize> = (0..alignments.len())
+        .map(|column| rows.iter().map(|row| row[column].width()).max().unwrap_or(0).max(1))
+        .collect();
+
+    // Each column takes 3 more columns for its padding and left border, plus the right border
+    if let Some(terminal_width) = terminal_width {
+        let available = terminal_width.saturating_sub(3 * widths.len() + 1);
+        while widths.iter().sum::<usize>() > available {
+            match widths.iter_mut().filter(|width| **width > MIN_COLUMN_WIDTH).max() {
+                Some(widest) => *widest -= 1,
+                None => break,
+            }
+        }
+    }
+
+    let border = |left: &str, middle: &str, right: &str| {
+        let line = widths.iter().map(|width| "─".repeat(width + 2)).collect::<Vec<_>>();
+        format!("{left}{}{right}", line.join(middle))
+            .with(TABLE_BORDER_COLOR)
+            .to_string()
+    };
+    let separator = "│".with(TABLE_BORDER_COLOR).to_string();
+
+    let mut out = border("┌", "┬", "┐");
+    out.push('\n');
+    for (index, row) in rows.iter().enumerate() {
+        let cells: Vec<Vec<String>> = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| wrap_text(cell, *width))
+            .collect();
+        let height = cells.iter().map(Vec::len).max().unwrap_or(1);
+        for line in 0..height {
+            out.push_str(&separator);
+            for ((cell, width), alignment) in cells.iter().zip(&widths).zip(alignments) {
+                let text = pad(cell.get(line).map_or("", String::as_str), *width, *alignment);
+                match index {
+                    0 => out.push_str(&format!(" {} ", text.bold())),
+                    _ => out.push_str(&format!(" {text} ")),
+                }
+                out.push_str(&separator);
+            }
+            out.push('\n');
+        }
+        if index == 0 {
+            out.push_str(&border("├", "┼", "┤"));
+            out.push('\n');
+        }
+    }
+    out.push_str(&border("└", "┴", "┘"));
+    out.push('\n');
+    out
+}
+
+/// Wraps `text` at word boundaries into lines of at most `width` columns, breaking words that
+/// are longer than that.
+fn wrap_text(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        if !line.is_empty() && line.width() + 1 + word.width() > width {
+            lines.push(std::mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        for c in word.chars() {
+            if !line.is_empty() && line.width() + c.width().unwrap_or(0) > width {
+                lines.push(std::mem::take(&mut line));
+            }
+            line.push(c);
+        }
+    }
+    if !line.is_empty() || lines.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+fn pad(text: &str, width: usize, alignment: Alignment) -> String {
+    let padding = width.saturating_sub(text.width());
+    match alignment {
+        Alignment::Left => format!("{text}{}", " ".repeat(padding)),
+        Alignment::Right => format!("{}{text}", " ".repeat(padding)),
+        Alignment::Center => format!("{}{text}{}", " ".repeat(padding / 2), " ".repeat(padding - padding / 2)),
+    }
+}
+
 fn bold<'a, 'b>(
     mut o: impl Write + 'b,
     state: &'b mut ParseState,
@@ -490,6 +815,7 @@ fn line_ending<'a, 'b>(
         ascii::line_ending.parse_next(i)?;
 
         state.column = 0;
+        state.indent = 0;
         state.set_newline = true;
 
         queue(&mut o, style::ResetColor)?;
@@ -521,9 +847,10 @@ fn queue_newline_or_advance<'a, 'b>(
     width: usize,
 ) -> Result<(), ErrMode<Error<'a>>> {
     if let Some(terminal_width) = state.terminal_width {
-        if state.column > 0 && state.column + width > terminal_width {
-            state.column = width;
+        if state.column > state.indent && state.column + width > terminal_width {
+            state.column = state.indent + width;
             queue(&mut o, style::Print('\n'))?;
+            queue(&mut o, style::Print(" ".repeat(state.indent)))?;
             return Ok(());
         }
     }
@@ -555,6 +882,11 @@ fn codeblock_begin<'a, 'b>(
         ascii::line_ending.parse_next(i)?;
 
         state.in_codeblock = true;
+        // The info string may hold more than the language, e.g. "rust title=main.rs"
+        let token = language.split_whitespace().next().unwrap_or_default();
+        if state.syntax_highlighting && !token.is_empty() {
+            state.highlighter = CodeHighlighter::new(token);
+        }
 
         if !language.is_empty() {
             queue(&mut o, style::Print(format!("{}\n", language).bold()))?;
@@ -573,10 +905,64 @@ fn codeblock_end<'a, 'b>(
     move |i| {
         "```".parse_next(i)?;
         state.in_codeblock = false;
+        state.highlighter = None;
         queue(&mut o, style::ResetColor)
     }
 }
 
+/// Prints a line of a code block whose language is known, once it is complete, since
+/// highlighting works a line at a time.
+fn highlighted_codeblock_line<'a, 'b>(
+    mut o: impl Write + 'b,
+    state: &'b mut ParseState,
+) -> impl FnMut(&mut Partial<&'a str>) -> PResult<(), Error<'a>> + 'b {
+    move |i| {
+        let line = terminated(till_line_ending, ascii::line_ending).parse_next(i)?;
+        let line = unescape_entities(line);
+        let (code, end) = match line.trim_start().strip_prefix("```") {
+            Some(_) => ("", true),
+            None => match line.strip_suffix("```") {
+                Some(code) => (code, true),
+                None => (line.as_str(), false),
+            },
+        };
+
+        if !code.is_empty() || !end {
+            if let Some(CodeHighlighter(highlighter)) = &mut state.highlighter {
+                // The syntaxes are loaded for lines that include their line ending
+                let code = format!("{code}\n");
+                match highlighter.highlight_line(&code, &SYNTAX_SET) {
+                    Ok(ranges) => queue(&mut o, style::Print(as_24_bit_terminal_escaped(&ranges, false)))?,
+                    Err(_) => {
+                        queue(&mut o, style::SetForegroundColor(CODE_COLOR))?;
+                        queue(&mut o, style::Print(code))?;
+                    },
+                }
+                queue(&mut o, style::ResetColor)?;
+            }
+        }
+
+        if end {
+            state.in_codeblock = false;
+            state.highlighter = None;
+            state.column = 0;
+            state.set_newline = true;
+            if !code.is_empty() {
+                return Ok(());
+            }
+            return queue(&mut o, style::Print("\n"));
+        }
+        Ok(())
+    }
+}
+
+fn unescape_entities(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&amp;", "&")
+}
+
 fn codeblock_less_than<'a, 'b>(
     mut o: impl Write + 'b,
     _state: &'b mut ParseState,
@@ -641,8 +1027,6 @@ fn codeblock_fallback<'a, 'b>(
 mod tests {
     use std::io::Write;
 
-    use winnow::stream::Offset;
-
     use super::*;
 
     macro_rules! validate {
@@ -743,6 +1127,25 @@ mod tests {
     validate!(bulleted_item_1, "- bullet", [style::Print("• bullet")]);
     validate!(bulleted_item_2, "* bullet", [style::Print("• bullet")]);
     validate!(numbered_item_1, "1. number", [style::Print("1. number")]);
+    validate!(nested_bulleted_item_1, "- outer\n  - inner", [
+        style::Print("• outer"),
+        style::ResetColor,
+        style::ResetColor,
+        style::Print("\n  ◦ inner"),
+    ]);
+    validate!(nested_numbered_item_1, "1. outer\n   1. inner", [
+        style::Print("1. outer"),
+        style::ResetColor,
+        style::ResetColor,
+        style::Print("\n  1. inner"),
+    ]);
+    validate!(task_item_1, "- [ ] todo", [style::Print("☐ todo")]);
+    validate!(task_item_2, "- [x] done", [
+        style::SetForegroundColor(TASK_DONE_COLOR),
+        style::Print("☑ "),
+        style::SetForegroundColor(Color::Reset),
+        style::Print("done"),
+    ]);
     validate!(blockquote_1, "> hello", [
         style::SetForegroundColor(BLOCKQUOTE_COLOR),
         style::Print("│ hello"),
@@ -759,4 +1162,67 @@ mod tests {
     validate!(square_bracket_url_like_2, "[text](without url part", [style::Print(
         "[text](without url part"
     )]);
+
+    /// Interprets the whole of `input` at once, returning the output without styling.
+    fn render(input: &str, state: &mut ParseState) -> String {
+        strip_ansi_escapes::strip_str(render_styled(input, state))
+    }
+
+    /// Interprets the whole of `input` at once, returning the output with styling.
+    fn render_styled(input: &str, state: &mut ParseState) -> String {
+        state.input_ended = true;
+        let mut output = vec![];
+        let mut offset = 0;
+        while offset < input.len() {
+            offset += render_markdown(&input[offset..], &mut output, state).unwrap().unwrap();
+        }
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_table() {
+        let input = "| Name | Count |\n|:-----|------:|\n| `a` \\| b | 1 |\n| c | 10 |\nafter\n";
+        assert_eq!(
+            render(input, &mut ParseState::new(Some(80))),
+            "┌───────┬───────┐\n\
+             │ Name  │ Count │\n\
+             ├───────┼───────┤\n\
+             │ a | b │     1 │\n\
+             │ c     │    10 │\n\
+             └───────┴───────┘\n\
+             after\n"
+        );
+
+        // Partial tables are held back until they are complete
+        let mut state = ParseState::new(Some(80));
+        let partial = Partial::new("| a | b |\n|---|---|\n| 1 | 2 |\n");
+        assert!(matches!(
+            interpret_markdown(partial, &mut vec![], &mut state),
+            Err(ErrMode::Incomplete(_))
+        ));
+
+        // Not a table without a delimiter row
+        assert_eq!(
+            render("| a | b |\ntext\n", &mut ParseState::new(Some(80))),
+            "| a | b |\ntext\n"
+        );
+    }
+
+    #[test]
+    fn test_table_wraps_to_terminal_width() {
+        let input = "| Key | Description |\n|---|---|\n| k | a rather long description of the key |\n";
+        let output = render(input, &mut ParseState::new(Some(30)));
+        assert!(output.lines().all(|line| line.width() <= 30), "{output}");
+        assert!(output.contains("│ k   │ a rather long        │\n"), "{output}");
+    }
+
+    #[test]
+    fn test_highlighted_codeblock() {
+        let mut state = ParseState::new(Some(80));
+        state.syntax_highlighting = true;
+        let output = render_styled("```rust\nfn main() {}\n```\ndone\n", &mut state);
+        assert!(output.contains("\x1b[38;2;"), "{output:?}");
+        assert_eq!(strip_ansi_escapes::strip_str(&output), "rust\nfn main() {}\n\ndone\n");
+        assert!(!state.in_codeblock);
+    }
 }
@@ -1,4 +1,5 @@
 use std::borrow::Cow;
+use std::path::PathBuf;
 
 use eyre::Result;
 use rustyline::completion::{
@@ -35,6 +36,7 @@ use rustyline::{
 };
 use winnow::stream::AsChar;
 
+use super::local_prompts;
 pub use super::prompt_parser::generate_prompt;
 use super::prompt_parser::parse_prompt_components;
 use crate::database::settings::Setting;
@@ -62,6 +64,11 @@ pub const COMMANDS: &[&str] = &[
     "/profile rename",
     "/profile set",
     "/prompts",
+    "/resources",
+    "/resources list",
+    "/resources read",
+    "/resources subscribe",
+    "/resources unsubscribe",
     "/context",
     "/context help",
     "/context show",
@@ -82,9 +89,25 @@ pub const COMMANDS: &[&str] = &[
     "/hooks disable-all",
     "/compact",
     "/compact help",
+    "/pin",
+    "/pin list",
+    "/pin add",
+    "/pin remove",
+    "/undo",
+    "/checkpoint",
+    "/checkpoint list",
+    "/checkpoint restore",
+    "/commit",
+    "/commit --no-edit",
     "/usage",
     "/save",
     "/load",
+    "/sessions",
+    "/sessions list",
+    "/sessions resume",
+    "/sessions rename",
+    "/sessions delete",
+    "/sessions prune",
     "/subscribe",
 ];
 
@@ -166,6 +189,8 @@ impl PromptCompleter {
 pub struct ChatCompleter {
     path_completer: PathCompleter,
     prompt_completer: PromptCompleter,
+    /// Directories of the local prompt templates, which are completed along with MCP prompts
+    prompt_dirs: Vec<PathBuf>,
 }
 
 impl ChatCompleter {
@@ -173,8 +198,14 @@ impl ChatCompleter {
         Self {
             path_completer: PathCompleter::new(),
             prompt_completer: PromptCompleter::new(sender, receiver),
+            prompt_dirs: Vec::new(),
         }
     }
+
+    fn with_prompt_dirs(mut self, prompt_dirs: Vec<PathBuf>) -> Self {
+        self.prompt_dirs = prompt_dirs;
+        self
+    }
 }
 
 impl Completer for ChatCompleter {
@@ -195,11 +226,21 @@ impl Completer for ChatCompleter {
 
         if line.starts_with('@') {
             let search_word = line.strip_prefix('@').unwrap_or("");
-            if let Ok(completions) = self.prompt_completer.complete_prompt(search_word) {
-                if !completions.is_empty() {
-                    return Ok((0, completions));
+            let mut completions = local_prompts::list_prompt_names(&self.prompt_dirs)
+                .into_iter()
+                .filter(|name| name.contains(search_word))
+                .map(|name| format!("@{name}"))
+                .collect::<Vec<_>>();
+            if let Ok(mcp_completions) = self.prompt_completer.complete_prompt(search_word) {
+                for completion in mcp_completions {
+                    if !completions.contains(&completion) {
+                        completions.push(completion);
+                    }
                 }
             }
+            if !completions.is_empty() {
+                return Ok((0, completions));
+            }
         }
 
         // Handle file path completion as fallback
@@ -370,7 +411,12 @@ pub fn rl(
         .edit_mode(edit_mode)
         .build();
     let h = ChatHelper {
-        completer: ChatCompleter::new(sender, receiver),
+        completer: ChatCompleter::new(sender, receiver).with_prompt_dirs(
+            local_prompts::prompt_dirs(os)
+                .into_iter()
+                .map(|(_, dir)| os.fs.chroot_path(dir))
+                .collect(),
+        ),
         hinter: ChatHinter::new(),
         validator: MultiLineValidator,
     };
@@ -445,6 +491,38 @@ mod tests {
         assert!(completions.is_empty());
     }
 
+    #[test]
+    fn test_chat_completer_local_prompt_completion() {
+        let prompt_dir = tempfile::tempdir().unwrap();
+        std::fs::write(prompt_dir.path().join("review.md"), "Review").unwrap();
+        std::fs::write(prompt_dir.path().join("explain.md"), "Explain").unwrap();
+
+        // The MCP prompts are still completed along with the local ones
+        let (prompt_request_sender, prompt_request_receiver) = std::sync::mpsc::channel::<Option<String>>();
+        let (prompt_response_sender, prompt_response_receiver) = std::sync::mpsc::channel::<Vec<String>>();
+        let completer = ChatCompleter::new(prompt_request_sender, prompt_response_receiver)
+            .with_prompt_dirs(vec![prompt_dir.path().to_path_buf()]);
+        let mcp = std::thread::spawn(move || {
+            while let Ok(search_word) = prompt_request_receiver.recv() {
+                let names = ["review", "mcp-review"]
+                    .into_iter()
+                    .filter(|name| name.contains(search_word.as_deref().unwrap_or("")))
+                    .map(str::to_string)
+                    .collect();
+                prompt_response_sender.send(names).unwrap();
+            }
+        });
+
+        let empty_history = DefaultHistory::new();
+        let os = Context::new(&empty_history);
+        let (start, completions) = completer.complete("@rev", 4, &os).unwrap();
+        assert_eq!(start, 0);
+        assert_eq!(completions, vec!["@review", "@mcp-review"]);
+
+        drop(completer);
+        mcp.join().unwrap();
+    }
+
     #[test]
     fn test_highlight_prompt_basic() {
         let (prompt_request_sender, _) = std::sync::mpsc::channel::<Option<String>>();
@@ -0,0 +1,624 @@
+//! Headless mode of `q chat --server`, in which another program drives the chat session with
+//! [JSON-RPC 2.0](https://www.jsonrpc.org/specification) messages over stdio, one message per
+//! line.
+//!
+//! The session runs as usual, except that whenever it needs input, it reads the next request from
+//! stdin instead of prompting the user. Requests that provide input are answered once the session
+//! needs input again, i.e. after the response and the tool uses it triggered have been handled,
+//! and further requests that provide input are refused until then. Their result describes what
+//! the session is waiting for, and the output it printed meanwhile:
+//!
+//! ```json
+//! {"state": "awaiting_prompt", "prompt": "> ", "output": "..."}
+//! {"state": "awaiting_approval", "tool": {"id": "...", "name": "fs_write", "args": {...}}, "prompt": "> ", "output": "..."}
+//! {"state": "awaiting_input", "prompt": "Are you sure? [y/n]", "output": "..."}
+//! {"state": "exited", "output": "..."}
+//! ```
+//!
+//! # Methods
+//!
+//! - `initialize`: returns the protocol version and the version of the server.
+//! - `chat/prompt` `{"text": string}`: sends a message to the model. If a tool is awaiting
+//!   approval, it is denied and the message is sent instead. Messages starting with `/` or `!` are
+//!   refused, since they would run commands.
+//! - `chat/command` `{"command": string}`: runs a slash command, e.g. `/context show`, or a shell
+//!   command starting with `!`. Commands that open an editor or a picker, e.g. `/editor`, `/model`
+//!   or `/commit` without `--no-edit`, are refused.
+//! - `chat/usage`: runs `/usage`, returning the context window usage as its output.
+//! - `chat/input` `{"text": string}`: answers any other question of the session, e.g. the
+//!   confirmation of `/clear`.
+//! - `tools/approve` `{"trust"?: bool}`: runs the tool awaiting approval, trusting it for the rest
+//!   of the session if `trust` is set.
+//! - `tools/deny`: denies the tool awaiting approval.
+//! - `shutdown`: ends the session.
+//!
+//! # Notifications
+//!
+//! Events of the session are sent as `chat/event` notifications as soon as they happen, with one
+//! of the [OutputEvent](super::output::OutputEvent)s of `--output-format stream-json` as params.
+
+use std::io::{
+    self,
+    BufRead,
+    Write,
+};
+use std::sync::atomic::{
+    AtomicBool,
+    Ordering,
+};
+use std::sync::mpsc::{
+    Receiver,
+    Sender,
+    channel,
+};
+use std::sync::{
+    Arc,
+    Mutex,
+};
+
+use clap::Parser;
+use serde::Serialize;
+use serde_json::{
+    Value,
+    json,
+};
+
+use super::cli::SlashCommand;
+
+/// Version of the protocol, increased whenever a breaking change is made to it.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Method of the notifications carrying the events of the session.
+pub const EVENT_METHOD: &str = "chat/event";
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// Methods that provide input to the session, of which only one is handled at a time
+const INPUT_METHODS: &[&str] = &[
+    "chat/prompt",
+    "chat/command",
+    "chat/usage",
+    "chat/input",
+    "tools/approve",
+    "tools/deny",
+];
+
+/// A tool use waiting for the client to approve or deny it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PendingTool {
+    pub id: String,
+    pub name: String,
+    pub args: Value,
+}
+
+/// What the session is waiting for, reported to the client in the result of its last request.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum SessionState {
+    /// The next message or command
+    AwaitingPrompt,
+    /// The approval of a tool use
+    AwaitingApproval { tool: PendingTool },
+    /// The answer to any other question
+    #[default]
+    AwaitingInput,
+    /// Nothing, the session ended
+    Exited,
+}
+
+#[derive(Debug, PartialEq)]
+enum Action {
+    /// Hand the input to the session, replying once it needs input again
+    Input(String),
+    /// Reply right away
+    Reply(Value),
+    /// Reply right away, then end the session
+    Shutdown,
+}
+
+#[derive(Debug, PartialEq)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+/// The stdio connection of a session in server mode, used by
+/// [InputSource](super::input_source::InputSource) in place of the line editor.
+pub struct Connection {
+    /// Requests read by [read_requests]
+    requests: Receiver<String>,
+    output: Arc<Mutex<Box<dyn Write + Send>>>,
+    /// Whether the session is handling the input of a request. Set by [read_requests] when it
+    /// hands such a request over, and cleared once the session needs input again.
+    busy: Arc<AtomicBool>,
+    /// Output the session prints for humans, sent with the reply to the request that caused it.
+    captured: Arc<Mutex<Vec<u8>>>,
+    /// Id of the request to reply to once the session needs input again.
+    pending_request: Option<Value>,
+    state: SessionState,
+    shut_down: bool,
+}
+
+impl std::fmt::Debug for Connection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Connection")
+            .field("pending_request", &self.pending_request)
+            .field("state", &self.state)
+            .field("shut_down", &self.shut_down)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Connection {
+    pub fn stdio() -> Self {
+        Self::new(Box::new(io::BufReader::new(io::stdin())), Box::new(io::stdout()))
+    }
+
+    fn new(input: Box<dyn BufRead + Send>, output: Box<dyn Write + Send>) -> Self {
+        let output = Arc::new(Mutex::new(output));
+        let busy = Arc::new(AtomicBool::new(false));
+        let (sender, requests) = channel();
+        std::thread::spawn({
+            let output = Arc::clone(&output);
+            let busy = Arc::clone(&busy);
+            move || read_requests(input, &sender, &output, &busy)
+        });
+        Self {
+            requests,
+            output,
+            busy,
+            captured: Arc::new(Mutex::new(Vec::new())),
+            pending_request: None,
+            state: SessionState::default(),
+            shut_down: false,
+        }
+    }
+
+    /// Buffer the session should print its styled output to.
+    pub fn capture(&self) -> Arc<Mutex<Vec<u8>>> {
+        Arc::clone(&self.captured)
+    }
+
+    /// Sets what the session waits for the next time it reads input. Reset after every read.
+    pub fn set_state(&mut self, state: SessionState) {
+        self.state = state;
+    }
+
+    /// Replies to the pending request, then reads requests until one of them provides input.
+    /// Returns [None] once the client shut the session down or closed stdin.
+    pub fn read_line(&mut self, prompt: Option<&str>) -> io::Result<Option<String>> {
+        if self.shut_down {
+            return Ok(None);
+        }
+        let state = std::mem::take(&mut self.state);
+        // The client may send the next input as soon as it got the reply
+        self.busy.store(false, Ordering::SeqCst);
+        self.reply_pending(&state, prompt)?;
+
+        loop {
+            let Ok(line) = self.requests.recv() else {
+                self.shut_down = true;
+                return Ok(None);
+            };
+
+            let (id, provides_input, action) = match parse_request(&line) {
+                Ok((id, method, params)) => (
+                    id,
+                    INPUT_METHODS.contains(&method.as_str()),
+                    handle_request(&method, &params, &state),
+                ),
+                Err((id, err)) => (id, false, Err(err)),
+            };
+            if provides_input && action.is_err() {
+                // The session still needs input, which the client may send once it got the error
+                self.busy.store(false, Ordering::SeqCst);
+            }
+            match action {
+                Ok(Action::Input(input)) => {
+                    // Only the output caused by this request is sent with its reply
+                    self.take_output();
+                    self.pending_request = id;
+                    return Ok(Some(input));
+                },
+                Ok(Action::Reply(result)) => self.send_result(id, result)?,
+                Ok(Action::Shutdown) => {
+                    self.send_result(id, Value::Null)?;
+                    self.shut_down = true;
+                    return Ok(None);
+                },
+                Err(err) => self.send(error_message(id, err))?,
+            }
+        }
+    }
+
+    /// Replies to the pending request, if any, once the session ended.
+    pub fn finish(&mut self) -> io::Result<()> {
+        self.reply_pending(&SessionState::Exited, None)
+    }
+
+    fn reply_pending(&mut self, state: &SessionState, prompt: Option<&str>) -> io::Result<()> {
+        let output = self.take_output();
+        let Some(id) = self.pending_request.take() else {
+            return Ok(());
+        };
+
+        let mut result = serde_json::to_value(state)?;
+        if let Some(result) = result.as_object_mut() {
+            if let Some(prompt) = prompt {
+                result.insert("prompt".to_string(), strip_ansi_escapes::strip_str(prompt).into());
+            }
+            result.insert("output".to_string(), output.into());
+        }
+        self.send_result(Some(id), result)
+    }
+
+    fn take_output(&self) -> String {
+        let mut captured = self.captured.lock().unwrap_or_else(|err| err.into_inner());
+        let output = String::from_utf8_lossy(&captured).into_owned();
+        captured.clear();
+        strip_ansi_escapes::strip_str(output)
+    }
+
+    fn send_result(&mut self, id: Option<Value>, result: Value) -> io::Result<()> {
+        // Notifications, i.e. requests without an id, are not replied to
+        match id {
+            Some(id) => self.send(json!({ "jsonrpc": "2.0", "id": id, "result": result })),
+            None => Ok(()),
+        }
+    }
+
+    fn send(&mut self, message: Value) -> io::Result<()> {
+        send(&self.output, &message)
+    }
+}
+
+/// Reads the requests of the client as they come, refusing those that provide input while the
+/// session is still handling the input of an earlier one.
+fn read_requests(
+    mut input: Box<dyn BufRead + Send>,
+    sender: &Sender<String>,
+    output: &Mutex<Box<dyn Write + Send>>,
+    busy: &AtomicBool,
+) {
+    let mut line = String::new();
+    loop {
+        line.clear();
+        match input.read_line(&mut line) {
+            Ok(0) => return,
+            Ok(_) => {},
+            Err(err) => {
+                tracing::error!(?err, "Failed to read a request");
+                return;
+            },
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        if let Ok((id, method, _)) = parse_request(&line) {
+            if INPUT_METHODS.contains(&method.as_str()) && busy.swap(true, Ordering::SeqCst) {
+                let err = RpcError::new(
+                    INVALID_REQUEST,
+                    "The session is still handling an earlier request, wait for its reply",
+                );
+                if let Err(err) = send(output, &error_message(id, err)) {
+                    tracing::error!(?err, "Failed to refuse a request");
+                }
+                continue;
+            }
+        }
+        if sender.send(line.clone()).is_err() {
+            return;
+        }
+    }
+}
+
+fn error_message(id: Option<Value>, err: RpcError) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": err.code, "message": err.message },
+    })
+}
+
+fn send(output: &Mutex<Box<dyn Write + Send>>, message: &Value) -> io::Result<()> {
+    let mut output = output.lock().unwrap_or_else(|err| err.into_inner());
+    serde_json::to_writer(&mut *output, message)?;
+    writeln!(output)?;
+    output.flush()
+}
+
+/// Parses a request into its id, method and params.
+#[allow(clippy::type_complexity)]
+fn parse_request(line: &str) -> Result<(Option<Value>, String, Value), (Option<Value>, RpcError)> {
+    let request: Value = serde_json::from_str(line).map_err(|err| {
+        (
+            Some(Value::Null),
+            RpcError::new(PARSE_ERROR, format!("Parse error: {err}")),
+        )
+    })?;
+    let id = request.get("id").cloned();
+    match (
+        request.get("jsonrpc").and_then(Value::as_str),
+        request.get("method").and_then(Value::as_str),
+    ) {
+        (Some("2.0"), Some(method)) => Ok((
+            id,
+            method.to_string(),
+            request.get("params").cloned().unwrap_or(Value::Null),
+        )),
+        _ => Err((
+            Some(id.unwrap_or(Value::Null)),
+            RpcError::new(INVALID_REQUEST, "Invalid request"),
+        )),
+    }
+}
+
+fn handle_request(method: &str, params: &Value, state: &SessionState) -> Result<Action, RpcError> {
+    let string_param = |name: &str| {
+        params
+            .get(name)
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .ok_or_else(|| RpcError::new(INVALID_PARAMS, format!("Missing string param '{name}'")))
+    };
+    let require_approval = || match state {
+        SessionState::AwaitingApproval { .. } => Ok(()),
+        _ => Err(RpcError::new(INVALID_REQUEST, "No tool is awaiting approval")),
+    };
+
+    match method {
+        "initialize" => Ok(Action::Reply(json!({
+            "protocol_version": PROTOCOL_VERSION,
+            "server_info": { "name": "q chat", "version": env!("CARGO_PKG_VERSION") },
+        }))),
+        "chat/prompt" | "chat/input" => {
+            let text = string_param("text")?;
+            if text.trim().is_empty() {
+                return Err(RpcError::new(INVALID_PARAMS, "'text' must not be empty"));
+            }
+            if method == "chat/prompt" && text.trim_start().starts_with(['/', '!']) {
+                return Err(RpcError::new(
+                    INVALID_PARAMS,
+                    "'text' must not start with '/' or '!', commands are run with 'chat/command'",
+                ));
+            }
+            Ok(Action::Input(text))
+        },
+        "chat/command" => {
+            let command = string_param("command")?;
+            if !command.starts_with('/') && !command.starts_with('!') {
+                return Err(RpcError::new(INVALID_PARAMS, "'command' must start with '/' or '!'"));
+            }
+            let slash_command = command.strip_prefix('/').and_then(shlex::split).and_then(|args| {
+                SlashCommand::try_parse_from(std::iter::once("slash_command".to_string()).chain(args)).ok()
+            });
+            if slash_command.is_some_and(|command| command.needs_terminal()) {
+                return Err(RpcError::new(
+                    INVALID_PARAMS,
+                    format!("'{command}' needs a terminal and cannot be run in server mode"),
+                ));
+            }
+            Ok(Action::Input(command))
+        },
+        "chat/usage" => Ok(Action::Input("/usage".to_string())),
+        "tools/approve" => {
+            require_approval()?;
+            let trust = params.get("trust").and_then(Value::as_bool).unwrap_or(false);
+            Ok(Action::Input(if trust { "t" } else { "y" }.to_string()))
+        },
+        "tools/deny" => {
+            require_approval()?;
+            Ok(Action::Input("n".to_string()))
+        },
+        "shutdown" => Ok(Action::Shutdown),
+        method => Err(RpcError::new(METHOD_NOT_FOUND, format!("Unknown method '{method}'"))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{
+        Duration,
+        Instant,
+    };
+
+    use super::*;
+
+    /// Output shared with the test, since the connection owns its writer.
+    #[derive(Clone, Default)]
+    struct SharedOutput(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedOutput {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl SharedOutput {
+        /// Waits for the connection to send `count` messages, and takes them.
+        fn messages(&self, count: usize) -> Vec<Value> {
+            let start = Instant::now();
+            loop {
+                let output = String::from_utf8(self.0.lock().unwrap().clone()).unwrap();
+                if output.lines().count() >= count || start.elapsed() > Duration::from_secs(5) {
+                    self.0.lock().unwrap().clear();
+                    return output.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+                }
+                std::thread::sleep(Duration::from_millis(10));
+            }
+        }
+    }
+
+    /// Input the test sends requests to as the session goes.
+    struct ChannelInput {
+        receiver: Receiver<String>,
+        line: io::Cursor<Vec<u8>>,
+    }
+
+    impl io::Read for ChannelInput {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.line.position() == self.line.get_ref().len() as u64 {
+                match self.receiver.recv() {
+                    Ok(line) => self.line = io::Cursor::new(line.into_bytes()),
+                    Err(_) => return Ok(0),
+                }
+            }
+            self.line.read(buf)
+        }
+    }
+
+    fn connection() -> (Connection, Sender<String>, SharedOutput) {
+        let (sender, receiver) = channel();
+        let input = ChannelInput {
+            receiver,
+            line: io::Cursor::new(Vec::new()),
+        };
+        let output = SharedOutput::default();
+        let connection = Connection::new(Box::new(io::BufReader::new(input)), Box::new(output.clone()));
+        (connection, sender, output)
+    }
+
+    fn send_requests(sender: &Sender<String>, requests: &[Value]) {
+        for request in requests {
+            sender.send(format!("{request}\n")).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_prompt_and_approval() {
+        let (mut connection, sender, output) = connection();
+        send_requests(&sender, &[
+            json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize" }),
+            json!({ "jsonrpc": "2.0", "id": 2, "method": "chat/prompt", "params": { "text": "hello" } }),
+        ]);
+
+        // Output printed before the first request is not sent to the client
+        connection.capture().lock().unwrap().extend(b"Welcome");
+        connection.set_state(SessionState::AwaitingPrompt);
+        assert_eq!(connection.read_line(Some("> ")).unwrap().as_deref(), Some("hello"));
+        let messages = output.messages(1);
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0]["id"], 1);
+        assert_eq!(messages[0]["result"]["protocol_version"], PROTOCOL_VERSION);
+
+        // Input is refused while the session is handling the prompt
+        send_requests(&sender, &[
+            json!({ "jsonrpc": "2.0", "id": 3, "method": "chat/prompt", "params": { "text": "again" } }),
+        ]);
+        let messages = output.messages(1);
+        assert_eq!(messages[0]["id"], 3);
+        assert_eq!(messages[0]["error"]["code"], INVALID_REQUEST);
+
+        connection
+            .capture()
+            .lock()
+            .unwrap()
+            .extend(b"\x1b[32mUsing tool\x1b[0m");
+        connection.set_state(SessionState::AwaitingApproval {
+            tool: PendingTool {
+                id: "tool_1".to_string(),
+                name: "fs_write".to_string(),
+                args: json!({}),
+            },
+        });
+        std::thread::scope(|scope| {
+            let input = scope.spawn(|| connection.read_line(Some("> ")).unwrap());
+            let messages = output.messages(1);
+            assert_eq!(messages[0]["id"], 2);
+            assert_eq!(messages[0]["result"]["state"], "awaiting_approval");
+            assert_eq!(messages[0]["result"]["tool"]["name"], "fs_write");
+            assert_eq!(messages[0]["result"]["output"], "Using tool");
+
+            send_requests(&sender, &[
+                json!({ "jsonrpc": "2.0", "id": 4, "method": "tools/approve", "params": { "trust": true } }),
+            ]);
+            assert_eq!(input.join().unwrap().as_deref(), Some("t"));
+        });
+
+        connection.set_state(SessionState::AwaitingPrompt);
+        std::thread::scope(|scope| {
+            let input = scope.spawn(|| connection.read_line(Some("> ")).unwrap());
+            let messages = output.messages(1);
+            assert_eq!(messages[0]["id"], 4);
+            assert_eq!(messages[0]["result"]["state"], "awaiting_prompt");
+
+            send_requests(&sender, &[json!({ "jsonrpc": "2.0", "id": 5, "method": "shutdown" })]);
+            assert_eq!(input.join().unwrap(), None);
+        });
+        let messages = output.messages(1);
+        assert_eq!(messages[0]["id"], 5);
+        assert_eq!(messages[0]["result"], Value::Null);
+
+        // The session is over, even if more requests were to come
+        assert_eq!(connection.read_line(None).unwrap(), None);
+    }
+
+    #[test]
+    fn test_errors() {
+        let (mut connection, sender, output) = connection();
+        connection.set_state(SessionState::AwaitingPrompt);
+        std::thread::scope(|scope| {
+            let input = scope.spawn(|| connection.read_line(None).unwrap());
+            for (request, code) in [
+                (
+                    json!({ "jsonrpc": "2.0", "id": 1, "method": "tools/deny" }),
+                    INVALID_REQUEST,
+                ),
+                (
+                    json!({ "jsonrpc": "2.0", "id": 2, "method": "chat/command", "params": { "command": "usage" } }),
+                    INVALID_PARAMS,
+                ),
+                (
+                    json!({ "jsonrpc": "2.0", "id": 3, "method": "chat/unknown" }),
+                    METHOD_NOT_FOUND,
+                ),
+                (json!({ "id": 4, "method": "chat/usage" }), INVALID_REQUEST),
+                (
+                    json!({ "jsonrpc": "2.0", "id": 5, "method": "chat/prompt", "params": { "text": " /quit" } }),
+                    INVALID_PARAMS,
+                ),
+                (
+                    json!({ "jsonrpc": "2.0", "id": 6, "method": "chat/prompt", "params": { "text": "!rm -rf ." } }),
+                    INVALID_PARAMS,
+                ),
+                (
+                    json!({ "jsonrpc": "2.0", "id": 7, "method": "chat/command", "params": { "command": "/editor" } }),
+                    INVALID_PARAMS,
+                ),
+                (
+                    json!({ "jsonrpc": "2.0", "id": 7, "method": "chat/command", "params": { "command": "/commit" } }),
+                    INVALID_PARAMS,
+                ),
+            ] {
+                send_requests(&sender, &[request.clone()]);
+                let messages = output.messages(1);
+                assert_eq!(messages[0]["error"]["code"], code, "{request}");
+            }
+            send_requests(&sender, &[json!({ "jsonrpc": "2.0", "id": 8, "method": "chat/usage" })]);
+            assert_eq!(input.join().unwrap().as_deref(), Some("/usage"));
+        });
+
+        // The pending request is replied to once the session ended
+        connection.finish().unwrap();
+        let messages = output.messages(1);
+        assert_eq!(messages[0]["id"], 8);
+        assert_eq!(messages[0]["result"]["state"], "exited");
+    }
+}
@@ -32,6 +32,10 @@ pub enum UpdateEventMessage {
         server_name: String,
         result: eyre::Result<ResourceTemplatesListResult>,
     },
+    ResourceUpdated {
+        server_name: String,
+        uri: String,
+    },
     InitStart {
         server_name: String,
     },
@@ -117,6 +121,17 @@ impl Messenger for ServerMessenger {
             .map_err(|e| MessengerError::Custom(e.to_string()))?)
     }
 
+    async fn send_resource_updated(&self, uri: String) -> Result<(), MessengerError> {
+        Ok(self
+            .update_event_sender
+            .send(UpdateEventMessage::ResourceUpdated {
+                server_name: self.server_name.clone(),
+                uri,
+            })
+            .await
+            .map_err(|e| MessengerError::Custom(e.to_string()))?)
+    }
+
     async fn send_init_msg(&self) -> Result<(), MessengerError> {
         Ok(self
             .update_event_sender
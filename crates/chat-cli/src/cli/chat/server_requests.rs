@@ -0,0 +1,687 @@
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{
+    Path,
+    PathBuf,
+};
+use std::sync::{
+    Arc,
+    Mutex as SyncMutex,
+    RwLock as SyncRwLock,
+};
+
+use crossterm::style::{
+    Color,
+    Stylize,
+};
+use crossterm::{
+    execute,
+    style,
+};
+use rustyline::ExternalPrinter;
+use serde_json::{
+    Value,
+    json,
+};
+use tokio::sync::mpsc::{
+    Receiver,
+    Sender,
+    channel,
+};
+use tokio::sync::oneshot;
+
+use super::input_source::InputSource;
+use super::{
+    CONTINUATION_LINE,
+    TOOL_BULLET,
+};
+use crate::api_client::model::{
+    AssistantResponseMessage,
+    ChatMessage,
+    ChatResponseStream,
+    ConversationState as FigConversationState,
+    UserInputMessage,
+};
+use crate::mcp_client::{
+    ServerRequestError,
+    ServerRequestResponder,
+};
+use crate::os::Os;
+
+/// A request from an mcp server that needs the attention of the user before it can be answered.
+#[derive(Debug)]
+pub struct PendingServerRequest {
+    pub server_name: String,
+    pub method: String,
+    pub params: Option<Value>,
+    pub responder: oneshot::Sender<Result<Value, ServerRequestError>>,
+}
+
+/// Tells the user about new requests while they are at the prompt, since the chat session only
+/// gets to serve them once the input is submitted.
+#[derive(Clone, Default)]
+pub struct PromptNotifier(Arc<SyncMutex<PromptNotifierState>>);
+
+#[derive(Default)]
+struct PromptNotifierState {
+    printer: Option<Box<dyn ExternalPrinter + Send>>,
+    at_prompt: bool,
+}
+
+impl std::fmt::Debug for PromptNotifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PromptNotifier").finish_non_exhaustive()
+    }
+}
+
+impl PromptNotifier {
+    /// Sets the printer that writes above the prompt while the user is typing.
+    pub fn set_printer(&self, printer: Box<dyn ExternalPrinter + Send>) {
+        if let Ok(mut state) = self.0.lock() {
+            state.printer = Some(printer);
+        }
+    }
+
+    /// Sets whether the user is at the prompt, where they are told about new requests.
+    pub fn set_at_prompt(&self, at_prompt: bool) {
+        if let Ok(mut state) = self.0.lock() {
+            state.at_prompt = at_prompt;
+        }
+    }
+
+    pub fn is_at_prompt(&self) -> bool {
+        self.0.lock().is_ok_and(|state| state.at_prompt)
+    }
+
+    fn notify(&self, server_name: &str) {
+        let Ok(mut state) = self.0.lock() else {
+            return;
+        };
+        if !state.at_prompt {
+            return;
+        }
+        if let Some(printer) = state.printer.as_mut() {
+            let notice = format!(
+                "\n{} {}\n",
+                format!("{server_name} is waiting on you to review a request.").magenta(),
+                "Press Enter to review it.".dark_grey()
+            );
+            if let Err(err) = printer.print(notice) {
+                tracing::warn!(?err, "Failed to print a notice about a server request");
+            }
+        }
+    }
+}
+
+/// Answers `roots/list` directly from the roots shared with the tool manager, and relays
+/// `sampling/createMessage` and `elicitation/create` to the chat session since they need to be
+/// approved or filled in by the user.
+#[derive(Clone, Debug)]
+pub struct ChatServerRequestResponder {
+    roots: Arc<SyncRwLock<Vec<PathBuf>>>,
+    notifier: PromptNotifier,
+    sender: Sender<PendingServerRequest>,
+}
+
+impl ChatServerRequestResponder {
+    pub fn new(
+        roots: Arc<SyncRwLock<Vec<PathBuf>>>,
+        notifier: PromptNotifier,
+        capacity: usize,
+    ) -> (Receiver<PendingServerRequest>, Self) {
+        let (sender, receiver) = channel::<PendingServerRequest>(capacity);
+        (receiver, Self {
+            roots,
+            notifier,
+            sender,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl ServerRequestResponder for ChatServerRequestResponder {
+    fn capabilities(&self) -> HashMap<String, Value> {
+        HashMap::from([
+            ("roots".to_owned(), json!({ "listChanged": true })),
+            ("sampling".to_owned(), json!({})),
+            ("elicitation".to_owned(), json!({})),
+        ])
+    }
+
+    async fn handle_request(
+        &self,
+        server_name: &str,
+        method: &str,
+        params: Option<Value>,
+    ) -> Result<Value, ServerRequestError> {
+        match method {
+            "roots/list" => {
+                let roots = self
+                    .roots
+                    .read()
+                    .map_err(|e| ServerRequestError::Internal(e.to_string()))?
+                    .iter()
+                    .filter_map(|root| {
+                        let uri = url::Url::from_file_path(root).ok()?;
+                        let name = root.file_name().map(|name| name.to_string_lossy().to_string());
+                        Some(json!({ "uri": uri.to_string(), "name": name }))
+                    })
+                    .collect::<Vec<_>>();
+                Ok(json!({ "roots": roots }))
+            },
+            "sampling/createMessage" | "elicitation/create" => {
+                let (responder, response) = oneshot::channel();
+                self.sender
+                    .send(PendingServerRequest {
+                        server_name: server_name.to_owned(),
+                        method: method.to_owned(),
+                        params,
+                        responder,
+                    })
+                    .await
+                    .map_err(|e| ServerRequestError::Internal(e.to_string()))?;
+                self.notifier.notify(server_name);
+                response
+                    .await
+                    .map_err(|_e| ServerRequestError::Internal("Chat session ended before responding".to_owned()))?
+            },
+            _ => Err(ServerRequestError::MethodNotFound(method.to_owned())),
+        }
+    }
+
+    fn duplicate(&self) -> Box<dyn ServerRequestResponder> {
+        Box::new(self.clone())
+    }
+}
+
+/// Waits for the next request that needs the user's attention. Never resolves if there is no
+/// receiver to wait on.
+pub async fn recv_pending_request(
+    receiver: &mut Option<Receiver<PendingServerRequest>>,
+) -> Option<PendingServerRequest> {
+    match receiver {
+        Some(receiver) => receiver.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Derives the roots exposed to mcp servers from the current working directory and the paths
+/// added to context. Glob patterns contribute the directory they are rooted at, and paths that
+/// are already covered by another root are left out.
+pub fn resolve_roots(cwd: &Path, context_paths: &[String]) -> Vec<PathBuf> {
+    let mut roots = vec![cwd.to_path_buf()];
+    for path in context_paths {
+        let expanded = shellexpand::tilde(path);
+        let mut has_glob = false;
+        let base = Path::new(expanded.as_ref())
+            .components()
+            .take_while(|c| {
+                has_glob = c.as_os_str().to_string_lossy().contains(['*', '?', '[', '{']);
+                !has_glob
+            })
+            .collect::<PathBuf>();
+        let base = cwd.join(base);
+        let dir = if has_glob || base.is_dir() {
+            base
+        } else {
+            match base.parent() {
+                Some(parent) => parent.to_path_buf(),
+                None => continue,
+            }
+        };
+        if !roots.iter().any(|root| dir.starts_with(root)) {
+            roots.retain(|root| !root.starts_with(&dir));
+            roots.push(dir);
+        }
+    }
+    roots
+}
+
+/// Prompts the user for the request and sends the outcome back to the server that made it.
+pub async fn handle_pending_request(
+    os: &Os,
+    output: &mut impl Write,
+    input_source: &mut InputSource,
+    interactive: bool,
+    model_id: Option<String>,
+    request: PendingServerRequest,
+) -> Result<(), std::io::Error> {
+    let PendingServerRequest {
+        server_name,
+        method,
+        params,
+        responder,
+    } = request;
+    let params = params.unwrap_or_default();
+    let result = match method.as_str() {
+        "sampling/createMessage" if !interactive => Err(ServerRequestError::Rejected(
+            "Sampling requests cannot be approved in non-interactive mode".to_owned(),
+        )),
+        "sampling/createMessage" => handle_sampling(os, output, input_source, model_id, &server_name, params).await?,
+        "elicitation/create" if !interactive => Ok(json!({ "action": "decline" })),
+        "elicitation/create" => handle_elicitation(output, input_source, &server_name, params)?,
+        _ => Err(ServerRequestError::MethodNotFound(method)),
+    };
+    let _ = responder.send(result);
+    Ok(())
+}
+
+async fn handle_sampling(
+    os: &Os,
+    output: &mut impl Write,
+    input_source: &mut InputSource,
+    model_id: Option<String>,
+    server_name: &str,
+    params: Value,
+) -> Result<Result<Value, ServerRequestError>, std::io::Error> {
+    let (conversation, preview) = match sampling_conversation(&params, model_id.clone()) {
+        Ok(res) => res,
+        Err(e) => return Ok(Err(e)),
+    };
+
+    execute!(
+        output,
+        style::SetForegroundColor(Color::Magenta),
+        style::Print(format!(
+            "\n{TOOL_BULLET}{server_name} is requesting a model completion:\n"
+        )),
+        style::SetForegroundColor(Color::Reset),
+    )?;
+    for line in preview.lines() {
+        execute!(
+            output,
+            style::Print(CONTINUATION_LINE),
+            style::Print(line),
+            style::Print("\n")
+        )?;
+    }
+    if !confirm(output, input_source, "Allow this request?")? {
+        return Ok(Err(ServerRequestError::Rejected(
+            "The user rejected the sampling request".to_owned(),
+        )));
+    }
+
+    let text = match os.client.send_message(conversation).await {
+        Ok(mut response) => {
+            let mut text = String::new();
+            loop {
+                match response.recv().await {
+                    Ok(Some(ChatResponseStream::AssistantResponseEvent { content })) => text.push_str(&content),
+                    Ok(Some(_)) => {},
+                    Ok(None) => break,
+                    Err(e) => return Ok(Err(ServerRequestError::Internal(e.to_string()))),
+                }
+            }
+            text
+        },
+        Err(e) => return Ok(Err(ServerRequestError::Internal(e.to_string()))),
+    };
+
+    // The user gets to review what is being sent back as well
+    execute!(output, style::Print("\n"))?;
+    for line in text.lines() {
+        execute!(
+            output,
+            style::Print(CONTINUATION_LINE),
+            style::Print(line),
+            style::Print("\n")
+        )?;
+    }
+    if !confirm(output, input_source, &format!("Send this response to {server_name}?"))? {
+        return Ok(Err(ServerRequestError::Rejected(
+            "The user rejected the sampling response".to_owned(),
+        )));
+    }
+
+    Ok(Ok(json!({
+        "role": "assistant",
+        "content": { "type": "text", "text": text },
+        "model": model_id.unwrap_or_else(|| "default".to_owned()),
+        "stopReason": "endTurn",
+    })))
+}
+
+/// Converts the messages of a sampling request into a conversation that can be sent to the
+/// backend, along with a human readable preview of it.
+fn sampling_conversation(
+    params: &Value,
+    model_id: Option<String>,
+) -> Result<(FigConversationState, String), ServerRequestError> {
+    let messages = params
+        .get("messages")
+        .and_then(Value::as_array)
+        .ok_or_else(|| ServerRequestError::InvalidParams("missing messages".to_owned()))?;
+    let system_prompt = params.get("systemPrompt").and_then(Value::as_str);
+
+    let mut preview = String::new();
+    if let Some(system_prompt) = system_prompt {
+        preview.push_str(&format!("system: {system_prompt}\n"));
+    }
+    // Consecutive messages of the same role are merged since the backend expects them to
+    // alternate.
+    let mut turns = Vec::<(String, String)>::new();
+    for message in messages {
+        let role = message.get("role").and_then(Value::as_str).unwrap_or("user");
+        let content = message.get("content");
+        let text = match content.and_then(|c| c.get("type")).and_then(Value::as_str) {
+            Some("text") => content
+                .and_then(|c| c.get("text"))
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_owned(),
+            Some(other) => format!("[{other} content omitted]"),
+            None => String::new(),
+        };
+        preview.push_str(&format!("{role}: {text}\n"));
+        match turns.last_mut() {
+            Some((last_role, last_text)) if last_role == role => {
+                last_text.push_str("\n\n");
+                last_text.push_str(&text);
+            },
+            _ => turns.push((role.to_owned(), text)),
+        }
+    }
+
+    let Some((last_role, last_text)) = turns.pop() else {
+        return Err(ServerRequestError::InvalidParams("messages is empty".to_owned()));
+    };
+    if last_role != "user" {
+        return Err(ServerRequestError::InvalidParams(
+            "the last message must be from the user".to_owned(),
+        ));
+    }
+    let user_message = |content: String| UserInputMessage {
+        content,
+        user_input_message_context: None,
+        user_intent: None,
+        images: None,
+        model_id: model_id.clone(),
+    };
+    let mut history = turns
+        .into_iter()
+        .map(|(role, content)| match role.as_str() {
+            "assistant" => ChatMessage::AssistantResponseMessage(AssistantResponseMessage {
+                message_id: None,
+                content,
+                tool_uses: None,
+            }),
+            _ => ChatMessage::UserInputMessage(user_message(content)),
+        })
+        .collect::<Vec<_>>();
+    let mut content = last_text;
+    if let Some(system_prompt) = system_prompt {
+        match history.first_mut() {
+            Some(ChatMessage::UserInputMessage(first)) => {
+                first.content = format!("{system_prompt}\n\n{}", first.content);
+            },
+            _ => content = format!("{system_prompt}\n\n{content}"),
+        }
+    }
+    if let Some(max_tokens) = params.get("maxTokens").and_then(Value::as_u64) {
+        preview.push_str(&format!("(max tokens: {max_tokens})\n"));
+    }
+
+    Ok((
+        FigConversationState {
+            conversation_id: None,
+            user_input_message: user_message(content),
+            history: (!history.is_empty()).then_some(history),
+        },
+        preview,
+    ))
+}
+
+fn handle_elicitation(
+    output: &mut impl Write,
+    input_source: &mut InputSource,
+    server_name: &str,
+    params: Value,
+) -> Result<Result<Value, ServerRequestError>, std::io::Error> {
+    let message = params.get("message").and_then(Value::as_str).unwrap_or_default();
+    let schema = params.get("requestedSchema").cloned().unwrap_or_default();
+    let required = schema
+        .get("required")
+        .and_then(Value::as_array)
+        .map(|r| {
+            r.iter()
+                .filter_map(Value::as_str)
+                .map(str::to_owned)
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    let properties = schema
+        .get("properties")
+        .and_then(Value::as_object)
+        .cloned()
+        .unwrap_or_default();
+
+    execute!(
+        output,
+        style::SetForegroundColor(Color::Magenta),
+        style::Print(format!("\n{TOOL_BULLET}{server_name} is requesting information:\n")),
+        style::SetForegroundColor(Color::Reset),
+    )?;
+    for line in message.lines() {
+        execute!(
+            output,
+            style::Print(CONTINUATION_LINE),
+            style::Print(line),
+            style::Print("\n")
+        )?;
+    }
+    match confirm_or_cancel(output, input_source, "Provide the requested information?")? {
+        Some(true) => {},
+        Some(false) => return Ok(Ok(json!({ "action": "decline" }))),
+        None => return Ok(Ok(json!({ "action": "cancel" }))),
+    }
+
+    let mut content = serde_json::Map::new();
+    for (name, property) in &properties {
+        let is_required = required.contains(name);
+        let title = property.get("title").and_then(Value::as_str).unwrap_or(name);
+        if let Some(description) = property.get("description").and_then(Value::as_str) {
+            execute!(output, style::Print(format!("{}\n", description.dark_grey())))?;
+        }
+        let options = property.get("enum").and_then(Value::as_array);
+        let prompt = format!(
+            "{title}{}{}: ",
+            options.map_or(String::new(), |o| format!(
+                " [{}]",
+                o.iter().filter_map(Value::as_str).collect::<Vec<_>>().join("/")
+            )),
+            if is_required { " (required)" } else { "" }
+        );
+        loop {
+            let Some(line) = input_source.read_line(Some(&prompt)).ok().flatten() else {
+                return Ok(Ok(json!({ "action": "cancel" })));
+            };
+            let line = line.trim();
+            if line.is_empty() {
+                if is_required {
+                    continue;
+                }
+                break;
+            }
+            match parse_elicitation_value(property, line) {
+                Some(value) => {
+                    content.insert(name.clone(), value);
+                    break;
+                },
+                None => execute!(
+                    output,
+                    style::SetForegroundColor(Color::Red),
+                    style::Print(format!("Invalid value for {title}\n")),
+                    style::SetForegroundColor(Color::Reset),
+                )?,
+            }
+        }
+    }
+
+    Ok(Ok(json!({ "action": "accept", "content": content })))
+}
+
+/// Parses the user's input according to the (primitive) schema of the requested property.
+fn parse_elicitation_value(property: &Value, input: &str) -> Option<Value> {
+    if let Some(options) = property.get("enum").and_then(Value::as_array) {
+        return options.iter().find(|o| o.as_str() == Some(input)).cloned();
+    }
+    match property.get("type").and_then(Value::as_str) {
+        Some("boolean") => match input.to_lowercase().as_str() {
+            "y" | "yes" | "true" => Some(Value::Bool(true)),
+            "n" | "no" | "false" => Some(Value::Bool(false)),
+            _ => None,
+        },
+        Some("integer") => input.parse::<i64>().ok().map(Value::from),
+        Some("number") => input.parse::<f64>().ok().map(Value::from),
+        _ => Some(Value::String(input.to_owned())),
+    }
+}
+
+fn confirm(output: &mut impl Write, input_source: &mut InputSource, question: &str) -> Result<bool, std::io::Error> {
+    Ok(confirm_or_cancel(output, input_source, question)?.unwrap_or(false))
+}
+
+/// Asks a yes or no question, returning [None] if the user hit ctrl+c or ctrl+d instead.
+fn confirm_or_cancel(
+    output: &mut impl Write,
+    input_source: &mut InputSource,
+    question: &str,
+) -> Result<Option<bool>, std::io::Error> {
+    execute!(
+        output,
+        style::SetForegroundColor(Color::DarkGrey),
+        style::Print(format!("\n{question} [")),
+        style::SetForegroundColor(Color::Green),
+        style::Print("y"),
+        style::SetForegroundColor(Color::DarkGrey),
+        style::Print("/"),
+        style::SetForegroundColor(Color::Green),
+        style::Print("n"),
+        style::SetForegroundColor(Color::DarkGrey),
+        style::Print("]:\n\n"),
+        style::SetForegroundColor(Color::Reset),
+    )?;
+    loop {
+        match input_source.read_line(Some("> ")).ok().flatten() {
+            Some(line) => match line.trim().to_lowercase().as_str() {
+                "y" | "yes" => return Ok(Some(true)),
+                "n" | "no" => return Ok(Some(false)),
+                _ => {},
+            },
+            None => return Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_roots() {
+        let cwd = PathBuf::from("/home/user/project");
+        let roots = resolve_roots(&cwd, &[
+            "README.md".to_owned(),
+            "docs/**/*.md".to_owned(),
+            "/etc/config/*.toml".to_owned(),
+            "/etc/config/nested/*.toml".to_owned(),
+        ]);
+        assert_eq!(roots, vec![cwd, PathBuf::from("/etc/config")]);
+    }
+
+    #[derive(Clone, Default)]
+    struct TestPrinter(Arc<SyncMutex<Vec<String>>>);
+
+    impl ExternalPrinter for TestPrinter {
+        fn print(&mut self, msg: String) -> rustyline::Result<()> {
+            self.0.lock().unwrap().push(msg);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_requests_are_announced_at_the_prompt() {
+        let printer = TestPrinter::default();
+        let notifier = PromptNotifier::default();
+        notifier.set_printer(Box::new(printer.clone()));
+        let (mut receiver, responder) = ChatServerRequestResponder::new(Default::default(), notifier.clone(), 1);
+
+        // Requests made while the session is busy are not announced
+        let request = |responder: ChatServerRequestResponder| {
+            tokio::spawn(async move { responder.handle_request("server", "elicitation/create", None).await })
+        };
+        let handle = request(responder.clone());
+        let _ = receiver.recv().await.unwrap().responder.send(Ok(json!({})));
+        handle.await.unwrap().unwrap();
+        assert!(printer.0.lock().unwrap().is_empty());
+
+        notifier.set_at_prompt(true);
+        let handle = request(responder);
+        let _ = receiver.recv().await.unwrap().responder.send(Ok(json!({})));
+        handle.await.unwrap().unwrap();
+        let notices = printer.0.lock().unwrap();
+        assert_eq!(notices.len(), 1);
+        assert!(notices[0].contains("server is waiting on you"));
+    }
+
+    #[test]
+    fn test_sampling_conversation() {
+        let params = json!({
+            "messages": [
+                { "role": "user", "content": { "type": "text", "text": "hello" } },
+                { "role": "assistant", "content": { "type": "text", "text": "hi" } },
+                { "role": "user", "content": { "type": "image", "data": "", "mimeType": "image/png" } },
+                { "role": "user", "content": { "type": "text", "text": "what is this?" } },
+            ],
+            "systemPrompt": "be brief",
+            "maxTokens": 100,
+        });
+        let (conversation, preview) = sampling_conversation(&params, None).unwrap();
+        assert_eq!(
+            conversation.user_input_message.content,
+            "[image content omitted]\n\nwhat is this?"
+        );
+        let history = conversation.history.unwrap();
+        assert_eq!(history.len(), 2);
+        assert!(matches!(&history[0], ChatMessage::UserInputMessage(m) if m.content == "be brief\n\nhello"));
+        assert!(preview.contains("max tokens: 100"));
+
+        let params = json!({ "messages": [{ "role": "assistant", "content": { "type": "text", "text": "hi" } }] });
+        assert!(sampling_conversation(&params, None).is_err());
+    }
+
+    #[test]
+    fn test_parse_elicitation_value() {
+        assert_eq!(
+            parse_elicitation_value(&json!({ "type": "boolean" }), "Yes"),
+            Some(Value::Bool(true))
+        );
+        assert_eq!(parse_elicitation_value(&json!({ "type": "integer" }), "1.5"), None);
+        assert_eq!(
+            parse_elicitation_value(&json!({ "type": "number" }), "1.5"),
+            Some(json!(1.5))
+        );
+        assert_eq!(
+            parse_elicitation_value(&json!({ "type": "string", "enum": ["a", "b"] }), "c"),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_elicitation_flow() {
+        let mut input_source = InputSource::new_mock(vec!["y".to_owned(), "".to_owned(), "octocat".to_owned()]);
+        let params = json!({
+            "message": "Who are you?",
+            "requestedSchema": {
+                "type": "object",
+                "properties": { "name": { "type": "string" } },
+                "required": ["name"],
+            },
+        });
+        let result = handle_elicitation(&mut vec![], &mut input_source, "test", params).unwrap();
+        assert_eq!(
+            result.unwrap(),
+            json!({ "action": "accept", "content": { "name": "octocat" } })
+        );
+    }
+}
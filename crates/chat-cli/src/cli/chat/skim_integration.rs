@@ -23,7 +23,12 @@ use rustyline::{
 use skim::prelude::*;
 use tempfile::NamedTempFile;
 
+use super::cli::sessions::{
+    format_session,
+    short_id,
+};
 use super::context::ContextManager;
+use crate::database::SessionSummary;
 use crate::os::Os;
 
 pub fn select_profile_with_skim(os: &Os, context_manager: &ContextManager) -> Result<Option<String>> {
@@ -33,6 +38,16 @@ pub fn select_profile_with_skim(os: &Os, context_manager: &ContextManager) -> Re
         .map(|selected| selected.and_then(|s| s.into_iter().next()))
 }
 
+/// Select a session using skim, returning its id
+pub fn select_session_with_skim(sessions: &[SessionSummary]) -> Result<Option<String>> {
+    let items: Vec<String> = sessions.iter().map(format_session).collect();
+
+    Ok(launch_skim_selector(&items, "Select session: ", false)?
+        .and_then(|selected| selected.into_iter().next())
+        .and_then(|line| sessions.iter().find(|session| format_session(session) == line))
+        .map(|session| session.id.clone()))
+}
+
 pub struct SkimCommandSelector {
     os: Os,
     context_manager: Arc<ContextManager>,
@@ -296,6 +311,13 @@ pub fn select_command(os: &Os, context_manager: &ContextManager, tools: &[String
                     // For other profile operations (like create), just return the command
                     Ok(Some(selected_command.clone()))
                 },
+                Some(CommandType::Sessions(_)) => {
+                    let sessions = os.database.list_sessions()?;
+                    match select_session_with_skim(&sessions)? {
+                        Some(id) => Ok(Some(format!("{} {}", selected_command, short_id(&id)))),
+                        None => Ok(Some(selected_command.clone())), // User cancelled session selection
+                    }
+                },
                 None => {
                     // Command doesn't need additional parameters
                     Ok(Some(selected_command.clone()))
@@ -312,6 +334,7 @@ enum CommandType {
     ContextRemove(String),
     Tools(&'static str),
     Profile(&'static str),
+    Sessions(&'static str),
 }
 
 impl CommandType {
@@ -332,6 +355,8 @@ impl CommandType {
                 "/profile delete" => Some(CommandType::Profile("delete")),
                 "/profile rename" => Some(CommandType::Profile("rename")),
                 "/profile create" => Some(CommandType::Profile("create")),
+                "/sessions resume" => Some(CommandType::Sessions("resume")),
+                "/sessions delete" => Some(CommandType::Sessions("delete")),
                 _ => None,
             }
         }
@@ -363,6 +388,8 @@ mod tests {
             "/profile delete",
             "/profile rename",
             "/profile create",
+            "/sessions resume",
+            "/sessions delete",
         ];
 
         // Check that each hardcoded command is in the COMMANDS array
@@ -7,10 +7,8 @@ use super::message::{
     UserMessage,
     UserMessageContent,
 };
-use crate::cli::chat::conversation::{
-    BackendConversationState,
-    ConversationSize,
-};
+use super::tokenizer::tokenizer;
+use crate::cli::chat::conversation::BackendConversationState;
 
 #[derive(Debug, Clone, Copy)]
 pub struct CharCount(usize);
@@ -60,9 +58,23 @@ impl Deref for TokenCount {
     }
 }
 
-impl From<CharCount> for TokenCount {
-    fn from(value: CharCount) -> Self {
-        Self(TokenCounter::count_tokens_char_count(value.value()))
+impl From<usize> for TokenCount {
+    fn from(value: usize) -> Self {
+        Self(value)
+    }
+}
+
+impl std::ops::Add for TokenCount {
+    type Output = TokenCount;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self(self.value() + rhs.value())
+    }
+}
+
+impl std::iter::Sum for TokenCount {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self(0), |acc, count| acc + count)
     }
 }
 
@@ -77,41 +89,37 @@ pub struct TokenCounter;
 impl TokenCounter {
     pub const TOKEN_TO_CHAR_RATIO: usize = 4;
 
-    /// Estimates the number of tokens in the input content.
-    /// Currently uses a simple heuristic: content length / TOKEN_TO_CHAR_RATIO
-    ///
-    /// Rounds up to the nearest multiple of 10 to avoid giving users a false sense of precision.
+    /// Counts the number of tokens in the input content with the [tokenizer] selected by the
+    /// `chat.tokenizer` setting.
     pub fn count_tokens(content: &str) -> usize {
-        Self::count_tokens_char_count(content.len())
-    }
-
-    fn count_tokens_char_count(count: usize) -> usize {
-        (count / Self::TOKEN_TO_CHAR_RATIO + 5) / 10 * 10
-    }
-
-    pub const fn token_to_chars(token: usize) -> usize {
-        token * Self::TOKEN_TO_CHAR_RATIO
+        tokenizer().count_tokens(content)
     }
 }
 
-/// A trait for types that represent some number of characters (aka bytes). For use in calculating
-/// context window size utilization.
+/// A trait for types that represent some number of characters (aka bytes) and tokens. For use in
+/// calculating context window size utilization.
 pub trait CharCounter {
     /// Returns the number of characters contained within this type.
     ///
     /// One "character" is essentially the same as one "byte"
     fn char_count(&self) -> CharCount;
+
+    /// Returns the number of tokens contained within this type, as counted by the [tokenizer].
+    fn token_count(&self) -> TokenCount;
 }
 
 impl CharCounter for BackendConversationState<'_> {
     fn char_count(&self) -> CharCount {
-        self.calculate_conversation_size().char_count()
+        self.history
+            .clone()
+            .chain(self.context_messages.iter().flatten())
+            .fold(CharCount::from(0), |acc, (user, assistant)| {
+                acc + user.char_count() + assistant.char_count()
+            })
     }
-}
 
-impl CharCounter for ConversationSize {
-    fn char_count(&self) -> CharCount {
-        self.user_messages + self.assistant_messages + self.context_messages
+    fn token_count(&self) -> TokenCount {
+        self.calculate_conversation_size().total()
     }
 }
 
@@ -136,6 +144,26 @@ impl CharCounter for UserMessage {
         }
         total_chars.into()
     }
+
+    fn token_count(&self) -> TokenCount {
+        let mut total_tokens = TokenCounter::count_tokens(self.additional_context());
+        match self.content() {
+            UserMessageContent::Prompt { prompt } => {
+                total_tokens += TokenCounter::count_tokens(prompt);
+            },
+            UserMessageContent::CancelledToolUses {
+                prompt,
+                tool_use_results,
+            } => {
+                total_tokens += prompt.as_deref().map_or(0, TokenCounter::count_tokens);
+                total_tokens += tool_use_results.as_slice().token_count().0;
+            },
+            UserMessageContent::ToolUseResults { tool_use_results } => {
+                total_tokens += tool_use_results.as_slice().token_count().0;
+            },
+        }
+        total_tokens.into()
+    }
 }
 
 impl CharCounter for AssistantMessage {
@@ -151,6 +179,17 @@ impl CharCounter for AssistantMessage {
         }
         total_chars.into()
     }
+
+    fn token_count(&self) -> TokenCount {
+        let mut total_tokens = TokenCounter::count_tokens(self.content());
+        if let Some(tool_uses) = self.tool_uses() {
+            total_tokens += tool_uses
+                .iter()
+                .map(|v| TokenCounter::count_tokens(&v.name) + calculate_value_token_count(&v.args))
+                .sum::<usize>();
+        }
+        total_tokens.into()
+    }
 }
 
 impl CharCounter for &[ToolUseResult] {
@@ -165,6 +204,25 @@ impl CharCounter for &[ToolUseResult] {
             })
             .into()
     }
+
+    fn token_count(&self) -> TokenCount {
+        self.iter()
+            .flat_map(|v| &v.content)
+            .map(|v| match v {
+                ToolUseResultBlock::Json(v) => calculate_value_token_count(v),
+                ToolUseResultBlock::Text(s) => TokenCounter::count_tokens(s),
+            })
+            .sum::<usize>()
+            .into()
+    }
+}
+
+/// Counts the tokens of a JSON document as it is sent to the model, i.e. including its syntax.
+fn calculate_value_token_count(document: &serde_json::Value) -> usize {
+    match document {
+        serde_json::Value::String(s) => TokenCounter::count_tokens(s),
+        document => TokenCounter::count_tokens(&document.to_string()),
+    }
 }
 
 fn calculate_value_char_count(document: &serde_json::Value) -> usize {
@@ -187,7 +245,24 @@ mod tests {
     fn test_token_count() {
         let text = "This is a test sentence.";
         let count = TokenCounter::count_tokens(text);
-        assert_eq!(count, (text.len() / 3 + 5) / 10 * 10);
+        assert_eq!(count, 7);
+    }
+
+    #[test]
+    fn test_message_token_count() {
+        let user = UserMessage::new_prompt("This is a test sentence.".to_string());
+        assert_eq!(*user.token_count(), 7);
+
+        let results = vec![ToolUseResult {
+            tool_use_id: "1".to_string(),
+            content: vec![
+                ToolUseResultBlock::Text("fn main() {}".to_string()),
+                ToolUseResultBlock::Json(serde_json::json!({ "key": "value" })),
+            ],
+            status: crate::api_client::model::ToolResultStatus::Success,
+        }];
+        let json_tokens = TokenCounter::count_tokens(r#"{"key":"value"}"#);
+        assert_eq!(*results.as_slice().token_count(), 4 + json_tokens);
     }
 
     #[test]
@@ -0,0 +1,232 @@
+//! Tokenizers used to count the tokens of conversations, to manage the context window.
+//!
+//! The tokenizers of the models are not public, so by default tokens are counted with a
+//! byte-level BPE tokenizer whose merges are bundled in `bpe_merges.txt`. They were trained on
+//! source code and English documentation with `scripts/train-tokenizer.py`. Unlike estimates
+//! from the length of the text, counts follow how the text splits into words, e.g. taking more
+//! tokens for non-ASCII text, but they remain estimates of the counts of the models.
+
+use std::collections::HashMap;
+use std::sync::{
+    LazyLock,
+    Mutex,
+    OnceLock,
+};
+
+use regex::Regex;
+
+use super::token_counter::TokenCounter;
+use crate::database::settings::{
+    Setting,
+    Settings,
+};
+
+/// Splits text into words, which are tokenized independently. Mirrors the pattern of
+/// `scripts/train-tokenizer.py`.
+const PATTERN: &str = r"'(?:s|t|re|ve|m|ll|d)| ?\p{L}+| ?\d{1,3}| ?[^\s\p{L}\d]+|\s+";
+
+/// Each line is a merge of two token ids, in order of priority. Ids below 256 are single bytes,
+/// and the merge on line `i` creates the token `256 + i`.
+const BPE_MERGES: &str = include_str!("bpe_merges.txt");
+
+/// Longer words, e.g. base64 encoded data, are tokenized in chunks of this many bytes since the
+/// cost of merging grows with the square of the length.
+const MAX_WORD_LEN: usize = 256;
+
+/// Number of word counts cached by [BpeTokenizer] before the cache is cleared.
+const MAX_CACHED_WORDS: usize = 100_000;
+
+static TOKENIZER: OnceLock<Box<dyn Tokenizer>> = OnceLock::new();
+
+/// Counts the tokens of text.
+pub trait Tokenizer: Send + Sync {
+    fn count_tokens(&self, text: &str) -> usize;
+}
+
+/// The tokenizer backends that can be selected with the `chat.tokenizer` setting.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TokenizerBackend {
+    /// [BpeTokenizer] with the bundled merges
+    #[default]
+    Bpe,
+    /// [EstimateTokenizer]
+    Estimate,
+}
+
+impl TokenizerBackend {
+    pub fn from_settings(settings: &Settings) -> Self {
+        match settings.get_string(Setting::ChatTokenizer).as_deref() {
+            Some("estimate") => Self::Estimate,
+            _ => Self::Bpe,
+        }
+    }
+
+    fn build(self) -> Box<dyn Tokenizer> {
+        match self {
+            Self::Bpe => Box::new(BpeTokenizer::bundled()),
+            Self::Estimate => Box::new(EstimateTokenizer),
+        }
+    }
+}
+
+/// Sets the tokenizer returned by [tokenizer]. Has no effect once it was used.
+pub fn init(backend: TokenizerBackend) {
+    let _ = TOKENIZER.get_or_init(|| backend.build());
+}
+
+/// The tokenizer used for token counts, [TokenizerBackend::Bpe] unless [init] said otherwise.
+pub fn tokenizer() -> &'static dyn Tokenizer {
+    TOKENIZER.get_or_init(|| TokenizerBackend::default().build()).as_ref()
+}
+
+/// Estimates tokens from the length of the text, as one token per
+/// [TokenCounter::TOKEN_TO_CHAR_RATIO] bytes.
+#[derive(Debug, Clone, Copy)]
+pub struct EstimateTokenizer;
+
+impl Tokenizer for EstimateTokenizer {
+    fn count_tokens(&self, text: &str) -> usize {
+        text.len().div_ceil(TokenCounter::TOKEN_TO_CHAR_RATIO)
+    }
+}
+
+/// A byte-level BPE tokenizer.
+#[derive(Debug)]
+pub struct BpeTokenizer {
+    /// The rank, i.e. the priority, of each merge of two tokens.
+    ranks: HashMap<(u32, u32), u32>,
+    pattern: Regex,
+    /// Token counts of the words seen so far, since conversations are counted again on every
+    /// request.
+    cache: Mutex<HashMap<String, usize>>,
+}
+
+impl BpeTokenizer {
+    /// A tokenizer with the bundled merges, which are only parsed once.
+    pub fn bundled() -> Self {
+        static RANKS: LazyLock<HashMap<(u32, u32), u32>> =
+            LazyLock::new(|| BpeTokenizer::parse_merges(BPE_MERGES).expect("the bundled merges are valid"));
+        Self::new(RANKS.clone())
+    }
+
+    fn new(ranks: HashMap<(u32, u32), u32>) -> Self {
+        Self {
+            ranks,
+            pattern: Regex::new(PATTERN).expect("the pattern is valid"),
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Parses merges in the format of `bpe_merges.txt`, returning [None] if a line is invalid.
+    fn parse_merges(merges: &str) -> Option<HashMap<(u32, u32), u32>> {
+        merges
+            .lines()
+            .enumerate()
+            .map(|(rank, line)| {
+                let (left, right) = line.split_once(' ')?;
+                Some(((left.parse().ok()?, right.parse().ok()?), rank.try_into().ok()?))
+            })
+            .collect()
+    }
+
+    fn count_word(&self, word: &[u8]) -> usize {
+        let mut tokens: Vec<u32> = word.iter().map(|byte| u32::from(*byte)).collect();
+        loop {
+            let best = tokens
+                .windows(2)
+                .filter_map(|pair| {
+                    self.ranks
+                        .get(&(pair[0], pair[1]))
+                        .map(|rank| (*rank, (pair[0], pair[1])))
+                })
+                .min();
+            let Some((rank, pair)) = best else {
+                return tokens.len();
+            };
+
+            let mut merged = Vec::with_capacity(tokens.len());
+            let mut i = 0;
+            while i < tokens.len() {
+                if i + 1 < tokens.len() && (tokens[i], tokens[i + 1]) == pair {
+                    merged.push(256 + rank);
+                    i += 2;
+                } else {
+                    merged.push(tokens[i]);
+                    i += 1;
+                }
+            }
+            tokens = merged;
+        }
+    }
+}
+
+impl Tokenizer for BpeTokenizer {
+    fn count_tokens(&self, text: &str) -> usize {
+        let mut cache = self.cache.lock().unwrap_or_else(|err| err.into_inner());
+        if cache.len() > MAX_CACHED_WORDS {
+            cache.clear();
+        }
+
+        let mut count = 0;
+        for word in self.pattern.find_iter(text).map(|word| word.as_str()) {
+            if let Some(word_count) = cache.get(word) {
+                count += word_count;
+                continue;
+            }
+            let word_count = word
+                .as_bytes()
+                .chunks(MAX_WORD_LEN)
+                .map(|chunk| self.count_word(chunk))
+                .sum();
+            cache.insert(word.to_string(), word_count);
+            count += word_count;
+        }
+        count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bpe_tokenizer() {
+        let tokenizer = BpeTokenizer::bundled();
+        assert_eq!(tokenizer.count_tokens(""), 0);
+
+        // Common words and code are only a few tokens
+        assert_eq!(tokenizer.count_tokens(" the"), 1);
+        assert_eq!(tokenizer.count_tokens("This is a test sentence."), 7);
+        assert_eq!(tokenizer.count_tokens("fn main() {}"), 4);
+
+        // Text that is not in the training data takes more tokens per byte than code or English
+        let english = "The quick brown fox jumps over the lazy dog.";
+        let greek = "Η γρήγορη καφέ αλεπού πηδάει πάνω από τον τεμπέλη σκύλο.";
+        let english_ratio = tokenizer.count_tokens(english) as f64 / english.len() as f64;
+        let greek_ratio = tokenizer.count_tokens(greek) as f64 / greek.len() as f64;
+        assert!(greek_ratio > english_ratio, "{greek_ratio}");
+
+        // Long words are tokenized in chunks
+        let blob = "a".repeat(MAX_WORD_LEN * 4);
+        assert!(tokenizer.count_tokens(&blob) < blob.len());
+    }
+
+    #[test]
+    fn test_parse_merges() {
+        let ranks = BpeTokenizer::parse_merges("97 98\n256 99\n").unwrap();
+        let tokenizer = BpeTokenizer::new(ranks);
+        assert_eq!(tokenizer.count_word(b"abc"), 1);
+        assert_eq!(tokenizer.count_word(b"abab"), 2);
+        assert_eq!(tokenizer.count_word(b"cab"), 2);
+        assert_eq!(tokenizer.count_tokens("abc abc"), 3);
+
+        assert!(BpeTokenizer::parse_merges("97\n").is_none());
+        assert!(BpeTokenizer::parse_merges("a b\n").is_none());
+    }
+
+    #[test]
+    fn test_estimate_tokenizer() {
+        assert_eq!(EstimateTokenizer.count_tokens(""), 0);
+        assert_eq!(EstimateTokenizer.count_tokens("abcde"), 2);
+    }
+}
@@ -64,11 +64,17 @@ use crate::api_client::model::{
     ToolResultStatus,
 };
 use crate::cli::chat::cli::prompts::GetPromptError;
+use crate::cli::chat::cli::resources::GetResourceError;
 use crate::cli::chat::message::AssistantToolUse;
 use crate::cli::chat::server_messenger::{
     ServerMessengerBuilder,
     UpdateEventMessage,
 };
+use crate::cli::chat::server_requests::{
+    ChatServerRequestResponder,
+    PendingServerRequest,
+    PromptNotifier,
+};
 use crate::cli::chat::tools::custom_tool::{
     CustomTool,
     CustomToolClient,
@@ -78,6 +84,7 @@ use crate::cli::chat::tools::execute::ExecuteCommand;
 use crate::cli::chat::tools::fs_read::FsRead;
 use crate::cli::chat::tools::fs_write::FsWrite;
 use crate::cli::chat::tools::gh_issue::GhIssue;
+use crate::cli::chat::tools::git::Git;
 use crate::cli::chat::tools::knowledge::Knowledge;
 use crate::cli::chat::tools::thinking::Thinking;
 use crate::cli::chat::tools::use_aws::UseAws;
@@ -86,11 +93,18 @@ use crate::cli::chat::tools::{
     ToolOrigin,
     ToolSpec,
 };
+use crate::cli::chat::workspace_config::{
+    ConfigScope,
+    load_workspace_config,
+};
 use crate::database::settings::Setting;
 use crate::mcp_client::{
     JsonRpcResponse,
     Messenger,
     PromptGet,
+    ResourceInfo,
+    ResourceReadResult,
+    ResourceTemplate,
 };
 use crate::os::Os;
 use crate::telemetry::TelemetryThread;
@@ -99,7 +113,7 @@ use crate::util::directories::{
     home_dir,
 };
 
-const NAMESPACE_DELIMITER: &str = "___";
+pub(crate) const NAMESPACE_DELIMITER: &str = "___";
 // This applies for both mcp server and tool name since in the end the tool name as seen by the
 // model is just {server_name}{NAMESPACE_DELIMITER}{tool_name}
 const VALID_TOOL_NAME: &str = "^[a-zA-Z][a-zA-Z0-9_]*$";
@@ -119,40 +133,62 @@ pub fn profile_mcp_path(os: &Os, profile_name: &str) -> eyre::Result<PathBuf> {
 
 /// Gets MCP server configuration for a specific profile, using the multi-scope loading logic
 pub async fn get_mcp_server_configs_for_profile(os: &Os, profile_name: Option<&str>) -> eyre::Result<McpServerConfig> {
-    // This is a simplified version of the multi-scope loading logic for the reload scenario
-    // It loads configurations in priority order: Profile → Workspace → Global
+    // Servers of the layers with a higher precedence replace those of the same name
     let mut final_config = McpServerConfig::default();
-    
+    for (scope, config) in mcp_server_config_layers(os, profile_name).await? {
+        final_config.mcp_servers.extend(config.mcp_servers);
+        if scope == ConfigScope::Profile {
+            final_config.use_profile_servers_only = config.use_profile_servers_only;
+        }
+    }
+
+    Ok(final_config)
+}
+
+/// The MCP server configurations that apply to a chat with `profile_name`, in increasing order of
+/// precedence: global, workspace (`.amazonq/mcp.json` and then the `mcpServers` of
+/// `.amazonq/config.json`) and profile. Only the profile's is returned if it sets
+/// `useProfileServersOnly`. Configurations that fail to load are skipped.
+pub async fn mcp_server_config_layers(
+    os: &Os,
+    profile_name: Option<&str>,
+) -> eyre::Result<Vec<(ConfigScope, McpServerConfig)>> {
+    let mut layers = Vec::new();
+
     // Load global config first (lowest priority)
     let global_path = global_mcp_config_path(os)?;
     if os.fs.exists(&global_path) {
         match McpServerConfig::load_from_file(os, &global_path).await {
-            Ok(config) => {
-                for (name, server_config) in config.mcp_servers {
-                    final_config.mcp_servers.insert(name, server_config);
-                }
-            },
+            Ok(config) => layers.push((ConfigScope::Global, config)),
             Err(e) => {
                 warn!("Failed to load global MCP config: {}", e);
-            }
+            },
         }
     }
-    
-    // Load workspace config (medium priority)
+
+    // Load workspace configs (medium priority)
     let workspace_path = workspace_mcp_config_path(os)?;
     if os.fs.exists(&workspace_path) {
         match McpServerConfig::load_from_file(os, &workspace_path).await {
-            Ok(config) => {
-                for (name, server_config) in config.mcp_servers {
-                    final_config.mcp_servers.insert(name, server_config);
-                }
-            },
+            Ok(config) => layers.push((ConfigScope::Workspace, config)),
             Err(e) => {
                 warn!("Failed to load workspace MCP config: {}", e);
-            }
+            },
         }
     }
-    
+    match load_workspace_config(os).await {
+        Ok(config) if !config.mcp_servers.is_empty() => {
+            layers.push((ConfigScope::Workspace, McpServerConfig {
+                mcp_servers: config.mcp_servers,
+                use_profile_servers_only: false,
+            }));
+        },
+        Ok(_) => {},
+        Err(e) => {
+            warn!("Failed to load workspace config: {}", e);
+        },
+    }
+
     // Load profile config if specified (highest priority)
     if let Some(profile_name) = profile_name {
         let profile_path = profile_mcp_path(os, profile_name)?;
@@ -162,23 +198,18 @@ pub async fn get_mcp_server_configs_for_profile(os: &Os, profile_name: Option<&s
                     // Check for profile exclusivity
                     if config.use_profile_servers_only {
                         // Return only profile servers when exclusivity is enabled
-                        return Ok(config);
+                        return Ok(vec![(ConfigScope::Profile, config)]);
                     }
-                    
-                    // Otherwise, merge profile servers with others (profile takes precedence)
-                    for (name, server_config) in config.mcp_servers {
-                        final_config.mcp_servers.insert(name, server_config);
-                    }
-                    final_config.use_profile_servers_only = config.use_profile_servers_only;
+                    layers.push((ConfigScope::Profile, config));
                 },
                 Err(e) => {
                     warn!("Failed to load profile MCP config for {}: {}", profile_name, e);
-                }
+                },
             }
         }
     }
-    
-    Ok(final_config)
+
+    Ok(layers)
 }
 
 /// Messages used for communication between the tool initialization thread and the loading
@@ -229,7 +260,6 @@ pub struct McpServerConfig {
 }
 
 impl McpServerConfig {
-
     pub async fn load_from_file(os: &Os, path: impl AsRef<Path>) -> eyre::Result<Self> {
         let contents = os.fs.read_to_string(path.as_ref()).await?;
         Ok(serde_json::from_str(&contents)?)
@@ -240,7 +270,6 @@ impl McpServerConfig {
         os.fs.write(path.as_ref(), json).await?;
         Ok(())
     }
-
 }
 
 #[derive(Default)]
@@ -278,7 +307,10 @@ impl ToolManagerBuilder {
         mut output: Box<dyn Write + Send + Sync + 'static>,
         interactive: bool,
     ) -> eyre::Result<ToolManager> {
-        let McpServerConfig { mcp_servers, use_profile_servers_only: _ } = self.mcp_server_config.ok_or(eyre::eyre!("Missing mcp server config"))?;
+        let McpServerConfig {
+            mcp_servers,
+            use_profile_servers_only: _,
+        } = self.mcp_server_config.ok_or(eyre::eyre!("Missing mcp server config"))?;
         debug_assert!(self.conversation_id.is_some());
         let conversation_id = self.conversation_id.ok_or(eyre::eyre!("Missing conversation id"))?;
         let regex = regex::Regex::new(VALID_TOOL_NAME)?;
@@ -440,6 +472,16 @@ impl ToolManagerBuilder {
         let notify_weak = Arc::downgrade(&notify);
         let load_record = Arc::new(Mutex::new(HashMap::<String, Vec<LoadingRecord>>::new()));
         let load_record_clone = load_record.clone();
+        // Servers are started before the chat session (and thus its context) is loaded. The
+        // roots will be refined by the session once it is.
+        let roots = Arc::new(SyncRwLock::new(os.env.current_dir().into_iter().collect::<Vec<_>>()));
+        let prompt_notifier = PromptNotifier::default();
+        let (server_requests, server_request_responder) =
+            ChatServerRequestResponder::new(roots.clone(), prompt_notifier.clone(), 10);
+        let resources = Arc::new(SyncRwLock::new(HashMap::<String, ServerResources>::new()));
+        let resources_clone = resources.clone();
+        let updated_resources = Arc::new(SyncRwLock::new(Vec::<(String, String)>::new()));
+        let updated_resources_clone = updated_resources.clone();
         tokio::spawn(async move {
             let mut record_temp_buf = Vec::<u8>::new();
             let mut initialized = HashSet::<String>::new();
@@ -581,14 +623,31 @@ impl ToolManagerBuilder {
                         server_name: _,
                         result: _,
                     } => {},
-                    UpdateEventMessage::ResourcesListResult {
-                        server_name: _,
-                        result: _,
-                    } => {},
-                    UpdateEventMessage::ResourceTemplatesListResult {
-                        server_name: _,
-                        result: _,
-                    } => {},
+                    UpdateEventMessage::ResourcesListResult { server_name, result } => match result {
+                        Ok(result) => {
+                            let list = deserialize_list::<ResourceInfo>(&server_name, result.resources);
+                            if let Ok(mut resources) = resources_clone.write() {
+                                resources.entry(server_name).or_default().resources = list;
+                            }
+                        },
+                        Err(e) => warn!("Failed to retrieve resources for {server_name}: {:?}", e),
+                    },
+                    UpdateEventMessage::ResourceTemplatesListResult { server_name, result } => match result {
+                        Ok(result) => {
+                            let list = deserialize_list::<ResourceTemplate>(&server_name, result.resource_templates);
+                            if let Ok(mut resources) = resources_clone.write() {
+                                resources.entry(server_name).or_default().templates = list;
+                            }
+                        },
+                        Err(e) => warn!("Failed to retrieve resource templates for {server_name}: {:?}", e),
+                    },
+                    UpdateEventMessage::ResourceUpdated { server_name, uri } => {
+                        if let Ok(mut updated_resources) = updated_resources_clone.write() {
+                            if !updated_resources.iter().any(|(s, u)| *s == server_name && *u == uri) {
+                                updated_resources.push((server_name, uri));
+                            }
+                        }
+                    },
                     UpdateEventMessage::InitStart { server_name } => {
                         pending_clone.write().await.insert(server_name.clone());
                         loading_servers.insert(server_name, std::time::Instant::now());
@@ -601,6 +660,7 @@ impl ToolManagerBuilder {
             match init_res {
                 Ok(mut client) => {
                     client.assign_messenger(Box::new(messenger));
+                    client.assign_server_request_responder(Box::new(server_request_responder.clone()));
                     let mut client = Arc::new(client);
                     while let Some(collided_client) = clients.insert(name.clone(), client) {
                         // to avoid server name collision we are going to circumvent this by
@@ -720,6 +780,11 @@ impl ToolManagerBuilder {
             is_interactive: interactive,
             mcp_load_record: load_record,
             disabled_servers: disabled_servers_display,
+            roots,
+            server_requests: Some(server_requests),
+            prompt_notifier,
+            resources,
+            updated_resources,
             ..Default::default()
         })
     }
@@ -736,6 +801,13 @@ pub struct PromptBundle {
     pub prompt_get: PromptGet,
 }
 
+#[derive(Clone, Debug, Default)]
+/// Resources and resource templates offered by a server
+pub struct ServerResources {
+    pub resources: Vec<ResourceInfo>,
+    pub templates: Vec<ResourceTemplate>,
+}
+
 /// Categorizes different types of tool name validation failures:
 /// - `TooLong`: The tool name exceeds the maximum allowed length
 /// - `IllegalChar`: The tool name contains characters that are not allowed
@@ -812,6 +884,23 @@ pub struct ToolManager {
 
     /// List of disabled MCP server names for display purposes
     disabled_servers: Vec<String>,
+
+    /// Directories exposed to mcp servers via `roots/list`
+    roots: Arc<SyncRwLock<Vec<PathBuf>>>,
+
+    /// Requests from mcp servers that are waiting on the user, e.g. for sampling approval
+    pub server_requests: Option<tokio::sync::mpsc::Receiver<PendingServerRequest>>,
+
+    /// Tells the user about [Self::server_requests] made while they are at the prompt
+    pub prompt_notifier: PromptNotifier,
+
+    /// Cache for resources and resource templates collected from different servers.
+    /// Key: server name
+    pub resources: Arc<SyncRwLock<HashMap<String, ServerResources>>>,
+
+    /// Subscribed resources (as server name and uri) that servers have reported as updated since
+    /// they were last taken
+    updated_resources: Arc<SyncRwLock<Vec<(String, String)>>>,
 }
 
 impl Clone for ToolManager {
@@ -827,6 +916,10 @@ impl Clone for ToolManager {
             is_interactive: self.is_interactive,
             mcp_load_record: self.mcp_load_record.clone(),
             disabled_servers: self.disabled_servers.clone(),
+            roots: self.roots.clone(),
+            prompt_notifier: self.prompt_notifier.clone(),
+            resources: self.resources.clone(),
+            updated_resources: self.updated_resources.clone(),
             ..Default::default()
         }
     }
@@ -995,6 +1088,7 @@ impl ToolManager {
             },
             "use_aws" => Tool::UseAws(serde_json::from_value::<UseAws>(value.args).map_err(map_err)?),
             "report_issue" => Tool::GhIssue(serde_json::from_value::<GhIssue>(value.args).map_err(map_err)?),
+            "git" => Tool::Git(serde_json::from_value::<Git>(value.args).map_err(map_err)?),
             "thinking" => Tool::Thinking(serde_json::from_value::<Thinking>(value.args).map_err(map_err)?),
             "knowledge" => Tool::Knowledge(serde_json::from_value::<Knowledge>(value.args).map_err(map_err)?),
             // Note that this name is namespaced with server_name{DELIMITER}tool_name
@@ -1281,9 +1375,91 @@ impl ToolManager {
         Ok(())
     }
 
+    /// Reads the resource identified by `uri` from the server named `server_name`
+    pub async fn read_resource(&self, server_name: &str, uri: &str) -> Result<ResourceReadResult, GetResourceError> {
+        let client = self
+            .clients
+            .get(server_name)
+            .ok_or(GetResourceError::ServerNotFound(server_name.to_string()))?;
+        let params = serde_json::json!({ "uri": uri });
+        let resp = client.request("resources/read", Some(params)).await?;
+        if let Some(error) = resp.error {
+            return Err(GetResourceError::Mcp(serde_json::json!(error)));
+        }
+        let result = resp.result.ok_or(GetResourceError::MissingResult)?;
+        Ok(serde_json::from_value::<ResourceReadResult>(result).map_err(|e| eyre::eyre!(e))?)
+    }
+
+    /// Subscribes to (or unsubscribes from) updates of the resource identified by `uri`.
+    ///
+    /// Returns false without making a request if the server does not support subscriptions.
+    pub async fn set_resource_subscription(
+        &self,
+        server_name: &str,
+        uri: &str,
+        subscribe: bool,
+    ) -> Result<bool, GetResourceError> {
+        let client = self
+            .clients
+            .get(server_name)
+            .ok_or(GetResourceError::ServerNotFound(server_name.to_string()))?;
+        if !client.supports_resource_subscriptions().await {
+            return Ok(false);
+        }
+        let method = if subscribe {
+            "resources/subscribe"
+        } else {
+            "resources/unsubscribe"
+        };
+        let resp = client.request(method, Some(serde_json::json!({ "uri": uri }))).await?;
+        if let Some(error) = resp.error {
+            return Err(GetResourceError::Mcp(serde_json::json!(error)));
+        }
+        Ok(true)
+    }
+
+    /// Drains the subscribed resources that have been updated since this was last called
+    pub fn take_updated_resources(&self) -> Vec<(String, String)> {
+        self.updated_resources
+            .write()
+            .map(|mut updated| std::mem::take(&mut *updated))
+            .unwrap_or_default()
+    }
+
     pub async fn pending_clients(&self) -> Vec<String> {
         self.pending_clients.read().await.iter().cloned().collect::<Vec<_>>()
     }
+
+    /// Replaces the roots exposed to mcp servers, notifying them if they have changed.
+    pub fn update_roots(&self, roots: Vec<PathBuf>) {
+        let Ok(mut current_roots) = self.roots.write() else {
+            error!("Failed to obtain write lock on roots");
+            return;
+        };
+        if *current_roots == roots {
+            return;
+        }
+        *current_roots = roots;
+        for client in self.clients.values() {
+            let client = client.clone();
+            tokio::spawn(async move {
+                if let Err(e) = client.notify("roots/list_changed", None).await {
+                    warn!("Failed to notify {} of roots change: {:?}", client.get_server_name(), e);
+                }
+            });
+        }
+    }
+}
+
+/// Deserializes the entries of a list result, skipping (and logging) the ones that are malformed
+fn deserialize_list<T: serde::de::DeserializeOwned>(server_name: &str, list: Vec<serde_json::Value>) -> Vec<T> {
+    list.into_iter()
+        .filter_map(|v| {
+            serde_json::from_value::<T>(v)
+                .map_err(|e| warn!("Skipping malformed list entry from {server_name}: {:?}", e))
+                .ok()
+        })
+        .collect()
 }
 
 #[inline]
@@ -4,8 +4,10 @@ use std::sync::Arc;
 use std::sync::atomic::Ordering;
 
 use crossterm::{
+    cursor,
     queue,
     style,
+    terminal,
 };
 use eyre::Result;
 use serde::{
@@ -21,25 +23,37 @@ use crate::cli::chat::token_counter::TokenCounter;
 use crate::mcp_client::{
     Client as McpClient,
     ClientConfig as McpClientConfig,
+    HttpClientConfig as McpHttpClientConfig,
+    HttpTransport,
     JsonRpcResponse,
     JsonRpcStdioTransport,
     MessageContent,
     Messenger,
+    ProgressNotification,
     PromptGet,
     ServerCapabilities,
+    ServerRequestResponder,
     StdioTransport,
     ToolCallResult,
 };
 use crate::os::Os;
 
-// TODO: support http transport type
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct CustomToolConfig {
+    /// Command used to launch a server that communicates over stdio
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub command: String,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub args: Vec<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub env: Option<HashMap<String, String>>,
+    /// Endpoint of a remote server that communicates over streamable http (or the older http with
+    /// sse transport). Takes precedence over [Self::command] when both are present.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Headers to include in every request sent to [Self::url], e.g. for authorization
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub headers: Option<HashMap<String, String>>,
     #[serde(default = "default_timeout")]
     pub timeout: u64,
     #[serde(default)]
@@ -57,27 +71,54 @@ pub enum CustomToolClient {
         client: McpClient<StdioTransport>,
         server_capabilities: RwLock<Option<ServerCapabilities>>,
     },
+    Http {
+        server_name: String,
+        client: McpClient<HttpTransport>,
+        server_capabilities: RwLock<Option<ServerCapabilities>>,
+    },
 }
 
 impl CustomToolClient {
-    // TODO: add support for http transport
     pub fn from_config(server_name: String, config: CustomToolConfig) -> Result<Self> {
         let CustomToolConfig {
             command,
             args,
             env,
+            url,
+            headers,
             timeout,
             disabled: _,
         } = config;
+        let client_info = serde_json::json!({
+           "name": "Q CLI Chat",
+           "version": "1.0.0"
+        });
+        if let Some(url) = url {
+            let mcp_client_config = McpHttpClientConfig {
+                server_name: server_name.clone(),
+                url,
+                headers,
+                timeout,
+                client_info,
+            };
+            let client = McpClient::<HttpTransport>::from_config(mcp_client_config)?;
+            return Ok(CustomToolClient::Http {
+                server_name,
+                client,
+                server_capabilities: RwLock::new(None),
+            });
+        }
+        if command.is_empty() {
+            return Err(eyre::eyre!(
+                "Server {server_name} has neither a command nor a url configured"
+            ));
+        }
         let mcp_client_config = McpClientConfig {
             server_name: server_name.clone(),
             bin_path: command.clone(),
             args,
             timeout,
-            client_info: serde_json::json!({
-               "name": "Q CLI Chat",
-               "version": "1.0.0"
-            }),
+            client_info,
             env,
         };
         let client = McpClient::<JsonRpcStdioTransport>::from_config(mcp_client_config)?;
@@ -106,6 +147,18 @@ impl CustomToolClient {
                 server_capabilities.write().await.replace(cap);
                 Ok(())
             },
+            CustomToolClient::Http {
+                client,
+                server_capabilities,
+                ..
+            } => {
+                if let Some(messenger) = &client.messenger {
+                    let _ = messenger.send_init_msg().await;
+                }
+                let cap = client.init().await?;
+                server_capabilities.write().await.replace(cap);
+                Ok(())
+            },
         }
     }
 
@@ -114,43 +167,97 @@ impl CustomToolClient {
             CustomToolClient::Stdio { client, .. } => {
                 client.messenger = Some(messenger);
             },
+            CustomToolClient::Http { client, .. } => {
+                client.messenger = Some(messenger);
+            },
+        }
+    }
+
+    pub fn assign_server_request_responder(&mut self, handler: Box<dyn ServerRequestResponder>) {
+        match self {
+            CustomToolClient::Stdio { client, .. } => {
+                client.server_request_responder = Some(handler);
+            },
+            CustomToolClient::Http { client, .. } => {
+                client.server_request_responder = Some(handler);
+            },
         }
     }
 
     pub fn get_server_name(&self) -> &str {
         match self {
-            CustomToolClient::Stdio { server_name, .. } => server_name.as_str(),
+            CustomToolClient::Stdio { server_name, .. } | CustomToolClient::Http { server_name, .. } => {
+                server_name.as_str()
+            },
         }
     }
 
     pub async fn request(&self, method: &str, params: Option<serde_json::Value>) -> Result<JsonRpcResponse> {
         match self {
             CustomToolClient::Stdio { client, .. } => Ok(client.request(method, params).await?),
+            CustomToolClient::Http { client, .. } => Ok(client.request(method, params).await?),
+        }
+    }
+
+    pub async fn request_with_progress(
+        &self,
+        method: &str,
+        params: Option<serde_json::Value>,
+        progress_sender: tokio::sync::mpsc::Sender<ProgressNotification>,
+    ) -> Result<JsonRpcResponse> {
+        match self {
+            CustomToolClient::Stdio { client, .. } => {
+                Ok(client.request_with_progress(method, params, progress_sender).await?)
+            },
+            CustomToolClient::Http { client, .. } => {
+                Ok(client.request_with_progress(method, params, progress_sender).await?)
+            },
         }
     }
 
     pub fn list_prompt_gets(&self) -> Arc<std::sync::RwLock<HashMap<String, PromptGet>>> {
         match self {
             CustomToolClient::Stdio { client, .. } => client.prompt_gets.clone(),
+            CustomToolClient::Http { client, .. } => client.prompt_gets.clone(),
         }
     }
 
-    #[allow(dead_code)]
     pub async fn notify(&self, method: &str, params: Option<serde_json::Value>) -> Result<()> {
         match self {
             CustomToolClient::Stdio { client, .. } => Ok(client.notify(method, params).await?),
+            CustomToolClient::Http { client, .. } => Ok(client.notify(method, params).await?),
         }
     }
 
     pub fn is_prompts_out_of_date(&self) -> bool {
         match self {
             CustomToolClient::Stdio { client, .. } => client.is_prompts_out_of_date.load(Ordering::Relaxed),
+            CustomToolClient::Http { client, .. } => client.is_prompts_out_of_date.load(Ordering::Relaxed),
         }
     }
 
+    /// Whether the server has declared support for `resources/subscribe`
+    pub async fn supports_resource_subscriptions(&self) -> bool {
+        let server_capabilities = match self {
+            CustomToolClient::Stdio {
+                server_capabilities, ..
+            }
+            | CustomToolClient::Http {
+                server_capabilities, ..
+            } => server_capabilities.read().await,
+        };
+        server_capabilities
+            .as_ref()
+            .and_then(|cap| cap.resources.as_ref())
+            .and_then(|r| r.get("subscribe"))
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false)
+    }
+
     pub fn prompts_updated(&self) {
         match self {
             CustomToolClient::Stdio { client, .. } => client.is_prompts_out_of_date.store(false, Ordering::Relaxed),
+            CustomToolClient::Http { client, .. } => client.is_prompts_out_of_date.store(false, Ordering::Relaxed),
         }
     }
 }
@@ -172,9 +279,40 @@ pub struct CustomTool {
 }
 
 impl CustomTool {
-    pub async fn invoke(&self, _os: &Os, _updates: impl Write) -> Result<InvokeOutput> {
+    pub async fn invoke(&self, _os: &Os, mut updates: impl Write) -> Result<InvokeOutput> {
         // Assuming a response shape as per https://spec.modelcontextprotocol.io/specification/2024-11-05/server/tools/#calling-tools
-        let resp = self.client.request(self.method.as_str(), self.params.clone()).await?;
+        // Dropping this future (e.g. on ctrl-c) notifies the server that the call is cancelled.
+        let (progress_sender, mut progress_receiver) = tokio::sync::mpsc::channel::<ProgressNotification>(16);
+        let request = self
+            .client
+            .request_with_progress(self.method.as_str(), self.params.clone(), progress_sender);
+        tokio::pin!(request);
+        let mut has_shown_progress = false;
+        let resp = loop {
+            tokio::select! {
+                resp = &mut request => break resp,
+                Some(progress) = progress_receiver.recv() => {
+                    queue!(
+                        updates,
+                        cursor::MoveToColumn(0),
+                        terminal::Clear(terminal::ClearType::CurrentLine),
+                        style::SetForegroundColor(style::Color::DarkGrey),
+                        style::Print(format_progress(&progress)),
+                        style::ResetColor,
+                    )?;
+                    updates.flush()?;
+                    has_shown_progress = true;
+                },
+            }
+        };
+        if has_shown_progress {
+            queue!(
+                updates,
+                cursor::MoveToColumn(0),
+                terminal::Clear(terminal::ClearType::CurrentLine),
+            )?;
+        }
+        let resp = resp?;
         let result = match resp.result {
             Some(result) => result,
             None => {
@@ -243,3 +381,60 @@ impl CustomTool {
             + TokenCounter::count_tokens(self.params.as_ref().map_or("", |p| p.as_str().unwrap_or_default()))
     }
 }
+
+/// Renders a line describing the progress reported for a tool call. A bar is drawn when the
+/// server reports the total amount of work; otherwise only the progress thus far is shown.
+fn format_progress(progress: &ProgressNotification) -> String {
+    const BAR_WIDTH: usize = 30;
+    let ProgressNotification {
+        progress,
+        total,
+        message,
+        ..
+    } = progress;
+    let mut line = match total {
+        Some(total) if *total > 0.0 => {
+            let ratio = (progress / total).clamp(0.0, 1.0);
+            let filled = (ratio * BAR_WIDTH as f64).round() as usize;
+            format!(
+                "{CONTINUATION_LINE}[{}{}] {:>3.0}%",
+                "█".repeat(filled),
+                "░".repeat(BAR_WIDTH - filled),
+                ratio * 100.0
+            )
+        },
+        _ => format!("{CONTINUATION_LINE}Progress: {progress}"),
+    };
+    if let Some(message) = message {
+        line.push(' ');
+        line.push_str(message);
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn progress(progress: f64, total: Option<f64>, message: Option<&str>) -> ProgressNotification {
+        ProgressNotification {
+            progress_token: serde_json::json!(1),
+            progress,
+            total,
+            message: message.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_format_progress() {
+        let line = format_progress(&progress(15.0, Some(60.0), Some("Compiling")));
+        assert!(line.ends_with(&format!("[{}{}]  25% Compiling", "█".repeat(8), "░".repeat(22))));
+
+        // Progress beyond the total is capped
+        let line = format_progress(&progress(90.0, Some(60.0), None));
+        assert!(line.ends_with(&format!("[{}] 100%", "█".repeat(30))));
+
+        let line = format_progress(&progress(3.0, None, Some("files scanned")));
+        assert!(line.ends_with("Progress: 3 files scanned"));
+    }
+}
@@ -5,7 +5,10 @@ use crossterm::style::{
     self,
     Color,
 };
-use eyre::Result;
+use eyre::{
+    Result,
+    bail,
+};
 use serde::Deserialize;
 
 use crate::cli::chat::tools::{
@@ -16,6 +19,13 @@ use crate::cli::chat::tools::{
 use crate::cli::chat::util::truncate_safe;
 use crate::os::Os;
 
+pub mod parser;
+pub mod policy;
+pub mod sandbox;
+
+pub use policy::CommandPolicy;
+use sandbox::Sandbox;
+
 // Platform-specific modules
 #[cfg(windows)]
 mod windows;
@@ -27,7 +37,8 @@ mod unix;
 #[cfg(not(windows))]
 pub use unix::*;
 
-// Common readonly commands that are safe to execute without user confirmation
+// Common readonly commands that are safe to execute without user confirmation. See
+// [CommandPolicy::readonly] for the subcommands that are.
 pub const READONLY_COMMANDS: &[&str] = &[
     "ls", "cat", "echo", "pwd", "which", "head", "tail", "find", "grep", "dir", "type",
 ];
@@ -36,68 +47,24 @@ pub const READONLY_COMMANDS: &[&str] = &[
 pub struct ExecuteCommand {
     pub command: String,
     pub summary: Option<String>,
+    /// The sandbox the command runs in, if sandboxing is enabled
+    #[serde(skip)]
+    pub sandbox: Option<Sandbox>,
 }
 
 impl ExecuteCommand {
-    pub fn requires_acceptance(&self) -> bool {
-        let Some(args) = shlex::split(&self.command) else {
-            return true;
-        };
-
-        const DANGEROUS_PATTERNS: &[&str] = &["<(", "$(", "`", ">", "&&", "||", "&", ";"];
-        if args
-            .iter()
-            .any(|arg| DANGEROUS_PATTERNS.iter().any(|p| arg.contains(p)))
-        {
-            return true;
-        }
-
-        // Split commands by pipe and check each one
-        let mut current_cmd = Vec::new();
-        let mut all_commands = Vec::new();
-
-        for arg in args {
-            if arg == "|" {
-                if !current_cmd.is_empty() {
-                    all_commands.push(current_cmd);
-                }
-                current_cmd = Vec::new();
-            } else if arg.contains("|") {
-                // if pipe appears without spacing e.g. `echo myimportantfile|args rm` it won't get
-                // parsed out, in this case - we want to verify before running
-                return true;
-            } else {
-                current_cmd.push(arg);
-            }
-        }
-        if !current_cmd.is_empty() {
-            all_commands.push(current_cmd);
-        }
-
-        // Check if each command in the pipe chain starts with a safe command
-        for cmd_args in all_commands {
-            match cmd_args.first() {
-                // Special casing for `find` so that we support most cases while safeguarding
-                // against unwanted mutations
-                Some(cmd)
-                    if cmd == "find"
-                        && cmd_args
-                            .iter()
-                            .any(|arg| arg.contains("-exec") || arg.contains("-delete")) =>
-                {
-                    return true;
-                },
-                Some(cmd) if !READONLY_COMMANDS.contains(&cmd.as_str()) => return true,
-                None => return true,
-                _ => (),
-            }
-        }
-
-        false
+    pub fn requires_acceptance(&self, policy: &CommandPolicy) -> bool {
+        policy.requires_acceptance(&self.command)
     }
 
     pub async fn invoke(&self, output: &mut impl Write) -> Result<InvokeOutput> {
-        let output = run_command(&self.command, MAX_TOOL_RESPONSE_SIZE / 3, Some(output)).await?;
+        let output = run_command(
+            &self.command,
+            self.sandbox.as_ref(),
+            MAX_TOOL_RESPONSE_SIZE / 3,
+            Some(output),
+        )
+        .await?;
         let result = serde_json::json!({
             "exit_status": output.exit_status.unwrap_or(0).to_string(),
             "stdout": output.stdout,
@@ -125,6 +92,15 @@ impl ExecuteCommand {
             style::ResetColor
         )?;
 
+        if let Some(ref sandbox) = self.sandbox {
+            queue!(
+                output,
+                style::SetForegroundColor(Color::DarkGrey),
+                style::Print(format!("Sandboxed: {}\n", sandbox.description())),
+                style::ResetColor
+            )?;
+        }
+
         // Add the summary if available
         if let Some(ref summary) = self.summary {
             super::display_purpose(Some(summary), output)?;
@@ -136,6 +112,9 @@ impl ExecuteCommand {
     }
 
     pub async fn validate(&mut self, _os: &Os) -> Result<()> {
+        if self.command.trim().is_empty() {
+            bail!("Command must not be empty")
+        }
         // TODO: probably some small amount of PATH checking
         Ok(())
     }
@@ -191,7 +170,7 @@ mod tests {
             }))
             .unwrap();
             assert_eq!(
-                tool.requires_acceptance(),
+                tool.requires_acceptance(&CommandPolicy::readonly()),
                 *expected,
                 "expected command: `{}` to have requires_acceptance: `{}`",
                 cmd,
@@ -199,4 +178,67 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_requires_acceptance_for_shell_commands() {
+        let cmds = &[
+            // Read-only commands and subcommands
+            ("git status", false),
+            ("git log --oneline -n 5 | head -3", false),
+            ("cargo metadata --format-version 1", false),
+            ("ls -la && git diff HEAD~1", false),
+            ("(ls src; pwd) | grep mod", false),
+            ("ls 2>/dev/null || echo missing", false),
+            ("find . -name '*.rs' 2>&1 | grep chat", false),
+            ("cat <<EOF\nhello\nEOF", false),
+            ("cat <<'EOF'\n$(rm -rf /)\nEOF", false),
+            ("echo \"$(pwd)\"", true),
+            ("cd src && ls", true),
+            ("ls ~/.ssh", true),
+            // Mutating subcommands and arguments
+            ("git push", true),
+            ("git -C other push", true),
+            ("git log --output=log.txt", true),
+            ("find . -name '*.rs' -delete", true),
+            ("find . -exec rm {} \\;", true),
+            // Mutations hidden in lists, substitutions and redirections
+            ("ls; rm -rf target", true),
+            ("ls & rm file", true),
+            ("echo $(rm -rf /)", true),
+            ("echo `rm file`", true),
+            ("cat < <(rm file)", true),
+            ("cat <<EOF\n$(rm -rf /)\nEOF", true),
+            ("ls > files.txt", true),
+            ("GIT_EXTERNAL_DIFF=./script git diff", true),
+            // Syntax that is not understood
+            ("if true; then ls; fi", true),
+            ("echo 'unterminated", true),
+        ];
+
+        for (cmd, expected) in cmds {
+            let tool = serde_json::from_value::<ExecuteCommand>(serde_json::json!({
+                "command": cmd,
+            }))
+            .unwrap();
+            assert_eq!(
+                tool.requires_acceptance(&CommandPolicy::readonly()),
+                *expected,
+                "expected command: `{}` to have requires_acceptance: `{}`",
+                cmd,
+                expected
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_validate_rejects_empty_commands() {
+        let os = Os::new().await.unwrap();
+        for (cmd, valid) in [("ls", true), ("", false), (" \n\t", false)] {
+            let mut tool = serde_json::from_value::<ExecuteCommand>(serde_json::json!({
+                "command": cmd,
+            }))
+            .unwrap();
+            assert_eq!(tool.validate(&os).await.is_ok(), valid, "{:?}", cmd);
+        }
+    }
 }
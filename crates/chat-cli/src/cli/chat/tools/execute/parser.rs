@@ -0,0 +1,996 @@
+//! A parser for the subset of the shell grammar needed to tell which commands a command line will
+//! run: lists, pipelines, subshells, brace groups, redirections (including heredocs), and command,
+//! process and parameter substitutions. Control flow constructs such as `if` and `for`, and
+//! function definitions, are not supported and are reported as errors.
+
+use thiserror::Error;
+
+/// Words that start compound commands we do not parse
+const RESERVED_WORDS: &[&str] = &[
+    "if", "then", "else", "elif", "fi", "do", "done", "case", "esac", "while", "until", "for", "in", "select",
+    "function", "coproc", "[[", "]]",
+];
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ParseError {
+    #[error("Unexpected end of input")]
+    UnexpectedEof,
+    #[error("Unexpected '{0}'")]
+    Unexpected(char),
+    #[error("Unsupported shell syntax: {0}")]
+    Unsupported(String),
+}
+
+/// A parsed command line
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Script {
+    pub list: List,
+    /// Bodies of the heredocs in the script, indexed by [RedirectionOperator::HereDoc]
+    pub heredocs: Vec<Word>,
+}
+
+/// Commands separated by `;`, `&` or newlines
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct List {
+    pub items: Vec<AndOrList>,
+}
+
+/// Pipelines joined by `&&` and `||`
+#[derive(Debug, Clone, PartialEq)]
+pub struct AndOrList {
+    pub first: Pipeline,
+    pub rest: Vec<(AndOrOperator, Pipeline)>,
+    /// Whether the list is run asynchronously, i.e. it is terminated by `&`
+    pub background: bool,
+}
+
+impl AndOrList {
+    pub fn pipelines(&self) -> impl Iterator<Item = &Pipeline> {
+        std::iter::once(&self.first).chain(self.rest.iter().map(|(_, pipeline)| pipeline))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AndOrOperator {
+    And,
+    Or,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pipeline {
+    pub negated: bool,
+    pub commands: Vec<Command>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Simple(SimpleCommand),
+    /// `( list )`
+    Subshell(List, Vec<Redirection>),
+    /// `{ list; }`
+    Group(List, Vec<Redirection>),
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SimpleCommand {
+    /// Variable assignments preceding the command, e.g. `FOO=bar`
+    pub assignments: Vec<Word>,
+    /// The command name followed by its arguments
+    pub words: Vec<Word>,
+    pub redirections: Vec<Redirection>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Redirection {
+    /// The file descriptor being redirected, if given explicitly
+    pub fd: Option<u32>,
+    pub operator: RedirectionOperator,
+    /// The file, file descriptor or heredoc delimiter the redirection refers to
+    pub target: Word,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RedirectionOperator {
+    /// `<`
+    Input,
+    /// `>`
+    Output,
+    /// `>>`
+    Append,
+    /// `>|`
+    Clobber,
+    /// `<>`
+    ReadWrite,
+    /// `<&`
+    DuplicateInput,
+    /// `>&`
+    DuplicateOutput,
+    /// `&>`
+    OutputAndError,
+    /// `&>>`
+    AppendOutputAndError,
+    /// `<<` and `<<-`, with the index of the body in [Script::heredocs]
+    HereDoc(usize),
+    /// `<<<`
+    HereString,
+}
+
+/// A word after quote removal
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Word {
+    /// The text of the word with quotes removed. Expansions and substitutions are kept as they were
+    /// written.
+    pub value: String,
+    /// Whether any part of the word was quoted or escaped
+    pub quoted: bool,
+    /// Whether the word contains parameter, arithmetic, brace, tilde or pathname expansions
+    pub has_expansion: bool,
+    /// Command and process substitutions in the word, which are run when the word is expanded
+    pub substitutions: Vec<List>,
+}
+
+impl Word {
+    /// Whether the value of the word is known without running anything, i.e. it has no expansions
+    /// or substitutions.
+    pub fn is_static(&self) -> bool {
+        !self.has_expansion && self.substitutions.is_empty()
+    }
+}
+
+impl Script {
+    /// All the simple commands of the script, including those in subshells, brace groups and
+    /// substitutions
+    pub fn simple_commands(&self) -> Vec<&SimpleCommand> {
+        let mut commands = vec![];
+        collect_list(&self.list, &mut commands);
+        for body in &self.heredocs {
+            collect_word(body, &mut commands);
+        }
+        commands
+    }
+}
+
+fn collect_list<'a>(list: &'a List, commands: &mut Vec<&'a SimpleCommand>) {
+    let pipelines = list.items.iter().flat_map(|item| item.pipelines());
+    for command in pipelines.flat_map(|pipeline| pipeline.commands.iter()) {
+        match command {
+            Command::Simple(simple) => {
+                commands.push(simple);
+                let words = simple.assignments.iter().chain(&simple.words);
+                for word in words.chain(simple.redirections.iter().map(|r| &r.target)) {
+                    collect_word(word, commands);
+                }
+            },
+            Command::Subshell(list, redirections) | Command::Group(list, redirections) => {
+                collect_list(list, commands);
+                for redirection in redirections {
+                    collect_word(&redirection.target, commands);
+                }
+            },
+        }
+    }
+}
+
+fn collect_word<'a>(word: &'a Word, commands: &mut Vec<&'a SimpleCommand>) {
+    for list in &word.substitutions {
+        collect_list(list, commands);
+    }
+}
+
+struct PendingHereDoc {
+    id: usize,
+    delimiter: String,
+    strip_tabs: bool,
+    expand: bool,
+}
+
+pub fn parse(input: &str) -> Result<Script, ParseError> {
+    let mut parser = Parser::new(input);
+    let list = parser.parse_list(None)?;
+    if let Some(c) = parser.peek() {
+        return Err(ParseError::Unexpected(c));
+    }
+    Ok(Script {
+        list,
+        heredocs: parser.heredocs,
+    })
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    heredocs: Vec<Word>,
+    pending_heredocs: Vec<PendingHereDoc>,
+}
+
+impl Parser {
+    fn new(input: &str) -> Self {
+        Self {
+            chars: input.chars().collect(),
+            pos: 0,
+            heredocs: vec![],
+            pending_heredocs: vec![],
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.peek_at(0)
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.pos + offset).copied()
+    }
+
+    /// Consumes `s` if the input continues with it
+    fn eat(&mut self, s: &str) -> bool {
+        let matches = s.chars().enumerate().all(|(i, c)| self.peek_at(i) == Some(c));
+        if matches {
+            self.pos += s.chars().count();
+        }
+        matches
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), ParseError> {
+        match self.peek() {
+            Some(next) if next == c => {
+                self.pos += 1;
+                Ok(())
+            },
+            Some(next) => Err(ParseError::Unexpected(next)),
+            None => Err(ParseError::UnexpectedEof),
+        }
+    }
+
+    /// Skips blanks, line continuations and comments
+    fn skip_blanks(&mut self) {
+        loop {
+            match self.peek() {
+                Some(' ' | '\t') => self.pos += 1,
+                Some('\\') if self.peek_at(1) == Some('\n') => self.pos += 2,
+                Some('#') => {
+                    while self.peek().is_some_and(|c| c != '\n') {
+                        self.pos += 1;
+                    }
+                },
+                _ => break,
+            }
+        }
+    }
+
+    /// Skips blanks and newlines, reading the bodies of heredocs that start after each newline
+    fn skip_linebreaks(&mut self) -> Result<(), ParseError> {
+        loop {
+            self.skip_blanks();
+            if self.peek() != Some('\n') {
+                return Ok(());
+            }
+            self.pos += 1;
+            self.read_heredoc_bodies()?;
+        }
+    }
+
+    fn parse_list(&mut self, closing: Option<char>) -> Result<List, ParseError> {
+        let mut items = vec![];
+        loop {
+            self.skip_linebreaks()?;
+            let at_end = match self.peek() {
+                None | Some(')') => true,
+                Some('}') => closing == Some('}') && is_boundary(self.peek_at(1)),
+                _ => false,
+            };
+            if at_end {
+                break;
+            }
+
+            let mut and_or = self.parse_and_or()?;
+            self.skip_blanks();
+            let separated = match self.peek() {
+                Some(';') if self.peek_at(1) != Some(';') => {
+                    self.pos += 1;
+                    true
+                },
+                Some('&') => {
+                    self.pos += 1;
+                    and_or.background = true;
+                    true
+                },
+                Some('\n') => true,
+                _ => false,
+            };
+            items.push(and_or);
+            if !separated {
+                break;
+            }
+        }
+        Ok(List { items })
+    }
+
+    fn parse_and_or(&mut self) -> Result<AndOrList, ParseError> {
+        let first = self.parse_pipeline()?;
+        let mut rest = vec![];
+        loop {
+            self.skip_blanks();
+            let operator = if self.eat("&&") {
+                AndOrOperator::And
+            } else if self.eat("||") {
+                AndOrOperator::Or
+            } else {
+                break;
+            };
+            self.skip_linebreaks()?;
+            rest.push((operator, self.parse_pipeline()?));
+        }
+        Ok(AndOrList {
+            first,
+            rest,
+            background: false,
+        })
+    }
+
+    fn parse_pipeline(&mut self) -> Result<Pipeline, ParseError> {
+        self.skip_blanks();
+        let negated = self.peek() == Some('!') && is_boundary(self.peek_at(1));
+        if negated {
+            self.pos += 1;
+        }
+        let mut commands = vec![self.parse_command()?];
+        loop {
+            self.skip_blanks();
+            if self.peek() != Some('|') || self.peek_at(1) == Some('|') {
+                break;
+            }
+            self.pos += 1;
+            // `|&` also pipes stderr
+            self.eat("&");
+            self.skip_linebreaks()?;
+            commands.push(self.parse_command()?);
+        }
+        Ok(Pipeline { negated, commands })
+    }
+
+    fn parse_command(&mut self) -> Result<Command, ParseError> {
+        self.skip_blanks();
+        match self.peek() {
+            Some('(') => {
+                self.pos += 1;
+                let list = self.parse_list(Some(')'))?;
+                self.expect(')')?;
+                Ok(Command::Subshell(list, self.parse_redirections()?))
+            },
+            Some('{') if is_boundary(self.peek_at(1)) => {
+                self.pos += 1;
+                let list = self.parse_list(Some('}'))?;
+                self.expect('}')?;
+                Ok(Command::Group(list, self.parse_redirections()?))
+            },
+            _ => self.parse_simple_command().map(Command::Simple),
+        }
+    }
+
+    fn parse_redirections(&mut self) -> Result<Vec<Redirection>, ParseError> {
+        let mut redirections = vec![];
+        loop {
+            self.skip_blanks();
+            match self.parse_redirection()? {
+                Some(redirection) => redirections.push(redirection),
+                None => return Ok(redirections),
+            }
+        }
+    }
+
+    fn parse_simple_command(&mut self) -> Result<SimpleCommand, ParseError> {
+        let mut command = SimpleCommand::default();
+        loop {
+            self.skip_blanks();
+            if let Some(redirection) = self.parse_redirection()? {
+                command.redirections.push(redirection);
+                continue;
+            }
+            match self.peek() {
+                None | Some('\n' | ';' | '&' | '|' | '(' | ')') => break,
+                _ => {},
+            }
+            let word = self.parse_word()?;
+            if command.words.is_empty() && is_assignment(&word) {
+                command.assignments.push(word);
+            } else {
+                if command.words.is_empty() && !word.quoted && RESERVED_WORDS.contains(&word.value.as_str()) {
+                    return Err(ParseError::Unsupported(word.value));
+                }
+                command.words.push(word);
+            }
+        }
+
+        if command.words.is_empty() && command.assignments.is_empty() && command.redirections.is_empty() {
+            return Err(self.peek().map_or(ParseError::UnexpectedEof, ParseError::Unexpected));
+        }
+        Ok(command)
+    }
+
+    fn parse_redirection(&mut self) -> Result<Option<Redirection>, ParseError> {
+        let start = self.pos;
+        let mut digits = String::new();
+        while let Some(c) = self.peek().filter(char::is_ascii_digit) {
+            digits.push(c);
+            self.pos += 1;
+        }
+        // `<(` and `>(` start process substitutions rather than redirections
+        if matches!(self.peek(), Some('<' | '>')) && self.peek_at(1) == Some('(') {
+            self.pos = start;
+            return Ok(None);
+        }
+
+        let mut strip_tabs = false;
+        let operator = if self.eat("<<<") {
+            RedirectionOperator::HereString
+        } else if self.eat("<<-") {
+            strip_tabs = true;
+            RedirectionOperator::HereDoc(self.heredocs.len())
+        } else if self.eat("<<") {
+            RedirectionOperator::HereDoc(self.heredocs.len())
+        } else if self.eat("<>") {
+            RedirectionOperator::ReadWrite
+        } else if self.eat("<&") {
+            RedirectionOperator::DuplicateInput
+        } else if self.eat("<") {
+            RedirectionOperator::Input
+        } else if self.eat(">>") {
+            RedirectionOperator::Append
+        } else if self.eat(">|") {
+            RedirectionOperator::Clobber
+        } else if self.eat(">&") {
+            RedirectionOperator::DuplicateOutput
+        } else if self.eat(">") {
+            RedirectionOperator::Output
+        } else if digits.is_empty() && self.eat("&>>") {
+            RedirectionOperator::AppendOutputAndError
+        } else if digits.is_empty() && self.eat("&>") {
+            RedirectionOperator::OutputAndError
+        } else {
+            self.pos = start;
+            return Ok(None);
+        };
+
+        self.skip_blanks();
+        match self.peek() {
+            None => return Err(ParseError::UnexpectedEof),
+            Some(c @ ('\n' | ';' | '&' | '|' | '(' | ')' | '<' | '>')) => return Err(ParseError::Unexpected(c)),
+            _ => {},
+        }
+        let target = self.parse_word()?;
+        if let RedirectionOperator::HereDoc(id) = operator {
+            self.heredocs.push(Word::default());
+            self.pending_heredocs.push(PendingHereDoc {
+                id,
+                delimiter: target.value.clone(),
+                strip_tabs,
+                expand: !target.quoted,
+            });
+        }
+
+        Ok(Some(Redirection {
+            fd: digits.parse().ok(),
+            operator,
+            target,
+        }))
+    }
+
+    /// Reads the bodies of the heredocs whose redirections were on the line that just ended
+    fn read_heredoc_bodies(&mut self) -> Result<(), ParseError> {
+        for heredoc in std::mem::take(&mut self.pending_heredocs) {
+            let mut body = String::new();
+            while self.pos < self.chars.len() {
+                let line_end = self.chars[self.pos..]
+                    .iter()
+                    .position(|c| *c == '\n')
+                    .map_or(self.chars.len(), |i| self.pos + i);
+                let line: String = self.chars[self.pos..line_end].iter().collect();
+                self.pos = (line_end + 1).min(self.chars.len());
+                let line = if heredoc.strip_tabs {
+                    line.trim_start_matches('\t')
+                } else {
+                    &line
+                };
+                if line == heredoc.delimiter {
+                    break;
+                }
+                body.push_str(line);
+                body.push('\n');
+            }
+
+            self.heredocs[heredoc.id] = if heredoc.expand {
+                let mut parser = Parser::new(&body);
+                let mut word = Word::default();
+                parser.parse_double_quoted(&mut word, None)?;
+                if !parser.heredocs.is_empty() {
+                    return Err(ParseError::Unsupported("heredocs nested in heredocs".to_string()));
+                }
+                word
+            } else {
+                Word {
+                    value: body,
+                    quoted: true,
+                    ..Default::default()
+                }
+            };
+        }
+        Ok(())
+    }
+
+    fn parse_word(&mut self) -> Result<Word, ParseError> {
+        let mut word = Word::default();
+        while let Some(c) = self.peek() {
+            match c {
+                ' ' | '\t' | '\n' | ';' | '&' | '|' | '(' | ')' => break,
+                '<' | '>' if self.peek_at(1) == Some('(') => {
+                    let start = self.pos;
+                    self.pos += 2;
+                    let list = self.parse_list(Some(')'))?;
+                    self.expect(')')?;
+                    word.substitutions.push(list);
+                    word.value.extend(&self.chars[start..self.pos]);
+                },
+                '<' | '>' => break,
+                '\\' => {
+                    self.pos += 1;
+                    match self.peek() {
+                        Some('\n') => self.pos += 1,
+                        Some(c) => {
+                            word.value.push(c);
+                            word.quoted = true;
+                            self.pos += 1;
+                        },
+                        None => {},
+                    }
+                },
+                '\'' => {
+                    self.pos += 1;
+                    word.quoted = true;
+                    loop {
+                        match self.peek() {
+                            Some('\'') => break,
+                            Some(c) => word.value.push(c),
+                            None => return Err(ParseError::UnexpectedEof),
+                        }
+                        self.pos += 1;
+                    }
+                    self.pos += 1;
+                },
+                '"' => {
+                    self.pos += 1;
+                    word.quoted = true;
+                    self.parse_double_quoted(&mut word, Some('"'))?;
+                },
+                '$' => self.parse_dollar(&mut word)?,
+                '`' => self.parse_backquotes(&mut word)?,
+                c => {
+                    match c {
+                        '{' if self.is_brace_expansion() => word.has_expansion = true,
+                        '*' | '?' | '[' => word.has_expansion = true,
+                        '~' if word.value.is_empty() && !word.quoted => word.has_expansion = true,
+                        _ => {},
+                    }
+                    word.value.push(c);
+                    self.pos += 1;
+                },
+            }
+        }
+
+        if word.value.is_empty() && !word.quoted {
+            return Err(self.peek().map_or(ParseError::UnexpectedEof, ParseError::Unexpected));
+        }
+        Ok(word)
+    }
+
+    /// Parses the contents of a double quoted string up to and including `closing`, or up to the
+    /// end of the input if there is none, as is the case for heredoc bodies.
+    fn parse_double_quoted(&mut self, word: &mut Word, closing: Option<char>) -> Result<(), ParseError> {
+        loop {
+            match self.peek() {
+                None if closing.is_none() => return Ok(()),
+                None => return Err(ParseError::UnexpectedEof),
+                Some(c) if Some(c) == closing => {
+                    self.pos += 1;
+                    return Ok(());
+                },
+                Some('\\') => {
+                    self.pos += 1;
+                    match self.peek() {
+                        Some('\n') => self.pos += 1,
+                        Some(c @ ('$' | '`' | '\\')) => {
+                            word.value.push(c);
+                            self.pos += 1;
+                        },
+                        Some('"') if closing.is_some() => {
+                            word.value.push('"');
+                            self.pos += 1;
+                        },
+                        _ => word.value.push('\\'),
+                    }
+                },
+                Some('$') => self.parse_dollar(word)?,
+                Some('`') => self.parse_backquotes(word)?,
+                Some(c) => {
+                    word.value.push(c);
+                    self.pos += 1;
+                },
+            }
+        }
+    }
+
+    fn parse_dollar(&mut self, word: &mut Word) -> Result<(), ParseError> {
+        let start = self.pos;
+        self.pos += 1;
+        match self.peek() {
+            Some('(') if self.peek_at(1) == Some('(') => {
+                self.pos += 2;
+                let expression = self.scan_balanced('(', ')')?;
+                self.expect(')')?;
+                if expression.contains("$(") || expression.contains('`') {
+                    return Err(ParseError::Unsupported(
+                        "command substitution in arithmetic expansion".to_string(),
+                    ));
+                }
+                word.has_expansion = true;
+            },
+            Some('(') => {
+                self.pos += 1;
+                let list = self.parse_list(Some(')'))?;
+                self.expect(')')?;
+                word.substitutions.push(list);
+            },
+            Some('{') => {
+                self.pos += 1;
+                let expression = self.scan_balanced('{', '}')?;
+                if expression.contains("$(") || expression.contains('`') {
+                    return Err(ParseError::Unsupported(
+                        "command substitution in parameter expansion".to_string(),
+                    ));
+                }
+                word.has_expansion = true;
+            },
+            Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+                while self.peek().is_some_and(|c| c.is_ascii_alphanumeric() || c == '_') {
+                    self.pos += 1;
+                }
+                word.has_expansion = true;
+            },
+            Some(c) if c.is_ascii_digit() || "@*#?$!-".contains(c) => {
+                self.pos += 1;
+                word.has_expansion = true;
+            },
+            Some('\'') => {
+                // ANSI-C quoting
+                self.pos += 1;
+                word.quoted = true;
+                loop {
+                    match self.peek() {
+                        Some('\'') => break,
+                        Some('\\') => self.parse_ansi_c_escape(word),
+                        Some(c) => {
+                            word.value.push(c);
+                            self.pos += 1;
+                        },
+                        None => return Err(ParseError::UnexpectedEof),
+                    }
+                }
+                self.pos += 1;
+                return Ok(());
+            },
+            // Locale specific translation of a double quoted string, `$"..."`
+            Some('"') => return Ok(()),
+            _ => {
+                word.value.push('$');
+                return Ok(());
+            },
+        }
+        word.value.extend(&self.chars[start..self.pos]);
+        Ok(())
+    }
+
+    /// Decodes the escape sequence at the current position of an ANSI-C quoted string. Sequences
+    /// whose value cannot be represented, such as `\c` control characters and NUL characters, which
+    /// end the word, make the word non-static.
+    fn parse_ansi_c_escape(&mut self, word: &mut Word) {
+        self.pos += 1;
+        let Some(c) = self.peek() else {
+            word.value.push('\\');
+            return;
+        };
+        self.pos += 1;
+        let decoded = match c {
+            'a' => Some('\x07'),
+            'b' => Some('\x08'),
+            'e' | 'E' => Some('\x1b'),
+            'f' => Some('\x0c'),
+            'n' => Some('\n'),
+            'r' => Some('\r'),
+            't' => Some('\t'),
+            'v' => Some('\x0b'),
+            '\\' | '\'' | '"' | '?' => Some(c),
+            '0'..='7' => {
+                self.pos -= 1;
+                self.parse_escape_number(8, 3)
+            },
+            'x' => self.parse_escape_number(16, 2),
+            'u' => self.parse_escape_number(16, 4),
+            'U' => self.parse_escape_number(16, 8),
+            'c' => None,
+            c => {
+                word.value.push('\\');
+                Some(c)
+            },
+        };
+        match decoded {
+            Some(c) if c != '\0' => word.value.push(c),
+            _ => word.has_expansion = true,
+        }
+    }
+
+    /// Consumes up to `max_digits` digits in `radix`, returning the character with that code, if
+    /// any digits were consumed and the code is valid.
+    fn parse_escape_number(&mut self, radix: u32, max_digits: usize) -> Option<char> {
+        let mut code = None;
+        for _ in 0..max_digits {
+            let Some(digit) = self.peek().and_then(|c| c.to_digit(radix)) else {
+                break;
+            };
+            code = Some(code.unwrap_or(0) * radix + digit);
+            self.pos += 1;
+        }
+        code.and_then(char::from_u32)
+    }
+
+    /// Whether the unquoted `{` at the current position starts a brace expansion, i.e. a list
+    /// `{a,b}` or a sequence `{a..b}`.
+    fn is_brace_expansion(&self) -> bool {
+        let mut depth = 0;
+        let mut pos = self.pos;
+        while let Some(&c) = self.chars.get(pos) {
+            match c {
+                ' ' | '\t' | '\n' | ';' | '&' | '|' | '(' | ')' | '<' | '>' => return false,
+                '{' => depth += 1,
+                '}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return false;
+                    }
+                },
+                ',' if depth == 1 => return true,
+                '.' if depth == 1 && self.chars.get(pos + 1) == Some(&'.') => return true,
+                '\\' => pos += 1,
+                '\'' | '"' => {
+                    pos += 1;
+                    while self.chars.get(pos).is_some_and(|&quoted| quoted != c) {
+                        if c == '"' && self.chars[pos] == '\\' {
+                            pos += 1;
+                        }
+                        pos += 1;
+                    }
+                },
+                _ => {},
+            }
+            pos += 1;
+        }
+        false
+    }
+
+    fn parse_backquotes(&mut self, word: &mut Word) -> Result<(), ParseError> {
+        let start = self.pos;
+        self.pos += 1;
+        let mut body = String::new();
+        loop {
+            match self.peek() {
+                None => return Err(ParseError::UnexpectedEof),
+                Some('`') => break,
+                Some('\\') if matches!(self.peek_at(1), Some('`' | '\\' | '$')) => {
+                    body.push(self.chars[self.pos + 1]);
+                    self.pos += 2;
+                },
+                Some(c) => {
+                    body.push(c);
+                    self.pos += 1;
+                },
+            }
+        }
+        self.pos += 1;
+
+        let script = parse(&body)?;
+        if !script.heredocs.is_empty() {
+            return Err(ParseError::Unsupported(
+                "heredocs in backquoted command substitutions".to_string(),
+            ));
+        }
+        word.substitutions.push(script.list);
+        word.value.extend(&self.chars[start..self.pos]);
+        Ok(())
+    }
+
+    /// Consumes input up to and including the `close` character that balances an already consumed
+    /// `open` character, returning what was consumed before it.
+    fn scan_balanced(&mut self, open: char, close: char) -> Result<String, ParseError> {
+        let start = self.pos;
+        let mut depth = 1;
+        loop {
+            match self.peek() {
+                None => return Err(ParseError::UnexpectedEof),
+                Some(c) if c == open => depth += 1,
+                Some(c) if c == close => {
+                    depth -= 1;
+                    if depth == 0 {
+                        let scanned = self.chars[start..self.pos].iter().collect();
+                        self.pos += 1;
+                        return Ok(scanned);
+                    }
+                },
+                Some('\\') => self.pos += 1,
+                _ => {},
+            }
+            self.pos += 1;
+        }
+    }
+}
+
+/// Whether `c` ends a reserved word such as `{` or `!`
+fn is_boundary(c: Option<char>) -> bool {
+    matches!(
+        c,
+        None | Some(' ' | '\t' | '\n' | ';' | '&' | '|' | '(' | ')' | '<' | '>')
+    )
+}
+
+fn is_assignment(word: &Word) -> bool {
+    match word.value.split_once('=') {
+        Some((name, _)) => {
+            name.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+                && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        },
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(command: &Command) -> Vec<&str> {
+        match command {
+            Command::Simple(simple) => simple.words.iter().map(|w| w.value.as_str()).collect(),
+            _ => panic!("expected a simple command, got {command:?}"),
+        }
+    }
+
+    fn commands(list: &List) -> Vec<&Command> {
+        list.items
+            .iter()
+            .flat_map(|item| item.pipelines())
+            .flat_map(|pipeline| pipeline.commands.iter())
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_lists_and_pipelines() {
+        let script = parse("git status && cargo test -p 'chat cli' | tail -n 5; ls &\necho done").unwrap();
+        let items = &script.list.items;
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[0].rest[0].0, AndOrOperator::And);
+        assert!(items[1].background);
+        let commands = commands(&script.list);
+        assert_eq!(words(commands[0]), vec!["git", "status"]);
+        assert_eq!(words(commands[1]), vec!["cargo", "test", "-p", "chat cli"]);
+        assert_eq!(words(commands[2]), vec!["tail", "-n", "5"]);
+        assert_eq!(words(commands[3]), vec!["ls"]);
+        assert_eq!(words(commands[4]), vec!["echo", "done"]);
+    }
+
+    #[test]
+    fn test_parse_redirections() {
+        let script = parse("FOO=bar cmd 2>&1 >out.txt < in.txt").unwrap();
+        let Command::Simple(command) = commands(&script.list)[0] else {
+            panic!("expected a simple command");
+        };
+        assert_eq!(command.assignments[0].value, "FOO=bar");
+        assert_eq!(words(commands(&script.list)[0]), vec!["cmd"]);
+        let redirections: Vec<_> = command
+            .redirections
+            .iter()
+            .map(|r| (r.fd, r.operator, r.target.value.as_str()))
+            .collect();
+        assert_eq!(redirections, vec![
+            (Some(2), RedirectionOperator::DuplicateOutput, "1"),
+            (None, RedirectionOperator::Output, "out.txt"),
+            (None, RedirectionOperator::Input, "in.txt"),
+        ]);
+    }
+
+    #[test]
+    fn test_parse_substitutions() {
+        let script = parse("echo \"$(git rev-parse HEAD)\" `pwd` $HOME <(ls)").unwrap();
+        let Command::Simple(command) = commands(&script.list)[0] else {
+            panic!("expected a simple command");
+        };
+        assert_eq!(
+            words(&command.words[1].substitutions[0].items[0].first.commands[0]),
+            vec!["git", "rev-parse", "HEAD"]
+        );
+        assert_eq!(
+            words(&command.words[2].substitutions[0].items[0].first.commands[0]),
+            vec!["pwd"]
+        );
+        assert!(command.words[3].has_expansion);
+        assert_eq!(
+            words(&command.words[4].substitutions[0].items[0].first.commands[0]),
+            vec!["ls"]
+        );
+    }
+
+    #[test]
+    fn test_parse_brace_expansions() {
+        let script = parse("echo {-delete,} {1..3} a{b}c {} '{a,b}' \\{x,y} {'a b',c}").unwrap();
+        let Command::Simple(command) = commands(&script.list)[0] else {
+            panic!("expected a simple command");
+        };
+        let expansions: Vec<bool> = command.words.iter().map(|word| word.has_expansion).collect();
+        assert_eq!(expansions, vec![false, true, true, false, false, false, false, true]);
+    }
+
+    #[test]
+    fn test_parse_pathname_and_tilde_expansions() {
+        let script = parse("echo *.rs a?c -delet[e] '*' \\? ~ ~/a a~ '~' \"~\"/a").unwrap();
+        let Command::Simple(command) = commands(&script.list)[0] else {
+            panic!("expected a simple command");
+        };
+        let expansions: Vec<bool> = command.words.iter().map(|word| word.has_expansion).collect();
+        assert_eq!(expansions, vec![
+            false, true, true, true, false, false, true, true, false, false, false
+        ]);
+    }
+
+    #[test]
+    fn test_parse_ansi_c_strings() {
+        let script = parse(r"echo $'-de\x6cete' $'a\tb\\\'' $'\101é' $'\q' $'a\cb' $'\0x'").unwrap();
+        let Command::Simple(command) = commands(&script.list)[0] else {
+            panic!("expected a simple command");
+        };
+        assert_eq!(command.words[1].value, "-delete");
+        assert_eq!(command.words[2].value, "a\tb\\'");
+        assert_eq!(command.words[3].value, "Aé");
+        assert_eq!(command.words[4].value, r"\q");
+        assert!(command.words[1..5].iter().all(Word::is_static));
+        assert!(!command.words[5].is_static());
+        assert!(!command.words[6].is_static());
+    }
+
+    #[test]
+    fn test_parse_subshells_and_heredocs() {
+        let script =
+            parse("(cd src && ls) | { cat; }\ncat <<EOF\nhello $(whoami)\nEOF\ncat <<-'EOF'\n\t$(x)\n\tEOF").unwrap();
+        let commands = commands(&script.list);
+        assert!(matches!(commands[0], Command::Subshell(..)));
+        assert!(matches!(commands[1], Command::Group(..)));
+        assert_eq!(script.heredocs.len(), 2);
+        assert_eq!(script.heredocs[0].value, "hello $(whoami)\n");
+        assert_eq!(script.heredocs[0].substitutions.len(), 1);
+        assert_eq!(script.heredocs[1].value, "$(x)\n");
+        assert!(script.heredocs[1].is_static());
+
+        let names: Vec<&str> = script
+            .simple_commands()
+            .iter()
+            .map(|command| command.words[0].value.as_str())
+            .collect();
+        assert_eq!(names, vec!["cd", "ls", "cat", "cat", "cat", "whoami"]);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(parse("echo 'unterminated"), Err(ParseError::UnexpectedEof));
+        assert_eq!(parse("ls |"), Err(ParseError::UnexpectedEof));
+        assert_eq!(parse("ls )"), Err(ParseError::Unexpected(')')));
+        assert_eq!(
+            parse("if true; then ls; fi"),
+            Err(ParseError::Unsupported("if".to_string()))
+        );
+        assert!(parse("f() { ls; }").is_err());
+    }
+}
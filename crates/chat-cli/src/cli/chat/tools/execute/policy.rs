@@ -0,0 +1,278 @@
+use globset::{
+    Glob,
+    GlobMatcher,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use tracing::warn;
+
+use super::READONLY_COMMANDS;
+use super::parser::{
+    self,
+    Command,
+    List,
+    Redirection,
+    RedirectionOperator,
+    Script,
+    SimpleCommand,
+    Word,
+};
+use crate::database::settings::{
+    Setting,
+    Settings,
+};
+
+/// Subcommands that only read state, in addition to [READONLY_COMMANDS]
+const READONLY_SUBCOMMANDS: &[&str] = &[
+    "git status",
+    "git log",
+    "git diff",
+    "git show",
+    "git blame",
+    "git rev-parse",
+    "cargo metadata",
+];
+
+/// Uses of otherwise read-only commands that write files or run other commands
+const MUTATING_ARGUMENTS: &[&str] = &[
+    "find ... -exec*",
+    "find ... -ok*",
+    "find ... -delete",
+    "find ... -fprint*",
+    "find ... -fls",
+    "git ... --output*",
+];
+
+/// Rule word matching any number of words
+const ANY_WORDS: &str = "...";
+
+/// Decides which shell commands run without asking for confirmation. Commands are parsed, and
+/// every simple command they contain, including those in pipelines, lists, subshells and
+/// substitutions, has to match an allow rule and no deny rule.
+///
+/// Rules are made of glob patterns that are matched against the leading words of a command, e.g.
+/// `git log` matches `git log -p` but not `git push`. The word `...` matches any number of words,
+/// e.g. `find ... -delete`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CommandPolicy {
+    pub allow: Vec<String>,
+    pub deny: Vec<String>,
+}
+
+impl CommandPolicy {
+    /// The built-in policy, which trusts read-only commands
+    pub fn readonly() -> Self {
+        Self {
+            allow: READONLY_COMMANDS
+                .iter()
+                .chain(READONLY_SUBCOMMANDS)
+                .map(|rule| (*rule).to_string())
+                .collect(),
+            deny: MUTATING_ARGUMENTS.iter().map(|rule| (*rule).to_string()).collect(),
+        }
+    }
+
+    /// The built-in policy, extended with the rules of the `chat.commandPolicy` setting
+    pub fn from_settings(settings: &Settings) -> Self {
+        let mut policy = Self::readonly();
+        if let Some(value) = settings.get(Setting::ChatCommandPolicy) {
+            match serde_json::from_value::<CommandPolicy>(value.clone()) {
+                Ok(configured) => {
+                    policy.allow.extend(configured.allow);
+                    policy.deny.extend(configured.deny);
+                },
+                Err(err) => warn!(
+                    ?err,
+                    "Invalid {}, using the default command policy",
+                    Setting::ChatCommandPolicy
+                ),
+            }
+        }
+        policy
+    }
+
+    pub fn requires_acceptance(&self, command: &str) -> bool {
+        let script = match parser::parse(command) {
+            Ok(script) => script,
+            Err(err) => {
+                warn!(?err, "Failed to parse command");
+                return true;
+            },
+        };
+        let allow = compile_rules(&self.allow);
+        let deny = compile_rules(&self.deny);
+        !allows_script(&script, |words| {
+            !deny.iter().any(|rule| rule_matches(rule, words)) && allow.iter().any(|rule| rule_matches(rule, words))
+        })
+    }
+}
+
+/// Whether every simple command run by `script`, including those in pipelines, lists, subshells
+/// and substitutions, is allowed by `allows_words` given its words. Commands whose words are only
+/// known once expanded, commands preceded by assignments and redirections writing to files are
+/// never allowed.
+pub fn allows_script(script: &Script, allows_words: impl Fn(&[&str]) -> bool) -> bool {
+    ScriptChecker { allows_words }.allows_script(script)
+}
+
+type Rule = Vec<Option<GlobMatcher>>;
+
+/// Compiles rules into their words, where `None` stands for [ANY_WORDS]
+fn compile_rules(rules: &[String]) -> Vec<Rule> {
+    rules
+        .iter()
+        .filter_map(|rule| {
+            let words = shlex::split(rule).filter(|words| !words.is_empty());
+            let compiled = words.and_then(|words| {
+                words
+                    .iter()
+                    .map(|word| match word.as_str() {
+                        ANY_WORDS => Ok(None),
+                        word => Glob::new(word).map(|glob| Some(glob.compile_matcher())),
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+                    .ok()
+            });
+            if compiled.is_none() {
+                warn!("Ignoring invalid command policy rule '{rule}'");
+            }
+            compiled
+        })
+        .collect()
+}
+
+/// Whether the leading words of `words` match `rule`
+fn rule_matches(rule: &[Option<GlobMatcher>], words: &[&str]) -> bool {
+    match rule.split_first() {
+        None => true,
+        Some((None, rest)) => (0..=words.len()).any(|skip| rule_matches(rest, &words[skip..])),
+        Some((Some(glob), rest)) => match words.split_first() {
+            Some((word, remaining)) => glob.is_match(word) && rule_matches(rest, remaining),
+            None => false,
+        },
+    }
+}
+
+struct ScriptChecker<F> {
+    allows_words: F,
+}
+
+impl<F: Fn(&[&str]) -> bool> ScriptChecker<F> {
+    fn allows_script(&self, script: &Script) -> bool {
+        self.allows_list(&script.list) && script.heredocs.iter().all(|body| self.allows_substitutions(body))
+    }
+
+    fn allows_list(&self, list: &List) -> bool {
+        list.items
+            .iter()
+            .flat_map(|item| item.pipelines())
+            .flat_map(|pipeline| pipeline.commands.iter())
+            .all(|command| self.allows_command(command))
+    }
+
+    fn allows_command(&self, command: &Command) -> bool {
+        match command {
+            Command::Simple(simple) => self.allows_simple_command(simple),
+            Command::Subshell(list, redirections) | Command::Group(list, redirections) => {
+                self.allows_list(list) && self.allows_redirections(redirections)
+            },
+        }
+    }
+
+    fn allows_simple_command(&self, command: &SimpleCommand) -> bool {
+        if !command.words.iter().all(|word| self.allows_substitutions(word))
+            || !command.assignments.iter().all(|word| self.allows_substitutions(word))
+            || !self.allows_redirections(&command.redirections)
+        {
+            return false;
+        }
+        // Assignments on their own only set shell variables, but they can change the behavior of the
+        // command they precede, e.g. `GIT_EXTERNAL_DIFF=... git diff`.
+        if command.words.is_empty() {
+            return true;
+        }
+        if !command.assignments.is_empty() || !command.words.iter().all(Word::is_static) {
+            return false;
+        }
+
+        let words: Vec<&str> = command.words.iter().map(|word| word.value.as_str()).collect();
+        (self.allows_words)(&words)
+    }
+
+    /// Only redirections that do not write to files are allowed
+    fn allows_redirections(&self, redirections: &[Redirection]) -> bool {
+        redirections.iter().all(|redirection| {
+            let target = &redirection.target;
+            self.allows_substitutions(target)
+                && match redirection.operator {
+                    RedirectionOperator::Input | RedirectionOperator::HereDoc(_) | RedirectionOperator::HereString => {
+                        true
+                    },
+                    RedirectionOperator::DuplicateInput | RedirectionOperator::DuplicateOutput => {
+                        target.is_static() && (target.value == "-" || target.value.chars().all(|c| c.is_ascii_digit()))
+                    },
+                    RedirectionOperator::Output
+                    | RedirectionOperator::Append
+                    | RedirectionOperator::Clobber
+                    | RedirectionOperator::ReadWrite
+                    | RedirectionOperator::OutputAndError
+                    | RedirectionOperator::AppendOutputAndError => target.is_static() && target.value == "/dev/null",
+                }
+        })
+    }
+
+    fn allows_substitutions(&self, word: &Word) -> bool {
+        word.substitutions.iter().all(|list| self.allows_list(list))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rule_matches() {
+        let rules = compile_rules(&[
+            "git log".to_string(),
+            "find ... -delete".to_string(),
+            "cargo t*".to_string(),
+        ]);
+        assert!(rule_matches(&rules[0], &["git", "log", "-p"]));
+        assert!(!rule_matches(&rules[0], &["git", "push"]));
+        assert!(!rule_matches(&rules[0], &["git"]));
+        assert!(rule_matches(&rules[1], &["find", ".", "-name", "x", "-delete"]));
+        assert!(rule_matches(&rules[1], &["find", "-delete"]));
+        assert!(!rule_matches(&rules[1], &["find", ".", "-name", "x"]));
+        assert!(rule_matches(&rules[2], &["cargo", "test", "--workspace"]));
+        assert!(rule_matches(&rules[2], &["cargo", "tree"]));
+    }
+
+    #[test]
+    fn test_configured_policy() {
+        let policy = CommandPolicy {
+            allow: vec!["cargo test".to_string(), "git *".to_string()],
+            deny: vec!["git push".to_string()],
+        };
+        assert!(!policy.requires_acceptance("cargo test --workspace"));
+        assert!(policy.requires_acceptance("cargo publish"));
+        assert!(!policy.requires_acceptance("git fetch && git rebase origin/main"));
+        assert!(policy.requires_acceptance("git fetch && git push --force"));
+        assert!(policy.requires_acceptance("git {push,}"));
+        assert!(policy.requires_acceptance("git {push,fetch}"));
+    }
+
+    #[test]
+    fn test_obfuscated_arguments() {
+        let policy = CommandPolicy::readonly();
+        assert!(!policy.requires_acceptance("find . -name '*.rs'"));
+        assert!(policy.requires_acceptance("find . {-delete,}"));
+        assert!(policy.requires_acceptance("find . -{delete,print}"));
+        assert!(policy.requires_acceptance(r"find . $'-de\x6cete'"));
+        assert!(policy.requires_acceptance(r"find . $'-\144elete'"));
+        assert!(policy.requires_acceptance("find . -delet[e]"));
+        assert!(policy.requires_acceptance("find . -de*te"));
+    }
+}
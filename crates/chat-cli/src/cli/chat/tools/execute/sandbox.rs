@@ -0,0 +1,536 @@
+//! Opt-in sandbox for the commands run by `execute_bash`. On Linux, commands are confined with
+//! landlock so that they can only write to the workspace, a temporary directory of their own and
+//! explicitly allowed paths while the rest of the filesystem stays readable, and with a seccomp
+//! filter that keeps them from opening network sockets unless the network is allowed.
+
+use std::path::{
+    Path,
+    PathBuf,
+};
+
+use eyre::Result;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+/// Sandbox configuration as it is written in the global or a profile's context configuration
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct SandboxConfig {
+    /// Whether commands run in the sandbox
+    pub enabled: bool,
+    /// Paths besides the workspace that commands may write to, relative to the workspace
+    pub writable_paths: Vec<String>,
+    /// Whether commands may access the network
+    pub allow_network: bool,
+}
+
+impl SandboxConfig {
+    /// Combines the sandbox of the user's configuration with the one checked in to a workspace,
+    /// which can only make it stricter: it can enable the sandbox, but not disable it, deny the
+    /// network, and narrow down the writable paths.
+    pub fn restricted_by(user: Option<&Self>, workspace: Option<&Self>) -> Option<Self> {
+        let Some(workspace) = workspace.filter(|workspace| workspace.enabled) else {
+            return user.cloned();
+        };
+        match user.filter(|user| user.enabled) {
+            Some(user) => Some(Self {
+                enabled: true,
+                writable_paths: user
+                    .writable_paths
+                    .iter()
+                    .filter(|path| workspace.writable_paths.contains(path))
+                    .cloned()
+                    .collect(),
+                allow_network: user.allow_network && workspace.allow_network,
+            }),
+            None => Some(workspace.clone()),
+        }
+    }
+}
+
+/// Devices that sandboxed commands may write to
+const WRITABLE_DEVICES: &[&str] = &["/dev/null", "/dev/zero", "/dev/tty"];
+
+/// A sandbox resolved against the workspace commands run in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sandbox {
+    pub writable_paths: Vec<PathBuf>,
+    pub allow_network: bool,
+}
+
+impl Sandbox {
+    /// Returns [None] if the sandbox is not enabled by `config`
+    pub fn from_config(config: &SandboxConfig, workspace: &Path) -> Option<Self> {
+        if !config.enabled {
+            return None;
+        }
+        // Most commands need to write to these devices, and get a temporary directory of their own
+        // when they run
+        let mut writable_paths = vec![workspace.to_path_buf()];
+        writable_paths.extend(WRITABLE_DEVICES.iter().map(PathBuf::from));
+        writable_paths.extend(
+            config
+                .writable_paths
+                .iter()
+                .map(|path| workspace.join(shellexpand::tilde(path).as_ref())),
+        );
+        Some(Self {
+            writable_paths,
+            allow_network: config.allow_network,
+        })
+    }
+
+    pub fn description(&self) -> String {
+        format!(
+            "writable {}, a temporary directory; network {}",
+            self.writable_paths
+                .iter()
+                .map(|path| path.to_string_lossy())
+                .collect::<Vec<_>>()
+                .join(", "),
+            if self.allow_network { "allowed" } else { "disabled" }
+        )
+    }
+
+    /// Confines `command` to the sandbox once it is spawned. The command gets a temporary
+    /// directory of its own as `TMPDIR`, which is removed when the returned value is dropped.
+    #[cfg(target_os = "linux")]
+    pub fn apply(&self, command: &mut tokio::process::Command) -> Result<tempfile::TempDir> {
+        use std::os::unix::ffi::OsStrExt;
+
+        let Some(abi) = linux::landlock_abi() else {
+            eyre::bail!("Sandboxed execution requires landlock, which is not supported by this kernel");
+        };
+        let network_filter = match self.allow_network {
+            true => None,
+            false => Some(linux::network_filter().ok_or_else(|| {
+                eyre::eyre!("Disabling the network in the sandbox is not supported on this architecture")
+            })?),
+        };
+        let temp_dir = tempfile::Builder::new().prefix("qchat-sandbox-").tempdir()?;
+        command.env("TMPDIR", temp_dir.path());
+
+        // Everything the child needs is prepared beforehand since it must not allocate
+        let writable_paths = self
+            .writable_paths
+            .iter()
+            .chain(std::iter::once(&temp_dir.path().to_path_buf()))
+            .map(|path| {
+                Ok((
+                    std::ffi::CString::new(path.as_os_str().as_bytes())?,
+                    linux::write_access(abi, path.is_dir()),
+                ))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        // SAFETY: the closure only makes system calls with data prepared before forking
+        unsafe {
+            command.pre_exec(move || linux::confine(abi, &writable_paths, network_filter.as_deref()));
+        }
+        Ok(temp_dir)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn apply(&self, _command: &mut tokio::process::Command) -> Result<tempfile::TempDir> {
+        eyre::bail!("Sandboxed execution is only supported on Linux")
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::ffi::CString;
+    use std::io;
+
+    const LANDLOCK_CREATE_RULESET_VERSION: libc::c_uint = 1 << 0;
+    const LANDLOCK_RULE_PATH_BENEATH: libc::c_int = 1;
+
+    const LANDLOCK_ACCESS_FS_WRITE_FILE: u64 = 1 << 1;
+    const LANDLOCK_ACCESS_FS_REMOVE_DIR: u64 = 1 << 4;
+    const LANDLOCK_ACCESS_FS_REMOVE_FILE: u64 = 1 << 5;
+    const LANDLOCK_ACCESS_FS_MAKE_CHAR: u64 = 1 << 6;
+    const LANDLOCK_ACCESS_FS_MAKE_DIR: u64 = 1 << 7;
+    const LANDLOCK_ACCESS_FS_MAKE_REG: u64 = 1 << 8;
+    const LANDLOCK_ACCESS_FS_MAKE_SOCK: u64 = 1 << 9;
+    const LANDLOCK_ACCESS_FS_MAKE_FIFO: u64 = 1 << 10;
+    const LANDLOCK_ACCESS_FS_MAKE_BLOCK: u64 = 1 << 11;
+    const LANDLOCK_ACCESS_FS_MAKE_SYM: u64 = 1 << 12;
+    /// Available from ABI version 2
+    const LANDLOCK_ACCESS_FS_REFER: u64 = 1 << 13;
+    /// Available from ABI version 3
+    const LANDLOCK_ACCESS_FS_TRUNCATE: u64 = 1 << 14;
+
+    /// `AUDIT_ARCH_*` value of the architecture, as checked by the seccomp filter
+    const AUDIT_ARCH: Option<u32> = if cfg!(target_arch = "x86_64") {
+        Some(0xc000_003e)
+    } else if cfg!(target_arch = "aarch64") {
+        Some(0xc000_00b7)
+    } else {
+        None
+    };
+
+    #[repr(C)]
+    struct RulesetAttr {
+        handled_access_fs: u64,
+    }
+
+    #[repr(C, packed)]
+    struct PathBeneathAttr {
+        allowed_access: u64,
+        parent_fd: libc::c_int,
+    }
+
+    /// Returns the landlock ABI version supported by the kernel, if any
+    pub fn landlock_abi() -> Option<i64> {
+        // SAFETY: querying the version does not take a ruleset attribute
+        let abi = unsafe {
+            libc::syscall(
+                libc::SYS_landlock_create_ruleset,
+                std::ptr::null::<RulesetAttr>(),
+                0usize,
+                LANDLOCK_CREATE_RULESET_VERSION,
+            )
+        };
+        (abi > 0).then_some(abi)
+    }
+
+    /// Access rights that modify the filesystem, which are denied outside of the writable paths
+    pub fn write_access(abi: i64, is_dir: bool) -> u64 {
+        let mut access = LANDLOCK_ACCESS_FS_WRITE_FILE;
+        if abi >= 3 {
+            access |= LANDLOCK_ACCESS_FS_TRUNCATE;
+        }
+        // Rights on directory entries cannot be granted on files
+        if is_dir {
+            access |= LANDLOCK_ACCESS_FS_REMOVE_DIR
+                | LANDLOCK_ACCESS_FS_REMOVE_FILE
+                | LANDLOCK_ACCESS_FS_MAKE_CHAR
+                | LANDLOCK_ACCESS_FS_MAKE_DIR
+                | LANDLOCK_ACCESS_FS_MAKE_REG
+                | LANDLOCK_ACCESS_FS_MAKE_SOCK
+                | LANDLOCK_ACCESS_FS_MAKE_FIFO
+                | LANDLOCK_ACCESS_FS_MAKE_BLOCK
+                | LANDLOCK_ACCESS_FS_MAKE_SYM;
+            if abi >= 2 {
+                access |= LANDLOCK_ACCESS_FS_REFER;
+            }
+        }
+        access
+    }
+
+    /// Bit set in the numbers of the system calls of the x32 ABI, which share the x86_64
+    /// architecture
+    const X32_SYSCALL_BIT: u32 = 0x4000_0000;
+
+    /// A seccomp filter that makes the creation of IPv4 and IPv6 sockets fail with `EACCES`.
+    /// System calls made with another architecture's calling convention or the x32 ABI fail
+    /// altogether, as does io_uring, whose operations create sockets without system calls.
+    pub fn network_filter() -> Option<Vec<libc::sock_filter>> {
+        use libc::{
+            BPF_ABS,
+            BPF_JEQ,
+            BPF_JGE,
+            BPF_JMP,
+            BPF_K,
+            BPF_LD,
+            BPF_RET,
+            BPF_W,
+            SECCOMP_RET_ALLOW,
+            SECCOMP_RET_DATA,
+            SECCOMP_RET_ERRNO,
+            seccomp_data,
+        };
+
+        let load = (BPF_LD | BPF_W | BPF_ABS) as u16;
+        let jump_eq = (BPF_JMP | BPF_JEQ | BPF_K) as u16;
+        let jump_ge = (BPF_JMP | BPF_JGE | BPF_K) as u16;
+        let ret = (BPF_RET | BPF_K) as u16;
+        let deny = SECCOMP_RET_ERRNO | (libc::EACCES as u32 & SECCOMP_RET_DATA);
+        // The lower half of the first argument, which is the socket domain
+        let domain_offset = std::mem::offset_of!(seccomp_data, args) + if cfg!(target_endian = "big") { 4 } else { 0 };
+
+        Some(vec![
+            statement(load, std::mem::offset_of!(seccomp_data, arch) as u32),
+            jump(jump_eq, AUDIT_ARCH?, 1, 0),
+            statement(ret, deny),
+            statement(load, std::mem::offset_of!(seccomp_data, nr) as u32),
+            jump(jump_ge, X32_SYSCALL_BIT, 8, 0),
+            jump(jump_eq, libc::SYS_io_uring_setup as u32, 7, 0),
+            jump(jump_eq, libc::SYS_io_uring_enter as u32, 6, 0),
+            jump(jump_eq, libc::SYS_io_uring_register as u32, 5, 0),
+            jump(jump_eq, libc::SYS_socket as u32, 0, 3),
+            statement(load, domain_offset as u32),
+            jump(jump_eq, libc::AF_INET as u32, 2, 0),
+            jump(jump_eq, libc::AF_INET6 as u32, 1, 0),
+            statement(ret, SECCOMP_RET_ALLOW),
+            statement(ret, deny),
+        ])
+    }
+
+    fn statement(code: u16, k: u32) -> libc::sock_filter {
+        libc::sock_filter { code, jt: 0, jf: 0, k }
+    }
+
+    fn jump(code: u16, k: u32, jt: u8, jf: u8) -> libc::sock_filter {
+        libc::sock_filter { code, jt, jf, k }
+    }
+
+    /// Confines the calling process. This runs in the child between fork and exec, so it must only
+    /// make system calls.
+    pub fn confine(
+        abi: i64,
+        writable_paths: &[(CString, u64)],
+        network_filter: Option<&[libc::sock_filter]>,
+    ) -> io::Result<()> {
+        // SAFETY: all pointers passed to the system calls are valid for the duration of the calls
+        unsafe {
+            if libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+
+            let attr = RulesetAttr {
+                handled_access_fs: write_access(abi, true),
+            };
+            let ruleset = libc::syscall(
+                libc::SYS_landlock_create_ruleset,
+                &attr as *const RulesetAttr,
+                std::mem::size_of::<RulesetAttr>(),
+                0 as libc::c_uint,
+            );
+            if ruleset < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let ruleset = ruleset as libc::c_int;
+
+            for (path, allowed_access) in writable_paths {
+                let fd = libc::open(path.as_ptr(), libc::O_PATH | libc::O_CLOEXEC);
+                // Paths that do not exist cannot be written to in any case
+                if fd < 0 {
+                    continue;
+                }
+                let rule = PathBeneathAttr {
+                    allowed_access: *allowed_access,
+                    parent_fd: fd,
+                };
+                let result = libc::syscall(
+                    libc::SYS_landlock_add_rule,
+                    ruleset,
+                    LANDLOCK_RULE_PATH_BENEATH,
+                    &rule as *const PathBeneathAttr,
+                    0 as libc::c_uint,
+                );
+                libc::close(fd);
+                if result != 0 {
+                    let err = io::Error::last_os_error();
+                    libc::close(ruleset);
+                    return Err(err);
+                }
+            }
+
+            let result = libc::syscall(libc::SYS_landlock_restrict_self, ruleset, 0 as libc::c_uint);
+            let err = io::Error::last_os_error();
+            libc::close(ruleset);
+            if result != 0 {
+                return Err(err);
+            }
+        }
+        match network_filter {
+            Some(filter) => install_filter(filter),
+            None => Ok(()),
+        }
+    }
+
+    /// Installs a seccomp filter on the calling process, which must not gain privileges
+    pub fn install_filter(filter: &[libc::sock_filter]) -> io::Result<()> {
+        let program = libc::sock_fprog {
+            len: filter.len() as libc::c_ushort,
+            filter: filter.as_ptr() as *mut libc::sock_filter,
+        };
+        // SAFETY: the program points to the filter, which outlives the call
+        let result = unsafe {
+            libc::prctl(
+                libc::PR_SET_SECCOMP,
+                libc::SECCOMP_MODE_FILTER,
+                &program as *const libc::sock_fprog,
+            )
+        };
+        match result {
+            0 => Ok(()),
+            _ => Err(io::Error::last_os_error()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_config() {
+        let workspace = Path::new("/home/user/project");
+        assert_eq!(Sandbox::from_config(&SandboxConfig::default(), workspace), None);
+
+        let sandbox = Sandbox::from_config(
+            &SandboxConfig {
+                enabled: true,
+                writable_paths: vec!["../shared".to_string(), "/var/cache".to_string()],
+                allow_network: false,
+            },
+            workspace,
+        )
+        .unwrap();
+        assert_eq!(sandbox.writable_paths[0], workspace);
+        assert!(sandbox.writable_paths.contains(&PathBuf::from("/dev/null")));
+        assert!(!sandbox.writable_paths.contains(&PathBuf::from("/dev")));
+        assert!(!sandbox.writable_paths.contains(&std::env::temp_dir()));
+        assert!(
+            sandbox
+                .writable_paths
+                .contains(&PathBuf::from("/home/user/project/../shared"))
+        );
+        assert!(sandbox.writable_paths.contains(&PathBuf::from("/var/cache")));
+        assert!(sandbox.description().ends_with("network disabled"));
+    }
+
+    #[test]
+    fn test_restricted_by() {
+        let config = |enabled, writable_paths: &[&str], allow_network| SandboxConfig {
+            enabled,
+            writable_paths: writable_paths.iter().map(|path| (*path).to_string()).collect(),
+            allow_network,
+        };
+        let user = config(true, &["../shared", "/var/cache"], true);
+
+        // A workspace cannot disable or loosen the sandbox of the user
+        for workspace in [None, Some(config(false, &[], false)), Some(config(false, &["/"], true))] {
+            assert_eq!(
+                SandboxConfig::restricted_by(Some(&user), workspace.as_ref()),
+                Some(user.clone())
+            );
+        }
+        assert_eq!(
+            SandboxConfig::restricted_by(Some(&user), Some(&config(true, &["/var/cache", "/etc"], false))),
+            Some(config(true, &["/var/cache"], false))
+        );
+
+        // But it can enable one
+        let workspace = config(true, &["target"], false);
+        for user in [None, Some(config(false, &[], true))] {
+            assert_eq!(
+                SandboxConfig::restricted_by(user.as_ref(), Some(&workspace)),
+                Some(workspace.clone())
+            );
+        }
+        assert_eq!(SandboxConfig::restricted_by(None, None), None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_sandboxed_command() {
+        if linux::landlock_abi().is_none() {
+            eprintln!("landlock is not supported, skipping");
+            return;
+        }
+        let workspace = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir_in(std::env::current_dir().unwrap()).unwrap();
+        let sandbox = Sandbox {
+            writable_paths: vec![workspace.path().to_path_buf(), PathBuf::from("/dev/null")],
+            allow_network: true,
+        };
+
+        let run = |script: String| {
+            let sandbox = sandbox.clone();
+            async move {
+                let mut command = tokio::process::Command::new("bash");
+                command.arg("-c").arg(script);
+                let _temp_dir = sandbox.apply(&mut command).unwrap();
+                command.status().await.unwrap().success()
+            }
+        };
+        assert!(run(format!("touch '{}'", workspace.path().join("inside").display())).await);
+        assert!(!run(format!("touch '{}'", outside.path().join("outside").display())).await);
+        assert!(run("echo > /dev/null && touch \"$TMPDIR/inside\"".to_string()).await);
+        // The shared temporary directory is not writable
+        assert!(
+            !run(format!(
+                "touch '{}'",
+                std::env::temp_dir().join("qchat-outside").display()
+            ))
+            .await
+        );
+
+        // Sockets fail to be created rather than failing to connect
+        let mut command = tokio::process::Command::new("bash");
+        command.arg("-c").arg("exec 3<>/dev/tcp/127.0.0.1/9");
+        Sandbox {
+            allow_network: false,
+            ..sandbox
+        }
+        .apply(&mut command)
+        .unwrap();
+        let output = command.output().await.unwrap();
+        assert!(String::from_utf8_lossy(&output.stderr).contains("Permission denied"));
+    }
+
+    /// Runs `syscall` in a child confined by the network filter, and returns whether it failed
+    /// with `EACCES`
+    #[cfg(target_os = "linux")]
+    fn is_refused_by_network_filter(syscall: fn() -> libc::c_long) -> bool {
+        use std::os::unix::process::CommandExt;
+
+        let filter = linux::network_filter().unwrap();
+        let mut command = std::process::Command::new("true");
+        // SAFETY: the closure only makes system calls with data prepared before forking
+        unsafe {
+            command.pre_exec(move || {
+                if libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) != 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                linux::install_filter(&filter)?;
+                match (syscall(), std::io::Error::last_os_error().raw_os_error()) {
+                    (-1, Some(libc::EACCES)) => Ok(()),
+                    _ => Err(std::io::Error::from_raw_os_error(libc::EINVAL)),
+                }
+            });
+        }
+        command.status().is_ok_and(|status| status.success())
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_network_filter() {
+        // SAFETY: the system calls only take integer arguments
+        assert!(is_refused_by_network_filter(|| unsafe {
+            libc::syscall(libc::SYS_socket, libc::AF_INET, libc::SOCK_STREAM, 0)
+        }));
+        // io_uring can create sockets without the socket system call
+        assert!(is_refused_by_network_filter(|| unsafe {
+            libc::syscall(libc::SYS_io_uring_setup, 1, std::ptr::null_mut::<libc::c_void>())
+        }));
+        assert!(is_refused_by_network_filter(|| unsafe {
+            libc::syscall(
+                libc::SYS_io_uring_enter,
+                -1,
+                0,
+                0,
+                0,
+                std::ptr::null_mut::<libc::c_void>(),
+                0,
+            )
+        }));
+        // Unix sockets are allowed
+        assert!(!is_refused_by_network_filter(|| unsafe {
+            libc::syscall(libc::SYS_socket, libc::AF_UNIX, libc::SOCK_STREAM, 0)
+        }));
+    }
+
+    #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+    #[test]
+    fn test_network_filter_refuses_x32_syscalls() {
+        // SAFETY: the system call only takes integer arguments
+        assert!(is_refused_by_network_filter(|| unsafe {
+            libc::syscall(libc::SYS_socket | 0x4000_0000, libc::AF_INET, libc::SOCK_STREAM, 0)
+        }));
+    }
+}
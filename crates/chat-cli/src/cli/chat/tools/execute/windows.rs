@@ -10,6 +10,7 @@ use tokio::io::AsyncBufReadExt;
 use tokio::select;
 use tracing::error;
 
+use super::sandbox::Sandbox;
 use super::{
     CommandResult,
     format_output,
@@ -18,22 +19,27 @@ use super::{
 /// Run a command on Windows using cmd.exe.
 /// # Arguments
 /// * `command` - The command to run
+/// * `sandbox` - The sandbox to confine the command to, if any
 /// * `max_result_size` - max size of output streams, truncating if required
 /// * `updates` - output stream to push informational messages about the progress
 /// # Returns
 /// A [`CommandResult`]
 pub async fn run_command<W: Write>(
     command: &str,
+    sandbox: Option<&Sandbox>,
     max_result_size: usize,
     mut updates: Option<W>,
 ) -> Result<CommandResult> {
     // We need to maintain a handle on stderr and stdout, but pipe it to the terminal as well
-    let mut child = tokio::process::Command::new("cmd")
-        .arg("/C")
+    let mut cmd = tokio::process::Command::new("cmd");
+    cmd.arg("/C")
         .arg(command)
         .stdin(Stdio::inherit())
         .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
+        .stderr(Stdio::piped());
+    // The temporary directory of the sandbox is removed once the command is done
+    let _sandbox_temp_dir = sandbox.map(|sandbox| sandbox.apply(&mut cmd)).transpose()?;
+    let mut child = cmd
         .spawn()
         .wrap_err_with(|| format!("Unable to spawn command '{}'", command))?;
 
@@ -1,5 +1,9 @@
 use std::io::Write;
-use std::path::Path;
+use std::path::{
+    Component,
+    Path,
+    PathBuf,
+};
 use std::sync::LazyLock;
 
 use crossterm::queue;
@@ -27,6 +31,10 @@ use tracing::{
     warn,
 };
 
+use super::patch::{
+    self,
+    Hunk,
+};
 use super::{
     InvokeOutput,
     format_path,
@@ -35,8 +43,8 @@ use super::{
 };
 use crate::os::Os;
 
-static SYNTAX_SET: LazyLock<SyntaxSet> = LazyLock::new(SyntaxSet::load_defaults_newlines);
-static THEME_SET: LazyLock<ThemeSet> = LazyLock::new(ThemeSet::load_defaults);
+pub static SYNTAX_SET: LazyLock<SyntaxSet> = LazyLock::new(SyntaxSet::load_defaults_newlines);
+pub static THEME_SET: LazyLock<ThemeSet> = LazyLock::new(ThemeSet::load_defaults);
 
 #[derive(Debug, Clone, Deserialize)]
 #[serde(tag = "command")]
@@ -70,6 +78,39 @@ pub enum FsWrite {
         new_str: String,
         summary: Option<String>,
     },
+    /// Applies the replacements in order, writing the file only if all of them succeed.
+    #[serde(rename = "multi_edit")]
+    MultiEdit {
+        path: String,
+        edits: Vec<Edit>,
+        summary: Option<String>,
+    },
+    /// Applies a unified diff. `path` is the directory that the paths in the diff are relative
+    /// to, and the patch may only change files within it.
+    #[serde(rename = "apply_patch")]
+    ApplyPatch {
+        path: String,
+        patch: String,
+        summary: Option<String>,
+    },
+}
+
+/// A single replacement of the [FsWrite::MultiEdit] command
+#[derive(Debug, Clone, Deserialize)]
+pub struct Edit {
+    pub old_str: String,
+    pub new_str: String,
+}
+
+/// A file changed by the [FsWrite::ApplyPatch] command
+#[derive(Debug)]
+struct PatchedFile {
+    /// [None] if the file is created
+    source: Option<PathBuf>,
+    /// [None] if the file is deleted
+    target: Option<PathBuf>,
+    new: String,
+    hunks: Vec<Hunk>,
 }
 
 impl FsWrite {
@@ -173,6 +214,60 @@ impl FsWrite {
                 write_to_file(os, path, file).await?;
                 Ok(Default::default())
             },
+            FsWrite::MultiEdit { path, edits, .. } => {
+                let path = sanitize_path_tool_arg(os, path);
+                let file = os.fs.read_to_string(&path).await?;
+                queue!(
+                    output,
+                    style::Print("Updating: "),
+                    style::SetForegroundColor(Color::Green),
+                    style::Print(format_path(cwd, &path)),
+                    style::ResetColor,
+                    style::Print("\n"),
+                )?;
+
+                let file = apply_edits(file, edits)?;
+                os.fs.write(path, file).await?;
+                Ok(Default::default())
+            },
+            FsWrite::ApplyPatch { path, patch, .. } => {
+                // Every file is patched in memory before anything is written
+                let patched_files = patch_files(os, path, patch)?;
+                for file in &patched_files {
+                    let (description, path) = match (&file.source, &file.target) {
+                        (None, Some(target)) => ("Creating: ", target),
+                        (Some(source), None) => ("Deleting: ", source),
+                        (Some(source), Some(target)) if source != target => ("Renaming to: ", target),
+                        (_, Some(target)) => ("Updating: ", target),
+                        (None, None) => continue,
+                    };
+                    queue!(
+                        output,
+                        style::Print(description),
+                        style::SetForegroundColor(Color::Green),
+                        style::Print(format_path(&cwd, path)),
+                        style::ResetColor,
+                        style::Print("\n"),
+                    )?;
+                }
+
+                // Files written before a failure are restored, so that the patch applies entirely
+                // or not at all
+                let mut originals = Vec::new();
+                if let Err(err) = write_patched_files(os, &patched_files, &mut originals).await {
+                    for (path, content) in originals.into_iter().rev() {
+                        let restored = match content {
+                            Some(content) => os.fs.write(&path, content).await,
+                            None => os.fs.remove_file(&path).await,
+                        };
+                        if let Err(err) = restored {
+                            error!("Failed to restore {}: {err}", path.display());
+                        }
+                    }
+                    return Err(err);
+                }
+                Ok(Default::default())
+            },
         }
     }
 
@@ -256,6 +351,61 @@ impl FsWrite {
                 // Display summary as purpose if available after the diff
                 super::display_purpose(self.get_summary(), output)?;
 
+                Ok(())
+            },
+            FsWrite::MultiEdit { path, edits, .. } => {
+                let path = sanitize_path_tool_arg(os, path);
+                let relative_path = format_path(cwd, &path);
+                let mut file = os.fs.read_to_string_sync(&path)?;
+                // Each edit is shown against the file with the previous edits applied
+                for edit in edits {
+                    let start_line = line_number_at(&file, &edit.old_str).map_or(0, |(start_line, _)| start_line);
+                    let old_str = stylize_output_if_able(os, &relative_path, &edit.old_str);
+                    let new_str = stylize_output_if_able(os, &relative_path, &edit.new_str);
+                    print_diff(output, &old_str, &new_str, start_line)?;
+                    file = file.replacen(&edit.old_str, &edit.new_str, 1);
+                }
+
+                // Display summary as purpose if available after the diff
+                super::display_purpose(self.get_summary(), output)?;
+
+                Ok(())
+            },
+            FsWrite::ApplyPatch { path, patch, .. } => {
+                for file in patch_files(os, path, patch)? {
+                    let Some(path) = file.target.as_ref().or(file.source.as_ref()) else {
+                        continue;
+                    };
+                    let relative_path = format_path(&cwd, path);
+                    let description = match (&file.source, &file.target) {
+                        (None, _) => "created".to_string(),
+                        (_, None) => "deleted".to_string(),
+                        (Some(source), Some(target)) if source != target => {
+                            format!("renamed from {}", format_path(&cwd, source))
+                        },
+                        _ => "modified".to_string(),
+                    };
+                    queue!(
+                        output,
+                        style::SetForegroundColor(Color::Green),
+                        style::Print(&relative_path),
+                        style::ResetColor,
+                        style::Print(format!(" ({description})\n")),
+                    )?;
+
+                    for hunk in &file.hunks {
+                        let old = join_lines(hunk.old_lines());
+                        let new = join_lines(hunk.new_lines());
+                        let old = stylize_output_if_able(os, &relative_path, &old);
+                        let new = stylize_output_if_able(os, &relative_path, &new);
+                        print_diff(output, &old, &new, hunk.old_start.max(1))?;
+                    }
+                    queue!(output, style::Print("\n"))?;
+                }
+
+                // Display summary as purpose if available after the diff
+                super::display_purpose(self.get_summary(), output)?;
+
                 Ok(())
             },
         }
@@ -282,6 +432,19 @@ impl FsWrite {
                     bail!("Content to append must not be empty")
                 };
             },
+            FsWrite::MultiEdit { path, edits, .. } => {
+                if edits.is_empty() {
+                    bail!("At least one edit must be provided")
+                }
+                let path = sanitize_path_tool_arg(os, path);
+                if !path.exists() {
+                    bail!("The provided path must exist in order to edit it")
+                }
+                apply_edits(os.fs.read_to_string(&path).await?, edits)?;
+            },
+            FsWrite::ApplyPatch { path, patch, .. } => {
+                patch_files(os, path, patch)?;
+            },
         }
 
         Ok(())
@@ -294,6 +457,8 @@ impl FsWrite {
             FsWrite::StrReplace { path, .. } => path,
             FsWrite::Insert { path, .. } => path,
             FsWrite::Append { path, .. } => path,
+            FsWrite::MultiEdit { path, .. } => path,
+            FsWrite::ApplyPatch { path, .. } => path,
         };
         // Sanitize the path to handle tilde expansion
         let path = sanitize_path_tool_arg(os, path);
@@ -328,6 +493,42 @@ impl FsWrite {
         }
     }
 
+    /// Returns the name of the command, e.g. `str_replace`
+    pub fn command(&self) -> &'static str {
+        match self {
+            FsWrite::Create { .. } => "create",
+            FsWrite::StrReplace { .. } => "str_replace",
+            FsWrite::Insert { .. } => "insert",
+            FsWrite::Append { .. } => "append",
+            FsWrite::MultiEdit { .. } => "multi_edit",
+            FsWrite::ApplyPatch { .. } => "apply_patch",
+        }
+    }
+
+    /// Returns the sanitized paths of the files written by this command. Patches with paths outside
+    /// of their `path` are rejected when validated, and such paths are left out.
+    pub fn paths(&self, os: &Os) -> Vec<PathBuf> {
+        let path = match self {
+            FsWrite::Create { path, .. } => path,
+            FsWrite::StrReplace { path, .. } => path,
+            FsWrite::Insert { path, .. } => path,
+            FsWrite::Append { path, .. } => path,
+            FsWrite::MultiEdit { path, .. } => path,
+            FsWrite::ApplyPatch { path, patch, .. } => {
+                let Ok(files) = patch::parse(patch) else {
+                    return Vec::new();
+                };
+                return files
+                    .iter()
+                    .flat_map(|file| [&file.old_path, &file.new_path])
+                    .flatten()
+                    .filter_map(|file_path| resolve_patch_path(os, path, file_path).ok())
+                    .collect();
+            },
+        };
+        vec![sanitize_path_tool_arg(os, path)]
+    }
+
     /// Returns the summary from any variant of the FsWrite enum
     fn get_summary(&self) -> Option<&String> {
         match self {
@@ -335,10 +536,132 @@ impl FsWrite {
             FsWrite::StrReplace { summary, .. } => summary.as_ref(),
             FsWrite::Insert { summary, .. } => summary.as_ref(),
             FsWrite::Append { summary, .. } => summary.as_ref(),
+            FsWrite::MultiEdit { summary, .. } => summary.as_ref(),
+            FsWrite::ApplyPatch { summary, .. } => summary.as_ref(),
         }
     }
 }
 
+/// Applies `edits` to `file` in order. Like [FsWrite::StrReplace], every `old_str` has to occur
+/// exactly once in the file, with the previous edits applied.
+fn apply_edits(mut file: String, edits: &[Edit]) -> Result<String> {
+    for (i, Edit { old_str, new_str }) in edits.iter().enumerate() {
+        match file.matches(old_str.as_str()).count() {
+            0 => bail!("edit {}: no occurrences of \"{old_str}\" were found", i + 1),
+            1 => file = file.replacen(old_str, new_str, 1),
+            x => bail!(
+                "edit {}: {x} occurrences of old_str were found when only 1 is expected",
+                i + 1
+            ),
+        }
+    }
+    Ok(file)
+}
+
+/// Resolves a path of a patch relative to `base`, the `path` of the [FsWrite::ApplyPatch] command.
+/// Absolute paths and paths with `..` components are rejected, since permission rules only check
+/// `base` and a patch must not write outside of it.
+fn resolve_patch_path(os: &Os, base: &str, path: &str) -> Result<PathBuf> {
+    let relative = Path::new(path);
+    let is_contained = !path.starts_with('~')
+        && relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+    if !is_contained {
+        bail!("{path} is outside of {base}, patches may only change files within their path")
+    }
+
+    // Symlinks within `base` may still lead outside of it, so the closest existing ancestor of
+    // the path has to stay within `base` once they are resolved
+    let base_path = sanitize_path_tool_arg(os, base);
+    let resolved = base_path.join(relative);
+    let existing = resolved
+        .ancestors()
+        .take_while(|ancestor| ancestor.starts_with(&base_path))
+        .find(|ancestor| ancestor.symlink_metadata().is_ok());
+    if let Some(existing) = existing {
+        let is_contained = match (existing.canonicalize(), base_path.canonicalize()) {
+            (Ok(existing), Ok(base_path)) => existing.starts_with(base_path),
+            _ => false,
+        };
+        if !is_contained {
+            bail!("{path} leads outside of {base} through a symlink, patches may only change files within their path")
+        }
+    }
+    Ok(resolved)
+}
+
+/// Writes the files of a patch, recording the previous content of every path it writes or removes
+/// in `originals`, [None] for paths that did not exist.
+async fn write_patched_files(
+    os: &Os,
+    files: &[PatchedFile],
+    originals: &mut Vec<(PathBuf, Option<Vec<u8>>)>,
+) -> Result<()> {
+    for file in files {
+        if let Some(target) = &file.target {
+            if let Some(parent) = target.parent() {
+                os.fs.create_dir_all(parent).await?;
+            }
+            originals.push((target.clone(), os.fs.read(target).await.ok()));
+            os.fs.write(target, &file.new).await?;
+        }
+        match &file.source {
+            Some(source) if file.target.as_ref() != Some(source) => {
+                originals.push((source.clone(), Some(os.fs.read(source).await?)));
+                os.fs.remove_file(source).await?;
+            },
+            _ => (),
+        }
+    }
+    Ok(())
+}
+
+/// Parses `patch` and applies it to the files it touches in memory, failing if any of its hunks
+/// do not apply.
+fn patch_files(os: &Os, base: &str, patch: &str) -> Result<Vec<PatchedFile>> {
+    patch::parse(patch)?
+        .into_iter()
+        .map(|file_patch| {
+            let source = file_patch
+                .old_path
+                .map(|path| resolve_patch_path(os, base, &path))
+                .transpose()?;
+            let target = file_patch
+                .new_path
+                .map(|path| resolve_patch_path(os, base, &path))
+                .transpose()?;
+            let old = match (&source, &target) {
+                (Some(source), _) => os
+                    .fs
+                    .read_to_string_sync(source)
+                    .map_err(|err| eyre!("Failed to read {}: {err}", source.display()))?,
+                (None, Some(target)) if os.fs.exists(target) => {
+                    bail!("{} cannot be created, it already exists", target.display())
+                },
+                _ => String::new(),
+            };
+            let display_path = target
+                .as_ref()
+                .or(source.as_ref())
+                .map(|path| path.display().to_string());
+            let new = patch::apply(&old, &file_patch.hunks)
+                .map_err(|err| eyre!("Failed to patch {}: {err}", display_path.unwrap_or_default()))?;
+            Ok(PatchedFile {
+                source,
+                target,
+                new,
+                hunks: file_patch.hunks,
+            })
+        })
+        .collect()
+}
+
+/// Joins `lines` into the text of a hunk, for displaying it with [print_diff].
+fn join_lines<'a>(lines: impl Iterator<Item = &'a str>) -> String {
+    lines.map(|line| format!("{line}\n")).collect()
+}
+
 /// Writes `content` to `path`, adding a newline if necessary.
 async fn write_to_file(os: &Os, path: impl AsRef<Path>, mut content: String) -> Result<()> {
     let path_ref = path.as_ref();
@@ -988,6 +1311,171 @@ mod tests {
         assert!(result.is_err(), "Appending to non-existent file should fail");
     }
 
+    #[tokio::test]
+    async fn test_fs_write_tool_multi_edit() {
+        let os = setup_test_directory().await;
+        let mut stdout = std::io::stdout();
+
+        // "Hello world!" is not unique until the first line is replaced
+        let v = serde_json::json!({
+            "path": TEST_FILE_PATH,
+            "command": "multi_edit",
+            "edits": [
+                { "old_str": "1: Hello world!", "new_str": "1: Goodbye world!" },
+                { "old_str": "Hello world!", "new_str": "Hello again!" },
+            ],
+        });
+        let mut tool = serde_json::from_value::<FsWrite>(v).unwrap();
+        tool.validate(&os).await.unwrap();
+        tool.invoke(&os, &mut stdout).await.unwrap();
+        assert_eq!(
+            os.fs.read_to_string(TEST_FILE_PATH).await.unwrap(),
+            "1: Goodbye world!\n2: This is line 2\n3: asdf\n4: Hello again!\n"
+        );
+
+        // Nothing is written if any edit fails
+        let v = serde_json::json!({
+            "path": TEST_FILE_PATH,
+            "command": "multi_edit",
+            "edits": [
+                { "old_str": "asdf", "new_str": "qwer" },
+                { "old_str": "missing", "new_str": "found" },
+            ],
+        });
+        let mut tool = serde_json::from_value::<FsWrite>(v).unwrap();
+        assert!(tool.validate(&os).await.is_err());
+        assert!(tool.invoke(&os, &mut stdout).await.is_err());
+        assert!(os.fs.read_to_string(TEST_FILE_PATH).await.unwrap().contains("3: asdf"));
+    }
+
+    #[tokio::test]
+    async fn test_fs_write_tool_apply_patch() {
+        let os = setup_test_directory().await;
+        let mut stdout = std::io::stdout();
+
+        let patch = "\
+--- a/test_file.txt
++++ b/test_file.txt
+@@ -2,2 +2,2 @@
+ 2: This is line 2
+-3: asdf
++3: qwer
+--- /dev/null
++++ b/aaaa1/new.txt
+@@ -0,0 +1 @@
++new file
+";
+        let v = serde_json::json!({
+            "path": "/",
+            "command": "apply_patch",
+            "patch": patch,
+        });
+        let mut tool = serde_json::from_value::<FsWrite>(v).unwrap();
+        tool.validate(&os).await.unwrap();
+        assert_eq!(tool.paths(&os), vec![
+            os.fs.chroot_path(TEST_FILE_PATH),
+            os.fs.chroot_path(TEST_FILE_PATH),
+            os.fs.chroot_path("/aaaa1/new.txt")
+        ]);
+        tool.invoke(&os, &mut stdout).await.unwrap();
+        assert_eq!(
+            os.fs.read_to_string(TEST_FILE_PATH).await.unwrap(),
+            "1: Hello world!\n2: This is line 2\n3: qwer\n4: Hello world!\n"
+        );
+        assert_eq!(os.fs.read_to_string("/aaaa1/new.txt").await.unwrap(), "new file\n");
+
+        // Nothing is written if any hunk does not apply
+        let patch = "\
+--- /dev/null
++++ b/aaaa1/other.txt
+@@ -0,0 +1 @@
++other file
+--- a/test_file.txt
++++ b/test_file.txt
+@@ -3 +3 @@
+-3: asdf
++3: zxcv
+";
+        let v = serde_json::json!({
+            "path": "/",
+            "command": "apply_patch",
+            "patch": patch,
+        });
+        let mut tool = serde_json::from_value::<FsWrite>(v).unwrap();
+        assert!(tool.validate(&os).await.is_err());
+        assert!(tool.invoke(&os, &mut stdout).await.is_err());
+        assert!(!os.fs.exists("/aaaa1/other.txt"));
+    }
+
+    #[tokio::test]
+    async fn test_fs_write_tool_apply_patch_outside_path() {
+        let os = setup_test_directory().await;
+        let mut stdout = std::io::stdout();
+
+        for target in ["b/../test_file.txt", "/test_file.txt", "b/sub/../../../test_file.txt"] {
+            let patch = format!(
+                "\
+--- /dev/null
++++ {target}
+@@ -0,0 +1 @@
++escaped
+"
+            );
+            let v = serde_json::json!({
+                "path": "/aaaa1",
+                "command": "apply_patch",
+                "patch": patch,
+            });
+            let mut tool = serde_json::from_value::<FsWrite>(v).unwrap();
+            let err = tool.validate(&os).await.unwrap_err();
+            assert!(err.to_string().contains("outside of /aaaa1"), "{target}: {err}");
+            assert!(tool.paths(&os).is_empty());
+            assert!(tool.invoke(&os, &mut stdout).await.is_err());
+        }
+        assert!(!os.fs.read_to_string(TEST_FILE_PATH).await.unwrap().contains("escaped"));
+
+        // Symlinks within the path may not lead outside of it either
+        os.fs.create_dir_all("/outside").await.unwrap();
+        os.fs.symlink("/outside", "/aaaa1/link").await.unwrap();
+        let v = serde_json::json!({
+            "path": "/aaaa1",
+            "command": "apply_patch",
+            "patch": "--- /dev/null\n+++ b/link/escaped.txt\n@@ -0,0 +1 @@\n+escaped\n",
+        });
+        let mut tool = serde_json::from_value::<FsWrite>(v).unwrap();
+        let err = tool.validate(&os).await.unwrap_err();
+        assert!(err.to_string().contains("through a symlink"), "{err}");
+        assert!(tool.invoke(&os, &mut stdout).await.is_err());
+        assert!(!os.fs.exists("/outside/escaped.txt"));
+    }
+
+    #[tokio::test]
+    async fn test_fs_write_tool_apply_patch_restores_files_on_error() {
+        let os = setup_test_directory().await;
+        let mut stdout = std::io::stdout();
+
+        // The second file cannot be written since its parent is a file
+        let patch = "\
+--- a/test_file.txt
++++ b/test_file.txt
+@@ -3 +3 @@
+-3: asdf
++3: zxcv
+--- /dev/null
++++ b/test_file.txt/new.txt
+@@ -0,0 +1 @@
++new file
+";
+        let v = serde_json::json!({
+            "path": "/",
+            "command": "apply_patch",
+            "patch": patch,
+        });
+        let tool = serde_json::from_value::<FsWrite>(v).unwrap();
+        assert!(tool.invoke(&os, &mut stdout).await.is_err());
+        assert!(os.fs.read_to_string(TEST_FILE_PATH).await.unwrap().contains("3: asdf"));
+    }
+
     #[test]
     fn test_lines_with_context() {
         let content = "Hello\nWorld!\nhow\nare\nyou\ntoday?";
@@ -164,6 +164,15 @@ impl GhIssue {
             ));
         }
 
+        if os_manager.workspace_config.paths.is_empty() {
+            os_str.push_str("workspace_context=none\n\n");
+        } else {
+            os_str.push_str(&format!(
+                "workspace_context=\n{}\n\n",
+                &os_manager.workspace_config.paths.join("\n")
+            ));
+        }
+
         if os_manager.profile_config.paths.is_empty() {
             os_str.push_str("profile_context=none\n\n");
         } else {
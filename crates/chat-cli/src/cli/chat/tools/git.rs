@@ -0,0 +1,657 @@
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+use std::io::Write;
+use std::process::Stdio;
+use std::time::Duration;
+
+use crossterm::queue;
+use crossterm::style::{
+    self,
+    Color,
+};
+use eyre::{
+    Result,
+    bail,
+    eyre,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use super::{
+    InvokeOutput,
+    MAX_TOOL_RESPONSE_SIZE,
+    OutputKind,
+    sanitize_path_tool_arg,
+};
+use crate::cli::chat::util::truncate_safe;
+use crate::os::Os;
+
+/// Number of commits returned by `log` unless `max_count` is given.
+const DEFAULT_LOG_COUNT: usize = 20;
+
+/// Max time a git command can run.
+const GIT_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GitCommand {
+    Status,
+    Diff,
+    Log,
+    Blame,
+    Commit,
+}
+
+impl GitCommand {
+    fn as_str(self) -> &'static str {
+        match self {
+            GitCommand::Status => "status",
+            GitCommand::Diff => "diff",
+            GitCommand::Log => "log",
+            GitCommand::Blame => "blame",
+            GitCommand::Commit => "commit",
+        }
+    }
+}
+
+/// Runs git in the current directory. Every command but `commit` only reads the repository.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Git {
+    pub command: GitCommand,
+    /// File or directory the diff, log or blame is limited to
+    pub path: Option<String>,
+    /// Revision or range of revisions of the diff, log or blame
+    pub revision: Option<String>,
+    /// Whether the diff is of the staged changes rather than those of the working tree
+    #[serde(default)]
+    pub staged: bool,
+    pub max_count: Option<usize>,
+    pub start_line: Option<usize>,
+    pub end_line: Option<usize>,
+    /// Message of the commit
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct GitStatus {
+    /// The current branch, [None] if HEAD is detached
+    pub branch: Option<String>,
+    pub upstream: Option<String>,
+    pub ahead: u32,
+    pub behind: u32,
+    pub files: Vec<StatusEntry>,
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StatusEntry {
+    pub path: String,
+    /// The path before the file was renamed or copied
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub original_path: Option<String>,
+    /// Status of the file in the index, as in `git status --short`
+    pub index: String,
+    /// Status of the file in the working tree, as in `git status --short`
+    pub worktree: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct GitDiff {
+    pub files: Vec<DiffStat>,
+    pub patch: String,
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DiffStat {
+    pub path: String,
+    /// Number of added lines, [None] for binary files
+    pub additions: Option<u32>,
+    /// Number of deleted lines, [None] for binary files
+    pub deletions: Option<u32>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct GitLog {
+    pub commits: Vec<LogEntry>,
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LogEntry {
+    pub hash: String,
+    pub author: String,
+    pub email: String,
+    pub date: String,
+    pub subject: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct GitBlame {
+    pub lines: Vec<BlameLine>,
+    /// The commits of [Self::lines], by their abbreviated hash
+    pub commits: BTreeMap<String, BlameCommit>,
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BlameLine {
+    pub line: usize,
+    /// Abbreviated hash of the commit that last changed the line
+    pub commit: String,
+    pub content: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct BlameCommit {
+    pub author: String,
+    pub date: String,
+    pub summary: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GitCommit {
+    pub hash: String,
+    /// What git reported, e.g. `[main 1a2b3c4] Fix the parser`
+    pub summary: String,
+}
+
+impl Git {
+    pub fn requires_acceptance(&self) -> bool {
+        self.command == GitCommand::Commit
+    }
+
+    pub async fn invoke(&self, os: &Os, _updates: &mut impl Write) -> Result<InvokeOutput> {
+        let path = self.path.as_deref();
+        let revision = self.revision.as_deref();
+        let value = match self.command {
+            GitCommand::Status => serde_json::to_value(status(os).await?)?,
+            GitCommand::Diff => serde_json::to_value(diff(os, self.staged, revision, path).await?)?,
+            GitCommand::Log => {
+                serde_json::to_value(log(os, revision, path, self.max_count.unwrap_or(DEFAULT_LOG_COUNT)).await?)?
+            },
+            GitCommand::Blame => serde_json::to_value(
+                blame(
+                    os,
+                    path.unwrap_or_default(),
+                    revision,
+                    self.start_line.zip(self.end_line.or(self.start_line)),
+                )
+                .await?,
+            )?,
+            GitCommand::Commit => serde_json::to_value(commit(os, self.message.as_deref().unwrap_or_default()).await?)?,
+        };
+
+        Ok(InvokeOutput {
+            output: OutputKind::Json(value),
+        })
+    }
+
+    pub fn queue_description(&self, output: &mut impl Write) -> Result<()> {
+        queue!(
+            output,
+            style::Print("Running git "),
+            style::SetForegroundColor(Color::Green),
+            style::Print(self.command.as_str()),
+            style::ResetColor,
+        )?;
+        if self.staged && self.command == GitCommand::Diff {
+            queue!(output, style::Print(" of the staged changes"))?;
+        }
+        if let Some(revision) = &self.revision {
+            queue!(output, style::Print(format!(" of {}", revision)))?;
+        }
+        if let Some(path) = &self.path {
+            queue!(output, style::Print(format!(" for {}", path)))?;
+        }
+        if let Some(start_line) = self.start_line {
+            let end_line = self.end_line.unwrap_or(start_line);
+            queue!(output, style::Print(format!(" (lines {}-{})", start_line, end_line)))?;
+        }
+        if let Some(message) = &self.message {
+            queue!(output, style::Print(" with the message:\n\n"), style::Print(message))?;
+        }
+        Ok(())
+    }
+
+    pub async fn validate(&mut self, os: &Os) -> Result<()> {
+        // Revisions are passed as arguments, which must not be taken as options
+        if let Some(revision) = &self.revision {
+            if revision.trim().is_empty() || revision.starts_with('-') {
+                bail!("Invalid revision '{}'", revision);
+            }
+        }
+        match self.command {
+            GitCommand::Blame => {
+                if self.path.is_none() {
+                    bail!("A path is required to blame");
+                }
+                match (self.start_line, self.end_line) {
+                    (Some(0), _) | (_, Some(0)) => bail!("Lines start at 1"),
+                    (Some(start), Some(end)) if start > end => {
+                        bail!("start_line {} is after end_line {}", start, end)
+                    },
+                    (None, Some(_)) => bail!("end_line requires start_line"),
+                    _ => {},
+                }
+            },
+            GitCommand::Commit if self.message.as_deref().is_none_or(|message| message.trim().is_empty()) => {
+                bail!("A message is required to commit");
+            },
+            _ => {},
+        }
+
+        run_git(os, ["rev-parse", "--is-inside-work-tree"])
+            .await
+            .map_err(|_err| eyre!("The current directory is not in a git repository"))?;
+        Ok(())
+    }
+}
+
+/// Runs git with `args` in the current directory, returning its stdout.
+async fn run_git<I, S>(os: &Os, args: I) -> Result<String>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    let mut command = tokio::process::Command::new("git");
+    command
+        .arg("--no-pager")
+        .args(args)
+        .current_dir(os.fs.chroot_path(os.env.current_dir()?))
+        .stdin(Stdio::null())
+        .kill_on_drop(true);
+    let output = tokio::time::timeout(GIT_TIMEOUT, command.output())
+        .await
+        .map_err(|_elapsed| eyre!("git timed out after {} s", GIT_TIMEOUT.as_secs()))??;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!("git failed with {}: {}", output.status, stderr.trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Appends the path a command is limited to, after `--` so that it is not taken as a revision.
+fn push_path(os: &Os, args: &mut Vec<String>, path: Option<&str>) {
+    if let Some(path) = path {
+        args.push("--".to_string());
+        args.push(sanitize_path_tool_arg(os, path).to_string_lossy().into_owned());
+    }
+}
+
+/// Keeps the leading `entries` whose serialized size fits in `budget` bytes. Returns whether any
+/// were dropped.
+fn truncate_entries<T: Serialize>(entries: &mut Vec<T>, budget: usize) -> bool {
+    let mut size = 0;
+    let fitting = entries
+        .iter()
+        .take_while(|entry| {
+            size += serde_json::to_string(entry).map_or(0, |entry| entry.len()) + 1;
+            size <= budget
+        })
+        .count();
+    let truncated = fitting < entries.len();
+    entries.truncate(fitting);
+    truncated
+}
+
+pub async fn status(os: &Os) -> Result<GitStatus> {
+    let output = run_git(os, ["status", "--porcelain=v1", "--branch", "-z"]).await?;
+    let mut status = parse_status(&output);
+    status.truncated = truncate_entries(&mut status.files, MAX_TOOL_RESPONSE_SIZE);
+    Ok(status)
+}
+
+/// Parses the output of `git status --porcelain=v1 --branch -z`.
+fn parse_status(output: &str) -> GitStatus {
+    let mut status = GitStatus::default();
+    let mut records = output.split('\0').filter(|record| !record.is_empty());
+    while let Some(record) = records.next() {
+        if let Some(header) = record.strip_prefix("## ") {
+            parse_branch_header(header, &mut status);
+            continue;
+        }
+        if record.len() < 4 || !record.is_char_boundary(3) {
+            continue;
+        }
+        let (index, worktree) = (&record[0..1], &record[1..2]);
+        // The original path of renames and copies is the next record
+        let original_path = match index {
+            "R" | "C" => records.next().map(str::to_string),
+            _ => None,
+        };
+        status.files.push(StatusEntry {
+            path: record[3..].to_string(),
+            original_path,
+            index: index.to_string(),
+            worktree: worktree.to_string(),
+        });
+    }
+    status
+}
+
+/// Parses the branch header of `git status --branch`, e.g. `main...origin/main [ahead 1]`.
+fn parse_branch_header(header: &str, status: &mut GitStatus) {
+    let (refs, tracking) = match header.split_once(" [") {
+        Some((refs, tracking)) => (refs, tracking.trim_end_matches(']')),
+        None => (header, ""),
+    };
+    if let Some(branch) = refs.strip_prefix("No commits yet on ") {
+        status.branch = Some(branch.to_string());
+    } else if !refs.starts_with("HEAD (no branch)") {
+        let (branch, upstream) = match refs.split_once("...") {
+            Some((branch, upstream)) => (branch, Some(upstream.to_string())),
+            None => (refs, None),
+        };
+        status.branch = Some(branch.to_string());
+        status.upstream = upstream;
+    }
+    for part in tracking.split(", ") {
+        if let Some(ahead) = part.strip_prefix("ahead ") {
+            status.ahead = ahead.parse().unwrap_or_default();
+        } else if let Some(behind) = part.strip_prefix("behind ") {
+            status.behind = behind.parse().unwrap_or_default();
+        }
+    }
+}
+
+/// The changes of the working tree, of the index if `staged`, or of `revision`.
+pub async fn diff(os: &Os, staged: bool, revision: Option<&str>, path: Option<&str>) -> Result<GitDiff> {
+    // External diff drivers would run configured programs and change the output format
+    let mut args = vec!["diff".to_string(), "--no-ext-diff".to_string()];
+    if staged {
+        args.push("--staged".to_string());
+    }
+    args.extend(revision.map(str::to_string));
+
+    let mut numstat_args = args.clone();
+    numstat_args.push("--numstat".to_string());
+    push_path(os, &mut numstat_args, path);
+    push_path(os, &mut args, path);
+
+    let mut files = parse_numstat(&run_git(os, numstat_args).await?);
+    let patch = run_git(os, args).await?;
+
+    // Part of the patch is more useful than all the stats of a huge change
+    let mut truncated = truncate_entries(&mut files, MAX_TOOL_RESPONSE_SIZE / 4);
+    let budget = MAX_TOOL_RESPONSE_SIZE / 2;
+    let patch = if patch.len() > budget {
+        truncated = true;
+        truncate_safe(&patch, budget).to_string()
+    } else {
+        patch
+    };
+
+    Ok(GitDiff {
+        files,
+        patch,
+        truncated,
+    })
+}
+
+/// Parses the output of `git diff --numstat`, where binary files have `-` as their counts.
+fn parse_numstat(output: &str) -> Vec<DiffStat> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.splitn(3, '\t');
+            let additions = parts.next()?.parse().ok();
+            let deletions = parts.next()?.parse().ok();
+            Some(DiffStat {
+                path: parts.next()?.to_string(),
+                additions,
+                deletions,
+            })
+        })
+        .collect()
+}
+
+pub async fn log(os: &Os, revision: Option<&str>, path: Option<&str>, max_count: usize) -> Result<GitLog> {
+    let mut args = vec![
+        "log".to_string(),
+        format!("--max-count={}", max_count),
+        "--format=%H%x1f%an%x1f%ae%x1f%aI%x1f%s%x1e".to_string(),
+    ];
+    args.extend(revision.map(str::to_string));
+    push_path(os, &mut args, path);
+
+    let output = run_git(os, args).await?;
+    let mut commits = output
+        .split('\x1e')
+        .filter_map(|record| {
+            let mut fields = record.trim_start_matches('\n').split('\x1f');
+            Some(LogEntry {
+                hash: fields.next().filter(|hash| !hash.is_empty())?.to_string(),
+                author: fields.next()?.to_string(),
+                email: fields.next()?.to_string(),
+                date: fields.next()?.to_string(),
+                subject: fields.next()?.to_string(),
+            })
+        })
+        .collect::<Vec<_>>();
+    let truncated = truncate_entries(&mut commits, MAX_TOOL_RESPONSE_SIZE);
+    Ok(GitLog { commits, truncated })
+}
+
+/// Blames the lines of `path`, or only those of `lines` (first and last, starting at 1).
+pub async fn blame(os: &Os, path: &str, revision: Option<&str>, lines: Option<(usize, usize)>) -> Result<GitBlame> {
+    let mut args = vec!["blame".to_string(), "--porcelain".to_string()];
+    if let Some((start, end)) = lines {
+        args.push(format!("-L{},{}", start, end));
+    }
+    args.extend(revision.map(str::to_string));
+    push_path(os, &mut args, Some(path));
+
+    let mut blame = parse_blame(&run_git(os, args).await?);
+    blame.truncated = truncate_entries(&mut blame.lines, MAX_TOOL_RESPONSE_SIZE / 2);
+    if blame.truncated {
+        let lines = &blame.lines;
+        blame
+            .commits
+            .retain(|hash, _| lines.iter().any(|line| line.commit == *hash));
+    }
+    Ok(blame)
+}
+
+/// Parses the output of `git blame --porcelain`, where the information of each commit is only
+/// given before its first line.
+fn parse_blame(output: &str) -> GitBlame {
+    const HASH_LEN: usize = 8;
+
+    let mut blame = GitBlame::default();
+    let mut current: Option<(String, usize)> = None;
+    for line in output.lines() {
+        if let Some(content) = line.strip_prefix('\t') {
+            if let Some((commit, line)) = current.take() {
+                blame.lines.push(BlameLine {
+                    line,
+                    commit,
+                    content: content.to_string(),
+                });
+            }
+            continue;
+        }
+
+        let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+        match &current {
+            None => {
+                // `<hash> <original line> <final line> [<number of lines>]`
+                let hash = key.get(..HASH_LEN).unwrap_or(key).to_string();
+                let final_line = value.split(' ').nth(1).and_then(|line| line.parse().ok());
+                if let Some(final_line) = final_line {
+                    blame.commits.entry(hash.clone()).or_default();
+                    current = Some((hash, final_line));
+                }
+            },
+            Some((hash, _)) => {
+                let Some(commit) = blame.commits.get_mut(hash) else {
+                    continue;
+                };
+                match key {
+                    "author" => commit.author = value.to_string(),
+                    "author-time" => {
+                        commit.date = value
+                            .parse()
+                            .ok()
+                            .and_then(|secs| time::OffsetDateTime::from_unix_timestamp(secs).ok())
+                            .and_then(|date| {
+                                date.format(time::macros::format_description!("[year]-[month]-[day]"))
+                                    .ok()
+                            })
+                            .unwrap_or_default();
+                    },
+                    "summary" => commit.summary = value.to_string(),
+                    _ => {},
+                }
+            },
+        }
+    }
+    blame
+}
+
+/// Commits the staged changes with `message`.
+pub async fn commit(os: &Os, message: &str) -> Result<GitCommit> {
+    let output = run_git(os, ["commit", "--message", message]).await?;
+    let hash = run_git(os, ["rev-parse", "HEAD"]).await?;
+    Ok(GitCommit {
+        hash: hash.trim().to_string(),
+        summary: output.lines().next().unwrap_or_default().to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn git(os: &Os, args: &[&str]) {
+        run_git(os, args).await.unwrap();
+    }
+
+    /// A repository in the current directory with one commit of `a.txt`
+    async fn setup_repo() -> Os {
+        let os = Os::new().await.unwrap();
+        git(&os, &["init", "--quiet", "--initial-branch=main"]).await;
+        git(&os, &["config", "user.name", "Test"]).await;
+        git(&os, &["config", "user.email", "test@example.com"]).await;
+        os.fs.write("/a.txt", "one\ntwo\n").await.unwrap();
+        git(&os, &["add", "a.txt"]).await;
+        git(&os, &["commit", "--quiet", "--message", "Add a.txt"]).await;
+        os
+    }
+
+    #[test]
+    fn test_parse_status() {
+        let output =
+            "## main...origin/main [ahead 2, behind 1]\0M  src/lib.rs\0 M README.md\0R  new.rs\0old.rs\0?? notes.md\0";
+        let status = parse_status(output);
+        assert_eq!(status.branch.as_deref(), Some("main"));
+        assert_eq!(status.upstream.as_deref(), Some("origin/main"));
+        assert_eq!((status.ahead, status.behind), (2, 1));
+        assert_eq!(status.files.len(), 4);
+        assert_eq!(status.files[0].index, "M");
+        assert_eq!(status.files[1].worktree, "M");
+        assert_eq!(status.files[2].path, "new.rs");
+        assert_eq!(status.files[2].original_path.as_deref(), Some("old.rs"));
+        assert_eq!(status.files[3].index, "?");
+
+        let status = parse_status("## HEAD (no branch)\0");
+        assert_eq!(status.branch, None);
+        let status = parse_status("## No commits yet on main\0");
+        assert_eq!(status.branch.as_deref(), Some("main"));
+    }
+
+    #[test]
+    fn test_truncate_entries() {
+        let mut entries = vec!["a".repeat(10); 10];
+        assert!(!truncate_entries(&mut entries, 1000));
+        assert_eq!(entries.len(), 10);
+        // Each entry takes 13 bytes, with its quotes and a separator
+        assert!(truncate_entries(&mut entries, 40));
+        assert_eq!(entries.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_git_tool() {
+        let os = setup_repo().await;
+        os.fs.write("/a.txt", "one\n2\nthree\n").await.unwrap();
+        os.fs.write("/b.txt", "new").await.unwrap();
+
+        let status = status(&os).await.unwrap();
+        assert_eq!(status.branch.as_deref(), Some("main"));
+        assert_eq!(status.files.len(), 2);
+        assert_eq!(status.files[0].path, "a.txt");
+        assert_eq!(status.files[0].worktree, "M");
+        assert_eq!(status.files[1].index, "?");
+
+        let changes = diff(&os, false, None, None).await.unwrap();
+        assert_eq!(changes.files, vec![DiffStat {
+            path: "a.txt".to_string(),
+            additions: Some(2),
+            deletions: Some(1),
+        }]);
+        assert!(changes.patch.contains("+three"));
+        assert!(!changes.truncated);
+        assert!(diff(&os, true, None, None).await.unwrap().files.is_empty());
+
+        git(&os, &["add", "a.txt"]).await;
+        let commit = commit(&os, "Change a.txt").await.unwrap();
+        assert_eq!(commit.hash.len(), 40);
+
+        let history = log(&os, None, Some("a.txt"), 20).await.unwrap();
+        assert_eq!(history.commits.len(), 2);
+        assert_eq!(history.commits[0].subject, "Change a.txt");
+        assert_eq!(history.commits[1].author, "Test");
+        assert_eq!(log(&os, None, None, 1).await.unwrap().commits.len(), 1);
+
+        let blame = blame(&os, "a.txt", None, Some((2, 3))).await.unwrap();
+        assert_eq!(blame.lines.len(), 2);
+        assert_eq!(blame.lines[0].line, 2);
+        assert_eq!(blame.lines[0].content, "2");
+        assert!(commit.hash.starts_with(&blame.lines[0].commit));
+        assert_eq!(blame.commits[&blame.lines[0].commit].summary, "Change a.txt");
+        assert_eq!(blame.commits.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_validate() {
+        let os = setup_repo().await;
+        let tool = |args: serde_json::Value| serde_json::from_value::<Git>(args).unwrap();
+
+        assert!(
+            tool(serde_json::json!({ "command": "status" }))
+                .validate(&os)
+                .await
+                .is_ok()
+        );
+        assert!(
+            tool(serde_json::json!({ "command": "diff", "revision": "--output=/tmp/x" }))
+                .validate(&os)
+                .await
+                .is_err()
+        );
+        assert!(
+            tool(serde_json::json!({ "command": "blame" }))
+                .validate(&os)
+                .await
+                .is_err()
+        );
+        assert!(
+            tool(serde_json::json!({ "command": "blame", "path": "a.txt", "start_line": 3, "end_line": 1 }))
+                .validate(&os)
+                .await
+                .is_err()
+        );
+        assert!(
+            tool(serde_json::json!({ "command": "commit" }))
+                .validate(&os)
+                .await
+                .is_err()
+        );
+
+        let commit = tool(serde_json::json!({ "command": "commit", "message": "Fix" }));
+        assert!(commit.requires_acceptance());
+        assert!(!tool(serde_json::json!({ "command": "log" })).requires_acceptance());
+    }
+}
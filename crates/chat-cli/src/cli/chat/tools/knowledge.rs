@@ -6,6 +6,12 @@ use crossterm::style::{
     Color,
 };
 use eyre::Result;
+use semantic_search_client::{
+    IndexingFilter,
+    SearchMode,
+    SearchOptions,
+    SearchResult,
+};
 use serde::Deserialize;
 use tracing::warn;
 
@@ -62,6 +68,12 @@ pub struct KnowledgeClear {
 pub struct KnowledgeSearch {
     pub query: String,
     pub context_id: Option<String>,
+    /// Retrieval strategy, hybrid when not provided
+    #[serde(default)]
+    pub mode: SearchMode,
+    /// Whether to rerank the results with a local cross-encoder
+    #[serde(default)]
+    pub rerank: bool,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -321,7 +333,7 @@ impl Knowledge {
                     add.value.clone()
                 };
 
-                match store.add(&add.name, &value_to_use).await {
+                match store.add(&add.name, &value_to_use, IndexingFilter::default()).await {
                     Ok(context_id) => format!(
                         "Added '{}' to knowledge base with ID: {}. Track active jobs in '/knowledge status' with provided id.",
                         add.name, context_id
@@ -409,7 +421,11 @@ impl Knowledge {
                 .unwrap_or_else(|e| format!("Failed to clear knowledge base: {}", e)),
             Knowledge::Search(search) => {
                 // Only use a spinner for search, not a full progress bar
-                let results = store.search(&search.query, search.context_id.as_deref()).await;
+                let options = SearchOptions {
+                    mode: search.mode,
+                    rerank: search.rerank,
+                };
+                let results = store.search(&search.query, search.context_id.as_deref(), options).await;
                 match results {
                     Ok(results) => {
                         if results.is_empty() {
@@ -418,7 +434,10 @@ impl Knowledge {
                             let mut output = String::from("Search results:\n");
                             for result in results {
                                 if let Some(text) = result.text() {
-                                    output.push_str(&format!("- {}\n", text));
+                                    match Self::format_location(&result) {
+                                        Some(location) => output.push_str(&format!("- {}\n{}\n", location, text)),
+                                        None => output.push_str(&format!("- {}\n", text)),
+                                    }
                                 }
                             }
                             output
@@ -472,6 +491,20 @@ impl Knowledge {
         })
     }
 
+    /// Format where a search result comes from, as `path:start-end (symbol)`
+    fn format_location(result: &SearchResult) -> Option<String> {
+        let payload = &result.point.payload;
+        let mut location = payload.get("path")?.as_str()?.to_string();
+        let line = |key: &str| payload.get(key).and_then(|value| value.as_u64());
+        if let (Some(start_line), Some(end_line)) = (line("start_line"), line("end_line")) {
+            location.push_str(&format!(":{}-{}", start_line, end_line));
+        }
+        if let Some(symbol) = payload.get("symbol").and_then(|value| value.as_str()) {
+            location.push_str(&format!(" ({})", symbol));
+        }
+        Some(location)
+    }
+
     /// Format status data for display (UI rendering responsibility)
     fn format_status_display(status: &semantic_search_client::SystemStatus) -> String {
         let mut status_lines = Vec::new();
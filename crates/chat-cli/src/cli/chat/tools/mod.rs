@@ -3,7 +3,10 @@ pub mod execute;
 pub mod fs_read;
 pub mod fs_write;
 pub mod gh_issue;
+pub mod git;
 pub mod knowledge;
+pub mod patch;
+pub mod permissions;
 pub mod thinking;
 pub mod use_aws;
 
@@ -24,12 +27,17 @@ use crossterm::style::{
     Stylize,
 };
 use custom_tool::CustomTool;
-use execute::ExecuteCommand;
+use execute::{
+    CommandPolicy,
+    ExecuteCommand,
+};
 use eyre::Result;
 use fs_read::FsRead;
 use fs_write::FsWrite;
 use gh_issue::GhIssue;
+use git::Git;
 use knowledge::Knowledge;
+use permissions::ToolPermissionRules;
 use serde::{
     Deserialize,
     Serialize,
@@ -51,6 +59,7 @@ pub enum Tool {
     UseAws(UseAws),
     Custom(CustomTool),
     GhIssue(GhIssue),
+    Git(Git),
     Knowledge(Knowledge),
     Thinking(Thinking),
 }
@@ -68,6 +77,7 @@ impl Tool {
             Tool::UseAws(_) => "use_aws",
             Tool::Custom(custom_tool) => &custom_tool.name,
             Tool::GhIssue(_) => "gh_issue",
+            Tool::Git(_) => "git",
             Tool::Knowledge(_) => "knowledge",
             Tool::Thinking(_) => "thinking (prerelease)",
         }
@@ -75,14 +85,17 @@ impl Tool {
     }
 
     /// Whether or not the tool should prompt the user to accept before [Self::invoke] is called.
-    pub fn requires_acceptance(&self, _os: &Os) -> bool {
+    pub fn requires_acceptance(&self, os: &Os) -> bool {
         match self {
             Tool::FsRead(_) => false,
             Tool::FsWrite(_) => true,
-            Tool::ExecuteCommand(execute_command) => execute_command.requires_acceptance(),
+            Tool::ExecuteCommand(execute_command) => {
+                execute_command.requires_acceptance(&CommandPolicy::from_settings(&os.database.settings))
+            },
             Tool::UseAws(use_aws) => use_aws.requires_acceptance(),
             Tool::Custom(_) => true,
             Tool::GhIssue(_) => false,
+            Tool::Git(git) => git.requires_acceptance(),
             Tool::Knowledge(_) => false,
             Tool::Thinking(_) => false,
         }
@@ -97,6 +110,7 @@ impl Tool {
             Tool::UseAws(use_aws) => use_aws.invoke(os, stdout).await,
             Tool::Custom(custom_tool) => custom_tool.invoke(os, stdout).await,
             Tool::GhIssue(gh_issue) => gh_issue.invoke(os, stdout).await,
+            Tool::Git(git) => git.invoke(os, stdout).await,
             Tool::Knowledge(knowledge) => knowledge.invoke(os, stdout).await,
             Tool::Thinking(think) => think.invoke(stdout).await,
         }
@@ -111,6 +125,7 @@ impl Tool {
             Tool::UseAws(use_aws) => use_aws.queue_description(output),
             Tool::Custom(custom_tool) => custom_tool.queue_description(output),
             Tool::GhIssue(gh_issue) => gh_issue.queue_description(output),
+            Tool::Git(git) => git.queue_description(output),
             Tool::Knowledge(knowledge) => knowledge.queue_description(os, output).await,
             Tool::Thinking(thinking) => thinking.queue_description(output),
         }
@@ -125,6 +140,7 @@ impl Tool {
             Tool::UseAws(use_aws) => use_aws.validate(os).await,
             Tool::Custom(custom_tool) => custom_tool.validate(os).await,
             Tool::GhIssue(gh_issue) => gh_issue.validate(os).await,
+            Tool::Git(git) => git.validate(os).await,
             Tool::Knowledge(knowledge) => knowledge.validate(os).await,
             Tool::Thinking(think) => think.validate(os).await,
         }
@@ -146,6 +162,9 @@ pub struct ToolPermissions {
     pub permissions: HashMap<String, ToolPermission>,
     // Store pending trust-tool patterns for MCP tools that may be loaded later
     pub pending_trusted_tools: HashSet<String>,
+    /// Pattern and argument rules that apply for the rest of the session, in addition to those
+    /// configured in the global and profile context
+    pub rules: ToolPermissionRules,
 }
 
 impl ToolPermissions {
@@ -154,6 +173,7 @@ impl ToolPermissions {
             trust_all: false,
             permissions: HashMap::with_capacity(capacity),
             pending_trusted_tools: HashSet::new(),
+            rules: ToolPermissionRules::default(),
         }
     }
 
@@ -187,6 +207,7 @@ impl ToolPermissions {
     pub fn untrust_tool(&mut self, tool_name: &str) {
         self.trust_all = false;
         self.pending_trusted_tools.remove(tool_name);
+        self.rules.remove(tool_name);
         self.permissions
             .insert(tool_name.to_string(), ToolPermission { trusted: false });
     }
@@ -195,12 +216,14 @@ impl ToolPermissions {
         self.trust_all = false;
         self.permissions.clear();
         self.pending_trusted_tools.clear();
+        self.rules = ToolPermissionRules::default();
     }
 
     pub fn reset_tool(&mut self, tool_name: &str) {
         self.trust_all = false;
         self.permissions.remove(tool_name);
         self.pending_trusted_tools.remove(tool_name);
+        self.rules.remove(tool_name);
     }
 
     /// Add a pending trust pattern for tools that may be loaded later
@@ -236,6 +259,7 @@ impl ToolPermissions {
             "execute_cmd" => "trust read-only commands".dark_grey(),
             "use_aws" => "trust read-only commands".dark_grey(),
             "report_issue" => "trusted".dark_green().bold(),
+            "git" => "trust read-only commands".dark_grey(),
             "knowledge" => "trusted".dark_green().bold(),
             "thinking" => "trusted (prerelease)".dark_green().bold(),
             _ if self.trust_all => "trusted".dark_grey().bold(),
@@ -309,6 +333,8 @@ pub struct QueuedTool {
     pub name: String,
     pub accepted: bool,
     pub tool: Tool,
+    /// Arguments the tool was invoked with, as they are matched against permission rules
+    pub args: serde_json::Value,
 }
 
 /// The schema specification describing a tool's fields.
@@ -399,7 +425,7 @@ fn absolute_to_relative(cwd: impl AsRef<Path>, path: impl AsRef<Path>) -> Result
 }
 
 /// Small helper for formatting the path as a relative path, if able.
-fn format_path(cwd: impl AsRef<Path>, path: impl AsRef<Path>) -> String {
+pub(crate) fn format_path(cwd: impl AsRef<Path>, path: impl AsRef<Path>) -> String {
     absolute_to_relative(cwd, path.as_ref())
         .map(|p| p.to_string_lossy().to_string())
         // If we have three consecutive ".." then it should probably just stay as an absolute path.
@@ -414,7 +440,7 @@ fn format_path(cwd: impl AsRef<Path>, path: impl AsRef<Path>) -> String {
         .unwrap_or(path.as_ref().to_string_lossy().to_string())
 }
 
-fn supports_truecolor(os: &Os) -> bool {
+pub fn supports_truecolor(os: &Os) -> bool {
     // Simple override to disable truecolor since shell_color doesn't use Context.
     !os.env.get("Q_DISABLE_TRUECOLOR").is_ok_and(|s| !s.is_empty())
         && shell_color::get_color_support().contains(shell_color::ColorSupport::TERM24BIT)
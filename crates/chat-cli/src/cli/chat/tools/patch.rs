@@ -0,0 +1,275 @@
+//! Parsing and applying of unified diffs, used by the `apply_patch` command of `fs_write`.
+
+use eyre::{
+    ContextCompat as _,
+    Result,
+    bail,
+};
+
+/// The changes a patch makes to a single file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilePatch {
+    /// [None] if the file is created by the patch.
+    pub old_path: Option<String>,
+    /// [None] if the file is deleted by the patch.
+    pub new_path: Option<String>,
+    pub hunks: Vec<Hunk>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hunk {
+    /// 1-indexed line of the original file that the hunk starts at. For hunks that only insert
+    /// lines, this is the line after which they are inserted.
+    pub old_start: usize,
+    pub lines: Vec<HunkLine>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HunkLine {
+    Context(String),
+    Delete(String),
+    Insert(String),
+}
+
+impl Hunk {
+    /// The lines of the original file that the hunk replaces
+    pub fn old_lines(&self) -> impl Iterator<Item = &str> {
+        self.lines.iter().filter_map(|line| match line {
+            HunkLine::Context(line) | HunkLine::Delete(line) => Some(line.as_str()),
+            HunkLine::Insert(_) => None,
+        })
+    }
+
+    /// The lines that replace [Self::old_lines]
+    pub fn new_lines(&self) -> impl Iterator<Item = &str> {
+        self.lines.iter().filter_map(|line| match line {
+            HunkLine::Context(line) | HunkLine::Insert(line) => Some(line.as_str()),
+            HunkLine::Delete(_) => None,
+        })
+    }
+}
+
+/// Parses a unified diff, as produced by `diff -u` or `git diff`. Lines outside of the file
+/// headers and hunks, e.g. `diff --git` and `index` lines, are ignored.
+pub fn parse(patch: &str) -> Result<Vec<FilePatch>> {
+    let mut lines = patch.lines().peekable();
+    let mut files = Vec::new();
+    while let Some(line) = lines.next() {
+        let Some(old_path) = line.strip_prefix("--- ") else {
+            continue;
+        };
+        let Some(new_path) = lines.next().and_then(|line| line.strip_prefix("+++ ")) else {
+            bail!("Expected a '+++' line after '{line}'");
+        };
+        let mut file = FilePatch {
+            old_path: parse_path(old_path, "a/"),
+            new_path: parse_path(new_path, "b/"),
+            hunks: Vec::new(),
+        };
+        if file.old_path.is_none() && file.new_path.is_none() {
+            bail!("Invalid file header '{line}', both paths are /dev/null");
+        }
+
+        while let Some(header) = lines.peek().and_then(|line| line.strip_prefix("@@ ")) {
+            let (old_start, mut old_remaining, mut new_remaining) = parse_hunk_header(header)?;
+            let header = format!("@@ {header}");
+            lines.next();
+
+            let mut hunk = Hunk {
+                old_start,
+                lines: Vec::new(),
+            };
+            while old_remaining > 0 || new_remaining > 0 {
+                let line = lines
+                    .next()
+                    .with_context(|| format!("Unexpected end of the patch in hunk '{header}'"))?;
+                let (old, new) = match line.chars().next() {
+                    // Some tools strip the trailing whitespace of empty context lines
+                    Some(' ') | None => {
+                        hunk.lines
+                            .push(HunkLine::Context(line.get(1..).unwrap_or_default().to_string()));
+                        (1, 1)
+                    },
+                    Some('-') => {
+                        hunk.lines.push(HunkLine::Delete(line[1..].to_string()));
+                        (1, 0)
+                    },
+                    Some('+') => {
+                        hunk.lines.push(HunkLine::Insert(line[1..].to_string()));
+                        (0, 1)
+                    },
+                    // "\ No newline at end of file"
+                    Some('\\') => (0, 0),
+                    Some(_) => bail!("Invalid line '{line}' in hunk '{header}'"),
+                };
+                match (old_remaining.checked_sub(old), new_remaining.checked_sub(new)) {
+                    (Some(old), Some(new)) => (old_remaining, new_remaining) = (old, new),
+                    _ => bail!("Hunk '{header}' does not match its line counts"),
+                }
+            }
+            while lines.peek().is_some_and(|line| line.starts_with('\\')) {
+                lines.next();
+            }
+            file.hunks.push(hunk);
+        }
+
+        if file.hunks.is_empty() && file.old_path == file.new_path {
+            bail!("The patch for '{line}' does not contain any hunks");
+        }
+        files.push(file);
+    }
+
+    if files.is_empty() {
+        bail!("The patch does not contain any file changes");
+    }
+    Ok(files)
+}
+
+/// Parses the path of a `---` or `+++` line, removing the timestamp and the `a/` or `b/` prefix
+/// added by git.
+fn parse_path(path: &str, prefix: &str) -> Option<String> {
+    let path = path.split('\t').next().unwrap_or_default().trim();
+    match path {
+        "/dev/null" => None,
+        path => Some(path.strip_prefix(prefix).unwrap_or(path).to_string()),
+    }
+}
+
+/// Parses the ranges of a hunk header, e.g. `-1,3 +1,4 @@`, returning the start line of the
+/// original file and the line counts of the original and new file.
+fn parse_hunk_header(header: &str) -> Result<(usize, usize, usize)> {
+    fn parse_range(range: Option<&str>, sign: char) -> Option<(usize, usize)> {
+        let range = range?.strip_prefix(sign)?;
+        match range.split_once(',') {
+            Some((start, count)) => Some((start.parse().ok()?, count.parse().ok()?)),
+            None => Some((range.parse().ok()?, 1)),
+        }
+    }
+
+    let mut ranges = header.split_whitespace();
+    match (parse_range(ranges.next(), '-'), parse_range(ranges.next(), '+')) {
+        (Some((old_start, old_count)), Some((_, new_count))) => Ok((old_start, old_count, new_count)),
+        _ => bail!("Invalid hunk header '@@ {header}'"),
+    }
+}
+
+/// Applies `hunks` to `content`. Hunks are matched exactly, preferring the position closest to
+/// the one stated in their header, so that patches still apply after lines were added or removed
+/// above them.
+pub fn apply(content: &str, hunks: &[Hunk]) -> Result<String> {
+    let newline = if content.contains("\r\n") { "\r\n" } else { "\n" };
+    let mut lines: Vec<&str> = content.lines().collect();
+    // Difference between the positions in `lines` and the line numbers of the original file
+    let mut offset = 0_isize;
+    // Hunks may not overlap, so each one has to apply after the previous one
+    let mut min_position = 0;
+    for (i, hunk) in hunks.iter().enumerate() {
+        let old: Vec<&str> = hunk.old_lines().collect();
+        let new: Vec<&str> = hunk.new_lines().collect();
+        let stated = if old.is_empty() {
+            hunk.old_start
+        } else {
+            hunk.old_start.saturating_sub(1)
+        };
+        let expected = (stated as isize + offset).max(0) as usize;
+        let position = find_lines(&lines, &old, expected, min_position).with_context(|| {
+            format!(
+                "Hunk {} (line {}) does not apply, its original lines were not found",
+                i + 1,
+                hunk.old_start
+            )
+        })?;
+
+        lines.splice(position..position + old.len(), new.iter().copied());
+        offset = position as isize - stated as isize + new.len() as isize - old.len() as isize;
+        min_position = position + new.len();
+    }
+
+    let mut patched = lines.join(newline);
+    if !patched.is_empty() && (content.is_empty() || content.ends_with('\n')) {
+        patched.push_str(newline);
+    }
+    Ok(patched)
+}
+
+/// Returns the position of `needle` in `lines` closest to `expected`, not before `min_position`.
+fn find_lines(lines: &[&str], needle: &[&str], expected: usize, min_position: usize) -> Option<usize> {
+    let max_position = lines.len().checked_sub(needle.len())?;
+    if needle.is_empty() {
+        return Some(expected.clamp(min_position.min(max_position), max_position));
+    }
+    (min_position..=max_position)
+        .filter(|&position| lines[position..position + needle.len()] == *needle)
+        .min_by_key(|position| position.abs_diff(expected))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PATCH: &str = "\
+diff --git a/src/lib.rs b/src/lib.rs
+index 1111111..2222222 100644
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1,3 +1,3 @@
+ fn one() {}
+-fn two() {}
++fn dos() {}
+ fn three() {}
+@@ -6,2 +6,3 @@ fn four() {}
+ fn six() {}
++fn six_and_a_half() {}
+ fn seven() {}
+--- /dev/null
++++ b/new.txt
+@@ -0,0 +1,2 @@
++hello
++world
+--- a/old.txt
++++ /dev/null
+@@ -1 +0,0 @@
+-goodbye
+";
+
+    #[test]
+    fn test_parse() {
+        let files = parse(PATCH).unwrap();
+        assert_eq!(files.len(), 3);
+        assert_eq!(files[0].old_path.as_deref(), Some("src/lib.rs"));
+        assert_eq!(files[0].new_path.as_deref(), Some("src/lib.rs"));
+        assert_eq!(files[0].hunks.len(), 2);
+        assert_eq!(files[0].hunks[1].old_start, 6);
+        assert_eq!(files[0].hunks[1].lines.len(), 3);
+        assert_eq!(files[1].old_path, None);
+        assert_eq!(files[1].hunks[0].new_lines().collect::<Vec<_>>(), vec![
+            "hello", "world"
+        ]);
+        assert_eq!(files[2].new_path, None);
+
+        assert!(parse("not a patch").is_err());
+        assert!(parse("--- a/x\n+++ b/x\n@@ -1,2 +1,2 @@\n-a\n+b\n").is_err());
+        assert!(parse("--- a/x\n+++ b/x\n@@ -1 +1 @@\n*a\n+b\n").is_err());
+    }
+
+    #[test]
+    fn test_apply() {
+        let files = parse(PATCH).unwrap();
+        let content =
+            "fn one() {}\nfn two() {}\nfn three() {}\nfn four() {}\nfn five() {}\nfn six() {}\nfn seven() {}\n";
+        assert_eq!(
+            apply(content, &files[0].hunks).unwrap(),
+            "fn one() {}\nfn dos() {}\nfn three() {}\nfn four() {}\nfn five() {}\nfn six() {}\nfn six_and_a_half() {}\nfn seven() {}\n"
+        );
+        assert_eq!(apply("", &files[1].hunks).unwrap(), "hello\nworld\n");
+        assert_eq!(apply("goodbye\n", &files[2].hunks).unwrap(), "");
+
+        // Lines were added above the hunks since the patch was created
+        let shifted = format!("fn zero() {{}}\n{content}");
+        assert!(apply(&shifted, &files[0].hunks).unwrap().contains("fn dos() {}\n"));
+
+        // The original lines are missing
+        let changed = content.replace("fn two() {}", "fn deux() {}");
+        assert!(apply(&changed, &files[0].hunks).is_err());
+    }
+}
@@ -0,0 +1,471 @@
+//! The configuration of a repository, checked in at [WORKSPACE_CONFIG_PATH]. Besides the context
+//! rules, hooks, tool permission rules and sandbox of a [ContextConfig], it can set the default
+//! model and the MCP servers of the chats in the repository.
+//!
+//! Configurations apply in increasing order of precedence: global, workspace, profile, and then
+//! the command line arguments. The context rules, hooks and tool permission rules of all the
+//! configurations are used together, with deny rules of any configuration taking precedence over
+//! allow rules. The default model and MCP servers of the same name are taken from the configuration
+//! with the highest precedence that sets them. The sandbox is taken from the profile or global
+//! configuration, and the one of the workspace can only make it stricter, so that a checked-in
+//! configuration cannot weaken the sandbox of the user.
+
+use std::collections::{
+    BTreeMap,
+    HashMap,
+};
+use std::path::PathBuf;
+
+use eyre::{
+    Result,
+    bail,
+    eyre,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use super::cli::hooks::Hook;
+use super::cli::model::{
+    MODEL_OPTIONS,
+    default_model_id,
+};
+use super::context::{
+    ContextConfig,
+    ContextManager,
+    profile_context_path,
+};
+use super::tool_manager::{
+    global_mcp_config_path,
+    mcp_server_config_layers,
+    profile_mcp_path,
+    workspace_mcp_config_path,
+};
+use super::tools::custom_tool::CustomToolConfig;
+use super::tools::execute::sandbox::SandboxConfig;
+use crate::database::settings::Setting;
+use crate::os::Os;
+use crate::util::directories;
+
+/// Path of the workspace configuration, relative to the current directory.
+pub const WORKSPACE_CONFIG_PATH: &str = ".amazonq/config.json";
+
+/// A configuration, in increasing order of precedence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConfigScope {
+    Global,
+    Workspace,
+    Profile,
+}
+
+/// Configuration as it is written in [WORKSPACE_CONFIG_PATH].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WorkspaceConfig {
+    /// Context rules, hooks, tool permission rules and sandbox, as in the global and profile
+    /// configurations.
+    #[serde(flatten)]
+    pub context: ContextConfig,
+    /// Name of the model used unless `--model` is given, see [MODEL_OPTIONS].
+    #[serde(alias = "defaultModel", skip_serializing_if = "Option::is_none")]
+    pub default_model: Option<String>,
+    /// MCP servers, which take precedence over those of `.amazonq/mcp.json`.
+    #[serde(
+        rename = "mcpServers",
+        alias = "mcp_servers",
+        skip_serializing_if = "HashMap::is_empty"
+    )]
+    pub mcp_servers: HashMap<String, CustomToolConfig>,
+}
+
+pub fn workspace_config_path(os: &Os) -> Result<PathBuf> {
+    Ok(os.env.current_dir()?.join(WORKSPACE_CONFIG_PATH))
+}
+
+/// Loads the workspace configuration of the current directory, which is empty if there is none.
+pub async fn load_workspace_config(os: &Os) -> Result<WorkspaceConfig> {
+    let path = workspace_config_path(os)?;
+    if !os.fs.exists(&path) {
+        return Ok(WorkspaceConfig::default());
+    }
+    let contents = os.fs.read_to_string(&path).await?;
+    serde_json::from_str(&contents).map_err(|e| eyre!("Failed to parse {}: {}", WORKSPACE_CONFIG_PATH, e))
+}
+
+/// Where the model of a chat is configured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModelSource {
+    /// The `--model` argument
+    CommandLine,
+    /// The `default_model` of the workspace configuration
+    Workspace,
+    /// The `chat.defaultModel` setting
+    Setting,
+    /// Neither, the default model of the region
+    Default,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ResolvedModel {
+    pub model_id: String,
+    pub source: ModelSource,
+}
+
+/// Resolves the model of a chat from the `--model` argument, the default model of the workspace,
+/// the `chat.defaultModel` setting and lastly the default model of the region. Errors if the
+/// argument or the workspace name a model that does not exist.
+pub fn resolve_model(os: &Os, model_arg: Option<&str>, workspace: &WorkspaceConfig) -> Result<ResolvedModel> {
+    let find = |name: &str| {
+        let name = name.to_lowercase();
+        MODEL_OPTIONS
+            .iter()
+            .find(|opt| opt.name == name)
+            .map(|opt| opt.model_id.to_string())
+    };
+    let available_names = || MODEL_OPTIONS.iter().map(|opt| opt.name).collect::<Vec<_>>().join(", ");
+
+    let (model_id, source) = if let Some(model_name) = model_arg {
+        match find(model_name) {
+            Some(model_id) => (model_id, ModelSource::CommandLine),
+            None => bail!(
+                "Model '{}' does not exist. Available models: {}",
+                model_name,
+                available_names()
+            ),
+        }
+    } else if let Some(model_name) = &workspace.default_model {
+        match find(model_name) {
+            Some(model_id) => (model_id, ModelSource::Workspace),
+            None => bail!(
+                "Model '{}' of {} does not exist. Available models: {}",
+                model_name,
+                WORKSPACE_CONFIG_PATH,
+                available_names()
+            ),
+        }
+    } else if let Some(model_id) = os
+        .database
+        .settings
+        .get_string(Setting::ChatDefaultModel)
+        .and_then(|model_name| find(&model_name))
+    {
+        (model_id, ModelSource::Setting)
+    } else {
+        (default_model_id(os).to_string(), ModelSource::Default)
+    };
+
+    Ok(ResolvedModel { model_id, source })
+}
+
+/// A value along with the configuration it comes from.
+#[derive(Debug, Clone, Serialize)]
+pub struct Scoped<T> {
+    pub scope: ConfigScope,
+    #[serde(flatten)]
+    pub value: T,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigFile {
+    pub path: PathBuf,
+    pub exists: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ResolvedContextRule {
+    pub rule: String,
+    pub priority: i32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ResolvedHook {
+    pub name: String,
+    #[serde(flatten)]
+    pub hook: Hook,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ResolvedPermissionRules {
+    pub allow: Vec<Scoped<ResolvedPermissionRule>>,
+    pub deny: Vec<Scoped<ResolvedPermissionRule>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ResolvedPermissionRule {
+    pub rule: String,
+}
+
+/// The configuration that applies to a chat in the current directory, with the configuration
+/// each part comes from, as shown by `q chat --print-config`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ResolvedConfig {
+    pub profile: String,
+    /// The configuration files, in increasing order of precedence.
+    pub files: Vec<Scoped<ConfigFile>>,
+    pub model: ResolvedModel,
+    pub paths: Vec<Scoped<ResolvedContextRule>>,
+    pub hooks: Vec<Scoped<ResolvedHook>>,
+    pub tool_permissions: ResolvedPermissionRules,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sandbox: Option<Scoped<SandboxConfig>>,
+    #[serde(rename = "mcpServers")]
+    pub mcp_servers: BTreeMap<String, Scoped<CustomToolConfig>>,
+}
+
+impl ResolvedConfig {
+    /// Resolves the configuration of a chat started with the `--profile` and `--model` arguments
+    /// in the current directory. Unlike when starting a chat, a workspace configuration that
+    /// fails to parse is an error.
+    pub async fn resolve(os: &Os, profile: Option<&str>, model_arg: Option<&str>) -> Result<Self> {
+        let workspace = load_workspace_config(os).await?;
+        let mut context_manager = ContextManager::new(os, None).await?;
+        if let Some(profile) = profile {
+            context_manager.switch_profile(os, profile).await?;
+        }
+        let profile = context_manager.current_profile.clone();
+
+        let file = |scope, path: PathBuf| Scoped {
+            scope,
+            value: ConfigFile {
+                exists: os.fs.exists(&path),
+                path,
+            },
+        };
+        let files = vec![
+            file(ConfigScope::Global, directories::chat_global_context_path(os)?),
+            file(ConfigScope::Global, global_mcp_config_path(os)?),
+            file(ConfigScope::Workspace, workspace_mcp_config_path(os)?),
+            file(ConfigScope::Workspace, workspace_config_path(os)?),
+            file(ConfigScope::Profile, profile_context_path(os, &profile)?),
+            file(ConfigScope::Profile, profile_mcp_path(os, &profile)?),
+        ];
+
+        let mut paths = Vec::new();
+        let mut hooks = Vec::new();
+        let mut tool_permissions = ResolvedPermissionRules::default();
+        for (scope, config) in context_manager.configs() {
+            paths.extend(config.paths.iter().map(|rule| Scoped {
+                scope,
+                value: ResolvedContextRule {
+                    rule: rule.clone(),
+                    priority: config.priorities.get(rule).copied().unwrap_or_default(),
+                },
+            }));
+
+            let mut scope_hooks = config.hooks.iter().collect::<Vec<_>>();
+            scope_hooks.sort_by(|(a, _), (b, _)| a.cmp(b));
+            hooks.extend(scope_hooks.into_iter().map(|(name, hook)| Scoped {
+                scope,
+                value: ResolvedHook {
+                    name: name.clone(),
+                    hook: hook.clone(),
+                },
+            }));
+
+            for (rules, resolved) in [
+                (&config.tool_permissions.allow, &mut tool_permissions.allow),
+                (&config.tool_permissions.deny, &mut tool_permissions.deny),
+            ] {
+                resolved.extend(rules.iter().map(|rule| Scoped {
+                    scope,
+                    value: ResolvedPermissionRule { rule: rule.clone() },
+                }));
+            }
+        }
+        let sandbox = context_manager
+            .sandbox_config()
+            .map(|(scope, value)| Scoped { scope, value });
+
+        let mut mcp_servers = BTreeMap::new();
+        for (scope, config) in mcp_server_config_layers(os, Some(&profile)).await? {
+            for (name, server) in config.mcp_servers {
+                mcp_servers.insert(name, Scoped { scope, value: server });
+            }
+        }
+
+        Ok(Self {
+            model: resolve_model(os, model_arg, &workspace)?,
+            profile,
+            files,
+            paths,
+            hooks,
+            tool_permissions,
+            sandbox,
+            mcp_servers,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WORKSPACE_CONFIG: &str = r#"{
+        "paths": ["docs/**/*.md"],
+        "priorities": { "docs/**/*.md": 2 },
+        "hooks": {
+            "status": { "trigger": "per_prompt", "type": "inline", "command": "git status" }
+        },
+        "tool_permissions": { "allow": ["fs_read"], "deny": ["execute_bash(command=rm *)"] },
+        "sandbox": { "enabled": true },
+        "defaultModel": "claude-3.7-sonnet",
+        "mcpServers": {
+            "git": { "command": "git-mcp" },
+            "docs": { "url": "https://example.com/mcp" }
+        }
+    }"#;
+
+    async fn write_workspace_config(os: &Os, contents: &str) {
+        let path = workspace_config_path(os).unwrap();
+        os.fs.create_dir_all(path.parent().unwrap()).await.unwrap();
+        os.fs.write(&path, contents).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_load_workspace_config() {
+        let os = Os::new().await.unwrap();
+        let config = load_workspace_config(&os).await.unwrap();
+        assert!(config.context.paths.is_empty());
+        assert!(config.default_model.is_none());
+
+        write_workspace_config(&os, WORKSPACE_CONFIG).await;
+        let config = load_workspace_config(&os).await.unwrap();
+        assert_eq!(config.context.paths, vec!["docs/**/*.md"]);
+        assert_eq!(config.context.priorities.get("docs/**/*.md"), Some(&2));
+        assert!(config.context.hooks.contains_key("status"));
+        assert_eq!(config.context.tool_permissions.allow, vec!["fs_read"]);
+        assert!(config.context.sandbox.is_some());
+        assert_eq!(config.default_model.as_deref(), Some("claude-3.7-sonnet"));
+        assert_eq!(config.mcp_servers.len(), 2);
+
+        write_workspace_config(&os, r#"{ "paths": "README.md" }"#).await;
+        assert!(load_workspace_config(&os).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_resolve_model() {
+        let mut os = Os::new().await.unwrap();
+        let mut workspace = WorkspaceConfig::default();
+        assert_eq!(
+            resolve_model(&os, None, &workspace).unwrap().source,
+            ModelSource::Default
+        );
+
+        os.database
+            .settings
+            .set(Setting::ChatDefaultModel, "claude-3.5-sonnet")
+            .await
+            .unwrap();
+        assert_eq!(resolve_model(&os, None, &workspace).unwrap(), ResolvedModel {
+            model_id: "CLAUDE_3_5_SONNET_20241022_V2_0".to_string(),
+            source: ModelSource::Setting,
+        });
+
+        workspace.default_model = Some("claude-3.7-sonnet".to_string());
+        assert_eq!(resolve_model(&os, None, &workspace).unwrap(), ResolvedModel {
+            model_id: "CLAUDE_3_7_SONNET_20250219_V1_0".to_string(),
+            source: ModelSource::Workspace,
+        });
+        assert_eq!(
+            resolve_model(&os, Some("claude-4-sonnet"), &workspace).unwrap(),
+            ResolvedModel {
+                model_id: "CLAUDE_SONNET_4_20250514_V1_0".to_string(),
+                source: ModelSource::CommandLine,
+            }
+        );
+        assert!(resolve_model(&os, Some("gpt"), &workspace).is_err());
+
+        workspace.default_model = Some("gpt".to_string());
+        assert!(resolve_model(&os, None, &workspace).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_resolve_config_precedence() {
+        let os = Os::new().await.unwrap();
+        write_workspace_config(&os, WORKSPACE_CONFIG).await;
+        os.fs
+            .write(
+                workspace_mcp_config_path(&os).unwrap(),
+                r#"{ "mcpServers": { "git": { "command": "old-git-mcp" }, "fetch": { "command": "fetch-mcp" } } }"#,
+            )
+            .await
+            .unwrap();
+
+        let profile_path = profile_context_path(&os, "default").unwrap();
+        os.fs.create_dir_all(profile_path.parent().unwrap()).await.unwrap();
+        os.fs
+            .write(
+                &profile_path,
+                r#"{ "paths": ["notes.md"], "sandbox": { "enabled": false } }"#,
+            )
+            .await
+            .unwrap();
+        os.fs
+            .write(
+                profile_mcp_path(&os, "default").unwrap(),
+                r#"{ "mcpServers": { "docs": { "command": "local-docs-mcp" } } }"#,
+            )
+            .await
+            .unwrap();
+
+        let config = ResolvedConfig::resolve(&os, None, None).await.unwrap();
+        assert_eq!(config.profile, "default");
+        assert_eq!(config.model.source, ModelSource::Workspace);
+
+        // Rules of all the configurations are used, in order of precedence
+        let scopes = config.paths.iter().map(|path| path.scope).collect::<Vec<_>>();
+        assert!(scopes.is_sorted());
+        let workspace_rule = config
+            .paths
+            .iter()
+            .find(|path| path.scope == ConfigScope::Workspace)
+            .unwrap();
+        assert_eq!(workspace_rule.value.rule, "docs/**/*.md");
+        assert_eq!(workspace_rule.value.priority, 2);
+        assert_eq!(config.paths.last().unwrap().value.rule, "notes.md");
+        assert_eq!(config.hooks.len(), 1);
+        assert_eq!(config.hooks[0].scope, ConfigScope::Workspace);
+        assert_eq!(config.tool_permissions.deny[0].value.rule, "execute_bash(command=rm *)");
+
+        // The workspace can enable a sandbox the profile disables
+        let sandbox = config.sandbox.as_ref().unwrap();
+        assert_eq!(sandbox.scope, ConfigScope::Workspace);
+        assert!(sandbox.value.enabled);
+        // Profile settings take precedence over workspace ones
+        assert_eq!(config.mcp_servers["docs"].scope, ConfigScope::Profile);
+        assert_eq!(config.mcp_servers["docs"].value.command, "local-docs-mcp");
+        // The workspace configuration takes precedence over .amazonq/mcp.json
+        assert_eq!(config.mcp_servers["git"].value.command, "git-mcp");
+        assert_eq!(config.mcp_servers["fetch"].scope, ConfigScope::Workspace);
+
+        let json = serde_json::to_value(&config).unwrap();
+        assert_eq!(json["mcpServers"]["git"]["scope"], "workspace");
+        assert_eq!(json["model"]["source"], "workspace");
+    }
+
+    #[tokio::test]
+    async fn test_workspace_cannot_weaken_sandbox() {
+        let os = Os::new().await.unwrap();
+        let global_path = directories::chat_global_context_path(&os).unwrap();
+        os.fs.create_dir_all(global_path.parent().unwrap()).await.unwrap();
+        os.fs
+            .write(&global_path, r#"{ "sandbox": { "enabled": true } }"#)
+            .await
+            .unwrap();
+        write_workspace_config(
+            &os,
+            r#"{ "sandbox": { "enabled": false, "allowNetwork": true, "writablePaths": ["/"] } }"#,
+        )
+        .await;
+
+        let config = ResolvedConfig::resolve(&os, None, None).await.unwrap();
+        let sandbox = config.sandbox.unwrap();
+        assert_eq!(sandbox.scope, ConfigScope::Global);
+        assert!(sandbox.value.enabled);
+        assert!(!sandbox.value.allow_network);
+        assert!(sandbox.value.writable_paths.is_empty());
+    }
+}
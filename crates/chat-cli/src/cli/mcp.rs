@@ -87,11 +87,17 @@ pub struct AddArgs {
     #[arg(long)]
     pub name: String,
     /// The command used to launch the server
-    #[arg(long)]
-    pub command: String,
+    #[arg(long, required_unless_present = "url", conflicts_with = "url")]
+    pub command: Option<String>,
     /// Arguments to pass to the command
     #[arg(long, action = ArgAction::Append, allow_hyphen_values = true, value_delimiter = ',')]
     pub args: Vec<String>,
+    /// Url of a remote server that communicates over http
+    #[arg(long)]
+    pub url: Option<String>,
+    /// Headers to send with every request to a remote server, formatted as 'name: value'
+    #[arg(long = "header", value_parser = parse_header, requires = "url")]
+    pub headers: Vec<(String, String)>,
     /// Where to add the server to.
     #[arg(long, value_enum)]
     pub scope: Option<Scope>,
@@ -129,10 +135,13 @@ impl AddArgs {
         }
 
         let merged_env = self.env.into_iter().flatten().collect::<HashMap<_, _>>();
+        let headers = (!self.headers.is_empty()).then(|| self.headers.into_iter().collect::<HashMap<_, _>>());
         let tool: CustomToolConfig = serde_json::from_value(serde_json::json!({
-            "command": self.command,
+            "command": self.command.unwrap_or_default(),
             "args": self.args,
             "env": merged_env,
+            "url": self.url,
+            "headers": headers,
             "timeout": self.timeout.unwrap_or(default_timeout()),
             "disabled": self.disabled,
         }))?;
@@ -235,7 +244,8 @@ impl ListArgs {
                 Some(cfg) if !cfg.mcp_servers.is_empty() => {
                     for (name, tool_cfg) in &cfg.mcp_servers {
                         let status = if tool_cfg.disabled { " (disabled)" } else { "" };
-                        writeln!(output, "    • {name:<12} {}{}", tool_cfg.command, status)?;
+                        let location = tool_cfg.url.as_deref().unwrap_or(&tool_cfg.command);
+                        writeln!(output, "    • {name:<12} {}{}", location, status)?;
                     }
                 },
                 _ => {
@@ -336,7 +346,10 @@ impl StatusArgs {
                     style::Print("\n─────────────\n"),
                     style::Print(format!("Scope   : {}\n", scope_display(&sc))),
                     style::Print(format!("File    : {}\n", path.display())),
-                    style::Print(format!("Command : {}\n", cfg.command)),
+                    style::Print(match &cfg.url {
+                        Some(url) => format!("Url     : {}\n", url),
+                        None => format!("Command : {}\n", cfg.command),
+                    }),
                     style::Print(format!("Timeout : {} ms\n", cfg.timeout)),
                     style::Print(format!("Disabled: {}\n", cfg.disabled)),
                     style::Print(format!(
@@ -377,7 +390,7 @@ impl UseProfileServersOnlyArgs {
         let profile_dir = profile_mcp_path
             .parent()
             .ok_or_else(|| eyre::eyre!("Invalid profile path"))?;
-        
+
         if !os.fs.exists(profile_dir) {
             bail!("Profile '{}' does not exist", self.profile);
         }
@@ -422,7 +435,7 @@ async fn get_mcp_server_configs(
     profile: Option<String>,
 ) -> Result<Vec<(Scope, PathBuf, Option<McpServerConfig>)>> {
     let mut results = Vec::new();
-    
+
     // If a specific scope is requested, only load that scope
     if let Some(requested_scope) = scope {
         let path = resolve_scope_profile(os, Some(requested_scope), profile)?;
@@ -433,12 +446,12 @@ async fn get_mcp_server_configs(
 
     // Multi-scope loading in priority order: Profile → Workspace → Global
     let mut scopes_to_load = Vec::new();
-    
+
     // Add Profile scope if profile name is provided
     if let Some(ref profile_name) = profile {
         scopes_to_load.push((Scope::Profile, Some(profile_name.clone())));
     }
-    
+
     // Always add Workspace and Global scopes
     scopes_to_load.push((Scope::Workspace, None));
     scopes_to_load.push((Scope::Global, None));
@@ -447,7 +460,7 @@ async fn get_mcp_server_configs(
     for (scope_type, profile_name) in scopes_to_load {
         let path = resolve_scope_profile(os, Some(scope_type), profile_name)?;
         let cfg_opt = load_config_with_error_handling(os, &path).await;
-        
+
         // Check for profile exclusivity
         if let (Scope::Profile, Some(cfg)) = (&scope_type, &cfg_opt) {
             if cfg.use_profile_servers_only {
@@ -456,18 +469,15 @@ async fn get_mcp_server_configs(
                 return Ok(results);
             }
         }
-        
+
         results.push((scope_type, path, cfg_opt));
     }
-    
+
     Ok(results)
 }
 
 /// Helper function to load configuration with consistent error handling
-async fn load_config_with_error_handling(
-    os: &Os,
-    path: &PathBuf,
-) -> Option<McpServerConfig> {
+async fn load_config_with_error_handling(os: &Os, path: &PathBuf) -> Option<McpServerConfig> {
     if os.fs.exists(path) {
         match McpServerConfig::load_from_file(os, path).await {
             Ok(cfg) => Some(cfg),
@@ -493,7 +503,8 @@ fn resolve_scope_profile(os: &Os, scope: Option<Scope>, profile: Option<String>)
     Ok(match scope {
         Some(Scope::Global) => global_mcp_config_path(os)?,
         Some(Scope::Profile) => {
-            let profile_name = profile.ok_or_else(|| eyre::eyre!("Profile name is required when using profile scope"))?;
+            let profile_name =
+                profile.ok_or_else(|| eyre::eyre!("Profile name is required when using profile scope"))?;
             profile_mcp_path(os, &profile_name)?
         },
         _ => workspace_mcp_config_path(os)?,
@@ -506,14 +517,8 @@ fn queue_profile_exclusive_warning(output: &mut impl Write, profile_name: &str)
         "⚠️  Profile '{}' is configured for exclusive server usage.",
         profile_name
     )?;
-    writeln!(
-        output, 
-        "   Only MCP servers defined in this profile will be loaded."
-    )?;
-    writeln!(
-        output,
-        "   Global and workspace servers will be ignored."
-    )?;
+    writeln!(output, "   Only MCP servers defined in this profile will be loaded.")?;
+    writeln!(output, "   Global and workspace servers will be ignored.")?;
     Ok(())
 }
 
@@ -558,6 +563,13 @@ fn parse_env_vars(arg: &str) -> Result<HashMap<String, String>> {
     Ok(vars)
 }
 
+fn parse_header(arg: &str) -> Result<(String, String)> {
+    match arg.split_once(':') {
+        Some((name, value)) if !name.trim().is_empty() => Ok((name.trim().to_string(), value.trim().to_string())),
+        _ => bail!("Failed to parse header '{}'. Expected 'name: value'", arg),
+    }
+}
+
 async fn load_cfg(os: &Os, p: &PathBuf) -> Result<McpServerConfig> {
     Ok(if os.fs.exists(p) {
         McpServerConfig::load_from_file(os, p).await?
@@ -613,12 +625,14 @@ mod tests {
         // 1. add
         AddArgs {
             name: "local".into(),
-            command: "echo hi".into(),
+            command: Some("echo hi".into()),
             args: vec![
                 "awslabs.eks-mcp-server".to_string(),
                 "--allow-write".to_string(),
                 "--allow-sensitive-data-access".to_string(),
             ],
+            url: None,
+            headers: vec![],
             env: vec![],
             timeout: None,
             scope: None,
@@ -666,12 +680,14 @@ mod tests {
             ],
             RootSubcommand::Mcp(McpSubcommand::Add(AddArgs {
                 name: "test_server".to_string(),
-                command: "test_command".to_string(),
+                command: Some("test_command".to_string()),
                 args: vec![
                     "awslabs.eks-mcp-server".to_string(),
                     "--allow-write".to_string(),
                     "--allow-sensitive-data-access".to_string(),
                 ],
+                url: None,
+                headers: vec![],
                 scope: None,
                 profile: None,
                 env: vec![
@@ -689,6 +705,35 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_mcp_subcommand_add_remote() {
+        assert_parse!(
+            [
+                "mcp",
+                "add",
+                "--name",
+                "remote",
+                "--url",
+                "https://example.com/mcp",
+                "--header",
+                "Authorization: Bearer abc=="
+            ],
+            RootSubcommand::Mcp(McpSubcommand::Add(AddArgs {
+                name: "remote".to_string(),
+                command: None,
+                args: vec![],
+                url: Some("https://example.com/mcp".to_string()),
+                headers: vec![("Authorization".to_string(), "Bearer abc==".to_string())],
+                scope: None,
+                profile: None,
+                env: vec![],
+                timeout: None,
+                disabled: false,
+                force: false,
+            }))
+        );
+    }
+
     #[test]
     fn test_mcp_subcomman_remove_workspace() {
         assert_parse!(
@@ -718,7 +763,7 @@ mod tests {
     fn test_mcp_subcommand_status_simple() {
         assert_parse!(
             ["mcp", "status", "--name", "aws"],
-            RootSubcommand::Mcp(McpSubcommand::Status(StatusArgs { 
+            RootSubcommand::Mcp(McpSubcommand::Status(StatusArgs {
                 name: "aws".into(),
                 profile: None,
             }))
@@ -318,6 +318,7 @@ impl Cli {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::cli::chat::output::ChatOutputFormat;
     use crate::util::CHAT_BINARY_NAME;
     use crate::util::test::assert_parse;
 
@@ -350,12 +351,17 @@ mod test {
         assert_eq!(Cli::parse_from([CHAT_BINARY_NAME, "chat", "-vv"]), Cli {
             subcommand: Some(RootSubcommand::Chat(ChatArgs {
                 resume: false,
+                resume_id: None,
+                list_sessions: false,
                 input: None,
                 profile: None,
                 model: None,
                 trust_all_tools: false,
                 trust_tools: None,
-                non_interactive: false
+                non_interactive: false,
+                output_format: ChatOutputFormat::Text,
+                server: false,
+                print_config: false
             })),
             verbose: 2,
             help_all: false,
@@ -389,12 +395,17 @@ mod test {
             ["chat", "--profile", "my-profile"],
             RootSubcommand::Chat(ChatArgs {
                 resume: false,
+                resume_id: None,
+                list_sessions: false,
                 input: None,
                 profile: Some("my-profile".to_string()),
                 model: None,
                 trust_all_tools: false,
                 trust_tools: None,
-                non_interactive: false
+                non_interactive: false,
+                output_format: ChatOutputFormat::Text,
+                server: false,
+                print_config: false
             })
         );
     }
@@ -405,12 +416,17 @@ mod test {
             ["chat", "--profile", "my-profile", "Hello"],
             RootSubcommand::Chat(ChatArgs {
                 resume: false,
+                resume_id: None,
+                list_sessions: false,
                 input: Some("Hello".to_string()),
                 profile: Some("my-profile".to_string()),
                 model: None,
                 trust_all_tools: false,
                 trust_tools: None,
-                non_interactive: false
+                non_interactive: false,
+                output_format: ChatOutputFormat::Text,
+                server: false,
+                print_config: false
             })
         );
     }
@@ -421,12 +437,17 @@ mod test {
             ["chat", "--profile", "my-profile", "--trust-all-tools"],
             RootSubcommand::Chat(ChatArgs {
                 resume: false,
+                resume_id: None,
+                list_sessions: false,
                 input: None,
                 profile: Some("my-profile".to_string()),
                 model: None,
                 trust_all_tools: true,
                 trust_tools: None,
-                non_interactive: false
+                non_interactive: false,
+                output_format: ChatOutputFormat::Text,
+                server: false,
+                print_config: false
             })
         );
     }
@@ -437,24 +458,152 @@ mod test {
             ["chat", "--no-interactive", "--resume"],
             RootSubcommand::Chat(ChatArgs {
                 resume: true,
+                resume_id: None,
+                list_sessions: false,
                 input: None,
                 profile: None,
                 model: None,
                 trust_all_tools: false,
                 trust_tools: None,
-                non_interactive: true
+                non_interactive: true,
+                output_format: ChatOutputFormat::Text,
+                server: false,
+                print_config: false
             })
         );
         assert_parse!(
             ["chat", "--non-interactive", "-r"],
             RootSubcommand::Chat(ChatArgs {
                 resume: true,
+                resume_id: None,
+                list_sessions: false,
                 input: None,
                 profile: None,
                 model: None,
                 trust_all_tools: false,
                 trust_tools: None,
-                non_interactive: true
+                non_interactive: true,
+                output_format: ChatOutputFormat::Text,
+                server: false,
+                print_config: false
+            })
+        );
+    }
+
+    #[test]
+    fn test_chat_with_output_format() {
+        assert_parse!(
+            ["chat", "--no-interactive", "--output-format", "stream-json", "Hello"],
+            RootSubcommand::Chat(ChatArgs {
+                resume: false,
+                resume_id: None,
+                list_sessions: false,
+                input: Some("Hello".to_string()),
+                profile: None,
+                model: None,
+                trust_all_tools: false,
+                trust_tools: None,
+                non_interactive: true,
+                output_format: ChatOutputFormat::StreamJson,
+                server: false,
+                print_config: false
+            })
+        );
+    }
+
+    #[test]
+    fn test_chat_server() {
+        assert_parse!(
+            ["chat", "--server", "--trust-tools=fs_read"],
+            RootSubcommand::Chat(ChatArgs {
+                resume: false,
+                resume_id: None,
+                list_sessions: false,
+                input: None,
+                profile: None,
+                model: None,
+                trust_all_tools: false,
+                trust_tools: Some(vec!["fs_read".to_string()]),
+                non_interactive: false,
+                output_format: ChatOutputFormat::Text,
+                server: true,
+                print_config: false
+            })
+        );
+    }
+
+    #[test]
+    fn test_chat_print_config() {
+        assert_parse!(
+            ["chat", "--print-config", "--profile", "rust"],
+            RootSubcommand::Chat(ChatArgs {
+                resume: false,
+                resume_id: None,
+                list_sessions: false,
+                input: None,
+                profile: Some("rust".to_string()),
+                model: None,
+                trust_all_tools: false,
+                trust_tools: None,
+                non_interactive: false,
+                output_format: ChatOutputFormat::Text,
+                server: false,
+                print_config: true
+            })
+        );
+    }
+
+    #[test]
+    fn test_chat_with_resume_session() {
+        assert_parse!(
+            ["chat", "--resume-id", "3f2a1b7c"],
+            RootSubcommand::Chat(ChatArgs {
+                resume: false,
+                resume_id: Some("3f2a1b7c".to_string()),
+                list_sessions: false,
+                input: None,
+                profile: None,
+                model: None,
+                trust_all_tools: false,
+                trust_tools: None,
+                non_interactive: false,
+                output_format: ChatOutputFormat::Text,
+                server: false,
+                print_config: false
+            })
+        );
+        assert_parse!(
+            ["chat", "--resume", "Hello"],
+            RootSubcommand::Chat(ChatArgs {
+                resume: true,
+                resume_id: None,
+                list_sessions: false,
+                input: Some("Hello".to_string()),
+                profile: None,
+                model: None,
+                trust_all_tools: false,
+                trust_tools: None,
+                non_interactive: false,
+                output_format: ChatOutputFormat::Text,
+                server: false,
+                print_config: false
+            })
+        );
+        assert_parse!(
+            ["chat", "--list-sessions"],
+            RootSubcommand::Chat(ChatArgs {
+                resume: false,
+                resume_id: None,
+                list_sessions: true,
+                input: None,
+                profile: None,
+                model: None,
+                trust_all_tools: false,
+                trust_tools: None,
+                non_interactive: false,
+                output_format: ChatOutputFormat::Text,
+                server: false,
+                print_config: false
             })
         );
     }
@@ -465,12 +614,17 @@ mod test {
             ["chat", "--trust-all-tools"],
             RootSubcommand::Chat(ChatArgs {
                 resume: false,
+                resume_id: None,
+                list_sessions: false,
                 input: None,
                 profile: None,
                 model: None,
                 trust_all_tools: true,
                 trust_tools: None,
-                non_interactive: false
+                non_interactive: false,
+                output_format: ChatOutputFormat::Text,
+                server: false,
+                print_config: false
             })
         );
     }
@@ -481,12 +635,17 @@ mod test {
             ["chat", "--trust-tools="],
             RootSubcommand::Chat(ChatArgs {
                 resume: false,
+                resume_id: None,
+                list_sessions: false,
                 input: None,
                 profile: None,
                 model: None,
                 trust_all_tools: false,
                 trust_tools: Some(vec!["".to_string()]),
-                non_interactive: false
+                non_interactive: false,
+                output_format: ChatOutputFormat::Text,
+                server: false,
+                print_config: false
             })
         );
     }
@@ -497,12 +656,39 @@ mod test {
             ["chat", "--trust-tools=fs_read,fs_write"],
             RootSubcommand::Chat(ChatArgs {
                 resume: false,
+                resume_id: None,
+                list_sessions: false,
+                input: None,
+                profile: None,
+                model: None,
+                trust_all_tools: false,
+                trust_tools: Some(vec!["fs_read,fs_write".to_string()]),
+                non_interactive: false,
+                output_format: ChatOutputFormat::Text,
+                server: false,
+                print_config: false
+            })
+        );
+    }
+
+    #[test]
+    fn test_chat_with_tool_trust_rules() {
+        let rules = "use_aws(service_name=iam,operation_name=get*),fs_write(path={src,docs}/**),fs_read";
+        assert_parse!(
+            ["chat", &format!("--trust-tools={rules}")],
+            RootSubcommand::Chat(ChatArgs {
+                resume: false,
+                resume_id: None,
+                list_sessions: false,
                 input: None,
                 profile: None,
                 model: None,
                 trust_all_tools: false,
-                trust_tools: Some(vec!["fs_read".to_string(), "fs_write".to_string()]),
-                non_interactive: false
+                trust_tools: Some(vec![rules.to_string()]),
+                non_interactive: false,
+                output_format: ChatOutputFormat::Text,
+                server: false,
+                print_config: false
             })
         );
     }
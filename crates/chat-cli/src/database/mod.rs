@@ -27,6 +27,7 @@ use serde_json::{
 };
 use settings::Settings;
 use thiserror::Error;
+use time::OffsetDateTime;
 use tracing::{
     error,
     info,
@@ -69,7 +70,8 @@ const MIGRATIONS: &[Migration] = migrations![
     "004_state_table",
     "005_auth_table",
     "006_make_state_blob",
-    "007_conversations_table"
+    "007_conversations_table",
+    "008_sessions_table"
 ];
 
 #[derive(Debug, serde::Deserialize, serde::Serialize)]
@@ -95,6 +97,19 @@ impl From<amzn_codewhisperer_client::types::Profile> for AuthProfile {
     }
 }
 
+/// A chat session stored in the sessions table, without its conversation state.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SessionSummary {
+    /// The conversation id of the session.
+    pub id: String,
+    pub title: String,
+    /// The directory the session was last used in.
+    pub cwd: Option<String>,
+    pub model: Option<String>,
+    pub created_at: OffsetDateTime,
+    pub updated_at: OffsetDateTime,
+}
+
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, serde::Serialize, serde::Deserialize)]
 #[serde(transparent)]
 pub struct Secret(pub String);
@@ -157,6 +172,8 @@ pub enum Table {
     State,
     /// The conversations tables contains user chat conversations.
     Conversations,
+    /// The sessions table contains user chat conversations by conversation id.
+    Sessions,
     /// The auth table contains SSO and Builder ID credentials.
     Auth,
 }
@@ -166,6 +183,7 @@ impl std::fmt::Display for Table {
         match self {
             Table::State => write!(f, "state"),
             Table::Conversations => write!(f, "conversations"),
+            Table::Sessions => write!(f, "sessions"),
             Table::Auth => write!(f, "auth_kv"),
         }
     }
@@ -315,7 +333,8 @@ impl Database {
     //     self.delete_entry(Table::State, LAST_USED_MODEL_ID)
     // }
 
-    /// Get a chat conversation given a path to the conversation.
+    /// Get the chat conversation of the session last updated in the directory at `path`, or else
+    /// the conversation saved for the directory before there were sessions.
     pub fn get_conversation_by_path(
         &mut self,
         path: impl AsRef<Path>,
@@ -326,22 +345,107 @@ impl Database {
             None => return Ok(None),
         };
 
-        self.get_json_entry(Table::Conversations, path)
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT value FROM {} WHERE cwd = ?1 ORDER BY updated_at DESC, created_at DESC LIMIT 1",
+            Table::Sessions
+        ))?;
+        match stmt.query_row([path], |row| row.get::<_, String>(0)) {
+            Ok(value) => Ok(Some(serde_json::from_str(&value)?)),
+            Err(Error::QueryReturnedNoRows) => self.get_json_entry(Table::Conversations, path),
+            Err(err) => Err(err.into()),
+        }
     }
 
-    /// Set a chat conversation given a path to the conversation.
-    pub fn set_conversation_by_path(
+    /// Save a chat conversation as a session. The title is only set when the session is created,
+    /// see [Self::rename_session].
+    pub fn set_session(
         &mut self,
-        path: impl AsRef<Path>,
+        cwd: Option<&Path>,
+        title: &str,
         state: &ConversationState,
     ) -> Result<usize, DatabaseError> {
-        // We would need to encode this to support non utf8 paths.
-        let path = match path.as_ref().to_str() {
-            Some(path) => path,
-            None => return Ok(0),
-        };
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+        Ok(self.pool.get()?.execute(
+            &format!(
+                "INSERT INTO {} (id, title, cwd, model, created_at, updated_at, value)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?5, ?6)
+                ON CONFLICT(id) DO UPDATE SET
+                    cwd = excluded.cwd,
+                    model = excluded.model,
+                    updated_at = excluded.updated_at,
+                    value = excluded.value",
+                Table::Sessions
+            ),
+            params![
+                state.conversation_id(),
+                title,
+                cwd.and_then(|cwd| cwd.to_str()),
+                state.model,
+                now,
+                serde_json::to_string(state)?
+            ],
+        )?)
+    }
+
+    /// Get the chat conversation of a session.
+    pub fn get_session(&self, id: &str) -> Result<Option<ConversationState>, DatabaseError> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(&format!("SELECT value FROM {} WHERE id = ?1", Table::Sessions))?;
+        match stmt.query_row([id], |row| row.get::<_, String>(0)) {
+            Ok(value) => Ok(Some(serde_json::from_str(&value)?)),
+            Err(Error::QueryReturnedNoRows) => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
 
-        self.set_json_entry(Table::Conversations, path, state)
+    /// List all sessions, most recently updated first.
+    pub fn list_sessions(&self) -> Result<Vec<SessionSummary>, DatabaseError> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT id, title, cwd, model, created_at, updated_at FROM {} ORDER BY updated_at DESC, created_at DESC",
+            Table::Sessions
+        ))?;
+        let timestamp = |secs: i64| OffsetDateTime::from_unix_timestamp(secs).unwrap_or(OffsetDateTime::UNIX_EPOCH);
+        let rows = stmt.query_map([], |row| {
+            Ok(SessionSummary {
+                id: row.get(0)?,
+                title: row.get(1)?,
+                cwd: row.get(2)?,
+                model: row.get(3)?,
+                created_at: timestamp(row.get(4)?),
+                updated_at: timestamp(row.get(5)?),
+            })
+        })?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+
+    /// Set the title of a session. Returns whether the session exists.
+    pub fn rename_session(&mut self, id: &str, title: &str) -> Result<bool, DatabaseError> {
+        let updated = self.pool.get()?.execute(
+            &format!("UPDATE {} SET title = ?2 WHERE id = ?1", Table::Sessions),
+            params![id, title],
+        )?;
+        Ok(updated > 0)
+    }
+
+    /// Delete a session. Returns whether the session existed.
+    pub fn delete_session(&mut self, id: &str) -> Result<bool, DatabaseError> {
+        let deleted = self
+            .pool
+            .get()?
+            .execute(&format!("DELETE FROM {} WHERE id = ?1", Table::Sessions), [id])?;
+        Ok(deleted > 0)
+    }
+
+    /// Delete all sessions last updated before `updated_before`, returning how many were deleted.
+    pub fn prune_sessions(&mut self, updated_before: OffsetDateTime) -> Result<usize, DatabaseError> {
+        Ok(self
+            .pool
+            .get()?
+            .execute(&format!("DELETE FROM {} WHERE updated_at < ?1", Table::Sessions), [
+                updated_before.unix_timestamp(),
+            ])?)
     }
 
     pub async fn get_secret(&self, key: &str) -> Result<Option<Secret>, DatabaseError> {
@@ -576,6 +680,46 @@ mod tests {
         assert!(db.get_entry::<bool>(Table::State, "bool").unwrap().is_some());
     }
 
+    #[tokio::test]
+    async fn sessions_table_tests() {
+        let mut os = crate::os::Os::new().await.unwrap();
+        let conversation = ConversationState::new(
+            &mut os,
+            "3f2a1b7c-session",
+            Default::default(),
+            None,
+            Default::default(),
+            Some("model".to_string()),
+        )
+        .await;
+        let db = &mut os.database;
+
+        db.set_session(Some(Path::new("/repo")), "First title", &conversation)
+            .unwrap();
+        // The title is kept when the session is updated
+        db.set_session(None, "Second title", &conversation).unwrap();
+        let sessions = db.list_sessions().unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].id, "3f2a1b7c-session");
+        assert_eq!(sessions[0].title, "First title");
+        assert_eq!(sessions[0].cwd, None);
+        assert_eq!(sessions[0].model.as_deref(), Some("model"));
+        assert!(db.get_session("3f2a1b7c-session").unwrap().is_some());
+        assert!(db.get_session("missing").unwrap().is_none());
+        assert!(db.get_conversation_by_path("/repo").unwrap().is_none());
+        db.set_session(Some(Path::new("/repo")), "First title", &conversation)
+            .unwrap();
+        assert!(db.get_conversation_by_path("/repo").unwrap().is_some());
+
+        assert!(db.rename_session("3f2a1b7c-session", "Renamed").unwrap());
+        assert_eq!(db.list_sessions().unwrap()[0].title, "Renamed");
+
+        assert_eq!(db.prune_sessions(OffsetDateTime::UNIX_EPOCH).unwrap(), 0);
+        assert!(db.delete_session("3f2a1b7c-session").unwrap());
+        assert!(!db.delete_session("3f2a1b7c-session").unwrap());
+        assert!(db.list_sessions().unwrap().is_empty());
+    }
+
     #[tokio::test]
     #[ignore = "not on ci"]
     async fn test_set_password() {
@@ -22,6 +22,7 @@ pub enum Setting {
     ShareCodeWhispererContent,
     EnabledThinking,
     EnabledKnowledge,
+    EnabledKnowledgeWatch,
     SkimCommandKey,
     ChatGreetingEnabled,
     ApiTimeout,
@@ -33,6 +34,11 @@ pub enum Setting {
     McpNoInteractiveTimeout,
     McpLoadedBefore,
     ChatDefaultModel,
+    ChatCommandPolicy,
+    ChatSessionRetentionDays,
+    ChatTokenizer,
+    ChatCompactionStrategy,
+    ChatAutoCompactThreshold,
 }
 
 impl AsRef<str> for Setting {
@@ -43,6 +49,7 @@ impl AsRef<str> for Setting {
             Self::ShareCodeWhispererContent => "codeWhisperer.shareCodeWhispererContentWithAWS",
             Self::EnabledThinking => "chat.enableThinking",
             Self::EnabledKnowledge => "chat.enableKnowledge",
+            Self::EnabledKnowledgeWatch => "chat.enableKnowledgeWatch",
             Self::SkimCommandKey => "chat.skimCommandKey",
             Self::ChatGreetingEnabled => "chat.greeting.enabled",
             Self::ApiTimeout => "api.timeout",
@@ -54,6 +61,11 @@ impl AsRef<str> for Setting {
             Self::McpNoInteractiveTimeout => "mcp.noInteractiveTimeout",
             Self::McpLoadedBefore => "mcp.loadedBefore",
             Self::ChatDefaultModel => "chat.defaultModel",
+            Self::ChatCommandPolicy => "chat.commandPolicy",
+            Self::ChatSessionRetentionDays => "chat.sessionRetentionDays",
+            Self::ChatTokenizer => "chat.tokenizer",
+            Self::ChatCompactionStrategy => "chat.compactionStrategy",
+            Self::ChatAutoCompactThreshold => "chat.autoCompactThreshold",
         }
     }
 }
@@ -74,6 +86,7 @@ impl TryFrom<&str> for Setting {
             "codeWhisperer.shareCodeWhispererContentWithAWS" => Ok(Self::ShareCodeWhispererContent),
             "chat.enableThinking" => Ok(Self::EnabledThinking),
             "chat.enableKnowledge" => Ok(Self::EnabledKnowledge),
+            "chat.enableKnowledgeWatch" => Ok(Self::EnabledKnowledgeWatch),
             "chat.skimCommandKey" => Ok(Self::SkimCommandKey),
             "chat.greeting.enabled" => Ok(Self::ChatGreetingEnabled),
             "api.timeout" => Ok(Self::ApiTimeout),
@@ -85,6 +98,11 @@ impl TryFrom<&str> for Setting {
             "mcp.noInteractiveTimeout" => Ok(Self::McpNoInteractiveTimeout),
             "mcp.loadedBefore" => Ok(Self::McpLoadedBefore),
             "chat.defaultModel" => Ok(Self::ChatDefaultModel),
+            "chat.commandPolicy" => Ok(Self::ChatCommandPolicy),
+            "chat.sessionRetentionDays" => Ok(Self::ChatSessionRetentionDays),
+            "chat.tokenizer" => Ok(Self::ChatTokenizer),
+            "chat.compactionStrategy" => Ok(Self::ChatCompactionStrategy),
+            "chat.autoCompactThreshold" => Ok(Self::ChatAutoCompactThreshold),
             _ => Err(DatabaseError::InvalidSetting(value.to_string())),
         }
     }
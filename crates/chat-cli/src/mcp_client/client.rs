@@ -25,6 +25,7 @@ use super::transport::base_protocol::{
     JsonRpcRequest,
     JsonRpcVersion,
 };
+use super::transport::http::JsonRpcHttpTransport;
 use super::transport::stdio::JsonRpcStdioTransport;
 use super::transport::{
     self,
@@ -37,11 +38,14 @@ use super::{
     LogListener,
     Messenger,
     PaginationSupportedOps,
+    ProgressNotification,
     PromptGet,
     PromptsListResult,
     ResourceTemplatesListResult,
     ResourcesListResult,
     ServerCapabilities,
+    ServerRequestError,
+    ServerRequestResponder,
     ToolsListResult,
 };
 use crate::util::process::{
@@ -51,6 +55,7 @@ use crate::util::process::{
 
 pub type ClientInfo = serde_json::Value;
 pub type StdioTransport = JsonRpcStdioTransport;
+pub type HttpTransport = JsonRpcHttpTransport;
 
 /// Represents the capabilities of a client in the Model Context Protocol.
 /// This structure is sent to the server during initialization to communicate
@@ -84,6 +89,15 @@ pub struct ClientConfig {
     pub env: Option<HashMap<String, String>>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct HttpClientConfig {
+    pub server_name: String,
+    pub url: String,
+    pub headers: Option<HashMap<String, String>>,
+    pub timeout: u64,
+    pub client_info: serde_json::Value,
+}
+
 #[allow(dead_code)]
 #[derive(Debug, Error)]
 pub enum ClientError {
@@ -128,6 +142,9 @@ pub struct Client<T: Transport> {
     client_info: serde_json::Value,
     current_id: Arc<AtomicU64>,
     pub messenger: Option<Box<dyn Messenger>>,
+    /// Answers requests initiated by the server. Servers that send requests to a client without
+    /// one are told that the method is not found.
+    pub server_request_responder: Option<Box<dyn ServerRequestResponder>>,
     // TODO: move this to tool manager that way all the assets are treated equally
     pub prompt_gets: Arc<SyncRwLock<HashMap<String, PromptGet>>>,
     pub is_prompts_out_of_date: Arc<AtomicBool>,
@@ -145,6 +162,7 @@ impl<T: Transport> Clone for Client<T> {
             client_info: self.client_info.clone(),
             current_id: self.current_id.clone(),
             messenger: None,
+            server_request_responder: None,
             prompt_gets: self.prompt_gets.clone(),
             is_prompts_out_of_date: self.is_prompts_out_of_date.clone(),
         }
@@ -207,6 +225,7 @@ impl Client<StdioTransport> {
             client_info,
             current_id: Arc::new(AtomicU64::new(0)),
             messenger: None,
+            server_request_responder: None,
             prompt_gets: Arc::new(SyncRwLock::new(HashMap::new())),
             is_prompts_out_of_date: Arc::new(AtomicBool::new(false)),
         })
@@ -266,6 +285,32 @@ impl Client<StdioTransport> {
     }
 }
 
+impl Client<HttpTransport> {
+    pub fn from_config(config: HttpClientConfig) -> Result<Self, ClientError> {
+        let HttpClientConfig {
+            server_name,
+            url,
+            headers,
+            timeout,
+            client_info,
+        } = config;
+        let transport = Arc::new(transport::http::JsonRpcHttpTransport::client(&url, headers)?);
+        Ok(Self {
+            server_name,
+            transport,
+            timeout,
+            // There is no process to manage for remote servers
+            server_process_id: None,
+            client_info,
+            current_id: Arc::new(AtomicU64::new(0)),
+            messenger: None,
+            server_request_responder: None,
+            prompt_gets: Arc::new(SyncRwLock::new(HashMap::new())),
+            is_prompts_out_of_date: Arc::new(AtomicBool::new(false)),
+        })
+    }
+}
+
 impl<T> Drop for Client<T>
 where
     T: Transport,
@@ -313,7 +358,10 @@ where
         });
 
         let init_params = Some({
-            let client_cap = ClientCapabilities::from(self.client_info.clone());
+            let mut client_cap = ClientCapabilities::from(self.client_info.clone());
+            if let Some(handler) = &self.server_request_responder {
+                client_cap.capabilities = handler.capabilities();
+            }
             serde_json::json!(client_cap)
         });
         let init_resp = self.request("initialize", init_params).await?;
@@ -357,21 +405,42 @@ where
                 fetch_tools_and_notify_with_messenger(&client_ref, messenger_ref.as_ref()).await;
             });
         }
+        if cap.resources.is_some() {
+            let client_ref = (*self).clone();
+            let messenger_ref = self.messenger.as_ref().map(|m| m.duplicate());
+            tokio::spawn(async move {
+                fetch_resources_and_notify_with_messenger(&client_ref, messenger_ref.as_ref()).await;
+            });
+        }
 
         let transport_ref = self.transport.clone();
         let server_name = self.server_name.clone();
         let messenger_ref = self.messenger.as_ref().map(|m| m.duplicate());
+        let handler_ref = self.server_request_responder.as_ref().map(|h| h.duplicate());
         let client_ref = (*self).clone();
 
         let prompts_list_changed_supported = cap.prompts.as_ref().is_some_and(|p| p.get("listChanged").is_some());
         let tools_list_changed_supported = cap.tools.as_ref().is_some_and(|t| t.get("listChanged").is_some());
+        let resources_list_changed_supported = cap.resources.as_ref().is_some_and(|r| r.get("listChanged").is_some());
         tokio::spawn(async move {
             let mut listener = transport_ref.get_listener();
             loop {
                 match listener.recv().await {
                     Ok(msg) => {
                         match msg {
-                            JsonRpcMessage::Request(_req) => {},
+                            JsonRpcMessage::Request(req) => {
+                                // Requests are answered in their own task since answering them
+                                // could involve waiting on the user
+                                let handler = handler_ref.as_ref().map(|h| h.duplicate());
+                                let transport_ref = transport_ref.clone();
+                                let server_name = server_name.clone();
+                                tokio::spawn(async move {
+                                    let resp = respond_to_server_request(&server_name, handler, req).await;
+                                    if let Err(e) = transport_ref.send(&JsonRpcMessage::Response(resp)).await {
+                                        tracing::error!("Failed to respond to request from {server_name}: {:?}", e);
+                                    }
+                                });
+                            },
                             JsonRpcMessage::Notification(notif) => {
                                 let JsonRpcNotification { method, params, .. } = notif;
                                 match method.as_str() {
@@ -421,6 +490,23 @@ where
                                         fetch_tools_and_notify_with_messenger(&client_ref, messenger_ref.as_ref())
                                             .await;
                                     },
+                                    "notifications/resources/list_changed" | "resources/list_changed"
+                                        if resources_list_changed_supported =>
+                                    {
+                                        fetch_resources_and_notify_with_messenger(&client_ref, messenger_ref.as_ref())
+                                            .await;
+                                    },
+                                    "notifications/resources/updated" | "resources/updated" => {
+                                        let uri = params.as_ref().and_then(|p| p.get("uri")).and_then(|v| v.as_str());
+                                        if let (Some(uri), Some(messenger)) = (uri, messenger_ref.as_ref()) {
+                                            if let Err(e) = messenger.send_resource_updated(uri.to_string()).await {
+                                                tracing::error!(
+                                                    "Failed to send resource update through messenger {:?}",
+                                                    e
+                                                );
+                                            }
+                                        }
+                                    },
                                     _ => {},
                                 }
                             },
@@ -466,10 +552,12 @@ where
         };
         tracing::trace!(target: "mcp", "To {}:\n{:#?}", self.server_name, request);
         let msg = JsonRpcMessage::Request(request);
+        // The listener needs to be obtained before the request is sent. Transports such as http
+        // can relay the response before send returns, in which case it would otherwise be missed.
+        let mut listener = self.transport.get_listener();
         time::timeout(Duration::from_millis(self.timeout), self.transport.send(&msg))
             .await
             .map_err(send_map_err)??;
-        let mut listener = self.transport.get_listener();
         let mut resp = time::timeout(Duration::from_millis(self.timeout), async {
             // we want to ignore all other messages sent by the server at this point and let the
             // background loop handle them
@@ -487,7 +575,12 @@ where
         .await
         .map_err(recv_map_err)??;
         // Pagination support: https://spec.modelcontextprotocol.io/specification/2024-11-05/server/utilities/pagination/#pagination-model
-        let mut next_cursor = resp.result.as_ref().and_then(|v| v.get("nextCursor"));
+        // A null cursor also marks the last page
+        let mut next_cursor = resp
+            .result
+            .as_ref()
+            .and_then(|v| v.get("nextCursor"))
+            .filter(|cursor| !cursor.is_null());
         if next_cursor.is_some() {
             let mut current_resp = resp.clone();
             let mut results = Vec::<serde_json::Value>::new();
@@ -554,7 +647,11 @@ where
                     .await
                     .map_err(recv_map_err)??;
                     current_resp = resp;
-                    next_cursor = current_resp.result.as_ref().and_then(|v| v.get("nextCursor"));
+                    next_cursor = current_resp
+                        .result
+                        .as_ref()
+                        .and_then(|v| v.get("nextCursor"))
+                        .filter(|cursor| !cursor.is_null());
                 }
                 resp.result = Some({
                     let mut map = serde_json::Map::new();
@@ -567,6 +664,83 @@ where
         Ok(resp)
     }
 
+    /// Sends a request to the server associated with a progress token, relaying the progress the
+    /// server reports for it to `progress_sender`.
+    ///
+    /// Unlike [Self::request], the timeout is measured from the last progress reported rather
+    /// than from when the request is sent, so long running operations are not cut short for as
+    /// long as the server keeps reporting progress. Should the returned future be dropped (or
+    /// time out) before a response is received, the server is notified that the request has been
+    /// cancelled.
+    pub async fn request_with_progress(
+        &self,
+        method: &str,
+        params: Option<serde_json::Value>,
+        progress_sender: tokio::sync::mpsc::Sender<ProgressNotification>,
+    ) -> Result<JsonRpcResponse, ClientError> {
+        let send_map_err = |e: Elapsed| (e, method.to_string());
+        let recv_map_err = |e: Elapsed| (e, format!("recv for {method}"));
+        let id = self.get_id();
+        // The request id is unique among the requests in flight so it doubles as the token
+        let progress_token = serde_json::Value::from(id);
+        let mut params = params.unwrap_or_else(|| serde_json::json!({}));
+        if let Some(params) = params.as_object_mut() {
+            let meta = params.entry("_meta").or_insert_with(|| serde_json::json!({}));
+            if let Some(meta) = meta.as_object_mut() {
+                meta.insert("progressToken".to_string(), progress_token.clone());
+            }
+        }
+        let request = JsonRpcRequest {
+            jsonrpc: JsonRpcVersion::default(),
+            id,
+            method: method.to_owned(),
+            params: Some(params),
+        };
+        tracing::trace!(target: "mcp", "To {}:\n{:#?}", self.server_name, request);
+        let msg = JsonRpcMessage::Request(request);
+        let mut listener = self.transport.get_listener();
+        time::timeout(Duration::from_millis(self.timeout), self.transport.send(&msg))
+            .await
+            .map_err(send_map_err)??;
+        let mut cancellation_guard = CancellationGuard {
+            server_name: self.server_name.clone(),
+            transport: Some(self.transport.clone()),
+            id,
+        };
+        let mut deadline = time::Instant::now() + Duration::from_millis(self.timeout);
+        let resp = loop {
+            match time::timeout_at(deadline, listener.recv())
+                .await
+                .map_err(recv_map_err)?
+            {
+                Ok(JsonRpcMessage::Response(resp)) if resp.id == id => break resp,
+                Ok(JsonRpcMessage::Notification(JsonRpcNotification { method, params, .. }))
+                    if method == "notifications/progress" =>
+                {
+                    let Some(progress) = params.and_then(|p| serde_json::from_value::<ProgressNotification>(p).ok())
+                    else {
+                        continue;
+                    };
+                    if progress.progress_token != progress_token {
+                        continue;
+                    }
+                    deadline = time::Instant::now() + Duration::from_millis(self.timeout);
+                    // Progress is only informational, so it is dropped rather than waited on
+                    // should the consumer fall behind
+                    let _ = progress_sender.try_send(progress);
+                },
+                Err(e @ TransportError::RecvError(tokio::sync::broadcast::error::RecvError::Closed)) => {
+                    cancellation_guard.disarm();
+                    return Err(e.into());
+                },
+                _ => {},
+            }
+        };
+        cancellation_guard.disarm();
+        tracing::trace!(target: "mcp", "From {}:\n{:#?}", self.server_name, resp);
+        Ok(resp)
+    }
+
     /// Sends a notification to the server associated.
     /// Notifications are requests that expect no responses.
     pub async fn notify(&self, method: &str, params: Option<serde_json::Value>) -> Result<(), ClientError> {
@@ -589,6 +763,45 @@ where
     }
 }
 
+/// Notifies the server that the request with the given id has been cancelled when dropped, unless
+/// it has been disarmed.
+struct CancellationGuard<T: Transport> {
+    server_name: String,
+    transport: Option<Arc<T>>,
+    id: u64,
+}
+
+impl<T: Transport> CancellationGuard<T> {
+    fn disarm(&mut self) {
+        self.transport.take();
+    }
+}
+
+impl<T: Transport> Drop for CancellationGuard<T> {
+    fn drop(&mut self) {
+        let Some(transport) = self.transport.take() else {
+            return;
+        };
+        let Ok(handle) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        let msg = JsonRpcMessage::Notification(JsonRpcNotification {
+            jsonrpc: JsonRpcVersion::default(),
+            method: "notifications/cancelled".to_owned(),
+            params: Some(serde_json::json!({
+                "requestId": self.id,
+                "reason": "The client is no longer waiting for the result",
+            })),
+        });
+        let server_name = self.server_name.clone();
+        handle.spawn(async move {
+            if let Err(e) = transport.send(&msg).await {
+                tracing::error!("Failed to notify {server_name} of cancellation: {:?}", e);
+            }
+        });
+    }
+}
+
 fn examine_server_capabilities(ser_cap: &JsonRpcResponse) -> Result<(), ClientError> {
     // Check the jrpc version.
     // Currently we are only proceeding if the versions are EXACTLY the same.
@@ -604,6 +817,34 @@ fn examine_server_capabilities(ser_cap: &JsonRpcResponse) -> Result<(), ClientEr
     Ok(())
 }
 
+async fn respond_to_server_request(
+    server_name: &str,
+    handler: Option<Box<dyn ServerRequestResponder>>,
+    req: JsonRpcRequest,
+) -> JsonRpcResponse {
+    let JsonRpcRequest { id, method, params, .. } = req;
+    tracing::trace!(target: "mcp", "Request from {}: {} {:?}", server_name, method, params);
+    let result = match (method.as_str(), handler) {
+        ("ping", _) => Ok(serde_json::json!({})),
+        (_, Some(handler)) => handler.handle_request(server_name, &method, params).await,
+        (_, None) => Err(ServerRequestError::MethodNotFound(method)),
+    };
+    match result {
+        Ok(result) => JsonRpcResponse {
+            jsonrpc: JsonRpcVersion::default(),
+            id,
+            result: Some(result),
+            error: None,
+        },
+        Err(e) => JsonRpcResponse {
+            jsonrpc: JsonRpcVersion::default(),
+            id,
+            result: None,
+            error: Some(e.into()),
+        },
+    }
+}
+
 // TODO: after we move prompts to tool manager, use the messenger to notify the listener spawned by
 // tool manager to update its own field. Currently this function does not make use of the
 // messesnger.
@@ -682,6 +923,76 @@ where
     }
 }
 
+#[allow(clippy::borrowed_box)]
+async fn fetch_resources_and_notify_with_messenger<T>(client: &Client<T>, messenger: Option<&Box<dyn Messenger>>)
+where
+    T: Transport,
+{
+    let resources_list_result = 'resources_list_result: {
+        let resp = match client.request("resources/list", None).await {
+            Ok(resp) => resp,
+            Err(e) => break 'resources_list_result Err(e.into()),
+        };
+        if let Some(error) = resp.error {
+            let msg = format!(
+                "Failed to retrieve resource list for {}: {:?}",
+                client.server_name, error
+            );
+            break 'resources_list_result Err(eyre::eyre!(msg));
+        }
+        let Some(result) = resp.result else {
+            let msg = format!("Resource list response from {} is missing result", client.server_name);
+            break 'resources_list_result Err(eyre::eyre!(msg));
+        };
+        serde_json::from_value::<ResourcesListResult>(result).map_err(|e| {
+            eyre::eyre!(
+                "Failed to deserialize resource list from {}: {:?}",
+                client.server_name,
+                e
+            )
+        })
+    };
+    // Templates are optional for servers that offer resources, in which case the request is
+    // met with an error that is relayed all the same.
+    let resource_templates_list_result = 'resource_templates_list_result: {
+        let resp = match client.request("resources/templates/list", None).await {
+            Ok(resp) => resp,
+            Err(e) => break 'resource_templates_list_result Err(e.into()),
+        };
+        if let Some(error) = resp.error {
+            let msg = format!(
+                "Failed to retrieve resource template list for {}: {:?}",
+                client.server_name, error
+            );
+            break 'resource_templates_list_result Err(eyre::eyre!(msg));
+        }
+        let Some(result) = resp.result else {
+            let msg = format!(
+                "Resource template list response from {} is missing result",
+                client.server_name
+            );
+            break 'resource_templates_list_result Err(eyre::eyre!(msg));
+        };
+        serde_json::from_value::<ResourceTemplatesListResult>(result).map_err(|e| {
+            eyre::eyre!(
+                "Failed to deserialize resource template list from {}: {:?}",
+                client.server_name,
+                e
+            )
+        })
+    };
+    if let Some(messenger) = messenger {
+        let _ = messenger
+            .send_resources_list_result(resources_list_result)
+            .await
+            .map_err(|e| tracing::error!("Failed to send resource result through messenger {:?}", e));
+        let _ = messenger
+            .send_resource_templates_list_result(resource_templates_list_result)
+            .await
+            .map_err(|e| tracing::error!("Failed to send resource template result through messenger {:?}", e));
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::path::PathBuf;
@@ -689,6 +1000,7 @@ mod tests {
     use serde_json::Value;
 
     use super::*;
+    use crate::mcp_client::error::ErrorCode;
     const TEST_BIN_OUT_DIR: &str = "target/debug";
     const TEST_SERVER_NAME: &str = "test_mcp_server";
 
@@ -920,6 +1232,237 @@ mod tests {
         Ok(())
     }
 
+    #[derive(Debug, Clone)]
+    struct EchoRequestResponder;
+
+    #[async_trait::async_trait]
+    impl ServerRequestResponder for EchoRequestResponder {
+        fn capabilities(&self) -> HashMap<String, serde_json::Value> {
+            HashMap::from([("roots".to_owned(), serde_json::json!({}))])
+        }
+
+        async fn handle_request(
+            &self,
+            server_name: &str,
+            method: &str,
+            _params: Option<serde_json::Value>,
+        ) -> Result<serde_json::Value, ServerRequestError> {
+            match method {
+                "roots/list" => Ok(serde_json::json!({ "server": server_name })),
+                _ => Err(ServerRequestError::Rejected("nope".to_owned())),
+            }
+        }
+
+        fn duplicate(&self) -> Box<dyn ServerRequestResponder> {
+            Box::new(self.clone())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_respond_to_server_request() {
+        let request = |id: u64, method: &str| JsonRpcRequest {
+            jsonrpc: JsonRpcVersion::default(),
+            id,
+            method: method.to_owned(),
+            params: None,
+        };
+
+        let resp = respond_to_server_request("test", None, request(1, "ping")).await;
+        assert_eq!(resp.id, 1);
+        assert_eq!(resp.result, Some(serde_json::json!({})));
+
+        let resp = respond_to_server_request("test", None, request(2, "roots/list")).await;
+        assert_eq!(resp.error.map(|e| e.code), Some(i32::from(ErrorCode::MethodNotFound)));
+
+        let handler = Some(EchoRequestResponder.duplicate());
+        let resp = respond_to_server_request("test", handler, request(3, "roots/list")).await;
+        assert_eq!(resp.result, Some(serde_json::json!({ "server": "test" })));
+
+        let handler = Some(EchoRequestResponder.duplicate());
+        let resp = respond_to_server_request("test", handler, request(4, "sampling/createMessage")).await;
+        assert_eq!(resp.error.map(|e| e.code), Some(-1));
+    }
+
+    /// A transport standing in for a server that reports progress twice before responding to
+    /// `slow/op`, lists resources over two pages, and never responds to anything else
+    #[derive(Debug)]
+    struct ScriptedTransport {
+        tx: tokio::sync::broadcast::Sender<JsonRpcMessage>,
+        notifications: Arc<std::sync::Mutex<Vec<JsonRpcNotification>>>,
+    }
+
+    struct ScriptedListener(tokio::sync::broadcast::Receiver<JsonRpcMessage>);
+
+    #[async_trait::async_trait]
+    impl transport::Listener for ScriptedListener {
+        async fn recv(&mut self) -> Result<JsonRpcMessage, TransportError> {
+            Ok(self.0.recv().await?)
+        }
+    }
+
+    struct ScriptedLogListener;
+
+    #[async_trait::async_trait]
+    impl LogListener for ScriptedLogListener {
+        async fn recv(&mut self) -> Result<String, TransportError> {
+            std::future::pending().await
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl Transport for ScriptedTransport {
+        async fn send(&self, msg: &JsonRpcMessage) -> Result<(), TransportError> {
+            match msg {
+                JsonRpcMessage::Request(req) if req.method == "slow/op" => {
+                    let token = req.params.as_ref().unwrap()["_meta"]["progressToken"].clone();
+                    let progress = |progress: u64, token: Value| {
+                        JsonRpcMessage::Notification(JsonRpcNotification {
+                            jsonrpc: JsonRpcVersion::default(),
+                            method: "notifications/progress".to_owned(),
+                            params: Some(serde_json::json!({
+                                "progressToken": token,
+                                "progress": progress,
+                                "total": 2,
+                            })),
+                        })
+                    };
+                    let tx = self.tx.clone();
+                    let id = req.id;
+                    tokio::spawn(async move {
+                        // Progress for some other request should be ignored
+                        let _ = tx.send(progress(1, serde_json::json!("other")));
+                        let _ = tx.send(progress(1, token.clone()));
+                        let _ = tx.send(progress(2, token));
+                        let _ = tx.send(JsonRpcMessage::Response(JsonRpcResponse {
+                            jsonrpc: JsonRpcVersion::default(),
+                            id,
+                            result: Some(serde_json::json!({ "done": true })),
+                            error: None,
+                        }));
+                    });
+                },
+                JsonRpcMessage::Request(req) if req.method == "resources/list" => {
+                    let cursor = req.params.as_ref().and_then(|params| params.get("cursor"));
+                    let result = match cursor.and_then(Value::as_str) {
+                        None => serde_json::json!({
+                            "resources": [{ "uri": "file:///a.txt", "name": "a" }],
+                            "nextCursor": "page-2",
+                        }),
+                        Some(_) => serde_json::json!({
+                            "resources": [{ "uri": "file:///b.txt", "name": "b" }],
+                            "nextCursor": null,
+                        }),
+                    };
+                    let _ = self.tx.send(JsonRpcMessage::Response(JsonRpcResponse {
+                        jsonrpc: JsonRpcVersion::default(),
+                        id: req.id,
+                        result: Some(result),
+                        error: None,
+                    }));
+                },
+                JsonRpcMessage::Notification(notif) => {
+                    self.notifications.lock().unwrap().push(notif.clone());
+                },
+                _ => {},
+            }
+            Ok(())
+        }
+
+        fn get_listener(&self) -> impl transport::Listener {
+            ScriptedListener(self.tx.subscribe())
+        }
+
+        async fn shutdown(&self) -> Result<(), TransportError> {
+            Ok(())
+        }
+
+        fn get_log_listener(&self) -> impl LogListener {
+            ScriptedLogListener
+        }
+    }
+
+    fn scripted_client() -> (
+        Client<ScriptedTransport>,
+        Arc<std::sync::Mutex<Vec<JsonRpcNotification>>>,
+    ) {
+        let notifications = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let transport = ScriptedTransport {
+            tx: tokio::sync::broadcast::channel(16).0,
+            notifications: notifications.clone(),
+        };
+        let client = Client {
+            server_name: "scripted".to_owned(),
+            transport: Arc::new(transport),
+            timeout: 5000,
+            server_process_id: None,
+            client_info: serde_json::json!({}),
+            current_id: Arc::new(AtomicU64::new(0)),
+            messenger: None,
+            server_request_responder: None,
+            prompt_gets: Arc::new(SyncRwLock::new(HashMap::new())),
+            is_prompts_out_of_date: Arc::new(AtomicBool::new(false)),
+        };
+        (client, notifications)
+    }
+
+    #[tokio::test]
+    async fn test_request_with_progress() {
+        let (client, notifications) = scripted_client();
+        let (tx, mut rx) = tokio::sync::mpsc::channel(16);
+        let resp = client
+            .request_with_progress("slow/op", Some(serde_json::json!({ "name": "build" })), tx)
+            .await
+            .expect("request failed");
+        assert_eq!(resp.result, Some(serde_json::json!({ "done": true })));
+
+        let mut progress = vec![];
+        while let Ok(p) = rx.try_recv() {
+            progress.push((p.progress, p.total));
+        }
+        assert_eq!(progress, vec![(1.0, Some(2.0)), (2.0, Some(2.0))]);
+
+        // A completed request is not cancelled
+        tokio::task::yield_now().await;
+        assert!(notifications.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_request_with_progress_cancelled_on_drop() {
+        let (client, notifications) = scripted_client();
+        let (tx, _rx) = tokio::sync::mpsc::channel(16);
+        let res = time::timeout(
+            Duration::from_millis(50),
+            client.request_with_progress("hanging/op", None, tx),
+        )
+        .await;
+        assert!(res.is_err());
+
+        // The notification is sent from a task spawned on drop
+        for _ in 0..10 {
+            if !notifications.lock().unwrap().is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let notifications = notifications.lock().unwrap();
+        assert_eq!(notifications.len(), 1);
+        assert_eq!(notifications[0].method, "notifications/cancelled");
+        assert_eq!(notifications[0].params.as_ref().unwrap()["requestId"], 0);
+    }
+
+    #[tokio::test]
+    async fn test_request_follows_next_cursor() {
+        let (client, _) = scripted_client();
+        let resp = client.request("resources/list", None).await.expect("request failed");
+        let result = serde_json::from_value::<ResourcesListResult>(resp.result.unwrap()).unwrap();
+        let uris: Vec<_> = result
+            .resources
+            .iter()
+            .map(|resource| resource["uri"].clone())
+            .collect();
+        assert_eq!(uris, vec!["file:///a.txt", "file:///b.txt"]);
+    }
+
     fn are_json_values_equal(a: &Value, b: &Value) -> bool {
         match (a, b) {
             (Value::Null, Value::Null) => true,
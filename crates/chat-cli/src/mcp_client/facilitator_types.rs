@@ -229,6 +229,85 @@ pub struct Resource {
     pub contents: ResourceContents,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+/// A resource as it is advertised in the result of `resources/list`
+pub struct ResourceInfo {
+    /// Unique identifier for the resource
+    pub uri: String,
+    /// Programmatic name of the resource
+    pub name: String,
+    /// Optional human-readable title
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// Optional description
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Optional MIME type of the resource
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+/// A parameterized resource as it is advertised in the result of `resources/templates/list`
+pub struct ResourceTemplate {
+    /// RFC 6570 URI template from which resource uris are constructed
+    pub uri_template: String,
+    /// Programmatic name of the template
+    pub name: String,
+    /// Optional human-readable title
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// Optional description
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Optional MIME type of the resources constructed from this template
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+/// `result` field in [JsonRpcResponse] from a `resources/read` request
+pub struct ResourceReadResult {
+    pub contents: Vec<ResourceReadContents>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+/// Contents of a resource returned by a `resources/read` request. Exactly one of [Self::text]
+/// and [Self::blob] is expected to be present.
+pub struct ResourceReadContents {
+    /// Uri of the resource these contents belong to
+    pub uri: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+    /// Contents of a text resource
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    /// Base64-encoded contents of a binary resource
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blob: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+/// `params` field of a `notifications/progress` notification sent by a server for a request
+/// that was made with a progress token
+pub struct ProgressNotification {
+    /// The progress token the request was made with
+    pub progress_token: serde_json::Value,
+    /// Progress thus far, which increases with every notification
+    pub progress: f64,
+    /// Total amount of work, if known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<f64>,
+    /// Optional description of the current progress
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
 /// Represents the capabilities supported by a Model Context Protocol server
 /// This is the "capabilities" field in the result of a response for init
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -34,6 +34,10 @@ pub trait Messenger: std::fmt::Debug + Send + Sync + 'static {
         result: eyre::Result<ResourceTemplatesListResult>,
     ) -> Result<(), MessengerError>;
 
+    /// Relays a `notifications/resources/updated` notification for a subscribed resource to the
+    /// consumer
+    async fn send_resource_updated(&self, uri: String) -> Result<(), MessengerError>;
+
     /// Signals to the orchestrator that a server has started initializing
     async fn send_init_msg(&self) -> Result<(), MessengerError>;
 
@@ -75,6 +79,10 @@ impl Messenger for NullMessenger {
         Ok(())
     }
 
+    async fn send_resource_updated(&self, _uri: String) -> Result<(), MessengerError> {
+        Ok(())
+    }
+
     async fn send_init_msg(&self) -> Result<(), MessengerError> {
         Ok(())
     }
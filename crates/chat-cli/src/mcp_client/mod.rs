@@ -2,12 +2,14 @@ pub mod client;
 pub mod error;
 pub mod facilitator_types;
 pub mod messenger;
+pub mod request_responder;
 pub mod server;
 pub mod transport;
 
 pub use client::*;
 pub use facilitator_types::*;
 pub use messenger::*;
+pub use request_responder::*;
 #[allow(unused_imports)]
 pub use server::*;
 pub use transport::*;
@@ -0,0 +1,58 @@
+use std::collections::HashMap;
+
+use thiserror::Error;
+
+use super::JsonRpcError;
+use super::error::ErrorCode;
+
+/// An interface through which the consumer of the client answers requests initiated by the
+/// server, such as `roots/list`, `sampling/createMessage` and `elicitation/create`.
+/// See https://modelcontextprotocol.io/specification/2025-06-18/client
+#[async_trait::async_trait]
+pub trait ServerRequestResponder: std::fmt::Debug + Send + Sync + 'static {
+    /// Capabilities to be declared to the server during initialization. These should correspond
+    /// to the methods the handler is able to respond to.
+    fn capabilities(&self) -> HashMap<String, serde_json::Value>;
+
+    /// Handles a request sent by the server and produces the result to be sent back to it.
+    async fn handle_request(
+        &self,
+        server_name: &str,
+        method: &str,
+        params: Option<serde_json::Value>,
+    ) -> Result<serde_json::Value, ServerRequestError>;
+
+    /// Creates a duplicate of the handler object
+    fn duplicate(&self) -> Box<dyn ServerRequestResponder>;
+}
+
+#[derive(Clone, Debug, Error)]
+pub enum ServerRequestError {
+    #[error("Method not found: {0}")]
+    MethodNotFound(String),
+    #[error("Invalid params: {0}")]
+    InvalidParams(String),
+    #[error("{0}")]
+    Rejected(String),
+    #[error("{0}")]
+    Internal(String),
+}
+
+/// Error code the spec suggests for requests that are rejected by the user
+const USER_REJECTED_CODE: i32 = -1;
+
+impl From<ServerRequestError> for JsonRpcError {
+    fn from(err: ServerRequestError) -> Self {
+        let code = match &err {
+            ServerRequestError::MethodNotFound(_) => ErrorCode::MethodNotFound.into(),
+            ServerRequestError::InvalidParams(_) => ErrorCode::InvalidParams.into(),
+            ServerRequestError::Rejected(_) => USER_REJECTED_CODE,
+            ServerRequestError::Internal(_) => ErrorCode::InternalError.into(),
+        };
+        JsonRpcError {
+            code,
+            message: err.to_string(),
+            data: None,
+        }
+    }
+}
@@ -0,0 +1,663 @@
+//! Client side of the HTTP based transports.
+//!
+//! Streamable HTTP is described in
+//! https://modelcontextprotocol.io/specification/2025-03-26/basic/transports#streamable-http
+//! The older HTTP with SSE transport is described in
+//! https://modelcontextprotocol.io/specification/2024-11-05/basic/transports#http-with-sse
+//! Servers that reject the initialize POST with a 4xx status are assumed to only speak the older
+//! transport, in which case we fall back to it.
+use std::collections::HashMap;
+use std::sync::Mutex as SyncMutex;
+
+use reqwest::header::{
+    ACCEPT,
+    CONTENT_TYPE,
+    HeaderMap,
+    HeaderName,
+    HeaderValue,
+};
+use reqwest::{
+    Response,
+    StatusCode,
+};
+use tokio::sync::{
+    Mutex,
+    broadcast,
+    oneshot,
+};
+use tokio::task::JoinHandle;
+use url::Url;
+
+use super::base_protocol::JsonRpcMessage;
+use super::{
+    Listener,
+    LogListener,
+    Transport,
+    TransportError,
+};
+
+const SESSION_ID_HEADER: &str = "mcp-session-id";
+const EVENT_STREAM_MIME: &str = "text/event-stream";
+const JSON_MIME: &str = "application/json";
+
+#[derive(Debug, Clone)]
+enum HttpMode {
+    /// No message has been exchanged yet. The response to initialize decides which transport the
+    /// server speaks.
+    Undetermined,
+    Streamable,
+    /// The older HTTP with SSE transport. Messages are posted to the endpoint announced by the
+    /// server on the event stream and all responses arrive on that same stream.
+    LegacySse {
+        endpoint: Url,
+    },
+}
+
+#[derive(Debug)]
+pub struct JsonRpcHttpTransport {
+    client: reqwest::Client,
+    url: Url,
+    mode: Mutex<HttpMode>,
+    session_id: SyncMutex<Option<String>>,
+    sender: broadcast::Sender<Result<JsonRpcMessage, TransportError>>,
+    receiver: broadcast::Receiver<Result<JsonRpcMessage, TransportError>>,
+    log_sender: broadcast::Sender<String>,
+    log_receiver: broadcast::Receiver<String>,
+    tasks: SyncMutex<Vec<JoinHandle<()>>>,
+}
+
+impl JsonRpcHttpTransport {
+    pub fn client(url: &str, headers: Option<HashMap<String, String>>) -> Result<Self, TransportError> {
+        let url = Url::parse(url).map_err(|e| TransportError::Http(format!("Invalid url {url}: {e}")))?;
+        let mut default_headers = HeaderMap::new();
+        for (name, value) in headers.unwrap_or_default() {
+            let name = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|e| TransportError::Http(format!("Invalid header name {name}: {e}")))?;
+            let value = HeaderValue::from_str(&value)
+                .map_err(|e| TransportError::Http(format!("Invalid header value for {name}: {e}")))?;
+            default_headers.insert(name, value);
+        }
+        let client = reqwest::Client::builder()
+            .default_headers(default_headers)
+            .build()
+            .map_err(TransportError::from)?;
+        let (sender, receiver) = broadcast::channel::<Result<JsonRpcMessage, TransportError>>(100);
+        let (log_sender, log_receiver) = broadcast::channel::<String>(100);
+        Ok(Self {
+            client,
+            url,
+            mode: Mutex::new(HttpMode::Undetermined),
+            session_id: SyncMutex::new(None),
+            sender,
+            receiver,
+            log_sender,
+            log_receiver,
+            tasks: SyncMutex::new(Vec::new()),
+        })
+    }
+
+    fn session_id(&self) -> Option<String> {
+        self.session_id.lock().ok().and_then(|id| id.clone())
+    }
+
+    fn track_task(&self, handle: JoinHandle<()>) {
+        if let Ok(mut tasks) = self.tasks.lock() {
+            tasks.retain(|task| !task.is_finished());
+            tasks.push(handle);
+        }
+    }
+
+    fn with_session_id(&self, builder: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match self.session_id() {
+            Some(id) => builder.header(SESSION_ID_HEADER, id),
+            None => builder,
+        }
+    }
+
+    async fn post(&self, url: &Url, msg: &JsonRpcMessage) -> Result<Response, TransportError> {
+        let body = serde_json::to_vec(msg)?;
+        let builder = self
+            .client
+            .post(url.clone())
+            .header(ACCEPT, format!("{JSON_MIME}, {EVENT_STREAM_MIME}"))
+            .header(CONTENT_TYPE, JSON_MIME)
+            .body(body);
+        Ok(self.with_session_id(builder).send().await?)
+    }
+
+    async fn send_streamable(&self, msg: &JsonRpcMessage, is_first: bool) -> Result<(), TransportError> {
+        let resp = self.post(&self.url, msg).await?;
+        let status = resp.status();
+        if is_first && msg.is_initialize() && status.is_client_error() {
+            let _ = self.log_sender.send(format!(
+                "Server responded to initialize with {status}, falling back to the HTTP with SSE transport"
+            ));
+            let endpoint = self.connect_legacy_sse().await?;
+            *self.mode.lock().await = HttpMode::LegacySse {
+                endpoint: endpoint.clone(),
+            };
+            return self.send_legacy(&endpoint, msg).await;
+        }
+        if !status.is_success() {
+            let body = resp.text().await.unwrap_or_default();
+            return Err(TransportError::Http(format!("Server responded with {status}: {body}")));
+        }
+        if is_first {
+            *self.mode.lock().await = HttpMode::Streamable;
+        }
+        if let Some(id) = resp.headers().get(SESSION_ID_HEADER).and_then(|v| v.to_str().ok()) {
+            if let Ok(mut session_id) = self.session_id.lock() {
+                *session_id = Some(id.to_owned());
+            }
+        }
+        self.handle_post_response(resp).await?;
+
+        // Once the session is established, the server is free to open a stream of its own for
+        // requests and notifications that are not tied to any request of ours.
+        if let JsonRpcMessage::Notification(notif) = msg {
+            if notif.method == "notifications/initialized" {
+                self.open_server_stream();
+            }
+        }
+        Ok(())
+    }
+
+    async fn handle_post_response(&self, resp: Response) -> Result<(), TransportError> {
+        if resp.status() == StatusCode::ACCEPTED {
+            return Ok(());
+        }
+        let content_type = resp
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default()
+            .to_owned();
+        if content_type.starts_with(EVENT_STREAM_MIME) {
+            let sender = self.sender.clone();
+            let log_sender = self.log_sender.clone();
+            self.track_task(tokio::spawn(async move {
+                read_event_stream(resp, |event| forward_event(&sender, event), &log_sender).await;
+            }));
+        } else {
+            let body = resp.bytes().await?;
+            if !body.iter().all(u8::is_ascii_whitespace) {
+                for msg in parse_json_body(&body) {
+                    let _ = self.sender.send(msg);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn open_server_stream(&self) {
+        let builder = self.with_session_id(self.client.get(self.url.clone()).header(ACCEPT, EVENT_STREAM_MIME));
+        let sender = self.sender.clone();
+        let log_sender = self.log_sender.clone();
+        self.track_task(tokio::spawn(async move {
+            let resp = match builder.send().await {
+                Ok(resp) => resp,
+                Err(e) => {
+                    let _ = log_sender.send(format!("Failed to open server event stream: {e}"));
+                    return;
+                },
+            };
+            // Servers are not required to offer this stream and signal so with a 405
+            if !resp.status().is_success() {
+                let _ = log_sender.send(format!("Server event stream not available: {}", resp.status()));
+                return;
+            }
+            read_event_stream(resp, |event| forward_event(&sender, event), &log_sender).await;
+        }));
+    }
+
+    /// Opens the long lived event stream used by the HTTP with SSE transport and waits for the
+    /// server to announce the endpoint that messages are to be posted to.
+    async fn connect_legacy_sse(&self) -> Result<Url, TransportError> {
+        let resp = self
+            .client
+            .get(self.url.clone())
+            .header(ACCEPT, EVENT_STREAM_MIME)
+            .send()
+            .await?;
+        if !resp.status().is_success() {
+            return Err(TransportError::Http(format!(
+                "Server responded to event stream request with {}",
+                resp.status()
+            )));
+        }
+        let (endpoint_tx, endpoint_rx) = oneshot::channel::<String>();
+        let mut endpoint_tx = Some(endpoint_tx);
+        let sender = self.sender.clone();
+        let log_sender = self.log_sender.clone();
+        self.track_task(tokio::spawn(async move {
+            read_event_stream(
+                resp,
+                |event| {
+                    if event.event == "endpoint" {
+                        if let Some(tx) = endpoint_tx.take() {
+                            let _ = tx.send(event.data);
+                        }
+                    } else {
+                        forward_event(&sender, event);
+                    }
+                },
+                &log_sender,
+            )
+            .await;
+        }));
+        let endpoint = endpoint_rx
+            .await
+            .map_err(|_e| TransportError::Http("Event stream closed before an endpoint was received".to_owned()))?;
+        self.url
+            .join(endpoint.trim())
+            .map_err(|e| TransportError::Http(format!("Invalid endpoint {endpoint}: {e}")))
+    }
+
+    async fn send_legacy(&self, endpoint: &Url, msg: &JsonRpcMessage) -> Result<(), TransportError> {
+        let resp = self.post(endpoint, msg).await?;
+        let status = resp.status();
+        if !status.is_success() {
+            let body = resp.text().await.unwrap_or_default();
+            return Err(TransportError::Http(format!("Server responded with {status}: {body}")));
+        }
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl Transport for JsonRpcHttpTransport {
+    async fn send(&self, msg: &JsonRpcMessage) -> Result<(), TransportError> {
+        let mode = self.mode.lock().await.clone();
+        match mode {
+            HttpMode::Undetermined => self.send_streamable(msg, true).await,
+            HttpMode::Streamable => self.send_streamable(msg, false).await,
+            HttpMode::LegacySse { endpoint } => self.send_legacy(&endpoint, msg).await,
+        }
+    }
+
+    fn get_listener(&self) -> impl Listener {
+        HttpListener {
+            receiver: self.receiver.resubscribe(),
+        }
+    }
+
+    async fn shutdown(&self) -> Result<(), TransportError> {
+        if let Ok(mut tasks) = self.tasks.lock() {
+            for task in tasks.drain(..) {
+                task.abort();
+            }
+        }
+        // Let the server know that the session can be discarded
+        if let Some(id) = self.session_id() {
+            let _ = self
+                .client
+                .delete(self.url.clone())
+                .header(SESSION_ID_HEADER, id)
+                .send()
+                .await;
+        }
+        Ok(())
+    }
+
+    fn get_log_listener(&self) -> impl LogListener {
+        HttpLogListener {
+            receiver: self.log_receiver.resubscribe(),
+        }
+    }
+}
+
+impl Drop for JsonRpcHttpTransport {
+    fn drop(&mut self) {
+        if let Ok(mut tasks) = self.tasks.lock() {
+            for task in tasks.drain(..) {
+                task.abort();
+            }
+        }
+    }
+}
+
+pub struct HttpListener {
+    pub receiver: broadcast::Receiver<Result<JsonRpcMessage, TransportError>>,
+}
+
+#[async_trait::async_trait]
+impl Listener for HttpListener {
+    async fn recv(&mut self) -> Result<JsonRpcMessage, TransportError> {
+        self.receiver.recv().await?
+    }
+}
+
+pub struct HttpLogListener {
+    pub receiver: broadcast::Receiver<String>,
+}
+
+#[async_trait::async_trait]
+impl LogListener for HttpLogListener {
+    async fn recv(&mut self) -> Result<String, TransportError> {
+        Ok(self.receiver.recv().await?)
+    }
+}
+
+/// A body of JSON may either be a single message or a batch of them.
+fn parse_json_body(body: &[u8]) -> Vec<Result<JsonRpcMessage, TransportError>> {
+    match serde_json::from_slice::<JsonRpcMessage>(body) {
+        Ok(msg) => vec![Ok(msg)],
+        Err(e) => match serde_json::from_slice::<Vec<JsonRpcMessage>>(body) {
+            Ok(msgs) => msgs.into_iter().map(Ok).collect(),
+            Err(_) => vec![Err(e.into())],
+        },
+    }
+}
+
+fn forward_event(sender: &broadcast::Sender<Result<JsonRpcMessage, TransportError>>, event: SseEvent) {
+    if event.event != "message" {
+        return;
+    }
+    for msg in parse_json_body(event.data.as_bytes()) {
+        let _ = sender.send(msg);
+    }
+}
+
+async fn read_event_stream(
+    mut resp: Response,
+    mut on_event: impl FnMut(SseEvent) + Send,
+    log_sender: &broadcast::Sender<String>,
+) {
+    let mut parser = SseParser::default();
+    loop {
+        match resp.chunk().await {
+            Ok(Some(chunk)) => {
+                for event in parser.feed(&chunk) {
+                    on_event(event);
+                }
+            },
+            Ok(None) => break,
+            Err(e) => {
+                let _ = log_sender.send(format!("Event stream terminated: {e}"));
+                break;
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct SseEvent {
+    event: String,
+    data: String,
+}
+
+/// Incremental parser for `text/event-stream` bodies.
+/// Chunks can split lines (and utf-8 sequences) arbitrarily, so incomplete lines are buffered
+/// until the rest of them arrives.
+#[derive(Debug, Default)]
+struct SseParser {
+    buffer: Vec<u8>,
+    event: Option<String>,
+    data: Vec<String>,
+}
+
+impl SseParser {
+    fn feed(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        self.buffer.extend_from_slice(chunk);
+        let mut events = Vec::new();
+        while let Some(pos) = self.buffer.iter().position(|b| *b == b'\n') {
+            let line = self.buffer.drain(..=pos).collect::<Vec<u8>>();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\n', '\r']);
+            if line.is_empty() {
+                if !self.data.is_empty() {
+                    events.push(SseEvent {
+                        event: self.event.take().unwrap_or_else(|| "message".to_owned()),
+                        data: self.data.join("\n"),
+                    });
+                }
+                self.event = None;
+                self.data.clear();
+                continue;
+            }
+            // Lines starting with a colon are comments, commonly used as keep-alives
+            if line.starts_with(':') {
+                continue;
+            }
+            let (field, value) = match line.split_once(':') {
+                Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+                None => (line, ""),
+            };
+            match field {
+                "event" => self.event = Some(value.to_owned()),
+                "data" => self.data.push(value.to_owned()),
+                _ => {},
+            }
+        }
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+    use std::net::SocketAddr;
+    use std::sync::Arc;
+
+    use bytes::Bytes;
+    use futures::StreamExt;
+    use http_body_util::combinators::UnsyncBoxBody;
+    use http_body_util::{
+        BodyExt,
+        Full,
+        StreamBody,
+    };
+    use hyper::body::{
+        Frame,
+        Incoming,
+    };
+    use hyper::server::conn::http1;
+    use hyper::service::service_fn;
+    use hyper::{
+        Method,
+        Request,
+    };
+    use hyper_util::rt::TokioIo;
+    use serde_json::json;
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    type TestResponse = hyper::Response<UnsyncBoxBody<Bytes, Infallible>>;
+
+    #[derive(Debug, Default)]
+    struct ServerState {
+        posts: Vec<(Option<String>, serde_json::Value)>,
+        deleted_session: Option<String>,
+    }
+
+    fn response(status: u16, content_type: Option<&str>, body: String) -> TestResponse {
+        let mut builder = hyper::Response::builder()
+            .status(status)
+            .header(SESSION_ID_HEADER, "test-session");
+        if let Some(content_type) = content_type {
+            builder = builder.header(CONTENT_TYPE.as_str(), content_type);
+        }
+        builder.body(Full::new(Bytes::from(body)).boxed_unsync()).unwrap()
+    }
+
+    /// A stand-in for a streamable HTTP server. Requests with an even id are answered with plain
+    /// json and the rest are answered over an event stream.
+    async fn handle_streamable(
+        req: Request<Incoming>,
+        state: Arc<SyncMutex<ServerState>>,
+    ) -> Result<TestResponse, Infallible> {
+        let session = req
+            .headers()
+            .get(SESSION_ID_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .map(String::from);
+        match *req.method() {
+            Method::GET => Ok(response(405, None, String::new())),
+            Method::DELETE => {
+                state.lock().unwrap().deleted_session = session;
+                Ok(response(200, None, String::new()))
+            },
+            _ => {
+                let body = req.into_body().collect().await.unwrap().to_bytes();
+                let value = serde_json::from_slice::<serde_json::Value>(&body).unwrap();
+                state.lock().unwrap().posts.push((session, value.clone()));
+                let Some(id) = value.get("id").and_then(|id| id.as_u64()) else {
+                    return Ok(response(202, None, String::new()));
+                };
+                let resp = json!({ "jsonrpc": "2.0", "id": id, "result": { "echo": value["method"] } });
+                if id % 2 == 0 {
+                    Ok(response(200, Some(JSON_MIME), resp.to_string()))
+                } else {
+                    let notif = json!({ "jsonrpc": "2.0", "method": "notifications/message", "params": {} });
+                    let body = format!(": keep-alive\n\ndata: {notif}\n\nevent: message\ndata: {resp}\n\n");
+                    Ok(response(200, Some(EVENT_STREAM_MIME), body))
+                }
+            },
+        }
+    }
+
+    async fn serve<F, Fut>(handler: F) -> SocketAddr
+    where
+        F: Fn(Request<Incoming>) -> Fut + Clone + Send + 'static,
+        Fut: std::future::Future<Output = Result<TestResponse, Infallible>> + Send + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let handler = handler.clone();
+                tokio::spawn(async move {
+                    let _ = http1::Builder::new()
+                        .serve_connection(TokioIo::new(stream), service_fn(handler))
+                        .await;
+                });
+            }
+        });
+        addr
+    }
+
+    fn request(id: u64, method: &str) -> JsonRpcMessage {
+        serde_json::from_value(json!({ "jsonrpc": "2.0", "id": id, "method": method })).unwrap()
+    }
+
+    async fn recv_response(listener: &mut impl Listener) -> JsonRpcMessage {
+        loop {
+            let msg = tokio::time::timeout(std::time::Duration::from_secs(5), listener.recv())
+                .await
+                .expect("timed out waiting for response")
+                .expect("failed to receive message");
+            if let JsonRpcMessage::Response(_) = msg {
+                return msg;
+            }
+        }
+    }
+
+    #[test]
+    fn test_sse_parser() {
+        let mut parser = SseParser::default();
+        assert!(parser.feed(b"event: endpoint\r\nda").is_empty());
+        let events = parser.feed(b"ta: /messages?id=1\r\n\r\n: comment\n\ndata: a\ndata:b\n\n");
+        assert_eq!(events, vec![
+            SseEvent {
+                event: "endpoint".to_owned(),
+                data: "/messages?id=1".to_owned(),
+            },
+            SseEvent {
+                event: "message".to_owned(),
+                data: "a\nb".to_owned(),
+            },
+        ]);
+    }
+
+    #[test]
+    fn test_parse_json_body_batch() {
+        let body = json!([
+            { "jsonrpc": "2.0", "id": 1, "result": {} },
+            { "jsonrpc": "2.0", "method": "notifications/message" },
+        ]);
+        let msgs = parse_json_body(body.to_string().as_bytes());
+        assert_eq!(msgs.len(), 2);
+        assert!(msgs.iter().all(Result::is_ok));
+    }
+
+    #[tokio::test]
+    async fn test_streamable_http_transport() {
+        let state = Arc::new(SyncMutex::new(ServerState::default()));
+        let state_clone = state.clone();
+        let addr = serve(move |req| handle_streamable(req, state_clone.clone())).await;
+        let transport = JsonRpcHttpTransport::client(
+            &format!("http://{addr}/mcp"),
+            Some(HashMap::from([("Authorization".to_owned(), "Bearer token".to_owned())])),
+        )
+        .unwrap();
+
+        for (id, method) in [(0, "initialize"), (1, "tools/list")] {
+            let mut listener = transport.get_listener();
+            transport.send(&request(id, method)).await.unwrap();
+            let JsonRpcMessage::Response(resp) = recv_response(&mut listener).await else {
+                unreachable!()
+            };
+            assert_eq!(resp.id, id);
+            assert_eq!(resp.result, Some(json!({ "echo": method })));
+        }
+        let initialized = serde_json::from_value::<JsonRpcMessage>(
+            json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }),
+        )
+        .unwrap();
+        transport.send(&initialized).await.unwrap();
+        transport.shutdown().await.unwrap();
+
+        let state = state.lock().unwrap();
+        let sessions = state.posts.iter().map(|(s, _)| s.as_deref()).collect::<Vec<_>>();
+        assert_eq!(sessions, vec![None, Some("test-session"), Some("test-session")]);
+        assert_eq!(state.deleted_session.as_deref(), Some("test-session"));
+    }
+
+    #[tokio::test]
+    async fn test_legacy_sse_fallback() {
+        let (stream_tx, stream_rx) = tokio::sync::mpsc::unbounded_channel::<String>();
+        let stream_rx = Arc::new(Mutex::new(Some(stream_rx)));
+        let addr = serve(move |req: Request<Incoming>| {
+            let stream_tx = stream_tx.clone();
+            let stream_rx = stream_rx.clone();
+            async move {
+                match (req.method().clone(), req.uri().path()) {
+                    (Method::GET, "/sse") => {
+                        // The stream stays open and relays whatever the posts below queue up
+                        let rx = stream_rx.lock().await.take().unwrap();
+                        let endpoint = "event: endpoint\ndata: /messages?session=abc\n\n".to_owned();
+                        let events = futures::stream::once(async move { endpoint })
+                            .chain(futures::stream::unfold(rx, |mut rx| async move {
+                                rx.recv().await.map(|e| (e, rx))
+                            }));
+                        let body = StreamBody::new(events.map(|e| Ok::<_, Infallible>(Frame::data(Bytes::from(e)))));
+                        Ok(hyper::Response::builder()
+                            .header(CONTENT_TYPE.as_str(), EVENT_STREAM_MIME)
+                            .body(body.boxed_unsync())
+                            .unwrap())
+                    },
+                    (Method::POST, "/sse") => Ok(response(405, None, String::new())),
+                    (Method::POST, "/messages") => {
+                        assert_eq!(req.uri().query(), Some("session=abc"));
+                        let body = req.into_body().collect().await.unwrap().to_bytes();
+                        let value = serde_json::from_slice::<serde_json::Value>(&body).unwrap();
+                        let resp = json!({ "jsonrpc": "2.0", "id": value["id"], "result": {} });
+                        let _ = stream_tx.send(format!("event: message\ndata: {resp}\n\n"));
+                        Ok(response(202, None, String::new()))
+                    },
+                    _ => Ok(response(404, None, String::new())),
+                }
+            }
+        })
+        .await;
+
+        let transport = JsonRpcHttpTransport::client(&format!("http://{addr}/sse"), None).unwrap();
+        let mut listener = transport.get_listener();
+        transport.send(&request(7, "initialize")).await.unwrap();
+        let JsonRpcMessage::Response(resp) = recv_response(&mut listener).await else {
+            unreachable!()
+        };
+        assert_eq!(resp.id, 7);
+    }
+}
@@ -117,6 +117,15 @@ impl Env {
         }
     }
 
+    /// Changes the current directory of a fake process environment.
+    #[cfg(test)]
+    pub fn set_current_dir_for_test(&self, path: impl Into<PathBuf>) {
+        match &self.0 {
+            inner::Inner::Real => panic!("the current directory can only be changed in a fake environment"),
+            inner::Inner::Fake(fake) => fake.lock().unwrap().cwd = path.into(),
+        }
+    }
+
     pub fn home(&self) -> Option<PathBuf> {
         match &self.0 {
             inner::Inner::Real => dirs::home_dir(),
@@ -139,6 +139,22 @@ pub fn chat_profiles_dir(os: &Os) -> Result<PathBuf> {
     Ok(home_dir(os)?.join(".aws").join("amazonq").join("profiles"))
 }
 
+/// The directory of the local copies of the URLs of context rules, which are used when the URLs
+/// cannot be fetched
+pub fn chat_context_mirror_dir(os: &Os) -> Result<PathBuf> {
+    Ok(home_dir(os)?.join(".aws").join("amazonq").join("context_mirror"))
+}
+
+/// The directory of the prompt templates available in every workspace
+pub fn chat_global_prompts_dir(os: &Os) -> Result<PathBuf> {
+    Ok(home_dir(os)?.join(".aws").join("amazonq").join("prompts"))
+}
+
+/// The directory the contents of the files snapshotted by `/checkpoint` are stored in
+pub fn chat_checkpoints_dir(os: &Os) -> Result<PathBuf> {
+    Ok(home_dir(os)?.join(".aws").join("amazonq").join("checkpoints"))
+}
+
 /// The path to the fig settings file
 pub fn settings_path() -> Result<PathBuf> {
     Ok(fig_data_dir()?.join("settings.json"))
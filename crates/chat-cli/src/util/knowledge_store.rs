@@ -4,9 +4,16 @@ use std::sync::{
 };
 
 use eyre::Result;
-use semantic_search_client::KnowledgeContext;
-use semantic_search_client::client::AsyncSemanticSearchClient;
+use semantic_search_client::client::{
+    AsyncSemanticSearchClient,
+    ContextWatcher,
+};
 use semantic_search_client::types::SearchResult;
+use semantic_search_client::{
+    IndexingFilter,
+    KnowledgeContext,
+    SearchOptions,
+};
 use tokio::sync::Mutex;
 use uuid::Uuid;
 
@@ -65,7 +72,7 @@ impl KnowledgeStore {
     }
 
     /// Add context - delegates to async client
-    pub async fn add(&mut self, name: &str, path_str: &str) -> Result<String, String> {
+    pub async fn add(&mut self, name: &str, path_str: &str, filter: IndexingFilter) -> Result<String, String> {
         let path_buf = std::path::PathBuf::from(path_str);
         let canonical_path = path_buf
             .canonicalize()
@@ -73,7 +80,13 @@ impl KnowledgeStore {
 
         match self
             .client
-            .add_context_from_path(&canonical_path, name, &format!("Knowledge context for {}", name), true)
+            .add_context_from_path_with_filter(
+                &canonical_path,
+                name,
+                &format!("Knowledge context for {}", name),
+                true,
+                filter,
+            )
             .await
         {
             Ok((operation_id, _)) => Ok(format!(
@@ -92,10 +105,15 @@ impl KnowledgeStore {
     }
 
     /// Search - delegates to async client
-    pub async fn search(&self, query: &str, _context_id: Option<&str>) -> Result<Vec<SearchResult>, KnowledgeError> {
+    pub async fn search(
+        &self,
+        query: &str,
+        _context_id: Option<&str>,
+        options: SearchOptions,
+    ) -> Result<Vec<SearchResult>, KnowledgeError> {
         let results = self
             .client
-            .search_all(query, None)
+            .search_all_with_options(query, None, options)
             .await
             .map_err(|e| KnowledgeError::ClientError(e.to_string()))?;
 
@@ -198,17 +216,38 @@ impl KnowledgeStore {
             .map_err(|e| e.to_string())
     }
 
+    /// Watch the source paths of all contexts, updating them as files change
+    ///
+    /// The watcher runs until the returned handle is dropped.
+    pub fn watch(&self) -> Result<ContextWatcher, String> {
+        self.client.watch_contexts().map_err(|e| e.to_string())
+    }
+
+    /// Re-index only the files of a context that changed since it was indexed
+    async fn update_incrementally(&mut self, context: &KnowledgeContext) -> Result<String, String> {
+        match self.client.update_context(&context.id).await {
+            Ok((operation_id, _)) => Ok(format!(
+                "🚀 Started updating '{}'\n📁 Path: {}\n🆔 Operation ID: {}.",
+                context.name,
+                context.source_path.as_deref().unwrap_or_default(),
+                &operation_id.to_string()[..8]
+            )),
+            Err(e) => Err(format!("Failed to start updating: {}", e)),
+        }
+    }
+
+    /// Whether a path is the one a context was indexed from
+    fn is_source_path(context: &KnowledgeContext, path_str: &str) -> bool {
+        let canonical = |path: &str| std::path::PathBuf::from(path).canonicalize().ok();
+        context.source_path.as_deref().is_some_and(|source_path| {
+            canonical(source_path).is_some() && canonical(source_path) == canonical(path_str)
+        })
+    }
+
     /// Update context by path
     pub async fn update_by_path(&mut self, path_str: &str) -> Result<String, String> {
         if let Some(context) = self.client.get_context_by_path(path_str).await {
-            // Remove the existing context first
-            self.client
-                .remove_context_by_id(&context.id)
-                .await
-                .map_err(|e| e.to_string())?;
-
-            // Then add it back with the same name
-            self.add(&context.name, path_str).await
+            self.update_incrementally(&context).await
         } else {
             // Debug: List all available contexts
             let available_paths = self.client.list_context_paths().await;
@@ -233,6 +272,10 @@ impl KnowledgeStore {
             .ok_or_else(|| format!("Context '{}' not found", context_id))?;
 
         let context_name = context.name.clone();
+        let filter = context.filter.clone();
+        if Self::is_source_path(context, path_str) {
+            return self.update_incrementally(context).await;
+        }
 
         // Remove the existing context first
         self.client
@@ -241,12 +284,16 @@ impl KnowledgeStore {
             .map_err(|e| e.to_string())?;
 
         // Then add it back with the same name
-        self.add(&context_name, path_str).await
+        self.add(&context_name, path_str, filter).await
     }
 
     /// Update context by name
     pub async fn update_context_by_name(&mut self, name: &str, path_str: &str) -> Result<String, String> {
         if let Some(context) = self.client.get_context_by_name(name).await {
+            if Self::is_source_path(&context, path_str) {
+                return self.update_incrementally(&context).await;
+            }
+
             // Remove the existing context first
             self.client
                 .remove_context_by_id(&context.id)
@@ -254,7 +301,7 @@ impl KnowledgeStore {
                 .map_err(|e| e.to_string())?;
 
             // Then add it back with the same name
-            self.add(name, path_str).await
+            self.add(name, path_str, context.filter).await
         } else {
             Err(format!("Context with name '{}' not found", name))
         }
@@ -1,4 +1,7 @@
-use std::collections::HashMap;
+use std::collections::{
+    HashMap,
+    HashSet,
+};
 use std::path::{
     Path,
     PathBuf,
@@ -8,8 +11,10 @@ use std::time::SystemTime;
 
 use tokio::sync::{
     Mutex,
+    OnceCell,
     RwLock,
     Semaphore,
+    SemaphorePermit,
     mpsc,
 };
 use tokio_util::sync::CancellationToken;
@@ -17,21 +22,29 @@ use uuid::Uuid;
 
 use crate::client::semantic_context::SemanticContext;
 use crate::client::{
+    ContextWatcher,
     embedder_factory,
+    storage,
     utils,
 };
 use crate::config::SemanticSearchConfig;
 use crate::embedding::{
     EmbeddingType,
+    RerankerTrait,
     TextEmbedderTrait,
 };
 use crate::error::{
     Result,
     SemanticSearchError,
 };
+use crate::processing::{
+    FileMatcher,
+    walk_files,
+};
 use crate::types::{
     ContextId,
     DataPoint,
+    IndexingFilter,
     IndexingJob,
     KnowledgeContext,
     OperationHandle,
@@ -39,6 +52,8 @@ use crate::types::{
     OperationType,
     ProgressInfo,
     ProgressStatus,
+    SearchMode,
+    SearchOptions,
     SearchResults,
     SystemStatus,
 };
@@ -59,6 +74,8 @@ pub struct AsyncSemanticSearchClient {
     volatile_contexts: Arc<RwLock<HashMap<ContextId, Arc<Mutex<SemanticContext>>>>>,
     /// Text embedder for generating embeddings
     embedder: Box<dyn TextEmbedderTrait>,
+    /// Cross-encoder reranker, loaded on the first search asking for reranking
+    reranker: OnceCell<Arc<dyn RerankerTrait>>,
     /// Configuration for the client
     config: SemanticSearchConfig,
     /// Background job processor
@@ -81,6 +98,9 @@ struct BackgroundWorker {
 
 const MAX_CONCURRENT_OPERATIONS: usize = 3;
 
+/// Number of candidates retrieved per requested result when reranking
+const RERANK_CANDIDATES_FACTOR: usize = 4;
+
 impl AsyncSemanticSearchClient {
     /// Create a new async semantic search client
     pub async fn new(base_dir: impl AsRef<Path>) -> Result<Self> {
@@ -154,6 +174,7 @@ impl AsyncSemanticSearchClient {
             contexts,
             volatile_contexts,
             embedder,
+            reranker: OnceCell::new(),
             config,
             job_tx,
             active_operations,
@@ -173,6 +194,24 @@ impl AsyncSemanticSearchClient {
         description: &str,
         persistent: bool,
     ) -> Result<(Uuid, CancellationToken)> {
+        self.add_context_from_path_with_filter(path, name, description, persistent, IndexingFilter::default())
+            .await
+    }
+
+    /// Add a context from the files of a path selected by a filter (async, cancellable)
+    ///
+    /// The filter is recorded on the context and reused when it is updated.
+    pub async fn add_context_from_path_with_filter(
+        &self,
+        path: impl AsRef<Path>,
+        name: &str,
+        description: &str,
+        persistent: bool,
+        filter: IndexingFilter,
+    ) -> Result<(Uuid, CancellationToken)> {
+        // Reject invalid patterns before queueing the job
+        FileMatcher::new(&filter)?;
+
         let path = path.as_ref();
         let canonical_path = path.canonicalize().map_err(|_e| {
             SemanticSearchError::InvalidPath(format!("Path does not exist or is not accessible: {}", path.display()))
@@ -203,6 +242,7 @@ impl AsyncSemanticSearchClient {
             name: name.to_string(),
             description: description.to_string(),
             persistent,
+            filter,
         };
 
         self.job_tx
@@ -212,6 +252,35 @@ impl AsyncSemanticSearchClient {
         Ok((operation_id, cancel_token))
     }
 
+    /// Update a context from its source path (async, cancellable)
+    ///
+    /// Only the files whose content changed since the last indexing are re-embedded, and the
+    /// data points of removed files are deleted.
+    pub async fn update_context(&self, context_id: &str) -> Result<(Uuid, CancellationToken)> {
+        let context = self
+            .contexts
+            .read()
+            .await
+            .get(context_id)
+            .cloned()
+            .ok_or_else(|| SemanticSearchError::ContextNotFound(context_id.to_string()))?;
+
+        queue_update(&self.active_operations, &self.job_tx, &context).await
+    }
+
+    /// Watch the source paths of the contexts, updating them as their files change
+    ///
+    /// Changes are batched until the files are quiet for a moment, and the watcher runs until the
+    /// returned handle is dropped.
+    pub fn watch_contexts(&self) -> Result<ContextWatcher> {
+        ContextWatcher::start(
+            self.contexts.clone(),
+            self.active_operations.clone(),
+            self.job_tx.clone(),
+        )
+        .map_err(|e| SemanticSearchError::OperationFailed(format!("Failed to start file watcher: {}", e)))
+    }
+
     /// Get all contexts (concurrent with indexing)
     pub async fn get_contexts(&self) -> Vec<KnowledgeContext> {
         // Try to get a read lock with timeout
@@ -231,10 +300,26 @@ impl AsyncSemanticSearchClient {
     }
 
     /// Search across all contexts (concurrent with indexing)
+    ///
+    /// Uses the search mode and reranking of the client configuration.
     pub async fn search_all(
         &self,
         query_text: &str,
         result_limit: Option<usize>,
+    ) -> Result<Vec<(ContextId, SearchResults)>> {
+        let options = SearchOptions {
+            mode: self.config.search_mode,
+            rerank: self.config.rerank,
+        };
+        self.search_all_with_options(query_text, result_limit, options).await
+    }
+
+    /// Search across all contexts with a specific retrieval strategy (concurrent with indexing)
+    pub async fn search_all_with_options(
+        &self,
+        query_text: &str,
+        result_limit: Option<usize>,
+        options: SearchOptions,
     ) -> Result<Vec<(ContextId, SearchResults)>> {
         if query_text.is_empty() {
             return Err(SemanticSearchError::InvalidArgument(
@@ -243,7 +328,34 @@ impl AsyncSemanticSearchClient {
         }
 
         let effective_limit = result_limit.unwrap_or(self.config.default_results);
-        let query_vector = self.embedder.embed(query_text)?;
+        // Loading the reranker model and reranking are CPU bound, so they run on blocking threads
+        let reranker = if options.rerank {
+            let reranker = self
+                .reranker
+                .get_or_try_init(|| async {
+                    match tokio::task::spawn_blocking(embedder_factory::create_reranker).await {
+                        Ok(reranker) => reranker.map(Arc::from),
+                        Err(e) => Err(SemanticSearchError::OperationFailed(format!(
+                            "Reranker loading task failed: {}",
+                            e
+                        ))),
+                    }
+                })
+                .await?;
+            Some(Arc::clone(reranker))
+        } else {
+            None
+        };
+        // The reranker picks the best results among a larger set of candidates
+        let candidates_limit = if reranker.is_some() {
+            effective_limit.saturating_mul(RERANK_CANDIDATES_FACTOR)
+        } else {
+            effective_limit
+        };
+        let query_vector = match options.mode {
+            SearchMode::Lexical => Vec::new(),
+            SearchMode::Vector | SearchMode::Hybrid => self.embedder.embed(query_text)?,
+        };
 
         // Try to get volatile contexts with timeout
         let volatile_contexts =
@@ -263,7 +375,7 @@ impl AsyncSemanticSearchClient {
 
         for (context_id, context) in volatile_contexts.iter() {
             if let Ok(context_guard) = context.try_lock() {
-                match context_guard.search(&query_vector, effective_limit) {
+                match context_guard.search_with_mode(options.mode, query_text, &query_vector, candidates_limit) {
                     Ok(results) => {
                         if !results.is_empty() {
                             all_results.push((context_id.clone(), results));
@@ -275,6 +387,36 @@ impl AsyncSemanticSearchClient {
                 }
             }
         }
+        // Indexing doesn't need to wait for the reranking
+        drop(volatile_contexts);
+
+        if let Some(reranker) = reranker {
+            let query_text = query_text.to_string();
+            let reranked = tokio::task::spawn_blocking(move || {
+                all_results
+                    .into_iter()
+                    .filter_map(
+                        |(context_id, results)| match reranker.rerank(&query_text, results, effective_limit) {
+                            Ok(results) => Some((context_id, results)),
+                            Err(e) => {
+                                tracing::warn!("Failed to rerank the results of context {}: {}", context_id, e);
+                                None
+                            },
+                        },
+                    )
+                    .collect()
+            })
+            .await;
+            all_results = match reranked {
+                Ok(results) => results,
+                Err(e) => {
+                    return Err(SemanticSearchError::OperationFailed(format!(
+                        "Reranking task failed: {}",
+                        e
+                    )));
+                },
+            };
+        }
 
         // Sort by best match
         all_results.sort_by(|(_, a), (_, b)| {
@@ -599,7 +741,7 @@ impl AsyncSemanticSearchClient {
         }
 
         // Create a new semantic context
-        let semantic_context = SemanticContext::new(context_dir.join("data.json"))?;
+        let semantic_context = SemanticContext::new(context_dir.join(storage::DATA_FILE_NAME))?;
 
         // Store the semantic context
         let mut volatile_contexts = self.volatile_contexts.write().await;
@@ -715,6 +857,43 @@ impl AsyncSemanticSearchClient {
     }
 }
 
+/// Register an update operation for a context and submit it to the background worker
+pub(crate) async fn queue_update(
+    active_operations: &RwLock<HashMap<Uuid, OperationHandle>>,
+    job_tx: &mpsc::UnboundedSender<IndexingJob>,
+    context: &KnowledgeContext,
+) -> Result<(Uuid, CancellationToken)> {
+    let source_path = context.source_path.clone().ok_or_else(|| {
+        SemanticSearchError::InvalidArgument(format!("Context '{}' has no source path to update from", context.name))
+    })?;
+
+    let operation_id = Uuid::new_v4();
+    let cancel_token = CancellationToken::new();
+
+    let handle = OperationHandle {
+        operation_type: OperationType::Updating {
+            name: context.name.clone(),
+            path: source_path,
+        },
+        started_at: SystemTime::now(),
+        progress: Arc::new(Mutex::new(ProgressInfo::new())),
+        cancel_token: cancel_token.clone(),
+        task_handle: None,
+    };
+    active_operations.write().await.insert(operation_id, handle);
+
+    let job = IndexingJob::Update {
+        id: operation_id,
+        cancel: cancel_token.clone(),
+        context_id: context.id.clone(),
+    };
+    job_tx
+        .send(job)
+        .map_err(|_send_error| SemanticSearchError::OperationFailed("Background worker unavailable".to_string()))?;
+
+    Ok((operation_id, cancel_token))
+}
+
 // Background Worker Implementation
 impl BackgroundWorker {
     async fn run(mut self) {
@@ -729,10 +908,14 @@ impl BackgroundWorker {
                     name,
                     description,
                     persistent,
+                    filter,
                 } => {
-                    self.process_add_directory(id, path, name, description, persistent, cancel)
+                    self.process_add_directory(id, path, name, description, persistent, filter, cancel)
                         .await;
                 },
+                IndexingJob::Update { id, cancel, context_id } => {
+                    self.process_update(id, context_id, cancel).await;
+                },
                 IndexingJob::Clear { id, cancel } => {
                     self.process_clear(id, cancel).await;
                 },
@@ -742,6 +925,7 @@ impl BackgroundWorker {
         tracing::info!("Background worker stopped");
     }
 
+    #[allow(clippy::too_many_arguments)]
     async fn process_add_directory(
         &self,
         operation_id: Uuid,
@@ -749,6 +933,7 @@ impl BackgroundWorker {
         name: String,
         description: String,
         persistent: bool,
+        filter: IndexingFilter,
         cancel_token: CancellationToken,
     ) {
         tracing::info!("Processing AddDirectory job: {} -> {}", name, path.display());
@@ -758,15 +943,38 @@ impl BackgroundWorker {
             return;
         }
 
+        let Some(_permit) = self.acquire_slot(operation_id).await else {
+            return;
+        };
+
+        // Perform actual indexing
+        let result = self
+            .perform_indexing(operation_id, path, name, description, persistent, filter, cancel_token)
+            .await;
+
+        match result {
+            Ok(context_id) => {
+                tracing::info!("Successfully indexed context: {}", context_id);
+                self.mark_operation_completed(operation_id).await;
+            },
+            Err(e) => {
+                tracing::error!("Indexing failed: {}", e);
+                self.mark_operation_failed(operation_id, e).await;
+            },
+        }
+    }
+
+    /// Wait for an indexing slot, marking the operation as failed if none can be acquired
+    async fn acquire_slot(&self, operation_id: Uuid) -> Option<SemaphorePermit<'_>> {
         // Update status and acquire semaphore
         self.update_operation_status(operation_id, "Waiting in queue...".to_string())
             .await;
 
-        let _permit = match self.indexing_semaphore.try_acquire() {
+        match self.indexing_semaphore.try_acquire() {
             Ok(permit) => {
                 self.update_operation_status(operation_id, "Acquired slot, starting indexing...".to_string())
                     .await;
-                permit
+                Some(permit)
             },
             Err(_) => {
                 self.update_operation_status(
@@ -781,34 +989,19 @@ impl BackgroundWorker {
                     Ok(permit) => {
                         self.update_operation_status(operation_id, "Acquired slot, starting indexing...".to_string())
                             .await;
-                        permit
+                        Some(permit)
                     },
                     Err(_) => {
                         self.mark_operation_failed(operation_id, "Semaphore unavailable".to_string())
                             .await;
-                        return;
+                        None
                     },
                 }
             },
-        };
-
-        // Perform actual indexing
-        let result = self
-            .perform_indexing(operation_id, path, name, description, persistent, cancel_token)
-            .await;
-
-        match result {
-            Ok(context_id) => {
-                tracing::info!("Successfully indexed context: {}", context_id);
-                self.mark_operation_completed(operation_id).await;
-            },
-            Err(e) => {
-                tracing::error!("Indexing failed: {}", e);
-                self.mark_operation_failed(operation_id, e).await;
-            },
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     async fn perform_indexing(
         &self,
         operation_id: Uuid,
@@ -816,6 +1009,7 @@ impl BackgroundWorker {
         name: String,
         description: String,
         persistent: bool,
+        filter: IndexingFilter,
         cancel_token: CancellationToken,
     ) -> std::result::Result<String, String> {
         if !path.exists() {
@@ -856,7 +1050,7 @@ impl BackgroundWorker {
         }
 
         // Count files and notify progress
-        let file_count = self.count_files_in_directory(&path, operation_id).await?;
+        let file_count = self.count_files_in_directory(&path, &filter, operation_id).await?;
 
         // Check if file count exceeds the configured limit
         if file_count > config.max_files {
@@ -887,8 +1081,8 @@ impl BackgroundWorker {
         }
 
         // Process files with cancellation checks
-        let items = self
-            .process_directory_files(&path, file_count, operation_id, &cancel_token_clone)
+        let (items, file_hashes) = self
+            .process_directory_files(&path, &filter, file_count, operation_id, &cancel_token_clone)
             .await?;
 
         // Check cancellation before creating semantic context
@@ -934,12 +1128,195 @@ impl BackgroundWorker {
             Some(path.to_string_lossy().to_string()),
             semantic_context,
             file_count,
+            file_hashes,
+            filter,
         )
         .await?;
 
         Ok(context_id)
     }
 
+    async fn process_update(&self, operation_id: Uuid, context_id: String, cancel_token: CancellationToken) {
+        tracing::info!("Processing Update job: {}", context_id);
+
+        if cancel_token.is_cancelled() {
+            self.mark_operation_cancelled(operation_id).await;
+            return;
+        }
+
+        let Some(_permit) = self.acquire_slot(operation_id).await else {
+            return;
+        };
+
+        match self.perform_update(operation_id, &context_id, &cancel_token).await {
+            Ok(summary) => {
+                tracing::info!("Successfully updated context {}: {}", context_id, summary);
+                self.mark_operation_completed(operation_id).await;
+            },
+            Err(e) => {
+                tracing::error!("Update failed: {}", e);
+                self.mark_operation_failed(operation_id, e).await;
+            },
+        }
+    }
+
+    /// Re-index the files of a context that changed since it was last indexed
+    async fn perform_update(
+        &self,
+        operation_id: Uuid,
+        context_id: &str,
+        cancel_token: &CancellationToken,
+    ) -> std::result::Result<String, String> {
+        use crate::processing::process_file;
+
+        let context = self
+            .contexts
+            .read()
+            .await
+            .get(context_id)
+            .cloned()
+            .ok_or_else(|| format!("Context '{}' not found", context_id))?;
+        let source_path = context
+            .source_path
+            .as_deref()
+            .map(PathBuf::from)
+            .ok_or_else(|| format!("Context '{}' has no source path", context.name))?;
+        if !source_path.exists() {
+            return Err(format!("Path '{}' does not exist", source_path.display()));
+        }
+        let semantic_context = self
+            .volatile_contexts
+            .read()
+            .await
+            .get(context_id)
+            .cloned()
+            .ok_or_else(|| format!("Context '{}' is not loaded", context.name))?;
+
+        // Compare the content of the files with the one they were indexed with
+        self.update_operation_status(operation_id, "Checking for changes...".to_string())
+            .await;
+        let file_hashes = Self::hash_directory_files(&source_path, &context.filter, cancel_token).await?;
+        if file_hashes.len() > self.config.max_files {
+            return Err(format!(
+                "Failed: Directory contains {} files, which exceeds the maximum limit of {} files",
+                file_hashes.len(),
+                self.config.max_files
+            ));
+        }
+
+        let changed: Vec<&String> = file_hashes
+            .iter()
+            .filter(|(path, hash)| context.file_hashes.get(*path) != Some(*hash))
+            .map(|(path, _)| path)
+            .collect();
+        let removed: Vec<&String> = context
+            .file_hashes
+            .keys()
+            .filter(|path| !file_hashes.contains_key(*path))
+            .collect();
+        let summary = format!("{} files changed, {} removed", changed.len(), removed.len());
+
+        // Contexts indexed before hashes were recorded are indexed again entirely
+        let reindex_all = context.file_hashes.is_empty();
+        if changed.is_empty() && removed.is_empty() && !reindex_all {
+            return Ok(summary);
+        }
+
+        // Extract and embed the new content before locking the context, so it stays searchable
+        let mut items = Vec::new();
+        for (i, path) in changed.iter().enumerate() {
+            if cancel_token.is_cancelled() {
+                return Err("Operation was cancelled during file processing".to_string());
+            }
+
+            if let Ok(mut file_items) = process_file(Path::new(path.as_str())) {
+                items.append(&mut file_items);
+            }
+
+            self.update_operation_progress(
+                operation_id,
+                (i + 1) as u64,
+                changed.len() as u64,
+                format!("Indexing changed files ({}/{})", i + 1, changed.len()),
+            )
+            .await;
+        }
+
+        let mut data_points = Vec::with_capacity(items.len());
+        for (i, item) in items.iter().enumerate() {
+            if cancel_token.is_cancelled() {
+                return Err("Operation was cancelled during embedding generation".to_string());
+            }
+
+            if i % 10 == 0 {
+                self.update_operation_progress(
+                    operation_id,
+                    i as u64,
+                    items.len() as u64,
+                    format!("Generating embeddings ({}/{})", i, items.len()),
+                )
+                .await;
+            }
+
+            let data_point = Self::create_data_point_from_item(item, i, &*self.embedder)
+                .map_err(|e| format!("Failed to create data point: {}", e))?;
+            data_points.push(data_point);
+        }
+
+        if cancel_token.is_cancelled() {
+            return Err("Operation was cancelled before updating the index".to_string());
+        }
+
+        self.update_operation_status(operation_id, "Updating vector index...".to_string())
+            .await;
+        {
+            let mut semantic_context = semantic_context.lock().await;
+
+            let mut stale: HashSet<String> = changed.into_iter().chain(removed).cloned().collect();
+            if reindex_all {
+                stale.extend(
+                    semantic_context
+                        .get_data_points()
+                        .iter()
+                        .filter_map(|point| point.payload.get("path").and_then(|v| v.as_str()))
+                        .map(str::to_string),
+                );
+            }
+            semantic_context
+                .remove_data_points_by_paths(&stale)
+                .map_err(|e| format!("Failed to remove data points: {}", e))?;
+
+            let start = semantic_context.get_data_points().len();
+            for (i, data_point) in data_points.iter_mut().enumerate() {
+                data_point.id = start + i;
+            }
+            semantic_context
+                .add_data_points(data_points)
+                .map_err(|e| format!("Failed to add data points: {}", e))?;
+
+            if context.persistent {
+                semantic_context
+                    .save()
+                    .map_err(|e| format!("Failed to save context: {}", e))?;
+            }
+        }
+
+        // Update the context metadata
+        {
+            let mut contexts = self.contexts.write().await;
+            if let Some(context) = contexts.get_mut(context_id) {
+                context.item_count = file_hashes.len();
+                context.file_hashes = file_hashes;
+                context.updated_at = chrono::Utc::now();
+            }
+        }
+        if context.persistent {
+            self.save_contexts_metadata().await?;
+        }
+
+        Ok(summary)
+    }
+
     async fn process_clear(&self, operation_id: Uuid, cancel_token: CancellationToken) {
         tracing::info!("Processing Clear job");
 
@@ -1170,9 +1547,11 @@ impl BackgroundWorker {
         source_path: Option<String>,
         semantic_context: SemanticContext,
         item_count: usize,
+        file_hashes: HashMap<String, String>,
+        filter: IndexingFilter,
     ) -> std::result::Result<(), String> {
         // Create the context metadata
-        let context = KnowledgeContext::new(
+        let mut context = KnowledgeContext::new(
             context_id.to_string(),
             name,
             description,
@@ -1180,6 +1559,8 @@ impl BackgroundWorker {
             source_path,
             item_count,
         );
+        context.file_hashes = file_hashes;
+        context.filter = filter;
 
         // Store in contexts map
         {
@@ -1205,32 +1586,21 @@ impl BackgroundWorker {
     async fn count_files_in_directory(
         &self,
         dir_path: &Path,
+        filter: &IndexingFilter,
         operation_id: Uuid,
     ) -> std::result::Result<usize, String> {
         self.update_operation_status(operation_id, "Counting files...".to_string())
             .await;
 
-        // Use tokio::task::spawn_blocking to make the synchronous walkdir operation non-blocking
-        let dir_path = dir_path.to_path_buf();
+        // Use tokio::task::spawn_blocking to make the synchronous walk non-blocking
+        let files = walk_files(dir_path, filter).map_err(|e| e.to_string())?;
         let active_operations = self.active_operations.clone();
 
         let count_result = tokio::task::spawn_blocking(move || {
             let mut count = 0;
             let mut checked = 0;
 
-            for _entry in walkdir::WalkDir::new(&dir_path)
-                .follow_links(true)
-                .into_iter()
-                .filter_map(|e| e.ok())
-                .filter(|e| e.file_type().is_file())
-                .filter(|e| {
-                    // Skip hidden files
-                    !e.path()
-                        .file_name()
-                        .and_then(|n| n.to_str())
-                        .is_some_and(|s| s.starts_with('.'))
-                })
-            {
+            for _path in files {
                 count += 1;
                 checked += 1;
 
@@ -1268,13 +1638,49 @@ impl BackgroundWorker {
         }
     }
 
+    /// Hash the files of a directory like they are indexed, by path
+    async fn hash_directory_files(
+        dir_path: &Path,
+        filter: &IndexingFilter,
+        cancel_token: &CancellationToken,
+    ) -> std::result::Result<HashMap<String, String>, String> {
+        let dir_path = dir_path.to_path_buf();
+        let filter = filter.clone();
+        let cancel_token = cancel_token.clone();
+
+        // Walking and hashing read the whole directory, so both run on a blocking thread
+        let hash_result = tokio::task::spawn_blocking(move || {
+            let mut file_hashes = HashMap::new();
+
+            for path in walk_files(&dir_path, &filter).map_err(|e| e.to_string())? {
+                if cancel_token.is_cancelled() {
+                    return Err("Operation cancelled while checking for changes".to_string());
+                }
+
+                // Files that can't be read are treated as removed
+                if let Ok(hash) = utils::hash_file(&path) {
+                    file_hashes.insert(path.to_string_lossy().to_string(), hash);
+                }
+            }
+
+            Ok(file_hashes)
+        })
+        .await;
+
+        match hash_result {
+            Ok(result) => result,
+            Err(e) => Err(format!("File hashing task failed: {}", e)),
+        }
+    }
+
     async fn process_directory_files(
         &self,
         dir_path: &Path,
+        filter: &IndexingFilter,
         file_count: usize,
         operation_id: Uuid,
         cancel_token: &CancellationToken,
-    ) -> std::result::Result<Vec<serde_json::Value>, String> {
+    ) -> std::result::Result<(Vec<serde_json::Value>, HashMap<String, String>), String> {
         use crate::processing::process_file;
 
         self.update_operation_status(operation_id, format!("Starting indexing ({} files)", file_count))
@@ -1282,31 +1688,21 @@ impl BackgroundWorker {
 
         let mut processed_files = 0;
         let mut items = Vec::new();
+        let mut file_hashes = HashMap::new();
 
-        for entry in walkdir::WalkDir::new(dir_path)
-            .follow_links(true)
-            .into_iter()
-            .filter_map(|e| e.ok())
-            .filter(|e| e.file_type().is_file())
-        {
+        for path in walk_files(dir_path, filter).map_err(|e| e.to_string())? {
             // Check for cancellation frequently
             if cancel_token.is_cancelled() {
                 return Err("Operation was cancelled during file processing".to_string());
             }
 
-            let path = entry.path();
-
-            // Skip hidden files
-            if path
-                .file_name()
-                .and_then(|n| n.to_str())
-                .is_some_and(|s| s.starts_with('.'))
-            {
-                continue;
+            // Record the content of the file, so updates can skip it while it is unchanged
+            if let Ok(hash) = utils::hash_file(&path) {
+                file_hashes.insert(path.to_string_lossy().to_string(), hash);
             }
 
             // Process the file
-            match process_file(path) {
+            match process_file(&path) {
                 Ok(mut file_items) => items.append(&mut file_items),
                 Err(_) => continue, // Skip files that fail to process
             }
@@ -1325,7 +1721,7 @@ impl BackgroundWorker {
             }
         }
 
-        Ok(items)
+        Ok((items, file_hashes))
     }
 
     async fn create_semantic_context_impl(
@@ -1344,7 +1740,7 @@ impl BackgroundWorker {
             return Err("Operation was cancelled during semantic context creation".to_string());
         }
 
-        let mut semantic_context = SemanticContext::new(context_dir.join("data.json"))
+        let mut semantic_context = SemanticContext::new(context_dir.join(storage::DATA_FILE_NAME))
             .map_err(|e| format!("Failed to create semantic context: {}", e))?;
 
         // Process items to data points with cancellation checks
@@ -1430,3 +1826,105 @@ impl BackgroundWorker {
             .map_err(|e| format!("Failed to save contexts metadata: {}", e))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::embedding::MockTextEmbedder;
+
+    fn worker(base_dir: &Path) -> BackgroundWorker {
+        crate::config::init_config(base_dir).unwrap();
+        let (_job_tx, job_rx) = mpsc::unbounded_channel();
+        BackgroundWorker {
+            job_rx,
+            contexts: Arc::default(),
+            volatile_contexts: Arc::default(),
+            active_operations: Arc::default(),
+            embedder: Box::new(MockTextEmbedder::new(384)),
+            config: SemanticSearchConfig::default(),
+            base_dir: base_dir.to_path_buf(),
+            indexing_semaphore: Arc::new(Semaphore::new(MAX_CONCURRENT_OPERATIONS)),
+        }
+    }
+
+    /// The file name and text of the data points of a context, checking that they are numbered
+    /// by position
+    async fn indexed_texts(worker: &BackgroundWorker, context_id: &str) -> Vec<(String, String)> {
+        let context = worker.volatile_contexts.read().await[context_id].clone();
+        let context = context.lock().await;
+        let mut texts: Vec<(String, String)> = context
+            .get_data_points()
+            .iter()
+            .enumerate()
+            .map(|(i, point)| {
+                assert_eq!(point.id, i);
+                let path = Path::new(point.payload["path"].as_str().unwrap());
+                let text = point.payload["text"].as_str().unwrap();
+                (
+                    path.file_name().unwrap().to_string_lossy().to_string(),
+                    text.trim().to_string(),
+                )
+            })
+            .collect();
+        texts.sort();
+        texts
+    }
+
+    #[tokio::test]
+    async fn test_perform_update() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("source");
+        fs::create_dir_all(&source).unwrap();
+        fs::write(source.join("a.txt"), "alpha").unwrap();
+        fs::write(source.join("b.txt"), "beta").unwrap();
+        fs::write(source.join("c.txt"), "gamma").unwrap();
+
+        let worker = worker(&temp_dir.path().join("base"));
+        worker
+            .perform_indexing(
+                Uuid::new_v4(),
+                source.clone(),
+                "source".to_string(),
+                String::new(),
+                false,
+                IndexingFilter::default(),
+                CancellationToken::new(),
+            )
+            .await
+            .unwrap();
+        let context_id = worker.contexts.read().await.keys().next().unwrap().clone();
+        let update = || async {
+            worker
+                .perform_update(Uuid::new_v4(), &context_id, &CancellationToken::new())
+                .await
+                .unwrap()
+        };
+
+        assert_eq!(update().await, "0 files changed, 0 removed");
+
+        // A modified, a deleted and an added file
+        fs::write(source.join("b.txt"), "beta, modified").unwrap();
+        fs::remove_file(source.join("c.txt")).unwrap();
+        fs::write(source.join("d.txt"), "delta").unwrap();
+        assert_eq!(update().await, "2 files changed, 1 removed");
+
+        assert_eq!(indexed_texts(&worker, &context_id).await, vec![
+            ("a.txt".to_string(), "alpha".to_string()),
+            ("b.txt".to_string(), "beta, modified".to_string()),
+            ("d.txt".to_string(), "delta".to_string()),
+        ]);
+        let contexts = worker.contexts.read().await;
+        let mut hashed: Vec<&str> = contexts[&context_id]
+            .file_hashes
+            .keys()
+            .map(|path| path.rsplit(std::path::MAIN_SEPARATOR).next().unwrap())
+            .collect();
+        hashed.sort_unstable();
+        assert_eq!(hashed, vec!["a.txt", "b.txt", "d.txt"]);
+        assert_eq!(contexts[&context_id].item_count, 3);
+    }
+}
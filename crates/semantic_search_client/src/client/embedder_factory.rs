@@ -1,13 +1,19 @@
-#[cfg(not(all(target_os = "linux", target_arch = "aarch64")))]
-use crate::embedding::CandleTextEmbedder;
 #[cfg(test)]
 use crate::embedding::MockTextEmbedder;
 use crate::embedding::{
     BM25TextEmbedder,
     EmbeddingType,
+    RerankerTrait,
     TextEmbedderTrait,
 };
+#[cfg(not(all(target_os = "linux", target_arch = "aarch64")))]
+use crate::embedding::{
+    CandleTextEmbedder,
+    CrossEncoderReranker,
+};
 use crate::error::Result;
+#[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+use crate::error::SemanticSearchError;
 
 /// Creates a text embedder based on the specified embedding type
 ///
@@ -53,3 +59,26 @@ pub fn create_embedder(embedding_type: EmbeddingType) -> Result<Box<dyn TextEmbe
 
     Ok(embedder)
 }
+
+/// Creates the cross-encoder reranker of search results
+///
+/// # Returns
+///
+/// A reranker instance, or an error on platforms without Candle support
+#[cfg(not(all(target_os = "linux", target_arch = "aarch64")))]
+pub fn create_reranker() -> Result<Box<dyn RerankerTrait>> {
+    Ok(Box::new(CrossEncoderReranker::new()?))
+}
+
+/// Creates the cross-encoder reranker of search results
+/// (Linux ARM version)
+///
+/// # Returns
+///
+/// An error, since reranking requires Candle
+#[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+pub fn create_reranker() -> Result<Box<dyn RerankerTrait>> {
+    Err(SemanticSearchError::EmbeddingError(
+        "Reranking is not supported on this platform".to_string(),
+    ))
+}
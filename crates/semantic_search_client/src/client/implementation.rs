@@ -14,6 +14,7 @@ use serde_json::Value;
 use crate::client::semantic_context::SemanticContext;
 use crate::client::{
     embedder_factory,
+    storage,
     utils,
 };
 use crate::config;
@@ -25,11 +26,15 @@ use crate::error::{
     Result,
     SemanticSearchError,
 };
-use crate::processing::process_file;
+use crate::processing::{
+    process_file,
+    walk_files,
+};
 use crate::types::{
     ContextId,
     ContextMap,
     DataPoint,
+    IndexingFilter,
     KnowledgeContext,
     ProgressStatus,
     SearchResults,
@@ -446,7 +451,7 @@ impl SemanticSearchClient {
     where
         F: Fn(ProgressStatus) + Send + 'static,
     {
-        utils::count_files_in_directory(dir_path, progress_callback)
+        utils::count_files_in_directory(dir_path, &IndexingFilter::default(), progress_callback)
     }
 
     /// Process files in a directory
@@ -467,25 +472,9 @@ impl SemanticSearchClient {
         let mut processed_files = 0;
         let mut items = Vec::new();
 
-        for entry in walkdir::WalkDir::new(dir_path)
-            .follow_links(true)
-            .into_iter()
-            .filter_map(|e| e.ok())
-            .filter(|e| e.file_type().is_file())
-        {
-            let path = entry.path();
-
-            // Skip hidden files
-            if path
-                .file_name()
-                .and_then(|n| n.to_str())
-                .is_some_and(|s| s.starts_with('.'))
-            {
-                continue;
-            }
-
+        for path in walk_files(dir_path, &IndexingFilter::default())? {
             // Process the file
-            match process_file(path) {
+            match process_file(&path) {
                 Ok(mut file_items) => items.append(&mut file_items),
                 Err(_) => continue, // Skip files that fail to process
             }
@@ -517,7 +506,7 @@ impl SemanticSearchClient {
         }
 
         // Create a new semantic context
-        let mut semantic_context = SemanticContext::new(context_dir.join("data.json"))?;
+        let mut semantic_context = SemanticContext::new(context_dir.join(storage::DATA_FILE_NAME))?;
 
         // Process items to data points
         let data_points = self.process_items_to_data_points(items, progress_callback)?;
@@ -680,7 +669,7 @@ impl SemanticSearchClient {
         let context_dir = self.create_context_directory(&context_id, is_persistent)?;
 
         // Create a new semantic context
-        let mut semantic_context = SemanticContext::new(context_dir.join("data.json"))?;
+        let mut semantic_context = SemanticContext::new(context_dir.join(storage::DATA_FILE_NAME))?;
 
         // Create a data point from the text
         let data_point = self.create_data_point_from_text(text, 0)?;
@@ -898,8 +887,8 @@ impl SemanticSearchClient {
             .map_err(|e| SemanticSearchError::OperationFailed(format!("Failed to acquire lock on context: {}", e)))?;
 
         // Save the data to the persistent directory
-        let data_path = persistent_dir.join("data.json");
-        utils::save_json_to_file(&data_path, context_guard.get_data_points())?;
+        let data_path = persistent_dir.join(storage::DATA_FILE_NAME);
+        storage::write_data_points(&data_path, context_guard.get_data_points())?;
 
         // Create the context metadata
         let context_meta = KnowledgeContext::new(
@@ -1071,7 +1060,7 @@ impl SemanticSearchClient {
         }
 
         // Create a new semantic context
-        let semantic_context = SemanticContext::new(context_dir.join("data.json"))?;
+        let semantic_context = SemanticContext::new(context_dir.join(storage::DATA_FILE_NAME))?;
 
         // Store the semantic context
         self.volatile_contexts
@@ -6,10 +6,15 @@ pub mod embedder_factory;
 mod implementation;
 /// Semantic context implementation for search operations
 pub mod semantic_context;
+/// Binary storage of the data points of semantic contexts
+pub mod storage;
 /// Utility functions for semantic search operations
 pub mod utils;
+/// File watcher keeping contexts in sync with their source paths
+mod watcher;
 
 // Re-export types for external use
 pub use async_implementation::AsyncSemanticSearchClient;
 pub use implementation::SemanticSearchClient;
 pub use semantic_context::SemanticContext;
+pub use watcher::ContextWatcher;
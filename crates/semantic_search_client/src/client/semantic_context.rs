@@ -1,32 +1,45 @@
-use std::fs::{
-    self,
-    File,
-};
-use std::io::{
-    BufReader,
-    BufWriter,
-};
+use std::collections::HashSet;
+use std::fs;
 use std::path::PathBuf;
 
+use crate::client::storage;
 use crate::error::Result;
-use crate::index::VectorIndex;
+use crate::index::{
+    LexicalIndex,
+    RRF_K,
+    VectorIndex,
+    reciprocal_rank_fusion,
+};
 use crate::types::{
     DataPoint,
+    SearchMode,
     SearchResult,
 };
 
+/// Number of candidates each ranking contributes per requested result in hybrid search
+const HYBRID_CANDIDATES_FACTOR: usize = 4;
+
 /// A semantic context containing data points and a vector index
 pub struct SemanticContext {
     /// The data points stored in the index
     pub(crate) data_points: Vec<DataPoint>,
     /// The vector index for fast approximate nearest neighbor search
     index: Option<VectorIndex>,
+    /// The BM25 index for exact term matches
+    lexical_index: Option<LexicalIndex>,
     /// Path to save/load the data points
     data_path: PathBuf,
 }
 
 impl SemanticContext {
     /// Create a new semantic context
+    ///
+    /// Data points stored as JSON next to the data file by previous versions are migrated to the
+    /// binary format.
+    ///
+    /// The vector index saved next to the data file is loaded when it holds the vectors of the
+    /// data points, and rebuilt otherwise. The lexical index is rebuilt each time a context is
+    /// loaded.
     pub fn new(data_path: PathBuf) -> Result<Self> {
         // Create the directory if it doesn't exist
         if let Some(parent) = data_path.parent() {
@@ -37,32 +50,90 @@ impl SemanticContext {
         let mut context = Self {
             data_points: Vec::new(),
             index: None,
+            lexical_index: None,
             data_path: data_path.clone(),
         };
 
         // Load data points if the file exists
+        let legacy_path = data_path.with_file_name(storage::LEGACY_DATA_FILE_NAME);
         if data_path.exists() {
-            let file = File::open(&data_path)?;
-            let reader = BufReader::new(file);
-            context.data_points = serde_json::from_reader(reader)?;
+            context.data_points = storage::read_data_points(&data_path)?;
+        } else if legacy_path != data_path && legacy_path.exists() {
+            tracing::info!("Migrating {} to the binary format", legacy_path.display());
+            context.data_points = storage::read_legacy_data_points(&legacy_path)?;
+            context.save()?;
+            fs::remove_file(&legacy_path)?;
         }
 
-        // If we have data points, rebuild the index
         if !context.data_points.is_empty() {
-            context.rebuild_index()?;
+            match context.load_vector_index() {
+                Some(index) => {
+                    context.index = Some(index);
+                    context.rebuild_lexical_index();
+                },
+                None => {
+                    context.rebuild_index()?;
+                    // Save the index of contexts saved without one, so it is loaded next time
+                    if let Err(err) = context.save_vector_index() {
+                        tracing::warn!("Failed to save the vector index: {}", err);
+                    }
+                },
+            }
         }
 
         Ok(context)
     }
 
-    /// Save data points to disk
+    /// Save data points and the vector index to disk
     pub fn save(&self) -> Result<()> {
-        // Save the data points as JSON
-        let file = File::create(&self.data_path)?;
-        let writer = BufWriter::new(file);
-        serde_json::to_writer(writer, &self.data_points)?;
+        storage::write_data_points(&self.data_path, &self.data_points)?;
+        self.save_vector_index()
+    }
 
-        Ok(())
+    /// Save the vector index next to the data file, or remove the saved one if there is no index
+    fn save_vector_index(&self) -> Result<()> {
+        let Some(directory) = self.data_path.parent() else {
+            return Ok(());
+        };
+        match &self.index {
+            Some(index) if !index.is_empty() => index.save(directory, storage::INDEX_BASENAME),
+            _ => {
+                for extension in ["hnsw.graph", "hnsw.data", "hnsw.ids"] {
+                    let path = directory.join(format!("{}.{}", storage::INDEX_BASENAME, extension));
+                    if path.exists() {
+                        fs::remove_file(path)?;
+                    }
+                }
+                Ok(())
+            },
+        }
+    }
+
+    /// Load the vector index saved next to the data file, if it holds the vectors of the data
+    /// points
+    fn load_vector_index(&self) -> Option<VectorIndex> {
+        let directory = self.data_path.parent()?;
+        if !directory
+            .join(format!("{}.hnsw.graph", storage::INDEX_BASENAME))
+            .exists()
+        {
+            return None;
+        }
+        let index = match VectorIndex::load(directory, storage::INDEX_BASENAME) {
+            Ok(index) => index,
+            Err(err) => {
+                tracing::warn!("Rebuilding the vector index of {}: {}", self.data_path.display(), err);
+                return None;
+            },
+        };
+
+        // The data file may have been saved without the index, by an interrupted save
+        let vectors: Vec<&[f32]> = self.data_points.iter().map(|point| point.vector.as_slice()).collect();
+        if !index.matches(&vectors) {
+            tracing::info!("Rebuilding the outdated vector index of {}", self.data_path.display());
+            return None;
+        }
+        Some(index)
     }
 
     /// Rebuild the index from the current data points
@@ -71,16 +142,31 @@ impl SemanticContext {
         let index = VectorIndex::new(self.data_points.len().max(100));
 
         // Add all data points to the index
-        for (i, point) in self.data_points.iter().enumerate() {
-            index.insert(&point.vector, i);
-        }
+        let vectors: Vec<(&Vec<f32>, usize)> = self
+            .data_points
+            .iter()
+            .enumerate()
+            .map(|(i, point)| (&point.vector, i))
+            .collect();
+        index.insert_batch(&vectors);
 
         // Set the new index
         self.index = Some(index);
+        self.rebuild_lexical_index();
 
         Ok(())
     }
 
+    /// Fit the lexical index to the texts of the current data points
+    fn rebuild_lexical_index(&mut self) {
+        let texts: Vec<&str> = self.data_points.iter().map(point_text).collect();
+        let mut lexical_index = LexicalIndex::new(&texts);
+        for (i, text) in texts.into_iter().enumerate() {
+            lexical_index.insert(text, i);
+        }
+        self.lexical_index = Some(lexical_index);
+    }
+
     /// Add data points to the context
     pub fn add_data_points(&mut self, data_points: Vec<DataPoint>) -> Result<usize> {
         // Store the count before extending the data points
@@ -101,6 +187,55 @@ impl SemanticContext {
         Ok(count)
     }
 
+    /// Remove the data points extracted from the given files
+    ///
+    /// The remaining data points are renumbered. Removed vectors are only dropped from the vector
+    /// index once they outnumber the remaining ones, when it is rebuilt.
+    ///
+    /// # Arguments
+    ///
+    /// * `paths` - Paths of the files, as stored in the `path` payload of the data points
+    ///
+    /// # Returns
+    ///
+    /// The number of data points removed
+    pub fn remove_data_points_by_paths(&mut self, paths: &HashSet<String>) -> Result<usize> {
+        let mut new_ids = Vec::with_capacity(self.data_points.len());
+        let mut kept = 0;
+        for point in &self.data_points {
+            let removed = point
+                .payload
+                .get("path")
+                .and_then(|v| v.as_str())
+                .is_some_and(|path| paths.contains(path));
+            new_ids.push((!removed).then(|| {
+                kept += 1;
+                kept - 1
+            }));
+        }
+
+        let removed = self.data_points.len() - kept;
+        if removed == 0 {
+            return Ok(0);
+        }
+
+        let mut ids = new_ids.iter();
+        self.data_points.retain(|_| ids.next().is_some_and(Option::is_some));
+        for (i, point) in self.data_points.iter_mut().enumerate() {
+            point.id = i;
+        }
+
+        match self.index.as_mut() {
+            Some(index) if index.removed_len() + removed <= kept => {
+                index.renumber(&new_ids);
+                self.rebuild_lexical_index();
+            },
+            _ => self.rebuild_index()?,
+        }
+
+        Ok(removed)
+    }
+
     /// Update the index with data points in a specific range
     pub fn update_index_by_range(&mut self, start_idx: usize, end_idx: usize) -> Result<()> {
         // If we don't have an index yet, or if the index is small and we're adding many points,
@@ -118,6 +253,12 @@ impl SemanticContext {
             index.insert(&self.data_points[i].vector, i);
         }
 
+        if let Some(lexical_index) = self.lexical_index.as_mut() {
+            for i in start_idx..end_idx {
+                lexical_index.insert(point_text(&self.data_points[i]), i);
+            }
+        }
+
         Ok(())
     }
 
@@ -143,8 +284,184 @@ impl SemanticContext {
         Ok(search_results)
     }
 
+    /// Search for the items sharing terms with the given query text
+    ///
+    /// The distance of the results decreases as their BM25 score increases.
+    pub fn search_lexical(&self, query_text: &str, limit: usize) -> Result<Vec<SearchResult>> {
+        let lexical_index = match &self.lexical_index {
+            Some(idx) => idx,
+            None => return Ok(Vec::new()),
+        };
+
+        let search_results = lexical_index
+            .search(query_text, limit)
+            .into_iter()
+            .map(|(id, score)| SearchResult::new(self.data_points[id].clone(), score_to_distance(score)))
+            .collect();
+
+        Ok(search_results)
+    }
+
+    /// Search with both the vector and the lexical index, fusing the two rankings
+    ///
+    /// The distance of the results decreases as their fused score increases, and is only
+    /// comparable with the distance of other hybrid results.
+    pub fn search_hybrid(&self, query_text: &str, query_vector: &[f32], limit: usize) -> Result<Vec<SearchResult>> {
+        let (index, lexical_index) = match (&self.index, &self.lexical_index) {
+            (Some(index), Some(lexical_index)) => (index, lexical_index),
+            _ => return Ok(Vec::new()),
+        };
+
+        let candidates = limit.saturating_mul(HYBRID_CANDIDATES_FACTOR);
+        let rankings = [
+            index
+                .search(query_vector, candidates, 100)
+                .into_iter()
+                .map(|(id, _)| id)
+                .collect(),
+            lexical_index
+                .search(query_text, candidates)
+                .into_iter()
+                .map(|(id, _)| id)
+                .collect(),
+        ];
+
+        let search_results = reciprocal_rank_fusion(&rankings, RRF_K)
+            .into_iter()
+            .take(limit)
+            .map(|(id, score)| SearchResult::new(self.data_points[id].clone(), score_to_distance(score)))
+            .collect();
+
+        Ok(search_results)
+    }
+
+    /// Search with the given retrieval strategy
+    pub fn search_with_mode(
+        &self,
+        mode: SearchMode,
+        query_text: &str,
+        query_vector: &[f32],
+        limit: usize,
+    ) -> Result<Vec<SearchResult>> {
+        match mode {
+            SearchMode::Vector => self.search(query_vector, limit),
+            SearchMode::Lexical => self.search_lexical(query_text, limit),
+            SearchMode::Hybrid => self.search_hybrid(query_text, query_vector, limit),
+        }
+    }
+
     /// Get the data points for serialization
     pub fn get_data_points(&self) -> &Vec<DataPoint> {
         &self.data_points
     }
 }
+
+/// Get the text a data point was embedded from
+fn point_text(point: &DataPoint) -> &str {
+    point.payload.get("text").and_then(|v| v.as_str()).unwrap_or_default()
+}
+
+/// Map a relevance score, higher is better, to a distance, lower is better
+fn score_to_distance(score: f32) -> f32 {
+    1.0 / (1.0 + score)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use tempfile::TempDir;
+
+    use super::*;
+
+    fn data_points(count: usize, dimension: usize) -> Vec<DataPoint> {
+        (0..count)
+            .map(|id| DataPoint {
+                id,
+                payload: HashMap::from([("text".to_string(), serde_json::Value::String(format!("point {}", id)))]),
+                vector: (0..dimension).map(|i| ((id * 31 + i * 17) % 97) as f32 + 1.0).collect(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_load_saved_vector_index() {
+        let temp_dir = TempDir::new().unwrap();
+        let data_path = temp_dir.path().join(storage::DATA_FILE_NAME);
+
+        let mut context = SemanticContext::new(data_path.clone()).unwrap();
+        context.add_data_points(data_points(300, 16)).unwrap();
+        context.save().unwrap();
+        let layers = context.index.as_ref().unwrap().point_layers();
+
+        // HNSW draws the layers of the points at random, so a rebuilt graph would differ
+        let loaded = SemanticContext::new(data_path.clone()).unwrap();
+        assert_eq!(loaded.index.as_ref().unwrap().point_layers(), layers);
+        let query = &loaded.data_points[42].vector;
+        assert_eq!(loaded.search(query, 1).unwrap()[0].point.id, 42);
+        assert_eq!(loaded.search_lexical("42", 1).unwrap()[0].point.id, 42);
+
+        // A corrupt index is rebuilt from the same data points
+        fs::write(temp_dir.path().join("index.hnsw.graph"), b"not a graph").unwrap();
+        let rebuilt = SemanticContext::new(data_path.clone()).unwrap();
+        assert_eq!(rebuilt.index.as_ref().unwrap().len(), 300);
+        assert_ne!(rebuilt.index.as_ref().unwrap().point_layers(), layers);
+
+        // So is an index which doesn't hold the vectors of the data file
+        storage::write_data_points(&data_path, &data_points(200, 16)).unwrap();
+        let rebuilt = SemanticContext::new(data_path.clone()).unwrap();
+        assert_eq!(rebuilt.index.as_ref().unwrap().len(), 200);
+
+        // Empty contexts don't keep an index
+        let mut empty = SemanticContext::new(data_path).unwrap();
+        empty.data_points.clear();
+        empty.index = None;
+        empty.save().unwrap();
+        assert!(!temp_dir.path().join("index.hnsw.graph").exists());
+    }
+
+    #[test]
+    fn test_remove_data_points_keeps_the_vector_index() {
+        let temp_dir = TempDir::new().unwrap();
+        let data_path = temp_dir.path().join(storage::DATA_FILE_NAME);
+
+        // Every tenth point comes from a.txt
+        let mut points = data_points(300, 16);
+        for point in &mut points {
+            let path = if point.id % 10 == 0 { "a.txt" } else { "b.txt" };
+            point.payload.insert("path".to_string(), serde_json::Value::from(path));
+        }
+        let mut context = SemanticContext::new(data_path.clone()).unwrap();
+        context.add_data_points(points.clone()).unwrap();
+        let layers = context.index.as_ref().unwrap().point_layers();
+
+        // The removed vectors are skipped rather than rebuilt, and the others are renumbered
+        let removed = context
+            .remove_data_points_by_paths(&HashSet::from(["a.txt".to_string()]))
+            .unwrap();
+        assert_eq!(removed, 30);
+        let index = context.index.as_ref().unwrap();
+        assert_eq!((index.len(), index.removed_len()), (270, 30));
+        assert_eq!(index.point_layers(), layers);
+        let results = context.search(&points[10].vector, 300).unwrap();
+        assert_eq!(results.len(), 270);
+        assert!(results.iter().all(|result| result.point.payload["path"] == "b.txt"));
+        let query = &points[42].vector;
+        assert_eq!(context.search(query, 1).unwrap()[0].point.id, 42 - 5);
+
+        // So is the saved index
+        context.save().unwrap();
+        let mut loaded = SemanticContext::new(data_path).unwrap();
+        let index = loaded.index.as_ref().unwrap();
+        assert_eq!((index.len(), index.removed_len()), (270, 30));
+        assert_eq!(index.point_layers(), layers);
+        assert_eq!(loaded.search(query, 1).unwrap()[0].point.id, 42 - 5);
+
+        // The index is rebuilt once most of its vectors were removed
+        loaded
+            .remove_data_points_by_paths(&HashSet::from(["b.txt".to_string()]))
+            .unwrap();
+        assert!(loaded.data_points.is_empty());
+        assert_eq!(loaded.index.as_ref().unwrap().removed_len(), 0);
+    }
+}
@@ -0,0 +1,297 @@
+use std::collections::HashMap;
+use std::fs::{
+    self,
+    File,
+};
+use std::io::{
+    BufReader,
+    BufWriter,
+    Read,
+    Write,
+};
+use std::path::Path;
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::error::{
+    Result,
+    SemanticSearchError,
+};
+use crate::types::DataPoint;
+
+/// Name of the file holding the data points of a context
+pub const DATA_FILE_NAME: &str = "data.bin";
+
+/// Name of the JSON file data points were stored in before the binary format
+pub const LEGACY_DATA_FILE_NAME: &str = "data.json";
+
+/// Name the files holding the vector index of a context start with, `index.hnsw.graph` for the
+/// HNSW graph and `index.hnsw.data` for its vectors
+pub const INDEX_BASENAME: &str = "index";
+
+/// Bytes starting every data file
+const MAGIC: &[u8; 4] = b"QSEM";
+
+/// Version of the data file layout, increased on incompatible changes
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Size of the header fields preceding the body of a data file: magic, version, count and dimension
+const HEADER_LEN: u64 = 4 + 4 + 8 + 4;
+
+/// Metadata of a data point, stored apart from its vector
+#[derive(Serialize)]
+struct PointMetadataRef<'a> {
+    id: usize,
+    payload: &'a HashMap<String, serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct PointMetadata {
+    id: usize,
+    payload: HashMap<String, serde_json::Value>,
+}
+
+/// Write data points to a binary data file
+///
+/// The file starts with a header holding the format version, the number of points and the
+/// dimension of their vectors. It is followed by all the vectors as little-endian f32 arrays,
+/// then by a JSON table of the point IDs and payloads. The file is replaced atomically.
+///
+/// # Arguments
+///
+/// * `path` - Path to the data file
+/// * `data_points` - The data points to write, all with vectors of the same dimension
+pub fn write_data_points(path: &Path, data_points: &[DataPoint]) -> Result<()> {
+    let dimension = data_points.first().map_or(0, |point| point.vector.len());
+    if data_points.iter().any(|point| point.vector.len() != dimension) {
+        return Err(SemanticSearchError::InvalidArgument(
+            "All data points must have vectors of the same dimension".to_string(),
+        ));
+    }
+
+    let metadata: Vec<PointMetadataRef<'_>> = data_points
+        .iter()
+        .map(|point| PointMetadataRef {
+            id: point.id,
+            payload: &point.payload,
+        })
+        .collect();
+    let metadata = serde_json::to_vec(&metadata)?;
+
+    let temp_path = path.with_extension("tmp");
+    {
+        let mut writer = BufWriter::new(File::create(&temp_path)?);
+        writer.write_all(MAGIC)?;
+        writer.write_all(&FORMAT_VERSION.to_le_bytes())?;
+        writer.write_all(&(data_points.len() as u64).to_le_bytes())?;
+        writer.write_all(&(dimension as u32).to_le_bytes())?;
+        for point in data_points {
+            for value in &point.vector {
+                writer.write_all(&value.to_le_bytes())?;
+            }
+        }
+        writer.write_all(&(metadata.len() as u64).to_le_bytes())?;
+        writer.write_all(&metadata)?;
+        writer.flush()?;
+    }
+    fs::rename(&temp_path, path)?;
+
+    Ok(())
+}
+
+/// Read data points from a binary data file
+///
+/// # Arguments
+///
+/// * `path` - Path to the data file
+///
+/// # Returns
+///
+/// The data points, or an error if the file is not a data file or was written by a newer version
+pub fn read_data_points(path: &Path) -> Result<Vec<DataPoint>> {
+    let file = File::open(path)?;
+    let file_len = file.metadata()?.len();
+    let mut reader = BufReader::new(file);
+
+    let mut magic = [0; 4];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(SemanticSearchError::SerializationError(format!(
+            "Not a semantic context data file: {}",
+            path.display()
+        )));
+    }
+
+    let version = u32::from_le_bytes(read_array(&mut reader)?);
+    match version {
+        1 => read_v1(&mut reader, file_len.saturating_sub(HEADER_LEN)),
+        _ => Err(SemanticSearchError::SerializationError(format!(
+            "Unsupported data file version {} (latest supported is {})",
+            version, FORMAT_VERSION
+        ))),
+    }
+}
+
+/// Read the body of a version 1 data file
+///
+/// The sizes in the file are checked against the length of its body before allocating, so corrupt
+/// files fail instead of exhausting memory.
+fn read_v1(reader: &mut impl Read, body_len: u64) -> Result<Vec<DataPoint>> {
+    let count = u64::from_le_bytes(read_array(reader)?);
+    let dimension = u32::from_le_bytes(read_array(reader)?);
+
+    let vector_size = u64::from(dimension) * size_of::<f32>() as u64;
+    let vectors_size = count
+        .checked_mul(vector_size)
+        .filter(|&size| size.saturating_add(8) <= body_len)
+        .ok_or_else(|| {
+            SemanticSearchError::SerializationError(format!(
+                "Invalid data file header: {} vectors of dimension {} don't fit in {} bytes",
+                count, dimension, body_len
+            ))
+        })?;
+    let mut vectors = vec![0; vectors_size as usize];
+    reader.read_exact(&mut vectors)?;
+
+    let metadata_len = u64::from_le_bytes(read_array(reader)?);
+    if metadata_len > body_len - vectors_size - 8 {
+        return Err(SemanticSearchError::SerializationError(format!(
+            "Invalid data file: {} bytes of metadata don't fit in the file",
+            metadata_len
+        )));
+    }
+    let mut metadata = vec![0; metadata_len as usize];
+    reader.read_exact(&mut metadata)?;
+    let metadata: Vec<PointMetadata> = serde_json::from_slice(&metadata)?;
+    if metadata.len() as u64 != count {
+        return Err(SemanticSearchError::SerializationError(format!(
+            "Data file holds {} vectors but {} metadata entries",
+            count,
+            metadata.len()
+        )));
+    }
+
+    let vector_size = vector_size as usize;
+    let data_points = metadata
+        .into_iter()
+        .enumerate()
+        .map(|(i, metadata)| DataPoint {
+            id: metadata.id,
+            payload: metadata.payload,
+            vector: vectors[i * vector_size..(i + 1) * vector_size]
+                .chunks_exact(size_of::<f32>())
+                .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+                .collect(),
+        })
+        .collect();
+
+    Ok(data_points)
+}
+
+fn read_array<const N: usize>(reader: &mut impl Read) -> Result<[u8; N]> {
+    let mut bytes = [0; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// Read data points from a legacy JSON data file
+///
+/// # Arguments
+///
+/// * `path` - Path to the JSON file
+///
+/// # Returns
+///
+/// The data points
+pub fn read_legacy_data_points(path: &Path) -> Result<Vec<DataPoint>> {
+    let reader = BufReader::new(File::open(path)?);
+    Ok(serde_json::from_reader(reader)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    fn data_point(id: usize, vector: Vec<f32>) -> DataPoint {
+        DataPoint {
+            id,
+            payload: HashMap::from([("text".to_string(), serde_json::Value::String(format!("point {}", id)))]),
+            vector,
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join(DATA_FILE_NAME);
+
+        let data_points = vec![
+            data_point(0, vec![0.5, -1.0, 3.25]),
+            data_point(1, vec![f32::MIN, 0.0, 1e-7]),
+        ];
+        write_data_points(&path, &data_points).unwrap();
+
+        let loaded = read_data_points(&path).unwrap();
+        assert_eq!(loaded.len(), 2);
+        for (loaded, original) in loaded.iter().zip(&data_points) {
+            assert_eq!(loaded.id, original.id);
+            assert_eq!(loaded.payload, original.payload);
+            assert_eq!(loaded.vector, original.vector);
+        }
+
+        write_data_points(&path, &[]).unwrap();
+        assert!(read_data_points(&path).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_invalid_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join(DATA_FILE_NAME);
+
+        // Vectors must share a dimension
+        let data_points = vec![data_point(0, vec![1.0]), data_point(1, vec![1.0, 2.0])];
+        assert!(write_data_points(&path, &data_points).is_err());
+
+        // JSON is not mistaken for the binary format
+        fs::write(&path, r#"[{"id": 0, "payload": {}, "vector": []}]"#).unwrap();
+        assert!(read_data_points(&path).is_err());
+
+        // Files from a newer version are rejected
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend((FORMAT_VERSION + 1).to_le_bytes());
+        fs::write(&path, bytes).unwrap();
+        let error = read_data_points(&path).unwrap_err();
+        assert!(error.to_string().contains("Unsupported data file version"));
+
+        // Truncated files fail instead of returning partial data
+        write_data_points(&path, &[data_point(0, vec![1.0, 2.0])]).unwrap();
+        let bytes = fs::read(&path).unwrap();
+        fs::write(&path, &bytes[..bytes.len() - 1]).unwrap();
+        assert!(read_data_points(&path).is_err());
+
+        // Huge sizes in a corrupt header are rejected before allocating
+        for (count, dimension) in [(u64::MAX, u32::MAX), (1 << 40, 384)] {
+            let mut bytes = MAGIC.to_vec();
+            bytes.extend(FORMAT_VERSION.to_le_bytes());
+            bytes.extend(count.to_le_bytes());
+            bytes.extend(dimension.to_le_bytes());
+            fs::write(&path, bytes).unwrap();
+            let error = read_data_points(&path).unwrap_err();
+            assert!(error.to_string().contains("Invalid data file header"));
+        }
+
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend(FORMAT_VERSION.to_le_bytes());
+        bytes.extend(0u64.to_le_bytes());
+        bytes.extend(0u32.to_le_bytes());
+        bytes.extend(u64::MAX.to_le_bytes());
+        fs::write(&path, bytes).unwrap();
+        let error = read_data_points(&path).unwrap_err();
+        assert!(error.to_string().contains("metadata don't fit"));
+    }
+}
@@ -4,10 +4,18 @@ use std::path::{
     PathBuf,
 };
 
+use sha2::{
+    Digest,
+    Sha256,
+};
 use uuid::Uuid;
 
 use crate::error::Result;
-use crate::types::ProgressStatus;
+use crate::processing::walk_files;
+use crate::types::{
+    IndexingFilter,
+    ProgressStatus,
+};
 
 /// Create a context directory based on persistence setting
 ///
@@ -49,12 +57,17 @@ pub fn generate_context_id() -> String {
 /// # Arguments
 ///
 /// * `dir_path` - Path to the directory
+/// * `filter` - Selection of the files to count
 /// * `progress_callback` - Optional callback for progress updates
 ///
 /// # Returns
 ///
 /// The number of files found
-pub fn count_files_in_directory<F>(dir_path: &Path, progress_callback: &Option<F>) -> Result<usize>
+pub fn count_files_in_directory<F>(
+    dir_path: &Path,
+    filter: &IndexingFilter,
+    progress_callback: &Option<F>,
+) -> Result<usize>
 where
     F: Fn(ProgressStatus) + Send + 'static,
 {
@@ -64,28 +77,21 @@ where
     }
 
     // Count files first to provide progress information
-    let mut file_count = 0;
-    for entry in walkdir::WalkDir::new(dir_path)
-        .follow_links(true)
-        .into_iter()
-        .filter_map(|e| e.ok())
-        .filter(|e| e.file_type().is_file())
-    {
-        let path = entry.path();
-
-        // Skip hidden files
-        if path
-            .file_name()
-            .and_then(|n| n.to_str())
-            .is_some_and(|s| s.starts_with('.'))
-        {
-            continue;
-        }
-
-        file_count += 1;
-    }
+    Ok(walk_files(dir_path, filter)?.count())
+}
 
-    Ok(file_count)
+/// Hash the content of a file
+///
+/// # Arguments
+///
+/// * `path` - Path to the file
+///
+/// # Returns
+///
+/// The hex-encoded SHA-256 digest of the file content
+pub fn hash_file(path: &Path) -> Result<String> {
+    let content = fs::read(path)?;
+    Ok(format!("{:x}", Sha256::digest(&content)))
 }
 
 /// Save JSON data to a file
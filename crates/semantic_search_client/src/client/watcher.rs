@@ -0,0 +1,366 @@
+use std::collections::{
+    HashMap,
+    HashSet,
+};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use notify::event::ModifyKind;
+use notify::{
+    Event,
+    EventKind,
+    RecommendedWatcher,
+    RecursiveMode,
+    Watcher,
+};
+use tokio::sync::{
+    RwLock,
+    mpsc,
+};
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
+use crate::client::async_implementation::queue_update;
+use crate::processing::WalkMatcher;
+use crate::types::{
+    ContextId,
+    IndexingFilter,
+    IndexingJob,
+    KnowledgeContext,
+    OperationHandle,
+};
+
+/// Time without file events after which the changed contexts are updated
+const DEBOUNCE_DELAY: Duration = Duration::from_secs(2);
+
+/// Interval at which the watched paths are synced with the registered contexts
+const RESYNC_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Names of the files whose changes alter which files are indexed
+const IGNORE_FILE_NAMES: &[&str] = &[".ignore", ".gitignore"];
+
+/// Handle on a background watcher keeping contexts in sync with their source paths
+///
+/// The watcher stops when the handle is dropped.
+pub struct ContextWatcher {
+    cancel_token: CancellationToken,
+}
+
+impl ContextWatcher {
+    /// Start watching the source paths of the contexts
+    ///
+    /// Contexts added or removed later are picked up while the watcher runs.
+    pub(crate) fn start(
+        contexts: Arc<RwLock<HashMap<ContextId, KnowledgeContext>>>,
+        active_operations: Arc<RwLock<HashMap<Uuid, OperationHandle>>>,
+        job_tx: mpsc::UnboundedSender<IndexingJob>,
+    ) -> notify::Result<Self> {
+        let (event_tx, event_rx) = mpsc::unbounded_channel();
+        let watcher = notify::recommended_watcher(move |event| {
+            // The receiver is only gone once the watcher is stopping
+            let _ = event_tx.send(event);
+        })?;
+
+        let cancel_token = CancellationToken::new();
+        let task = WatchTask {
+            watcher,
+            watched: HashMap::new(),
+            sources: HashMap::new(),
+            contexts,
+            active_operations,
+            job_tx,
+        };
+        tokio::spawn(task.run(event_rx, cancel_token.clone()));
+
+        Ok(Self { cancel_token })
+    }
+}
+
+impl Drop for ContextWatcher {
+    fn drop(&mut self) {
+        self.cancel_token.cancel();
+    }
+}
+
+/// Source of a watched context, with the matcher of the files indexed from it
+struct WatchedSource {
+    path: PathBuf,
+    filter: IndexingFilter,
+    matcher: WalkMatcher,
+}
+
+/// Background task translating file events into context updates
+struct WatchTask {
+    watcher: RecommendedWatcher,
+    /// Paths registered with the watcher
+    watched: HashMap<PathBuf, RecursiveMode>,
+    /// Canonical source path and filter of each watched context
+    sources: HashMap<ContextId, WatchedSource>,
+    contexts: Arc<RwLock<HashMap<ContextId, KnowledgeContext>>>,
+    active_operations: Arc<RwLock<HashMap<Uuid, OperationHandle>>>,
+    job_tx: mpsc::UnboundedSender<IndexingJob>,
+}
+
+impl WatchTask {
+    async fn run(
+        mut self,
+        mut events: mpsc::UnboundedReceiver<notify::Result<Event>>,
+        cancel_token: CancellationToken,
+    ) {
+        tracing::info!("Context watcher started");
+
+        let mut resync = tokio::time::interval(RESYNC_INTERVAL);
+        let mut changed: HashSet<ContextId> = HashSet::new();
+        let debounce = tokio::time::sleep(DEBOUNCE_DELAY);
+        tokio::pin!(debounce);
+
+        loop {
+            tokio::select! {
+                () = cancel_token.cancelled() => break,
+                _ = resync.tick() => self.sync_watched_paths().await,
+                event = events.recv() => match event {
+                    Some(Ok(event)) if is_content_change(&event.kind) => {
+                        let affected = affected_contexts(&mut self.sources, &event.paths);
+                        if !affected.is_empty() {
+                            changed.extend(affected);
+                            debounce.as_mut().reset(Instant::now() + DEBOUNCE_DELAY);
+                        }
+                    },
+                    Some(Ok(_)) => {},
+                    Some(Err(e)) => tracing::warn!("File watcher error: {}", e),
+                    None => break,
+                },
+                () = &mut debounce, if !changed.is_empty() => {
+                    for context_id in changed.drain() {
+                        self.update_context(&context_id).await;
+                    }
+                },
+            }
+        }
+
+        tracing::info!("Context watcher stopped");
+    }
+
+    /// Watch the source paths of new contexts and stop watching the ones of removed contexts
+    async fn sync_watched_paths(&mut self) {
+        let source_paths: Vec<(ContextId, String, IndexingFilter)> = {
+            let contexts = self.contexts.read().await;
+            contexts
+                .values()
+                .filter_map(|context| Some((context.id.clone(), context.source_path.clone()?, context.filter.clone())))
+                .collect()
+        };
+
+        let mut sources = HashMap::new();
+        let mut wanted: HashMap<PathBuf, RecursiveMode> = HashMap::new();
+        for (context_id, source_path, filter) in source_paths {
+            let Ok(source_path) = PathBuf::from(source_path).canonicalize() else {
+                continue;
+            };
+
+            // Files are watched through their directory, which survives editors replacing them
+            let (target, mode) = if source_path.is_dir() {
+                (source_path.clone(), RecursiveMode::Recursive)
+            } else {
+                match source_path.parent() {
+                    Some(parent) => (parent.to_path_buf(), RecursiveMode::NonRecursive),
+                    None => continue,
+                }
+            };
+            let entry = wanted.entry(target).or_insert(mode);
+            if mode == RecursiveMode::Recursive {
+                *entry = mode;
+            }
+
+            // Keep the matchers of unchanged sources, with the ignore files they read
+            let source = match self.sources.remove(&context_id) {
+                Some(source) if source.path == source_path && source.filter == filter => source,
+                _ => match WalkMatcher::new(&source_path, &filter) {
+                    Ok(matcher) => WatchedSource {
+                        path: source_path,
+                        filter,
+                        matcher,
+                    },
+                    Err(e) => {
+                        tracing::warn!("Failed to watch context {}: {}", context_id, e);
+                        continue;
+                    },
+                },
+            };
+            sources.insert(context_id, source);
+        }
+        self.sources = sources;
+
+        let stale: Vec<PathBuf> = self
+            .watched
+            .iter()
+            .filter(|(path, mode)| wanted.get(*path) != Some(*mode))
+            .map(|(path, _)| path.clone())
+            .collect();
+        for path in stale {
+            if let Err(e) = self.watcher.unwatch(&path) {
+                tracing::debug!("Failed to stop watching {}: {}", path.display(), e);
+            }
+            self.watched.remove(&path);
+        }
+
+        for (path, mode) in wanted {
+            if self.watched.contains_key(&path) {
+                continue;
+            }
+            match self.watcher.watch(&path, mode) {
+                Ok(()) => {
+                    self.watched.insert(path, mode);
+                },
+                Err(e) => tracing::warn!("Failed to watch {}: {}", path.display(), e),
+            }
+        }
+    }
+
+    async fn update_context(&self, context_id: &str) {
+        let context = self.contexts.read().await.get(context_id).cloned();
+        let Some(context) = context else {
+            return;
+        };
+
+        tracing::info!("Source of context '{}' changed, updating", context.name);
+        if let Err(e) = queue_update(&self.active_operations, &self.job_tx, &context).await {
+            tracing::warn!("Failed to queue update of context '{}': {}", context.name, e);
+        }
+    }
+}
+
+/// Whether a file event may have changed the content of the indexed files
+///
+/// Access and metadata events are ignored, since reading the files while indexing them would
+/// otherwise trigger updates forever.
+fn is_content_change(kind: &EventKind) -> bool {
+    match kind {
+        EventKind::Modify(ModifyKind::Metadata(_)) => false,
+        EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_) | EventKind::Any => true,
+        EventKind::Access(_) | EventKind::Other => false,
+    }
+}
+
+/// Find the contexts whose indexed files include any of the given paths
+///
+/// Paths are matched with the patterns and ignore rules used when indexing. Changes to the ignore
+/// files under a source also affect its context, since they change which files are indexed.
+fn affected_contexts(sources: &mut HashMap<ContextId, WatchedSource>, paths: &[PathBuf]) -> HashSet<ContextId> {
+    let mut affected = HashSet::new();
+    for (context_id, source) in sources {
+        let changes_ignore_files = paths.iter().any(|path| {
+            path.starts_with(&source.path)
+                && (path.ends_with(".git/info/exclude")
+                    || path
+                        .file_name()
+                        .and_then(|name| name.to_str())
+                        .is_some_and(|name| IGNORE_FILE_NAMES.contains(&name)))
+        });
+        if changes_ignore_files {
+            source.matcher.clear_ignore_files();
+        }
+        if changes_ignore_files || paths.iter().any(|path| source.matcher.is_walked(path)) {
+            affected.insert(context_id.clone());
+        }
+    }
+    affected
+}
+
+#[cfg(test)]
+mod tests {
+    use ignore::gitignore::Gitignore;
+    use notify::event::{
+        AccessKind,
+        CreateKind,
+        DataChange,
+        MetadataKind,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_is_content_change() {
+        assert!(is_content_change(&EventKind::Create(CreateKind::File)));
+        assert!(is_content_change(&EventKind::Modify(ModifyKind::Data(
+            DataChange::Content
+        ))));
+        assert!(!is_content_change(&EventKind::Modify(ModifyKind::Metadata(
+            MetadataKind::AccessTime
+        ))));
+        assert!(!is_content_change(&EventKind::Access(AccessKind::Read)));
+    }
+
+    fn source(path: PathBuf, filter: IndexingFilter) -> WatchedSource {
+        WatchedSource {
+            matcher: WalkMatcher::new(&path, &filter)
+                .unwrap()
+                .with_global_ignore(Gitignore::empty()),
+            path,
+            filter,
+        }
+    }
+
+    #[test]
+    fn test_affected_contexts() {
+        let mut sources = HashMap::from([
+            (
+                "docs".to_string(),
+                source(PathBuf::from("/repo/docs"), IndexingFilter::default()),
+            ),
+            (
+                "notes".to_string(),
+                source(PathBuf::from("/home/notes.md"), IndexingFilter::default()),
+            ),
+        ]);
+
+        let affected = affected_contexts(&mut sources, &[PathBuf::from("/repo/docs/guide/install.md")]);
+        assert_eq!(affected, HashSet::from(["docs".to_string()]));
+
+        // Files are watched through their directory, so siblings must not match
+        let affected = affected_contexts(&mut sources, &[
+            PathBuf::from("/home/notes.md"),
+            PathBuf::from("/home/todo.md"),
+        ]);
+        assert_eq!(affected, HashSet::from(["notes".to_string()]));
+
+        // Hidden files and directories are not indexed
+        assert!(affected_contexts(&mut sources, &[PathBuf::from("/repo/docs/.git/index")]).is_empty());
+        assert!(affected_contexts(&mut sources, &[PathBuf::from("/repo/src/main.rs")]).is_empty());
+    }
+
+    #[test]
+    fn test_affected_contexts_with_filter() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let root = temp_dir.path().to_path_buf();
+        std::fs::create_dir_all(root.join("target")).unwrap();
+        std::fs::write(root.join(".gitignore"), "target/\n").unwrap();
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::write(root.join("src/main.rs"), "fn main() {}").unwrap();
+        std::fs::write(root.join("notes.txt"), "notes").unwrap();
+        let mut sources = HashMap::from([(
+            "code".to_string(),
+            source(root.clone(), IndexingFilter {
+                include: vec!["*.rs".to_string()],
+                exclude: vec!["vendor/".to_string()],
+                no_ignore: false,
+            }),
+        )]);
+
+        // Build output, excluded and non-matching files don't trigger updates
+        for path in ["target/debug/main.o", "vendor/lib.rs", "notes.txt"] {
+            assert!(affected_contexts(&mut sources, &[root.join(path)]).is_empty(), "{path}");
+        }
+        assert_eq!(affected_contexts(&mut sources, &[root.join("src/main.rs")]).len(), 1);
+
+        // Changing the ignore rules changes the indexed files
+        std::fs::write(root.join(".gitignore"), "").unwrap();
+        assert_eq!(affected_contexts(&mut sources, &[root.join(".gitignore")]).len(), 1);
+        assert_eq!(
+            affected_contexts(&mut sources, &[root.join("target/build.rs")]).len(),
+            1
+        );
+    }
+}
@@ -16,6 +16,8 @@ use serde::{
     Serialize,
 };
 
+use crate::types::SearchMode;
+
 /// Main configuration structure for the semantic search client.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SemanticSearchConfig {
@@ -39,6 +41,14 @@ pub struct SemanticSearchConfig {
 
     /// Maximum number of files allowed for indexing (default: 5000)
     pub max_files: usize,
+
+    /// Default retrieval strategy of searches
+    #[serde(default)]
+    pub search_mode: SearchMode,
+
+    /// Whether searches rerank their results with a cross-encoder by default
+    #[serde(default)]
+    pub rerank: bool,
 }
 
 impl SemanticSearchConfig {
@@ -83,6 +93,8 @@ impl Default for SemanticSearchConfig {
             timeout: 30000, // 30 seconds
             base_dir: get_default_base_dir(),
             max_files: 5000, // Default limit of 5000 files
+            search_mode: SearchMode::default(),
+            rerank: false,
         }
     }
 }
@@ -333,6 +345,8 @@ mod tests {
             timeout: 30000,
             base_dir: temp_dir.path().to_path_buf(),
             max_files: 10000,
+            search_mode: SearchMode::Vector,
+            rerank: false,
         };
 
         // Update the config
@@ -261,7 +261,7 @@ impl CandleTextEmbedder {
 }
 
 /// Ensure model directory exists
-fn ensure_model_directory_exists(model_path: &Path) -> Result<()> {
+pub(crate) fn ensure_model_directory_exists(model_path: &Path) -> Result<()> {
     let model_dir = model_path.parent().unwrap_or_else(|| Path::new("."));
     if let Err(err) = std::fs::create_dir_all(model_dir) {
         error!("Failed to create model directory: {}", err);
@@ -271,7 +271,7 @@ fn ensure_model_directory_exists(model_path: &Path) -> Result<()> {
 }
 
 /// Ensure model files exist, downloading them if necessary
-fn ensure_model_files(model_path: &Path, tokenizer_path: &Path, config: &ModelConfig) -> Result<()> {
+pub(crate) fn ensure_model_files(model_path: &Path, tokenizer_path: &Path, config: &ModelConfig) -> Result<()> {
     // Check if files already exist
     if model_path.exists() && tokenizer_path.exists() {
         return Ok(());
@@ -345,7 +345,7 @@ fn initialize_thread_pool() -> Result<usize> {
 }
 
 /// Load tokenizer from file
-fn load_tokenizer(tokenizer_path: &Path) -> Result<Tokenizer> {
+pub(crate) fn load_tokenizer(tokenizer_path: &Path) -> Result<Tokenizer> {
     match Tokenizer::from_file(tokenizer_path) {
         Ok(t) => Ok(t),
         Err(e) => {
@@ -359,7 +359,7 @@ fn load_tokenizer(tokenizer_path: &Path) -> Result<Tokenizer> {
 }
 
 /// Get the best available device for inference
-fn get_best_available_device() -> Device {
+pub(crate) fn get_best_available_device() -> Device {
     // Always use CPU for embedding to avoid hardware acceleration issues
     info!("Using CPU for text embedding (hardware acceleration disabled)");
     Device::Cpu
@@ -395,7 +395,7 @@ fn load_model(model_path: &Path, config: &ModelConfig, device: &Device) -> Resul
 }
 
 /// Prepare tokenizer with padding configuration
-fn prepare_tokenizer(tokenizer: &Tokenizer) -> Result<Tokenizer> {
+pub(crate) fn prepare_tokenizer(tokenizer: &Tokenizer) -> Result<Tokenizer> {
     let mut tokenizer = tokenizer.clone();
     if let Some(pp) = tokenizer.get_padding_mut() {
         pp.strategy = tokenizers::PaddingStrategy::BatchLongest;
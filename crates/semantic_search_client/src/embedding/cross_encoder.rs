@@ -0,0 +1,165 @@
+use std::path::Path;
+
+use candle_core::{
+    Device,
+    IndexOp,
+    Tensor,
+};
+use candle_nn::{
+    Linear,
+    Module,
+    VarBuilder,
+};
+use candle_transformers::models::bert::{
+    BertModel,
+    DTYPE,
+};
+use tokenizers::{
+    Tokenizer,
+    TruncationParams,
+};
+use tracing::{
+    debug,
+    info,
+};
+
+use crate::embedding::candle::{
+    ensure_model_directory_exists,
+    ensure_model_files,
+    get_best_available_device,
+    load_tokenizer,
+    prepare_tokenizer,
+};
+use crate::embedding::candle_models::{
+    ModelConfig,
+    cross_encoder_config,
+};
+use crate::error::{
+    Result,
+    SemanticSearchError,
+};
+
+/// Reranker scoring (query, text) pairs with a local BERT cross-encoder
+///
+/// A cross-encoder reads the query and the text together, which ranks far more accurately than
+/// comparing separate embeddings but is too slow to run on more than a few candidates.
+pub struct CrossEncoderReranker {
+    /// The BERT encoder
+    model: BertModel,
+    /// Dense layer pooling the classification token
+    pooler: Linear,
+    /// Layer mapping the pooled output to a relevance logit
+    classifier: Linear,
+    /// The tokenizer, truncating pairs to the model input size
+    tokenizer: Tokenizer,
+    /// The device to run on
+    device: Device,
+    /// Model configuration
+    config: ModelConfig,
+}
+
+impl CrossEncoderReranker {
+    /// Create a new reranker with the default cross-encoder (ms-marco-MiniLM-L-6-v2)
+    ///
+    /// The model files are downloaded on first use.
+    pub fn new() -> Result<Self> {
+        let config = cross_encoder_config();
+        let (model_path, tokenizer_path) = config.get_local_paths();
+
+        ensure_model_directory_exists(&model_path)?;
+        ensure_model_files(&model_path, &tokenizer_path, &config)?;
+
+        Self::with_model_config(&model_path, &tokenizer_path, config)
+    }
+
+    /// Create a new reranker with specific model paths and configuration
+    ///
+    /// # Arguments
+    ///
+    /// * `model_path` - Path to the model file (.safetensors)
+    /// * `tokenizer_path` - Path to the tokenizer file (.json)
+    /// * `config` - Model configuration
+    pub fn with_model_config(model_path: &Path, tokenizer_path: &Path, config: ModelConfig) -> Result<Self> {
+        info!("Initializing cross-encoder reranker with model: {:?}", model_path);
+
+        let mut tokenizer = prepare_tokenizer(&load_tokenizer(tokenizer_path)?)?;
+        tokenizer
+            .with_truncation(Some(TruncationParams {
+                max_length: config.config.max_position_embeddings,
+                ..Default::default()
+            }))
+            .map_err(|e| SemanticSearchError::EmbeddingError(format!("Failed to configure truncation: {}", e)))?;
+
+        let device = get_best_available_device();
+        let vb = unsafe { VarBuilder::from_mmaped_safetensors(&[model_path], DTYPE, &device) }
+            .map_err(|e| SemanticSearchError::EmbeddingError(format!("Failed to load model weights: {}", e)))?;
+
+        let hidden_size = config.config.hidden_size;
+        let model = BertModel::load(vb.clone(), &config.config)
+            .map_err(|e| SemanticSearchError::EmbeddingError(format!("Failed to create BERT model: {}", e)))?;
+        let pooler = candle_nn::linear(hidden_size, hidden_size, vb.pp("bert.pooler.dense"))
+            .map_err(|e| SemanticSearchError::EmbeddingError(format!("Failed to load pooler: {}", e)))?;
+        let classifier = candle_nn::linear(hidden_size, 1, vb.pp("classifier"))
+            .map_err(|e| SemanticSearchError::EmbeddingError(format!("Failed to load classifier: {}", e)))?;
+
+        debug!("Cross-encoder reranker initialized successfully");
+
+        Ok(Self {
+            model,
+            pooler,
+            classifier,
+            tokenizer,
+            device,
+            config,
+        })
+    }
+
+    /// Score the relevance of texts to a query
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - The query text
+    /// * `texts` - The texts to score
+    ///
+    /// # Returns
+    ///
+    /// One relevance logit per text, higher is more relevant
+    pub fn score(&self, query: &str, texts: &[String]) -> Result<Vec<f32>> {
+        let mut scores = Vec::with_capacity(texts.len());
+        for batch in texts.chunks(self.config.batch_size) {
+            scores.extend(self.score_batch(query, batch)?);
+        }
+        Ok(scores)
+    }
+
+    fn score_batch(&self, query: &str, texts: &[String]) -> Result<Vec<f32>> {
+        let pairs: Vec<(String, String)> = texts.iter().map(|text| (query.to_string(), text.clone())).collect();
+        let encodings = self
+            .tokenizer
+            .encode_batch(pairs, true)
+            .map_err(|e| SemanticSearchError::EmbeddingError(format!("Failed to tokenize pairs: {}", e)))?;
+
+        let logits = self
+            .forward(&encodings)
+            .map_err(|e| SemanticSearchError::EmbeddingError(format!("Cross-encoder inference failed: {}", e)))?;
+        Ok(logits)
+    }
+
+    fn forward(&self, encodings: &[tokenizers::Encoding]) -> candle_core::Result<Vec<f32>> {
+        let stack = |values: &dyn Fn(&tokenizers::Encoding) -> &[u32]| -> candle_core::Result<Tensor> {
+            let rows = encodings
+                .iter()
+                .map(|encoding| Tensor::new(values(encoding), &self.device))
+                .collect::<candle_core::Result<Vec<_>>>()?;
+            Tensor::stack(&rows, 0)
+        };
+        let token_ids = stack(&|encoding| encoding.get_ids())?;
+        let token_type_ids = stack(&|encoding| encoding.get_type_ids())?;
+        let attention_mask = stack(&|encoding| encoding.get_attention_mask())?;
+
+        // Classify the pooled [CLS] token like BertForSequenceClassification
+        let hidden = self.model.forward(&token_ids, &token_type_ids, Some(&attention_mask))?;
+        let pooled = self.pooler.forward(&hidden.i((.., 0))?)?.tanh()?;
+        self.classifier.forward(&pooled)?.squeeze(1)?.to_vec1::<f32>()
+    }
+}
@@ -6,6 +6,8 @@ mod bm25;
 mod candle;
 #[cfg(not(all(target_os = "linux", target_arch = "aarch64")))]
 mod candle_models;
+#[cfg(not(all(target_os = "linux", target_arch = "aarch64")))]
+mod cross_encoder;
 /// Mock embedder for testing
 #[cfg(test)]
 pub mod mock;
@@ -22,9 +24,12 @@ pub use bm25::BM25TextEmbedder;
 pub use candle::CandleTextEmbedder;
 #[cfg(not(all(target_os = "linux", target_arch = "aarch64")))]
 pub use candle_models::ModelType;
+#[cfg(not(all(target_os = "linux", target_arch = "aarch64")))]
+pub use cross_encoder::CrossEncoderReranker;
 #[cfg(test)]
 pub use mock::MockTextEmbedder;
 pub use trait_def::{
     EmbeddingType,
+    RerankerTrait,
     TextEmbedderTrait,
 };
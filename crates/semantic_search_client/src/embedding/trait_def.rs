@@ -1,4 +1,5 @@
 use crate::error::Result;
+use crate::types::SearchResult;
 
 /// Embedding engine type to use
 #[derive(Debug, Clone, Copy)]
@@ -41,6 +42,32 @@ pub trait TextEmbedderTrait: Send + Sync {
     fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>>;
 }
 
+/// Common trait for rerankers of search results
+pub trait RerankerTrait: Send + Sync {
+    /// Score the relevance of texts to a query, higher is more relevant
+    fn score(&self, query: &str, texts: &[String]) -> Result<Vec<f32>>;
+
+    /// Reorder search results by relevance to a query and keep the best `limit` ones
+    ///
+    /// The distance of the reranked results is derived from their relevance score.
+    fn rerank(&self, query: &str, results: Vec<SearchResult>, limit: usize) -> Result<Vec<SearchResult>> {
+        let texts: Vec<String> = results
+            .iter()
+            .map(|result| result.text().unwrap_or_default().to_string())
+            .collect();
+        let scores = self.score(query, &texts)?;
+
+        let mut reranked: Vec<SearchResult> = results
+            .into_iter()
+            .zip(scores)
+            .map(|(result, score)| SearchResult::new(result.point, 1.0 / (1.0 + score.exp())))
+            .collect();
+        reranked.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+        reranked.truncate(limit);
+        Ok(reranked)
+    }
+}
+
 #[cfg(not(all(target_os = "linux", target_arch = "aarch64")))]
 impl TextEmbedderTrait for super::CandleTextEmbedder {
     fn embed(&self, text: &str) -> Result<Vec<f32>> {
@@ -72,3 +99,57 @@ impl TextEmbedderTrait for super::MockTextEmbedder {
         self.embed_batch(texts)
     }
 }
+
+#[cfg(not(all(target_os = "linux", target_arch = "aarch64")))]
+impl RerankerTrait for super::CrossEncoderReranker {
+    fn score(&self, query: &str, texts: &[String]) -> Result<Vec<f32>> {
+        self.score(query, texts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::types::DataPoint;
+
+    /// Reranker preferring the texts containing the query
+    struct ContainsReranker;
+
+    impl RerankerTrait for ContainsReranker {
+        fn score(&self, query: &str, texts: &[String]) -> Result<Vec<f32>> {
+            Ok(texts
+                .iter()
+                .map(|text| if text.contains(query) { 5.0 } else { -5.0 })
+                .collect())
+        }
+    }
+
+    fn result(id: usize, text: &str, distance: f32) -> SearchResult {
+        let payload = HashMap::from([("text".to_string(), serde_json::Value::String(text.to_string()))]);
+        SearchResult::new(
+            DataPoint {
+                id,
+                payload,
+                vector: Vec::new(),
+            },
+            distance,
+        )
+    }
+
+    #[test]
+    fn test_rerank() {
+        let results = vec![
+            result(0, "retry the request", 0.1),
+            result(1, "error E0382 is a use after move", 0.2),
+            result(2, "unrelated", 0.3),
+        ];
+
+        let reranked = ContainsReranker.rerank("E0382", results, 2).unwrap();
+        assert_eq!(reranked.len(), 2);
+        assert_eq!(reranked[0].point.id, 1);
+        assert!(reranked[0].distance < 0.01);
+        assert!(reranked[1].distance > 0.99);
+    }
+}
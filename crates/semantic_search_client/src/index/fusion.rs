@@ -0,0 +1,51 @@
+use std::collections::HashMap;
+
+/// Constant dampening the weight of the top ranks in reciprocal rank fusion
+pub const RRF_K: f32 = 60.0;
+
+/// Fuse several rankings of the same items with reciprocal rank fusion
+///
+/// Each item scores `1 / (k + rank)` in every ranking it appears in, so items ranked well by
+/// several retrievers come first without having to compare their raw scores.
+///
+/// # Arguments
+///
+/// * `rankings` - The rankings to fuse, each listing item IDs best match first
+/// * `k` - The rank constant, usually [`RRF_K`]
+///
+/// # Returns
+///
+/// A vector of (id, fused score) pairs, best match first
+pub fn reciprocal_rank_fusion(rankings: &[Vec<usize>], k: f32) -> Vec<(usize, f32)> {
+    let mut scores: HashMap<usize, f32> = HashMap::new();
+    for ranking in rankings {
+        for (rank, id) in ranking.iter().enumerate() {
+            *scores.entry(*id).or_default() += 1.0 / (k + rank as f32 + 1.0);
+        }
+    }
+
+    let mut fused: Vec<(usize, f32)> = scores.into_iter().collect();
+    fused.sort_by(|(a_id, a_score), (b_id, b_score)| b_score.total_cmp(a_score).then(a_id.cmp(b_id)));
+    fused
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reciprocal_rank_fusion() {
+        let fused = reciprocal_rank_fusion(&[vec![1, 2, 3], vec![3, 1, 4]], RRF_K);
+        let ids: Vec<usize> = fused.iter().map(|(id, _)| *id).collect();
+
+        // 1 is ranked well by both, 3 first by one and last by the other
+        assert_eq!(ids, vec![1, 3, 2, 4]);
+        assert!((fused[0].1 - (1.0 / 61.0 + 1.0 / 62.0)).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn test_reciprocal_rank_fusion_empty() {
+        assert!(reciprocal_rank_fusion(&[], RRF_K).is_empty());
+        assert_eq!(reciprocal_rank_fusion(&[vec![], vec![7]], RRF_K), vec![(7, 1.0 / 61.0)]);
+    }
+}
@@ -0,0 +1,302 @@
+//! Validation of the files hnsw_rs dumps an index to
+//!
+//! hnsw_rs asserts the consistency of the files it loads, and even exits the process on some
+//! inconsistencies, rather than returning errors. Files are checked against the layout written by
+//! `Hnsw::file_dump` before they are loaded, so corrupt files are reported as errors instead.
+
+use std::fs::File;
+use std::io::{
+    BufReader,
+    Read,
+};
+use std::path::Path;
+
+use crate::error::{
+    Result,
+    SemanticSearchError,
+};
+
+/// Magic starting the description of the graph file, for version 4 of the layout
+const MAGIC_DESCRIPTION: u32 = 0x002a_6779;
+
+/// Magic starting each layer in the graph file
+const MAGIC_LAYER: u32 = 0x000a_676f;
+
+/// Magic starting each point in the graph file
+const MAGIC_POINT: u32 = 0x000a_678f;
+
+/// Magic starting the data file and each vector in it
+const MAGIC_DATA: u32 = 0xa67f_0000;
+
+/// Number of layers of every graph dumped by hnsw_rs
+const LAYER_COUNT: u8 = 16;
+
+/// Longest type names hnsw_rs loads
+const MAX_NAME_LEN: usize = 256;
+
+/// Size of the usize fields, which hnsw_rs writes with the size of the platform
+const USIZE_LEN: u64 = size_of::<usize>() as u64;
+
+/// Size of a neighbour in the graph file: origin ID, layer, rank and distance
+const NEIGHBOUR_LEN: u64 = USIZE_LEN + 1 + 4 + 4;
+
+/// Smallest size of a point in the graph file: magic, origin ID, layer, rank and the neighbour
+/// count of each layer
+const MIN_POINT_LEN: u64 = 4 + USIZE_LEN + 1 + 4 + USIZE_LEN * LAYER_COUNT as u64;
+
+/// Check that `{basename}.hnsw.graph` and `{basename}.hnsw.data` in `directory` hold a consistent
+/// index of f32 vectors compared by cosine distance, as dumped by [hnsw_rs::api::AnnT::file_dump]
+pub fn validate(directory: &Path, basename: &str) -> Result<()> {
+    let mut graph = DumpReader::open(&directory.join(format!("{}.hnsw.graph", basename)))?;
+    let mut data = DumpReader::open(&directory.join(format!("{}.hnsw.data", basename)))?;
+
+    // Description
+    graph.expect_u32(MAGIC_DESCRIPTION, "graph description")?;
+    graph.expect_u8(1, "dump mode")?;
+    let _max_connections = graph.u8()?;
+    let _level_scale = graph.u64()?;
+    graph.expect_u8(LAYER_COUNT, "layer count")?;
+    let _ef_construction = graph.usize()?;
+    let point_count = graph.usize()?;
+    let dimension = graph.usize()?;
+    let distance = graph.name()?;
+    if distance.rsplit("::").next() != Some("DistCosine") {
+        return Err(corrupt(format!("unexpected distance {}", distance)));
+    }
+    let type_name = graph.name()?;
+    if type_name != std::any::type_name::<f32>() {
+        return Err(corrupt(format!("unexpected vector type {}", type_name)));
+    }
+
+    data.expect_u32(MAGIC_DATA, "data header")?;
+    data.expect_usize(dimension, "data dimension")?;
+    let vector_len = dimension
+        .checked_mul(size_of::<f32>() as u64)
+        .ok_or_else(|| corrupt(format!("invalid dimension {}", dimension)))?;
+
+    // Layers, with each point in the graph file followed by its vector in the data file
+    let layer_count = graph.u8()?;
+    if layer_count > LAYER_COUNT {
+        return Err(corrupt(format!("{} layers", layer_count)));
+    }
+    let mut layer_sizes = Vec::with_capacity(layer_count as usize);
+    // The highest rank referenced in each layer by neighbours, checked once all layers are read
+    let mut max_ranks = [None; LAYER_COUNT as usize];
+    let mut loaded_points = 0;
+    for layer in 0..layer_count {
+        graph.expect_u32(MAGIC_LAYER, "layer")?;
+        let size = graph.usize()?;
+        graph.check_count(size, MIN_POINT_LEN, "points")?;
+        loaded_points += size;
+        if loaded_points > point_count {
+            return Err(corrupt(format!("more than {} points", point_count)));
+        }
+
+        for rank in 0..size {
+            graph.expect_u32(MAGIC_POINT, "point")?;
+            let origin_id = graph.usize()?;
+            graph.expect_u8(layer, "point layer")?;
+            let point_rank = graph.i32()?;
+            if i64::from(point_rank) != rank as i64 {
+                return Err(corrupt(format!("point rank is {} instead of {}", point_rank, rank)));
+            }
+            for _ in 0..LAYER_COUNT {
+                let neighbours = graph.usize()?;
+                graph.check_count(neighbours, NEIGHBOUR_LEN, "neighbours")?;
+                for _ in 0..neighbours {
+                    let _origin_id = graph.usize()?;
+                    let (layer, rank) = (graph.u8()?, graph.i32()?);
+                    let max_rank = max_ranks
+                        .get_mut(layer as usize)
+                        .filter(|_| rank >= 0)
+                        .ok_or_else(|| corrupt(format!("invalid neighbour ({}, {})", layer, rank)))?;
+                    *max_rank = (*max_rank).max(Some(rank as u64));
+                    let _distance = graph.u32()?;
+                }
+            }
+
+            data.expect_u32(MAGIC_DATA, "vector")?;
+            data.expect_u64(origin_id, "vector origin ID")?;
+            data.expect_u64(vector_len, "vector length")?;
+            data.skip(vector_len)?;
+        }
+        layer_sizes.push(size);
+    }
+    if loaded_points != point_count {
+        return Err(corrupt(format!("{} of {} points", loaded_points, point_count)));
+    }
+
+    let in_layers = |layer: usize, rank: u64| layer_sizes.get(layer).is_some_and(|&size| rank < size);
+    if let Some((layer, rank)) = max_ranks.iter().enumerate().find_map(|(layer, max_rank)| {
+        max_rank
+            .filter(|&rank| !in_layers(layer, rank))
+            .map(|rank| (layer, rank))
+    }) {
+        return Err(corrupt(format!("missing neighbour ({}, {})", layer, rank)));
+    }
+
+    // Entry point
+    let _origin_id = graph.usize()?;
+    let (layer, rank) = (graph.u8()?, graph.i32()?);
+    if rank < 0 || !in_layers(layer as usize, rank as u64) {
+        return Err(corrupt(format!("missing entry point ({}, {})", layer, rank)));
+    }
+
+    if graph.remaining != 0 || data.remaining != 0 {
+        return Err(corrupt("trailing bytes".to_string()));
+    }
+    Ok(())
+}
+
+fn corrupt(message: String) -> SemanticSearchError {
+    SemanticSearchError::SerializationError(format!("Corrupt vector index: {}", message))
+}
+
+/// Reads the native-endian fields of a dump file, tracking how many bytes are left
+struct DumpReader {
+    reader: BufReader<File>,
+    remaining: u64,
+}
+
+impl DumpReader {
+    fn open(path: &Path) -> Result<Self> {
+        let file = File::open(path)?;
+        let remaining = file.metadata()?.len();
+        Ok(Self {
+            reader: BufReader::new(file),
+            remaining,
+        })
+    }
+
+    fn read<const N: usize>(&mut self) -> Result<[u8; N]> {
+        if self.remaining < N as u64 {
+            return Err(corrupt("truncated file".to_string()));
+        }
+        let mut bytes = [0; N];
+        self.reader.read_exact(&mut bytes)?;
+        self.remaining -= N as u64;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(u8::from_ne_bytes(self.read()?))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_ne_bytes(self.read()?))
+    }
+
+    fn i32(&mut self) -> Result<i32> {
+        Ok(i32::from_ne_bytes(self.read()?))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_ne_bytes(self.read()?))
+    }
+
+    fn usize(&mut self) -> Result<u64> {
+        Ok(usize::from_ne_bytes(self.read()?) as u64)
+    }
+
+    fn expect_u8(&mut self, expected: u8, field: &str) -> Result<()> {
+        match self.u8()? {
+            value if value == expected => Ok(()),
+            value => Err(corrupt(format!("{} is {} instead of {}", field, value, expected))),
+        }
+    }
+
+    fn expect_u32(&mut self, expected: u32, field: &str) -> Result<()> {
+        match self.u32()? {
+            value if value == expected => Ok(()),
+            value => Err(corrupt(format!("{} is {:#x} instead of {:#x}", field, value, expected))),
+        }
+    }
+
+    fn expect_u64(&mut self, expected: u64, field: &str) -> Result<()> {
+        match self.u64()? {
+            value if value == expected => Ok(()),
+            value => Err(corrupt(format!("{} is {} instead of {}", field, value, expected))),
+        }
+    }
+
+    fn expect_usize(&mut self, expected: u64, field: &str) -> Result<()> {
+        match self.usize()? {
+            value if value == expected => Ok(()),
+            value => Err(corrupt(format!("{} is {} instead of {}", field, value, expected))),
+        }
+    }
+
+    /// Read a name, as its length followed by its UTF-8 bytes
+    fn name(&mut self) -> Result<String> {
+        let len = self.usize()?;
+        if len > MAX_NAME_LEN as u64 {
+            return Err(corrupt(format!("name of {} bytes", len)));
+        }
+        let mut name = Vec::new();
+        for _ in 0..len {
+            name.push(self.u8()?);
+        }
+        String::from_utf8(name).map_err(|_err| corrupt("invalid name".to_string()))
+    }
+
+    /// Check that `count` items of at least `item_len` bytes fit in the rest of the file, since
+    /// hnsw_rs allocates for them before reading them
+    fn check_count(&self, count: u64, item_len: u64, items: &str) -> Result<()> {
+        match count.checked_mul(item_len) {
+            Some(len) if len <= self.remaining => Ok(()),
+            _ => Err(corrupt(format!("{} {} don't fit in the file", count, items))),
+        }
+    }
+
+    fn skip(&mut self, len: u64) -> Result<()> {
+        if self.remaining < len {
+            return Err(corrupt("truncated file".to_string()));
+        }
+        std::io::copy(&mut (&mut self.reader).take(len), &mut std::io::sink())?;
+        self.remaining -= len;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::index::VectorIndex;
+
+    #[test]
+    fn test_validate() {
+        let temp_dir = TempDir::new().unwrap();
+        let directory = temp_dir.path();
+        let index = VectorIndex::new(100);
+        for id in 0..50 {
+            index.insert(&[id as f32 + 1.0, 1.0, 2.0], id);
+        }
+        index.save(directory, "index").unwrap();
+        validate(directory, "index").unwrap();
+        assert!(!directory.join("index.tmp.hnsw.graph").exists());
+
+        let graph = fs::read(directory.join("index.hnsw.graph")).unwrap();
+        let data = fs::read(directory.join("index.hnsw.data")).unwrap();
+        let corruptions: [(&str, Vec<u8>); 4] = [
+            ("index.hnsw.graph", graph[..graph.len() - 1].to_vec()),
+            ("index.hnsw.graph", [graph.as_slice(), &[0]].concat()),
+            ("index.hnsw.data", data[..data.len() / 2].to_vec()),
+            ("index.hnsw.data", [&data[..4], &[7; 8], &data[12..]].concat()),
+        ];
+        for (file, contents) in corruptions {
+            fs::write(directory.join(file), contents).unwrap();
+            assert!(validate(directory, "index").is_err(), "{}", file);
+            assert!(VectorIndex::load(directory, "index").is_err(), "{}", file);
+            index.save(directory, "index").unwrap();
+        }
+
+        // A loaded index is saved in place of its own files
+        let loaded = VectorIndex::load(directory, "index").unwrap();
+        loaded.save(directory, "index").unwrap();
+        assert_eq!(VectorIndex::load(directory, "index").unwrap().len(), 50);
+    }
+}
@@ -0,0 +1,74 @@
+use bm25::{
+    Embedder,
+    EmbedderBuilder,
+    Language,
+    Scorer,
+};
+use tracing::debug;
+
+/// Lexical index ranking texts with BM25
+///
+/// Unlike the vector index, it only matches on shared terms, which makes it reliable for exact
+/// identifiers such as error codes or function names that dense embeddings tend to miss.
+pub struct LexicalIndex {
+    /// Embedder producing the sparse BM25 term vectors
+    embedder: Embedder,
+    /// Scorer holding the term vectors of the indexed texts
+    scorer: Scorer<usize>,
+}
+
+impl LexicalIndex {
+    /// Create a new lexical index fitted to a corpus
+    ///
+    /// # Arguments
+    ///
+    /// * `corpus` - The texts the average document length is computed from
+    ///
+    /// # Returns
+    ///
+    /// A new empty LexicalIndex instance
+    pub fn new(corpus: &[&str]) -> Self {
+        // A fixed language keeps the stemming of the queries consistent with the one of the texts
+        let embedder = EmbedderBuilder::with_fit_to_corpus(Language::English, corpus).build();
+        debug!("Lexical index created with avgdl: {}", embedder.avgdl());
+
+        Self {
+            embedder,
+            scorer: Scorer::new(),
+        }
+    }
+
+    /// Insert a text into the index
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The text to insert
+    /// * `id` - The ID associated with the text
+    pub fn insert(&mut self, text: &str, id: usize) {
+        self.scorer.upsert(&id, self.embedder.embed(text));
+    }
+
+    /// Search for the texts sharing terms with a query
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - The query text
+    /// * `limit` - Maximum number of results to return
+    ///
+    /// # Returns
+    ///
+    /// A vector of (id, score) pairs, best match first
+    pub fn search(&self, query: &str, limit: usize) -> Vec<(usize, f32)> {
+        let mut matches: Vec<(usize, f32)> = self
+            .scorer
+            .matches(&self.embedder.embed(query))
+            .into_iter()
+            .map(|document| (document.id, document.score))
+            .collect();
+
+        // Break ties by ID so results are deterministic
+        matches.sort_by(|(a_id, a_score), (b_id, b_score)| b_score.total_cmp(a_score).then(a_id.cmp(b_id)));
+        matches.truncate(limit);
+        matches
+    }
+}
@@ -1,3 +1,11 @@
+mod fusion;
+mod hnsw_dump;
+mod lexical_index;
 mod vector_index;
 
+pub use fusion::{
+    RRF_K,
+    reciprocal_rank_fusion,
+};
+pub use lexical_index::LexicalIndex;
 pub use vector_index::VectorIndex;
@@ -1,18 +1,70 @@
+use std::fs;
+use std::path::Path;
+use std::ptr::NonNull;
+use std::sync::RwLock;
+
 use hnsw_rs::hnsw::Hnsw;
-use hnsw_rs::prelude::DistCosine;
+use hnsw_rs::prelude::{
+    AnnT,
+    DistCosine,
+    Distance,
+    HnswIo,
+};
 use tracing::{
     debug,
     info,
 };
 
+use super::hnsw_dump;
+use crate::error::{
+    Result,
+    SemanticSearchError,
+};
+
+/// Number of vectors from which batches are inserted in parallel, as parallel insertion only pays
+/// off for large batches
+const PARALLEL_INSERT_THRESHOLD: usize = 1000;
+
+/// Number of vectors below which searches compare the query with every vector
+///
+/// HNSW only searches the bottom layer of its graph, which stays empty when every point was drawn
+/// on a higher layer. That is likely with a handful of points, so small indexes are searched
+/// exactly instead.
+const EXACT_SEARCH_THRESHOLD: usize = 1000;
+
 /// Vector index for fast approximate nearest neighbor search
 pub struct VectorIndex {
-    /// The HNSW index
+    /// The HNSW index, declared before `loader` so that it is dropped first
     index: Hnsw<'static, f32, DistCosine>,
+    /// The loader of an index loaded with [Self::load], which the index borrows from
+    _loader: Option<IndexLoader>,
+    /// The ID of the vector HNSW stores under each slot, or `None` once the vector was removed
+    ///
+    /// HNSW can't remove vectors from its graph, so removed vectors stay in the graph and are
+    /// skipped by searches.
+    ids: RwLock<Vec<Option<usize>>>,
     /// Counter to track the number of elements
     count: std::sync::atomic::AtomicUsize,
 }
 
+/// Owns the loader of a saved index
+///
+/// hnsw_rs ties a loaded graph to the lifetime of its loader. The loader is kept on the heap,
+/// where it doesn't move, and only freed along with the [VectorIndex] holding the graph.
+struct IndexLoader(NonNull<HnswIo>);
+
+// SAFETY: The loader is not accessed once the index is loaded, and it only holds paths since the
+// vectors are not memory mapped
+unsafe impl Send for IndexLoader {}
+unsafe impl Sync for IndexLoader {}
+
+impl Drop for IndexLoader {
+    fn drop(&mut self) {
+        // SAFETY: The pointer comes from a leaked box, and the index borrowing from it was dropped
+        drop(unsafe { Box::from_raw(self.0.as_ptr()) });
+    }
+}
+
 impl VectorIndex {
     /// Create a new empty vector index
     ///
@@ -37,10 +89,129 @@ impl VectorIndex {
         debug!("Vector index created successfully");
         Self {
             index,
+            _loader: None,
+            ids: RwLock::new(Vec::new()),
             count: std::sync::atomic::AtomicUsize::new(0),
         }
     }
 
+    /// Load an index saved with [Self::save]
+    ///
+    /// # Arguments
+    ///
+    /// * `directory` - Directory holding the files of the index
+    /// * `basename` - Name the files of the index start with
+    ///
+    /// # Returns
+    ///
+    /// The index, or an error if its files are missing or corrupt
+    pub fn load(directory: &Path, basename: &str) -> Result<Self> {
+        info!("Loading vector index {} from {}", basename, directory.display());
+
+        // hnsw_rs panics on inconsistent files rather than returning errors
+        hnsw_dump::validate(directory, basename)?;
+
+        let loader = IndexLoader(NonNull::from(Box::leak(Box::new(HnswIo::new(directory, basename)))));
+        // SAFETY: The loader is only freed after the index, see IndexLoader
+        let index = unsafe { &mut *loader.0.as_ptr() }
+            .load_hnsw::<f32, DistCosine>()
+            .map_err(|e| SemanticSearchError::SerializationError(format!("Failed to load the vector index: {}", e)))?;
+
+        // Indexes without removed vectors are saved without their IDs, which are their slots
+        let ids_path = directory.join(format!("{}.hnsw.ids", basename));
+        let ids: Vec<Option<usize>> = match ids_path.exists() {
+            true => serde_json::from_slice(&fs::read(ids_path)?)?,
+            false => (0..index.get_nb_point()).map(Some).collect(),
+        };
+        if ids.len() != index.get_nb_point()
+            || index
+                .get_point_indexation()
+                .into_iter()
+                .any(|point| point.get_origin_id() >= ids.len())
+        {
+            return Err(SemanticSearchError::SerializationError(
+                "Corrupt vector index: the IDs don't match the graph".to_string(),
+            ));
+        }
+
+        let count = ids.iter().flatten().count();
+        Ok(Self {
+            index,
+            _loader: Some(loader),
+            ids: RwLock::new(ids),
+            count: std::sync::atomic::AtomicUsize::new(count),
+        })
+    }
+
+    /// Save the graph and the vectors of the index, to `{basename}.hnsw.graph` and
+    /// `{basename}.hnsw.data` in `directory`, along with `{basename}.hnsw.ids` once vectors were
+    /// removed
+    ///
+    /// The files are written under a temporary name, then renamed. The graph file is removed first
+    /// and renamed last, so an interrupted save leaves no graph rather than a graph that doesn't
+    /// match the vectors.
+    ///
+    /// # Arguments
+    ///
+    /// * `directory` - Directory to write the files of the index to
+    /// * `basename` - Name the files of the index start with
+    pub fn save(&self, directory: &Path, basename: &str) -> Result<()> {
+        let temp_basename = format!("{}.tmp", basename);
+        let paths = |basename: &str| {
+            (
+                directory.join(format!("{}.hnsw.graph", basename)),
+                directory.join(format!("{}.hnsw.data", basename)),
+            )
+        };
+        let (temp_graph, temp_data) = paths(&temp_basename);
+        let (graph, data) = paths(basename);
+
+        // hnsw_rs picks another name rather than replacing the data file of a loaded index
+        if temp_data.exists() {
+            fs::remove_file(&temp_data)?;
+        }
+        let dumped = self
+            .index
+            .file_dump(directory, &temp_basename)
+            .map_err(|e| format!("Failed to save the vector index: {}", e))
+            .and_then(|dumped| match dumped == temp_basename {
+                true => Ok(()),
+                false => Err(format!("Vector index saved as {} instead of {}", dumped, temp_basename)),
+            });
+        if let Err(message) = dumped {
+            let _ = fs::remove_file(&temp_graph);
+            let _ = fs::remove_file(&temp_data);
+            return Err(SemanticSearchError::OperationFailed(message));
+        }
+
+        if graph.exists() {
+            fs::remove_file(&graph)?;
+        }
+        fs::rename(&temp_data, &data)?;
+        let ids_path = directory.join(format!("{}.hnsw.ids", basename));
+        let ids = self.ids.read().unwrap_or_else(|e| e.into_inner());
+        if ids.iter().enumerate().all(|(slot, id)| *id == Some(slot)) {
+            if ids_path.exists() {
+                fs::remove_file(&ids_path)?;
+            }
+        } else {
+            fs::write(&ids_path, serde_json::to_vec(&*ids)?)?;
+        }
+        fs::rename(&temp_graph, &graph)?;
+        Ok(())
+    }
+
+    /// Whether the index holds exactly the given vectors, each with its position as ID
+    pub fn matches(&self, vectors: &[&[f32]]) -> bool {
+        let ids = self.ids.read().unwrap_or_else(|e| e.into_inner());
+        self.len() == vectors.len()
+            && self
+                .index
+                .get_point_indexation()
+                .into_iter()
+                .all(|point| ids[point.get_origin_id()].is_none_or(|id| vectors.get(id) == Some(&point.get_v())))
+    }
+
     /// Insert a vector into the index
     ///
     /// # Arguments
@@ -48,10 +219,65 @@ impl VectorIndex {
     /// * `vector` - The vector to insert
     /// * `id` - The ID associated with the vector
     pub fn insert(&self, vector: &[f32], id: usize) {
-        self.index.insert((vector, id));
+        let slot = {
+            let mut ids = self.ids.write().unwrap_or_else(|e| e.into_inner());
+            ids.push(Some(id));
+            ids.len() - 1
+        };
+        self.index.insert((vector, slot));
         self.count.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
     }
 
+    /// Insert vectors into the index, in parallel for large batches
+    ///
+    /// # Arguments
+    ///
+    /// * `vectors` - The vectors to insert with their IDs
+    pub fn insert_batch(&self, vectors: &[(&Vec<f32>, usize)]) {
+        let slotted: Vec<(&Vec<f32>, usize)> = {
+            let mut ids = self.ids.write().unwrap_or_else(|e| e.into_inner());
+            let start = ids.len();
+            ids.extend(vectors.iter().map(|&(_, id)| Some(id)));
+            vectors
+                .iter()
+                .enumerate()
+                .map(|(i, &(vector, _))| (vector, start + i))
+                .collect()
+        };
+        if slotted.len() < PARALLEL_INSERT_THRESHOLD {
+            for &(vector, slot) in &slotted {
+                self.index.insert((vector.as_slice(), slot));
+            }
+        } else {
+            self.index.parallel_insert(&slotted);
+        }
+        self.count.fetch_add(vectors.len(), std::sync::atomic::Ordering::SeqCst);
+    }
+
+    /// Remove vectors and renumber the others
+    ///
+    /// The removed vectors stay in the HNSW graph, where they are skipped by searches, until the
+    /// index is rebuilt. See [Self::removed_len].
+    ///
+    /// # Arguments
+    ///
+    /// * `new_ids` - The new ID of the vector of each ID, or `None` to remove it. IDs past the end
+    ///   are kept.
+    pub fn renumber(&mut self, new_ids: &[Option<usize>]) {
+        let ids = self.ids.get_mut().unwrap_or_else(|e| e.into_inner());
+        for id in ids.iter_mut() {
+            if let Some(old_id) = *id {
+                *id = new_ids.get(old_id).copied().unwrap_or(Some(old_id));
+            }
+        }
+        *self.count.get_mut() = ids.iter().flatten().count();
+    }
+
+    /// Get the number of removed vectors that remain in the HNSW graph
+    pub fn removed_len(&self) -> usize {
+        self.ids.read().unwrap_or_else(|e| e.into_inner()).len() - self.len()
+    }
+
     /// Search for nearest neighbors
     ///
     /// # Arguments
@@ -64,14 +290,38 @@ impl VectorIndex {
     ///
     /// A vector of (id, distance) pairs
     pub fn search(&self, query: &[f32], limit: usize, ef_search: usize) -> Vec<(usize, f32)> {
-        let results = self.index.search(query, limit, ef_search);
+        if self.len() < EXACT_SEARCH_THRESHOLD {
+            return self.exact_search(query, limit);
+        }
 
+        // Removed vectors may be among the nearest neighbors
+        let removed = self.removed_len();
+        let results = self
+            .index
+            .search(query, limit + removed, ef_search.max(limit + removed));
+
+        let ids = self.ids.read().unwrap_or_else(|e| e.into_inner());
         results
             .into_iter()
-            .map(|neighbor| (neighbor.d_id, neighbor.distance))
+            .filter_map(|neighbor| ids[neighbor.d_id].map(|id| (id, neighbor.distance)))
+            .take(limit)
             .collect()
     }
 
+    /// Search for nearest neighbors by comparing the query with every vector
+    fn exact_search(&self, query: &[f32], limit: usize) -> Vec<(usize, f32)> {
+        let ids = self.ids.read().unwrap_or_else(|e| e.into_inner());
+        let mut results: Vec<(usize, f32)> = self
+            .index
+            .get_point_indexation()
+            .into_iter()
+            .filter_map(|point| ids[point.get_origin_id()].map(|id| (id, DistCosine.eval(query, point.get_v()))))
+            .collect();
+        results.sort_by(|a, b| a.1.total_cmp(&b.1));
+        results.truncate(limit);
+        results
+    }
+
     /// Get the number of elements in the index
     ///
     /// # Returns
@@ -89,4 +339,20 @@ impl VectorIndex {
     pub fn is_empty(&self) -> bool {
         self.len() == 0
     }
+
+    /// The IDs of the points in the index with the layer and rank HNSW drew for them
+    #[cfg(test)]
+    pub(crate) fn point_layers(&self) -> Vec<(usize, u8, i32)> {
+        let mut layers: Vec<_> = self
+            .index
+            .get_point_indexation()
+            .into_iter()
+            .map(|point| {
+                let point_id = point.get_point_id();
+                (point.get_origin_id(), point_id.0, point_id.1)
+            })
+            .collect();
+        layers.sort_unstable();
+        layers
+    }
 }
@@ -31,11 +31,14 @@ pub use error::{
 pub use types::{
     DataPoint,
     FileType,
+    IndexingFilter,
     KnowledgeContext,
     OperationStatus,
     OperationType,
     ProgressInfo,
     ProgressStatus,
+    SearchMode,
+    SearchOptions,
     SearchResult,
     SystemStatus,
 };
@@ -0,0 +1,941 @@
+use tree_sitter::{
+    Language,
+    Node,
+    Parser,
+};
+
+use crate::config;
+use crate::processing::text_chunker::{
+    TextChunk,
+    chunk_lines,
+};
+
+/// Items spanning fewer lines are grouped with their neighbors, so imports and constants don't
+/// end up in chunks of their own
+const SMALL_ITEM_LINES: usize = 5;
+
+/// Maximum length of a symbol name taken from a declaration line
+const MAX_SYMBOL_LENGTH: usize = 80;
+
+/// Keywords introducing a named declaration, in any of the supported languages
+const DECLARATION_KEYWORDS: &[&str] = &[
+    "fn",
+    "struct",
+    "enum",
+    "union",
+    "trait",
+    "mod",
+    "type",
+    "const",
+    "static",
+    "macro_rules!",
+    "class",
+    "interface",
+    "record",
+    "object",
+    "protocol",
+    "extension",
+    "namespace",
+    "module",
+    "def",
+    "func",
+    "fun",
+    "function",
+];
+
+/// Keywords of statements which aren't worth naming a chunk after
+const IMPORT_KEYWORDS: &[&str] = &["use", "import", "from", "package", "require", "#include", "extern"];
+
+/// Fields of syntax nodes wrapping the declaration they name, such as exports, decorated
+/// definitions or C declarators
+const WRAPPER_FIELDS: &[&str] = &["declarator", "declaration", "definition", "type"];
+
+/// Parts of the kinds of syntax nodes whose members are chunked on their own when the node
+/// doesn't fit in a chunk
+const CONTAINER_KINDS: &[&str] = &[
+    "class",
+    "impl_item",
+    "trait_item",
+    "mod_item",
+    "interface",
+    "namespace",
+    "module",
+    "protocol",
+    "extension",
+    "object",
+];
+
+/// Nesting of wrappers searched for the name of a declaration
+const MAX_WRAPPER_DEPTH: usize = 4;
+
+/// The tree-sitter grammar of the language of a file extension, if its feature is enabled
+fn grammar(extension: &str) -> Option<Language> {
+    match extension {
+        #[cfg(feature = "grammar-rust")]
+        "rs" => Some(tree_sitter_rust::LANGUAGE.into()),
+        #[cfg(feature = "grammar-python")]
+        "py" => Some(tree_sitter_python::LANGUAGE.into()),
+        #[cfg(feature = "grammar-javascript")]
+        "js" | "jsx" => Some(tree_sitter_javascript::LANGUAGE.into()),
+        #[cfg(feature = "grammar-typescript")]
+        "ts" => Some(tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into()),
+        #[cfg(feature = "grammar-typescript")]
+        "tsx" => Some(tree_sitter_typescript::LANGUAGE_TSX.into()),
+        #[cfg(feature = "grammar-java")]
+        "java" => Some(tree_sitter_java::LANGUAGE.into()),
+        #[cfg(feature = "grammar-c")]
+        "c" | "h" => Some(tree_sitter_c::LANGUAGE.into()),
+        #[cfg(feature = "grammar-cpp")]
+        "cpp" | "hpp" => Some(tree_sitter_cpp::LANGUAGE.into()),
+        #[cfg(feature = "grammar-c-sharp")]
+        "cs" => Some(tree_sitter_c_sharp::LANGUAGE.into()),
+        #[cfg(feature = "grammar-go")]
+        "go" => Some(tree_sitter_go::LANGUAGE.into()),
+        #[cfg(feature = "grammar-ruby")]
+        "rb" => Some(tree_sitter_ruby::LANGUAGE.into()),
+        #[cfg(feature = "grammar-php")]
+        "php" => Some(tree_sitter_php::LANGUAGE_PHP.into()),
+        #[cfg(feature = "grammar-swift")]
+        "swift" => Some(tree_sitter_swift::LANGUAGE.into()),
+        #[cfg(feature = "grammar-bash")]
+        "sh" | "bash" | "zsh" => Some(tree_sitter_bash::LANGUAGE.into()),
+        #[cfg(feature = "grammar-toml")]
+        "toml" => Some(tree_sitter_toml_ng::LANGUAGE.into()),
+        #[cfg(feature = "grammar-yaml")]
+        "yaml" | "yml" => Some(tree_sitter_yaml::LANGUAGE.into()),
+        _ => None,
+    }
+}
+
+/// How the top-level items of a language are delimited
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Syntax {
+    /// Items are delimited by balanced brackets, as in Rust, C or JavaScript
+    Brackets {
+        /// Whether `'` starts lifetimes as well as character literals
+        lifetimes: bool,
+        /// Whether `//` starts a comment
+        line_comments: bool,
+    },
+    /// Items start at the lines without indentation, as in Python or YAML
+    Indentation,
+    /// No item structure is recognized, lines are chunked as they come
+    Lines,
+}
+
+impl Syntax {
+    fn from_extension(extension: &str) -> Self {
+        match extension {
+            "rs" => Syntax::Brackets {
+                lifetimes: true,
+                line_comments: true,
+            },
+            "js" | "jsx" | "ts" | "tsx" | "java" | "c" | "cpp" | "h" | "hpp" | "go" | "php" | "swift" | "kt"
+            | "kts" | "cs" | "scss" | "less" => Syntax::Brackets {
+                lifetimes: false,
+                line_comments: true,
+            },
+            // `//` appears in URLs rather than comments
+            "css" => Syntax::Brackets {
+                lifetimes: false,
+                line_comments: false,
+            },
+            "py" | "rb" | "sass" | "yaml" | "yml" | "toml" | "sh" | "bash" | "zsh" => Syntax::Indentation,
+            _ => Syntax::Lines,
+        }
+    }
+}
+
+/// A top-level item, as a range of line indices
+#[derive(Debug)]
+struct Item {
+    start: usize,
+    end: usize,
+    symbol: Option<String>,
+}
+
+/// Chunk source code along its top-level items
+///
+/// Each function, type or other top-level declaration makes up its own chunk together with its
+/// doc comments and attributes, named after the declared symbol. Runs of small items such as
+/// imports are grouped. Classes, implementations and modules larger than the chunk size are
+/// chunked by their members, and other items larger than the chunk size are split between lines.
+///
+/// Items are found in the syntax tree of the languages with a tree-sitter grammar. The source of
+/// other languages, and the parts of the source which don't parse, are scanned for brackets or
+/// indentation instead.
+///
+/// # Arguments
+///
+/// * `text` - The source code to chunk
+/// * `extension` - Extension of the source file, selecting the language
+/// * `chunk_size` - Optional maximum number of words per chunk (if None, uses config value)
+///
+/// # Returns
+///
+/// A vector of chunks with their line ranges
+pub fn chunk_code(text: &str, extension: &str, chunk_size: Option<usize>) -> Vec<TextChunk> {
+    let chunk_size = chunk_size.unwrap_or_else(|| config::get_config().chunk_size);
+    let lines: Vec<&str> = text.lines().collect();
+    let items = syntax_items(text, &lines, extension, chunk_size).unwrap_or_else(|| scanned_items(&lines, extension));
+    chunks_from_items(&lines, items, chunk_size)
+}
+
+/// Find the items of source code by scanning its lines for brackets or indentation
+fn scanned_items(lines: &[&str], extension: &str) -> Vec<Item> {
+    match Syntax::from_extension(extension) {
+        Syntax::Brackets {
+            lifetimes,
+            line_comments,
+        } => bracket_items(lines, lifetimes, line_comments),
+        Syntax::Indentation => indentation_items(lines),
+        Syntax::Lines => match lines.iter().position(|line| !line.trim().is_empty()) {
+            Some(start) => vec![Item {
+                start,
+                end: last_non_blank(lines, start, lines.len() - 1),
+                symbol: None,
+            }],
+            None => Vec::new(),
+        },
+    }
+}
+
+/// Find the items in the syntax tree of the source, if its language has a grammar and any of the
+/// source parses
+fn syntax_items(text: &str, lines: &[&str], extension: &str, chunk_size: usize) -> Option<Vec<Item>> {
+    let mut parser = Parser::new();
+    parser.set_language(&grammar(extension)?).ok()?;
+    let tree = parser.parse(text, None)?;
+    let mut root = tree.root_node();
+    if root.is_error() {
+        return None;
+    }
+    // Documents such as YAML wrap their items in a single node
+    while root.named_child_count() == 1 {
+        match root.named_child(0) {
+            Some(child) if !is_preamble_node(child) && !child.is_error() => root = child,
+            _ => break,
+        }
+    }
+
+    let mut items = Vec::new();
+    node_items(root, text, lines, extension, chunk_size, &mut items);
+
+    // Statements sharing a line make up a single item
+    let mut merged: Vec<Item> = Vec::with_capacity(items.len());
+    for item in items {
+        match merged.last_mut() {
+            Some(last) if item.start <= last.end => {
+                last.end = last.end.max(item.end);
+                last.symbol = last.symbol.take().or(item.symbol);
+            },
+            _ => merged.push(item),
+        }
+    }
+    Some(merged)
+}
+
+/// Add the items of the children of a syntax node
+///
+/// Comments and attributes preceding a child without a blank line in between belong to its item.
+/// The lines of children which don't parse are scanned for items instead.
+fn node_items(node: Node<'_>, text: &str, lines: &[&str], extension: &str, chunk_size: usize, items: &mut Vec<Item>) {
+    let mut preamble: Option<(usize, usize)> = None;
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        let (start, end) = (child.start_position().row, end_row(child, lines));
+        if let Some((preamble_start, preamble_end)) = preamble {
+            if lines[preamble_end + 1..start.max(preamble_end + 1)]
+                .iter()
+                .any(|line| line.trim().is_empty())
+            {
+                items.push(Item {
+                    start: preamble_start,
+                    end: preamble_end,
+                    symbol: None,
+                });
+                preamble = None;
+            }
+        }
+        if is_preamble_node(child) {
+            preamble = Some((preamble.map_or(start, |(start, _)| start), end));
+            continue;
+        }
+
+        let start = preamble.take().map_or(start, |(start, _)| start);
+        if child.is_error() {
+            items.extend(
+                error_items(&lines[start..=end], extension)
+                    .into_iter()
+                    .map(|item| Item {
+                        start: start + item.start,
+                        end: start + item.end,
+                        symbol: item.symbol,
+                    }),
+            );
+            continue;
+        }
+        let symbol = node_symbol(child, text, lines);
+        let words: usize = lines[start..=end]
+            .iter()
+            .map(|line| line.split_whitespace().count())
+            .sum();
+        match members(child) {
+            Some(body) if words > chunk_size => {
+                let first_member = body.named_child(0).map_or(end, |member| member.start_position().row);
+                if first_member > start {
+                    items.push(Item {
+                        start,
+                        end: first_member - 1,
+                        symbol,
+                    });
+                }
+                node_items(body, text, lines, extension, chunk_size, items);
+                // The closing lines of the container belong to its last member
+                if let Some(last) = items.last_mut() {
+                    last.end = last.end.max(end);
+                }
+            },
+            _ => items.push(Item { start, end, symbol }),
+        }
+    }
+    if let Some((start, end)) = preamble {
+        items.push(Item {
+            start,
+            end,
+            symbol: None,
+        });
+    }
+}
+
+/// Find the items in lines of source code which don't parse, relative to the first line
+///
+/// The lines are scanned without their common indentation, since they may come from the body of
+/// a container. Lines in which no item is found make up a single item.
+fn error_items(lines: &[&str], extension: &str) -> Vec<Item> {
+    let indent = lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    let dedented: Vec<&str> = lines
+        .iter()
+        .map(|line| line.get(indent..).unwrap_or_else(|| line.trim_start()))
+        .collect();
+    let items = scanned_items(&dedented, extension);
+    if !items.is_empty() || lines.iter().all(|line| line.trim().is_empty()) {
+        return items;
+    }
+    let start = lines.iter().position(|line| !line.trim().is_empty()).unwrap_or(0);
+    vec![Item {
+        start,
+        end: last_non_blank(lines, start, lines.len() - 1),
+        symbol: None,
+    }]
+}
+
+/// The last line of a syntax node, which some grammars end at the start of the next line
+fn end_row(node: Node<'_>, lines: &[&str]) -> usize {
+    let end = node.end_position();
+    let row = if end.column == 0 && end.row > node.start_position().row {
+        end.row - 1
+    } else {
+        end.row
+    };
+    row.min(lines.len().saturating_sub(1))
+}
+
+/// Whether a syntax node belongs to the item that follows it, such as a comment or an attribute
+fn is_preamble_node(node: Node<'_>) -> bool {
+    let kind = node.kind();
+    kind.contains("comment")
+        || matches!(
+            kind,
+            "attribute_item" | "inner_attribute_item" | "attribute" | "attribute_list" | "decorator" | "annotation"
+        )
+}
+
+/// The body holding the members of a container such as a class, if a syntax node is one
+fn members(node: Node<'_>) -> Option<Node<'_>> {
+    if CONTAINER_KINDS.iter().any(|kind| node.kind().contains(kind)) {
+        return node
+            .child_by_field_name("body")
+            .filter(|body| body.named_child_count() > 1);
+    }
+    ["declaration", "definition"]
+        .iter()
+        .find_map(|field| node.child_by_field_name(field))
+        .and_then(members)
+}
+
+/// Find the name of the symbol declared by a syntax node
+fn node_symbol(node: Node<'_>, text: &str, lines: &[&str]) -> Option<String> {
+    let head = lines[node.start_position().row].trim();
+    if is_import(head) {
+        return None;
+    }
+    if node.kind() == "impl_item" {
+        // Name implementations after the implemented trait and type
+        let node_text = |field| node.child_by_field_name(field).map(|node| &text[node.byte_range()]);
+        let name = match (node_text("trait"), node_text("type")) {
+            (Some(trait_name), Some(type_name)) => Some(format!("{trait_name} for {type_name}")),
+            (None, type_name) => type_name.map(str::to_string),
+            (trait_name, None) => trait_name.map(str::to_string),
+        };
+        if let Some(name) = name {
+            return non_empty(&name.split_whitespace().collect::<Vec<_>>().join(" "));
+        }
+    }
+
+    declared_name(node, text, 0)
+        .and_then(|name| non_empty(name.lines().next().unwrap_or_default().trim()))
+        .or_else(|| symbol_name(head))
+}
+
+/// The name of a declaration, searched through the wrappers of the declaration
+fn declared_name<'a>(node: Node<'_>, text: &'a str, depth: usize) -> Option<&'a str> {
+    if node.kind().ends_with("identifier") {
+        return Some(&text[node.byte_range()]);
+    }
+    if let Some(name) = node
+        .child_by_field_name("name")
+        .or_else(|| node.child_by_field_name("key"))
+    {
+        return Some(&text[name.byte_range()]);
+    }
+    if depth == MAX_WRAPPER_DEPTH {
+        return None;
+    }
+    if let Some(name) = WRAPPER_FIELDS
+        .iter()
+        .filter_map(|field| node.child_by_field_name(field))
+        .find_map(|wrapped| declared_name(wrapped, text, depth + 1))
+    {
+        return Some(name);
+    }
+    // Declarations listing specs or declarators, as in Go or JavaScript, are named by the first
+    let mut cursor = node.walk();
+    node.named_children(&mut cursor)
+        .find(|child| child.kind().ends_with("_spec") || child.kind().ends_with("declarator"))
+        .and_then(|child| declared_name(child, text, depth + 1))
+}
+
+/// Group the items into chunks
+fn chunks_from_items(lines: &[&str], items: Vec<Item>, chunk_size: usize) -> Vec<TextChunk> {
+    let word_count = |item: &Item| -> usize {
+        lines[item.start..=item.end]
+            .iter()
+            .map(|line| line.split_whitespace().count())
+            .sum()
+    };
+
+    let mut chunks = Vec::new();
+    let mut group: Vec<Item> = Vec::new();
+    let mut group_words = 0;
+    let flush = |group: &mut Vec<Item>, chunks: &mut Vec<TextChunk>| {
+        if let (Some(first), Some(last)) = (group.first(), group.last()) {
+            let symbols: Vec<&str> = group.iter().filter_map(|item| item.symbol.as_deref()).collect();
+            chunks.push(TextChunk {
+                text: lines[first.start..=last.end].join("\n"),
+                symbol: (!symbols.is_empty()).then(|| symbols.join(", ")),
+                lines: Some((first.start + 1, last.end + 1)),
+            });
+        }
+        group.clear();
+    };
+
+    for item in items {
+        let words = word_count(&item);
+        if item.end - item.start + 1 < SMALL_ITEM_LINES && words <= chunk_size {
+            if group_words + words > chunk_size {
+                flush(&mut group, &mut chunks);
+                group_words = 0;
+            }
+            group_words += words;
+            group.push(item);
+            continue;
+        }
+
+        flush(&mut group, &mut chunks);
+        group_words = 0;
+        chunks.extend(chunk_lines(
+            &lines[item.start..=item.end],
+            item.start + 1,
+            chunk_size,
+            item.symbol.as_deref(),
+        ));
+    }
+    flush(&mut group, &mut chunks);
+
+    chunks
+}
+
+/// Find the items of a language delimiting blocks with brackets
+///
+/// An item starts at a line outside of any bracket and ends once its brackets are balanced on a
+/// line ending with `;` or `}`, or at the next blank line.
+fn bracket_items(lines: &[&str], lifetimes: bool, line_comments: bool) -> Vec<Item> {
+    let mut scanner = BracketScanner {
+        lifetimes,
+        line_comments,
+        ..Default::default()
+    };
+    let mut items = Vec::new();
+    let mut start = None;
+    let mut head = None;
+
+    for (i, line) in lines.iter().enumerate() {
+        let trimmed = line.trim();
+        if scanner.at_top_level() {
+            if trimmed.is_empty() {
+                if let Some(start) = start.take() {
+                    items.push(item(lines, start, i - 1, head.take()));
+                }
+                continue;
+            }
+            start.get_or_insert(i);
+            if head.is_none() && !is_preamble(trimmed) {
+                head = Some(i);
+            }
+        }
+
+        scanner.scan(line);
+        if let Some(item_start) = start {
+            if head.is_some() && scanner.at_top_level() && ends_item(trimmed) {
+                items.push(item(lines, item_start, i, head.take()));
+                start = None;
+            }
+        }
+    }
+    if let Some(start) = start {
+        items.push(item(lines, start, lines.len() - 1, head));
+    }
+
+    items
+}
+
+/// Find the items of a language delimiting blocks with indentation
+///
+/// An item starts at a line without indentation, after its comments and decorators, and extends
+/// over the indented lines and the closing keywords such as `end` that follow it.
+fn indentation_items(lines: &[&str]) -> Vec<Item> {
+    let mut items = Vec::new();
+    let mut start = None;
+    let mut head = None;
+    let mut in_docstring = false;
+
+    for (i, line) in lines.iter().enumerate() {
+        let trimmed = line.trim();
+        let continues = in_docstring || trimmed.is_empty() || line.starts_with(char::is_whitespace);
+        in_docstring ^= (line.matches("\"\"\"").count() + line.matches("'''").count()) % 2 == 1;
+        if continues || is_closer(trimmed) {
+            continue;
+        }
+
+        if let (Some(item_start), Some(_)) = (start, head) {
+            items.push(item(lines, item_start, i - 1, head.take()));
+            start = None;
+        }
+        start.get_or_insert(i);
+        if !is_preamble(trimmed) {
+            head = Some(i);
+        }
+    }
+    if let Some(start) = start {
+        items.push(item(lines, start, lines.len() - 1, head));
+    }
+
+    items
+}
+
+fn item(lines: &[&str], start: usize, end: usize, head: Option<usize>) -> Item {
+    Item {
+        start,
+        end: last_non_blank(lines, start, end),
+        symbol: head.and_then(|head| symbol_name(lines[head].trim())),
+    }
+}
+
+fn last_non_blank(lines: &[&str], start: usize, end: usize) -> usize {
+    (start..=end)
+        .rev()
+        .find(|&i| !lines[i].trim().is_empty())
+        .unwrap_or(start)
+}
+
+/// Whether a line belongs to the item that follows it, such as a comment or an attribute
+fn is_preamble(trimmed: &str) -> bool {
+    ["//", "/*", "*", "#[", "#!", "@", "# ", "--"]
+        .iter()
+        .any(|prefix| trimmed.starts_with(prefix))
+        || trimmed == "#"
+}
+
+/// Whether a line at the top level completes the item it belongs to
+fn ends_item(trimmed: &str) -> bool {
+    if is_preamble(trimmed) {
+        return false;
+    }
+    // Preprocessor directives hold on a single line
+    trimmed.ends_with(';')
+        || trimmed.ends_with('}')
+        || trimmed.starts_with('#')
+        || (trimmed.starts_with([')', ']']) && trimmed.ends_with([')', ']']))
+}
+
+/// Whether a line without indentation closes or continues the item before it
+fn is_closer(trimmed: &str) -> bool {
+    let first_word = trimmed.split(|c: char| !c.is_alphanumeric()).next().unwrap_or_default();
+    trimmed.starts_with(['}', ')', ']'])
+        || [
+            "end", "fi", "done", "esac", "else", "elif", "elsif", "except", "finally", "rescue", "ensure",
+        ]
+        .contains(&first_word)
+}
+
+/// Find the name of the symbol declared on a line
+fn symbol_name(trimmed: &str) -> Option<String> {
+    let words: Vec<&str> = trimmed
+        .split(|c: char| c.is_whitespace() || matches!(c, '(' | ')' | '{' | '<' | ':' | '=' | ';' | ','))
+        .filter(|word| !word.is_empty())
+        .collect();
+    if is_import(trimmed) {
+        return None;
+    }
+
+    if let Some(position) = words.iter().position(|word| *word == "impl") {
+        // Name implementations after the implemented trait and type
+        let signature = trimmed.split_once("impl").map_or("", |(_, rest)| rest);
+        let signature = signature.split(['{', ';']).next().unwrap_or_default();
+        let signature = signature.split(" where").next().unwrap_or_default().trim();
+        let signature = match signature.strip_prefix('<') {
+            Some(generics) => skip_generics(generics).trim(),
+            None => signature,
+        };
+        return non_empty(signature).or_else(|| words.get(position + 1).map(|word| (*word).to_string()));
+    }
+    // `static` and `const` also qualify C functions, named by the identifier before their parameters
+    let is_function = trimmed
+        .find('(')
+        .is_some_and(|paren| trimmed.find('=').is_none_or(|eq| paren < eq));
+    if let Some(position) = words
+        .iter()
+        .position(|word| DECLARATION_KEYWORDS.contains(word) && !(is_function && matches!(*word, "static" | "const")))
+    {
+        let name = if matches!(words[position], "func" | "fun") && trimmed.contains("func (") {
+            // Go methods declare their receiver before their name
+            trimmed
+                .split_once(')')
+                .and_then(|(_, rest)| rest.split('(').next())
+                .map(str::trim)
+        } else {
+            words.get(position + 1).copied()
+        };
+        if let Some(name) = name.and_then(non_empty) {
+            return Some(name);
+        }
+    }
+
+    // Functions without a keyword, as in C, are named by the identifier before their parameters
+    if let Some((before, _)) = trimmed.split_once('(') {
+        if let Some(name) = before.split_whitespace().last() {
+            let name = name.trim_start_matches(['*', '&']);
+            if !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || matches!(c, '_' | ':' | '.' | '$'))
+            {
+                return Some(name.to_string());
+            }
+        }
+    }
+
+    // Otherwise keys, selectors and table headers name themselves
+    let name = trimmed.split(['{', '=', ':']).next().unwrap_or_default();
+    non_empty(name.trim().trim_start_matches('[').trim_end_matches(']').trim())
+}
+
+/// Whether a line imports other code rather than declaring a symbol
+fn is_import(trimmed: &str) -> bool {
+    trimmed
+        .split(|c: char| c.is_whitespace() || c == '(')
+        .next()
+        .is_some_and(|word| IMPORT_KEYWORDS.contains(&word))
+}
+
+/// Skip the generic parameters opened before `generics`
+fn skip_generics(generics: &str) -> &str {
+    let mut depth = 1;
+    for (i, c) in generics.char_indices() {
+        match c {
+            '<' => depth += 1,
+            '>' => {
+                depth -= 1;
+                if depth == 0 {
+                    return &generics[i + 1..];
+                }
+            },
+            _ => {},
+        }
+    }
+    ""
+}
+
+fn non_empty(name: &str) -> Option<String> {
+    (!name.is_empty()).then(|| name.chars().take(MAX_SYMBOL_LENGTH).collect())
+}
+
+/// Tracks the bracket depth of source code, skipping strings and comments
+#[derive(Debug, Default)]
+struct BracketScanner {
+    lifetimes: bool,
+    line_comments: bool,
+    depth: usize,
+    in_block_comment: bool,
+    string_quote: Option<char>,
+}
+
+impl BracketScanner {
+    fn at_top_level(&self) -> bool {
+        self.depth == 0 && !self.in_block_comment && self.string_quote.is_none()
+    }
+
+    fn scan(&mut self, line: &str) {
+        let chars: Vec<char> = line.chars().collect();
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i];
+            let next = chars.get(i + 1).copied();
+            if self.in_block_comment {
+                if c == '*' && next == Some('/') {
+                    self.in_block_comment = false;
+                    i += 1;
+                }
+            } else if let Some(quote) = self.string_quote {
+                if c == '\\' {
+                    i += 1;
+                } else if c == quote {
+                    self.string_quote = None;
+                }
+            } else {
+                match c {
+                    '/' if self.line_comments && next == Some('/') => break,
+                    '/' if next == Some('*') => {
+                        self.in_block_comment = true;
+                        i += 1;
+                    },
+                    // A lifetime such as 'a isn't closed like a character literal
+                    '\'' if self.lifetimes && !is_char_literal(&chars[i + 1..]) => {},
+                    '"' | '\'' | '`' => self.string_quote = Some(c),
+                    '{' | '(' | '[' => self.depth += 1,
+                    '}' | ')' | ']' => self.depth = self.depth.saturating_sub(1),
+                    _ => {},
+                }
+            }
+            i += 1;
+        }
+
+        // Only template literals and Rust strings span several lines
+        if self.string_quote == Some('\'') || (self.string_quote == Some('"') && !self.lifetimes) {
+            self.string_quote = None;
+        }
+    }
+}
+
+/// Whether the characters following a `'` make up a character literal
+fn is_char_literal(rest: &[char]) -> bool {
+    matches!(rest, ['\\', ..] | [_, '\'', ..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RUST: &str = r#"use std::fmt;
+use std::io;
+
+/// A parser
+#[derive(Debug)]
+pub struct Parser<'a> {
+    input: &'a str,
+}
+
+impl<'a> fmt::Display for Parser<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let brace = '{';
+        write!(f, "{} {}", brace, self.input)
+    }
+}
+
+// Parses the input
+pub fn parse(input: &str) -> Result<(), io::Error> {
+    /* a comment with a } brace */
+    let _ = "a string with a } brace";
+    Ok(())
+}
+"#;
+
+    #[test]
+    fn test_chunk_rust() {
+        let chunks = chunk_code(RUST, "rs", Some(500));
+        let summary: Vec<_> = chunks
+            .iter()
+            .map(|chunk| (chunk.symbol.as_deref(), chunk.lines))
+            .collect();
+
+        assert_eq!(summary, vec![
+            (None, Some((1, 2))),
+            (Some("Parser"), Some((4, 8))),
+            (Some("fmt::Display for Parser<'a>"), Some((10, 15))),
+            (Some("parse"), Some((17, 22))),
+        ]);
+        assert!(chunks[1].text.starts_with("/// A parser\n#[derive(Debug)]"));
+        assert!(chunks[3].text.ends_with("Ok(())\n}"));
+    }
+
+    #[test]
+    fn test_chunk_python() {
+        let python = "import os\n\n\n@decorator\ndef first(a):\n    \"\"\"Docstring\n\nwith a blank line\n    \"\"\"\n    if a:\n        return 1\n\n    return 2\n\n\nclass Second:\n    def method(self):\n        pass\n\n    def other(self):\n        pass\n";
+        let chunks = chunk_code(python, "py", Some(500));
+
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks[0].lines, Some((1, 1)));
+        assert_eq!(chunks[1].symbol.as_deref(), Some("first"));
+        assert_eq!(chunks[1].lines, Some((4, 13)));
+        assert_eq!(chunks[2].symbol.as_deref(), Some("Second"));
+        assert_eq!(chunks[2].lines, Some((16, 21)));
+    }
+
+    #[test]
+    #[cfg(feature = "all-grammars")]
+    fn test_chunk_groups_small_items() {
+        let go =
+            "package main\n\nconst A = 1\nconst B = 2\n\nfunc (s *Server) Start(port int) error {\n\treturn nil\n}\n";
+        let chunks = chunk_code(go, "go", Some(500));
+
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].symbol.as_deref(), Some("A, B, Start"));
+        assert_eq!(chunks[0].lines, Some((1, 8)));
+    }
+
+    #[test]
+    fn test_chunk_splits_large_items() {
+        let body: Vec<String> = (0..20).map(|i| format!("    let x{} = {};", i, i)).collect();
+        let rust = format!("fn large() {{\n{}\n}}\n", body.join("\n"));
+        let chunks = chunk_code(&rust, "rs", Some(20));
+
+        assert!(chunks.len() > 1);
+        assert!(chunks.iter().all(|chunk| chunk.symbol.as_deref() == Some("large")));
+        assert_eq!(chunks[0].lines.unwrap().0, 1);
+        assert_eq!(chunks.last().unwrap().lines.unwrap().1, 22);
+    }
+
+    #[test]
+    #[cfg(feature = "all-grammars")]
+    fn test_chunk_splits_large_classes_by_members() {
+        let methods: Vec<String> = (0..3)
+            .map(|i| {
+                format!("    /** Method {i} */\n    public int method{i}(int a) {{\n        return a + {i};\n    }}")
+            })
+            .collect();
+        let java = format!(
+            "package app;\n\n@Service\npublic class Large {{\n    private int count = 0;\n\n{}\n}}\n",
+            methods.join("\n\n")
+        );
+        let chunks = chunk_code(&java, "java", Some(25));
+        let summary: Vec<_> = chunks
+            .iter()
+            .map(|chunk| (chunk.symbol.as_deref(), chunk.lines))
+            .collect();
+
+        assert_eq!(summary, vec![
+            (Some("Large, count"), Some((1, 5))),
+            (Some("method0"), Some((7, 10))),
+            (Some("method1"), Some((12, 15))),
+            (Some("method2"), Some((17, 21))),
+        ]);
+        assert!(chunks[3].text.ends_with("    }\n}"));
+    }
+
+    #[test]
+    fn test_chunk_names_wrapped_declarations() {
+        let typescript = "import { a } from \"a\";\n\nexport const handler = async (event: Event) => {\n  return a(event);\n};\n\nexport default class App {}\n";
+        let chunks = chunk_code(typescript, "ts", Some(500));
+        let symbols: Vec<_> = chunks.iter().map(|chunk| chunk.symbol.as_deref()).collect();
+        assert_eq!(symbols, vec![Some("handler, App")]);
+
+        let c = "#include <stdio.h>\n\nstatic int *make(void) {\n    return NULL;\n}\n";
+        let chunks = chunk_code(c, "c", Some(500));
+        let symbols: Vec<_> = chunks.iter().map(|chunk| chunk.symbol.as_deref()).collect();
+        assert_eq!(symbols, vec![Some("make")]);
+
+        let yaml = "services:\n  web:\n    image: nginx\nvolumes:\n  data: {}\n";
+        let chunks = chunk_code(yaml, "yaml", Some(500));
+        let symbols: Vec<_> = chunks.iter().map(|chunk| chunk.symbol.as_deref()).collect();
+        assert_eq!(symbols, vec![Some("services, volumes")]);
+    }
+
+    #[test]
+    #[cfg(feature = "all-grammars")]
+    fn test_grammars_load() {
+        for extension in [
+            "rs", "py", "js", "ts", "tsx", "java", "c", "cpp", "cs", "go", "rb", "php", "swift", "sh", "toml", "yaml",
+        ] {
+            let mut parser = Parser::new();
+            assert!(parser.set_language(&grammar(extension).unwrap()).is_ok(), "{extension}");
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "all-grammars")]
+    fn test_chunk_scans_syntax_errors() {
+        let items = |text: &str, extension| -> Vec<_> {
+            let lines: Vec<&str> = text.lines().collect();
+            syntax_items(text, &lines, extension, 500)
+                .unwrap()
+                .into_iter()
+                .map(|item| (item.start + 1, item.end + 1, item.symbol))
+                .collect()
+        };
+
+        // The items around the error still come from the syntax tree
+        let rust = "/// First\nfn first() {\n    1\n}\n\nimpl {\n    fn x() {}\n\nfn second() {}\n";
+        assert_eq!(items(rust, "rs"), vec![(1, 4, Some("first".to_string())), (6, 9, None)]);
+
+        let python = "def first():\n    return 1\n\n\ndef second(:\n    pass\n\n\nx = [\n\ndef third():\n    pass\n";
+        assert_eq!(items(python, "py"), vec![
+            (1, 2, Some("first".to_string())),
+            (5, 6, Some("second".to_string())),
+            (9, 9, Some("x".to_string())),
+            (11, 12, Some("third".to_string())),
+        ]);
+    }
+
+    #[test]
+    fn test_symbol_name() {
+        assert_eq!(symbol_name("pub async fn run(&self) {").as_deref(), Some("run"));
+        assert_eq!(
+            symbol_name("export default class App extends Component {").as_deref(),
+            Some("App")
+        );
+        assert_eq!(symbol_name("static int main(void) {").as_deref(), Some("main"));
+        assert_eq!(symbol_name("pub const fn new() -> Self {").as_deref(), Some("new"));
+        assert_eq!(symbol_name("const MAX: usize = 10;").as_deref(), Some("MAX"));
+        assert_eq!(
+            symbol_name("export const handler = (event) => {").as_deref(),
+            Some("handler")
+        );
+        assert_eq!(
+            symbol_name("func (s *Server) Start(port int) error {").as_deref(),
+            Some("Start")
+        );
+        assert_eq!(
+            symbol_name("impl<T: Clone> From<T> for Wrapper<T> where T: Debug {").as_deref(),
+            Some("From<T> for Wrapper<T>")
+        );
+        assert_eq!(symbol_name("[package]").as_deref(), Some("package"));
+        assert_eq!(symbol_name("services:").as_deref(), Some("services"));
+        assert_eq!(symbol_name("use std::fmt;"), None);
+    }
+}
@@ -7,8 +7,17 @@ use crate::error::{
     Result,
     SemanticSearchError,
 };
-use crate::processing::text_chunker::chunk_text;
-use crate::types::FileType;
+use crate::processing::code_chunker::chunk_code;
+use crate::processing::file_walker::walk_files;
+use crate::processing::markdown_chunker::chunk_markdown;
+use crate::processing::text_chunker::{
+    TextChunk,
+    chunk_text,
+};
+use crate::types::{
+    FileType,
+    IndexingFilter,
+};
 
 /// Determine the file type based on extension
 pub fn get_file_type(path: &Path) -> FileType {
@@ -67,8 +76,17 @@ pub fn process_file(path: &Path) -> Result<Vec<Value>> {
     match file_type {
         FileType::Text | FileType::Markdown | FileType::Code => {
             // For text-based files, chunk the content and create multiple data points
-            // Use the configured chunk size and overlap
-            let chunks = chunk_text(&content, None, None);
+            // Code and Markdown are chunked along their structure, other text by word windows
+            // of the configured chunk size and overlap
+            let extension = path.extension().and_then(|ext| ext.to_str()).unwrap_or("unknown");
+            let chunks: Vec<TextChunk> = match file_type {
+                FileType::Code => chunk_code(&content, extension, None),
+                FileType::Markdown => chunk_markdown(&content, None),
+                _ => chunk_text(&content, None, None)
+                    .into_iter()
+                    .map(TextChunk::from)
+                    .collect(),
+            };
             let path_str = path.to_string_lossy().to_string();
             let file_type_str = format!("{:?}", file_type);
 
@@ -76,23 +94,22 @@ pub fn process_file(path: &Path) -> Result<Vec<Value>> {
 
             for (i, chunk) in chunks.iter().enumerate() {
                 let mut metadata = serde_json::Map::new();
-                metadata.insert("text".to_string(), Value::String(chunk.clone()));
+                metadata.insert("text".to_string(), Value::String(chunk.text.clone()));
                 metadata.insert("path".to_string(), Value::String(path_str.clone()));
                 metadata.insert("file_type".to_string(), Value::String(file_type_str.clone()));
                 metadata.insert("chunk_index".to_string(), Value::Number((i as u64).into()));
                 metadata.insert("total_chunks".to_string(), Value::Number((chunks.len() as u64).into()));
+                if let Some(symbol) = &chunk.symbol {
+                    metadata.insert("symbol".to_string(), Value::String(symbol.clone()));
+                }
+                if let Some((start_line, end_line)) = chunk.lines {
+                    metadata.insert("start_line".to_string(), Value::Number((start_line as u64).into()));
+                    metadata.insert("end_line".to_string(), Value::Number((end_line as u64).into()));
+                }
 
                 // For code files, add additional metadata
                 if file_type == FileType::Code {
-                    metadata.insert(
-                        "language".to_string(),
-                        Value::String(
-                            path.extension()
-                                .and_then(|ext| ext.to_str())
-                                .unwrap_or("unknown")
-                                .to_string(),
-                        ),
-                    );
+                    metadata.insert("language".to_string(), Value::String(extension.to_string()));
                 }
 
                 results.push(Value::Object(metadata));
@@ -144,32 +161,17 @@ pub fn process_file(path: &Path) -> Result<Vec<Value>> {
 /// # Arguments
 ///
 /// * `dir_path` - Path to the directory
+/// * `filter` - Selection of the files to process
 ///
 /// # Returns
 ///
 /// A vector of JSON objects representing the content of all files
-pub fn process_directory(dir_path: &Path) -> Result<Vec<Value>> {
+pub fn process_directory(dir_path: &Path, filter: &IndexingFilter) -> Result<Vec<Value>> {
     let mut results = Vec::new();
 
-    for entry in walkdir::WalkDir::new(dir_path)
-        .follow_links(true)
-        .into_iter()
-        .filter_map(|e| e.ok())
-        .filter(|e| e.file_type().is_file())
-    {
-        let path = entry.path();
-
-        // Skip hidden files
-        if path
-            .file_name()
-            .and_then(|n| n.to_str())
-            .is_some_and(|s| s.starts_with('.'))
-        {
-            continue;
-        }
-
+    for path in walk_files(dir_path, filter)? {
         // Process the file
-        if let Ok(mut items) = process_file(path) {
+        if let Ok(mut items) = process_file(&path) {
             results.append(&mut items);
         }
     }
@@ -0,0 +1,411 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::{
+    Component,
+    Path,
+    PathBuf,
+};
+
+use globset::{
+    Glob,
+    GlobSet,
+    GlobSetBuilder,
+};
+use ignore::gitignore::{
+    Gitignore,
+    GitignoreBuilder,
+};
+use ignore::{
+    Match,
+    WalkBuilder,
+};
+
+use crate::error::{
+    Result,
+    SemanticSearchError,
+};
+use crate::types::IndexingFilter;
+
+/// Number of bytes read from the start of a file to tell whether it is binary
+const BINARY_SNIFF_LEN: u64 = 8192;
+
+/// Files holding ignore rules, relative to the directory they apply to, in decreasing order of
+/// precedence
+const IGNORE_FILES: &[&str] = &[".ignore", ".gitignore", ".git/info/exclude"];
+
+/// Compiled include and exclude patterns of an [`IndexingFilter`]
+#[derive(Debug, Clone)]
+pub struct FileMatcher {
+    include: Option<GlobSet>,
+    exclude: GlobSet,
+}
+
+impl FileMatcher {
+    /// Compile the patterns of a filter
+    ///
+    /// # Arguments
+    ///
+    /// * `filter` - The filter to compile
+    ///
+    /// # Returns
+    ///
+    /// The matcher, or an error naming the first invalid pattern
+    pub fn new(filter: &IndexingFilter) -> Result<Self> {
+        let include = match filter.include.is_empty() {
+            true => None,
+            false => Some(build_glob_set(&filter.include)?),
+        };
+
+        Ok(Self {
+            include,
+            exclude: build_glob_set(&filter.exclude)?,
+        })
+    }
+
+    /// Whether a file or directory matches the exclude patterns
+    ///
+    /// Patterns are matched against the path relative to the root and against the file name, so
+    /// `node_modules` excludes such directories at any depth.
+    pub fn is_excluded(&self, root: &Path, path: &Path) -> bool {
+        matches(&self.exclude, root, path)
+    }
+
+    /// Whether a file matches the include patterns, always true without include patterns
+    pub fn is_included(&self, root: &Path, path: &Path) -> bool {
+        self.include.as_ref().is_none_or(|include| matches(include, root, path))
+    }
+}
+
+fn build_glob_set(patterns: &[String]) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        // A trailing slash marks directories in gitignore syntax, which are matched by name here
+        let glob = Glob::new(pattern.trim_end_matches('/'))
+            .map_err(|e| SemanticSearchError::InvalidArgument(format!("Invalid pattern '{}': {}", pattern, e)))?;
+        builder.add(glob);
+    }
+    builder
+        .build()
+        .map_err(|e| SemanticSearchError::InvalidArgument(format!("Invalid patterns: {}", e)))
+}
+
+fn matches(set: &GlobSet, root: &Path, path: &Path) -> bool {
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    (!relative.as_os_str().is_empty() && set.is_match(relative))
+        || path.file_name().is_some_and(|name| set.is_match(name))
+}
+
+/// Walk the files to index under a path
+///
+/// Hidden files and directories are skipped, as are binary files, files ignored by `.gitignore`
+/// or `.ignore` files (unless the filter disables them) and files rejected by the include and
+/// exclude patterns of the filter. Files are listed in a stable order.
+///
+/// # Arguments
+///
+/// * `path` - The directory to walk, or a single file
+/// * `filter` - The selection of files to index
+///
+/// # Returns
+///
+/// An iterator over the paths of the files, or an error if a pattern is invalid
+pub fn walk_files(path: &Path, filter: &IndexingFilter) -> Result<impl Iterator<Item = PathBuf> + use<>> {
+    let matcher = FileMatcher::new(filter)?;
+    let root = path.to_path_buf();
+
+    let exclude_matcher = matcher.clone();
+    let exclude_root = root.clone();
+    let walker = WalkBuilder::new(path)
+        .standard_filters(!filter.no_ignore)
+        .hidden(true)
+        // Honor .gitignore files even in directories that are not git repositories
+        .require_git(false)
+        .follow_links(true)
+        .sort_by_file_name(|a, b| a.cmp(b))
+        .filter_entry(move |entry| entry.depth() == 0 || !exclude_matcher.is_excluded(&exclude_root, entry.path()))
+        .build();
+
+    Ok(walker
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_some_and(|file_type| file_type.is_file()))
+        .map(|entry| entry.into_path())
+        .filter(move |path| matcher.is_included(&root, path))
+        .filter(|path| !is_binary(path)))
+}
+
+/// Tells whether paths under a root are walked by [walk_files]
+///
+/// Used to match changed paths with the files indexed from the root, with the same patterns and
+/// ignore rules. The ignore files read are cached until [Self::clear_ignore_files] is called, and
+/// the global git ignore file is read once.
+#[derive(Debug)]
+pub struct WalkMatcher {
+    root: PathBuf,
+    matcher: FileMatcher,
+    no_ignore: bool,
+    /// Rules of the ignore files read so far, by path of the file
+    ignore_files: HashMap<PathBuf, Option<Gitignore>>,
+    /// Rules of the global git ignore file
+    global_ignore: Gitignore,
+}
+
+impl WalkMatcher {
+    /// Compile the patterns of a filter for the files under a root
+    ///
+    /// # Arguments
+    ///
+    /// * `root` - The directory walked, or a single file
+    /// * `filter` - The selection of files to index
+    ///
+    /// # Returns
+    ///
+    /// The matcher, or an error naming the first invalid pattern
+    pub fn new(root: &Path, filter: &IndexingFilter) -> Result<Self> {
+        Ok(Self {
+            root: root.to_path_buf(),
+            matcher: FileMatcher::new(filter)?,
+            no_ignore: filter.no_ignore,
+            ignore_files: HashMap::new(),
+            global_ignore: Gitignore::global().0,
+        })
+    }
+
+    /// Replace the rules of the global git ignore file, so tests don't depend on the git
+    /// configuration of the host
+    #[cfg(test)]
+    pub(crate) fn with_global_ignore(mut self, global_ignore: Gitignore) -> Self {
+        self.global_ignore = global_ignore;
+        self
+    }
+
+    /// Whether a path is a file [walk_files] lists, or may hold or have been such files
+    ///
+    /// Paths which no longer exist are matched as files as far as possible: only hidden, excluded
+    /// and ignored paths are rejected, since they may have been directories.
+    pub fn is_walked(&mut self, path: &Path) -> bool {
+        let Ok(relative) = path.strip_prefix(&self.root) else {
+            return false;
+        };
+        if relative.as_os_str().is_empty() {
+            return true;
+        }
+
+        // Hidden and excluded directories aren't entered
+        let mut ancestor = self.root.clone();
+        for component in relative.components() {
+            if let Component::Normal(name) = component {
+                if name.to_str().is_some_and(|name| name.starts_with('.')) {
+                    return false;
+                }
+            }
+            ancestor.push(component);
+            if self.matcher.is_excluded(&self.root, &ancestor) {
+                return false;
+            }
+        }
+
+        let is_dir = path.is_dir();
+        if !self.no_ignore && self.is_ignored(path, is_dir) {
+            return false;
+        }
+        is_dir || !path.exists() || (self.matcher.is_included(&self.root, path) && !is_binary(path))
+    }
+
+    /// Forget the ignore files read so far, so changes to them are taken into account
+    pub fn clear_ignore_files(&mut self) {
+        self.ignore_files.clear();
+    }
+
+    /// Whether a path is ignored by the ignore files of its directories or the global git ignore
+    /// file
+    ///
+    /// Like for `ignore`, `.ignore` files take precedence over `.gitignore` files, and files in
+    /// deeper directories over the ones in their parents.
+    fn is_ignored(&mut self, path: &Path, is_dir: bool) -> bool {
+        for ignore_file in IGNORE_FILES {
+            for directory in path.ancestors().skip(1) {
+                let rules = self
+                    .ignore_files
+                    .entry(directory.join(ignore_file))
+                    .or_insert_with_key(|ignore_path| read_ignore_file(directory, ignore_path));
+                match rules
+                    .as_ref()
+                    .map(|rules| rules.matched_path_or_any_parents(path, is_dir))
+                {
+                    Some(Match::Ignore(_)) => return true,
+                    Some(Match::Whitelist(_)) => return false,
+                    Some(Match::None) | None => {},
+                }
+            }
+        }
+
+        self.global_ignore.matched_path_or_any_parents(path, is_dir).is_ignore()
+    }
+}
+
+/// Read the rules of an ignore file applying to a directory, if there is one
+fn read_ignore_file(directory: &Path, path: &Path) -> Option<Gitignore> {
+    if !path.is_file() {
+        return None;
+    }
+    let mut builder = GitignoreBuilder::new(directory);
+    if let Some(err) = builder.add(path) {
+        tracing::debug!("Failed to read {}: {}", path.display(), err);
+    }
+    builder.build().ok()
+}
+
+/// Whether a file looks binary, i.e. has a NUL byte near its start like git checks
+///
+/// Files that can't be read are not considered binary.
+pub fn is_binary(path: &Path) -> bool {
+    let Ok(file) = File::open(path) else {
+        return false;
+    };
+
+    let mut buffer = Vec::new();
+    match file.take(BINARY_SNIFF_LEN).read_to_end(&mut buffer) {
+        Ok(_) => buffer.contains(&0),
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::TempDir;
+
+    use super::*;
+
+    fn walk(root: &Path, filter: &IndexingFilter) -> Vec<String> {
+        walk_files(root, filter)
+            .unwrap()
+            .map(|path| path.strip_prefix(root).unwrap().to_string_lossy().replace('\\', "/"))
+            .collect()
+    }
+
+    fn create_tree() -> TempDir {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        for (path, content) in [
+            ("README.md", "# Readme"),
+            ("src/main.rs", "fn main() {}"),
+            ("src/generated.rs", "// generated"),
+            ("target/debug/build.log", "log"),
+            ("node_modules/pkg/index.js", "module.exports = {}"),
+            (".git/config", "[core]"),
+            (".gitignore", "target/\n*.log\n"),
+            (".ignore", "src/generated.rs\n"),
+        ] {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        fs::write(root.join("logo.png"), [0x89, b'P', b'N', b'G', 0, 0, 0, 0x0d]).unwrap();
+        temp_dir
+    }
+
+    #[test]
+    fn test_walk_files_honors_ignore_files() {
+        let temp_dir = create_tree();
+
+        let files = walk(temp_dir.path(), &IndexingFilter::default());
+        assert_eq!(files, vec!["README.md", "node_modules/pkg/index.js", "src/main.rs"]);
+
+        // Hidden and binary files stay skipped without the ignore files
+        let files = walk(temp_dir.path(), &IndexingFilter {
+            no_ignore: true,
+            ..Default::default()
+        });
+        assert_eq!(files, vec![
+            "README.md",
+            "node_modules/pkg/index.js",
+            "src/generated.rs",
+            "src/main.rs",
+            "target/debug/build.log",
+        ]);
+    }
+
+    #[test]
+    fn test_walk_files_with_patterns() {
+        let temp_dir = create_tree();
+
+        let files = walk(temp_dir.path(), &IndexingFilter {
+            include: vec!["*.rs".to_string(), "*.js".to_string()],
+            exclude: vec!["node_modules/".to_string()],
+            no_ignore: false,
+        });
+        assert_eq!(files, vec!["src/main.rs"]);
+
+        let files = walk(temp_dir.path(), &IndexingFilter {
+            exclude: vec!["src/**".to_string(), "*.md".to_string()],
+            ..Default::default()
+        });
+        assert_eq!(files, vec!["node_modules/pkg/index.js"]);
+
+        let filter = IndexingFilter {
+            include: vec!["[".to_string()],
+            ..Default::default()
+        };
+        assert!(walk_files(temp_dir.path(), &filter).is_err());
+    }
+
+    #[test]
+    fn test_walk_matcher() {
+        let temp_dir = create_tree();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("docs")).unwrap();
+        fs::write(root.join("docs/.gitignore"), "*.md\n!keep.md\n").unwrap();
+
+        let mut matcher = WalkMatcher::new(root, &IndexingFilter {
+            exclude: vec!["node_modules/".to_string()],
+            ..Default::default()
+        })
+        .unwrap()
+        .with_global_ignore(Gitignore::empty());
+        for (path, walked) in [
+            ("src/main.rs", true),
+            ("src/new.rs", true),
+            ("src", true),
+            ("src/generated.rs", false),
+            ("target/debug/build.log", false),
+            ("target/debug/new.rs", false),
+            ("node_modules/pkg/index.js", false),
+            (".git/config", false),
+            ("logo.png", false),
+            ("docs/guide.md", false),
+            ("docs/keep.md", true),
+        ] {
+            assert_eq!(matcher.is_walked(&root.join(path)), walked, "{path}");
+        }
+        assert!(!matcher.is_walked(Path::new("/elsewhere/main.rs")));
+
+        // Changed ignore files apply once the cached ones are cleared
+        fs::write(root.join(".ignore"), "src/main.rs\n").unwrap();
+        assert!(matcher.is_walked(&root.join("src/main.rs")));
+        matcher.clear_ignore_files();
+        assert!(!matcher.is_walked(&root.join("src/main.rs")));
+
+        // Include patterns only apply to files
+        let mut matcher = WalkMatcher::new(root, &IndexingFilter {
+            include: vec!["*.rs".to_string()],
+            no_ignore: true,
+            ..Default::default()
+        })
+        .unwrap();
+        assert!(matcher.is_walked(&root.join("src/main.rs")));
+        assert!(matcher.is_walked(&root.join("src/generated.rs")));
+        assert!(matcher.is_walked(&root.join("src")));
+        assert!(!matcher.is_walked(&root.join("README.md")));
+    }
+
+    #[test]
+    fn test_is_binary() {
+        let temp_dir = create_tree();
+        assert!(is_binary(&temp_dir.path().join("logo.png")));
+        assert!(!is_binary(&temp_dir.path().join("README.md")));
+        assert!(!is_binary(&temp_dir.path().join("missing.txt")));
+    }
+}
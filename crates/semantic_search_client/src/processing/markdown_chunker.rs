@@ -0,0 +1,153 @@
+use crate::config;
+use crate::processing::text_chunker::{
+    TextChunk,
+    chunk_lines,
+};
+
+/// A Markdown section, as a range of line indices
+#[derive(Debug)]
+struct Section {
+    start: usize,
+    end: usize,
+    level: usize,
+    /// Titles of the enclosing headings down to the one of the section
+    path: Option<String>,
+}
+
+/// Chunk Markdown along its headings
+///
+/// Each section makes up its own chunk, named after the path of headings leading to it, such as
+/// `Installation > Linux`. Sections larger than the chunk size are split between lines, and
+/// headings inside fenced code blocks are ignored.
+///
+/// # Arguments
+///
+/// * `text` - The Markdown to chunk
+/// * `chunk_size` - Optional maximum number of words per chunk (if None, uses config value)
+///
+/// # Returns
+///
+/// A vector of chunks with their line ranges
+pub fn chunk_markdown(text: &str, chunk_size: Option<usize>) -> Vec<TextChunk> {
+    let chunk_size = chunk_size.unwrap_or_else(|| config::get_config().chunk_size);
+    let lines: Vec<&str> = text.lines().collect();
+
+    let mut sections = Vec::new();
+    let mut headings: Vec<(usize, String)> = Vec::new();
+    let mut current = Section {
+        start: 0,
+        end: 0,
+        level: 0,
+        path: None,
+    };
+    let mut fence: Option<&str> = None;
+
+    for (i, line) in lines.iter().enumerate() {
+        let trimmed = line.trim_start();
+        match fence {
+            Some(marker) if trimmed.starts_with(marker) => fence = None,
+            Some(_) => {},
+            None if trimmed.starts_with("```") => fence = Some("```"),
+            None if trimmed.starts_with("~~~") => fence = Some("~~~"),
+            None => {
+                if let Some((level, title)) = heading(line) {
+                    if i > 0 {
+                        current.end = i - 1;
+                        sections.push(current);
+                    }
+                    headings.retain(|(heading_level, _)| *heading_level < level);
+                    headings.push((level, title.to_string()));
+                    let path: Vec<&str> = headings.iter().map(|(_, title)| title.as_str()).collect();
+                    current = Section {
+                        start: i,
+                        end: i,
+                        level,
+                        path: Some(path.join(" > ")),
+                    };
+                }
+            },
+        }
+    }
+    if !lines.is_empty() {
+        current.end = lines.len() - 1;
+        sections.push(current);
+    }
+
+    let mut chunks = Vec::new();
+    for (i, section) in sections.iter().enumerate() {
+        let Some(end) = (section.start..=section.end)
+            .rev()
+            .find(|&i| !lines[i].trim().is_empty())
+        else {
+            continue;
+        };
+        // A heading directly followed by a subsection is part of the subsection's path
+        let heading_only = section.level > 0 && end == section.start;
+        if heading_only && sections.get(i + 1).is_some_and(|next| next.level > section.level) {
+            continue;
+        }
+        let start = (section.start..=end)
+            .find(|&i| !lines[i].trim().is_empty())
+            .unwrap_or(section.start);
+
+        chunks.extend(chunk_lines(
+            &lines[start..=end],
+            start + 1,
+            chunk_size,
+            section.path.as_deref(),
+        ));
+    }
+
+    chunks
+}
+
+/// Parse an ATX heading such as `## Title` into its level and title
+fn heading(line: &str) -> Option<(usize, &str)> {
+    // Up to 3 spaces of indentation are allowed
+    let indentation = line.len() - line.trim_start_matches(' ').len();
+    if indentation > 3 {
+        return None;
+    }
+    let line = line.trim_start_matches(' ');
+    let level = line.len() - line.trim_start_matches('#').len();
+    let rest = &line[level..];
+    if !(1..=6).contains(&level) || !(rest.is_empty() || rest.starts_with([' ', '\t'])) {
+        return None;
+    }
+
+    let title = rest.trim().trim_end_matches('#').trim_end();
+    Some((level, title))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunk_markdown() {
+        let markdown = "Intro text\n\n# Guide\n\n## Install\n\nRun the installer.\n\n```sh\n# not a heading\n```\n\n### Linux\nUse the package.\n\n## Usage ##\nRun it.\n";
+        let chunks = chunk_markdown(markdown, Some(500));
+        let summary: Vec<_> = chunks
+            .iter()
+            .map(|chunk| (chunk.symbol.as_deref(), chunk.lines))
+            .collect();
+
+        assert_eq!(summary, vec![
+            (None, Some((1, 1))),
+            (Some("Guide > Install"), Some((5, 11))),
+            (Some("Guide > Install > Linux"), Some((13, 14))),
+            (Some("Guide > Usage"), Some((16, 17))),
+        ]);
+        assert!(chunks[1].text.contains("# not a heading"));
+    }
+
+    #[test]
+    fn test_heading() {
+        assert_eq!(heading("# Title"), Some((1, "Title")));
+        assert_eq!(heading("### Closed ###"), Some((3, "Closed")));
+        assert_eq!(heading("   ## Indented"), Some((2, "Indented")));
+        assert_eq!(heading("    # Code"), None);
+        assert_eq!(heading("#hashtag"), None);
+        assert_eq!(heading("####### Too deep"), None);
+    }
+}
@@ -1,11 +1,28 @@
+/// Code chunking along top-level items
+pub mod code_chunker;
 /// File processing utilities for handling different file types and extracting content
 pub mod file_processor;
+/// Selection of the files to index under a directory
+pub mod file_walker;
+/// Markdown chunking along headings
+pub mod markdown_chunker;
 /// Text chunking utilities for breaking down text into manageable pieces for embedding
 pub mod text_chunker;
 
+pub use code_chunker::chunk_code;
 pub use file_processor::{
     get_file_type,
     process_directory,
     process_file,
 };
-pub use text_chunker::chunk_text;
+pub use file_walker::{
+    FileMatcher,
+    WalkMatcher,
+    is_binary,
+    walk_files,
+};
+pub use markdown_chunker::chunk_markdown;
+pub use text_chunker::{
+    TextChunk,
+    chunk_text,
+};
@@ -1,5 +1,26 @@
 use crate::config;
 
+/// A chunk of a file, with the lines and the item it comes from when known
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextChunk {
+    /// Text of the chunk
+    pub text: String,
+    /// Name of the code item or Markdown section the chunk belongs to
+    pub symbol: Option<String>,
+    /// First and last line of the chunk, starting from 1
+    pub lines: Option<(usize, usize)>,
+}
+
+impl From<String> for TextChunk {
+    fn from(text: String) -> Self {
+        Self {
+            text,
+            symbol: None,
+            lines: None,
+        }
+    }
+}
+
 /// Chunk text into smaller pieces with overlap
 ///
 /// # Arguments
@@ -40,6 +61,61 @@ pub fn chunk_text(text: &str, chunk_size: Option<usize>, overlap: Option<usize>)
     chunks
 }
 
+/// Split lines into chunks of at most `chunk_size` words without breaking lines
+///
+/// A single line longer than `chunk_size` words, such as minified code, is split into windows of
+/// `chunk_size` words as [chunk_text] does, each spanning that line.
+///
+/// # Arguments
+///
+/// * `lines` - The lines to chunk
+/// * `first_line` - Line number of the first line, starting from 1
+/// * `chunk_size` - Maximum number of words per chunk
+/// * `symbol` - Name of the item the lines belong to
+///
+/// # Returns
+///
+/// A vector of chunks covering all the lines
+pub(crate) fn chunk_lines(
+    lines: &[&str],
+    first_line: usize,
+    chunk_size: usize,
+    symbol: Option<&str>,
+) -> Vec<TextChunk> {
+    let mut chunks = Vec::new();
+    let push = |chunks: &mut Vec<TextChunk>, text: String, start: usize, end: usize| {
+        chunks.push(TextChunk {
+            text,
+            symbol: symbol.map(str::to_string),
+            lines: Some((first_line + start, first_line + end)),
+        });
+    };
+
+    let mut start = 0;
+    let mut words = 0;
+    for (i, line) in lines.iter().enumerate() {
+        let line_words = line.split_whitespace().count();
+        if i > start && (words + line_words > chunk_size || line_words > chunk_size) {
+            push(&mut chunks, lines[start..i].join("\n"), start, i - 1);
+            start = i;
+            words = 0;
+        }
+        if line_words > chunk_size {
+            for window in chunk_text(line, Some(chunk_size), Some(0)) {
+                push(&mut chunks, window, i, i);
+            }
+            start = i + 1;
+            continue;
+        }
+        words += line_words;
+    }
+    if start < lines.len() {
+        push(&mut chunks, lines[start..].join("\n"), start, lines.len() - 1);
+    }
+
+    chunks
+}
+
 #[cfg(test)]
 mod tests {
     use std::sync::Once;
@@ -60,6 +136,8 @@ mod tests {
                     timeout: 30000,
                     base_dir: std::path::PathBuf::from("."),
                     max_files: 1000, // Add missing max_files field
+                    search_mode: crate::types::SearchMode::default(),
+                    rerank: false,
                 };
                 // Use a different approach that doesn't access private static
                 let _ = crate::config::init_config(&std::env::temp_dir());
@@ -104,6 +182,31 @@ mod tests {
         assert!(chunks[4].ends_with("word199"));
     }
 
+    #[test]
+    fn test_chunk_lines() {
+        let lines = ["one two three", "four five", "six", "seven eight nine ten"];
+        let chunks = chunk_lines(&lines, 10, 5, Some("item"));
+
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].text, "one two three\nfour five");
+        assert_eq!(chunks[0].lines, Some((10, 11)));
+        assert_eq!(chunks[1].text, "six\nseven eight nine ten");
+        assert_eq!(chunks[1].lines, Some((12, 13)));
+        assert!(chunks.iter().all(|chunk| chunk.symbol.as_deref() == Some("item")));
+
+        // A line longer than the chunk size is split into windows of words
+        let chunks = chunk_lines(&lines, 1, 2, None);
+        let summary: Vec<_> = chunks.iter().map(|chunk| (chunk.text.as_str(), chunk.lines)).collect();
+        assert_eq!(summary, vec![
+            ("one two", Some((1, 1))),
+            ("three", Some((1, 1))),
+            ("four five", Some((2, 2))),
+            ("six", Some((3, 3))),
+            ("seven eight", Some((4, 4))),
+            ("nine ten", Some((4, 4))),
+        ]);
+    }
+
     #[test]
     fn test_chunk_text_with_config_defaults() {
         setup();
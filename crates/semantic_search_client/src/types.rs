@@ -54,6 +54,16 @@ pub struct KnowledgeContext {
 
     /// Number of items in the context
     pub item_count: usize,
+
+    /// SHA-256 hash of the content of each indexed file, by path
+    ///
+    /// Updates compare these to only re-index the files that changed.
+    #[serde(default)]
+    pub file_hashes: HashMap<String, String>,
+
+    /// Selection of the files indexed from the source path, reused by updates
+    #[serde(default)]
+    pub filter: IndexingFilter,
 }
 
 impl KnowledgeContext {
@@ -76,10 +86,30 @@ impl KnowledgeContext {
             source_path,
             persistent,
             item_count,
+            file_hashes: HashMap::new(),
+            filter: IndexingFilter::default(),
         }
     }
 }
 
+/// Selection of the files indexed from a directory
+///
+/// Hidden and binary files are never indexed.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexingFilter {
+    /// Glob patterns files must match to be indexed, all files if empty
+    #[serde(default)]
+    pub include: Vec<String>,
+
+    /// Glob patterns of the files and directories to skip
+    #[serde(default)]
+    pub exclude: Vec<String>,
+
+    /// Whether to also index the files ignored by `.gitignore` and `.ignore` files
+    #[serde(default)]
+    pub no_ignore: bool,
+}
+
 /// A data point in the semantic index
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DataPoint {
@@ -115,6 +145,28 @@ impl SearchResult {
     }
 }
 
+/// Retrieval strategy used to search a context
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchMode {
+    /// Nearest neighbors of the query embedding
+    Vector,
+    /// BM25 ranking on the terms of the query
+    Lexical,
+    /// Vector and lexical rankings fused with reciprocal rank fusion
+    #[default]
+    Hybrid,
+}
+
+/// Options of a search across contexts
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SearchOptions {
+    /// Retrieval strategy
+    pub mode: SearchMode,
+    /// Whether to rerank the retrieved results with a cross-encoder
+    pub rerank: bool,
+}
+
 /// File type for processing
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FileType {
@@ -172,6 +224,13 @@ pub enum OperationType {
         /// Path being indexed
         path: String,
     },
+    /// Incremental update of a context from its source path
+    Updating {
+        /// Display name for the operation
+        name: String,
+        /// Path being re-indexed
+        path: String,
+    },
     /// Clearing all contexts
     Clearing,
 }
@@ -181,6 +240,7 @@ impl OperationType {
     pub fn display_name(&self) -> String {
         match self {
             OperationType::Indexing { name, .. } => format!("Indexing '{}'", name),
+            OperationType::Updating { name, .. } => format!("Updating '{}'", name),
             OperationType::Clearing => "Clearing all".to_string(),
         }
     }
@@ -302,6 +362,12 @@ pub(crate) enum IndexingJob {
         name: String,
         description: String,
         persistent: bool,
+        filter: IndexingFilter,
+    },
+    Update {
+        id: Uuid,
+        cancel: CancellationToken,
+        context_id: ContextId,
     },
     Clear {
         id: Uuid,
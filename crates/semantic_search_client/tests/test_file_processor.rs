@@ -119,3 +119,44 @@ fn test_process_binary_file() {
     // Clean up
     fs::remove_dir_all(temp_dir).unwrap_or(());
 }
+
+#[test]
+fn test_process_code_file() {
+    // Create a temporary directory for the test
+    let temp_dir = env::temp_dir().join("memory_bank_test_process_code");
+    fs::create_dir_all(&temp_dir).unwrap();
+
+    // Initialize config
+    config::init_config(&temp_dir).unwrap();
+
+    // Create a test source file with two functions
+    let test_file = temp_dir.join("lib.rs");
+    let first: Vec<String> = (0..5).map(|i| format!("    let a{} = {};", i, i)).collect();
+    let second: Vec<String> = (0..5).map(|i| format!("    let b{} = {};", i, i)).collect();
+    fs::write(
+        &test_file,
+        format!(
+            "/// First function\nfn first() {{\n{}\n}}\n\nfn second() {{\n{}\n}}\n",
+            first.join("\n"),
+            second.join("\n")
+        ),
+    )
+    .unwrap();
+
+    // Process the file
+    let items = process_file(&test_file).unwrap();
+
+    // Each function makes up its own chunk, with its symbol and lines
+    assert_eq!(items.len(), 2);
+    assert_eq!(items[0]["symbol"], "first");
+    assert_eq!(items[0]["start_line"], 1);
+    assert_eq!(items[0]["end_line"], 8);
+    assert!(items[0]["text"].as_str().unwrap().starts_with("/// First function"));
+    assert_eq!(items[1]["symbol"], "second");
+    assert_eq!(items[1]["start_line"], 10);
+    assert_eq!(items[1]["end_line"], 16);
+    assert_eq!(items[1]["language"], "rs");
+
+    // Clean up
+    fs::remove_dir_all(temp_dir).unwrap_or(());
+}
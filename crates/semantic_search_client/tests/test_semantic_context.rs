@@ -1,11 +1,17 @@
-use std::collections::HashMap;
+use std::collections::{
+    HashMap,
+    HashSet,
+};
 use std::{
     env,
     fs,
 };
 
 use semantic_search_client::client::SemanticContext;
-use semantic_search_client::types::DataPoint;
+use semantic_search_client::types::{
+    DataPoint,
+    SearchMode,
+};
 use serde_json::Value;
 
 #[test]
@@ -14,7 +20,7 @@ fn test_semantic_context_creation() {
     let temp_dir = env::temp_dir().join("memory_bank_test_semantic_context");
     fs::create_dir_all(&temp_dir).unwrap();
 
-    let data_path = temp_dir.join("data.json");
+    let data_path = temp_dir.join("data.bin");
 
     // Create a new semantic context
     let semantic_context = SemanticContext::new(data_path).unwrap();
@@ -32,7 +38,7 @@ fn test_add_data_points() {
     let temp_dir = env::temp_dir().join("memory_bank_test_add_data");
     fs::create_dir_all(&temp_dir).unwrap();
 
-    let data_path = temp_dir.join("data.json");
+    let data_path = temp_dir.join("data.bin");
 
     // Create a new semantic context
     let mut semantic_context = SemanticContext::new(data_path.clone()).unwrap();
@@ -98,3 +104,181 @@ fn test_add_data_points() {
     // Clean up
     fs::remove_dir_all(temp_dir).unwrap_or(());
 }
+
+#[test]
+fn test_search_modes() {
+    // Create a temporary directory for the test
+    let temp_dir = env::temp_dir().join("memory_bank_test_search_modes");
+    fs::create_dir_all(&temp_dir).unwrap();
+
+    let mut semantic_context = SemanticContext::new(temp_dir.join("data.bin")).unwrap();
+
+    // Each data point points in its own direction
+    let texts = [
+        "Retrying failed requests with exponential backoff",
+        "Caching responses to reduce latency",
+        "The parser fails with error E0382 when a value is used after a move",
+    ];
+    let data_points = texts
+        .into_iter()
+        .enumerate()
+        .map(|(id, text)| {
+            let mut vector = vec![0.0; 384];
+            vector[id] = 1.0;
+            DataPoint {
+                id,
+                payload: HashMap::from([("text".to_string(), Value::String(text.to_string()))]),
+                vector,
+            }
+        })
+        .collect();
+    semantic_context.add_data_points(data_points).unwrap();
+
+    // The query embedding is close to the first point, but only the last one has the identifier
+    let mut query_vector = vec![0.0; 384];
+    query_vector[0] = 1.0;
+    let query_text = "E0382";
+
+    let vector = semantic_context
+        .search_with_mode(SearchMode::Vector, query_text, &query_vector, 1)
+        .unwrap();
+    assert_eq!(vector[0].point.id, 0);
+
+    let lexical = semantic_context
+        .search_with_mode(SearchMode::Lexical, query_text, &query_vector, 3)
+        .unwrap();
+    assert_eq!(lexical.len(), 1);
+    assert_eq!(lexical[0].point.id, 2);
+
+    // Hybrid search keeps both, the exact match first since it is also in the vector ranking
+    let hybrid = semantic_context
+        .search_with_mode(SearchMode::Hybrid, query_text, &query_vector, 2)
+        .unwrap();
+    let ids: Vec<usize> = hybrid.iter().map(|result| result.point.id).collect();
+    assert_eq!(ids, vec![2, 0]);
+    assert!(hybrid[0].distance < hybrid[1].distance);
+
+    // Clean up
+    fs::remove_dir_all(temp_dir).unwrap_or(());
+}
+
+#[test]
+fn test_remove_data_points_by_paths() {
+    // Create a temporary directory for the test
+    let temp_dir = env::temp_dir().join("memory_bank_test_remove_by_paths");
+    fs::create_dir_all(&temp_dir).unwrap();
+
+    let mut semantic_context = SemanticContext::new(temp_dir.join("data.bin")).unwrap();
+
+    // Two chunks of a.txt and one of b.txt
+    let data_points = ["a.txt", "b.txt", "a.txt"]
+        .into_iter()
+        .enumerate()
+        .map(|(id, path)| {
+            let mut vector = vec![0.0; 384];
+            vector[id] = 1.0;
+            DataPoint {
+                id,
+                payload: HashMap::from([
+                    ("text".to_string(), Value::String(format!("chunk {} of {}", id, path))),
+                    ("path".to_string(), Value::String(path.to_string())),
+                ]),
+                vector,
+            }
+        })
+        .collect();
+    semantic_context.add_data_points(data_points).unwrap();
+
+    let removed = semantic_context
+        .remove_data_points_by_paths(&HashSet::from(["a.txt".to_string()]))
+        .unwrap();
+    assert_eq!(removed, 2);
+
+    // The remaining point is renumbered and still searchable
+    let points = semantic_context.get_data_points();
+    assert_eq!(points.len(), 1);
+    assert_eq!(points[0].id, 0);
+    assert_eq!(points[0].payload["path"], "b.txt");
+
+    let mut query_vector = vec![0.0; 384];
+    query_vector[1] = 1.0;
+    let results = semantic_context.search(&query_vector, 3).unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].point.payload["path"], "b.txt");
+
+    // Unknown paths leave the context untouched
+    let removed = semantic_context
+        .remove_data_points_by_paths(&HashSet::from(["c.txt".to_string()]))
+        .unwrap();
+    assert_eq!(removed, 0);
+
+    // Clean up
+    fs::remove_dir_all(temp_dir).unwrap_or(());
+}
+
+#[test]
+fn test_migrate_json_data() {
+    // Create a temporary directory for the test
+    let temp_dir = env::temp_dir().join("memory_bank_test_migrate_json");
+    fs::create_dir_all(&temp_dir).unwrap();
+
+    // Data points saved as JSON by previous versions
+    let data_points: Vec<DataPoint> = (0..3)
+        .map(|id| DataPoint {
+            id,
+            payload: HashMap::from([("text".to_string(), Value::String(format!("point {}", id)))]),
+            vector: vec![id as f32; 384],
+        })
+        .collect();
+    let legacy_path = temp_dir.join("data.json");
+    fs::write(&legacy_path, serde_json::to_string(&data_points).unwrap()).unwrap();
+
+    let data_path = temp_dir.join("data.bin");
+    let semantic_context = SemanticContext::new(data_path.clone()).unwrap();
+    assert_eq!(semantic_context.get_data_points().len(), 3);
+    assert_eq!(semantic_context.get_data_points()[2].vector, vec![2.0; 384]);
+
+    // The JSON file is replaced by the binary one
+    assert!(!legacy_path.exists());
+    assert!(data_path.exists());
+
+    let loaded_context = SemanticContext::new(data_path).unwrap();
+    assert_eq!(loaded_context.get_data_points().len(), 3);
+    assert_eq!(loaded_context.get_data_points()[1].payload["text"], "point 1");
+
+    // Clean up
+    fs::remove_dir_all(temp_dir).unwrap_or(());
+}
+
+#[test]
+fn test_search_single_point_context() {
+    // Create a temporary directory for the test
+    let temp_dir = env::temp_dir().join("memory_bank_test_single_point");
+    fs::remove_dir_all(&temp_dir).unwrap_or(());
+    fs::create_dir_all(&temp_dir).unwrap();
+
+    // HNSW occasionally draws a lone point on an upper layer only, so try many fresh indexes
+    for i in 0..100 {
+        let data_path = temp_dir.join(format!("context_{}", i)).join("data.bin");
+        let mut semantic_context = SemanticContext::new(data_path.clone()).unwrap();
+        semantic_context
+            .add_data_points(vec![DataPoint {
+                id: 0,
+                payload: HashMap::from([("text".to_string(), Value::String("the only point".to_string()))]),
+                vector: vec![0.1; 384],
+            }])
+            .unwrap();
+        semantic_context.save().unwrap();
+
+        let results = semantic_context.search(&[0.2; 384], 5).unwrap();
+        assert_eq!(results.len(), 1);
+
+        // Loading the context restores its index
+        let loaded_context = SemanticContext::new(data_path).unwrap();
+        let results = loaded_context.search(&[0.2; 384], 5).unwrap();
+        assert_eq!(results.len(), 1);
+    }
+
+    // Clean up
+    fs::remove_dir_all(temp_dir).unwrap_or(());
+}
@@ -0,0 +1,110 @@
+#!/usr/bin/env python3
+"""Trains the byte-level BPE merges bundled with the chat tokenizer.
+
+Usage: scripts/train-tokenizer.py [--merges N] [--max-bytes N] OUTPUT DIR...
+
+Every source and documentation file under DIR... is split into words with the same pattern as
+`crates/chat-cli/src/cli/chat/tokenizer/mod.rs`, and the most frequent pair of adjacent tokens is
+merged until there are N merges. Each line of OUTPUT is a merge of two token ids, where ids below
+256 are single bytes and the merge on line i (counting from 0) creates the token 256 + i.
+"""
+
+import argparse
+import collections
+import heapq
+import os
+import random
+import re
+
+EXTENSIONS = (".rs", ".md", ".ts", ".tsx", ".py", ".json", ".toml", ".sh", ".txt")
+# Mirrors `PATTERN` in tokenizer/mod.rs, with `[^\W\d_]` standing in for `\p{L}`
+PATTERN = re.compile(r"'(?:s|t|re|ve|m|ll|d)| ?[^\W\d_]+| ?\d{1,3}| ?(?:[^\s\w]|_)+|\s+")
+
+
+def read_corpus(dirs, max_bytes):
+    paths = []
+    for root in dirs:
+        for directory, subdirs, files in os.walk(root):
+            subdirs[:] = [d for d in subdirs if d not in ("node_modules", "target", ".git")]
+            paths.extend(os.path.join(directory, f) for f in files if f.endswith(EXTENSIONS))
+    random.Random(0).shuffle(paths)
+
+    words = collections.Counter()
+    total = 0
+    for path in paths:
+        try:
+            with open(path, encoding="utf-8") as file:
+                text = file.read()
+        except (OSError, UnicodeDecodeError):
+            continue
+        words.update(PATTERN.findall(text))
+        total += len(text)
+        if total >= max_bytes:
+            break
+    return words
+
+
+def train(words, merges):
+    vocab = [list(word.encode()) for word in words]
+    counts = list(words.values())
+    pairs = collections.Counter()
+    where = collections.defaultdict(set)
+    for index, tokens in enumerate(vocab):
+        for pair in zip(tokens, tokens[1:]):
+            pairs[pair] += counts[index]
+            where[pair].add(index)
+
+    # Max heap of pairs by count, whose outdated entries are skipped
+    heap = [(-count, pair) for pair, count in pairs.items()]
+    heapq.heapify(heap)
+    result = []
+    while len(result) < merges and heap:
+        count, best = heapq.heappop(heap)
+        if pairs.get(best) != -count:
+            continue
+        new = 256 + len(result)
+        result.append(best)
+        changed = set()
+        for index in where.pop(best):
+            tokens = vocab[index]
+            for pair in zip(tokens, tokens[1:]):
+                pairs[pair] -= counts[index]
+                changed.add(pair)
+                if pairs[pair] <= 0:
+                    del pairs[pair]
+            merged = []
+            i = 0
+            while i < len(tokens):
+                if i + 1 < len(tokens) and (tokens[i], tokens[i + 1]) == best:
+                    merged.append(new)
+                    i += 2
+                else:
+                    merged.append(tokens[i])
+                    i += 1
+            vocab[index] = merged
+            for pair in zip(merged, merged[1:]):
+                pairs[pair] += counts[index]
+                where[pair].add(index)
+                changed.add(pair)
+        pairs.pop(best, None)
+        for pair in changed:
+            if pair in pairs:
+                heapq.heappush(heap, (-pairs[pair], pair))
+    return result
+
+
+def main():
+    parser = argparse.ArgumentParser()
+    parser.add_argument("--merges", type=int, default=12000)
+    parser.add_argument("--max-bytes", type=int, default=40_000_000)
+    parser.add_argument("output")
+    parser.add_argument("dirs", nargs="+")
+    args = parser.parse_args()
+
+    merges = train(read_corpus(args.dirs, args.max_bytes), args.merges)
+    with open(args.output, "w") as file:
+        file.writelines(f"{left} {right}\n" for left, right in merges)
+
+
+if __name__ == "__main__":
+    main()